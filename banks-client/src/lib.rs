@@ -6,8 +6,11 @@
 //! cumbersome to use.
 
 pub use {
-    crate::error::BanksClientError,
-    trezoa_banks_interface::{BanksClient as TarpcClient, TransactionStatus},
+    crate::{error::BanksClientError, subscription::BanksSubscription},
+    trezoa_banks_interface::{
+        BanksClient as TarpcClient, BanksFilterType, BanksLogsFilter, BanksNotification,
+        BanksSubscriptionId, TransactionStatus,
+    },
 };
 use {
     borsh::BorshDeserialize,
@@ -27,6 +30,7 @@ use {
         signature::Signature,
         transaction::{self, VersionedTransaction},
     },
+    std::io,
     tarpc::{
         client::{self, NewClient, RequestDispatch},
         context::{self, Context},
@@ -38,6 +42,7 @@ use {
 };
 
 mod error;
+mod subscription;
 
 // This exists only for backward compatibility
 pub trait BanksClientExt {}
@@ -175,6 +180,91 @@ impl BanksClient {
             .map_err(Into::into)
     }
 
+    pub fn get_multiple_accounts_with_commitment_and_context(
+        &mut self,
+        ctx: Context,
+        addresses: Vec<Pubkey>,
+        commitment: CommitmentLevel,
+    ) -> impl Future<Output = Result<Vec<Option<Account>>, BanksClientError>> + '_ {
+        self.inner
+            .get_multiple_accounts_with_commitment_and_context(ctx, addresses, commitment)
+            .map_err(Into::into)
+    }
+
+    pub fn get_program_accounts_with_commitment_and_context(
+        &mut self,
+        ctx: Context,
+        program_id: Pubkey,
+        filters: Vec<BanksFilterType>,
+        commitment: CommitmentLevel,
+    ) -> impl Future<Output = Result<Result<Vec<(Pubkey, Account)>, String>, BanksClientError>> + '_
+    {
+        self.inner
+            .get_program_accounts_with_commitment_and_context(ctx, program_id, filters, commitment)
+            .map_err(Into::into)
+    }
+
+    pub fn get_signature_statuses_with_context(
+        &mut self,
+        ctx: Context,
+        signatures: Vec<Signature>,
+    ) -> impl Future<Output = Result<Vec<Option<TransactionStatus>>, BanksClientError>> + '_ {
+        self.inner
+            .get_signature_statuses_with_context(ctx, signatures)
+            .map_err(Into::into)
+    }
+
+    pub fn account_subscribe_with_context(
+        &mut self,
+        ctx: Context,
+        address: Pubkey,
+        commitment: CommitmentLevel,
+    ) -> impl Future<Output = Result<BanksSubscriptionId, BanksClientError>> + '_ {
+        self.inner
+            .account_subscribe_with_context(ctx, address, commitment)
+            .map_err(Into::into)
+    }
+
+    pub fn slot_subscribe_with_context(
+        &mut self,
+        ctx: Context,
+    ) -> impl Future<Output = Result<BanksSubscriptionId, BanksClientError>> + '_ {
+        self.inner
+            .slot_subscribe_with_context(ctx)
+            .map_err(Into::into)
+    }
+
+    pub fn logs_subscribe_with_context(
+        &mut self,
+        ctx: Context,
+        filter: BanksLogsFilter,
+        commitment: CommitmentLevel,
+    ) -> impl Future<Output = Result<BanksSubscriptionId, BanksClientError>> + '_ {
+        self.inner
+            .logs_subscribe_with_context(ctx, filter, commitment)
+            .map_err(Into::into)
+    }
+
+    pub fn get_notifications_with_context(
+        &mut self,
+        ctx: Context,
+        subscription_id: BanksSubscriptionId,
+    ) -> impl Future<Output = Result<Option<Vec<BanksNotification>>, BanksClientError>> + '_ {
+        self.inner
+            .get_notifications_with_context(ctx, subscription_id)
+            .map_err(Into::into)
+    }
+
+    pub fn unsubscribe_with_context(
+        &mut self,
+        ctx: Context,
+        subscription_id: BanksSubscriptionId,
+    ) -> impl Future<Output = Result<bool, BanksClientError>> + '_ {
+        self.inner
+            .unsubscribe_with_context(ctx, subscription_id)
+            .map_err(Into::into)
+    }
+
     /// Send a transaction and return immediately. The server will resend the
     /// transaction until either it is accepted by the cluster or the transaction's
     /// blockhash expires.
@@ -390,6 +480,56 @@ impl BanksClient {
         self.get_account_with_commitment(address, CommitmentLevel::default())
     }
 
+    /// Return the accounts at the given addresses at the slot corresponding to the given
+    /// commitment level. Accounts that are not found are returned as None.
+    pub fn get_multiple_accounts_with_commitment(
+        &mut self,
+        addresses: Vec<Pubkey>,
+        commitment: CommitmentLevel,
+    ) -> impl Future<Output = Result<Vec<Option<Account>>, BanksClientError>> + '_ {
+        self.get_multiple_accounts_with_commitment_and_context(
+            context::current(),
+            addresses,
+            commitment,
+        )
+    }
+
+    /// Return the accounts at the given addresses at the time of the most recent root slot.
+    /// Accounts that are not found are returned as None.
+    pub fn get_multiple_accounts(
+        &mut self,
+        addresses: Vec<Pubkey>,
+    ) -> impl Future<Output = Result<Vec<Option<Account>>, BanksClientError>> + '_ {
+        self.get_multiple_accounts_with_commitment(addresses, CommitmentLevel::default())
+    }
+
+    /// Return all accounts owned by the given program that pass every filter, at the slot
+    /// corresponding to the given commitment level.
+    pub fn get_program_accounts_with_commitment(
+        &mut self,
+        program_id: Pubkey,
+        filters: Vec<BanksFilterType>,
+        commitment: CommitmentLevel,
+    ) -> impl Future<Output = Result<Vec<(Pubkey, Account)>, BanksClientError>> + '_ {
+        self.get_program_accounts_with_commitment_and_context(
+            context::current(),
+            program_id,
+            filters,
+            commitment,
+        )
+        .map(|result| result?.map_err(|err| io::Error::new(io::ErrorKind::Other, err).into()))
+    }
+
+    /// Return all accounts owned by the given program that pass every filter, at the time
+    /// of the most recent root slot.
+    pub fn get_program_accounts(
+        &mut self,
+        program_id: Pubkey,
+        filters: Vec<BanksFilterType>,
+    ) -> impl Future<Output = Result<Vec<(Pubkey, Account)>, BanksClientError>> + '_ {
+        self.get_program_accounts_with_commitment(program_id, filters, CommitmentLevel::default())
+    }
+
     /// Return the unpacked account data at the given address
     /// If the account is not found, an error is returned
     pub fn get_packed_account_data<T: Pack>(
@@ -447,6 +587,48 @@ impl BanksClient {
         self.get_transaction_status_with_context(context::current(), signature)
     }
 
+    /// Same as get_transaction_status, but for multiple transactions, fetched in a single
+    /// request.
+    pub fn get_signature_statuses(
+        &mut self,
+        signatures: Vec<Signature>,
+    ) -> impl Future<Output = Result<Vec<Option<TransactionStatus>>, BanksClientError>> + '_ {
+        self.get_signature_statuses_with_context(context::current(), signatures)
+    }
+
+    /// Subscribe to changes of the account at `address` at the given
+    /// commitment level. A notification is produced each time the account
+    /// differs from the previously notified state.
+    pub async fn account_subscribe(
+        &mut self,
+        address: Pubkey,
+        commitment: CommitmentLevel,
+    ) -> Result<BanksSubscription, BanksClientError> {
+        let subscription_id = self
+            .account_subscribe_with_context(context::current(), address, commitment)
+            .await?;
+        Ok(BanksSubscription::new(self.clone(), subscription_id))
+    }
+
+    /// Subscribe to changes of the server's processed slot.
+    pub async fn slot_subscribe(&mut self) -> Result<BanksSubscription, BanksClientError> {
+        let subscription_id = self.slot_subscribe_with_context(context::current()).await?;
+        Ok(BanksSubscription::new(self.clone(), subscription_id))
+    }
+
+    /// Subscribe to the logs of transactions matching `filter` once they reach
+    /// the given commitment level.
+    pub async fn logs_subscribe(
+        &mut self,
+        filter: BanksLogsFilter,
+        commitment: CommitmentLevel,
+    ) -> Result<BanksSubscription, BanksClientError> {
+        let subscription_id = self
+            .logs_subscribe_with_context(context::current(), filter, commitment)
+            .await?;
+        Ok(BanksSubscription::new(self.clone(), subscription_id))
+    }
+
     /// Same as get_transaction_status, but for multiple transactions.
     pub async fn get_transaction_statuses(
         &mut self,
@@ -554,11 +736,16 @@ mod tests {
         super::*,
         trezoa_banks_server::banks_server::start_local_server,
         trezoa_runtime::{
-            bank::Bank, bank_forks::BankForks, commitment::BlockCommitmentCache,
+            bank::{Bank, TransactionLogCollectorFilter},
+            bank_forks::BankForks,
+            commitment::BlockCommitmentCache,
             genesis_utils::create_genesis_config,
         },
         trezoa_sdk::{
-            message::Message, signature::Signer, system_instruction, transaction::Transaction,
+            message::Message,
+            signature::{Keypair, Signer},
+            system_instruction,
+            transaction::Transaction,
         },
         std::sync::{Arc, RwLock},
         tarpc::transport,
@@ -661,4 +848,181 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn test_banks_server_batch_queries() -> Result<(), BanksClientError> {
+        let genesis = create_genesis_config(1_000_000_000);
+        let rent = genesis.genesis_config.rent.clone();
+        let bank = Bank::new_for_tests(&genesis.genesis_config);
+        let slot = bank.slot();
+        let block_commitment_cache = Arc::new(RwLock::new(
+            BlockCommitmentCache::new_for_tests_with_slots(slot, slot),
+        ));
+        let bank_forks = BankForks::new_rw_arc(bank);
+
+        let mint_pubkey = genesis.mint_keypair.pubkey();
+        let owner = trezoa_sdk::pubkey::new_rand();
+        let small_keypair = Keypair::new();
+        let large_keypair = Keypair::new();
+        let missing_pubkey = trezoa_sdk::pubkey::new_rand();
+        let instructions = [
+            system_instruction::create_account(&mint_pubkey, &small_keypair.pubkey(), 1, 2, &owner),
+            system_instruction::create_account(&mint_pubkey, &large_keypair.pubkey(), 1, 4, &owner),
+        ];
+        let message = Message::new(&instructions, Some(&mint_pubkey));
+
+        Runtime::new()?.block_on(async {
+            let client_transport =
+                start_local_server(bank_forks, block_commitment_cache, Duration::from_millis(1))
+                    .await;
+            let mut banks_client = start_client(client_transport).await?;
+
+            let recent_blockhash = banks_client.get_latest_blockhash().await?;
+            let transaction = Transaction::new(
+                &[&genesis.mint_keypair, &small_keypair, &large_keypair],
+                message,
+                recent_blockhash,
+            );
+            let signature = transaction.signatures[0];
+            banks_client.process_transaction(transaction).await?;
+
+            let accounts = banks_client
+                .get_multiple_accounts(vec![small_keypair.pubkey(), missing_pubkey])
+                .await?;
+            assert_eq!(accounts.len(), 2);
+            assert_eq!(accounts[0].as_ref().unwrap().data.len(), 2);
+            assert!(accounts[1].is_none());
+
+            let mut program_accounts = banks_client.get_program_accounts(owner, vec![]).await?;
+            program_accounts.sort_by_key(|(_, account)| account.data.len());
+            let program_account_keys: Vec<_> =
+                program_accounts.iter().map(|(pubkey, _)| *pubkey).collect();
+            assert_eq!(
+                program_account_keys,
+                vec![small_keypair.pubkey(), large_keypair.pubkey()]
+            );
+
+            let program_accounts = banks_client
+                .get_program_accounts(owner, vec![BanksFilterType::DataSize(4)])
+                .await?;
+            assert_eq!(program_accounts.len(), 1);
+            assert_eq!(program_accounts[0].0, large_keypair.pubkey());
+
+            let missing_signature = Signature::new_unique();
+            let statuses = banks_client
+                .get_signature_statuses(vec![signature, missing_signature])
+                .await?;
+            assert_eq!(statuses.len(), 2);
+            assert!(statuses[0].as_ref().unwrap().err.is_none());
+            assert!(statuses[1].is_none());
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[allow(clippy::result_large_err)]
+    fn test_banks_server_subscriptions() -> Result<(), BanksClientError> {
+        let genesis = create_genesis_config(10);
+        let bank = Bank::new_for_tests(&genesis.genesis_config);
+        let slot = bank.slot();
+        let block_commitment_cache = Arc::new(RwLock::new(
+            BlockCommitmentCache::new_for_tests_with_slots(slot, slot),
+        ));
+        let bank_forks = BankForks::new_rw_arc(bank);
+
+        let mint_pubkey = genesis.mint_keypair.pubkey();
+        let bob_pubkey = trezoa_sdk::pubkey::new_rand();
+        let instruction = system_instruction::transfer(&mint_pubkey, &bob_pubkey, 1);
+        let message = Message::new(&[instruction], Some(&mint_pubkey));
+
+        Runtime::new()?.block_on(async {
+            let client_transport = start_local_server(
+                bank_forks.clone(),
+                block_commitment_cache.clone(),
+                Duration::from_millis(1),
+            )
+            .await;
+            let mut banks_client = start_client(client_transport).await?;
+
+            let mut account_subscription = banks_client
+                .account_subscribe(bob_pubkey, CommitmentLevel::Processed)
+                .await?;
+            let mut logs_subscription = banks_client
+                .logs_subscribe(
+                    BanksLogsFilter::Mentions(bob_pubkey),
+                    CommitmentLevel::Processed,
+                )
+                .await?;
+            let mut slot_subscription = banks_client.slot_subscribe().await?;
+
+            let recent_blockhash = banks_client.get_latest_blockhash().await?;
+            let transaction = Transaction::new(&[&genesis.mint_keypair], message, recent_blockhash);
+            let signature = transaction.signatures[0];
+            banks_client.process_transaction(transaction).await?;
+
+            match account_subscription.next_notification().await? {
+                BanksNotification::Account {
+                    slot: notified_slot,
+                    account,
+                } => {
+                    assert_eq!(notified_slot, slot);
+                    assert_eq!(account.unwrap().lamports, 1);
+                }
+                notification => panic!("unexpected notification {notification:?}"),
+            }
+            match logs_subscription.next_notification().await? {
+                BanksNotification::Logs {
+                    signature: notified_signature,
+                    err,
+                    logs,
+                    ..
+                } => {
+                    assert_eq!(notified_signature, signature);
+                    assert!(err.is_none());
+                    assert!(!logs.is_empty());
+                }
+                notification => panic!("unexpected notification {notification:?}"),
+            }
+
+            let parent = bank_forks.read().unwrap().root_bank();
+            let child = Bank::new_from_parent(parent, &Pubkey::default(), slot + 1);
+            bank_forks.write().unwrap().insert(child);
+            block_commitment_cache
+                .write()
+                .unwrap()
+                .set_all_slots(slot + 1, slot);
+            assert_eq!(
+                slot_subscription.next_notification().await?,
+                BanksNotification::Slot {
+                    slot: slot + 1,
+                    parent: slot,
+                    root: slot,
+                }
+            );
+
+            let subscription_id = slot_subscription.id();
+            slot_subscription.unsubscribe().await?;
+            assert!(banks_client
+                .get_notifications_with_context(context::current(), subscription_id)
+                .await?
+                .is_none());
+
+            // Dropping the remaining subscriptions removes them from the
+            // server and stops the log collection they enabled
+            let subscription_id = logs_subscription.id();
+            drop(account_subscription);
+            drop(logs_subscription);
+            while banks_client
+                .get_notifications_with_context(context::current(), subscription_id)
+                .await?
+                .is_some()
+            {}
+            let root_bank = bank_forks.read().unwrap().root_bank();
+            let config = root_bank.transaction_log_collector_config.read().unwrap();
+            assert_eq!(config.filter, TransactionLogCollectorFilter::None);
+            assert!(config.mentioned_addresses.is_empty());
+            Ok(())
+        })
+    }
 }
@@ -0,0 +1,96 @@
+//! Notifications for a subscription created on a `Banks` server
+//!
+//! The tarpc transport has no server push, so a subscription is polled: each
+//! request waits on the server until notifications are available or a short
+//! timeout expires. Dropping a subscription removes it from the server.
+
+use {
+    crate::{BanksClient, BanksClientError},
+    futures::{stream, Stream},
+    trezoa_banks_interface::{BanksNotification, BanksSubscriptionId},
+    std::collections::VecDeque,
+    tarpc::context,
+    tokio::runtime::Handle,
+};
+
+pub struct BanksSubscription {
+    client: BanksClient,
+    subscription_id: BanksSubscriptionId,
+    pending: VecDeque<BanksNotification>,
+    unsubscribed: bool,
+}
+
+impl BanksSubscription {
+    pub(crate) fn new(client: BanksClient, subscription_id: BanksSubscriptionId) -> Self {
+        Self {
+            client,
+            subscription_id,
+            pending: VecDeque::new(),
+            unsubscribed: false,
+        }
+    }
+
+    pub fn id(&self) -> BanksSubscriptionId {
+        self.subscription_id
+    }
+
+    /// Wait for the next notification of the subscription.
+    pub async fn next_notification(&mut self) -> Result<BanksNotification, BanksClientError> {
+        loop {
+            if let Some(notification) = self.pending.pop_front() {
+                return Ok(notification);
+            }
+            let notifications = self
+                .client
+                .get_notifications_with_context(context::current(), self.subscription_id)
+                .await?
+                .ok_or(BanksClientError::ClientError("subscription not found"))?;
+            self.pending.extend(notifications);
+        }
+    }
+
+    /// Turn the subscription into a stream of notifications that ends after
+    /// the first error.
+    pub fn into_stream(self) -> impl Stream<Item = Result<BanksNotification, BanksClientError>> {
+        stream::unfold(Some(self), |subscription| async move {
+            let mut subscription = subscription?;
+            match subscription.next_notification().await {
+                Ok(notification) => Some((Ok(notification), Some(subscription))),
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+
+    /// Remove the subscription from the server.
+    pub async fn unsubscribe(mut self) -> Result<(), BanksClientError> {
+        self.unsubscribed = true;
+        let removed = self
+            .client
+            .unsubscribe_with_context(context::current(), self.subscription_id)
+            .await?;
+        if removed {
+            Ok(())
+        } else {
+            Err(BanksClientError::ClientError("subscription not found"))
+        }
+    }
+}
+
+impl Drop for BanksSubscription {
+    fn drop(&mut self) {
+        if self.unsubscribed {
+            return;
+        }
+        // Outside of a runtime the request can't be sent, the server then
+        // drops the subscription once the connection is closed
+        if let Ok(runtime) = Handle::try_current() {
+            let mut client = self.client.clone();
+            let subscription_id = self.subscription_id;
+            runtime.spawn(async move {
+                let _ = client
+                    .unsubscribe_with_context(context::current(), subscription_id)
+                    .await;
+            });
+        }
+    }
+}
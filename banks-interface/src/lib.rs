@@ -3,7 +3,7 @@
 use {
     serde::{Deserialize, Serialize},
     trezoa_sdk::{
-        account::Account,
+        account::{Account, ReadableAccount},
         clock::Slot,
         commitment_config::CommitmentLevel,
         fee_calculator::FeeCalculator,
@@ -61,6 +61,68 @@ pub struct BanksTransactionResultWithMetadata {
     pub metadata: Option<TransactionMetadata>,
 }
 
+/// Filters for `get_program_accounts_with_commitment_and_context`, mirroring
+/// the RPC `getProgramAccounts` filters
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BanksFilterType {
+    /// Account data length must equal the given size
+    DataSize(u64),
+    /// Account data must contain `bytes` starting at `offset`
+    Memcmp { offset: usize, bytes: Vec<u8> },
+}
+
+impl BanksFilterType {
+    pub fn allows(&self, account: &impl ReadableAccount) -> bool {
+        let data = account.data();
+        match self {
+            BanksFilterType::DataSize(size) => data.len() as u64 == *size,
+            BanksFilterType::Memcmp { offset, bytes } => data
+                .get(*offset..)
+                .and_then(|data| data.get(..bytes.len()))
+                .map(|data| data == &bytes[..])
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Identifies a subscription created on a `Banks` server
+pub type BanksSubscriptionId = u64;
+
+/// Transactions reported by `logs_subscribe_with_context`, mirroring the
+/// pubsub `logsSubscribe` filters
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BanksLogsFilter {
+    /// All transactions except simple vote transactions
+    All,
+    /// All transactions including simple vote transactions
+    AllWithVotes,
+    /// Transactions that mention the given address
+    Mentions(Pubkey),
+}
+
+/// Notification delivered by `get_notifications_with_context`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BanksNotification {
+    /// The subscribed account changed; `None` if it no longer exists
+    Account {
+        slot: Slot,
+        account: Option<Account>,
+    },
+    /// A new bank became the processed bank
+    Slot {
+        slot: Slot,
+        parent: Slot,
+        root: Slot,
+    },
+    /// A transaction matching the subscription's filter was processed
+    Logs {
+        slot: Slot,
+        signature: Signature,
+        err: Option<TransactionError>,
+        logs: Vec<String>,
+    },
+}
+
 #[tarpc::service]
 pub trait Banks {
     async fn send_transaction_with_context(transaction: VersionedTransaction);
@@ -102,6 +164,35 @@ pub trait Banks {
         message: Message,
         commitment: CommitmentLevel,
     ) -> Option<u64>;
+    // New methods are appended below so that the order of the generated
+    // request and response variants stays compatible with existing clients
+    async fn get_multiple_accounts_with_commitment_and_context(
+        addresses: Vec<Pubkey>,
+        commitment: CommitmentLevel,
+    ) -> Vec<Option<Account>>;
+    async fn get_program_accounts_with_commitment_and_context(
+        program_id: Pubkey,
+        filters: Vec<BanksFilterType>,
+        commitment: CommitmentLevel,
+    ) -> Result<Vec<(Pubkey, Account)>, String>;
+    async fn get_signature_statuses_with_context(
+        signatures: Vec<Signature>,
+    ) -> Vec<Option<TransactionStatus>>;
+    async fn account_subscribe_with_context(
+        address: Pubkey,
+        commitment: CommitmentLevel,
+    ) -> BanksSubscriptionId;
+    async fn slot_subscribe_with_context() -> BanksSubscriptionId;
+    async fn logs_subscribe_with_context(
+        filter: BanksLogsFilter,
+        commitment: CommitmentLevel,
+    ) -> BanksSubscriptionId;
+    /// Wait briefly for notifications on the subscription, returning `None` if
+    /// the subscription does not exist
+    async fn get_notifications_with_context(
+        subscription_id: BanksSubscriptionId,
+    ) -> Option<Vec<BanksNotification>>;
+    async fn unsubscribe_with_context(subscription_id: BanksSubscriptionId) -> bool;
 }
 
 #[cfg(test)]
@@ -116,4 +207,25 @@ mod tests {
         let (client_transport, _server_transport) = transport::channel::unbounded();
         BanksClient::new(client::Config::default(), client_transport);
     }
+
+    #[test]
+    fn test_banks_filter_allows() {
+        let account = Account {
+            data: vec![1, 2, 3, 4],
+            ..Account::default()
+        };
+        assert!(BanksFilterType::DataSize(4).allows(&account));
+        assert!(!BanksFilterType::DataSize(3).allows(&account));
+
+        let memcmp = |offset, bytes: &[u8]| BanksFilterType::Memcmp {
+            offset,
+            bytes: bytes.to_vec(),
+        };
+        assert!(memcmp(0, &[1, 2]).allows(&account));
+        assert!(memcmp(2, &[3, 4]).allows(&account));
+        assert!(memcmp(4, &[]).allows(&account));
+        assert!(!memcmp(1, &[1]).allows(&account));
+        assert!(!memcmp(3, &[4, 5]).allows(&account));
+        assert!(!memcmp(5, &[]).allows(&account));
+    }
 }
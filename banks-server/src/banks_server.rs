@@ -2,14 +2,19 @@ use {
     bincode::{deserialize, serialize},
     crossbeam_channel::{unbounded, Receiver, Sender},
     futures::{future, prelude::stream::StreamExt},
+    trezoa_accounts_db::accounts_index::ScanConfig,
     trezoa_banks_interface::{
-        Banks, BanksRequest, BanksResponse, BanksTransactionResultWithMetadata,
+        Banks, BanksFilterType, BanksLogsFilter, BanksNotification, BanksRequest, BanksResponse,
+        BanksSubscriptionId, BanksTransactionResultWithMetadata,
         BanksTransactionResultWithSimulation, TransactionConfirmationStatus, TransactionMetadata,
         TransactionSimulationDetails, TransactionStatus,
     },
     trezoa_client::connection_cache::ConnectionCache,
     trezoa_runtime::{
-        bank::{Bank, TransactionSimulationResult},
+        bank::{
+            Bank, TransactionLogCollectorConfig, TransactionLogCollectorFilter,
+            TransactionSimulationResult,
+        },
         bank_forks::BankForks,
         commitment::BlockCommitmentCache,
     },
@@ -23,7 +28,9 @@ use {
         message::{Message, SanitizedMessage},
         pubkey::Pubkey,
         signature::Signature,
-        transaction::{self, MessageHash, SanitizedTransaction, VersionedTransaction},
+        transaction::{
+            self, MessageHash, SanitizedTransaction, TransactionError, VersionedTransaction,
+        },
     },
     trezoa_send_transaction_service::{
         send_transaction_service::{SendTransactionService, TransactionInfo},
@@ -31,11 +38,12 @@ use {
     },
     trezoa_svm::transaction_results::TransactionExecutionResult,
     std::{
+        collections::{HashMap, HashSet},
         io,
         net::{Ipv4Addr, SocketAddr},
-        sync::{atomic::AtomicBool, Arc, RwLock},
+        sync::{atomic::AtomicBool, Arc, Mutex, RwLock},
         thread::Builder,
-        time::Duration,
+        time::{Duration, Instant},
     },
     tarpc::{
         context::Context,
@@ -48,12 +56,165 @@ use {
     tokio_serde::formats::Bincode,
 };
 
+/// How long `get_notifications_with_context` waits for a notification before
+/// returning an empty batch
+const MAX_NOTIFICATION_WAIT: Duration = Duration::from_secs(1);
+
+enum Subscription {
+    Account {
+        address: Pubkey,
+        commitment: CommitmentLevel,
+        last_account: Option<Account>,
+    },
+    Slot {
+        last_slot: Slot,
+    },
+    Logs {
+        filter: BanksLogsFilter,
+        commitment: CommitmentLevel,
+        last_slot: Slot,
+        // number of log entries of the bank at `last_slot` already delivered
+        num_delivered: usize,
+    },
+}
+
+/// The logs subscriptions of every connection to the same banks
+///
+/// The banks' log collection is widened for as long as any of them is alive,
+/// and put back to the configuration the banks had before the first one once
+/// the last one is gone, so that other users of the banks are unaffected.
+struct LogsSubscribers {
+    config: Arc<RwLock<TransactionLogCollectorConfig>>,
+    next_connection_id: u64,
+    filters: HashMap<(u64, BanksSubscriptionId), BanksLogsFilter>,
+    /// the original log collection, saved while there are subscriptions
+    saved: Option<(TransactionLogCollectorFilter, HashSet<Pubkey>)>,
+}
+
+impl LogsSubscribers {
+    fn new(bank_forks: &RwLock<BankForks>) -> Self {
+        let config = bank_forks
+            .read()
+            .unwrap()
+            .root_bank()
+            .transaction_log_collector_config
+            .clone();
+        Self {
+            config,
+            next_connection_id: 0,
+            filters: HashMap::default(),
+            saved: None,
+        }
+    }
+
+    fn new_connection_id(&mut self) -> u64 {
+        let connection_id = self.next_connection_id;
+        self.next_connection_id += 1;
+        connection_id
+    }
+
+    fn insert(
+        &mut self,
+        connection_id: u64,
+        subscription_id: BanksSubscriptionId,
+        filter: BanksLogsFilter,
+    ) {
+        self.filters
+            .insert((connection_id, subscription_id), filter);
+        self.update_transaction_logs();
+    }
+
+    fn remove(&mut self, connection_id: u64, subscription_id: BanksSubscriptionId) {
+        if self
+            .filters
+            .remove(&(connection_id, subscription_id))
+            .is_some()
+        {
+            self.update_transaction_logs();
+        }
+    }
+
+    fn remove_connection(&mut self, connection_id: u64) {
+        let num_filters = self.filters.len();
+        self.filters.retain(|(id, _), _| *id != connection_id);
+        if self.filters.len() != num_filters {
+            self.update_transaction_logs();
+        }
+    }
+
+    /// Make the banks collect exactly the logs needed by the current logs
+    /// subscriptions on top of their original configuration
+    fn update_transaction_logs(&mut self) {
+        let mut config = self.config.write().unwrap();
+        if self.filters.is_empty() {
+            if let Some((filter, mentioned_addresses)) = self.saved.take() {
+                config.filter = filter;
+                config.mentioned_addresses = mentioned_addresses;
+            }
+            return;
+        }
+        let (filter, mentioned_addresses) = self
+            .saved
+            .get_or_insert_with(|| (config.filter, config.mentioned_addresses.clone()));
+        config.filter = *filter;
+        config.mentioned_addresses = mentioned_addresses.clone();
+        for filter in self.filters.values() {
+            match filter {
+                BanksLogsFilter::AllWithVotes => {
+                    config.filter = TransactionLogCollectorFilter::AllWithVotes;
+                }
+                BanksLogsFilter::All => {
+                    if config.filter != TransactionLogCollectorFilter::AllWithVotes {
+                        config.filter = TransactionLogCollectorFilter::All;
+                    }
+                }
+                BanksLogsFilter::Mentions(address) => {
+                    config.mentioned_addresses.insert(*address);
+                    if config.filter == TransactionLogCollectorFilter::None {
+                        config.filter = TransactionLogCollectorFilter::OnlyMentionedAddresses;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The subscriptions of a single connection
+struct Subscriptions {
+    next_id: BanksSubscriptionId,
+    subscriptions: HashMap<BanksSubscriptionId, Subscription>,
+    connection_id: u64,
+    logs_subscribers: Arc<Mutex<LogsSubscribers>>,
+}
+
+impl Subscriptions {
+    fn new(logs_subscribers: Arc<Mutex<LogsSubscribers>>) -> Self {
+        let connection_id = logs_subscribers.lock().unwrap().new_connection_id();
+        Self {
+            next_id: 0,
+            subscriptions: HashMap::default(),
+            connection_id,
+            logs_subscribers,
+        }
+    }
+}
+
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        self.logs_subscribers
+            .lock()
+            .unwrap()
+            .remove_connection(self.connection_id);
+    }
+}
+
 #[derive(Clone)]
 struct BanksServer {
     bank_forks: Arc<RwLock<BankForks>>,
     block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
     transaction_sender: Sender<TransactionInfo>,
     poll_signature_status_sleep_duration: Duration,
+    subscriptions: Arc<Mutex<Subscriptions>>,
 }
 
 impl BanksServer {
@@ -66,12 +227,14 @@ impl BanksServer {
         block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
         transaction_sender: Sender<TransactionInfo>,
         poll_signature_status_sleep_duration: Duration,
+        logs_subscribers: Arc<Mutex<LogsSubscribers>>,
     ) -> Self {
         Self {
             bank_forks,
             block_commitment_cache,
             transaction_sender,
             poll_signature_status_sleep_duration,
+            subscriptions: Arc::new(Mutex::new(Subscriptions::new(logs_subscribers))),
         }
     }
 
@@ -92,6 +255,9 @@ impl BanksServer {
                 // has been processed
                 let lock = bank.freeze_lock();
                 if *lock == Hash::default() {
+                    for transaction in &transactions {
+                        bank.preload_message_accounts_from_account_source(&transaction.message);
+                    }
                     let _ = bank.try_process_entry_transactions(transactions);
                     // break out of inner loop and release bank freeze lock
                     break;
@@ -119,11 +285,13 @@ impl BanksServer {
             .name("trzBankForksCli".to_string())
             .spawn(move || Self::run(server_bank_forks, transaction_receiver))
             .unwrap();
+        let logs_subscribers = Arc::new(Mutex::new(LogsSubscribers::new(&bank_forks)));
         Self::new(
             bank_forks,
             block_commitment_cache,
             transaction_sender,
             poll_signature_status_sleep_duration,
+            logs_subscribers,
         )
     }
 
@@ -138,6 +306,156 @@ impl BanksServer {
         self.bank_forks.read().unwrap()[self.slot(commitment)].clone()
     }
 
+    fn transaction_status(
+        &self,
+        processed_bank: &Bank,
+        confirmed_bank: &Bank,
+        signature: &Signature,
+    ) -> Option<TransactionStatus> {
+        let (slot, status) = processed_bank.get_signature_status_slot(signature)?;
+        let r_block_commitment_cache = self.block_commitment_cache.read().unwrap();
+
+        let optimistically_confirmed = confirmed_bank.get_signature_status_slot(signature);
+
+        let confirmations = if r_block_commitment_cache.root() >= slot
+            && r_block_commitment_cache.highest_super_majority_root() >= slot
+        {
+            None
+        } else {
+            r_block_commitment_cache
+                .get_confirmation_count(slot)
+                .or(Some(0))
+        };
+        Some(TransactionStatus {
+            slot,
+            confirmations,
+            err: status.err(),
+            confirmation_status: if confirmations.is_none() {
+                Some(TransactionConfirmationStatus::Finalized)
+            } else if optimistically_confirmed.is_some() {
+                Some(TransactionConfirmationStatus::Confirmed)
+            } else {
+                Some(TransactionConfirmationStatus::Processed)
+            },
+        })
+    }
+
+    fn subscribe(&self, subscription: Subscription) -> BanksSubscriptionId {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let subscription_id = subscriptions.next_id;
+        subscriptions.next_id += 1;
+        if let Subscription::Logs { filter, .. } = &subscription {
+            subscriptions.logs_subscribers.lock().unwrap().insert(
+                subscriptions.connection_id,
+                subscription_id,
+                filter.clone(),
+            );
+        }
+        subscriptions
+            .subscriptions
+            .insert(subscription_id, subscription);
+        subscription_id
+    }
+
+    /// Collect the notifications for everything that changed since the
+    /// subscription was last polled
+    fn poll_subscription(&self, subscription: &mut Subscription) -> Vec<BanksNotification> {
+        match subscription {
+            Subscription::Account {
+                address,
+                commitment,
+                last_account,
+            } => {
+                let bank = self.bank(*commitment);
+                let account = bank.get_account(address).map(Account::from);
+                if account == *last_account {
+                    return vec![];
+                }
+                *last_account = account.clone();
+                vec![BanksNotification::Account {
+                    slot: bank.slot(),
+                    account,
+                }]
+            }
+            Subscription::Slot { last_slot } => {
+                let bank = self.bank(CommitmentLevel::Processed);
+                if bank.slot() == *last_slot {
+                    return vec![];
+                }
+                *last_slot = bank.slot();
+                vec![BanksNotification::Slot {
+                    slot: bank.slot(),
+                    parent: bank.parent_slot(),
+                    root: self.block_commitment_cache.read().unwrap().root(),
+                }]
+            }
+            Subscription::Logs {
+                filter,
+                commitment,
+                last_slot,
+                num_delivered,
+            } => {
+                let bank = self.bank(*commitment);
+                let address = match filter {
+                    BanksLogsFilter::Mentions(address) => Some(*address),
+                    BanksLogsFilter::All | BanksLogsFilter::AllWithVotes => None,
+                };
+
+                // Banks processed since the last poll, oldest first
+                let mut banks = vec![];
+                let mut next_bank = Some(bank.clone());
+                while let Some(bank) = next_bank {
+                    if bank.slot() < *last_slot {
+                        break;
+                    }
+                    next_bank = bank.parent();
+                    banks.push(bank);
+                }
+
+                let mut notifications = vec![];
+                for bank in banks.iter().rev() {
+                    let logs = bank
+                        .get_transaction_logs(address.as_ref())
+                        .unwrap_or_default();
+                    let skip = if bank.slot() == *last_slot {
+                        *num_delivered
+                    } else {
+                        0
+                    };
+                    notifications.extend(
+                        logs.into_iter()
+                            .skip(skip)
+                            .filter(|log| !log.is_vote || *filter != BanksLogsFilter::All)
+                            .map(|log| BanksNotification::Logs {
+                                slot: bank.slot(),
+                                signature: log.signature,
+                                err: log.result.err(),
+                                logs: log.log_messages,
+                            }),
+                    );
+                }
+                *last_slot = bank.slot();
+                *num_delivered = Self::num_transaction_logs(&bank, address.as_ref());
+                notifications
+            }
+        }
+    }
+
+    fn num_transaction_logs(bank: &Bank, address: Option<&Pubkey>) -> usize {
+        bank.get_transaction_logs(address)
+            .map(|logs| logs.len())
+            .unwrap_or_default()
+    }
+
+    /// Whether an injected fault drops the transaction as if its blockhash
+    /// expired
+    fn should_drop_transaction(bank: &Bank, signature: &Signature) -> bool {
+        bank.runtime_config()
+            .fault_injection
+            .as_ref()
+            .is_some_and(|fault_injection| fault_injection.should_drop_transaction(signature))
+    }
+
     async fn poll_signature_status(
         self,
         signature: &Signature,
@@ -173,6 +491,7 @@ fn simulate_transaction(
     bank: &Bank,
     transaction: VersionedTransaction,
 ) -> BanksTransactionResultWithSimulation {
+    bank.preload_message_accounts_from_account_source(&transaction.message);
     let sanitized_transaction = match SanitizedTransaction::try_create(
         transaction,
         MessageHash::Compute,
@@ -220,6 +539,9 @@ impl Banks for BanksServer {
             .get_blockhash_last_valid_block_height(blockhash)
             .unwrap();
         let signature = transaction.signatures.first().cloned().unwrap_or_default();
+        if Self::should_drop_transaction(&self.bank(CommitmentLevel::Processed), &signature) {
+            return;
+        }
         let info = TransactionInfo::new(
             signature,
             serialize(&transaction).unwrap(),
@@ -254,35 +576,22 @@ impl Banks for BanksServer {
         _: Context,
         signature: Signature,
     ) -> Option<TransactionStatus> {
-        let bank = self.bank(CommitmentLevel::Processed);
-        let (slot, status) = bank.get_signature_status_slot(&signature)?;
-        let r_block_commitment_cache = self.block_commitment_cache.read().unwrap();
-
-        let optimistically_confirmed_bank = self.bank(CommitmentLevel::Confirmed);
-        let optimistically_confirmed =
-            optimistically_confirmed_bank.get_signature_status_slot(&signature);
+        let processed_bank = self.bank(CommitmentLevel::Processed);
+        let confirmed_bank = self.bank(CommitmentLevel::Confirmed);
+        self.transaction_status(&processed_bank, &confirmed_bank, &signature)
+    }
 
-        let confirmations = if r_block_commitment_cache.root() >= slot
-            && r_block_commitment_cache.highest_super_majority_root() >= slot
-        {
-            None
-        } else {
-            r_block_commitment_cache
-                .get_confirmation_count(slot)
-                .or(Some(0))
-        };
-        Some(TransactionStatus {
-            slot,
-            confirmations,
-            err: status.err(),
-            confirmation_status: if confirmations.is_none() {
-                Some(TransactionConfirmationStatus::Finalized)
-            } else if optimistically_confirmed.is_some() {
-                Some(TransactionConfirmationStatus::Confirmed)
-            } else {
-                Some(TransactionConfirmationStatus::Processed)
-            },
-        })
+    async fn get_signature_statuses_with_context(
+        self,
+        _: Context,
+        signatures: Vec<Signature>,
+    ) -> Vec<Option<TransactionStatus>> {
+        let processed_bank = self.bank(CommitmentLevel::Processed);
+        let confirmed_bank = self.bank(CommitmentLevel::Confirmed);
+        signatures
+            .iter()
+            .map(|signature| self.transaction_status(&processed_bank, &confirmed_bank, signature))
+            .collect()
     }
 
     async fn get_slot_with_context(self, _: Context, commitment: CommitmentLevel) -> Slot {
@@ -327,6 +636,7 @@ impl Banks for BanksServer {
         commitment: CommitmentLevel,
     ) -> Option<transaction::Result<()>> {
         let bank = self.bank(commitment);
+        bank.preload_message_accounts_from_account_source(&transaction.message);
         let sanitized_transaction = match SanitizedTransaction::try_create(
             transaction.clone(),
             MessageHash::Compute,
@@ -340,6 +650,9 @@ impl Banks for BanksServer {
         if let Err(err) = verify_transaction(&sanitized_transaction, &bank.feature_set) {
             return Some(Err(err));
         }
+        if Self::should_drop_transaction(&bank, sanitized_transaction.signature()) {
+            return Some(Err(TransactionError::BlockhashNotFound));
+        }
 
         let blockhash = transaction.message.recent_blockhash();
         let last_valid_block_height = self
@@ -366,6 +679,14 @@ impl Banks for BanksServer {
         transaction: VersionedTransaction,
     ) -> BanksTransactionResultWithMetadata {
         let bank = self.bank_forks.read().unwrap().working_bank();
+        let signature = transaction.signatures.first().cloned().unwrap_or_default();
+        if Self::should_drop_transaction(&bank, &signature) {
+            return BanksTransactionResultWithMetadata {
+                result: Err(TransactionError::BlockhashNotFound),
+                metadata: None,
+            };
+        }
+        bank.preload_message_accounts_from_account_source(&transaction.message);
         match bank.process_transaction_with_metadata(transaction) {
             TransactionExecutionResult::NotExecuted(error) => BanksTransactionResultWithMetadata {
                 result: Err(error),
@@ -391,7 +712,47 @@ impl Banks for BanksServer {
         commitment: CommitmentLevel,
     ) -> Option<Account> {
         let bank = self.bank(commitment);
-        bank.get_account(&address).map(Account::from)
+        bank.get_account(&address)
+            .or_else(|| bank.get_account_from_source(&address))
+            .map(Account::from)
+    }
+
+    async fn get_multiple_accounts_with_commitment_and_context(
+        self,
+        _: Context,
+        addresses: Vec<Pubkey>,
+        commitment: CommitmentLevel,
+    ) -> Vec<Option<Account>> {
+        let bank = self.bank(commitment);
+        addresses
+            .iter()
+            .map(|address| {
+                bank.get_account(address)
+                    .or_else(|| bank.get_account_from_source(address))
+                    .map(Account::from)
+            })
+            .collect()
+    }
+
+    async fn get_program_accounts_with_commitment_and_context(
+        self,
+        _: Context,
+        program_id: Pubkey,
+        filters: Vec<BanksFilterType>,
+        commitment: CommitmentLevel,
+    ) -> Result<Vec<(Pubkey, Account)>, String> {
+        let bank = self.bank(commitment);
+        let accounts = bank
+            .get_filtered_program_accounts(
+                &program_id,
+                |account| filters.iter().all(|filter| filter.allows(account)),
+                &ScanConfig::default(),
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(accounts
+            .into_iter()
+            .map(|(pubkey, account)| (pubkey, Account::from(account)))
+            .collect())
     }
 
     async fn get_latest_blockhash_with_context(self, _: Context) -> Hash {
@@ -420,6 +781,86 @@ impl Banks for BanksServer {
         let sanitized_message = SanitizedMessage::try_from_legacy_message(message).ok()?;
         bank.get_fee_for_message(&sanitized_message)
     }
+
+    async fn account_subscribe_with_context(
+        self,
+        _: Context,
+        address: Pubkey,
+        commitment: CommitmentLevel,
+    ) -> BanksSubscriptionId {
+        let last_account = self
+            .bank(commitment)
+            .get_account(&address)
+            .map(Account::from);
+        self.subscribe(Subscription::Account {
+            address,
+            commitment,
+            last_account,
+        })
+    }
+
+    async fn slot_subscribe_with_context(self, _: Context) -> BanksSubscriptionId {
+        let last_slot = self.slot(CommitmentLevel::Processed);
+        self.subscribe(Subscription::Slot { last_slot })
+    }
+
+    async fn logs_subscribe_with_context(
+        self,
+        _: Context,
+        filter: BanksLogsFilter,
+        commitment: CommitmentLevel,
+    ) -> BanksSubscriptionId {
+        let bank = self.bank(commitment);
+        let address = match &filter {
+            BanksLogsFilter::Mentions(address) => Some(*address),
+            BanksLogsFilter::All | BanksLogsFilter::AllWithVotes => None,
+        };
+        let num_delivered = Self::num_transaction_logs(&bank, address.as_ref());
+        self.subscribe(Subscription::Logs {
+            filter,
+            commitment,
+            last_slot: bank.slot(),
+            num_delivered,
+        })
+    }
+
+    async fn get_notifications_with_context(
+        self,
+        _: Context,
+        subscription_id: BanksSubscriptionId,
+    ) -> Option<Vec<BanksNotification>> {
+        let deadline = Instant::now() + MAX_NOTIFICATION_WAIT;
+        loop {
+            let notifications = {
+                let mut subscriptions = self.subscriptions.lock().unwrap();
+                let subscription = subscriptions.subscriptions.get_mut(&subscription_id)?;
+                self.poll_subscription(subscription)
+            };
+            if !notifications.is_empty() || Instant::now() >= deadline {
+                return Some(notifications);
+            }
+            sleep(self.poll_signature_status_sleep_duration).await;
+        }
+    }
+
+    async fn unsubscribe_with_context(
+        self,
+        _: Context,
+        subscription_id: BanksSubscriptionId,
+    ) -> bool {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let Some(subscription) = subscriptions.subscriptions.remove(&subscription_id) else {
+            return false;
+        };
+        if matches!(subscription, Subscription::Logs { .. }) {
+            subscriptions
+                .logs_subscribers
+                .lock()
+                .unwrap()
+                .remove(subscriptions.connection_id, subscription_id);
+        }
+        true
+    }
 }
 
 pub async fn start_local_server(
@@ -446,6 +887,7 @@ pub async fn start_tcp_server(
     connection_cache: Arc<ConnectionCache>,
     exit: Arc<AtomicBool>,
 ) -> io::Result<()> {
+    let logs_subscribers = Arc::new(Mutex::new(LogsSubscribers::new(&bank_forks)));
     // Note: These settings are copied straight from the tarpc example.
     let server = tcp::listen(listen_addr, Bincode::default)
         .await?
@@ -480,6 +922,7 @@ pub async fn start_tcp_server(
                 block_commitment_cache.clone(),
                 sender,
                 Duration::from_millis(200),
+                logs_subscribers.clone(),
             );
             chan.execute(server.serve())
         })
@@ -490,3 +933,92 @@ pub async fn start_tcp_server(
     server.await;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, trezoa_runtime::genesis_utils::create_genesis_config, tarpc::context};
+
+    #[tokio::test]
+    async fn test_logs_subscriptions_across_connections() {
+        let genesis = create_genesis_config(10);
+        let bank = Bank::new_for_tests(&genesis.genesis_config);
+        let bank_forks = BankForks::new_rw_arc(bank);
+        let block_commitment_cache = Arc::new(RwLock::new(BlockCommitmentCache::new_for_tests()));
+        let logs_subscribers = Arc::new(Mutex::new(LogsSubscribers::new(&bank_forks)));
+        let new_server = || {
+            BanksServer::new(
+                bank_forks.clone(),
+                block_commitment_cache.clone(),
+                unbounded().0,
+                Duration::from_millis(1),
+                logs_subscribers.clone(),
+            )
+        };
+        let server_a = new_server();
+        let server_b = new_server();
+        let config = bank_forks
+            .read()
+            .unwrap()
+            .root_bank()
+            .transaction_log_collector_config
+            .clone();
+        let address = Pubkey::new_unique();
+
+        let subscription_a = server_a
+            .clone()
+            .logs_subscribe_with_context(
+                context::current(),
+                BanksLogsFilter::All,
+                CommitmentLevel::Processed,
+            )
+            .await;
+        server_b
+            .clone()
+            .logs_subscribe_with_context(
+                context::current(),
+                BanksLogsFilter::Mentions(address),
+                CommitmentLevel::Processed,
+            )
+            .await;
+        assert_eq!(
+            config.read().unwrap().filter,
+            TransactionLogCollectorFilter::All
+        );
+        assert!(config
+            .read()
+            .unwrap()
+            .mentioned_addresses
+            .contains(&address));
+
+        // The other connection's subscription keeps collecting its logs
+        assert!(
+            server_a
+                .clone()
+                .unsubscribe_with_context(context::current(), subscription_a)
+                .await
+        );
+        assert_eq!(
+            config.read().unwrap().filter,
+            TransactionLogCollectorFilter::OnlyMentionedAddresses
+        );
+        assert!(config
+            .read()
+            .unwrap()
+            .mentioned_addresses
+            .contains(&address));
+
+        // The original configuration is restored once the last subscriber
+        // disconnects
+        drop(server_b);
+        assert_eq!(
+            config.read().unwrap().filter,
+            TransactionLogCollectorFilter::None
+        );
+        assert!(config.read().unwrap().mentioned_addresses.is_empty());
+        drop(server_a);
+        assert_eq!(
+            config.read().unwrap().filter,
+            TransactionLogCollectorFilter::None
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use {crate::ArgConstant, clap::Arg};
+use {
+    crate::ArgConstant,
+    clap::Arg,
+    std::{fmt, str::FromStr},
+};
 
 pub const COMPUTE_UNIT_PRICE_ARG: ArgConstant<'static> = ArgConstant {
     name: "compute_unit_price",
@@ -6,6 +10,60 @@ pub const COMPUTE_UNIT_PRICE_ARG: ArgConstant<'static> = ArgConstant {
     help: "Set compute unit price for transaction, in increments of 0.000001 lamports per compute unit.",
 };
 
+pub const PRIORITY_FEE_ARG: ArgConstant<'static> = ArgConstant {
+    name: "priority_fee",
+    long: "--priority-fee",
+    help: "Estimate the compute unit price from recent prioritization fees paid for the \
+           transaction's writable accounts, at the given percentile (default 75), and set \
+           the compute unit limit from a simulation. A transaction that doesn't land is \
+           resent until its blockhash expires, then re-signed with a new blockhash and an \
+           escalated fee. Format: auto[:PERCENTILE]",
+};
+
+/// Default percentile of recent prioritization fees used by `--priority-fee auto`
+pub const DEFAULT_PRIORITY_FEE_PERCENTILE: u8 = 75;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoPriorityFee {
+    pub percentile: u8,
+}
+
+impl Default for AutoPriorityFee {
+    fn default() -> Self {
+        Self {
+            percentile: DEFAULT_PRIORITY_FEE_PERCENTILE,
+        }
+    }
+}
+
+impl FromStr for AutoPriorityFee {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        if parts.next() != Some("auto") {
+            return Err(format!(
+                "Unsupported priority fee {s}, expected auto[:PERCENTILE]"
+            ));
+        }
+        match parts.next() {
+            None => Ok(Self::default()),
+            Some(percentile) => match percentile.parse::<u8>() {
+                Ok(percentile) if percentile <= 100 => Ok(Self { percentile }),
+                _ => Err(format!(
+                    "Invalid priority fee percentile {percentile}, expected 0-100"
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for AutoPriorityFee {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "auto:{}", self.percentile)
+    }
+}
+
 pub fn compute_unit_price_arg<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name(COMPUTE_UNIT_PRICE_ARG.name)
         .long(COMPUTE_UNIT_PRICE_ARG.long)
@@ -13,3 +71,37 @@ pub fn compute_unit_price_arg<'a, 'b>() -> Arg<'a, 'b> {
         .value_name("COMPUTE-UNIT-PRICE")
         .help(COMPUTE_UNIT_PRICE_ARG.help)
 }
+
+pub fn priority_fee_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(PRIORITY_FEE_ARG.name)
+        .long(PRIORITY_FEE_ARG.long)
+        .takes_value(true)
+        .value_name("auto[:PERCENTILE]")
+        .validator(|value| value.parse::<AutoPriorityFee>().map(|_| ()))
+        .help(PRIORITY_FEE_ARG.help)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_auto_priority_fee() {
+        assert_eq!(
+            "auto".parse::<AutoPriorityFee>(),
+            Ok(AutoPriorityFee { percentile: 75 })
+        );
+        assert_eq!(
+            "auto:90".parse::<AutoPriorityFee>(),
+            Ok(AutoPriorityFee { percentile: 90 })
+        );
+        assert_eq!(
+            "auto:0".parse::<AutoPriorityFee>(),
+            Ok(AutoPriorityFee { percentile: 0 })
+        );
+        assert!("auto:101".parse::<AutoPriorityFee>().is_err());
+        assert!("auto:".parse::<AutoPriorityFee>().is_err());
+        assert!("manual".parse::<AutoPriorityFee>().is_err());
+        assert!("1000".parse::<AutoPriorityFee>().is_err());
+    }
+}
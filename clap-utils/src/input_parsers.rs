@@ -1,7 +1,7 @@
 use {
     crate::keypair::{
-        keypair_from_seed_phrase, pubkey_from_path, resolve_signer_from_path, signer_from_path,
-        ASK_KEYWORD, SKIP_SEED_PHRASE_VALIDATION_ARG,
+        keypair_from_seed_phrase, pubkey_from_path, read_keypair_file_with_prompt,
+        resolve_signer_from_path, signer_from_path, ASK_KEYWORD, SKIP_SEED_PHRASE_VALIDATION_ARG,
     },
     chrono::DateTime,
     clap::ArgMatches,
@@ -12,7 +12,8 @@ use {
         genesis_config::ClusterType,
         native_token::trz_to_lamports,
         pubkey::Pubkey,
-        signature::{read_keypair_file, Keypair, Signature, Signer},
+        signature::{Keypair, Signature, Signer},
+        signer::encrypted_keypair::read_keypair_file_pubkey,
     },
     std::{rc::Rc, str::FromStr},
 };
@@ -62,7 +63,7 @@ pub fn keypair_of(matches: &ArgMatches<'_>, name: &str) -> Option<Keypair> {
             let skip_validation = matches.is_present(SKIP_SEED_PHRASE_VALIDATION_ARG.name);
             keypair_from_seed_phrase(name, skip_validation, true, None, true).ok()
         } else {
-            read_keypair_file(value).ok()
+            read_keypair_file_with_prompt(value, name).ok()
         }
     } else {
         None
@@ -77,7 +78,7 @@ pub fn keypairs_of(matches: &ArgMatches<'_>, name: &str) -> Option<Vec<Keypair>>
                     let skip_validation = matches.is_present(SKIP_SEED_PHRASE_VALIDATION_ARG.name);
                     keypair_from_seed_phrase(name, skip_validation, true, None, true).ok()
                 } else {
-                    read_keypair_file(value).ok()
+                    read_keypair_file_with_prompt(value, name).ok()
                 }
             })
             .collect()
@@ -95,9 +96,7 @@ pub fn pubkeys_of(matches: &ArgMatches<'_>, name: &str) -> Option<Vec<Pubkey>> {
         values
             .map(|value| {
                 value.parse::<Pubkey>().unwrap_or_else(|_| {
-                    read_keypair_file(value)
-                        .expect("read_keypair_file failed")
-                        .pubkey()
+                    read_keypair_file_pubkey(value).expect("read_keypair_file_pubkey failed")
                 })
             })
             .collect()
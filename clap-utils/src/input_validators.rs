@@ -5,7 +5,8 @@ use {
         clock::{Epoch, Slot},
         hash::Hash,
         pubkey::{Pubkey, MAX_SEED_LEN},
-        signature::{read_keypair_file, Signature},
+        signature::Signature,
+        signer::encrypted_keypair::read_keypair_file_pubkey,
     },
     std::{fmt::Display, ops::RangeBounds, str::FromStr},
 };
@@ -74,7 +75,7 @@ pub fn is_keypair<T>(string: T) -> Result<(), String>
 where
     T: AsRef<str> + Display,
 {
-    read_keypair_file(string.as_ref())
+    read_keypair_file_pubkey(string.as_ref())
         .map(|_| ())
         .map_err(|err| format!("{err}"))
 }
@@ -87,7 +88,7 @@ where
     if string.as_ref() == ASK_KEYWORD {
         return Ok(());
     }
-    read_keypair_file(string.as_ref())
+    read_keypair_file_pubkey(string.as_ref())
         .map(|_| ())
         .map_err(|err| format!("{err}"))
 }
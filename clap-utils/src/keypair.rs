@@ -19,6 +19,10 @@ use {
     clap::ArgMatches,
     rpassword::prompt_password,
     trezoa_remote_wallet::{
+        external_signer::{
+            ExternalSigner, ExternalSignerError, ExternalSignerLocator,
+            EXTERNAL_SIGNER_EXEC_SCHEME, EXTERNAL_SIGNER_PLUGIN_SCHEME,
+        },
         locator::{Locator as RemoteWalletLocator, LocatorError as RemoteWalletLocatorError},
         remote_keypair::generate_remote_keypair,
         remote_wallet::{maybe_wallet_manager, RemoteWalletError, RemoteWalletManager},
@@ -31,8 +35,9 @@ use {
         signature::{
             generate_seed_from_seed_phrase_and_passphrase, keypair_from_seed,
             keypair_from_seed_and_derivation_path, keypair_from_seed_phrase_and_passphrase,
-            read_keypair, read_keypair_file, Keypair, NullSigner, Presigner, Signature, Signer,
+            read_keypair, Keypair, NullSigner, Presigner, Signature, Signer,
         },
+        signer::encrypted_keypair::{read_keypair_file_pubkey, read_keypair_file_with_passphrase},
     },
     std::{
         cell::RefCell,
@@ -407,6 +412,7 @@ pub(crate) enum SignerSourceKind {
     Usb(RemoteWalletLocator),
     Stdin,
     Pubkey(Pubkey),
+    External(ExternalSignerLocator),
 }
 
 impl AsRef<str> for SignerSourceKind {
@@ -417,6 +423,7 @@ impl AsRef<str> for SignerSourceKind {
             Self::Usb(_) => SIGNER_SOURCE_USB,
             Self::Stdin => SIGNER_SOURCE_STDIN,
             Self::Pubkey(_) => SIGNER_SOURCE_PUBKEY,
+            Self::External(locator) => locator.scheme(),
         }
     }
 }
@@ -438,6 +445,8 @@ pub(crate) enum SignerSourceError {
     DerivationPathError(#[from] DerivationPathError),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    ExternalSignerError(#[from] ExternalSignerError),
 }
 
 pub(crate) fn parse_signer_source<S: AsRef<str>>(
@@ -483,6 +492,11 @@ pub(crate) fn parse_signer_source<S: AsRef<str>>(
                         legacy: false,
                     }),
                     SIGNER_SOURCE_STDIN => Ok(SignerSource::new(SignerSourceKind::Stdin)),
+                    EXTERNAL_SIGNER_EXEC_SCHEME | EXTERNAL_SIGNER_PLUGIN_SCHEME => {
+                        Ok(SignerSource::new(SignerSourceKind::External(
+                            ExternalSignerLocator::new_from_uri(&uri)?,
+                        )))
+                    }
                     _ => {
                         #[cfg(target_family = "windows")]
                         // On Windows, an absolute path's drive letter will be parsed as the URI
@@ -618,6 +632,22 @@ pub struct SignerFromPathConfig {
 ///   - `usb://ledger/9rPVSygg3brqghvdZ6wsL2i5YNQTGhXGdJzF65YxaCQd`
 ///   - `usb://ledger/9rPVSygg3brqghvdZ6wsL2i5YNQTGhXGdJzF65YxaCQd?key=0/0`
 ///
+/// - `exec:` &mdash; Spawn an external signer program and sign by talking to
+///   it over its stdin and stdout. The URI path is the path of the program,
+///   and each `arg=` query parameter is passed to it as a command line
+///   argument, in order.
+///
+/// - `plugin:` &mdash; Sign by talking to an external signer listening on the
+///   Unix domain socket at the URI path.
+///
+///   Both external signer schemes use the line-delimited JSON-RPC protocol
+///   documented in [`trezoa_remote_wallet::external_signer`].
+///
+///   Examples:
+///
+///   - `exec:///usr/local/bin/kms-signer?arg=--key&arg=treasury`
+///   - `plugin:///run/hsm-signer.sock`
+///
 /// Next the `path` argument may be one of the following strings:
 ///
 /// - `-` &mdash; Read the keypair from stdin. This is the same as the `stdin:`
@@ -773,14 +803,19 @@ pub fn signer_from_path_with_config(
                 legacy,
             )?))
         }
-        SignerSourceKind::Filepath(path) => match read_keypair_file(&path) {
-            Err(e) => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("could not read keypair file \"{path}\". Run \"trezoa-keygen new\" to create a keypair file: {e}"),
-            )
-            .into()),
-            Ok(file) => Ok(Box::new(file)),
-        },
+        SignerSourceKind::Filepath(path) => {
+            match read_keypair_file_with_prompt(&path, keypair_name) {
+                Err(e) => Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!(
+                        "could not read keypair file \"{path}\". \
+                        Run \"trezoa-keygen new\" to create a keypair file: {e}"
+                    ),
+                )
+                .into()),
+                Ok(file) => Ok(Box::new(file)),
+            }
+        }
         SignerSourceKind::Stdin => {
             let mut stdin = std::io::stdin();
             Ok(Box::new(read_keypair(&mut stdin)?))
@@ -817,6 +852,7 @@ pub fn signer_from_path_with_config(
                 .into())
             }
         }
+        SignerSourceKind::External(locator) => Ok(Box::new(ExternalSigner::new(&locator)?)),
     }
 }
 
@@ -895,7 +931,7 @@ pub fn resolve_signer_from_path(
             )
             .map(|_| None)
         }
-        SignerSourceKind::Filepath(path) => match read_keypair_file(&path) {
+        SignerSourceKind::Filepath(path) => match read_keypair_file_pubkey(&path) {
             Err(e) => Err(std::io::Error::new(
                 std::io::ErrorKind::Other,
                 format!(
@@ -955,6 +991,18 @@ pub fn prompt_passphrase(prompt: &str) -> Result<String, Box<dyn error::Error>>
     Ok(passphrase)
 }
 
+/// Reads a keypair file, prompting for the passphrase if the file is encrypted
+pub(crate) fn read_keypair_file_with_prompt(
+    path: &str,
+    keypair_name: &str,
+) -> Result<Keypair, Box<dyn error::Error>> {
+    read_keypair_file_with_passphrase(path, |pubkey| {
+        Ok(prompt_password(format!(
+            "[{keypair_name}] passphrase for encrypted keypair {pubkey}: "
+        ))?)
+    })
+}
+
 /// Loads a [Keypair] from one of several possible sources.
 ///
 /// The `path` is not strictly a file system path, but is interpreted as various
@@ -1017,17 +1065,19 @@ pub fn keypair_from_path(
                 legacy,
             )?)
         }
-        SignerSourceKind::Filepath(path) => match read_keypair_file(&path) {
-            Err(e) => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!(
-                    "could not read keypair file \"{path}\". \
-                    Run \"trezoa-keygen new\" to create a keypair file: {e}"
-                ),
-            )
-            .into()),
-            Ok(file) => Ok(file),
-        },
+        SignerSourceKind::Filepath(path) => {
+            match read_keypair_file_with_prompt(&path, keypair_name) {
+                Err(e) => Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!(
+                        "could not read keypair file \"{path}\". \
+                        Run \"trezoa-keygen new\" to create a keypair file: {e}"
+                    ),
+                )
+                .into()),
+                Ok(file) => Ok(file),
+            }
+        }
         SignerSourceKind::Stdin => {
             let mut stdin = std::io::stdin();
             Ok(read_keypair(&mut stdin)?)
@@ -1253,6 +1303,29 @@ mod tests {
                 derivation_path: d,
                 legacy: false,
             } if u == expected_locator && d == expected_derivation_path);
+        let exec = "exec:///usr/local/bin/kms-signer?arg=--key&arg=treasury".to_string();
+        let expected_locator = ExternalSignerLocator::Exec {
+            program: "/usr/local/bin/kms-signer".into(),
+            args: vec!["--key".to_string(), "treasury".to_string()],
+        };
+        assert_matches!(parse_signer_source(exec).unwrap(), SignerSource {
+                kind: SignerSourceKind::External(l),
+                derivation_path: None,
+                legacy: false,
+            } if l == expected_locator);
+        let plugin = "plugin:///run/hsm-signer.sock".to_string();
+        let expected_locator = ExternalSignerLocator::Plugin {
+            socket: "/run/hsm-signer.sock".into(),
+        };
+        assert_matches!(parse_signer_source(plugin).unwrap(), SignerSource {
+                kind: SignerSourceKind::External(l),
+                derivation_path: None,
+                legacy: false,
+            } if l == expected_locator);
+        assert_matches!(
+            parse_signer_source("exec://"),
+            Err(SignerSourceError::ExternalSignerError(_))
+        );
         // Catchall into SignerSource::Filepath fails
         let junk = "sometextthatisnotapubkeyorfile".to_string();
         assert!(Pubkey::from_str(&junk).is_err());
@@ -1,7 +1,10 @@
 use {
     crate::{
         input_validators::normalize_to_url_if_moniker,
-        keypair::{keypair_from_seed_phrase, ASK_KEYWORD, SKIP_SEED_PHRASE_VALIDATION_ARG},
+        keypair::{
+            keypair_from_seed_phrase, read_keypair_file_with_prompt, ASK_KEYWORD,
+            SKIP_SEED_PHRASE_VALIDATION_ARG,
+        },
     },
     chrono::DateTime,
     clap::ArgMatches,
@@ -11,7 +14,8 @@ use {
         genesis_config::ClusterType,
         native_token::trz_to_lamports,
         pubkey::{Pubkey, MAX_SEED_LEN},
-        signature::{read_keypair_file, Keypair, Signer},
+        signature::{Keypair, Signer},
+        signer::encrypted_keypair::read_keypair_file_pubkey,
     },
     std::str::FromStr,
 };
@@ -252,7 +256,7 @@ pub fn keypair_of(matches: &ArgMatches, name: &str) -> Option<Keypair> {
             let skip_validation = matches.is_present(SKIP_SEED_PHRASE_VALIDATION_ARG.name);
             keypair_from_seed_phrase(name, skip_validation, true, None, true).ok()
         } else {
-            read_keypair_file(value).ok()
+            read_keypair_file_with_prompt(value, name).ok()
         }
     } else {
         None
@@ -267,7 +271,7 @@ pub fn keypairs_of(matches: &ArgMatches, name: &str) -> Option<Vec<Keypair>> {
                     let skip_validation = matches.is_present(SKIP_SEED_PHRASE_VALIDATION_ARG.name);
                     keypair_from_seed_phrase(name, skip_validation, true, None, true).ok()
                 } else {
-                    read_keypair_file(value).ok()
+                    read_keypair_file_with_prompt(value, name).ok()
                 }
             })
             .collect()
@@ -285,9 +289,7 @@ pub fn pubkeys_of(matches: &ArgMatches, name: &str) -> Option<Vec<Pubkey>> {
         values
             .map(|value| {
                 value.parse::<Pubkey>().unwrap_or_else(|_| {
-                    read_keypair_file(value)
-                        .expect("read_keypair_file failed")
-                        .pubkey()
+                    read_keypair_file_pubkey(value).expect("read_keypair_file_pubkey failed")
                 })
             })
             .collect()
@@ -5,6 +5,10 @@ use {
     },
     clap::{builder::ValueParser, ArgMatches},
     trezoa_remote_wallet::{
+        external_signer::{
+            ExternalSignerError, ExternalSignerLocator, EXTERNAL_SIGNER_EXEC_SCHEME,
+            EXTERNAL_SIGNER_PLUGIN_SCHEME,
+        },
         locator::{Locator as RemoteWalletLocator, LocatorError as RemoteWalletLocatorError},
         remote_wallet::RemoteWalletManager,
     },
@@ -33,6 +37,8 @@ pub enum SignerSourceError {
     DerivationPathError(#[from] DerivationPathError),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    ExternalSignerError(#[from] ExternalSignerError),
     #[error("unsupported source")]
     UnsupportedSource,
 }
@@ -44,6 +50,7 @@ pub enum SignerSourceKind {
     Usb(RemoteWalletLocator),
     Stdin,
     Pubkey(Pubkey),
+    External(ExternalSignerLocator),
 }
 
 impl AsRef<str> for SignerSourceKind {
@@ -54,6 +61,7 @@ impl AsRef<str> for SignerSourceKind {
             Self::Usb(_) => SIGNER_SOURCE_USB,
             Self::Stdin => SIGNER_SOURCE_STDIN,
             Self::Pubkey(_) => SIGNER_SOURCE_PUBKEY,
+            Self::External(locator) => locator.scheme(),
         }
     }
 }
@@ -130,6 +138,11 @@ impl SignerSource {
                             legacy: false,
                         }),
                         SIGNER_SOURCE_STDIN => Ok(SignerSource::new(SignerSourceKind::Stdin)),
+                        EXTERNAL_SIGNER_EXEC_SCHEME | EXTERNAL_SIGNER_PLUGIN_SCHEME => {
+                            Ok(SignerSource::new(SignerSourceKind::External(
+                                ExternalSignerLocator::new_from_uri(&uri)?,
+                            )))
+                        }
                         _ => {
                             #[cfg(target_family = "windows")]
                             // On Windows, an absolute path's drive letter will be parsed as the URI
@@ -167,6 +180,7 @@ pub struct SignerSourceParserBuilder {
     allow_usb: bool,
     allow_stdin: bool,
     allow_pubkey: bool,
+    allow_external: bool,
     allow_legacy: bool,
 }
 
@@ -177,6 +191,7 @@ impl SignerSourceParserBuilder {
         self.allow_usb = true;
         self.allow_stdin = true;
         self.allow_pubkey = true;
+        self.allow_external = true;
         self.allow_legacy = true;
         self
     }
@@ -206,6 +221,11 @@ impl SignerSourceParserBuilder {
         self
     }
 
+    pub fn allow_external(mut self) -> Self {
+        self.allow_external = true;
+        self
+    }
+
     pub fn allow_legacy(mut self) -> Self {
         self.allow_legacy = true;
         self
@@ -224,6 +244,7 @@ impl SignerSourceParserBuilder {
                     SignerSourceKind::Usb(_) if self.allow_usb => Ok(signer_source),
                     SignerSourceKind::Stdin if self.allow_stdin => Ok(signer_source),
                     SignerSourceKind::Pubkey(_) if self.allow_pubkey => Ok(signer_source),
+                    SignerSourceKind::External(_) if self.allow_external => Ok(signer_source),
                     _ => Err(SignerSourceError::UnsupportedSource),
                 }
             },
@@ -473,6 +494,15 @@ mod tests {
                 derivation_path: d,
                 legacy: false,
             } if u == expected_locator && d == expected_derivation_path);
+        let plugin = "plugin:///run/hsm-signer.sock".to_string();
+        let expected_locator = ExternalSignerLocator::Plugin {
+            socket: "/run/hsm-signer.sock".into(),
+        };
+        assert_matches!(SignerSource::parse(plugin).unwrap(), SignerSource {
+                kind: SignerSourceKind::External(l),
+                derivation_path: None,
+                legacy: false,
+            } if l == expected_locator);
         // Catchall into SignerSource::Filepath fails
         let junk = "sometextthatisnotapubkeyorfile".to_string();
         assert!(Pubkey::from_str(&junk).is_err());
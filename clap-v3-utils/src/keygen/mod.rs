@@ -9,6 +9,7 @@ use {
 
 pub mod derivation_path;
 pub mod mnemonic;
+pub mod shamir;
 
 pub const NO_OUTFILE_ARG: ArgConstant<'static> = ArgConstant {
     long: "no-outfile",
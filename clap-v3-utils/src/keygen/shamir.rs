@@ -0,0 +1,404 @@
+//! Shamir secret sharing of keypair seeds
+//!
+//! A 32-byte keypair seed is split into `shares` shares, any `threshold` of
+//! which recover it. Sharing is done byte-wise over GF(256), so a share is as
+//! long as the seed.
+//!
+//! Shares are written as mnemonics of words from the BIP39 English word list,
+//! 11 bits per word. A share encodes:
+//!
+//! | bytes | field                                             |
+//! |-------|---------------------------------------------------|
+//! | 2     | random identifier of the split                    |
+//! | 1     | threshold                                         |
+//! | 1     | share index, from 1                               |
+//! | 32    | share value                                       |
+//! | 4     | checksum, the first bytes of SHA-256 of the above |
+//!
+//! which takes 30 words, the last 10 bits being zero padding.
+
+use {
+    bip39::{Language, Mnemonic},
+    rand::{rngs::OsRng, RngCore},
+    trezoa_sdk::hash::hash,
+    std::{collections::HashMap, sync::OnceLock},
+    thiserror::Error,
+};
+
+pub const SECRET_LEN: usize = 32;
+pub const SHARE_WORD_COUNT: usize = 30;
+
+const CHECKSUM_LEN: usize = 4;
+const PAYLOAD_LEN: usize = 2 + 1 + 1 + SECRET_LEN;
+const BITS_PER_WORD: usize = 11;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ShareError {
+    #[error(
+        "threshold must be between 1 and the number of shares, and there may be at most 255 shares"
+    )]
+    InvalidParameters,
+    #[error("share must have {SHARE_WORD_COUNT} words, found {0}")]
+    InvalidWordCount(usize),
+    #[error("unknown word `{0}` in share")]
+    UnknownWord(String),
+    #[error("share checksum mismatch")]
+    InvalidChecksum,
+    #[error("share index must not be zero")]
+    InvalidIndex,
+    #[error("share threshold must not be zero")]
+    InvalidThreshold,
+    #[error("shares are from different splits")]
+    MismatchedShares,
+    #[error("duplicate share {0}")]
+    DuplicateShare(u8),
+    #[error("{threshold} shares are required, found {found}")]
+    NotEnoughShares { threshold: u8, found: usize },
+}
+
+/// One share of a split secret
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecretShare {
+    /// Identifier shared by all shares of the same split
+    pub id: u16,
+    pub threshold: u8,
+    /// Index of the share, which is the x coordinate it was evaluated at
+    pub index: u8,
+    pub value: [u8; SECRET_LEN],
+}
+
+impl SecretShare {
+    pub fn to_mnemonic(&self) -> String {
+        let mut bytes = Vec::with_capacity(PAYLOAD_LEN + CHECKSUM_LEN);
+        bytes.extend_from_slice(&self.id.to_be_bytes());
+        bytes.push(self.threshold);
+        bytes.push(self.index);
+        bytes.extend_from_slice(&self.value);
+        let checksum = hash(&bytes);
+        bytes.extend_from_slice(&checksum.as_ref()[..CHECKSUM_LEN]);
+
+        let words = wordlist();
+        (0..SHARE_WORD_COUNT)
+            .map(|word_index| {
+                let bits = (0..BITS_PER_WORD).fold(0usize, |bits, bit| {
+                    let position = word_index * BITS_PER_WORD + bit;
+                    (bits << 1) | usize::from(get_bit(&bytes, position))
+                });
+                words[bits].as_str()
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Result<Self, ShareError> {
+        let share_words: Vec<_> = mnemonic.split_whitespace().collect();
+        if share_words.len() != SHARE_WORD_COUNT {
+            return Err(ShareError::InvalidWordCount(share_words.len()));
+        }
+        let word_indexes = word_indexes();
+        let mut bytes = [0u8; PAYLOAD_LEN + CHECKSUM_LEN];
+        for (word_index, word) in share_words.iter().enumerate() {
+            let bits = *word_indexes
+                .get(word.to_lowercase().as_str())
+                .ok_or_else(|| ShareError::UnknownWord(word.to_string()))?;
+            for bit in 0..BITS_PER_WORD {
+                let position = word_index * BITS_PER_WORD + bit;
+                let set = bits & (1 << (BITS_PER_WORD - 1 - bit)) != 0;
+                if position < bytes.len() * 8 {
+                    if set {
+                        bytes[position / 8] |= 0x80 >> (position % 8);
+                    }
+                } else if set {
+                    // Padding bits must be zero
+                    return Err(ShareError::InvalidChecksum);
+                }
+            }
+        }
+
+        let (payload, checksum) = bytes.split_at(PAYLOAD_LEN);
+        if &hash(payload).as_ref()[..CHECKSUM_LEN] != checksum {
+            return Err(ShareError::InvalidChecksum);
+        }
+        let threshold = payload[2];
+        if threshold == 0 {
+            return Err(ShareError::InvalidThreshold);
+        }
+        let index = payload[3];
+        if index == 0 {
+            return Err(ShareError::InvalidIndex);
+        }
+        Ok(Self {
+            id: u16::from_be_bytes([payload[0], payload[1]]),
+            threshold,
+            index,
+            value: payload[4..].try_into().unwrap(),
+        })
+    }
+}
+
+/// Splits `secret` into `shares` shares, any `threshold` of which recover it
+pub fn split_secret(
+    secret: &[u8; SECRET_LEN],
+    threshold: u8,
+    shares: u8,
+) -> Result<Vec<SecretShare>, ShareError> {
+    if threshold == 0 || threshold > shares {
+        return Err(ShareError::InvalidParameters);
+    }
+    let id = OsRng.next_u32() as u16;
+    // One random polynomial of degree `threshold - 1` per secret byte, with
+    // the secret byte as its constant term
+    let mut coefficients = vec![[0u8; SECRET_LEN]; usize::from(threshold)];
+    coefficients[0] = *secret;
+    for coefficient in coefficients.iter_mut().skip(1) {
+        OsRng.fill_bytes(coefficient);
+    }
+
+    let shares = (1..=shares)
+        .map(|index| {
+            let mut value = [0u8; SECRET_LEN];
+            for (byte, value) in value.iter_mut().enumerate() {
+                // Horner's method
+                *value = coefficients
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, coefficient| gf_mul(acc, index) ^ coefficient[byte]);
+            }
+            SecretShare {
+                id,
+                threshold,
+                index,
+                value,
+            }
+        })
+        .collect();
+    for coefficient in coefficients.iter_mut() {
+        coefficient.fill(0);
+    }
+    Ok(shares)
+}
+
+/// Recovers the secret from at least `threshold` shares of the same split
+pub fn combine_shares(shares: &[SecretShare]) -> Result<[u8; SECRET_LEN], ShareError> {
+    let first = shares.first().ok_or(ShareError::NotEnoughShares {
+        threshold: 1,
+        found: 0,
+    })?;
+    // A threshold of zero would interpolate over no shares at all
+    if first.threshold == 0 {
+        return Err(ShareError::InvalidThreshold);
+    }
+    let mut indexes = Vec::with_capacity(shares.len());
+    for share in shares {
+        if share.id != first.id || share.threshold != first.threshold {
+            return Err(ShareError::MismatchedShares);
+        }
+        if indexes.contains(&share.index) {
+            return Err(ShareError::DuplicateShare(share.index));
+        }
+        indexes.push(share.index);
+    }
+    if shares.len() < usize::from(first.threshold) {
+        return Err(ShareError::NotEnoughShares {
+            threshold: first.threshold,
+            found: shares.len(),
+        });
+    }
+
+    // Lagrange interpolation at x = 0 over the first `threshold` shares. In
+    // GF(256) subtraction is addition, so the basis polynomial of share i at
+    // zero is the product of x_j / (x_j + x_i) over the other shares j.
+    let shares = &shares[..usize::from(first.threshold)];
+    let mut secret = [0u8; SECRET_LEN];
+    for share in shares {
+        let basis = shares
+            .iter()
+            .filter(|other| other.index != share.index)
+            .fold(1, |basis, other| {
+                gf_mul(
+                    basis,
+                    gf_mul(other.index, gf_inverse(other.index ^ share.index)),
+                )
+            });
+        for (secret, value) in secret.iter_mut().zip(share.value.iter()) {
+            *secret ^= gf_mul(basis, *value);
+        }
+    }
+    Ok(secret)
+}
+
+/// Multiplication in GF(256) with the AES reduction polynomial
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        // Branch-free to avoid leaking secret bytes through timing
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse in GF(256), as a^254
+fn gf_inverse(a: u8) -> u8 {
+    let mut result = 1;
+    let mut power = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = gf_mul(result, power);
+        }
+        power = gf_mul(power, power);
+        exponent >>= 1;
+    }
+    result
+}
+
+fn get_bit(bytes: &[u8], position: usize) -> bool {
+    bytes
+        .get(position / 8)
+        .map(|byte| byte & (0x80 >> (position % 8)) != 0)
+        .unwrap_or(false)
+}
+
+/// The BIP39 English word list, in order
+///
+/// `tiny-bip39` does not expose its word lists, so each word is recovered as
+/// the first word of the mnemonic for entropy starting with its 11-bit index.
+fn wordlist() -> &'static [String] {
+    static WORDLIST: OnceLock<Vec<String>> = OnceLock::new();
+    WORDLIST.get_or_init(|| {
+        (0u16..1 << BITS_PER_WORD)
+            .map(|bits| {
+                let mut entropy = [0u8; 16];
+                entropy[..2].copy_from_slice(&(bits << 5).to_be_bytes());
+                let mnemonic = Mnemonic::from_entropy(&entropy, Language::English)
+                    .expect("valid entropy length");
+                mnemonic.phrase().split(' ').next().unwrap().to_string()
+            })
+            .collect()
+    })
+}
+
+fn word_indexes() -> &'static HashMap<&'static str, u16> {
+    static WORD_INDEXES: OnceLock<HashMap<&'static str, u16>> = OnceLock::new();
+    WORD_INDEXES.get_or_init(|| {
+        wordlist()
+            .iter()
+            .enumerate()
+            .map(|(index, word)| (word.as_str(), index as u16))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gf_arithmetic() {
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf_mul(0x57, 0x13), 0xfe);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inverse(a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_wordlist() {
+        let words = wordlist();
+        assert_eq!(words.len(), 2048);
+        assert_eq!(words[0], "abandon");
+        assert_eq!(words[2047], "zoo");
+    }
+
+    #[test]
+    fn test_split_combine() {
+        let mut secret = [0u8; SECRET_LEN];
+        OsRng.fill_bytes(&mut secret);
+        let shares = split_secret(&secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for combination in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let subset: Vec<_> = combination.iter().map(|i| shares[*i].clone()).collect();
+            assert_eq!(combine_shares(&subset).unwrap(), secret);
+        }
+        assert_eq!(
+            combine_shares(&shares[..2]).unwrap_err(),
+            ShareError::NotEnoughShares {
+                threshold: 3,
+                found: 2
+            }
+        );
+        assert_eq!(
+            combine_shares(&[shares[0].clone(), shares[0].clone(), shares[1].clone()]).unwrap_err(),
+            ShareError::DuplicateShare(1)
+        );
+        let mut other = split_secret(&secret, 3, 5).unwrap();
+        other[0].id = shares[0].id.wrapping_add(1);
+        assert_eq!(
+            combine_shares(&[shares[1].clone(), shares[2].clone(), other[0].clone()]).unwrap_err(),
+            ShareError::MismatchedShares
+        );
+
+        // A threshold of one hands out copies of the secret
+        let shares = split_secret(&secret, 1, 2).unwrap();
+        assert_eq!(shares[1].value, secret);
+
+        assert_eq!(
+            split_secret(&secret, 3, 2).unwrap_err(),
+            ShareError::InvalidParameters
+        );
+        assert_eq!(
+            split_secret(&secret, 0, 2).unwrap_err(),
+            ShareError::InvalidParameters
+        );
+    }
+
+    #[test]
+    fn test_share_mnemonic() {
+        let share = SecretShare {
+            id: 0xbeef,
+            threshold: 2,
+            index: 3,
+            value: [7; SECRET_LEN],
+        };
+        let mnemonic = share.to_mnemonic();
+        assert_eq!(mnemonic.split(' ').count(), SHARE_WORD_COUNT);
+        assert_eq!(SecretShare::from_mnemonic(&mnemonic).unwrap(), share);
+        assert_eq!(
+            SecretShare::from_mnemonic(&format!("  {}\n", mnemonic.to_uppercase())).unwrap(),
+            share
+        );
+
+        let mut words: Vec<_> = mnemonic.split(' ').collect();
+        words[5] = if words[5] == "zoo" { "abandon" } else { "zoo" };
+        assert_eq!(
+            SecretShare::from_mnemonic(&words.join(" ")).unwrap_err(),
+            ShareError::InvalidChecksum
+        );
+        words.pop();
+        assert_eq!(
+            SecretShare::from_mnemonic(&words.join(" ")).unwrap_err(),
+            ShareError::InvalidWordCount(SHARE_WORD_COUNT - 1)
+        );
+        assert_eq!(
+            SecretShare::from_mnemonic(&mnemonic.replace(words[0], "trezoa")).unwrap_err(),
+            ShareError::UnknownWord("trezoa".to_string())
+        );
+
+        let share = SecretShare {
+            threshold: 0,
+            ..share
+        };
+        assert_eq!(
+            SecretShare::from_mnemonic(&share.to_mnemonic()).unwrap_err(),
+            ShareError::InvalidThreshold
+        );
+        assert_eq!(
+            combine_shares(&[share]).unwrap_err(),
+            ShareError::InvalidThreshold
+        );
+    }
+}
@@ -19,6 +19,7 @@ use {
     clap::ArgMatches,
     rpassword::prompt_password,
     trezoa_remote_wallet::{
+        external_signer::ExternalSigner,
         remote_keypair::generate_remote_keypair,
         remote_wallet::{maybe_wallet_manager, RemoteWalletError, RemoteWalletManager},
     },
@@ -28,10 +29,10 @@ use {
         message::Message,
         pubkey::Pubkey,
         signature::{
-            generate_seed_from_seed_phrase_and_passphrase, read_keypair, read_keypair_file,
-            EncodableKey, EncodableKeypair, Keypair, NullSigner, Presigner, SeedDerivable,
-            Signature, Signer,
+            generate_seed_from_seed_phrase_and_passphrase, read_keypair, EncodableKey,
+            EncodableKeypair, Keypair, NullSigner, Presigner, SeedDerivable, Signature, Signer,
         },
+        signer::encrypted_keypair::{read_keypair_file_pubkey, read_keypair_file_with_passphrase},
     },
     trezoa_zk_token_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
     std::{
@@ -476,6 +477,22 @@ pub struct SignerFromPathConfig {
 ///   - `usb://ledger/9rPVSygg3brqghvdZ6wsL2i5YNQTGhXGdJzF65YxaCQd`
 ///   - `usb://ledger/9rPVSygg3brqghvdZ6wsL2i5YNQTGhXGdJzF65YxaCQd?key=0/0`
 ///
+/// - `exec:` &mdash; Spawn an external signer program and sign by talking to
+///   it over its stdin and stdout. The URI path is the path of the program,
+///   and each `arg=` query parameter is passed to it as a command line
+///   argument, in order.
+///
+/// - `plugin:` &mdash; Sign by talking to an external signer listening on the
+///   Unix domain socket at the URI path.
+///
+///   Both external signer schemes use the line-delimited JSON-RPC protocol
+///   documented in [`trezoa_remote_wallet::external_signer`].
+///
+///   Examples:
+///
+///   - `exec:///usr/local/bin/kms-signer?arg=--key&arg=treasury`
+///   - `plugin:///run/hsm-signer.sock`
+///
 /// Next the `path` argument may be one of the following strings:
 ///
 /// - `-` &mdash; Read the keypair from stdin. This is the same as the `stdin:`
@@ -652,14 +669,19 @@ pub fn signer_from_source_with_config(
                 *legacy,
             )?))
         }
-        SignerSourceKind::Filepath(path) => match read_keypair_file(path) {
-            Err(e) => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("could not read keypair file \"{path}\". Run \"trezoa-keygen new\" to create a keypair file: {e}"),
-            )
-            .into()),
-            Ok(file) => Ok(Box::new(file)),
-        },
+        SignerSourceKind::Filepath(path) => {
+            match read_keypair_file_with_prompt(path, keypair_name) {
+                Err(e) => Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!(
+                        "could not read keypair file \"{path}\". \
+                        Run \"trezoa-keygen new\" to create a keypair file: {e}"
+                    ),
+                )
+                .into()),
+                Ok(file) => Ok(Box::new(file)),
+            }
+        }
         SignerSourceKind::Stdin => {
             let mut stdin = std::io::stdin();
             Ok(Box::new(read_keypair(&mut stdin)?))
@@ -697,6 +719,7 @@ pub fn signer_from_source_with_config(
                 .into())
             }
         }
+        SignerSourceKind::External(locator) => Ok(Box::new(ExternalSigner::new(locator)?)),
     }
 }
 
@@ -794,7 +817,7 @@ pub fn resolve_signer_from_source(
             )
             .map(|_| None)
         }
-        SignerSourceKind::Filepath(path) => match read_keypair_file(path) {
+        SignerSourceKind::Filepath(path) => match read_keypair_file_pubkey(path) {
             Err(e) => Err(std::io::Error::new(
                 std::io::ErrorKind::Other,
                 format!(
@@ -831,6 +854,7 @@ pub fn resolve_signer_from_source(
             }
         }
         SignerSourceKind::Pubkey(pubkey) => Ok(Some(pubkey.to_string())),
+        SignerSourceKind::External(locator) => Ok(Some(locator.to_string())),
     }
 }
 
@@ -855,6 +879,18 @@ pub fn prompt_passphrase(prompt: &str) -> Result<String, Box<dyn error::Error>>
     Ok(passphrase)
 }
 
+/// Reads a keypair file, prompting for the passphrase if the file is encrypted
+pub(crate) fn read_keypair_file_with_prompt(
+    path: &str,
+    keypair_name: &str,
+) -> Result<Keypair, Box<dyn error::Error>> {
+    read_keypair_file_with_passphrase(path, |pubkey| {
+        Ok(prompt_password(format!(
+            "[{keypair_name}] passphrase for encrypted keypair {pubkey}: "
+        ))?)
+    })
+}
+
 /// Loads a [Keypair] from one of several possible sources.
 ///
 /// The `path` is not strictly a file system path, but is interpreted as various
@@ -901,12 +937,8 @@ pub fn keypair_from_path(
     keypair_name: &str,
     confirm_pubkey: bool,
 ) -> Result<Keypair, Box<dyn error::Error>> {
-    let skip_validation = matches.try_contains_id(SKIP_SEED_PHRASE_VALIDATION_ARG.name)?;
-    let keypair = encodable_key_from_path(path, keypair_name, skip_validation)?;
-    if confirm_pubkey {
-        confirm_encodable_keypair_pubkey(&keypair, "pubkey");
-    }
-    Ok(keypair)
+    let source = SignerSource::parse(path)?;
+    keypair_from_source(matches, &source, keypair_name, confirm_pubkey)
 }
 
 pub fn keypair_from_source(
@@ -915,8 +947,21 @@ pub fn keypair_from_source(
     keypair_name: &str,
     confirm_pubkey: bool,
 ) -> Result<Keypair, Box<dyn error::Error>> {
-    let skip_validation = matches.try_contains_id(SKIP_SEED_PHRASE_VALIDATION_ARG.name)?;
-    let keypair = encodable_key_from_source(source, keypair_name, skip_validation)?;
+    let keypair = if let SignerSourceKind::Filepath(path) = &source.kind {
+        // Keypair files may be encrypted, which other encodable keys do not support
+        read_keypair_file_with_prompt(path, keypair_name).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!(
+                    "could not read keypair file \"{path}\". \
+                    Run \"trezoa-keygen new\" to create a keypair file: {e}"
+                ),
+            )
+        })?
+    } else {
+        let skip_validation = matches.try_contains_id(SKIP_SEED_PHRASE_VALIDATION_ARG.name)?;
+        encodable_key_from_source(source, keypair_name, skip_validation)?
+    };
     if confirm_pubkey {
         confirm_encodable_keypair_pubkey(&keypair, "pubkey");
     }
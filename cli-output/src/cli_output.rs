@@ -1877,6 +1877,89 @@ impl fmt::Display for CliSignOnlyData {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliPartiallySignedTransaction {
+    pub file: String,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<CliTransaction>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub signers: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub missing_signers: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub invalid_signers: Vec<String>,
+}
+
+impl QuietDisplay for CliPartiallySignedTransaction {}
+impl VerboseDisplay for CliPartiallySignedTransaction {}
+
+impl fmt::Display for CliPartiallySignedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(transaction) = self.transaction.as_ref() {
+            write!(f, "{transaction}")?;
+        }
+        writeln!(f)?;
+        writeln_name_value(f, "Transaction File:", &self.file)?;
+        if !self.signers.is_empty() {
+            writeln!(f, "{}", style("Signers (Pubkey=Signature):").bold())?;
+            for signer in self.signers.iter() {
+                writeln!(f, " {signer}")?;
+            }
+        }
+        if !self.missing_signers.is_empty() {
+            writeln!(f, "{}", style("Missing Signers (Pubkey):").bold())?;
+            for pubkey in self.missing_signers.iter() {
+                writeln!(f, " {pubkey}")?;
+            }
+        }
+        if !self.invalid_signers.is_empty() {
+            writeln!(f, "{}", style("Bad Signatures (Pubkey):").bold())?;
+            for pubkey in self.invalid_signers.iter() {
+                writeln!(f, " {pubkey}")?;
+            }
+        }
+        if self.missing_signers.is_empty() && self.invalid_signers.is_empty() {
+            writeln!(f, "All required signatures are present")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliTransactionSimulation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub err: Option<String>,
+    pub logs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub units_consumed: Option<u64>,
+}
+
+impl QuietDisplay for CliTransactionSimulation {}
+impl VerboseDisplay for CliTransactionSimulation {}
+
+impl fmt::Display for CliTransactionSimulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f)?;
+        writeln_name_value(
+            f,
+            "Simulation Result:",
+            self.err.as_deref().unwrap_or("Success"),
+        )?;
+        if let Some(units_consumed) = self.units_consumed {
+            writeln_name_value(f, "Compute Units Consumed:", &units_consumed.to_string())?;
+        }
+        if !self.logs.is_empty() {
+            writeln!(f, "{}", style("Log Messages:").bold())?;
+            for log in self.logs.iter() {
+                writeln!(f, "  {log}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CliSignature {
@@ -2611,7 +2694,7 @@ pub fn parse_sign_only_reply_string(reply: &str) -> SignOnly {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum CliSignatureVerificationStatus {
     None,
@@ -2,7 +2,7 @@ use {
     crate::{
         address_lookup_table::AddressLookupTableSubCommands, cli::*, cluster_query::*, feature::*,
         inflation::*, nonce::*, program::*, program_v4::ProgramV4SubCommands, stake::*,
-        validator_info::*, vote::*, wallet::*,
+        transaction::TransactionSubCommands, validator_info::*, vote::*, wallet::*,
     },
     clap::{App, AppSettings, Arg, ArgGroup, SubCommand},
     trezoa_clap_utils::{self, hidden_unless_forced, input_validators::*, keypair::*},
@@ -150,6 +150,7 @@ pub fn get_clap_app<'ab, 'v>(name: &str, about: &'ab str, version: &'v str) -> A
         .program_v4_subcommands()
         .address_lookup_table_subcommands()
         .stake_subcommands()
+        .transaction_subcommands()
         .validator_info_subcommands()
         .vote_subcommands()
         .wallet_subcommands()
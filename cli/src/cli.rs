@@ -1,13 +1,14 @@
 use {
     crate::{
         address_lookup_table::*, clap_app::*, cluster_query::*, feature::*, inflation::*, nonce::*,
-        program::*, program_v4::*, spend_utils::*, stake::*, validator_info::*, vote::*, wallet::*,
+        program::*, program_v4::*, spend_utils::*, stake::*, transaction::*, validator_info::*,
+        vote::*, wallet::*,
     },
     clap::{crate_description, crate_name, value_t_or_exit, ArgMatches, Shell},
     log::*,
     num_traits::FromPrimitive,
     serde_json::{self, Value},
-    trezoa_clap_utils::{self, input_parsers::*, keypair::*},
+    trezoa_clap_utils::{self, compute_unit_price::AutoPriorityFee, input_parsers::*, keypair::*},
     trezoa_cli_config::ConfigInput,
     trezoa_cli_output::{
         display::println_name_value, CliSignature, CliValidatorsSortOrder, OutputFormat,
@@ -194,6 +195,7 @@ pub enum CliCommand {
         fee_payer: SignerIndex,
         from: SignerIndex,
         compute_unit_price: Option<u64>,
+        priority_fee: Option<AutoPriorityFee>,
     },
     DeactivateStake {
         stake_account_pubkey: Pubkey,
@@ -208,6 +210,7 @@ pub enum CliCommand {
         seed: Option<String>,
         fee_payer: SignerIndex,
         compute_unit_price: Option<u64>,
+        priority_fee: Option<AutoPriorityFee>,
     },
     DelegateStake {
         stake_account_pubkey: Pubkey,
@@ -223,6 +226,7 @@ pub enum CliCommand {
         fee_payer: SignerIndex,
         redelegation_stake_account: Option<SignerIndex>,
         compute_unit_price: Option<u64>,
+        priority_fee: Option<AutoPriorityFee>,
     },
     SplitStake {
         stake_account_pubkey: Pubkey,
@@ -238,6 +242,7 @@ pub enum CliCommand {
         lamports: u64,
         fee_payer: SignerIndex,
         compute_unit_price: Option<u64>,
+        priority_fee: Option<AutoPriorityFee>,
         rent_exempt_reserve: Option<u64>,
     },
     MergeStake {
@@ -252,6 +257,7 @@ pub enum CliCommand {
         memo: Option<String>,
         fee_payer: SignerIndex,
         compute_unit_price: Option<u64>,
+        priority_fee: Option<AutoPriorityFee>,
     },
     ShowStakeHistory {
         use_lamports_unit: bool,
@@ -276,6 +282,7 @@ pub enum CliCommand {
         custodian: Option<SignerIndex>,
         no_wait: bool,
         compute_unit_price: Option<u64>,
+        priority_fee: Option<AutoPriorityFee>,
     },
     StakeSetLockup {
         stake_account_pubkey: Pubkey,
@@ -290,6 +297,7 @@ pub enum CliCommand {
         memo: Option<String>,
         fee_payer: SignerIndex,
         compute_unit_price: Option<u64>,
+        priority_fee: Option<AutoPriorityFee>,
     },
     WithdrawStake {
         stake_account_pubkey: Pubkey,
@@ -306,6 +314,7 @@ pub enum CliCommand {
         seed: Option<String>,
         fee_payer: SignerIndex,
         compute_unit_price: Option<u64>,
+        priority_fee: Option<AutoPriorityFee>,
     },
     // Validator Info Commands
     GetValidatorInfo(Option<Pubkey>),
@@ -445,6 +454,8 @@ pub enum CliCommand {
     },
     // Address lookup table commands
     AddressLookupTable(AddressLookupTableCliCommand),
+    // Partially signed transaction commands
+    Transaction(TransactionCliCommand),
     SignOffchainMessage {
         message: OffchainMessage,
     },
@@ -697,6 +708,9 @@ pub fn parse_command(
         ("address-lookup-table", Some(matches)) => {
             parse_address_lookup_table_subcommand(matches, default_signer, wallet_manager)
         }
+        ("tx", Some(matches)) => {
+            parse_transaction_subcommand(matches, default_signer, wallet_manager)
+        }
         ("wait-for-max-stake", Some(matches)) => {
             let max_stake_percent = value_t_or_exit!(matches, "max_percent", f32);
             Ok(CliCommandInfo {
@@ -1135,6 +1149,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             fee_payer,
             from,
             compute_unit_price,
+            priority_fee,
         } => process_create_stake_account(
             &rpc_client,
             config,
@@ -1154,6 +1169,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             *fee_payer,
             *from,
             compute_unit_price.as_ref(),
+            *priority_fee,
         ),
         CliCommand::DeactivateStake {
             stake_account_pubkey,
@@ -1168,6 +1184,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             seed,
             fee_payer,
             compute_unit_price,
+            priority_fee,
         } => process_deactivate_stake_account(
             &rpc_client,
             config,
@@ -1183,6 +1200,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             seed.as_ref(),
             *fee_payer,
             compute_unit_price.as_ref(),
+            *priority_fee,
         ),
         CliCommand::DelegateStake {
             stake_account_pubkey,
@@ -1198,6 +1216,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             fee_payer,
             redelegation_stake_account,
             compute_unit_price,
+            priority_fee,
         } => process_delegate_stake(
             &rpc_client,
             config,
@@ -1214,6 +1233,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             *fee_payer,
             *redelegation_stake_account,
             compute_unit_price.as_ref(),
+            *priority_fee,
         ),
         CliCommand::SplitStake {
             stake_account_pubkey,
@@ -1229,6 +1249,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             lamports,
             fee_payer,
             compute_unit_price,
+            priority_fee,
             rent_exempt_reserve,
         } => process_split_stake(
             &rpc_client,
@@ -1246,6 +1267,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             *lamports,
             *fee_payer,
             compute_unit_price.as_ref(),
+            *priority_fee,
             rent_exempt_reserve.as_ref(),
         ),
         CliCommand::MergeStake {
@@ -1260,6 +1282,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             memo,
             fee_payer,
             compute_unit_price,
+            priority_fee,
         } => process_merge_stake(
             &rpc_client,
             config,
@@ -1274,6 +1297,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             memo.as_ref(),
             *fee_payer,
             compute_unit_price.as_ref(),
+            *priority_fee,
         ),
         CliCommand::ShowStakeAccount {
             pubkey: stake_account_pubkey,
@@ -1305,6 +1329,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             custodian,
             no_wait,
             compute_unit_price,
+            priority_fee,
         } => process_stake_authorize(
             &rpc_client,
             config,
@@ -1320,6 +1345,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             *fee_payer,
             *no_wait,
             compute_unit_price.as_ref(),
+            *priority_fee,
         ),
         CliCommand::StakeSetLockup {
             stake_account_pubkey,
@@ -1334,6 +1360,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             memo,
             fee_payer,
             compute_unit_price,
+            priority_fee,
         } => process_stake_set_lockup(
             &rpc_client,
             config,
@@ -1349,6 +1376,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             memo.as_ref(),
             *fee_payer,
             compute_unit_price.as_ref(),
+            *priority_fee,
         ),
         CliCommand::WithdrawStake {
             stake_account_pubkey,
@@ -1365,6 +1393,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             seed,
             fee_payer,
             compute_unit_price,
+            priority_fee,
         } => process_withdraw_stake(
             &rpc_client,
             config,
@@ -1382,6 +1411,7 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             seed.as_ref(),
             *fee_payer,
             compute_unit_price.as_ref(),
+            *priority_fee,
         ),
         CliCommand::StakeMinimumDelegation { use_lamports_unit } => {
             process_stake_minimum_delegation(&rpc_client, config, *use_lamports_unit)
@@ -1654,6 +1684,10 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
         CliCommand::AddressLookupTable(subcommand) => {
             process_address_lookup_table_subcommand(rpc_client, config, subcommand)
         }
+        // Partially Signed Transaction Commands
+        CliCommand::Transaction(subcommand) => {
+            process_transaction_subcommand(rpc_client, config, subcommand)
+        }
         CliCommand::SignOffchainMessage { message } => {
             process_sign_offchain_message(config, message)
         }
@@ -1743,6 +1777,7 @@ mod tests {
         },
         trezoa_rpc_client_nonce_utils::blockhash_query,
         trezoa_sdk::{
+            offchain_message::v1,
             pubkey::Pubkey,
             signature::{
                 keypair_from_seed, read_keypair_file, write_keypair_file, Keypair, Presigner,
@@ -2048,6 +2083,55 @@ mod tests {
                 signers: vec![Box::new(read_keypair_file(&keypair_file).unwrap())],
             }
         );
+
+        // Test SignOffchainMessage version 1
+        let cosigner = Pubkey::new_unique();
+        let application_domain = Hash::new_unique();
+        let test_sign_offchain_v1 = test_commands.clone().get_matches_from(vec![
+            "test",
+            "sign-offchain-message",
+            "Test Message",
+            "--version",
+            "1",
+            "--application-domain",
+            &application_domain.to_string(),
+            "--message-signer",
+            &cosigner.to_string(),
+            "--message-signer",
+            &keypair.pubkey().to_string(),
+        ]);
+        let message = OffchainMessage::V1(
+            v1::OffchainMessage::new(
+                application_domain.to_bytes(),
+                &[cosigner, keypair.pubkey()],
+                b"Test Message",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            parse_command(&test_sign_offchain_v1, &default_signer, &mut None).unwrap(),
+            CliCommandInfo {
+                command: CliCommand::SignOffchainMessage { message },
+                signers: vec![Box::new(read_keypair_file(&keypair_file).unwrap())],
+            }
+        );
+        let test_sign_offchain_v1 = test_commands.clone().get_matches_from(vec![
+            "test",
+            "sign-offchain-message",
+            "Test Message",
+            "--version",
+            "1",
+            "--message-signer",
+            &cosigner.to_string(),
+        ]);
+        assert!(parse_command(&test_sign_offchain_v1, &default_signer, &mut None).is_err());
+        let test_sign_offchain_v0 = test_commands.clone().get_matches_from(vec![
+            "test",
+            "sign-offchain-message",
+            "Test Message",
+            "--document",
+        ]);
+        assert!(parse_command(&test_sign_offchain_v0, &default_signer, &mut None).is_err());
     }
 
     #[test]
@@ -2196,6 +2280,7 @@ mod tests {
             fee_payer: 0,
             from: 0,
             compute_unit_price: None,
+            priority_fee: None,
         };
         config.signers = vec![&keypair, &bob_keypair];
         let result = process_command(&config);
@@ -2218,6 +2303,7 @@ mod tests {
             seed: None,
             fee_payer: 0,
             compute_unit_price: None,
+            priority_fee: None,
         };
         config.signers = vec![&keypair];
         let result = process_command(&config);
@@ -2237,6 +2323,7 @@ mod tests {
             seed: None,
             fee_payer: 0,
             compute_unit_price: None,
+            priority_fee: None,
         };
         let result = process_command(&config);
         assert!(result.is_ok());
@@ -2257,6 +2344,7 @@ mod tests {
             lamports: 30,
             fee_payer: 0,
             compute_unit_price: None,
+            priority_fee: None,
             rent_exempt_reserve: None,
         };
         config.signers = vec![&keypair, &split_stake_account];
@@ -2278,6 +2366,7 @@ mod tests {
             memo: None,
             fee_payer: 0,
             compute_unit_price: None,
+            priority_fee: None,
         };
         config.signers = vec![&keypair, &merge_stake_account];
         let result = process_command(&config);
@@ -1,4 +1,39 @@
-use trezoa_sdk::{compute_budget::ComputeBudgetInstruction, instruction::Instruction};
+use {
+    crate::cli::CliConfig,
+    trezoa_clap_utils::compute_unit_price::AutoPriorityFee,
+    trezoa_rpc_client::{rpc_client::RpcClient, spinner::new_progress_bar},
+    trezoa_rpc_client_api::{
+        client_error::{ErrorKind as ClientErrorKind, Result as ClientResult},
+        config::RpcSimulateTransactionConfig,
+    },
+    trezoa_sdk::{
+        commitment_config::CommitmentConfig,
+        compute_budget::{self, ComputeBudgetInstruction},
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        pubkey::Pubkey,
+        signature::Signature,
+        signers::Signers,
+        transaction::{uses_durable_nonce, Transaction},
+    },
+    std::{
+        thread::sleep,
+        time::{Duration, Instant},
+    },
+};
+
+/// Compute unit limit requested while simulating, the most a transaction may use
+const SIMULATION_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+/// Percentage the compute unit price is raised by for each new blockhash
+const PRIORITY_FEE_ESCALATION_PERCENT: u64 = 50;
+/// Escalated compute unit prices are capped at this multiple of the estimate
+const MAX_PRIORITY_FEE_MULTIPLIER: u64 = 8;
+/// How long to wait for a transaction to land before resending it
+const RESEND_INTERVAL: Duration = Duration::from_secs(2);
+/// How many blockhashes to try, escalating the compute unit price for each
+const MAX_SEND_ATTEMPTS: usize = 5;
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 pub trait WithComputeUnitPrice {
     fn with_compute_unit_price(self, compute_unit_price: Option<&u64>) -> Self;
@@ -14,3 +49,406 @@ impl WithComputeUnitPrice for Vec<Instruction> {
         self
     }
 }
+
+/// Nearest-rank percentile of recent prioritization fees, or 0 if there are none
+pub fn prioritization_fee_percentile(fees: &[u64], percentile: u8) -> u64 {
+    if fees.is_empty() {
+        return 0;
+    }
+    let mut fees = fees.to_vec();
+    fees.sort_unstable();
+    let rank = (fees.len() * usize::from(percentile)).div_ceil(100).max(1);
+    fees[rank.min(fees.len()) - 1]
+}
+
+/// The compute unit price to re-sign with after `compute_unit_price` didn't land
+pub fn escalate_compute_unit_price(compute_unit_price: u64, estimate: u64) -> u64 {
+    let max_compute_unit_price = estimate.max(1).saturating_mul(MAX_PRIORITY_FEE_MULTIPLIER);
+    let increase =
+        (compute_unit_price.saturating_mul(PRIORITY_FEE_ESCALATION_PERCENT) / 100).max(1);
+    compute_unit_price
+        .saturating_add(increase)
+        .min(max_compute_unit_price)
+        .max(compute_unit_price)
+}
+
+/// Recover the instructions of a legacy message, leaving out any compute
+/// budget instructions so they can be replaced
+pub fn decompile_instructions(message: &Message) -> Vec<Instruction> {
+    message
+        .instructions
+        .iter()
+        .filter_map(|instruction| {
+            let program_id = message.account_keys[instruction.program_id_index as usize];
+            if compute_budget::check_id(&program_id) {
+                return None;
+            }
+            let accounts = instruction
+                .accounts
+                .iter()
+                .map(|index| {
+                    let index = *index as usize;
+                    AccountMeta {
+                        pubkey: message.account_keys[index],
+                        is_signer: message.is_signer(index),
+                        is_writable: message.is_maybe_writable(index),
+                    }
+                })
+                .collect();
+            Some(Instruction {
+                program_id,
+                accounts,
+                data: instruction.data.clone(),
+            })
+        })
+        .collect()
+}
+
+fn writable_accounts(message: &Message) -> Vec<Pubkey> {
+    message
+        .account_keys
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| message.is_maybe_writable(*i))
+        .map(|(_, pubkey)| *pubkey)
+        .collect()
+}
+
+fn message_with_compute_budget(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: u64,
+    blockhash: &Hash,
+) -> Message {
+    let mut instructions = instructions.to_vec();
+    if let Some(compute_unit_limit) = compute_unit_limit {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+            compute_unit_limit,
+        ));
+    }
+    instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+        compute_unit_price,
+    ));
+    Message::new_with_blockhash(&instructions, Some(payer), blockhash)
+}
+
+/// Simulate the instructions with the maximum compute unit limit and return
+/// the units they consumed, if the node reported it
+fn simulate_compute_unit_limit(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    compute_unit_price: u64,
+    blockhash: &Hash,
+) -> ClientResult<Option<u32>> {
+    let message = message_with_compute_budget(
+        instructions,
+        payer,
+        Some(SIMULATION_COMPUTE_UNIT_LIMIT),
+        compute_unit_price,
+        blockhash,
+    );
+    let result = rpc_client
+        .simulate_transaction_with_config(
+            &Transaction::new_unsigned(message),
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(config.commitment),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )?
+        .value;
+    if let Some(err) = result.err {
+        return Err(err.into());
+    }
+    Ok(result
+        .units_consumed
+        .map(|units_consumed| units_consumed.min(u64::from(SIMULATION_COMPUTE_UNIT_LIMIT)) as u32))
+}
+
+/// Send a transaction and wait for it to reach the configured commitment.
+///
+/// With `--priority-fee auto`, the compute unit price is estimated from recent
+/// prioritization fees paid for the transaction's writable accounts and the
+/// compute unit limit is set to the units consumed in a simulation. Any
+/// compute budget instructions already in the transaction are replaced. The
+/// signed transaction is resent unchanged until it lands or its blockhash
+/// expires; only then is it re-signed with a fresh blockhash and an escalated
+/// compute unit price, so at most one version can ever execute.
+///
+/// Without a `priority_fee`, and for transactions using a durable nonce, the
+/// transaction is sent unchanged by `send`.
+pub fn send_and_confirm_with_priority_fee<T, F>(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    priority_fee: Option<AutoPriorityFee>,
+    transaction: &Transaction,
+    signers: &T,
+    send: F,
+) -> ClientResult<Signature>
+where
+    T: Signers + ?Sized,
+    F: FnOnce() -> ClientResult<Signature>,
+{
+    match priority_fee {
+        Some(priority_fee) if uses_durable_nonce(transaction).is_none() => {
+            send_with_auto_priority_fee(
+                rpc_client,
+                config,
+                &transaction.message,
+                signers,
+                priority_fee,
+            )
+        }
+        _ => send(),
+    }
+}
+
+fn send_with_auto_priority_fee<T: Signers + ?Sized>(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    message: &Message,
+    signers: &T,
+    priority_fee: AutoPriorityFee,
+) -> ClientResult<Signature> {
+    let payer = message.account_keys[0];
+    let instructions = decompile_instructions(message);
+    if instructions.len() < message.instructions.len() {
+        eprintln!(
+            "Warning: replacing the transaction's compute budget instructions with the ones \
+             estimated by --priority-fee"
+        );
+    }
+    let progress_bar = new_progress_bar();
+    let result = send_until_confirmed(
+        rpc_client,
+        config,
+        message,
+        &instructions,
+        &payer,
+        signers,
+        priority_fee,
+        &|message| progress_bar.set_message(message),
+    );
+    progress_bar.finish_and_clear();
+    result
+}
+
+fn send_until_confirmed<T: Signers + ?Sized>(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    message: &Message,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &T,
+    priority_fee: AutoPriorityFee,
+    set_message: &dyn Fn(String),
+) -> ClientResult<Signature> {
+    set_message("Estimating priority fee...".to_string());
+    let fees: Vec<u64> = rpc_client
+        .get_recent_prioritization_fees(&writable_accounts(message))?
+        .into_iter()
+        .map(|fee| fee.prioritization_fee)
+        .collect();
+    let estimate = prioritization_fee_percentile(&fees, priority_fee.percentile);
+
+    set_message("Simulating transaction...".to_string());
+    let compute_unit_limit = simulate_compute_unit_limit(
+        rpc_client,
+        config,
+        instructions,
+        payer,
+        estimate,
+        &Hash::default(),
+    )?;
+
+    let mut compute_unit_price = estimate;
+    let mut signatures = vec![];
+    let mut sent = false;
+    for attempt in 0..MAX_SEND_ATTEMPTS {
+        if attempt > 0 {
+            compute_unit_price = escalate_compute_unit_price(compute_unit_price, estimate);
+        }
+        let (blockhash, last_valid_block_height) =
+            rpc_client.get_latest_blockhash_with_commitment(config.commitment)?;
+        let message = message_with_compute_budget(
+            instructions,
+            payer,
+            compute_unit_limit,
+            compute_unit_price,
+            &blockhash,
+        );
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction.try_sign(signers, blockhash)?;
+        signatures.push(transaction.signatures[0]);
+
+        let mut landed = false;
+        loop {
+            if !landed {
+                set_message(format!(
+                    "Sending transaction with compute unit price {compute_unit_price}..."
+                ));
+                let result = rpc_client
+                    .send_transaction_with_config(&transaction, config.send_transaction_config);
+                // Once anything was sent, a preflight failure may just mean a
+                // copy already landed, so keep waiting on the signatures instead
+                if let Err(err) = result {
+                    if !sent {
+                        return Err(err);
+                    }
+                }
+                sent = true;
+            }
+
+            let resend_at = Instant::now() + RESEND_INTERVAL;
+            while Instant::now() < resend_at {
+                match poll_signatures(rpc_client, config, &signatures)? {
+                    SignatureStatus::Confirmed(signature) => return Ok(signature),
+                    SignatureStatus::Landed(signature) => {
+                        if !landed {
+                            set_message(format!(
+                                "Waiting for transaction {signature} to reach {:?} commitment...",
+                                config.commitment.commitment
+                            ));
+                        }
+                        landed = true;
+                    }
+                    // The fork it landed on may have been abandoned, send it again
+                    SignatureStatus::Pending => landed = false,
+                }
+                sleep(STATUS_POLL_INTERVAL);
+            }
+
+            // Only re-price once no fork can include this version anymore
+            if !landed
+                && rpc_client.get_block_height_with_commitment(CommitmentConfig::finalized())?
+                    > last_valid_block_height
+            {
+                match poll_signatures(rpc_client, config, &signatures)? {
+                    SignatureStatus::Confirmed(signature) => return Ok(signature),
+                    SignatureStatus::Landed(_) => landed = true,
+                    SignatureStatus::Pending => break,
+                }
+            }
+        }
+    }
+    Err(ClientErrorKind::Custom(format!(
+        "Transaction did not land after {MAX_SEND_ATTEMPTS} blockhashes expired, last compute \
+         unit price {compute_unit_price}"
+    ))
+    .into())
+}
+
+enum SignatureStatus {
+    Pending,
+    Landed(Signature),
+    Confirmed(Signature),
+}
+
+/// Check every version sent so far, failing if one of them landed with an error
+fn poll_signatures(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    signatures: &[Signature],
+) -> ClientResult<SignatureStatus> {
+    let statuses = rpc_client.get_signature_statuses(signatures)?.value;
+    let mut result = SignatureStatus::Pending;
+    for (signature, status) in signatures.iter().zip(statuses) {
+        let Some(status) = status else {
+            continue;
+        };
+        if let Some(err) = status.err {
+            return Err(err.into());
+        }
+        if status.satisfies_commitment(config.commitment) {
+            return Ok(SignatureStatus::Confirmed(*signature));
+        }
+        result = SignatureStatus::Landed(*signature);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::clap_app::get_clap_app,
+        trezoa_clap_utils::compute_unit_price::PRIORITY_FEE_ARG,
+        trezoa_sdk::{signature::Keypair, signer::Signer, system_instruction},
+    };
+
+    #[test]
+    fn test_prioritization_fee_percentile() {
+        assert_eq!(prioritization_fee_percentile(&[], 75), 0);
+        let fees = [50, 10, 40, 20, 30];
+        assert_eq!(prioritization_fee_percentile(&fees, 0), 10);
+        assert_eq!(prioritization_fee_percentile(&fees, 20), 10);
+        assert_eq!(prioritization_fee_percentile(&fees, 50), 30);
+        assert_eq!(prioritization_fee_percentile(&fees, 75), 40);
+        assert_eq!(prioritization_fee_percentile(&fees, 100), 50);
+    }
+
+    #[test]
+    fn test_escalate_compute_unit_price() {
+        assert_eq!(escalate_compute_unit_price(0, 0), 1);
+        assert_eq!(escalate_compute_unit_price(1, 0), 2);
+        assert_eq!(escalate_compute_unit_price(100, 100), 150);
+        assert_eq!(escalate_compute_unit_price(150, 100), 225);
+        assert_eq!(escalate_compute_unit_price(700, 100), 800);
+        assert_eq!(escalate_compute_unit_price(800, 100), 800);
+        assert_eq!(escalate_compute_unit_price(u64::MAX, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_decompile_instructions() {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&payer.pubkey(), &recipient, 42);
+        let instructions = vec![transfer.clone()].with_compute_unit_price(Some(&5));
+        let message = Message::new(&instructions, Some(&payer.pubkey()));
+
+        assert_eq!(decompile_instructions(&message), vec![transfer.clone()]);
+        assert_eq!(writable_accounts(&message), vec![payer.pubkey(), recipient]);
+
+        let message = message_with_compute_budget(
+            &decompile_instructions(&message),
+            &payer.pubkey(),
+            Some(300),
+            7,
+            &Hash::new_unique(),
+        );
+        assert_eq!(
+            message.instructions.len(),
+            3,
+            "transfer, compute unit limit and price"
+        );
+        assert_eq!(decompile_instructions(&message), vec![transfer]);
+    }
+
+    #[test]
+    fn test_priority_fee_arg_scope() {
+        let test_commands = get_clap_app("test", "desc", "version");
+        let stake_account = Pubkey::new_unique().to_string();
+
+        let matches = test_commands.clone().get_matches_from(vec![
+            "test",
+            "deactivate-stake",
+            &stake_account,
+            "--priority-fee",
+            "auto:90",
+        ]);
+        let (_, sub_matches) = matches.subcommand();
+        assert_eq!(
+            sub_matches.unwrap().value_of(PRIORITY_FEE_ARG.name),
+            Some("auto:90")
+        );
+
+        // Commands that don't honour the flag reject it
+        assert!(test_commands
+            .get_matches_from_safe(vec!["test", "balance", "--priority-fee", "auto"])
+            .is_err());
+    }
+}
@@ -38,6 +38,8 @@ pub mod program_v4;
 pub mod spend_utils;
 pub mod stake;
 pub mod test_utils;
+pub mod transaction;
+pub mod transaction_builder;
 pub mod validator_info;
 pub mod vote;
 pub mod wallet;
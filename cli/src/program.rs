@@ -5,6 +5,7 @@ use {
             log_instruction_custom_error, CliCommand, CliCommandInfo, CliConfig, CliError,
             ProcessResult,
         },
+        compute_unit_price::send_and_confirm_with_priority_fee,
     },
     bip39::{Language, Mnemonic, MnemonicType, Seed},
     clap::{App, AppSettings, Arg, ArgMatches, SubCommand},
@@ -13,6 +14,7 @@ use {
     trezoa_bpf_loader_program::syscalls::create_program_runtime_environment_v1,
     trezoa_clap_utils::{
         self,
+        compute_unit_price::{priority_fee_arg, AutoPriorityFee, PRIORITY_FEE_ARG},
         fee_payer::{fee_payer_arg, FEE_PAYER_ARG},
         hidden_unless_forced,
         input_parsers::*,
@@ -90,6 +92,7 @@ pub enum ProgramCliCommand {
         max_len: Option<usize>,
         allow_excessive_balance: bool,
         skip_fee_check: bool,
+        priority_fee: Option<AutoPriorityFee>,
     },
     Upgrade {
         fee_payer_signer_index: SignerIndex,
@@ -108,6 +111,7 @@ pub enum ProgramCliCommand {
         buffer_authority_signer_index: SignerIndex,
         max_len: Option<usize>,
         skip_fee_check: bool,
+        priority_fee: Option<AutoPriorityFee>,
     },
     SetBufferAuthority {
         buffer_pubkey: Pubkey,
@@ -183,6 +187,7 @@ impl ProgramSubCommands for App<'_, '_> {
                                 .help("/path/to/program.so"),
                         )
                         .arg(fee_payer_arg())
+                        .arg(priority_fee_arg())
                         .arg(
                             Arg::with_name("buffer")
                                 .long("buffer")
@@ -280,6 +285,7 @@ impl ProgramSubCommands for App<'_, '_> {
                                 .help("/path/to/program.so"),
                         )
                         .arg(fee_payer_arg())
+                        .arg(priority_fee_arg())
                         .arg(
                             Arg::with_name("buffer")
                                 .long("buffer")
@@ -616,6 +622,7 @@ pub fn parse_program_subcommand(
                     max_len,
                     allow_excessive_balance: matches.is_present("allow_excessive_balance"),
                     skip_fee_check,
+                    priority_fee: value_of(matches, PRIORITY_FEE_ARG.name),
                 }),
                 signers: signer_info.signers,
             }
@@ -698,6 +705,7 @@ pub fn parse_program_subcommand(
                         .unwrap(),
                     max_len,
                     skip_fee_check,
+                    priority_fee: value_of(matches, PRIORITY_FEE_ARG.name),
                 }),
                 signers: signer_info.signers,
             }
@@ -899,6 +907,7 @@ pub fn process_program_subcommand(
             max_len,
             allow_excessive_balance,
             skip_fee_check,
+            priority_fee,
         } => process_program_deploy(
             rpc_client,
             config,
@@ -913,6 +922,7 @@ pub fn process_program_subcommand(
             *max_len,
             *allow_excessive_balance,
             *skip_fee_check,
+            *priority_fee,
         ),
         ProgramCliCommand::Upgrade {
             fee_payer_signer_index,
@@ -941,6 +951,7 @@ pub fn process_program_subcommand(
             buffer_authority_signer_index,
             max_len,
             skip_fee_check,
+            priority_fee,
         } => process_write_buffer(
             rpc_client,
             config,
@@ -951,6 +962,7 @@ pub fn process_program_subcommand(
             *buffer_authority_signer_index,
             *max_len,
             *skip_fee_check,
+            *priority_fee,
         ),
         ProgramCliCommand::SetBufferAuthority {
             buffer_pubkey,
@@ -1082,6 +1094,7 @@ fn process_program_deploy(
     max_len: Option<usize>,
     allow_excessive_balance: bool,
     skip_fee_check: bool,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let fee_payer_signer = config.signers[fee_payer_signer_index];
     let upgrade_authority_signer = config.signers[upgrade_authority_signer_index];
@@ -1221,6 +1234,7 @@ fn process_program_deploy(
             upgrade_authority_signer,
             allow_excessive_balance,
             skip_fee_check,
+            priority_fee,
         )
     } else {
         do_process_program_upgrade(
@@ -1235,6 +1249,7 @@ fn process_program_deploy(
             &buffer_pubkey,
             buffer_signer,
             skip_fee_check,
+            priority_fee,
         )
     };
     if result.is_ok() && is_final {
@@ -1372,6 +1387,7 @@ fn process_program_upgrade(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_write_buffer(
     rpc_client: Arc<RpcClient>,
     config: &CliConfig,
@@ -1382,6 +1398,7 @@ fn process_write_buffer(
     buffer_authority_signer_index: SignerIndex,
     max_len: Option<usize>,
     skip_fee_check: bool,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let fee_payer_signer = config.signers[fee_payer_signer_index];
     let buffer_authority = config.signers[buffer_authority_signer_index];
@@ -1447,6 +1464,7 @@ fn process_write_buffer(
         buffer_authority,
         true,
         skip_fee_check,
+        priority_fee,
     );
     if result.is_err() && buffer_signer_index.is_none() && buffer_signer.is_some() {
         report_ephemeral_mnemonic(words, mnemonic);
@@ -2200,6 +2218,7 @@ fn do_process_program_write_and_deploy(
     buffer_authority_signer: &dyn Signer,
     allow_excessive_balance: bool,
     skip_fee_check: bool,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let blockhash = rpc_client.get_latest_blockhash()?;
 
@@ -2329,6 +2348,7 @@ fn do_process_program_write_and_deploy(
     let final_tx_sig = send_deploy_messages(
         rpc_client,
         config,
+        priority_fee,
         &initial_message,
         &write_messages,
         &final_message,
@@ -2365,6 +2385,7 @@ fn do_process_program_upgrade(
     buffer_pubkey: &Pubkey,
     buffer_signer: Option<&dyn Signer>,
     skip_fee_check: bool,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let blockhash = rpc_client.get_latest_blockhash()?;
 
@@ -2472,6 +2493,7 @@ fn do_process_program_upgrade(
     let final_tx_sig = send_deploy_messages(
         rpc_client,
         config,
+        priority_fee,
         &initial_message,
         &write_messages,
         &final_message,
@@ -2598,9 +2620,11 @@ fn check_payer(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn send_deploy_messages(
     rpc_client: Arc<RpcClient>,
     config: &CliConfig,
+    priority_fee: Option<AutoPriorityFee>,
     initial_message: &Option<Message>,
     write_messages: &[Message],
     final_message: &Option<Message>,
@@ -2619,12 +2643,20 @@ fn send_deploy_messages(
             // account to sign the transaction. One (transfer) only requires the fee-payer signature.
             // This check is to ensure signing does not fail on a KeypairPubkeyMismatch error from an
             // extraneous signature.
-            if message.header.num_required_signatures == 2 {
-                initial_transaction.try_sign(&[fee_payer_signer, initial_signer], blockhash)?;
+            let initial_signers = if message.header.num_required_signatures == 2 {
+                vec![fee_payer_signer, initial_signer]
             } else {
-                initial_transaction.try_sign(&[fee_payer_signer], blockhash)?;
-            }
-            let result = rpc_client.send_and_confirm_transaction_with_spinner(&initial_transaction);
+                vec![fee_payer_signer]
+            };
+            initial_transaction.try_sign(&initial_signers, blockhash)?;
+            let result = send_and_confirm_with_priority_fee(
+                &rpc_client,
+                config,
+                priority_fee,
+                &initial_transaction,
+                &initial_signers,
+                || rpc_client.send_and_confirm_transaction_with_spinner(&initial_transaction),
+            );
             log_instruction_custom_error::<SystemError>(result, config)
                 .map_err(|err| format!("Account allocation failed: {err}"))?;
         } else {
@@ -2635,58 +2667,78 @@ fn send_deploy_messages(
     if !write_messages.is_empty() {
         if let Some(write_signer) = write_signer {
             trace!("Writing program data");
-            let connection_cache = if config.use_quic {
-                ConnectionCache::new_quic("connection_cache_cli_program_quic", 1)
+            if priority_fee.is_some() {
+                // Every write is priced and confirmed on its own, rather than
+                // being sent in parallel through the TPU client
+                let signers = [fee_payer_signer, write_signer];
+                for message in write_messages {
+                    let blockhash = rpc_client.get_latest_blockhash()?;
+                    let mut write_tx = Transaction::new_unsigned(message.clone());
+                    write_tx.try_sign(&signers, blockhash)?;
+                    send_and_confirm_with_priority_fee(
+                        &rpc_client,
+                        config,
+                        priority_fee,
+                        &write_tx,
+                        &signers,
+                        || rpc_client.send_and_confirm_transaction_with_spinner(&write_tx),
+                    )
+                    .map_err(|err| format!("Data writes to account failed: {err}"))?;
+                }
             } else {
-                ConnectionCache::with_udp("connection_cache_cli_program_udp", 1)
-            };
-            let transaction_errors = match connection_cache {
-                ConnectionCache::Udp(cache) => TpuClient::new_with_connection_cache(
-                    rpc_client.clone(),
-                    &config.websocket_url,
-                    TpuClientConfig::default(),
-                    cache,
-                )?
-                .send_and_confirm_messages_with_spinner(
-                    write_messages,
-                    &[fee_payer_signer, write_signer],
-                ),
-                ConnectionCache::Quic(cache) => {
-                    let tpu_client_fut = trezoa_client::nonblocking::tpu_client::TpuClient::new_with_connection_cache(
-                        rpc_client.get_inner_client().clone(),
-                        config.websocket_url.as_str(),
-                        trezoa_client::tpu_client::TpuClientConfig::default(),
-                        cache,
-                    );
-                    let tpu_client = rpc_client
-                        .runtime()
-                        .block_on(tpu_client_fut)
-                        .expect("Should return a valid tpu client");
-
-                    send_and_confirm_transactions_in_parallel_blocking(
+                let connection_cache = if config.use_quic {
+                    ConnectionCache::new_quic("connection_cache_cli_program_quic", 1)
+                } else {
+                    ConnectionCache::with_udp("connection_cache_cli_program_udp", 1)
+                };
+                let transaction_errors = match connection_cache {
+                    ConnectionCache::Udp(cache) => TpuClient::new_with_connection_cache(
                         rpc_client.clone(),
-                        Some(tpu_client),
+                        &config.websocket_url,
+                        TpuClientConfig::default(),
+                        cache,
+                    )?
+                    .send_and_confirm_messages_with_spinner(
                         write_messages,
                         &[fee_payer_signer, write_signer],
-                        SendAndConfirmConfig {
-                            resign_txs_count: Some(5),
-                            with_spinner: true,
-                        },
-                    )
-                },
-            }
-            .map_err(|err| format!("Data writes to account failed: {err}"))?
-            .into_iter()
-            .flatten()
-            .collect::<Vec<_>>();
-
-            if !transaction_errors.is_empty() {
-                for transaction_error in &transaction_errors {
-                    error!("{:?}", transaction_error);
+                    ),
+                    ConnectionCache::Quic(cache) => {
+                        let tpu_client_fut = trezoa_client::nonblocking::tpu_client::TpuClient::new_with_connection_cache(
+                            rpc_client.get_inner_client().clone(),
+                            config.websocket_url.as_str(),
+                            trezoa_client::tpu_client::TpuClientConfig::default(),
+                            cache,
+                        );
+                        let tpu_client = rpc_client
+                            .runtime()
+                            .block_on(tpu_client_fut)
+                            .expect("Should return a valid tpu client");
+
+                        send_and_confirm_transactions_in_parallel_blocking(
+                            rpc_client.clone(),
+                            Some(tpu_client),
+                            write_messages,
+                            &[fee_payer_signer, write_signer],
+                            SendAndConfirmConfig {
+                                resign_txs_count: Some(5),
+                                with_spinner: true,
+                            },
+                        )
+                    },
+                }
+                .map_err(|err| format!("Data writes to account failed: {err}"))?
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+
+                if !transaction_errors.is_empty() {
+                    for transaction_error in &transaction_errors {
+                        error!("{:?}", transaction_error);
+                    }
+                    return Err(
+                        format!("{} write transactions failed", transaction_errors.len()).into(),
+                    );
                 }
-                return Err(
-                    format!("{} write transactions failed", transaction_errors.len()).into(),
-                );
             }
         }
     }
@@ -2701,16 +2753,24 @@ fn send_deploy_messages(
             signers.push(fee_payer_signer);
             final_tx.try_sign(&signers, blockhash)?;
             return Ok(Some(
-                rpc_client
-                    .send_and_confirm_transaction_with_spinner_and_config(
-                        &final_tx,
-                        config.commitment,
-                        RpcSendTransactionConfig {
-                            preflight_commitment: Some(config.commitment.commitment),
-                            ..RpcSendTransactionConfig::default()
-                        },
-                    )
-                    .map_err(|e| format!("Deploying program failed: {e}"))?,
+                send_and_confirm_with_priority_fee(
+                    &rpc_client,
+                    config,
+                    priority_fee,
+                    &final_tx,
+                    &signers,
+                    || {
+                        rpc_client.send_and_confirm_transaction_with_spinner_and_config(
+                            &final_tx,
+                            config.commitment,
+                            RpcSendTransactionConfig {
+                                preflight_commitment: Some(config.commitment.commitment),
+                                ..RpcSendTransactionConfig::default()
+                            },
+                        )
+                    },
+                )
+                .map_err(|e| format!("Deploying program failed: {e}"))?,
             ));
         }
     }
@@ -2798,6 +2858,7 @@ mod tests {
                     max_len: None,
                     allow_excessive_balance: false,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![Box::new(read_keypair_file(&keypair_file).unwrap())],
             }
@@ -2826,6 +2887,7 @@ mod tests {
                     max_len: Some(42),
                     allow_excessive_balance: false,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![Box::new(read_keypair_file(&keypair_file).unwrap())],
             }
@@ -2856,6 +2918,7 @@ mod tests {
                     max_len: None,
                     allow_excessive_balance: false,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![
                     Box::new(read_keypair_file(&keypair_file).unwrap()),
@@ -2888,6 +2951,7 @@ mod tests {
                     max_len: None,
                     allow_excessive_balance: false,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![Box::new(read_keypair_file(&keypair_file).unwrap())],
             }
@@ -2919,6 +2983,7 @@ mod tests {
                     max_len: None,
                     allow_excessive_balance: false,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![
                     Box::new(read_keypair_file(&keypair_file).unwrap()),
@@ -2953,6 +3018,7 @@ mod tests {
                     max_len: None,
                     allow_excessive_balance: false,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![
                     Box::new(read_keypair_file(&keypair_file).unwrap()),
@@ -2982,6 +3048,7 @@ mod tests {
                     is_final: true,
                     max_len: None,
                     skip_fee_check: false,
+                    priority_fee: None,
                     allow_excessive_balance: false,
                 }),
                 signers: vec![Box::new(read_keypair_file(&keypair_file).unwrap())],
@@ -3017,6 +3084,7 @@ mod tests {
                     buffer_authority_signer_index: 0,
                     max_len: None,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![Box::new(read_keypair_file(&keypair_file).unwrap())],
             }
@@ -3042,6 +3110,7 @@ mod tests {
                     buffer_authority_signer_index: 0,
                     max_len: Some(42),
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![Box::new(read_keypair_file(&keypair_file).unwrap())],
             }
@@ -3070,6 +3139,7 @@ mod tests {
                     buffer_authority_signer_index: 0,
                     max_len: None,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![
                     Box::new(read_keypair_file(&keypair_file).unwrap()),
@@ -3101,6 +3171,7 @@ mod tests {
                     buffer_authority_signer_index: 1,
                     max_len: None,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![
                     Box::new(read_keypair_file(&keypair_file).unwrap()),
@@ -3137,6 +3208,7 @@ mod tests {
                     buffer_authority_signer_index: 2,
                     max_len: None,
                     skip_fee_check: false,
+                    priority_fee: None,
                 }),
                 signers: vec![
                     Box::new(read_keypair_file(&keypair_file).unwrap()),
@@ -3695,6 +3767,7 @@ mod tests {
                 max_len: None,
                 allow_excessive_balance: false,
                 skip_fee_check: false,
+                priority_fee: None,
             }),
             signers: vec![&default_keypair],
             output_format: OutputFormat::JsonCompact,
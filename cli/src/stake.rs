@@ -5,7 +5,7 @@ use {
             log_instruction_custom_error, CliCommand, CliCommandInfo, CliConfig, CliError,
             ProcessResult,
         },
-        compute_unit_price::WithComputeUnitPrice,
+        compute_unit_price::{send_and_confirm_with_priority_fee, WithComputeUnitPrice},
         feature::get_feature_activation_epoch,
         memo::WithMemo,
         nonce::check_nonce_account,
@@ -13,7 +13,10 @@ use {
     },
     clap::{value_t, App, Arg, ArgGroup, ArgMatches, SubCommand},
     trezoa_clap_utils::{
-        compute_unit_price::{compute_unit_price_arg, COMPUTE_UNIT_PRICE_ARG},
+        compute_unit_price::{
+            compute_unit_price_arg, priority_fee_arg, AutoPriorityFee, COMPUTE_UNIT_PRICE_ARG,
+            PRIORITY_FEE_ARG,
+        },
         fee_payer::{fee_payer_arg, FEE_PAYER_ARG},
         hidden_unless_forced,
         input_parsers::*,
@@ -226,7 +229,8 @@ impl StakeSubCommands for App<'_, '_> {
                 .nonce_args(false)
                 .arg(fee_payer_arg())
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("create-stake-account-checked")
@@ -292,7 +296,8 @@ impl StakeSubCommands for App<'_, '_> {
                 .nonce_args(false)
                 .arg(fee_payer_arg())
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("delegate-stake")
@@ -323,7 +328,8 @@ impl StakeSubCommands for App<'_, '_> {
                 .nonce_args(false)
                 .arg(fee_payer_arg())
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("redelegate-stake")
@@ -369,7 +375,8 @@ impl StakeSubCommands for App<'_, '_> {
                 .offline_args()
                 .nonce_args(false)
                 .arg(fee_payer_arg())
-                .arg(memo_arg()),
+                .arg(memo_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("stake-authorize")
@@ -411,7 +418,8 @@ impl StakeSubCommands for App<'_, '_> {
                         ),
                 )
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("stake-authorize-checked")
@@ -460,7 +468,8 @@ impl StakeSubCommands for App<'_, '_> {
                         ),
                 )
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("deactivate-stake")
@@ -498,7 +507,8 @@ impl StakeSubCommands for App<'_, '_> {
                 .nonce_args(false)
                 .arg(fee_payer_arg())
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("split-stake")
@@ -544,6 +554,7 @@ impl StakeSubCommands for App<'_, '_> {
                 .arg(fee_payer_arg())
                 .arg(memo_arg())
                 .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg())
                 .arg(
                     Arg::with_name("rent_exempt_reserve_sol")
                         .long("rent-exempt-reserve-trz")
@@ -580,7 +591,8 @@ impl StakeSubCommands for App<'_, '_> {
                 .nonce_args(false)
                 .arg(fee_payer_arg())
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("withdraw-stake")
@@ -628,7 +640,8 @@ impl StakeSubCommands for App<'_, '_> {
                 .arg(fee_payer_arg())
                 .arg(custodian_arg())
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("stake-set-lockup")
@@ -685,7 +698,8 @@ impl StakeSubCommands for App<'_, '_> {
                 .nonce_args(false)
                 .arg(fee_payer_arg())
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("stake-set-lockup-checked")
@@ -744,7 +758,8 @@ impl StakeSubCommands for App<'_, '_> {
                 .nonce_args(false)
                 .arg(fee_payer_arg())
                 .arg(memo_arg())
-                .arg(compute_unit_price_arg()),
+                .arg(compute_unit_price_arg())
+                .arg(priority_fee_arg()),
         )
         .subcommand(
             SubCommand::with_name("stake-account")
@@ -869,6 +884,7 @@ pub fn parse_create_stake_account(
     let signer_info =
         default_signer.generate_unique_signers(bulk_signers, matches, wallet_manager)?;
     let compute_unit_price = value_of(matches, COMPUTE_UNIT_PRICE_ARG.name);
+    let priority_fee = value_of(matches, PRIORITY_FEE_ARG.name);
 
     Ok(CliCommandInfo {
         command: CliCommand::CreateStakeAccount {
@@ -896,6 +912,7 @@ pub fn parse_create_stake_account(
             fee_payer: signer_info.index_of(fee_payer_pubkey).unwrap(),
             from: signer_info.index_of(from_pubkey).unwrap(),
             compute_unit_price,
+            priority_fee,
         },
         signers: signer_info.signers,
     })
@@ -934,6 +951,7 @@ pub fn parse_stake_delegate_stake(
     let signer_info =
         default_signer.generate_unique_signers(bulk_signers, matches, wallet_manager)?;
     let compute_unit_price = value_of(matches, COMPUTE_UNIT_PRICE_ARG.name);
+    let priority_fee = value_of(matches, PRIORITY_FEE_ARG.name);
 
     Ok(CliCommandInfo {
         command: CliCommand::DelegateStake {
@@ -951,6 +969,7 @@ pub fn parse_stake_delegate_stake(
             redelegation_stake_account: redelegation_stake_account_pubkey
                 .and_then(|_| signer_info.index_of(redelegation_stake_account_pubkey)),
             compute_unit_price,
+            priority_fee,
         },
         signers: signer_info.signers,
     })
@@ -1042,6 +1061,7 @@ pub fn parse_stake_authorize(
     let signer_info =
         default_signer.generate_unique_signers(bulk_signers, matches, wallet_manager)?;
     let compute_unit_price = value_of(matches, COMPUTE_UNIT_PRICE_ARG.name);
+    let priority_fee = value_of(matches, PRIORITY_FEE_ARG.name);
 
     if new_authorizations.is_empty() {
         return Err(CliError::BadParameter(
@@ -1080,6 +1100,7 @@ pub fn parse_stake_authorize(
             custodian: custodian_pubkey.and_then(|_| signer_info.index_of(custodian_pubkey)),
             no_wait,
             compute_unit_price,
+            priority_fee,
         },
         signers: signer_info.signers,
     })
@@ -1115,6 +1136,7 @@ pub fn parse_split_stake(
     let signer_info =
         default_signer.generate_unique_signers(bulk_signers, matches, wallet_manager)?;
     let compute_unit_price = value_of(matches, COMPUTE_UNIT_PRICE_ARG.name);
+    let priority_fee = value_of(matches, PRIORITY_FEE_ARG.name);
     let rent_exempt_reserve = lamports_of_sol(matches, "rent_exempt_reserve_sol");
 
     Ok(CliCommandInfo {
@@ -1132,6 +1154,7 @@ pub fn parse_split_stake(
             lamports,
             fee_payer: signer_info.index_of(fee_payer_pubkey).unwrap(),
             compute_unit_price,
+            priority_fee,
             rent_exempt_reserve,
         },
         signers: signer_info.signers,
@@ -1166,6 +1189,7 @@ pub fn parse_merge_stake(
     let signer_info =
         default_signer.generate_unique_signers(bulk_signers, matches, wallet_manager)?;
     let compute_unit_price = value_of(matches, COMPUTE_UNIT_PRICE_ARG.name);
+    let priority_fee = value_of(matches, PRIORITY_FEE_ARG.name);
 
     Ok(CliCommandInfo {
         command: CliCommand::MergeStake {
@@ -1180,6 +1204,7 @@ pub fn parse_merge_stake(
             memo,
             fee_payer: signer_info.index_of(fee_payer_pubkey).unwrap(),
             compute_unit_price,
+            priority_fee,
         },
         signers: signer_info.signers,
     })
@@ -1213,6 +1238,7 @@ pub fn parse_stake_deactivate_stake(
     let signer_info =
         default_signer.generate_unique_signers(bulk_signers, matches, wallet_manager)?;
     let compute_unit_price = value_of(matches, COMPUTE_UNIT_PRICE_ARG.name);
+    let priority_fee = value_of(matches, PRIORITY_FEE_ARG.name);
 
     Ok(CliCommandInfo {
         command: CliCommand::DeactivateStake {
@@ -1228,6 +1254,7 @@ pub fn parse_stake_deactivate_stake(
             seed,
             fee_payer: signer_info.index_of(fee_payer_pubkey).unwrap(),
             compute_unit_price,
+            priority_fee,
         },
         signers: signer_info.signers,
     })
@@ -1266,6 +1293,7 @@ pub fn parse_stake_withdraw_stake(
     let signer_info =
         default_signer.generate_unique_signers(bulk_signers, matches, wallet_manager)?;
     let compute_unit_price = value_of(matches, COMPUTE_UNIT_PRICE_ARG.name);
+    let priority_fee = value_of(matches, PRIORITY_FEE_ARG.name);
 
     Ok(CliCommandInfo {
         command: CliCommand::WithdrawStake {
@@ -1283,6 +1311,7 @@ pub fn parse_stake_withdraw_stake(
             fee_payer: signer_info.index_of(fee_payer_pubkey).unwrap(),
             custodian: custodian_pubkey.and_then(|_| signer_info.index_of(custodian_pubkey)),
             compute_unit_price,
+            priority_fee,
         },
         signers: signer_info.signers,
     })
@@ -1329,6 +1358,7 @@ pub fn parse_stake_set_lockup(
     let signer_info =
         default_signer.generate_unique_signers(bulk_signers, matches, wallet_manager)?;
     let compute_unit_price = value_of(matches, COMPUTE_UNIT_PRICE_ARG.name);
+    let priority_fee = value_of(matches, PRIORITY_FEE_ARG.name);
 
     Ok(CliCommandInfo {
         command: CliCommand::StakeSetLockup {
@@ -1352,6 +1382,7 @@ pub fn parse_stake_set_lockup(
             memo,
             fee_payer: signer_info.index_of(fee_payer_pubkey).unwrap(),
             compute_unit_price,
+            priority_fee,
         },
         signers: signer_info.signers,
     })
@@ -1423,6 +1454,7 @@ pub fn process_create_stake_account(
     fee_payer: SignerIndex,
     from: SignerIndex,
     compute_unit_price: Option<&u64>,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let stake_account = config.signers[stake_account];
     let stake_account_address = if let Some(seed) = seed {
@@ -1549,7 +1581,14 @@ pub fn process_create_stake_account(
         )
     } else {
         tx.try_sign(&config.signers, recent_blockhash)?;
-        let result = rpc_client.send_and_confirm_transaction_with_spinner(&tx);
+        let result = send_and_confirm_with_priority_fee(
+            rpc_client,
+            config,
+            priority_fee,
+            &tx,
+            &config.signers,
+            || rpc_client.send_and_confirm_transaction_with_spinner(&tx),
+        );
         log_instruction_custom_error::<SystemError>(result, config)
     }
 }
@@ -1570,6 +1609,7 @@ pub fn process_stake_authorize(
     fee_payer: SignerIndex,
     no_wait: bool,
     compute_unit_price: Option<&u64>,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let mut ixs = Vec::new();
     let custodian = custodian.map(|index| config.signers[index]);
@@ -1684,7 +1724,14 @@ pub fn process_stake_authorize(
         let result = if no_wait {
             rpc_client.send_transaction(&tx)
         } else {
-            rpc_client.send_and_confirm_transaction_with_spinner(&tx)
+            send_and_confirm_with_priority_fee(
+                rpc_client,
+                config,
+                priority_fee,
+                &tx,
+                &config.signers,
+                || rpc_client.send_and_confirm_transaction_with_spinner(&tx),
+            )
         };
         log_instruction_custom_error::<StakeError>(result, config)
     }
@@ -1706,6 +1753,7 @@ pub fn process_deactivate_stake_account(
     seed: Option<&String>,
     fee_payer: SignerIndex,
     compute_unit_price: Option<&u64>,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let recent_blockhash = blockhash_query.get_blockhash(rpc_client, config.commitment)?;
 
@@ -1824,7 +1872,14 @@ pub fn process_deactivate_stake_account(
             &tx.message,
             config.commitment,
         )?;
-        let result = rpc_client.send_and_confirm_transaction_with_spinner(&tx);
+        let result = send_and_confirm_with_priority_fee(
+            rpc_client,
+            config,
+            priority_fee,
+            &tx,
+            &config.signers,
+            || rpc_client.send_and_confirm_transaction_with_spinner(&tx),
+        );
         log_instruction_custom_error::<StakeError>(result, config)
     }
 }
@@ -1847,6 +1902,7 @@ pub fn process_withdraw_stake(
     seed: Option<&String>,
     fee_payer: SignerIndex,
     compute_unit_price: Option<&u64>,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let withdraw_authority = config.signers[withdraw_authority];
     let custodian = custodian.map(|index| config.signers[index]);
@@ -1923,7 +1979,14 @@ pub fn process_withdraw_stake(
             &tx.message,
             config.commitment,
         )?;
-        let result = rpc_client.send_and_confirm_transaction_with_spinner(&tx);
+        let result = send_and_confirm_with_priority_fee(
+            rpc_client,
+            config,
+            priority_fee,
+            &tx,
+            &config.signers,
+            || rpc_client.send_and_confirm_transaction_with_spinner(&tx),
+        );
         log_instruction_custom_error::<StakeError>(result, config)
     }
 }
@@ -1945,6 +2008,7 @@ pub fn process_split_stake(
     lamports: u64,
     fee_payer: SignerIndex,
     compute_unit_price: Option<&u64>,
+    priority_fee: Option<AutoPriorityFee>,
     rent_exempt_reserve: Option<&u64>,
 ) -> ProcessResult {
     let split_stake_account = config.signers[split_stake_account];
@@ -2092,7 +2156,14 @@ pub fn process_split_stake(
             &tx.message,
             config.commitment,
         )?;
-        let result = rpc_client.send_and_confirm_transaction_with_spinner(&tx);
+        let result = send_and_confirm_with_priority_fee(
+            rpc_client,
+            config,
+            priority_fee,
+            &tx,
+            &config.signers,
+            || rpc_client.send_and_confirm_transaction_with_spinner(&tx),
+        );
         log_instruction_custom_error::<StakeError>(result, config)
     }
 }
@@ -2112,6 +2183,7 @@ pub fn process_merge_stake(
     memo: Option<&String>,
     fee_payer: SignerIndex,
     compute_unit_price: Option<&u64>,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let fee_payer = config.signers[fee_payer];
 
@@ -2198,10 +2270,19 @@ pub fn process_merge_stake(
             &tx.message,
             config.commitment,
         )?;
-        let result = rpc_client.send_and_confirm_transaction_with_spinner_and_config(
+        let result = send_and_confirm_with_priority_fee(
+            rpc_client,
+            config,
+            priority_fee,
             &tx,
-            config.commitment,
-            config.send_transaction_config,
+            &config.signers,
+            || {
+                rpc_client.send_and_confirm_transaction_with_spinner_and_config(
+                    &tx,
+                    config.commitment,
+                    config.send_transaction_config,
+                )
+            },
         );
         log_instruction_custom_error::<StakeError>(result, config)
     }
@@ -2223,6 +2304,7 @@ pub fn process_stake_set_lockup(
     memo: Option<&String>,
     fee_payer: SignerIndex,
     compute_unit_price: Option<&u64>,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     let recent_blockhash = blockhash_query.get_blockhash(rpc_client, config.commitment)?;
     let custodian = config.signers[custodian];
@@ -2293,7 +2375,14 @@ pub fn process_stake_set_lockup(
             &tx.message,
             config.commitment,
         )?;
-        let result = rpc_client.send_and_confirm_transaction_with_spinner(&tx);
+        let result = send_and_confirm_with_priority_fee(
+            rpc_client,
+            config,
+            priority_fee,
+            &tx,
+            &config.signers,
+            || rpc_client.send_and_confirm_transaction_with_spinner(&tx),
+        );
         log_instruction_custom_error::<StakeError>(result, config)
     }
 }
@@ -2643,6 +2732,7 @@ pub fn process_delegate_stake(
     fee_payer: SignerIndex,
     redelegation_stake_account: Option<SignerIndex>,
     compute_unit_price: Option<&u64>,
+    priority_fee: Option<AutoPriorityFee>,
 ) -> ProcessResult {
     check_unique_pubkeys(
         (&config.signers[0].pubkey(), "cli keypair".to_string()),
@@ -2775,7 +2865,14 @@ pub fn process_delegate_stake(
             &tx.message,
             config.commitment,
         )?;
-        let result = rpc_client.send_and_confirm_transaction_with_spinner(&tx);
+        let result = send_and_confirm_with_priority_fee(
+            rpc_client,
+            config,
+            priority_fee,
+            &tx,
+            &config.signers,
+            || rpc_client.send_and_confirm_transaction_with_spinner(&tx),
+        );
         log_instruction_custom_error::<StakeError>(result, config)
     }
 }
@@ -2885,6 +2982,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap()),],
             },
@@ -2934,6 +3032,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -2983,6 +3082,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3018,6 +3118,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap()),],
             },
@@ -3052,6 +3153,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3090,6 +3192,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3125,6 +3228,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap()),],
             },
@@ -3159,6 +3263,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3197,6 +3302,7 @@ mod tests {
                     custodian: None,
                     no_wait: true,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -3244,6 +3350,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3296,6 +3403,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3346,6 +3454,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3382,6 +3491,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3419,6 +3529,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3458,6 +3569,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3494,6 +3606,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3531,6 +3644,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3570,6 +3684,7 @@ mod tests {
                     custodian: None,
                     no_wait: true,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3612,6 +3727,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -3658,6 +3774,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3714,6 +3831,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3756,6 +3874,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -3803,6 +3922,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3846,6 +3966,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3893,6 +4014,7 @@ mod tests {
                     custodian: None,
                     no_wait: false,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3944,6 +4066,7 @@ mod tests {
                     fee_payer: 0,
                     from: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -3985,6 +4108,7 @@ mod tests {
                     fee_payer: 0,
                     from: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4025,6 +4149,7 @@ mod tests {
                     fee_payer: 0,
                     from: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4098,6 +4223,7 @@ mod tests {
                     fee_payer: 0,
                     from: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(Presigner::new(&offline_pubkey, &offline_sig)),
@@ -4132,6 +4258,7 @@ mod tests {
                     fee_payer: 0,
                     redelegation_stake_account: None,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4165,6 +4292,7 @@ mod tests {
                     fee_payer: 0,
                     redelegation_stake_account: None,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4198,6 +4326,7 @@ mod tests {
                     fee_payer: 0,
                     redelegation_stake_account: None,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4234,6 +4363,7 @@ mod tests {
                     fee_payer: 0,
                     redelegation_stake_account: None,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4265,6 +4395,7 @@ mod tests {
                     fee_payer: 0,
                     redelegation_stake_account: None,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4306,6 +4437,7 @@ mod tests {
                     fee_payer: 1,
                     redelegation_stake_account: None,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4356,6 +4488,7 @@ mod tests {
                     fee_payer: 1,
                     redelegation_stake_account: None,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4394,6 +4527,7 @@ mod tests {
                     fee_payer: 1,
                     redelegation_stake_account: None,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4437,6 +4571,7 @@ mod tests {
                     fee_payer: 0,
                     redelegation_stake_account: Some(1),
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4472,6 +4607,7 @@ mod tests {
                     seed: None,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4506,6 +4642,42 @@ mod tests {
                     seed: None,
                     fee_payer: 0,
                     compute_unit_price: Some(99),
+                    priority_fee: None,
+                },
+                signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
+            }
+        );
+
+        // Test WithdrawStake Subcommand w/ PriorityFee
+        let test_withdraw_stake = test_commands.clone().get_matches_from(vec![
+            "test",
+            "withdraw-stake",
+            &stake_account_string,
+            &stake_account_string,
+            "42",
+            "--priority-fee",
+            "auto:90",
+        ]);
+
+        assert_eq!(
+            parse_command(&test_withdraw_stake, &default_signer, &mut None).unwrap(),
+            CliCommandInfo {
+                command: CliCommand::WithdrawStake {
+                    stake_account_pubkey,
+                    destination_account_pubkey: stake_account_pubkey,
+                    amount: SpendAmount::Some(42_000_000_000),
+                    withdraw_authority: 0,
+                    custodian: None,
+                    sign_only: false,
+                    dump_transaction_message: false,
+                    blockhash_query: BlockhashQuery::All(blockhash_query::Source::Cluster),
+                    nonce_account: None,
+                    nonce_authority: 0,
+                    memo: None,
+                    seed: None,
+                    fee_payer: 0,
+                    compute_unit_price: None,
+                    priority_fee: Some(AutoPriorityFee { percentile: 90 }),
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4540,6 +4712,7 @@ mod tests {
                     seed: None,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4577,6 +4750,7 @@ mod tests {
                     seed: None,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4627,6 +4801,7 @@ mod tests {
                     seed: None,
                     fee_payer: 1,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&stake_authority_keypair_file).unwrap()),
@@ -4657,6 +4832,7 @@ mod tests {
                     seed: None,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4685,6 +4861,7 @@ mod tests {
                     seed: None,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4714,6 +4891,7 @@ mod tests {
                     seed: None,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4751,6 +4929,7 @@ mod tests {
                     seed: None,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4780,6 +4959,7 @@ mod tests {
                     seed: None,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap())],
             }
@@ -4819,6 +4999,7 @@ mod tests {
                     seed: None,
                     fee_payer: 1,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4867,6 +5048,7 @@ mod tests {
                     seed: None,
                     fee_payer: 1,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4900,6 +5082,7 @@ mod tests {
                     seed: None,
                     fee_payer: 1,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![
                     Box::new(read_keypair_file(&default_keypair_file).unwrap()),
@@ -4940,6 +5123,7 @@ mod tests {
                     lamports: 50_000_000_000,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                     rent_exempt_reserve: None,
                 },
                 signers: vec![
@@ -5006,6 +5190,7 @@ mod tests {
                     lamports: 50_000_000_000,
                     fee_payer: 1,
                     compute_unit_price: None,
+                    priority_fee: None,
                     rent_exempt_reserve: None,
                 },
                 signers: vec![
@@ -5043,6 +5228,7 @@ mod tests {
                     memo: None,
                     fee_payer: 0,
                     compute_unit_price: None,
+                    priority_fee: None,
                 },
                 signers: vec![Box::new(read_keypair_file(&default_keypair_file).unwrap()),],
             }
@@ -0,0 +1,1311 @@
+//! Partially signed transaction files for multi-party offline signing
+//!
+//! A transaction is created into a file from its message, handed around to
+//! each signer to add their signatures with `tx sign`, merged with
+//! `tx combine` when signers worked on separate copies, and finally sent with
+//! `tx broadcast` once every required signature is present.
+//!
+//! `tx build` composes a transaction from a JSON or YAML description of
+//! arbitrary instructions, see [`crate::transaction_builder`], and simulates
+//! or sends it, or writes it to a partially signed transaction file.
+
+use {
+    crate::{
+        checks::check_account_for_balance_with_commitment,
+        cli::{CliCommand, CliCommandInfo, CliConfig, CliError, ProcessResult},
+        compute_unit_price::WithComputeUnitPrice,
+        spend_utils::{resolve_spend_tx_and_check_account_balance, SpendAmount},
+        transaction_builder::TransactionDescription,
+    },
+    base64::{prelude::BASE64_STANDARD, Engine},
+    clap::{App, AppSettings, Arg, ArgMatches, SubCommand},
+    serde_derive::{Deserialize, Serialize},
+    trezoa_clap_utils::{
+        compute_unit_price::{compute_unit_price_arg, COMPUTE_UNIT_PRICE_ARG},
+        fee_payer::{fee_payer_arg, FEE_PAYER_ARG},
+        input_parsers::{pubkeys_sigs_of, signer_of, value_of},
+        input_validators::*,
+        keypair::*,
+        offline::SIGNER_ARG,
+    },
+    trezoa_cli_output::{
+        CliPartiallySignedTransaction, CliSignature, CliSignatureVerificationStatus,
+        CliTransaction, CliTransactionSimulation,
+    },
+    trezoa_remote_wallet::remote_wallet::RemoteWalletManager,
+    trezoa_rpc_client::rpc_client::RpcClient,
+    trezoa_rpc_client_api::{
+        client_error::Result as ClientResult, config::RpcSimulateTransactionConfig,
+    },
+    trezoa_sdk::{
+        address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
+        compute_budget::ComputeBudgetInstruction,
+        instruction::{Instruction, InstructionError},
+        message::{v0, Message, VersionedMessage},
+        native_token::lamports_to_trz,
+        pubkey::Pubkey,
+        signature::{Signature, Signer},
+        transaction::{TransactionError, VersionedTransaction},
+    },
+    trezoa_transaction_status::EncodableWithMeta,
+    std::{fs, path::Path, rc::Rc, str::FromStr, sync::Arc},
+};
+
+/// Version of the partially signed transaction file format
+pub const PARTIALLY_SIGNED_TRANSACTION_VERSION: u32 = 1;
+
+/// A transaction that may be missing some of its required signatures
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartiallySignedTransaction {
+    transaction: VersionedTransaction,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartiallySignedTransactionFile {
+    version: u32,
+    /// Base64 encoded serialized message
+    message: String,
+    /// Required signers of the message, in order
+    signatures: Vec<PartiallySignedTransactionFileSignature>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartiallySignedTransactionFileSignature {
+    pubkey: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    signature: Option<String>,
+}
+
+impl PartiallySignedTransaction {
+    pub fn new(message: VersionedMessage) -> Result<Self, CliError> {
+        let num_required_signatures = usize::from(message.header().num_required_signatures);
+        if num_required_signatures == 0
+            || num_required_signatures > message.static_account_keys().len()
+        {
+            return Err(CliError::BadParameter(
+                "Transaction message has an invalid number of required signers".to_string(),
+            ));
+        }
+        Ok(Self {
+            transaction: VersionedTransaction {
+                signatures: vec![Signature::default(); num_required_signatures],
+                message,
+            },
+        })
+    }
+
+    pub fn transaction(&self) -> &VersionedTransaction {
+        &self.transaction
+    }
+
+    pub fn into_transaction(self) -> VersionedTransaction {
+        self.transaction
+    }
+
+    /// The pubkeys that must sign the transaction, in signature order
+    pub fn required_signers(&self) -> &[Pubkey] {
+        &self.transaction.message.static_account_keys()[..self.transaction.signatures.len()]
+    }
+
+    pub fn signature_statuses(&self) -> Vec<CliSignatureVerificationStatus> {
+        CliSignatureVerificationStatus::verify_transaction(&self.transaction)
+    }
+
+    /// Required signers without a valid signature
+    pub fn missing_signers(&self) -> Vec<Pubkey> {
+        self.required_signers()
+            .iter()
+            .zip(self.signature_statuses())
+            .filter(|(_, status)| *status != CliSignatureVerificationStatus::Pass)
+            .map(|(pubkey, _)| *pubkey)
+            .collect()
+    }
+
+    pub fn is_fully_signed(&self) -> bool {
+        self.missing_signers().is_empty()
+    }
+
+    /// Adds a signature produced elsewhere, for example by `--sign-only`
+    pub fn add_signature(&mut self, pubkey: &Pubkey, signature: Signature) -> Result<(), CliError> {
+        let position = self.signer_position(pubkey)?;
+        if !signature.verify(pubkey.as_ref(), &self.transaction.message.serialize()) {
+            return Err(CliError::BadParameter(format!(
+                "Invalid signature for {pubkey}"
+            )));
+        }
+        self.transaction.signatures[position] = signature;
+        Ok(())
+    }
+
+    /// Signs with every signer in `signers` that is a required signer,
+    /// returning the pubkeys that signed
+    pub fn sign(&mut self, signers: &[&dyn Signer]) -> Result<Vec<Pubkey>, CliError> {
+        let message_data = self.transaction.message.serialize();
+        let mut signed = vec![];
+        for signer in signers {
+            let pubkey = signer
+                .try_pubkey()
+                .map_err(|err| CliError::BadParameter(err.to_string()))?;
+            if let Ok(position) = self.signer_position(&pubkey) {
+                self.transaction.signatures[position] = signer
+                    .try_sign_message(&message_data)
+                    .map_err(|err| CliError::BadParameter(err.to_string()))?;
+                signed.push(pubkey);
+            }
+        }
+        if signed.is_empty() {
+            return Err(CliError::BadParameter(
+                "None of the provided signers is a required signer of the transaction".to_string(),
+            ));
+        }
+        Ok(signed)
+    }
+
+    /// Copies the valid signatures of `other`, which must be for the same
+    /// message, into this transaction
+    pub fn combine(&mut self, other: &Self) -> Result<(), CliError> {
+        if self.transaction.message != other.transaction.message {
+            return Err(CliError::BadParameter(
+                "Transactions have different messages".to_string(),
+            ));
+        }
+        for ((signature, other_signature), other_status) in self
+            .transaction
+            .signatures
+            .iter_mut()
+            .zip(other.transaction.signatures.iter())
+            .zip(other.signature_statuses())
+        {
+            if other_status == CliSignatureVerificationStatus::Pass {
+                *signature = *other_signature;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, CliError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|err| {
+            CliError::BadParameter(format!("Unable to read {}: {err}", path.display()))
+        })?;
+        Self::from_json(&contents)
+            .map_err(|err| CliError::BadParameter(format!("{}: {err}", path.display())))
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), CliError> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json()).map_err(|err| {
+            CliError::BadParameter(format!("Unable to write {}: {err}", path.display()))
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let file = PartiallySignedTransactionFile {
+            version: PARTIALLY_SIGNED_TRANSACTION_VERSION,
+            message: BASE64_STANDARD.encode(self.transaction.message.serialize()),
+            signatures: self
+                .required_signers()
+                .iter()
+                .zip(self.transaction.signatures.iter())
+                .map(
+                    |(pubkey, signature)| PartiallySignedTransactionFileSignature {
+                        pubkey: pubkey.to_string(),
+                        signature: (*signature != Signature::default())
+                            .then(|| signature.to_string()),
+                    },
+                )
+                .collect(),
+        };
+        serde_json::to_string_pretty(&file).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: PartiallySignedTransactionFile =
+            serde_json::from_str(json).map_err(|err| err.to_string())?;
+        if file.version != PARTIALLY_SIGNED_TRANSACTION_VERSION {
+            return Err(format!(
+                "unsupported partially signed transaction version {}",
+                file.version
+            ));
+        }
+        let message_data = BASE64_STANDARD
+            .decode(&file.message)
+            .map_err(|err| format!("invalid message: {err}"))?;
+        let message =
+            bincode::deserialize(&message_data).map_err(|err| format!("invalid message: {err}"))?;
+        let mut transaction = Self::new(message).map_err(|err| err.to_string())?;
+        if file.signatures.len() != transaction.required_signers().len() {
+            return Err("signatures do not match the required signers".to_string());
+        }
+        for (position, entry) in file.signatures.iter().enumerate() {
+            if entry.pubkey != transaction.required_signers()[position].to_string() {
+                return Err(format!(
+                    "signature {position} is for {} but the message requires {}",
+                    entry.pubkey,
+                    transaction.required_signers()[position]
+                ));
+            }
+            if let Some(signature) = &entry.signature {
+                transaction.transaction.signatures[position] = Signature::from_str(signature)
+                    .map_err(|err| format!("invalid signature for {}: {err}", entry.pubkey))?;
+            }
+        }
+        Ok(transaction)
+    }
+
+    fn signer_position(&self, pubkey: &Pubkey) -> Result<usize, CliError> {
+        self.required_signers()
+            .iter()
+            .position(|signer| signer == pubkey)
+            .ok_or_else(|| {
+                CliError::BadParameter(format!(
+                    "{pubkey} is not a required signer of the transaction"
+                ))
+            })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TransactionCliCommand {
+    Create {
+        message: VersionedMessage,
+        presigners: Vec<(Pubkey, Signature)>,
+        file: String,
+        force: bool,
+    },
+    Sign {
+        file: String,
+        output_file: Option<String>,
+    },
+    Inspect {
+        file: String,
+    },
+    Combine {
+        files: Vec<String>,
+        output_file: String,
+    },
+    Broadcast {
+        file: String,
+    },
+    Build {
+        instructions: Vec<Instruction>,
+        compute_unit_limit: Option<u32>,
+        compute_unit_price: Option<u64>,
+        address_lookup_tables: Vec<Pubkey>,
+        fee_payer: SignerIndex,
+        simulate: bool,
+        output_file: Option<String>,
+    },
+}
+
+pub trait TransactionSubCommands {
+    fn transaction_subcommands(self) -> Self;
+}
+
+impl TransactionSubCommands for App<'_, '_> {
+    fn transaction_subcommands(self) -> Self {
+        self.subcommand(
+            SubCommand::with_name("tx")
+                .about("Partially signed transaction files for offline multi-party signing")
+                .alias("transaction")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("create")
+                        .about("Create a partially signed transaction file from a transaction message")
+                        .arg(
+                            Arg::with_name("file")
+                                .index(1)
+                                .value_name("FILEPATH")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Partially signed transaction file to create"),
+                        )
+                        .arg(
+                            Arg::with_name("message")
+                                .long("message")
+                                .value_name("BASE64_MESSAGE")
+                                .takes_value(true)
+                                .required(true)
+                                .help(
+                                    "Base64 encoded transaction message, as displayed by \
+                                     `--sign-only --dump-transaction-message`",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name(SIGNER_ARG.name)
+                                .long(SIGNER_ARG.long)
+                                .takes_value(true)
+                                .value_name("PUBKEY=SIGNATURE")
+                                .validator(is_pubkey_sig)
+                                .multiple(true)
+                                .number_of_values(1)
+                                .help(SIGNER_ARG.help),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .takes_value(false)
+                                .help("Overwrite the file if it exists"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("sign")
+                        .about("Add signatures to a partially signed transaction file")
+                        .arg(
+                            Arg::with_name("file")
+                                .index(1)
+                                .value_name("FILEPATH")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Partially signed transaction file"),
+                        )
+                        .arg(
+                            Arg::with_name("signers")
+                                .long("signer-keypair")
+                                .value_name("SIGNER")
+                                .takes_value(true)
+                                .validator(is_valid_signer)
+                                .multiple(true)
+                                .number_of_values(1)
+                                .help(
+                                    "Signer to sign the transaction with, if it is a required \
+                                     signer [default: the default configured keypair]",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("output_file")
+                                .long("outfile")
+                                .value_name("FILEPATH")
+                                .takes_value(true)
+                                .help("Write the signed transaction to this file instead of updating FILEPATH"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("inspect")
+                        .about("Display a partially signed transaction and its missing signers")
+                        .arg(
+                            Arg::with_name("file")
+                                .index(1)
+                                .value_name("FILEPATH")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Partially signed transaction file"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("combine")
+                        .about("Merge the signatures of partially signed transaction files of the same transaction")
+                        .arg(
+                            Arg::with_name("files")
+                                .index(1)
+                                .value_name("FILEPATH")
+                                .takes_value(true)
+                                .required(true)
+                                .multiple(true)
+                                .min_values(2)
+                                .help("Partially signed transaction files"),
+                        )
+                        .arg(
+                            Arg::with_name("output_file")
+                                .long("outfile")
+                                .value_name("FILEPATH")
+                                .takes_value(true)
+                                .required(true)
+                                .help("File to write the combined transaction to"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("broadcast")
+                        .about("Send a fully signed transaction file to the cluster")
+                        .arg(
+                            Arg::with_name("file")
+                                .index(1)
+                                .value_name("FILEPATH")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Partially signed transaction file"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("build")
+                        .about("Build a transaction from a JSON or YAML description of its instructions")
+                        .arg(
+                            Arg::with_name("file")
+                                .index(1)
+                                .value_name("FILEPATH")
+                                .takes_value(true)
+                                .required(true)
+                                .help(
+                                    "JSON or YAML file listing the instructions by program id, \
+                                     accounts and data, and optionally a compute unit limit, \
+                                     compute unit price and address lookup tables",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("signers")
+                                .long("signer-keypair")
+                                .value_name("SIGNER")
+                                .takes_value(true)
+                                .validator(is_valid_signer)
+                                .multiple(true)
+                                .number_of_values(1)
+                                .help("Signer required by the instructions"),
+                        )
+                        .arg(fee_payer_arg())
+                        .arg(compute_unit_price_arg())
+                        .arg(
+                            Arg::with_name("simulate")
+                                .long("simulate")
+                                .takes_value(false)
+                                .conflicts_with("output_file")
+                                .help("Simulate the transaction instead of sending it"),
+                        )
+                        .arg(
+                            Arg::with_name("output_file")
+                                .long("outfile")
+                                .value_name("FILEPATH")
+                                .takes_value(true)
+                                .help(
+                                    "Write the transaction to this partially signed transaction \
+                                     file instead of sending it, signed by the signers that are \
+                                     available",
+                                ),
+                        ),
+                ),
+        )
+    }
+}
+
+pub fn parse_transaction_subcommand(
+    matches: &ArgMatches<'_>,
+    default_signer: &DefaultSigner,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> Result<CliCommandInfo, CliError> {
+    let (subcommand, sub_matches) = matches.subcommand();
+
+    let response = match (subcommand, sub_matches) {
+        ("create", Some(matches)) => {
+            let message = matches.value_of("message").unwrap();
+            let message = BASE64_STANDARD
+                .decode(message)
+                .ok()
+                .and_then(|message| bincode::deserialize(&message).ok())
+                .ok_or_else(|| {
+                    CliError::BadParameter("Unable to decode transaction message".to_string())
+                })?;
+            CliCommandInfo {
+                command: CliCommand::Transaction(TransactionCliCommand::Create {
+                    message,
+                    presigners: pubkeys_sigs_of(matches, SIGNER_ARG.name).unwrap_or_default(),
+                    file: matches.value_of("file").unwrap().to_string(),
+                    force: matches.is_present("force"),
+                }),
+                signers: vec![],
+            }
+        }
+        ("sign", Some(matches)) => {
+            let signers = if let Some(paths) = matches.values_of("signers") {
+                paths
+                    .map(|path| signer_from_path(matches, path, "signer", wallet_manager))
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                vec![default_signer.signer_from_path(matches, wallet_manager)?]
+            };
+            CliCommandInfo {
+                command: CliCommand::Transaction(TransactionCliCommand::Sign {
+                    file: matches.value_of("file").unwrap().to_string(),
+                    output_file: matches.value_of("output_file").map(str::to_string),
+                }),
+                signers,
+            }
+        }
+        ("inspect", Some(matches)) => CliCommandInfo {
+            command: CliCommand::Transaction(TransactionCliCommand::Inspect {
+                file: matches.value_of("file").unwrap().to_string(),
+            }),
+            signers: vec![],
+        },
+        ("combine", Some(matches)) => CliCommandInfo {
+            command: CliCommand::Transaction(TransactionCliCommand::Combine {
+                files: matches
+                    .values_of("files")
+                    .unwrap()
+                    .map(str::to_string)
+                    .collect(),
+                output_file: matches.value_of("output_file").unwrap().to_string(),
+            }),
+            signers: vec![],
+        },
+        ("broadcast", Some(matches)) => CliCommandInfo {
+            command: CliCommand::Transaction(TransactionCliCommand::Broadcast {
+                file: matches.value_of("file").unwrap().to_string(),
+            }),
+            signers: vec![],
+        },
+        ("build", Some(matches)) => {
+            parse_build_transaction(matches, default_signer, wallet_manager)?
+        }
+        _ => unreachable!(),
+    };
+    Ok(response)
+}
+
+fn parse_build_transaction(
+    matches: &ArgMatches<'_>,
+    default_signer: &DefaultSigner,
+    wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
+) -> Result<CliCommandInfo, CliError> {
+    let description = TransactionDescription::read_from_file(matches.value_of("file").unwrap())
+        .map_err(CliError::BadParameter)?;
+    let instructions = description
+        .to_instructions()
+        .map_err(CliError::BadParameter)?;
+    let address_lookup_tables = description
+        .address_lookup_table_addresses()
+        .map_err(CliError::BadParameter)?;
+    let simulate = matches.is_present("simulate");
+    let output_file = matches.value_of("output_file").map(str::to_string);
+
+    let (fee_payer, fee_payer_pubkey) = signer_of(matches, FEE_PAYER_ARG.name, wallet_manager)?;
+    let mut bulk_signers = vec![fee_payer];
+    for path in matches.values_of("signers").into_iter().flatten() {
+        bulk_signers.push(Some(signer_from_path(
+            matches,
+            path,
+            "signer",
+            wallet_manager,
+        )?));
+    }
+    let signer_info =
+        default_signer.generate_unique_signers(bulk_signers, matches, wallet_manager)?;
+
+    // Transactions that are sent need every signature up front
+    if !simulate && output_file.is_none() {
+        let mut missing_signers: Vec<String> = instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts)
+            .filter(|account| {
+                account.is_signer && signer_info.index_of(Some(account.pubkey)).is_none()
+            })
+            .map(|account| account.pubkey.to_string())
+            .collect();
+        missing_signers.sort_unstable();
+        missing_signers.dedup();
+        if !missing_signers.is_empty() {
+            return Err(CliError::BadParameter(format!(
+                "Missing signers {}, provide them with --signer-keypair or write the \
+                 transaction to a file with --outfile",
+                missing_signers.join(", ")
+            )));
+        }
+    }
+
+    Ok(CliCommandInfo {
+        command: CliCommand::Transaction(TransactionCliCommand::Build {
+            instructions,
+            compute_unit_limit: description.compute_unit_limit,
+            compute_unit_price: value_of(matches, COMPUTE_UNIT_PRICE_ARG.name)
+                .or(description.compute_unit_price),
+            address_lookup_tables,
+            fee_payer: signer_info.index_of(fee_payer_pubkey).unwrap(),
+            simulate,
+            output_file,
+        }),
+        signers: signer_info.signers,
+    })
+}
+
+pub fn process_transaction_subcommand(
+    rpc_client: Arc<RpcClient>,
+    config: &CliConfig,
+    subcommand: &TransactionCliCommand,
+) -> ProcessResult {
+    match subcommand {
+        TransactionCliCommand::Create {
+            message,
+            presigners,
+            file,
+            force,
+        } => process_create_transaction(config, message, presigners, file, *force),
+        TransactionCliCommand::Sign { file, output_file } => {
+            process_sign_transaction(config, file, output_file.as_deref())
+        }
+        TransactionCliCommand::Inspect { file } => process_inspect_transaction(config, file),
+        TransactionCliCommand::Combine { files, output_file } => {
+            process_combine_transactions(config, files, output_file)
+        }
+        TransactionCliCommand::Broadcast { file } => {
+            process_broadcast_transaction(&rpc_client, config, file)
+        }
+        TransactionCliCommand::Build {
+            instructions,
+            compute_unit_limit,
+            compute_unit_price,
+            address_lookup_tables,
+            fee_payer,
+            simulate,
+            output_file,
+        } => process_build_transaction(
+            &rpc_client,
+            config,
+            instructions,
+            *compute_unit_limit,
+            compute_unit_price.as_ref(),
+            address_lookup_tables,
+            *fee_payer,
+            *simulate,
+            output_file.as_deref(),
+        ),
+    }
+}
+
+fn process_create_transaction(
+    config: &CliConfig,
+    message: &VersionedMessage,
+    presigners: &[(Pubkey, Signature)],
+    file: &str,
+    force: bool,
+) -> ProcessResult {
+    if !force && Path::new(file).exists() {
+        return Err(format!("Refusing to overwrite {file} without --force flag").into());
+    }
+    let mut transaction = PartiallySignedTransaction::new(message.clone())?;
+    for (pubkey, signature) in presigners {
+        transaction.add_signature(pubkey, *signature)?;
+    }
+    transaction.write_to_file(file)?;
+    Ok(config
+        .output_format
+        .formatted_string(&cli_partially_signed_transaction(&transaction, file, false)))
+}
+
+fn process_sign_transaction(
+    config: &CliConfig,
+    file: &str,
+    output_file: Option<&str>,
+) -> ProcessResult {
+    let mut transaction = PartiallySignedTransaction::read_from_file(file)?;
+    transaction.sign(&config.signers)?;
+    let output_file = output_file.unwrap_or(file);
+    transaction.write_to_file(output_file)?;
+    Ok(config
+        .output_format
+        .formatted_string(&cli_partially_signed_transaction(
+            &transaction,
+            output_file,
+            false,
+        )))
+}
+
+fn process_inspect_transaction(config: &CliConfig, file: &str) -> ProcessResult {
+    let transaction = PartiallySignedTransaction::read_from_file(file)?;
+    Ok(config
+        .output_format
+        .formatted_string(&cli_partially_signed_transaction(&transaction, file, true)))
+}
+
+fn process_combine_transactions(
+    config: &CliConfig,
+    files: &[String],
+    output_file: &str,
+) -> ProcessResult {
+    let mut files = files.iter();
+    let mut transaction = PartiallySignedTransaction::read_from_file(files.next().unwrap())?;
+    for file in files {
+        let other = PartiallySignedTransaction::read_from_file(file)?;
+        transaction
+            .combine(&other)
+            .map_err(|err| format!("{file}: {err}"))?;
+    }
+    transaction.write_to_file(output_file)?;
+    Ok(config
+        .output_format
+        .formatted_string(&cli_partially_signed_transaction(
+            &transaction,
+            output_file,
+            false,
+        )))
+}
+
+fn process_broadcast_transaction(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    file: &str,
+) -> ProcessResult {
+    let transaction = PartiallySignedTransaction::read_from_file(file)?;
+    let missing_signers = transaction.missing_signers();
+    if !missing_signers.is_empty() {
+        let missing_signers: Vec<_> = missing_signers.iter().map(Pubkey::to_string).collect();
+        return Err(CliError::BadParameter(format!(
+            "Transaction is missing signatures from {}",
+            missing_signers.join(", ")
+        ))
+        .into());
+    }
+    let result = rpc_client.send_and_confirm_transaction_with_spinner_and_config(
+        transaction.transaction(),
+        config.commitment,
+        config.send_transaction_config,
+    );
+    log_transaction_result(result, transaction.transaction(), config)
+}
+
+fn process_build_transaction(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    instructions: &[Instruction],
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: Option<&u64>,
+    address_lookup_tables: &[Pubkey],
+    fee_payer: SignerIndex,
+    simulate: bool,
+    output_file: Option<&str>,
+) -> ProcessResult {
+    let fee_payer = config.signers[fee_payer].pubkey();
+    let mut transaction_instructions: Vec<Instruction> = compute_unit_limit
+        .map(ComputeBudgetInstruction::set_compute_unit_limit)
+        .into_iter()
+        .collect::<Vec<_>>()
+        .with_compute_unit_price(compute_unit_price);
+    transaction_instructions.extend_from_slice(instructions);
+
+    let blockhash = rpc_client.get_latest_blockhash()?;
+    let message = if address_lookup_tables.is_empty() {
+        let (message, _) = resolve_spend_tx_and_check_account_balance(
+            rpc_client,
+            false,
+            SpendAmount::Some(0),
+            &blockhash,
+            &fee_payer,
+            |_| {
+                Message::new_with_blockhash(&transaction_instructions, Some(&fee_payer), &blockhash)
+            },
+            config.commitment,
+        )?;
+        VersionedMessage::Legacy(message)
+    } else {
+        let mut address_lookup_table_accounts = vec![];
+        for address in address_lookup_tables {
+            let account = rpc_client.get_account(address)?;
+            let table = AddressLookupTable::deserialize(&account.data).map_err(|err| {
+                CliError::BadParameter(format!("{address} is not an address lookup table: {err}"))
+            })?;
+            address_lookup_table_accounts.push(AddressLookupTableAccount {
+                key: *address,
+                addresses: table.addresses.to_vec(),
+            });
+        }
+        let message = v0::Message::try_compile(
+            &fee_payer,
+            &transaction_instructions,
+            &address_lookup_table_accounts,
+            blockhash,
+        )?;
+        let fee = rpc_client.get_fee_for_message(&message)?;
+        if !check_account_for_balance_with_commitment(
+            rpc_client,
+            &fee_payer,
+            fee,
+            config.commitment,
+        )? {
+            return Err(CliError::InsufficientFundsForFee(lamports_to_trz(fee), fee_payer).into());
+        }
+        VersionedMessage::V0(message)
+    };
+
+    let mut transaction = PartiallySignedTransaction::new(message)?;
+    transaction.sign(&config.signers)?;
+    if let Some(output_file) = output_file {
+        transaction.write_to_file(output_file)?;
+        return Ok(config
+            .output_format
+            .formatted_string(&cli_partially_signed_transaction(
+                &transaction,
+                output_file,
+                false,
+            )));
+    }
+
+    let transaction = transaction.into_transaction();
+    if simulate {
+        let result = rpc_client
+            .simulate_transaction_with_config(
+                &transaction,
+                RpcSimulateTransactionConfig {
+                    commitment: Some(config.commitment),
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )?
+            .value;
+        return Ok(config
+            .output_format
+            .formatted_string(&CliTransactionSimulation {
+                err: result.err.map(|err| err.to_string()),
+                logs: result.logs.unwrap_or_default(),
+                units_consumed: result.units_consumed,
+            }));
+    }
+    let result = rpc_client.send_and_confirm_transaction_with_spinner_and_config(
+        &transaction,
+        config.commitment,
+        config.send_transaction_config,
+    );
+    log_transaction_result(result, &transaction, config)
+}
+
+/// The transactions sent by `tx` commands may invoke any program, so a custom
+/// instruction error is reported with its raw code and the program that
+/// returned it instead of being decoded as the error of a known program
+fn log_transaction_result(
+    result: ClientResult<Signature>,
+    transaction: &VersionedTransaction,
+    config: &CliConfig,
+) -> ProcessResult {
+    match result {
+        Err(err) => {
+            if let Some(TransactionError::InstructionError(index, InstructionError::Custom(code))) =
+                err.get_transaction_error()
+            {
+                let message = &transaction.message;
+                if let Some(program_id) =
+                    message
+                        .instructions()
+                        .get(usize::from(index))
+                        .and_then(|instruction| {
+                            message
+                                .static_account_keys()
+                                .get(usize::from(instruction.program_id_index))
+                        })
+                {
+                    return Err(format!(
+                        "Instruction {index} failed: program {program_id} returned custom error \
+                         {code} ({code:#x})"
+                    )
+                    .into());
+                }
+            }
+            Err(err.into())
+        }
+        Ok(signature) => Ok(config.output_format.formatted_string(&CliSignature {
+            signature: signature.to_string(),
+        })),
+    }
+}
+
+fn cli_partially_signed_transaction(
+    transaction: &PartiallySignedTransaction,
+    file: &str,
+    decoded: bool,
+) -> CliPartiallySignedTransaction {
+    let mut signers = vec![];
+    let mut missing_signers = vec![];
+    let mut invalid_signers = vec![];
+    for ((pubkey, signature), status) in transaction
+        .required_signers()
+        .iter()
+        .zip(transaction.transaction().signatures.iter())
+        .zip(transaction.signature_statuses())
+    {
+        match status {
+            CliSignatureVerificationStatus::Pass => signers.push(format!("{pubkey}={signature}")),
+            CliSignatureVerificationStatus::None => missing_signers.push(pubkey.to_string()),
+            CliSignatureVerificationStatus::Fail => invalid_signers.push(pubkey.to_string()),
+        }
+    }
+    let transaction = decoded.then(|| {
+        let transaction = transaction.transaction();
+        CliTransaction {
+            transaction: transaction.json_encode(),
+            meta: None,
+            block_time: None,
+            slot: None,
+            decoded_transaction: transaction.clone(),
+            prefix: "".to_string(),
+            sigverify_status: CliSignatureVerificationStatus::verify_transaction(transaction),
+        }
+    });
+    CliPartiallySignedTransaction {
+        file: file.to_string(),
+        transaction,
+        signers,
+        missing_signers,
+        invalid_signers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{clap_app::get_clap_app, cli::parse_command},
+        trezoa_rpc_client_api::client_error::Error as ClientError,
+        trezoa_sdk::{
+            hash::Hash,
+            message::Message,
+            signature::{write_keypair, Keypair},
+            system_instruction, system_program,
+        },
+        tempfile::{NamedTempFile, TempDir},
+    };
+
+    fn make_tmp_file() -> (String, NamedTempFile) {
+        let tmp_file = NamedTempFile::new().unwrap();
+        (String::from(tmp_file.path().to_str().unwrap()), tmp_file)
+    }
+
+    fn two_signer_message(payer: &Keypair, from: &Keypair) -> VersionedMessage {
+        VersionedMessage::Legacy(Message::new_with_blockhash(
+            &[system_instruction::transfer(
+                &from.pubkey(),
+                &Pubkey::new_unique(),
+                42,
+            )],
+            Some(&payer.pubkey()),
+            &Hash::new_unique(),
+        ))
+    }
+
+    #[test]
+    fn test_partially_signed_transaction() {
+        let payer = Keypair::new();
+        let from = Keypair::new();
+        let message = two_signer_message(&payer, &from);
+        let mut transaction = PartiallySignedTransaction::new(message.clone()).unwrap();
+        assert_eq!(
+            transaction.required_signers(),
+            &[payer.pubkey(), from.pubkey()]
+        );
+        assert_eq!(
+            transaction.missing_signers(),
+            vec![payer.pubkey(), from.pubkey()]
+        );
+
+        // Only required signers sign
+        assert!(transaction.sign(&[&Keypair::new()]).is_err());
+        assert_eq!(
+            transaction.sign(&[&from, &Keypair::new()]).unwrap(),
+            vec![from.pubkey()]
+        );
+        assert_eq!(transaction.missing_signers(), vec![payer.pubkey()]);
+
+        // The file format round-trips, including missing signatures
+        let json = transaction.to_json();
+        assert_eq!(
+            PartiallySignedTransaction::from_json(&json).unwrap(),
+            transaction
+        );
+
+        // Presigned signatures are verified
+        let payer_signature = payer.sign_message(&message.serialize());
+        let mut other = PartiallySignedTransaction::new(message).unwrap();
+        assert!(other
+            .add_signature(&payer.pubkey(), Signature::new_unique())
+            .is_err());
+        assert!(other
+            .add_signature(&Pubkey::new_unique(), payer_signature)
+            .is_err());
+        other
+            .add_signature(&payer.pubkey(), payer_signature)
+            .unwrap();
+
+        transaction.combine(&other).unwrap();
+        assert!(transaction.is_fully_signed());
+        assert!(transaction
+            .transaction()
+            .verify_with_results()
+            .iter()
+            .all(|ok| *ok));
+
+        let unrelated = PartiallySignedTransaction::new(two_signer_message(&payer, &from)).unwrap();
+        assert!(transaction.combine(&unrelated).is_err());
+    }
+
+    #[test]
+    fn test_from_json_rejects_mismatched_signers() {
+        let payer = Keypair::new();
+        let from = Keypair::new();
+        let transaction =
+            PartiallySignedTransaction::new(two_signer_message(&payer, &from)).unwrap();
+        let json = transaction.to_json().replace(
+            &from.pubkey().to_string(),
+            &Pubkey::new_unique().to_string(),
+        );
+        assert!(PartiallySignedTransaction::from_json(&json)
+            .unwrap_err()
+            .starts_with("signature 1 is for"));
+        let json = transaction
+            .to_json()
+            .replace("\"version\": 1", "\"version\": 2");
+        assert_eq!(
+            PartiallySignedTransaction::from_json(&json).unwrap_err(),
+            "unsupported partially signed transaction version 2"
+        );
+    }
+
+    #[test]
+    fn test_transaction_commands() {
+        let test_commands = get_clap_app("test", "desc", "version");
+        let payer = Keypair::new();
+        let from = Keypair::new();
+        let (payer_file, mut tmp_file) = make_tmp_file();
+        write_keypair(&payer, tmp_file.as_file_mut()).unwrap();
+        let (from_file, mut tmp_file2) = make_tmp_file();
+        write_keypair(&from, tmp_file2.as_file_mut()).unwrap();
+        let default_signer = DefaultSigner::new("", &payer_file);
+
+        let message = two_signer_message(&payer, &from);
+        let encoded_message = BASE64_STANDARD.encode(message.serialize());
+        let from_signature = from.sign_message(&message.serialize());
+        let dir = TempDir::new().unwrap();
+        let tx_file = dir.path().join("tx.json").to_str().unwrap().to_string();
+
+        let matches = test_commands.clone().get_matches_from(vec![
+            "test",
+            "tx",
+            "create",
+            &tx_file,
+            "--message",
+            &encoded_message,
+            "--signer",
+            &format!("{}={from_signature}", from.pubkey()),
+        ]);
+        let command_info = parse_command(&matches, &default_signer, &mut None).unwrap();
+        assert_eq!(
+            command_info,
+            CliCommandInfo {
+                command: CliCommand::Transaction(TransactionCliCommand::Create {
+                    message: message.clone(),
+                    presigners: vec![(from.pubkey(), from_signature)],
+                    file: tx_file.clone(),
+                    force: false,
+                }),
+                signers: vec![],
+            }
+        );
+        let mut config = CliConfig::default();
+        process_create_transaction(
+            &config,
+            &message,
+            &[(from.pubkey(), from_signature)],
+            &tx_file,
+            false,
+        )
+        .unwrap();
+        // Refuses to overwrite without --force
+        assert!(process_create_transaction(&config, &message, &[], &tx_file, false).is_err());
+        let transaction = PartiallySignedTransaction::read_from_file(&tx_file).unwrap();
+        assert_eq!(transaction.missing_signers(), vec![payer.pubkey()]);
+
+        let matches = test_commands
+            .clone()
+            .get_matches_from(vec!["test", "tx", "sign", &tx_file]);
+        let command_info = parse_command(&matches, &default_signer, &mut None).unwrap();
+        assert_eq!(
+            command_info.command,
+            CliCommand::Transaction(TransactionCliCommand::Sign {
+                file: tx_file.clone(),
+                output_file: None,
+            })
+        );
+        assert_eq!(command_info.signers.len(), 1);
+        assert_eq!(command_info.signers[0].pubkey(), payer.pubkey());
+        config.signers = vec![&payer];
+        process_sign_transaction(&config, &tx_file, None).unwrap();
+        let transaction = PartiallySignedTransaction::read_from_file(&tx_file).unwrap();
+        assert!(transaction.is_fully_signed());
+
+        let matches = test_commands.clone().get_matches_from(vec![
+            "test",
+            "tx",
+            "sign",
+            &tx_file,
+            "--signer-keypair",
+            &from_file,
+            "--signer-keypair",
+            &payer_file,
+        ]);
+        let command_info = parse_command(&matches, &default_signer, &mut None).unwrap();
+        assert_eq!(command_info.signers.len(), 2);
+        assert_eq!(command_info.signers[0].pubkey(), from.pubkey());
+
+        let combined_file = dir
+            .path()
+            .join("combined.json")
+            .to_str()
+            .unwrap()
+            .to_string();
+        let matches = test_commands.clone().get_matches_from(vec![
+            "test",
+            "tx",
+            "combine",
+            &tx_file,
+            &tx_file,
+            "--outfile",
+            &combined_file,
+        ]);
+        assert_eq!(
+            parse_command(&matches, &default_signer, &mut None)
+                .unwrap()
+                .command,
+            CliCommand::Transaction(TransactionCliCommand::Combine {
+                files: vec![tx_file.clone(), tx_file.clone()],
+                output_file: combined_file,
+            })
+        );
+
+        let matches = test_commands
+            .clone()
+            .get_matches_from(vec!["test", "tx", "inspect", &tx_file]);
+        assert_eq!(
+            parse_command(&matches, &default_signer, &mut None)
+                .unwrap()
+                .command,
+            CliCommand::Transaction(TransactionCliCommand::Inspect { file: tx_file }),
+        );
+    }
+
+    #[test]
+    fn test_build_transaction_command() {
+        let test_commands = get_clap_app("test", "desc", "version");
+        let payer = Keypair::new();
+        let from = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let (payer_file, mut tmp_file) = make_tmp_file();
+        write_keypair(&payer, tmp_file.as_file_mut()).unwrap();
+        let (from_file, mut tmp_file2) = make_tmp_file();
+        write_keypair(&from, tmp_file2.as_file_mut()).unwrap();
+        let default_signer = DefaultSigner::new("", &payer_file);
+
+        let dir = TempDir::new().unwrap();
+        let description_file = dir.path().join("tx.yaml").to_str().unwrap().to_string();
+        fs::write(
+            &description_file,
+            format!(
+                r#"
+compute_unit_limit: 1000
+instructions:
+  - program_id: 11111111111111111111111111111111
+    accounts:
+      - {{ pubkey: {}, is_signer: true, is_writable: true }}
+      - {{ pubkey: {recipient}, is_writable: true }}
+    data:
+      borsh_values:
+        - u32: 2
+        - u64: 42
+"#,
+                from.pubkey()
+            ),
+        )
+        .unwrap();
+        let transfer = system_instruction::transfer(&from.pubkey(), &recipient, 42);
+
+        // Sending requires every signer
+        let matches =
+            test_commands
+                .clone()
+                .get_matches_from(vec!["test", "tx", "build", &description_file]);
+        assert!(parse_command(&matches, &default_signer, &mut None).is_err());
+
+        let matches = test_commands.clone().get_matches_from(vec![
+            "test",
+            "tx",
+            "build",
+            &description_file,
+            "--signer-keypair",
+            &from_file,
+            "--with-compute-unit-price",
+            "5",
+        ]);
+        let command_info = parse_command(&matches, &default_signer, &mut None).unwrap();
+        assert_eq!(
+            command_info.command,
+            CliCommand::Transaction(TransactionCliCommand::Build {
+                instructions: vec![transfer.clone()],
+                compute_unit_limit: Some(1000),
+                compute_unit_price: Some(5),
+                address_lookup_tables: vec![],
+                fee_payer: 0,
+                simulate: false,
+                output_file: None,
+            })
+        );
+        assert_eq!(command_info.signers.len(), 2);
+        assert_eq!(command_info.signers[0].pubkey(), payer.pubkey());
+        assert_eq!(command_info.signers[1].pubkey(), from.pubkey());
+
+        // Missing signers can sign the transaction file later
+        let tx_file = dir.path().join("tx.json").to_str().unwrap().to_string();
+        let matches = test_commands.clone().get_matches_from(vec![
+            "test",
+            "tx",
+            "build",
+            &description_file,
+            "--outfile",
+            &tx_file,
+        ]);
+        assert!(parse_command(&matches, &default_signer, &mut None).is_ok());
+        let config = CliConfig {
+            signers: vec![&payer],
+            ..CliConfig::default()
+        };
+        let rpc_client = RpcClient::new_mock("succeeds".to_string());
+        process_build_transaction(
+            &rpc_client,
+            &config,
+            &[transfer],
+            Some(1000),
+            None,
+            &[],
+            0,
+            false,
+            Some(&tx_file),
+        )
+        .unwrap();
+        let transaction = PartiallySignedTransaction::read_from_file(&tx_file).unwrap();
+        assert_eq!(transaction.missing_signers(), vec![from.pubkey()]);
+        let VersionedMessage::Legacy(message) = &transaction.transaction().message else {
+            panic!("expected a legacy message");
+        };
+        assert_eq!(message.instructions.len(), 2);
+        assert_eq!(
+            message.account_keys[message.instructions[0].program_id_index as usize],
+            trezoa_sdk::compute_budget::id()
+        );
+    }
+
+    #[test]
+    fn test_log_transaction_result() {
+        let payer = Keypair::new();
+        let from = Keypair::new();
+        let transaction = PartiallySignedTransaction::new(two_signer_message(&payer, &from))
+            .unwrap()
+            .into_transaction();
+        let config = CliConfig::default();
+
+        // A custom error names the program that returned it, without decoding it
+        let err = ClientError::from(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(1),
+        ));
+        assert_eq!(
+            log_transaction_result(Err(err), &transaction, &config)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "Instruction 0 failed: program {} returned custom error 1 (0x1)",
+                system_program::id()
+            )
+        );
+
+        let err = ClientError::from(TransactionError::InstructionError(
+            0,
+            InstructionError::InvalidArgument,
+        ));
+        assert!(log_transaction_result(Err(err), &transaction, &config)
+            .unwrap_err()
+            .to_string()
+            .contains("invalid program argument"));
+    }
+}
@@ -0,0 +1,318 @@
+//! Transaction descriptions for `tx build`
+//!
+//! A description lists instructions by program id, account metas and data,
+//! along with optional compute budget settings and address lookup tables to
+//! compress the accounts with. It can be written in JSON or YAML:
+//!
+//! ```yaml
+//! compute_unit_limit: 50000
+//! instructions:
+//!   - program_id: 11111111111111111111111111111111
+//!     accounts:
+//!       - { pubkey: 4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T, is_signer: true, is_writable: true }
+//!       - { pubkey: 8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR, is_writable: true }
+//!     data:
+//!       borsh_values:
+//!         - u32: 2
+//!         - u64: 1000000
+//! ```
+//!
+//! Instruction data is given as `hex`, `base58`, or as `borsh_values`, a list
+//! of typed values that are encoded in order with the borsh layout. There is
+//! no schema: each value names its own type.
+
+use {
+    serde_derive::Deserialize,
+    trezoa_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+    },
+    std::{path::Path, str::FromStr},
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransactionDescription {
+    #[serde(default)]
+    pub compute_unit_limit: Option<u32>,
+    #[serde(default)]
+    pub compute_unit_price: Option<u64>,
+    #[serde(default)]
+    pub address_lookup_tables: Vec<String>,
+    pub instructions: Vec<InstructionDescription>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstructionDescription {
+    pub program_id: String,
+    #[serde(default)]
+    pub accounts: Vec<AccountMetaDescription>,
+    #[serde(default)]
+    pub data: InstructionData,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountMetaDescription {
+    pub pubkey: String,
+    #[serde(default)]
+    pub is_signer: bool,
+    #[serde(default)]
+    pub is_writable: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionData {
+    Hex(String),
+    Base58(String),
+    BorshValues(Vec<BorshValue>),
+}
+
+impl Default for InstructionData {
+    fn default() -> Self {
+        Self::Hex(String::default())
+    }
+}
+
+/// A value encoded with the borsh layout: integers and booleans are little
+/// endian, pubkeys are 32 raw bytes, and strings and byte vectors are prefixed
+/// with their u32 length
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BorshValue {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Pubkey(String),
+    String(String),
+    /// Hex encoded bytes, length prefixed
+    Bytes(String),
+    /// Hex encoded bytes, without a length prefix
+    FixedBytes(String),
+}
+
+fn parse_pubkey(pubkey: &str, field: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(pubkey).map_err(|err| format!("Invalid {field} {pubkey}: {err}"))
+}
+
+fn decode_hex(data: &str) -> Result<Vec<u8>, String> {
+    let data = data.strip_prefix("0x").unwrap_or(data);
+    hex::decode(data).map_err(|err| format!("Invalid hex data {data}: {err}"))
+}
+
+impl BorshValue {
+    fn encode(&self, data: &mut Vec<u8>) -> Result<(), String> {
+        match self {
+            Self::Bool(value) => data.push(u8::from(*value)),
+            Self::U8(value) => data.push(*value),
+            Self::U16(value) => data.extend_from_slice(&value.to_le_bytes()),
+            Self::U32(value) => data.extend_from_slice(&value.to_le_bytes()),
+            Self::U64(value) => data.extend_from_slice(&value.to_le_bytes()),
+            Self::I8(value) => data.extend_from_slice(&value.to_le_bytes()),
+            Self::I16(value) => data.extend_from_slice(&value.to_le_bytes()),
+            Self::I32(value) => data.extend_from_slice(&value.to_le_bytes()),
+            Self::I64(value) => data.extend_from_slice(&value.to_le_bytes()),
+            Self::Pubkey(pubkey) => {
+                data.extend_from_slice(parse_pubkey(pubkey, "pubkey value")?.as_ref())
+            }
+            Self::String(value) => {
+                encode_length(value.len(), data)?;
+                data.extend_from_slice(value.as_bytes());
+            }
+            Self::Bytes(value) => {
+                let bytes = decode_hex(value)?;
+                encode_length(bytes.len(), data)?;
+                data.extend_from_slice(&bytes);
+            }
+            Self::FixedBytes(value) => data.extend_from_slice(&decode_hex(value)?),
+        }
+        Ok(())
+    }
+}
+
+fn encode_length(len: usize, data: &mut Vec<u8>) -> Result<(), String> {
+    let len = u32::try_from(len).map_err(|_| format!("Value of {len} bytes is too long"))?;
+    data.extend_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+impl InstructionData {
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Hex(data) => decode_hex(data),
+            Self::Base58(data) => bs58::decode(data)
+                .into_vec()
+                .map_err(|err| format!("Invalid base58 data {data}: {err}")),
+            Self::BorshValues(values) => {
+                let mut data = vec![];
+                for value in values {
+                    value.encode(&mut data)?;
+                }
+                Ok(data)
+            }
+        }
+    }
+}
+
+impl InstructionDescription {
+    pub fn to_instruction(&self) -> Result<Instruction, String> {
+        let program_id = parse_pubkey(&self.program_id, "program id")?;
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|account| {
+                Ok(AccountMeta {
+                    pubkey: parse_pubkey(&account.pubkey, "account")?,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Instruction {
+            program_id,
+            accounts,
+            data: self.data.encode()?,
+        })
+    }
+}
+
+impl TransactionDescription {
+    /// Parse a description in JSON, or in YAML if it isn't valid JSON
+    pub fn parse(description: &str) -> Result<Self, String> {
+        serde_json::from_str(description).or_else(|json_err| {
+            serde_yaml::from_str(description)
+                .map_err(|yaml_err| format!("Invalid description: {json_err}; {yaml_err}"))
+        })
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let description = std::fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read {}: {err}", path.display()))?;
+        Self::parse(&description).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn to_instructions(&self) -> Result<Vec<Instruction>, String> {
+        if self.instructions.is_empty() {
+            return Err("Description has no instructions".to_string());
+        }
+        self.instructions
+            .iter()
+            .enumerate()
+            .map(|(i, instruction)| {
+                instruction
+                    .to_instruction()
+                    .map_err(|err| format!("Instruction {i}: {err}"))
+            })
+            .collect()
+    }
+
+    pub fn address_lookup_table_addresses(&self) -> Result<Vec<Pubkey>, String> {
+        self.address_lookup_tables
+            .iter()
+            .map(|address| parse_pubkey(address, "address lookup table"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transaction_description() {
+        let payer = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let yaml = format!(
+            r#"
+compute_unit_limit: 50000
+address_lookup_tables: [{recipient}]
+instructions:
+  - program_id: 11111111111111111111111111111111
+    accounts:
+      - {{ pubkey: {payer}, is_signer: true, is_writable: true }}
+      - {{ pubkey: {recipient}, is_writable: true }}
+    data:
+      borsh_values:
+        - u32: 2
+        - u64: 1000000
+  - program_id: {recipient}
+    data:
+      hex: "0x68656c6c6f"
+"#
+        );
+        let description = TransactionDescription::parse(&yaml).unwrap();
+        assert_eq!(description.compute_unit_limit, Some(50_000));
+        assert_eq!(description.compute_unit_price, None);
+        assert_eq!(
+            description.address_lookup_table_addresses().unwrap(),
+            vec![recipient]
+        );
+        assert_eq!(
+            description.to_instructions().unwrap(),
+            vec![
+                trezoa_sdk::system_instruction::transfer(&payer, &recipient, 1_000_000),
+                Instruction::new_with_bytes(recipient, b"hello", vec![]),
+            ]
+        );
+
+        let json = format!(
+            r#"{{
+                "instructions": [{{
+                    "program_id": "{recipient}",
+                    "accounts": [{{ "pubkey": "{payer}", "is_signer": true }}],
+                    "data": {{ "base58": "{}" }}
+                }}]
+            }}"#,
+            bs58::encode(b"hello").into_string()
+        );
+        let description = TransactionDescription::parse(&json).unwrap();
+        assert_eq!(
+            description.to_instructions().unwrap(),
+            vec![Instruction::new_with_bytes(
+                recipient,
+                b"hello",
+                vec![AccountMeta::new_readonly(payer, true)]
+            )]
+        );
+
+        assert!(TransactionDescription::parse("instructions: []")
+            .unwrap()
+            .to_instructions()
+            .is_err());
+        assert!(TransactionDescription::parse("{ instructions: [], fee: 5 }").is_err());
+        let description = TransactionDescription::parse(
+            "instructions: [{ program_id: 11111111111111111111111111111111, data: { hex: zz } }]",
+        )
+        .unwrap();
+        assert!(description.to_instructions().is_err());
+    }
+
+    #[test]
+    fn test_borsh_values() {
+        let pubkey = Pubkey::new_unique();
+        let data = InstructionData::BorshValues(vec![
+            BorshValue::Bool(true),
+            BorshValue::U16(0x0102),
+            BorshValue::I32(-1),
+            BorshValue::Pubkey(pubkey.to_string()),
+            BorshValue::String("hi".to_string()),
+            BorshValue::Bytes("0a0b".to_string()),
+            BorshValue::FixedBytes("0c".to_string()),
+        ])
+        .encode()
+        .unwrap();
+        let mut expected = vec![1, 2, 1, 255, 255, 255, 255];
+        expected.extend_from_slice(pubkey.as_ref());
+        expected.extend_from_slice(&[2, 0, 0, 0, b'h', b'i', 2, 0, 0, 0, 10, 11, 12]);
+        assert_eq!(data, expected);
+    }
+}
@@ -34,8 +34,9 @@ use {
     trezoa_rpc_client_nonce_utils::blockhash_query::BlockhashQuery,
     trezoa_sdk::{
         commitment_config::CommitmentConfig,
+        hash::Hash,
         message::Message,
-        offchain_message::OffchainMessage,
+        offchain_message::{v1, OffchainMessage},
         pubkey::Pubkey,
         signature::Signature,
         stake,
@@ -343,7 +344,8 @@ impl WalletSubCommands for App<'_, '_> {
                             Ok(_) => Ok(()),
                         })
                         .help("The off-chain message version"),
-                ),
+                )
+                .args(&offchain_message_v1_args()),
         )
         .subcommand(
             SubCommand::with_name("verify-offchain-signature")
@@ -377,6 +379,7 @@ impl WalletSubCommands for App<'_, '_> {
                         })
                         .help("The off-chain message version"),
                 )
+                .args(&offchain_message_v1_args())
                 .arg(pubkey!(
                     Arg::with_name("signer")
                         .long("signer")
@@ -388,6 +391,79 @@ impl WalletSubCommands for App<'_, '_> {
     }
 }
 
+fn offchain_message_v1_args<'a, 'b>() -> [Arg<'a, 'b>; 3] {
+    [
+        Arg::with_name("application_domain")
+            .long("application-domain")
+            .takes_value(true)
+            .value_name("DOMAIN")
+            .validator(is_hash)
+            .help(
+                "Base58-encoded 32 byte application domain of a version 1 message \
+                 [default: all zeroes]",
+            ),
+        Arg::with_name("message_signers")
+            .long("message-signer")
+            .takes_value(true)
+            .value_name("PUBKEY")
+            .multiple(true)
+            .validator(is_valid_pubkey)
+            .help(
+                "Signer named by a version 1 message, in order. Repeat for each signer \
+                 [default: the signing key]",
+            ),
+        Arg::with_name("document")
+            .long("document")
+            .takes_value(false)
+            .help(
+                "Treat the message as the path to a document, and sign the SHA256 digest of \
+                 its contents in a version 1 message",
+            ),
+    ]
+}
+
+fn parse_offchain_message(
+    matches: &ArgMatches<'_>,
+    signer_pubkey: Pubkey,
+) -> Result<OffchainMessage, CliError> {
+    let version: u8 = value_of(matches, "version").unwrap();
+    let message_text: String = value_of(matches, "message")
+        .ok_or_else(|| CliError::BadParameter("MESSAGE".to_string()))?;
+    if version != 1 {
+        if ["application_domain", "message_signers", "document"]
+            .iter()
+            .any(|arg| matches.is_present(arg))
+        {
+            return Err(CliError::BadParameter(
+                "--application-domain, --message-signer and --document require --version 1"
+                    .to_string(),
+            ));
+        }
+        return OffchainMessage::new(version, message_text.as_bytes())
+            .map_err(|_| CliError::BadParameter("VERSION or MESSAGE".to_string()));
+    }
+
+    let application_domain = value_of::<Hash>(matches, "application_domain")
+        .map(Hash::to_bytes)
+        .unwrap_or_default();
+    let signers = pubkeys_of(matches, "message_signers").unwrap_or_else(|| vec![signer_pubkey]);
+    if !signers.contains(&signer_pubkey) {
+        return Err(CliError::BadParameter(format!(
+            "{signer_pubkey} is not one of the message signers"
+        )));
+    }
+    let message = if matches.is_present("document") {
+        let document = std::fs::read(&message_text).map_err(|err| {
+            CliError::BadParameter(format!("Unable to read document {message_text}: {err}"))
+        })?;
+        v1::OffchainMessage::new_hashed(application_domain, &signers, &document)
+    } else {
+        v1::OffchainMessage::new(application_domain, &signers, message_text.as_bytes())
+    }
+    .map_err(|_| CliError::BadParameter("MESSAGE or MESSAGE SIGNERS".to_string()))?;
+    Ok(OffchainMessage::V1(message))
+}
+
 fn resolve_derived_address_program_id(matches: &ArgMatches<'_>, arg_name: &str) -> Option<Pubkey> {
     matches.value_of(arg_name).and_then(|v| {
         let upper = v.to_ascii_uppercase();
@@ -609,15 +685,12 @@ pub fn parse_sign_offchain_message(
     default_signer: &DefaultSigner,
     wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
 ) -> Result<CliCommandInfo, CliError> {
-    let version: u8 = value_of(matches, "version").unwrap();
-    let message_text: String = value_of(matches, "message")
-        .ok_or_else(|| CliError::BadParameter("MESSAGE".to_string()))?;
-    let message = OffchainMessage::new(version, message_text.as_bytes())
-        .map_err(|_| CliError::BadParameter("VERSION or MESSAGE".to_string()))?;
+    let signer = default_signer.signer_from_path(matches, wallet_manager)?;
+    let message = parse_offchain_message(matches, signer.pubkey())?;
 
     Ok(CliCommandInfo {
         command: CliCommand::SignOffchainMessage { message },
-        signers: vec![default_signer.signer_from_path(matches, wallet_manager)?],
+        signers: vec![signer],
     })
 }
 
@@ -626,18 +699,16 @@ pub fn parse_verify_offchain_signature(
     default_signer: &DefaultSigner,
     wallet_manager: &mut Option<Rc<RemoteWalletManager>>,
 ) -> Result<CliCommandInfo, CliError> {
-    let version: u8 = value_of(matches, "version").unwrap();
-    let message_text: String = value_of(matches, "message")
-        .ok_or_else(|| CliError::BadParameter("MESSAGE".to_string()))?;
-    let message = OffchainMessage::new(version, message_text.as_bytes())
-        .map_err(|_| CliError::BadParameter("VERSION or MESSAGE".to_string()))?;
-
     let signer_pubkey = pubkey_of_signer(matches, "signer", wallet_manager)?;
     let signers = if signer_pubkey.is_some() {
         vec![]
     } else {
         vec![default_signer.signer_from_path(matches, wallet_manager)?]
     };
+    let message = parse_offchain_message(
+        matches,
+        signer_pubkey.unwrap_or_else(|| signers[0].pubkey()),
+    )?;
 
     let signature = value_of(matches, "signature")
         .ok_or_else(|| CliError::BadParameter("SIGNATURE".to_string()))?;
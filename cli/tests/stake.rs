@@ -139,6 +139,7 @@ fn test_stake_redelegation() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -158,6 +159,7 @@ fn test_stake_redelegation() {
         fee_payer: 0,
         redelegation_stake_account: None,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -223,6 +225,7 @@ fn test_stake_redelegation() {
         fee_payer: 0,
         redelegation_stake_account: Some(1),
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -374,6 +377,7 @@ fn test_stake_delegation_force() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -393,6 +397,7 @@ fn test_stake_delegation_force() {
         fee_payer: 0,
         redelegation_stake_account: None,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -416,6 +421,7 @@ fn test_stake_delegation_force() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -437,6 +443,7 @@ fn test_stake_delegation_force() {
         fee_payer: 0,
         redelegation_stake_account: None,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap_err();
 
@@ -455,6 +462,7 @@ fn test_stake_delegation_force() {
         fee_payer: 0,
         redelegation_stake_account: None,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 }
@@ -516,6 +524,7 @@ fn test_seed_stake_delegation_and_deactivation() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config_validator).unwrap();
 
@@ -534,6 +543,7 @@ fn test_seed_stake_delegation_and_deactivation() {
         fee_payer: 0,
         redelegation_stake_account: None,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config_validator).unwrap();
 
@@ -551,6 +561,7 @@ fn test_seed_stake_delegation_and_deactivation() {
         seed: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config_validator).unwrap();
 }
@@ -607,6 +618,7 @@ fn test_stake_delegation_and_deactivation() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config_validator).unwrap();
 
@@ -626,6 +638,7 @@ fn test_stake_delegation_and_deactivation() {
         fee_payer: 0,
         redelegation_stake_account: None,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config_validator).unwrap();
 
@@ -643,6 +656,7 @@ fn test_stake_delegation_and_deactivation() {
         seed: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config_validator).unwrap();
 }
@@ -723,6 +737,7 @@ fn test_offline_stake_delegation_and_deactivation() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config_validator).unwrap();
 
@@ -742,6 +757,7 @@ fn test_offline_stake_delegation_and_deactivation() {
         fee_payer: 0,
         redelegation_stake_account: None,
         compute_unit_price: None,
+        priority_fee: None,
     };
     config_offline.output_format = OutputFormat::JsonCompact;
     let sig_response = process_command(&config_offline).unwrap();
@@ -765,6 +781,7 @@ fn test_offline_stake_delegation_and_deactivation() {
         fee_payer: 0,
         redelegation_stake_account: None,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config_payer).unwrap();
 
@@ -783,6 +800,7 @@ fn test_offline_stake_delegation_and_deactivation() {
         seed: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     let sig_response = process_command(&config_offline).unwrap();
     let sign_only = parse_sign_only_reply_string(&sig_response);
@@ -804,6 +822,7 @@ fn test_offline_stake_delegation_and_deactivation() {
         seed: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config_payer).unwrap();
 }
@@ -858,6 +877,7 @@ fn test_nonced_stake_delegation_and_deactivation() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -903,6 +923,7 @@ fn test_nonced_stake_delegation_and_deactivation() {
         fee_payer: 0,
         redelegation_stake_account: None,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -933,6 +954,7 @@ fn test_nonced_stake_delegation_and_deactivation() {
         seed: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 }
@@ -1001,6 +1023,7 @@ fn test_stake_authorize() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -1026,6 +1049,7 @@ fn test_stake_authorize() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -1068,6 +1092,7 @@ fn test_stake_authorize() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -1100,6 +1125,7 @@ fn test_stake_authorize() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -1132,6 +1158,7 @@ fn test_stake_authorize() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     config_offline.output_format = OutputFormat::JsonCompact;
     let sign_reply = process_command(&config_offline).unwrap();
@@ -1157,6 +1184,7 @@ fn test_stake_authorize() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -1215,6 +1243,7 @@ fn test_stake_authorize() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     let sign_reply = process_command(&config_offline).unwrap();
     let sign_only = parse_sign_only_reply_string(&sign_reply);
@@ -1244,6 +1273,7 @@ fn test_stake_authorize() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -1344,6 +1374,7 @@ fn test_stake_authorize_with_fee_payer() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     check_balance!(
@@ -1372,6 +1403,7 @@ fn test_stake_authorize_with_fee_payer() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     // `config` balance has not changed, despite submitting the TX
@@ -1404,6 +1436,7 @@ fn test_stake_authorize_with_fee_payer() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     config_offline.output_format = OutputFormat::JsonCompact;
     let sign_reply = process_command(&config_offline).unwrap();
@@ -1429,6 +1462,7 @@ fn test_stake_authorize_with_fee_payer() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     // `config`'s balance again has not changed
@@ -1521,6 +1555,7 @@ fn test_stake_split() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     check_balance!(10 * stake_balance, &rpc_client, &stake_account_pubkey,);
@@ -1570,6 +1605,7 @@ fn test_stake_split() {
         lamports: 2 * stake_balance,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
         rent_exempt_reserve: Some(minimum_balance),
     };
     config_offline.output_format = OutputFormat::JsonCompact;
@@ -1595,6 +1631,7 @@ fn test_stake_split() {
         lamports: 2 * stake_balance,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
         rent_exempt_reserve: None,
     };
     process_command(&config).unwrap();
@@ -1687,6 +1724,7 @@ fn test_stake_set_lockup() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     check_balance!(10 * stake_balance, &rpc_client, &stake_account_pubkey,);
@@ -1712,6 +1750,7 @@ fn test_stake_set_lockup() {
         memo: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -1749,6 +1788,7 @@ fn test_stake_set_lockup() {
         memo: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -1771,6 +1811,7 @@ fn test_stake_set_lockup() {
         memo: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -1805,6 +1846,7 @@ fn test_stake_set_lockup() {
         memo: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -1855,6 +1897,7 @@ fn test_stake_set_lockup() {
         memo: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     config_offline.output_format = OutputFormat::JsonCompact;
     let sig_response = process_command(&config_offline).unwrap();
@@ -1878,6 +1921,7 @@ fn test_stake_set_lockup() {
         memo: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -1986,6 +2030,7 @@ fn test_offline_nonced_create_stake_account_and_withdraw() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     config_offline.output_format = OutputFormat::JsonCompact;
     let sig_response = process_command(&config_offline).unwrap();
@@ -2014,6 +2059,7 @@ fn test_offline_nonced_create_stake_account_and_withdraw() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     check_balance!(50_000_000_000, &rpc_client, &stake_pubkey);
@@ -2047,6 +2093,7 @@ fn test_offline_nonced_create_stake_account_and_withdraw() {
         seed: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     let sig_response = process_command(&config_offline).unwrap();
     let sign_only = parse_sign_only_reply_string(&sig_response);
@@ -2070,6 +2117,7 @@ fn test_offline_nonced_create_stake_account_and_withdraw() {
         seed: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     check_balance!(50_000_000_000, &rpc_client, &recipient_pubkey);
@@ -2104,6 +2152,7 @@ fn test_offline_nonced_create_stake_account_and_withdraw() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     let sig_response = process_command(&config_offline).unwrap();
     let sign_only = parse_sign_only_reply_string(&sig_response);
@@ -2130,6 +2179,7 @@ fn test_offline_nonced_create_stake_account_and_withdraw() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let seed_address =
@@ -2186,6 +2236,7 @@ fn test_stake_checked_instructions() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap_err(); // unsigned authority should fail
 
@@ -2207,6 +2258,7 @@ fn test_stake_checked_instructions() {
         fee_payer: 0,
         from: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
 
@@ -2232,6 +2284,7 @@ fn test_stake_checked_instructions() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap_err(); // unsigned authority should fail
 
@@ -2254,6 +2307,7 @@ fn test_stake_checked_instructions() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -2285,6 +2339,7 @@ fn test_stake_checked_instructions() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap_err(); // unsigned authority should fail
 
@@ -2311,6 +2366,7 @@ fn test_stake_checked_instructions() {
         custodian: None,
         no_wait: false,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
@@ -2343,6 +2399,7 @@ fn test_stake_checked_instructions() {
         memo: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap_err(); // unsigned new custodian should fail
 
@@ -2360,6 +2417,7 @@ fn test_stake_checked_instructions() {
         memo: None,
         fee_payer: 0,
         compute_unit_price: None,
+        priority_fee: None,
     };
     process_command(&config).unwrap();
     let stake_account = rpc_client.get_account(&stake_account_pubkey).unwrap();
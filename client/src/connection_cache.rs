@@ -227,7 +227,10 @@ mod tests {
         crossbeam_channel::unbounded,
         trezoa_sdk::{net::DEFAULT_TPU_COALESCE, signature::Keypair},
         trezoa_streamer::{
-            nonblocking::quic::DEFAULT_WAIT_FOR_CHUNK_TIMEOUT, quic::SpawnServerResult,
+            nonblocking::{
+                quic::DEFAULT_WAIT_FOR_CHUNK_TIMEOUT, rate_limiter::QuicRateLimitConfig,
+            },
+            quic::SpawnServerResult,
             streamer::StakedNodes,
         },
         std::{
@@ -272,6 +275,8 @@ mod tests {
             10,
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
 
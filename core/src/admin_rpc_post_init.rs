@@ -6,6 +6,7 @@ use {
     trezoa_gossip::cluster_info::ClusterInfo,
     trezoa_runtime::bank_forks::BankForks,
     trezoa_sdk::{pubkey::Pubkey, quic::NotifyKeyUpdate},
+    trezoa_streamer::nonblocking::connection_registry::ConnectionRegistry,
     std::{
         collections::HashSet,
         net::UdpSocket,
@@ -23,4 +24,6 @@ pub struct AdminRpcRequestMetadataPostInit {
     pub repair_socket: Arc<UdpSocket>,
     pub outstanding_repair_requests: Arc<RwLock<OutstandingRequests<ShredRepairType>>>,
     pub cluster_slots: Arc<ClusterSlots>,
+    pub tpu_connection_registry: Arc<ConnectionRegistry>,
+    pub tpu_forwards_connection_registry: Arc<ConnectionRegistry>,
 }
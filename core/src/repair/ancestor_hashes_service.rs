@@ -9,6 +9,7 @@ use {
             packet_threshold::DynamicPacketToProcessThreshold,
             quic_endpoint::LocalRequest,
             repair_service::{AncestorDuplicateSlotsSender, RepairInfo, RepairStatsGroup},
+            repair_trace::RepairRequestKind,
             request_response::RequestResponse,
             serve_repair::{
                 self, AncestorHashesRepairType, AncestorHashesResponse, RepairProtocol, ServeRepair,
@@ -157,7 +158,9 @@ impl AncestorHashesService {
         repair_info: RepairInfo,
         ancestor_hashes_replay_update_receiver: AncestorHashesReplayUpdateReceiver,
     ) -> Self {
-        let outstanding_requests = Arc::<RwLock<OutstandingAncestorHashesRepairs>>::default();
+        let outstanding_requests = Arc::new(RwLock::new(OutstandingAncestorHashesRepairs::new(
+            repair_info.repair_tracer.clone(),
+        )));
         let (response_sender, response_receiver) = unbounded();
         let t_receiver = streamer::receiver(
             "trzRcvrAncHash".to_string(),
@@ -396,14 +399,19 @@ impl AncestorHashesService {
                     return None;
                 }
 
-                let request_slot = outstanding_requests.write().unwrap().register_response(
-                    nonce,
-                    &response,
-                    timestamp(),
-                    // If the response is valid, return the slot the request
-                    // was for
-                    |ancestor_hashes_request| ancestor_hashes_request.0,
-                );
+                let (request_slot, trace_events) = {
+                    let mut outstanding_requests = outstanding_requests.write().unwrap();
+                    let request_slot = outstanding_requests.register_response(
+                        nonce,
+                        &response,
+                        timestamp(),
+                        // If the response is valid, return the slot the request
+                        // was for
+                        |ancestor_hashes_request| ancestor_hashes_request.0,
+                    );
+                    (request_slot, outstanding_requests.take_trace_events())
+                };
+                trace_events.notify();
 
                 if request_slot.is_none() {
                     stats.invalid_packets += 1;
@@ -850,10 +858,21 @@ impl AncestorHashesService {
                 .ancestor_requests
                 .update(pubkey, duplicate_slot, 0);
             let ancestor_hashes_repair_type = AncestorHashesRepairType(duplicate_slot);
-            let nonce = outstanding_requests
-                .write()
-                .unwrap()
-                .add_request(ancestor_hashes_repair_type, timestamp());
+            let (nonce, trace_events) = {
+                let mut outstanding_requests = outstanding_requests.write().unwrap();
+                let nonce =
+                    outstanding_requests.add_request(ancestor_hashes_repair_type, timestamp());
+                outstanding_requests.trace_request(
+                    nonce,
+                    RepairRequestKind::AncestorHashes,
+                    duplicate_slot,
+                    None,
+                    pubkey,
+                    *socket_addr,
+                );
+                (nonce, outstanding_requests.take_trace_events())
+            };
+            trace_events.notify();
             let Ok(request_bytes) = serve_repair.ancestor_repair_request_bytes(
                 identity_keypair,
                 pubkey,
@@ -1314,6 +1333,7 @@ mod test {
                 remote_request_receiver,
                 response_sender,
                 exit.clone(),
+                None,
             );
 
             Self {
@@ -1377,6 +1397,7 @@ mod test {
                 repair_validators: None,
                 repair_whitelist,
                 wen_restart_repair_slots: None,
+                repair_tracer: None,
             };
 
             let (ancestor_hashes_replay_update_sender, ancestor_hashes_replay_update_receiver) =
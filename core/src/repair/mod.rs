@@ -7,6 +7,7 @@ pub(crate) mod quic_endpoint;
 pub mod repair_generic_traversal;
 pub mod repair_response;
 pub mod repair_service;
+pub mod repair_trace;
 pub mod repair_weight;
 pub mod repair_weighted_traversal;
 pub mod request_response;
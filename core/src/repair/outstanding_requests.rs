@@ -1,14 +1,59 @@
 use {
-    crate::repair::request_response::RequestResponse,
+    crate::repair::{
+        repair_trace::{RepairRequestKind, RepairTraceEvent, RepairTraceEvents, RepairTracer},
+        request_response::RequestResponse,
+    },
     lru::LruCache,
     rand::{thread_rng, Rng},
     trezoa_ledger::shred::Nonce,
+    trezoa_sdk::{clock::Slot, pubkey::Pubkey},
+    std::{net::SocketAddr, sync::Arc},
 };
 
 pub const DEFAULT_REQUEST_EXPIRATION_MS: u64 = 60_000;
 
 pub struct OutstandingRequests<T> {
     requests: LruCache<Nonce, RequestStatus<T>>,
+    repair_tracer: Option<Arc<RepairTracer>>,
+    // Trace events not yet notified, because the lock on the requests is
+    // held while they are collected.
+    trace_events: Vec<RepairTraceEvent>,
+}
+
+impl<T> OutstandingRequests<T> {
+    pub fn new(repair_tracer: Option<Arc<RepairTracer>>) -> Self {
+        Self {
+            requests: LruCache::new(16 * 1024),
+            repair_tracer,
+            trace_events: Vec::new(),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn trace_request(
+        &mut self,
+        nonce: Nonce,
+        kind: RepairRequestKind,
+        slot: Slot,
+        shred_index: Option<u64>,
+        peer: &Pubkey,
+        peer_addr: SocketAddr,
+    ) {
+        if let Some(repair_tracer) = &self.repair_tracer {
+            let expired =
+                repair_tracer.request_sent(nonce, kind, slot, shred_index, peer, peer_addr);
+            self.trace_events.extend(expired);
+        }
+    }
+
+    /// Takes the trace events collected so far, to notify once the lock on
+    /// the requests is released
+    pub(crate) fn take_trace_events(&mut self) -> RepairTraceEvents {
+        RepairTraceEvents::new(
+            self.repair_tracer.clone(),
+            std::mem::take(&mut self.trace_events),
+        )
+    }
 }
 
 impl<T, S> OutstandingRequests<T>
@@ -58,6 +103,13 @@ where
             })
             .unwrap_or((None, false));
 
+        if let Some(repair_tracer) = &self.repair_tracer {
+            if response.is_some() || should_delete {
+                let event = repair_tracer.response_received(nonce, response.is_some());
+                self.trace_events.extend(event);
+            }
+        }
+
         if should_delete {
             self.requests
                 .pop(&nonce)
@@ -70,9 +122,7 @@ where
 
 impl<T> Default for OutstandingRequests<T> {
     fn default() -> Self {
-        Self {
-            requests: LruCache::new(16 * 1024),
-        }
+        Self::new(None)
     }
 }
 
@@ -86,9 +136,13 @@ pub struct RequestStatus<T> {
 pub(crate) mod tests {
     use {
         super::*,
-        crate::repair::serve_repair::ShredRepairType,
+        crate::repair::{
+            repair_trace::{RepairOutcome, RepairTraceNotifierInterface},
+            serve_repair::ShredRepairType,
+        },
         trezoa_ledger::shred::{Shred, ShredFlags},
         trezoa_sdk::timing::timestamp,
+        std::{net::Ipv4Addr, sync::Mutex},
     };
 
     #[test]
@@ -200,4 +254,46 @@ pub(crate) mod tests {
         }
         assert!(outstanding_requests.requests.get(&nonce).is_none());
     }
+
+    #[test]
+    fn test_trace_events_notified_once_taken() {
+        #[derive(Default)]
+        struct TestNotifier(Mutex<Vec<RepairOutcome>>);
+
+        impl RepairTraceNotifierInterface for TestNotifier {
+            fn notify_repair_event(&self, event: &RepairTraceEvent) {
+                self.0.lock().unwrap().push(event.outcome);
+            }
+        }
+
+        let notifier = Arc::<TestNotifier>::default();
+        let repair_tracer = Arc::new(RepairTracer::new(notifier.clone()));
+        let mut outstanding_requests = OutstandingRequests::new(Some(repair_tracer));
+        let repair_type = ShredRepairType::Orphan(9);
+        let nonce = outstanding_requests.add_request(repair_type, timestamp());
+        outstanding_requests.trace_request(
+            nonce,
+            RepairRequestKind::from(&repair_type),
+            9,
+            None,
+            &Pubkey::new_unique(),
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 8000)),
+        );
+        let shred = Shred::new_from_data(0, 0, 0, &[], ShredFlags::empty(), 0, 0, 0);
+        let expire_timestamp = outstanding_requests
+            .requests
+            .get(&nonce)
+            .unwrap()
+            .expire_timestamp;
+        assert!(outstanding_requests
+            .register_response(nonce, &shred, expire_timestamp - 1, |_| ())
+            .is_some());
+        assert!(notifier.0.lock().unwrap().is_empty());
+
+        outstanding_requests.take_trace_events().notify();
+        assert_eq!(*notifier.0.lock().unwrap(), vec![RepairOutcome::Repaired]);
+        // Events are notified once.
+        outstanding_requests.take_trace_events().notify();
+        assert_eq!(notifier.0.lock().unwrap().len(), 1);
+    }
 }
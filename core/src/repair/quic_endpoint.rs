@@ -484,6 +484,18 @@ async fn handle_streams(
     )
     .await
     .map_err(|_| Error::ReadToEndTimeout)??;
+    #[cfg(feature = "dev-context-only-utils")]
+    let local_address = endpoint.local_addr().ok();
+    #[cfg(feature = "dev-context-only-utils")]
+    if !trezoa_streamer::net_emulator::delay_or_drop_stream(
+        Some(&remote_pubkey),
+        &remote_address,
+        local_address,
+    )
+    .await
+    {
+        return Ok(());
+    }
     let (response_sender, response_receiver) = tokio::sync::oneshot::channel();
     let remote_request = RemoteRequest {
         remote_pubkey: Some(remote_pubkey),
@@ -498,6 +510,16 @@ async fn handle_streams(
     let Ok(response) = response_receiver.await else {
         return Err(Error::NoResponseReceived);
     };
+    #[cfg(feature = "dev-context-only-utils")]
+    if !trezoa_streamer::net_emulator::delay_or_drop_response(
+        local_address,
+        Some(&remote_pubkey),
+        &remote_address,
+    )
+    .await
+    {
+        return Ok(());
+    }
     for chunk in response {
         let size = chunk.len() as u64;
         send_stream.write_all(&size.to_le_bytes()).await?;
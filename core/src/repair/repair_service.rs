@@ -14,6 +14,7 @@ use {
             duplicate_repair_status::AncestorDuplicateSlotToRepair,
             outstanding_requests::OutstandingRequests,
             quic_endpoint::LocalRequest,
+            repair_trace::{RepairRequestKind, RepairTracer},
             repair_weight::RepairWeight,
             serve_repair::{
                 self, RepairProtocol, RepairRequestHeader, ServeRepair, ShredRepairType,
@@ -226,6 +227,8 @@ pub struct RepairInfo {
     pub repair_whitelist: Arc<RwLock<HashSet<Pubkey>>>,
     // A given list of slots to repair when in wen_restart
     pub wen_restart_repair_slots: Option<Arc<RwLock<Vec<Slot>>>>,
+    // Traces repair requests sent, if enabled
+    pub repair_tracer: Option<Arc<RepairTracer>>,
 }
 
 pub struct RepairSlotRange {
@@ -450,9 +453,9 @@ impl RepairService {
             let identity_keypair: &Keypair = &repair_info.cluster_info.keypair().clone();
 
             let mut build_repairs_batch_elapsed = Measure::start("build_repairs_batch_elapsed");
-            let batch: Vec<(Vec<u8>, SocketAddr)> = {
+            let (batch, trace_events) = {
                 let mut outstanding_requests = outstanding_requests.write().unwrap();
-                repairs
+                let batch: Vec<(Vec<u8>, SocketAddr)> = repairs
                     .into_iter()
                     .filter_map(|repair_request| {
                         let (to, req) = serve_repair
@@ -471,8 +474,10 @@ impl RepairService {
                             .ok()??;
                         Some((req, to))
                     })
-                    .collect()
+                    .collect();
+                (batch, outstanding_requests.take_trace_events())
             };
+            trace_events.notify();
             build_repairs_batch_elapsed.stop();
 
             let mut batch_send_repairs_elapsed = Measure::start("batch_send_repairs_elapsed");
@@ -623,6 +628,9 @@ impl RepairService {
                 best_repairs_stats = BestRepairsStats::default();
                 last_stats = Instant::now();
             }
+            if let Some(repair_tracer) = &repair_info.repair_tracer {
+                repair_tracer.flush_expired();
+            }
             sleep(Duration::from_millis(REPAIR_MS));
         }
     }
@@ -856,10 +864,20 @@ impl RepairService {
         // Setup repair request
         let identity_keypair = cluster_info.keypair();
         let repair_request = ShredRepairType::Shred(slot, shred_index);
-        let nonce = outstanding_repair_requests
-            .write()
-            .unwrap()
-            .add_request(repair_request, timestamp());
+        let (nonce, trace_events) = {
+            let mut outstanding_repair_requests = outstanding_repair_requests.write().unwrap();
+            let nonce = outstanding_repair_requests.add_request(repair_request, timestamp());
+            outstanding_repair_requests.trace_request(
+                nonce,
+                RepairRequestKind::from(&repair_request),
+                slot,
+                Some(shred_index),
+                &pubkey,
+                address,
+            );
+            (nonce, outstanding_repair_requests.take_trace_events())
+        };
+        trace_events.notify();
 
         // Create repair request
         let header = RepairRequestHeader::new(cluster_info.id(), pubkey, timestamp(), nonce);
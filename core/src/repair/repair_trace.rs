@@ -0,0 +1,597 @@
+//! Tracing of the repair requests a node sends and serves.
+//!
+//! Every request sent is reported once, when the first valid response
+//! arrives, when an invalid one does, or when it expires without any, along
+//! with the peer it was sent to and the time it took. Every request served is
+//! reported with the peer it came from and whether it was answered. Events are
+//! handed to a `RepairTraceNotifier`, such as `RepairTraceLog` which writes
+//! them to a file as JSON lines that `RepairTraceSummary` can analyze.
+
+use {
+    crate::repair::{
+        outstanding_requests::DEFAULT_REQUEST_EXPIRATION_MS,
+        serve_repair::{RepairProtocol, ShredRepairType},
+    },
+    crossbeam_channel::{bounded, Sender, TrySendError},
+    lru::LruCache,
+    trezoa_ledger::shred::Nonce,
+    trezoa_sdk::{clock::Slot, pubkey::Pubkey, timing::timestamp},
+    std::{
+        collections::HashMap,
+        fmt,
+        fs::File,
+        io::{self, BufRead, BufWriter, Write},
+        net::SocketAddr,
+        path::Path,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+        thread::Builder,
+        time::{Duration, Instant},
+    },
+};
+
+// Requests sent and not yet reported.
+const MAX_PENDING_REQUESTS: usize = 16 * 1024;
+// Events buffered for the trace log before they are dropped.
+const MAX_PENDING_EVENTS: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairRequestKind {
+    Shred,
+    HighestShred,
+    Orphan,
+    AncestorHashes,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairOutcome {
+    /// A valid response to a request sent arrived
+    Repaired,
+    /// The response to a request sent was invalid or arrived too late
+    Rejected,
+    /// A request sent got no response in time
+    Expired,
+    /// A request was answered
+    Served,
+    /// A request could not be answered from the blockstore
+    Missing,
+    /// A request was not answered to stay within the outbound data budget
+    Throttled,
+    /// A request was not answered because the peer has yet to respond to a
+    /// ping
+    Unverified,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepairTraceEvent {
+    /// Wallclock in milliseconds when the event was recorded
+    pub timestamp: u64,
+    /// Whether the request was served by this node rather than sent by it
+    pub served: bool,
+    pub kind: RepairRequestKind,
+    pub slot: Slot,
+    pub shred_index: Option<u64>,
+    /// Identity the request was sent to, or received from
+    pub peer: String,
+    pub peer_addr: SocketAddr,
+    /// Time until the response arrived, or until the request was answered
+    pub latency_us: Option<u64>,
+    pub outcome: RepairOutcome,
+}
+
+/// Callback receiving every repair trace event, e.g. from a plugin
+pub trait RepairTraceNotifierInterface {
+    fn notify_repair_event(&self, event: &RepairTraceEvent);
+}
+
+pub type RepairTraceNotifier = Arc<dyn RepairTraceNotifierInterface + Sync + Send>;
+
+struct PendingRequest {
+    kind: RepairRequestKind,
+    slot: Slot,
+    shred_index: Option<u64>,
+    peer: Pubkey,
+    peer_addr: SocketAddr,
+    sent: Instant,
+}
+
+impl PendingRequest {
+    fn into_event(self, outcome: RepairOutcome, latency: Option<Duration>) -> RepairTraceEvent {
+        RepairTraceEvent {
+            timestamp: timestamp(),
+            served: false,
+            kind: self.kind,
+            slot: self.slot,
+            shred_index: self.shred_index,
+            peer: self.peer.to_string(),
+            peer_addr: self.peer_addr,
+            latency_us: latency.map(|latency| latency.as_micros() as u64),
+            outcome,
+        }
+    }
+}
+
+pub struct RepairTracer {
+    notifier: RepairTraceNotifier,
+    pending: Mutex<LruCache<Nonce, PendingRequest>>,
+    // How long a request may go without a response before it is reported
+    // as expired.
+    expiration: Duration,
+}
+
+impl RepairTracer {
+    pub fn new(notifier: RepairTraceNotifier) -> Self {
+        Self {
+            notifier,
+            pending: Mutex::new(LruCache::new(MAX_PENDING_REQUESTS)),
+            expiration: Duration::from_millis(DEFAULT_REQUEST_EXPIRATION_MS),
+        }
+    }
+
+    /// Returns the events of the requests which expired meanwhile, to be
+    /// notified once any lock held by the caller is released
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn request_sent(
+        &self,
+        nonce: Nonce,
+        kind: RepairRequestKind,
+        slot: Slot,
+        shred_index: Option<u64>,
+        peer: &Pubkey,
+        peer_addr: SocketAddr,
+    ) -> Vec<RepairTraceEvent> {
+        let mut pending = self.pending.lock().unwrap();
+        let expired = self.pop_expired(&mut pending);
+        pending.put(
+            nonce,
+            PendingRequest {
+                kind,
+                slot,
+                shred_index,
+                peer: *peer,
+                peer_addr,
+                sent: Instant::now(),
+            },
+        );
+        expired
+    }
+
+    /// Reports the requests which got no response in time, so that they are
+    /// not held back until the next request is sent
+    pub(crate) fn flush_expired(&self) {
+        let expired = self.pop_expired(&mut self.pending.lock().unwrap());
+        for event in &expired {
+            self.notifier.notify_repair_event(event);
+        }
+    }
+
+    // Pops the expired requests, and the least recently sent one if there is
+    // no room for another.
+    fn pop_expired(&self, pending: &mut LruCache<Nonce, PendingRequest>) -> Vec<RepairTraceEvent> {
+        let mut expired = Vec::new();
+        // Requests are sent in order, so the least recently sent ones expire
+        // first.
+        while let Some((_, request)) = pending.peek_lru() {
+            if request.sent.elapsed() < self.expiration && pending.len() < pending.cap() {
+                break;
+            }
+            let (_, request) = pending.pop_lru().unwrap();
+            expired.push(request.into_event(RepairOutcome::Expired, None));
+        }
+        expired
+    }
+
+    /// Returns the event of the request the response is for, unless already
+    /// reported, to be notified once any lock held by the caller is released
+    pub(crate) fn response_received(&self, nonce: Nonce, valid: bool) -> Option<RepairTraceEvent> {
+        let request = self.pending.lock().unwrap().pop(&nonce)?;
+        let latency = request.sent.elapsed();
+        let outcome = if valid {
+            RepairOutcome::Repaired
+        } else {
+            RepairOutcome::Rejected
+        };
+        Some(request.into_event(outcome, Some(latency)))
+    }
+
+    pub(crate) fn request_served(&self, request: &ServedRequest, outcome: RepairOutcome) {
+        let event = RepairTraceEvent {
+            timestamp: timestamp(),
+            served: true,
+            kind: request.kind,
+            slot: request.slot,
+            shred_index: request.shred_index,
+            peer: request.peer.to_string(),
+            peer_addr: request.from_addr,
+            latency_us: Some(request.received.elapsed().as_micros() as u64),
+            outcome,
+        };
+        self.notifier.notify_repair_event(&event);
+    }
+}
+
+/// Trace events collected while a lock is held, so that the notifier, which
+/// may be a plugin, is only called once the lock is released
+#[must_use]
+pub(crate) struct RepairTraceEvents {
+    repair_tracer: Option<Arc<RepairTracer>>,
+    events: Vec<RepairTraceEvent>,
+}
+
+impl RepairTraceEvents {
+    pub(crate) fn new(
+        repair_tracer: Option<Arc<RepairTracer>>,
+        events: Vec<RepairTraceEvent>,
+    ) -> Self {
+        Self {
+            repair_tracer,
+            events,
+        }
+    }
+
+    pub(crate) fn notify(self) {
+        if let Some(repair_tracer) = self.repair_tracer {
+            for event in &self.events {
+                repair_tracer.notifier.notify_repair_event(event);
+            }
+        }
+    }
+}
+
+/// Request being served, kept to trace its outcome once the request itself
+/// has been handled
+pub(crate) struct ServedRequest {
+    kind: RepairRequestKind,
+    slot: Slot,
+    shred_index: Option<u64>,
+    peer: Pubkey,
+    from_addr: SocketAddr,
+    received: Instant,
+}
+
+impl ServedRequest {
+    /// Returns None for requests which are not for repairs
+    pub(crate) fn new(request: &RepairProtocol, from_addr: SocketAddr) -> Option<Self> {
+        let (kind, slot, shred_index) = match request {
+            RepairProtocol::WindowIndex {
+                slot, shred_index, ..
+            } => (RepairRequestKind::Shred, *slot, Some(*shred_index)),
+            RepairProtocol::HighestWindowIndex {
+                slot, shred_index, ..
+            } => (RepairRequestKind::HighestShred, *slot, Some(*shred_index)),
+            RepairProtocol::Orphan { slot, .. } => (RepairRequestKind::Orphan, *slot, None),
+            RepairProtocol::AncestorHashes { slot, .. } => {
+                (RepairRequestKind::AncestorHashes, *slot, None)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            slot,
+            shred_index,
+            peer: *request.sender(),
+            from_addr,
+            received: Instant::now(),
+        })
+    }
+}
+
+impl From<&ShredRepairType> for RepairRequestKind {
+    fn from(request: &ShredRepairType) -> Self {
+        match request {
+            ShredRepairType::Orphan(_) => Self::Orphan,
+            ShredRepairType::HighestShred(_, _) => Self::HighestShred,
+            ShredRepairType::Shred(_, _) => Self::Shred,
+        }
+    }
+}
+
+/// Writes repair trace events to a file, one JSON object per line
+pub struct RepairTraceLog {
+    sender: Sender<RepairTraceEvent>,
+    num_dropped: Arc<AtomicU64>,
+}
+
+impl RepairTraceLog {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let (sender, receiver) = bounded::<RepairTraceEvent>(MAX_PENDING_EVENTS);
+        let num_dropped = Arc::<AtomicU64>::default();
+        Builder::new().name("trzRepairTrace".to_string()).spawn({
+            let num_dropped = num_dropped.clone();
+            move || {
+                // Exits once the log is dropped.
+                for event in receiver.iter() {
+                    let result = serde_json::to_writer(&mut writer, &event)
+                        .map_err(io::Error::from)
+                        .and_then(|()| writeln!(writer))
+                        .and_then(|()| {
+                            if receiver.is_empty() {
+                                writer.flush()
+                            } else {
+                                Ok(())
+                            }
+                        });
+                    if let Err(err) = result {
+                        error!("Failed to write repair trace: {err}");
+                        num_dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        })?;
+        Ok(Self {
+            sender,
+            num_dropped,
+        })
+    }
+
+    /// Number of events not written, because writing is falling behind or
+    /// failed
+    pub fn num_dropped(&self) -> u64 {
+        self.num_dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl RepairTraceNotifierInterface for RepairTraceLog {
+    fn notify_repair_event(&self, event: &RepairTraceEvent) {
+        match self.sender.try_send(event.clone()) {
+            Ok(()) => (),
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                self.num_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Repairs requested from a peer
+#[derive(Clone, Debug, Default)]
+pub struct PeerRepairStats {
+    pub num_requests: usize,
+    pub num_repaired: usize,
+    pub num_rejected: usize,
+    pub num_expired: usize,
+    latencies_us: Vec<u64>,
+}
+
+impl PeerRepairStats {
+    /// Share of the requests validly answered in time
+    pub fn success_rate(&self) -> f64 {
+        if self.num_requests == 0 {
+            return 0.0;
+        }
+        self.num_repaired as f64 / self.num_requests as f64
+    }
+
+    /// Latency of the valid responses at the percentile, between 0 and 100
+    pub fn latency_percentile(&mut self, percentile: usize) -> Option<Duration> {
+        self.latencies_us.sort_unstable();
+        let index = (self.latencies_us.len() * percentile / 100)
+            .min(self.latencies_us.len().checked_sub(1)?);
+        Some(Duration::from_micros(self.latencies_us[index]))
+    }
+}
+
+/// Summary of a repair trace log
+#[derive(Debug, Default)]
+pub struct RepairTraceSummary {
+    /// Stats of the repairs requested, by peer identity
+    pub peers: HashMap<String, PeerRepairStats>,
+    /// Number of requests served, by outcome
+    pub served: HashMap<RepairOutcome, usize>,
+    /// Lines of the log which could not be parsed
+    pub num_invalid_lines: usize,
+}
+
+impl RepairTraceSummary {
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut summary = Self::default();
+        for line in reader.lines() {
+            match serde_json::from_str(&line?) {
+                Ok(event) => summary.add(&event),
+                Err(_) => summary.num_invalid_lines += 1,
+            }
+        }
+        Ok(summary)
+    }
+
+    pub fn add(&mut self, event: &RepairTraceEvent) {
+        if event.served {
+            *self.served.entry(event.outcome).or_default() += 1;
+            return;
+        }
+        let stats = self.peers.entry(event.peer.clone()).or_default();
+        stats.num_requests += 1;
+        match event.outcome {
+            RepairOutcome::Repaired => {
+                stats.num_repaired += 1;
+                stats.latencies_us.extend(event.latency_us);
+            }
+            RepairOutcome::Rejected => stats.num_rejected += 1,
+            RepairOutcome::Expired => stats.num_expired += 1,
+            RepairOutcome::Served
+            | RepairOutcome::Missing
+            | RepairOutcome::Throttled
+            | RepairOutcome::Unverified => (),
+        }
+    }
+
+    /// Peers with at least `min_requests` requests, the ones serving repairs
+    /// the worst first: by success rate, then by 99th percentile latency
+    pub fn worst_peers(&mut self, min_requests: usize) -> Vec<(String, PeerRepairStats)> {
+        let mut peers: Vec<_> = self
+            .peers
+            .iter_mut()
+            .filter(|(_, stats)| stats.num_requests >= min_requests)
+            .map(|(peer, stats)| {
+                let p99 = stats.latency_percentile(99).unwrap_or(Duration::MAX);
+                (stats.success_rate(), p99, peer.clone(), stats.clone())
+            })
+            .collect();
+        peers.sort_unstable_by(|a, b| {
+            a.0.total_cmp(&b.0)
+                .then_with(|| b.1.cmp(&a.1))
+                .then_with(|| a.2.cmp(&b.2))
+        });
+        peers
+            .into_iter()
+            .map(|(_, _, peer, stats)| (peer, stats))
+            .collect()
+    }
+}
+
+impl fmt::Display for RepairOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outcome = match self {
+            Self::Repaired => "repaired",
+            Self::Rejected => "rejected",
+            Self::Expired => "expired",
+            Self::Served => "served",
+            Self::Missing => "missing",
+            Self::Throttled => "throttled",
+            Self::Unverified => "unverified",
+        };
+        write!(f, "{outcome}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::net::Ipv4Addr};
+
+    #[derive(Default)]
+    struct TestNotifier {
+        events: Mutex<Vec<RepairTraceEvent>>,
+    }
+
+    impl RepairTraceNotifierInterface for TestNotifier {
+        fn notify_repair_event(&self, event: &RepairTraceEvent) {
+            self.events.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_repair_tracer() {
+        let notifier = Arc::<TestNotifier>::default();
+        let tracer = RepairTracer::new(notifier.clone());
+        let peer = Pubkey::new_unique();
+        let peer_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 8000));
+        assert!(tracer
+            .request_sent(1, RepairRequestKind::Shred, 7, Some(3), &peer, peer_addr)
+            .is_empty());
+        assert!(tracer
+            .request_sent(2, RepairRequestKind::Orphan, 9, None, &peer, peer_addr)
+            .is_empty());
+
+        let events: Vec<_> = [(1, true), (2, false), (1, true), (3, true)]
+            .into_iter()
+            .filter_map(|(nonce, valid)| tracer.response_received(nonce, valid))
+            .collect();
+        // Requests are reported once, and only notified when asked to.
+        assert_eq!(events.len(), 2);
+        assert!(notifier.events.lock().unwrap().is_empty());
+        RepairTraceEvents::new(Some(Arc::new(tracer)), events.clone()).notify();
+        assert_eq!(*notifier.events.lock().unwrap(), events);
+        assert_eq!(events[0].kind, RepairRequestKind::Shred);
+        assert_eq!(events[0].slot, 7);
+        assert_eq!(events[0].shred_index, Some(3));
+        assert_eq!(events[0].peer, peer.to_string());
+        assert_eq!(events[0].outcome, RepairOutcome::Repaired);
+        assert!(events[0].latency_us.is_some());
+        assert!(!events[0].served);
+        assert_eq!(events[1].outcome, RepairOutcome::Rejected);
+
+        // Serialized as JSON lines, and summarized.
+        let log: String = events
+            .iter()
+            .chain(&[RepairTraceEvent {
+                outcome: RepairOutcome::Expired,
+                latency_us: None,
+                ..events[0].clone()
+            }])
+            .map(|event| serde_json::to_string(event).unwrap() + "\n")
+            .collect();
+        let mut summary = RepairTraceSummary::from_reader(log.as_bytes()).unwrap();
+        assert_eq!(summary.num_invalid_lines, 0);
+        let stats = &summary.peers[&peer.to_string()];
+        assert_eq!(stats.num_requests, 3);
+        assert_eq!(stats.num_repaired, 1);
+        assert_eq!(stats.num_rejected, 1);
+        assert_eq!(stats.num_expired, 1);
+        assert!(summary.worst_peers(4).is_empty());
+        assert_eq!(summary.worst_peers(3).len(), 1);
+    }
+
+    #[test]
+    fn test_flush_expired() {
+        let notifier = Arc::<TestNotifier>::default();
+        let tracer = RepairTracer {
+            expiration: Duration::ZERO,
+            ..RepairTracer::new(notifier.clone())
+        };
+        let peer = Pubkey::new_unique();
+        let peer_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 8000));
+        assert!(tracer
+            .request_sent(1, RepairRequestKind::Shred, 7, Some(3), &peer, peer_addr)
+            .is_empty());
+        assert!(notifier.events.lock().unwrap().is_empty());
+
+        // Reported without waiting for another request to be sent.
+        tracer.flush_expired();
+        let events = notifier.events.lock().unwrap().clone();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].outcome, RepairOutcome::Expired);
+        assert_eq!(events[0].latency_us, None);
+
+        // A late response is not reported again.
+        assert_eq!(tracer.response_received(1, true), None);
+        tracer.flush_expired();
+        assert_eq!(notifier.events.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_worst_peers() {
+        let mut summary = RepairTraceSummary::default();
+        let event = |peer: &str, latency_us, outcome| RepairTraceEvent {
+            timestamp: 0,
+            served: false,
+            kind: RepairRequestKind::Shred,
+            slot: 1,
+            shred_index: Some(0),
+            peer: peer.to_string(),
+            peer_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 8000)),
+            latency_us: Some(latency_us),
+            outcome,
+        };
+        for (peer, latency_us, outcome) in [
+            ("fast", 1_000, RepairOutcome::Repaired),
+            ("fast", 2_000, RepairOutcome::Repaired),
+            ("slow", 90_000, RepairOutcome::Repaired),
+            ("slow", 80_000, RepairOutcome::Repaired),
+            ("lossy", 1_000, RepairOutcome::Repaired),
+            ("lossy", 0, RepairOutcome::Expired),
+        ] {
+            summary.add(&event(peer, latency_us, outcome));
+        }
+        summary.add(&RepairTraceEvent {
+            served: true,
+            ..event("requester", 10, RepairOutcome::Missing)
+        });
+        let peers: Vec<_> = summary
+            .worst_peers(1)
+            .into_iter()
+            .map(|(peer, _)| peer)
+            .collect();
+        assert_eq!(peers, vec!["lossy", "slow", "fast"]);
+        assert_eq!(summary.served[&RepairOutcome::Missing], 1);
+        let stats = summary.peers.get_mut("slow").unwrap();
+        assert_eq!(stats.success_rate(), 1.0);
+        assert_eq!(
+            stats.latency_percentile(50),
+            Some(Duration::from_millis(90))
+        );
+    }
+}
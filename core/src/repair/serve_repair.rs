@@ -6,6 +6,7 @@ use {
             quic_endpoint::{LocalRequest, RemoteRequest},
             repair_response,
             repair_service::{OutstandingShredRepairs, RepairStats, REPAIR_MS},
+            repair_trace::{RepairOutcome, RepairRequestKind, RepairTracer, ServedRequest},
             request_response::RequestResponse,
             result::{Error, RepairVerifyError, Result},
         },
@@ -109,6 +110,15 @@ impl ShredRepairType {
             | ShredRepairType::Shred(slot, _) => *slot,
         }
     }
+
+    pub fn shred_index(&self) -> Option<u64> {
+        match self {
+            ShredRepairType::Orphan(_) => None,
+            ShredRepairType::HighestShred(_, index) | ShredRepairType::Shred(_, index) => {
+                Some(*index)
+            }
+        }
+    }
 }
 
 impl RequestResponse for ShredRepairType {
@@ -267,7 +277,7 @@ pub(crate) enum RepairResponse {
 }
 
 impl RepairProtocol {
-    fn sender(&self) -> &Pubkey {
+    pub(crate) fn sender(&self) -> &Pubkey {
         match self {
             Self::LegacyWindowIndex(ci, _, _) => ci.pubkey(),
             Self::LegacyHighestWindowIndex(ci, _, _) => ci.pubkey(),
@@ -622,6 +632,7 @@ impl ServeRepair {
         response_sender: &PacketBatchSender,
         stats: &mut ServeRepairStats,
         data_budget: &DataBudget,
+        repair_tracer: Option<&RepairTracer>,
     ) -> std::result::Result<(), RecvTimeoutError> {
         const TIMEOUT: Duration = Duration::from_secs(1);
         let mut requests = vec![requests_receiver.recv_timeout(TIMEOUT)?];
@@ -696,6 +707,7 @@ impl ServeRepair {
             response_sender,
             stats,
             data_budget,
+            repair_tracer,
         );
         stats.handle_requests_time_us += handle_requests_start.elapsed().as_micros() as u64;
 
@@ -796,6 +808,7 @@ impl ServeRepair {
         requests_receiver: Receiver<RemoteRequest>,
         response_sender: PacketBatchSender,
         exit: Arc<AtomicBool>,
+        repair_tracer: Option<Arc<RepairTracer>>,
     ) -> JoinHandle<()> {
         const INTERVAL_MS: u64 = 1000;
         const MAX_BYTES_PER_SECOND: usize = 12_000_000;
@@ -826,6 +839,7 @@ impl ServeRepair {
                         &response_sender,
                         &mut stats,
                         &data_budget,
+                        repair_tracer.as_deref(),
                     );
                     match result {
                         Ok(_) | Err(RecvTimeoutError::Timeout) => {}
@@ -949,6 +963,7 @@ impl ServeRepair {
         packet_batch_sender: &PacketBatchSender,
         stats: &mut ServeRepairStats,
         data_budget: &DataBudget,
+        repair_tracer: Option<&RepairTracer>,
     ) {
         let identity_keypair = self.cluster_info.keypair().clone();
         let mut pending_pings = Vec::default();
@@ -961,8 +976,18 @@ impl ServeRepair {
             response_sender,
         } in requests.into_iter()
         {
+            let served_request =
+                repair_tracer.and_then(|_| ServedRequest::new(&request, from_addr));
+            let trace_served = |outcome| {
+                if let (Some(repair_tracer), Some(served_request)) =
+                    (repair_tracer, &served_request)
+                {
+                    repair_tracer.request_served(served_request, outcome);
+                }
+            };
             if !data_budget.check(request.max_response_bytes()) {
                 stats.dropped_requests_outbound_bandwidth += 1;
+                trace_served(RepairOutcome::Throttled);
                 continue;
             }
             // Bypass ping/pong check for requests coming from QUIC endpoint.
@@ -974,6 +999,7 @@ impl ServeRepair {
                 }
                 if !check {
                     stats.ping_cache_check_failed += 1;
+                    trace_served(RepairOutcome::Unverified);
                     continue;
                 }
             }
@@ -981,6 +1007,7 @@ impl ServeRepair {
             let Some(rsp) =
                 Self::handle_repair(recycler, &from_addr, blockstore, request, stats, ping_cache)
             else {
+                trace_served(RepairOutcome::Missing);
                 continue;
             };
             let num_response_packets = rsp.len();
@@ -993,9 +1020,11 @@ impl ServeRepair {
                     true => stats.total_response_bytes_staked += num_response_bytes,
                     false => stats.total_response_bytes_unstaked += num_response_bytes,
                 }
+                trace_served(RepairOutcome::Served);
             } else {
                 stats.dropped_requests_outbound_bandwidth += 1;
                 stats.total_dropped_response_packets += num_response_packets;
+                trace_served(RepairOutcome::Throttled);
             }
         }
 
@@ -1057,6 +1086,18 @@ impl ServeRepair {
         };
         let peer = repair_peers.sample(&mut rand::thread_rng());
         let nonce = outstanding_requests.add_request(repair_request, timestamp());
+        let peer_addr = match repair_protocol {
+            Protocol::UDP => peer.serve_repair,
+            Protocol::QUIC => peer.serve_repair_quic,
+        };
+        outstanding_requests.trace_request(
+            nonce,
+            RepairRequestKind::from(&repair_request),
+            slot,
+            repair_request.shred_index(),
+            &peer.pubkey,
+            peer_addr,
+        );
         let out = self.map_repair_request(
             &repair_request,
             &peer.pubkey,
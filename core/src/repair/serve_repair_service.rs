@@ -1,5 +1,7 @@
 use {
-    crate::repair::{quic_endpoint::RemoteRequest, serve_repair::ServeRepair},
+    crate::repair::{
+        quic_endpoint::RemoteRequest, repair_trace::RepairTracer, serve_repair::ServeRepair,
+    },
     crossbeam_channel::{unbounded, Receiver, Sender},
     trezoa_ledger::blockstore::Blockstore,
     trezoa_perf::{packet::PacketBatch, recycler::Recycler},
@@ -29,6 +31,7 @@ impl ServeRepairService {
         socket_addr_space: SocketAddrSpace,
         stats_reporter_sender: Sender<Box<dyn FnOnce() + Send>>,
         exit: Arc<AtomicBool>,
+        repair_tracer: Option<Arc<RepairTracer>>,
     ) -> Self {
         let (request_sender, request_receiver) = unbounded();
         let serve_repair_socket = Arc::new(serve_repair_socket);
@@ -60,8 +63,13 @@ impl ServeRepairService {
             socket_addr_space,
             Some(stats_reporter_sender),
         );
-        let t_listen =
-            serve_repair.listen(blockstore, remote_request_receiver, response_sender, exit);
+        let t_listen = serve_repair.listen(
+            blockstore,
+            remote_request_receiver,
+            response_sender,
+            exit,
+            repair_tracer,
+        );
 
         let thread_hdls = vec![t_receiver, t_packet_adapter, t_responder, t_listen];
         Self { thread_hdls }
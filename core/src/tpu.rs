@@ -33,7 +33,10 @@ use {
     trezoa_runtime::{bank_forks::BankForks, prioritization_fee_cache::PrioritizationFeeCache},
     trezoa_sdk::{clock::Slot, pubkey::Pubkey, quic::NotifyKeyUpdate, signature::Keypair},
     trezoa_streamer::{
-        nonblocking::quic::DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
+        nonblocking::{
+            connection_registry::ConnectionRegistry, quic::DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
+            rate_limiter::QuicRateLimitConfig,
+        },
         quic::{spawn_server, SpawnServerResult, MAX_STAKED_CONNECTIONS, MAX_UNSTAKED_CONNECTIONS},
         streamer::StakedNodes,
     },
@@ -98,6 +101,9 @@ impl Tpu {
         replay_vote_sender: ReplayVoteSender,
         bank_notification_sender: Option<BankNotificationSender>,
         tpu_coalesce: Duration,
+        tpu_rate_limits: QuicRateLimitConfig,
+        tpu_connection_registry: &Arc<ConnectionRegistry>,
+        tpu_forwards_connection_registry: &Arc<ConnectionRegistry>,
         duplicate_confirmed_slot_sender: DuplicateConfirmedSlotsSender,
         connection_cache: &Arc<ConnectionCache>,
         turbine_quic_endpoint_sender: AsyncSender<(SocketAddr, Bytes)>,
@@ -165,6 +171,8 @@ impl Tpu {
             MAX_UNSTAKED_CONNECTIONS,
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             tpu_coalesce,
+            tpu_rate_limits,
+            tpu_connection_registry.clone(),
         )
         .unwrap();
 
@@ -185,6 +193,8 @@ impl Tpu {
             0, // Prevent unstaked nodes from forwarding transactions
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             tpu_coalesce,
+            QuicRateLimitConfig::default(),
+            tpu_forwards_connection_registry.clone(),
         )
         .unwrap();
 
@@ -17,6 +17,7 @@ use {
         repair::{
             quic_endpoint::LocalRequest,
             repair_service::{OutstandingShredRepairs, RepairInfo},
+            repair_trace::RepairTracer,
         },
         replay_stage::{ReplayStage, ReplayStageConfig},
         rewards_recorder_service::RewardsRecorderSender,
@@ -91,6 +92,8 @@ pub struct TvuConfig {
     pub repair_whitelist: Arc<RwLock<HashSet<Pubkey>>>,
     pub wait_for_vote_to_start_leader: bool,
     pub replay_slots_concurrently: bool,
+    // Traces repair requests sent, if enabled
+    pub repair_tracer: Option<Arc<RepairTracer>>,
 }
 
 impl Tvu {
@@ -216,6 +219,7 @@ impl Tvu {
                 cluster_info: cluster_info.clone(),
                 cluster_slots: cluster_slots.clone(),
                 wen_restart_repair_slots,
+                repair_tracer: tvu_config.repair_tracer,
             };
             WindowService::new(
                 blockstore.clone(),
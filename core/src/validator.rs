@@ -15,7 +15,12 @@ use {
             ExternalRootSource, Tower,
         },
         poh_timing_report_service::PohTimingReportService,
-        repair::{self, serve_repair::ServeRepair, serve_repair_service::ServeRepairService},
+        repair::{
+            self,
+            repair_trace::{RepairTraceNotifier, RepairTracer},
+            serve_repair::ServeRepair,
+            serve_repair_service::ServeRepairService,
+        },
         rewards_recorder_service::{RewardsRecorderSender, RewardsRecorderService},
         sample_performance_service::SamplePerformanceService,
         sigverify,
@@ -115,14 +120,18 @@ use {
         timing::timestamp,
     },
     trezoa_send_transaction_service::send_transaction_service,
-    trezoa_streamer::{socket::SocketAddrSpace, streamer::StakedNodes},
+    trezoa_streamer::{
+        nonblocking::{connection_registry::ConnectionRegistry, rate_limiter::QuicRateLimitConfig},
+        socket::SocketAddrSpace,
+        streamer::StakedNodes,
+    },
     trezoa_turbine::{self, broadcast_stage::BroadcastStageType},
     trezoa_unified_scheduler_pool::DefaultSchedulerPool,
     trezoa_vote_program::vote_state,
     trezoa_wen_restart::wen_restart::wait_for_wen_restart,
     std::{
         collections::{HashMap, HashSet},
-        net::SocketAddr,
+        net::{SocketAddr, UdpSocket},
         path::{Path, PathBuf},
         sync::{
             atomic::{AtomicBool, AtomicU64, Ordering},
@@ -225,7 +234,12 @@ pub struct ValidatorConfig {
     pub known_validators: Option<HashSet<Pubkey>>, // None = trust all
     pub repair_validators: Option<HashSet<Pubkey>>, // None = repair from all
     pub repair_whitelist: Arc<RwLock<HashSet<Pubkey>>>, // Empty = repair with all
+    /// Receives every repair request sent and served, if set
+    pub repair_trace_notifier: Option<RepairTraceNotifier>,
     pub gossip_validators: Option<HashSet<Pubkey>>, // None = gossip with all
+    /// Advertise a QUIC gossip socket and gossip over QUIC with nodes which
+    /// also advertise one.
+    pub gossip_quic: bool,
     pub accounts_hash_interval_slots: u64,
     pub max_genesis_archive_unpacked_size: u64,
     pub wal_recovery_mode: Option<BlockstoreRecoveryMode>,
@@ -253,6 +267,7 @@ pub struct ValidatorConfig {
     pub accounts_db_skip_shrink: bool,
     pub accounts_db_force_initial_clean: bool,
     pub tpu_coalesce: Duration,
+    pub tpu_rate_limits: QuicRateLimitConfig,
     pub staked_nodes_overrides: Arc<RwLock<HashMap<Pubkey, u64>>>,
     pub validator_exit: Arc<RwLock<Exit>>,
     pub no_wait_for_vote_to_start_leader: bool,
@@ -295,7 +310,9 @@ impl Default for ValidatorConfig {
             known_validators: None,
             repair_validators: None,
             repair_whitelist: Arc::new(RwLock::new(HashSet::default())),
+            repair_trace_notifier: None,
             gossip_validators: None,
+            gossip_quic: false,
             accounts_hash_interval_slots: std::u64::MAX,
             max_genesis_archive_unpacked_size: MAX_GENESIS_ARCHIVE_UNPACKED_SIZE,
             wal_recovery_mode: None,
@@ -320,6 +337,7 @@ impl Default for ValidatorConfig {
             accounts_db_skip_shrink: false,
             accounts_db_force_initial_clean: false,
             tpu_coalesce: DEFAULT_TPU_COALESCE,
+            tpu_rate_limits: QuicRateLimitConfig::default(),
             staked_nodes_overrides: Arc::new(RwLock::new(HashMap::new())),
             validator_exit: Arc::new(RwLock::new(Exit::default())),
             no_wait_for_vote_to_start_leader: true,
@@ -474,6 +492,9 @@ pub struct Validator {
     blockstore_metric_report_service: BlockstoreMetricReportService,
     accounts_background_service: AccountsBackgroundService,
     accounts_hash_verifier: AccountsHashVerifier,
+    gossip_quic_endpoint: Option<Endpoint>,
+    gossip_quic_endpoint_runtime: Option<TokioRuntime>,
+    gossip_quic_endpoint_join_handle: Option<trezoa_gossip::quic_endpoint::AsyncTryJoinHandle>,
     turbine_quic_endpoint: Option<Endpoint>,
     turbine_quic_endpoint_runtime: Option<TokioRuntime>,
     turbine_quic_endpoint_join_handle: Option<trezoa_turbine::quic_endpoint::AsyncTryJoinHandle>,
@@ -724,6 +745,18 @@ impl Validator {
             &genesis_config.hash(),
             Some(&hard_forks),
         ));
+        if config.gossip_quic {
+            // Nodes created without it, such as in tests, bind it on any port
+            let gossip_quic = node.sockets.gossip_quic.get_or_insert_with(|| {
+                let gossip_bind_ip = node.sockets.gossip.local_addr().unwrap().ip();
+                UdpSocket::bind((gossip_bind_ip, 0)).unwrap()
+            });
+            let gossip_quic_port = gossip_quic.local_addr().unwrap().port();
+            let gossip_ip = node.info.gossip().unwrap().ip();
+            node.info
+                .set_gossip_quic((gossip_ip, gossip_quic_port))
+                .unwrap();
+        }
 
         Self::print_node_info(&node);
 
@@ -1081,6 +1114,45 @@ impl Validator {
         let stats_reporter_service =
             StatsReporterService::new(stats_reporter_receiver, exit.clone());
 
+        // test-validator crate may start the validator in a tokio runtime
+        // context which forces us to use the same runtime because a nested
+        // runtime will cause panic at drop.
+        // Outside test-validator crate, we always need a tokio runtime (and
+        // the respective handle) to initialize the QUIC endpoints.
+        let current_runtime_handle = tokio::runtime::Handle::try_current();
+
+        // Gossip quic endpoint.
+        let gossip_quic_endpoint_runtime = (config.gossip_quic && current_runtime_handle.is_err())
+            .then(|| {
+                tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .thread_name("trzGossipQuic")
+                    .build()
+                    .unwrap()
+            });
+        let (gossip_quic_endpoint, gossip_quic_endpoint_channels, gossip_quic_endpoint_join_handle) =
+            if config.gossip_quic {
+                let (gossip_quic_endpoint_sender, gossip_quic_endpoint_receiver) = unbounded();
+                let (endpoint, sender, join_handle) =
+                    trezoa_gossip::quic_endpoint::new_quic_endpoint(
+                        gossip_quic_endpoint_runtime
+                            .as_ref()
+                            .map(TokioRuntime::handle)
+                            .unwrap_or_else(|| current_runtime_handle.as_ref().unwrap()),
+                        &identity_keypair,
+                        node.sockets.gossip_quic.take().unwrap(),
+                        gossip_quic_endpoint_sender,
+                    )
+                    .unwrap();
+                (
+                    Some(endpoint),
+                    Some((sender, gossip_quic_endpoint_receiver)),
+                    Some(join_handle),
+                )
+            } else {
+                (None, None, None)
+            };
+
         let gossip_service = GossipService::new(
             &cluster_info,
             Some(bank_forks.clone()),
@@ -1088,6 +1160,7 @@ impl Validator {
             config.gossip_validators.clone(),
             should_check_duplicate_instance,
             Some(stats_reporter_sender.clone()),
+            gossip_quic_endpoint_channels,
             exit.clone(),
         );
         let serve_repair = ServeRepair::new(
@@ -1095,6 +1168,10 @@ impl Validator {
             bank_forks.clone(),
             config.repair_whitelist.clone(),
         );
+        let repair_tracer = config
+            .repair_trace_notifier
+            .clone()
+            .map(|notifier| Arc::new(RepairTracer::new(notifier)));
         let (repair_quic_endpoint_sender, repair_quic_endpoint_receiver) = unbounded();
         let serve_repair_service = ServeRepairService::new(
             serve_repair,
@@ -1107,6 +1184,7 @@ impl Validator {
             socket_addr_space,
             stats_reporter_sender,
             exit.clone(),
+            repair_tracer.clone(),
         );
 
         let waited_for_supermajority = wait_for_supermajority(
@@ -1173,12 +1251,6 @@ impl Validator {
             .as_ref()
             .map(|service| service.sender_cloned());
 
-        // test-validator crate may start the validator in a tokio runtime
-        // context which forces us to use the same runtime because a nested
-        // runtime will cause panic at drop.
-        // Outside test-validator crate, we always need a tokio runtime (and
-        // the respective handle) to initialize the turbine QUIC endpoint.
-        let current_runtime_handle = tokio::runtime::Handle::try_current();
         let turbine_quic_endpoint_runtime = (current_runtime_handle.is_err()
             && genesis_config.cluster_type != ClusterType::MainnetBeta)
             .then(|| {
@@ -1261,8 +1333,9 @@ impl Validator {
         };
         let last_vote = tower.last_vote();
 
-        let outstanding_repair_requests =
-            Arc::<RwLock<repair::repair_service::OutstandingShredRepairs>>::default();
+        let outstanding_repair_requests = Arc::new(RwLock::new(
+            repair::repair_service::OutstandingShredRepairs::new(repair_tracer.clone()),
+        ));
         let cluster_slots =
             Arc::new(crate::cluster_slots_service::cluster_slots::ClusterSlots::default());
 
@@ -1306,6 +1379,7 @@ impl Validator {
                 repair_whitelist: config.repair_whitelist.clone(),
                 wait_for_vote_to_start_leader,
                 replay_slots_concurrently: config.replay_slots_concurrently,
+                repair_tracer,
             },
             &max_slots,
             block_metadata_notifier,
@@ -1342,6 +1416,8 @@ impl Validator {
             };
         }
 
+        let tpu_connection_registry = Arc::<ConnectionRegistry>::default();
+        let tpu_forwards_connection_registry = Arc::<ConnectionRegistry>::default();
         let (tpu, mut key_notifies) = Tpu::new(
             &cluster_info,
             &poh_recorder,
@@ -1370,6 +1446,9 @@ impl Validator {
             replay_vote_sender,
             bank_notification_sender.map(|sender| sender.sender),
             config.tpu_coalesce,
+            config.tpu_rate_limits,
+            &tpu_connection_registry,
+            &tpu_forwards_connection_registry,
             duplicate_confirmed_slot_sender,
             &connection_cache,
             turbine_quic_endpoint_sender,
@@ -1406,6 +1485,8 @@ impl Validator {
             repair_socket: Arc::new(node.sockets.repair),
             outstanding_repair_requests,
             cluster_slots,
+            tpu_connection_registry,
+            tpu_forwards_connection_registry,
         });
 
         Ok(Self {
@@ -1438,6 +1519,9 @@ impl Validator {
             blockstore_metric_report_service,
             accounts_background_service,
             accounts_hash_verifier,
+            gossip_quic_endpoint,
+            gossip_quic_endpoint_runtime,
+            gossip_quic_endpoint_join_handle,
             turbine_quic_endpoint,
             turbine_quic_endpoint_runtime,
             turbine_quic_endpoint_join_handle,
@@ -1553,6 +1637,15 @@ impl Validator {
         }
 
         self.gossip_service.join().expect("gossip_service");
+        if let Some(gossip_quic_endpoint) = &self.gossip_quic_endpoint {
+            trezoa_gossip::quic_endpoint::close_quic_endpoint(gossip_quic_endpoint);
+        }
+        if let Some(gossip_quic_endpoint_join_handle) = self.gossip_quic_endpoint_join_handle {
+            self.gossip_quic_endpoint_runtime
+                .map(|runtime| runtime.block_on(gossip_quic_endpoint_join_handle))
+                .transpose()
+                .unwrap();
+        }
         if let Some(repair_quic_endpoint) = &self.repair_quic_endpoint {
             repair::quic_endpoint::close_quic_endpoint(repair_quic_endpoint);
         }
@@ -257,7 +257,7 @@ fn prune_shreds_by_repair_status(
     assert_eq!(shreds.len(), repair_infos.len());
     let mut i = 0;
     let mut removed = HashSet::new();
-    {
+    let trace_events = {
         let mut outstanding_requests = outstanding_requests.write().unwrap();
         shreds.retain(|shred| {
             let should_keep = (
@@ -271,7 +271,9 @@ fn prune_shreds_by_repair_status(
             }
             should_keep
         });
-    }
+        outstanding_requests.take_trace_events()
+    };
+    trace_events.notify();
     i = 0;
     repair_infos.retain(|_repair_info| (!removed.contains(&i), i += 1).0);
     assert_eq!(shreds.len(), repair_infos.len());
@@ -37,15 +37,17 @@ use {
         },
         duplicate_shred::DuplicateShred,
         epoch_slots::EpochSlots,
+        gossip_capture::{CaptureKind, CaptureSender, GossipCapture},
         gossip_error::GossipError,
         ping_pong::{self, PingCache, Pong},
+        quic_endpoint::MAX_MESSAGE_SIZE,
         restart_crds_values::{
             RestartHeaviestFork, RestartLastVotedForkSlots, RestartLastVotedForkSlotsError,
         },
         socketaddr, socketaddr_any,
         weighted_shuffle::WeightedShuffle,
     },
-    bincode::{serialize, serialized_size},
+    bincode::{serialize, serialized_size, Options},
     crossbeam_channel::{Receiver, RecvTimeoutError, Sender},
     itertools::Itertools,
     rand::{seq::SliceRandom, thread_rng, CryptoRng, Rng},
@@ -142,7 +144,7 @@ pub(crate) const CRDS_UNIQUE_PUBKEY_CAPACITY: usize = 8192;
 /// propagated through gossip (few types are exempted).
 const MIN_STAKE_FOR_GOSSIP: u64 = trezoa_sdk::native_token::LAMPORTS_PER_TRZ;
 /// Minimum number of staked nodes for enforcing stakes in gossip.
-const MIN_NUM_STAKED_NODES: usize = 500;
+pub(crate) const MIN_NUM_STAKED_NODES: usize = 500;
 
 #[derive(Debug, PartialEq, Eq, Error)]
 pub enum ClusterInfoError {
@@ -176,6 +178,10 @@ pub struct ClusterInfo {
     instance: RwLock<NodeInstance>,
     contact_info_path: PathBuf,
     socket_addr_space: SocketAddrSpace,
+    /// Records received push messages and pull responses while set
+    gossip_capture: Mutex<Option<GossipCapture>>,
+    /// Whether gossip_capture is set, checked before locking it
+    gossip_capture_running: AtomicBool,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, AbiExample)]
@@ -279,6 +285,18 @@ pub(crate) enum Protocol {
 }
 
 impl Protocol {
+    /// Identity of the node which sent the message.
+    pub(crate) fn sender(&self) -> Pubkey {
+        match self {
+            Protocol::PullRequest(_, caller) => caller.pubkey(),
+            Protocol::PullResponse(from, _) => *from,
+            Protocol::PushMessage(from, _) => *from,
+            Protocol::PruneMessage(from, _) => *from,
+            Protocol::PingMessage(ping) => ping.pubkey(),
+            Protocol::PongMessage(pong) => *pong.from(),
+        }
+    }
+
     fn par_verify(self, stats: &GossipStats) -> Option<Self> {
         match self {
             Protocol::PullRequest(_, ref caller) => {
@@ -369,7 +387,7 @@ impl Sanitize for Protocol {
 
 // Retains only CRDS values associated with nodes with enough stake.
 // (some crds types are exempted)
-fn retain_staked(values: &mut Vec<CrdsValue>, stakes: &HashMap<Pubkey, u64>) {
+pub(crate) fn retain_staked(values: &mut Vec<CrdsValue>, stakes: &HashMap<Pubkey, u64>) {
     values.retain(|value| {
         match value.data {
             CrdsData::ContactInfo(_) => true,
@@ -424,6 +442,8 @@ impl ClusterInfo {
             contact_info_path: PathBuf::default(),
             contact_save_interval: 0, // disabled
             socket_addr_space,
+            gossip_capture: Mutex::default(),
+            gossip_capture_running: AtomicBool::default(),
         };
         me.insert_self();
         me.push_self();
@@ -438,6 +458,43 @@ impl ClusterInfo {
         &self.socket_addr_space
     }
 
+    /// Start recording received push messages and pull responses to the
+    /// file, replacing any capture already running
+    pub fn start_gossip_capture(&self, path: &Path) -> Result<(), GossipError> {
+        let capture = GossipCapture::start(path)?;
+        let mut gossip_capture = self.gossip_capture.lock().unwrap();
+        let running = gossip_capture.replace(capture);
+        self.gossip_capture_running.store(true, Ordering::Relaxed);
+        drop(gossip_capture);
+        if let Some(capture) = running {
+            capture.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Stop the running capture, returning the number of messages recorded
+    pub fn stop_gossip_capture(&self) -> Result<Option<usize>, GossipError> {
+        let mut gossip_capture = self.gossip_capture.lock().unwrap();
+        let capture = gossip_capture.take();
+        self.gossip_capture_running.store(false, Ordering::Relaxed);
+        drop(gossip_capture);
+        capture.map(GossipCapture::finish).transpose()
+    }
+
+    fn gossip_capture_sender(
+        &self,
+        now: u64,
+        stakes: &Arc<HashMap<Pubkey, u64>>,
+        epoch_duration: Duration,
+    ) -> Option<CaptureSender> {
+        if !self.gossip_capture_running.load(Ordering::Relaxed) {
+            return None;
+        }
+        let mut gossip_capture = self.gossip_capture.lock().unwrap();
+        let capture = gossip_capture.as_mut()?;
+        Some(capture.sender(now, stakes, epoch_duration))
+    }
+
     fn push_self(&self) {
         let now = timestamp();
         let node = {
@@ -1271,6 +1328,35 @@ impl ClusterInfo {
             .collect()
     }
 
+    /// Returns the crds values of the given `CrdsData::name` and origin, or
+    /// all of them if unspecified, with the local time they were inserted at.
+    /// Values are ordered by that time; `offset` of them are skipped and at
+    /// most `limit` returned, along with the number of values which matched.
+    pub fn get_crds_values(
+        &self,
+        name: Option<&str>,
+        origin: Option<&Pubkey>,
+        offset: usize,
+        limit: usize,
+    ) -> (Vec<(CrdsValue, /*local_timestamp:*/ u64)>, usize) {
+        let gossip_crds = self.gossip.crds.read().unwrap();
+        let entries: Box<dyn Iterator<Item = _>> = match origin {
+            Some(origin) => Box::new(gossip_crds.get_records(origin)),
+            None => Box::new(gossip_crds.values()),
+        };
+        let mut entries: Vec<_> = entries
+            .filter(|entry| name.map_or(true, |name| entry.value.data.name() == name))
+            .collect();
+        entries.sort_unstable_by_key(|entry| (entry.local_timestamp, entry.value_hash));
+        let values = entries
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|entry| (entry.value.clone(), entry.local_timestamp))
+            .collect();
+        (values, entries.len())
+    }
+
     pub fn get_node_version(&self, pubkey: &Pubkey) -> Option<trezoa_version::LegacyVersion2> {
         let gossip_crds = self.gossip.crds.read().unwrap();
         if let Some(version) = gossip_crds.get::<&Version>(*pubkey) {
@@ -1319,6 +1405,36 @@ impl ClusterInfo {
             .collect()
     }
 
+    /// Nodes which advertise a QUIC gossip socket, keyed by their UDP gossip
+    /// address, along with their identity and QUIC gossip address.
+    pub fn gossip_quic_peers(&self) -> HashMap<SocketAddr, (Pubkey, SocketAddr)> {
+        let me = self.id();
+        let gossip_crds = self.gossip.crds.read().unwrap();
+        gossip_crds
+            .get_nodes_contact_info()
+            .filter(|node| node.pubkey() != &me)
+            .filter_map(|node| {
+                let node = gossip_crds.get::<&ContactInfo>(*node.pubkey())?;
+                let gossip = node.gossip().ok()?;
+                let gossip_quic = node.gossip_quic().ok()?;
+                (self.socket_addr_space.check(&gossip)
+                    && self.socket_addr_space.check(&gossip_quic))
+                .then_some((gossip, (*node.pubkey(), gossip_quic)))
+            })
+            .collect()
+    }
+
+    /// UDP gossip addresses of the known nodes, keyed by their identity.
+    pub(crate) fn gossip_addrs(&self) -> HashMap<Pubkey, SocketAddr> {
+        let me = self.id();
+        let gossip_crds = self.gossip.crds.read().unwrap();
+        gossip_crds
+            .get_nodes_contact_info()
+            .filter(|node| node.pubkey() != &me)
+            .filter_map(|node| Some((*node.pubkey(), node.gossip().ok()?)))
+            .collect()
+    }
+
     /// all validators that have a valid tvu port regardless of `shred_version`.
     pub fn all_tvu_peers(&self) -> Vec<LegacyContactInfo> {
         let self_pubkey = self.id();
@@ -2382,7 +2498,7 @@ impl ClusterInfo {
         thread_pool: &ThreadPool,
         recycler: &PacketBatchRecycler,
         response_sender: &PacketBatchSender,
-        stakes: &HashMap<Pubkey, u64>,
+        stakes: &Arc<HashMap<Pubkey, u64>>,
         _feature_set: Option<&FeatureSet>,
         epoch_duration: Duration,
         should_check_duplicate_instance: bool,
@@ -2431,17 +2547,25 @@ impl ClusterInfo {
         let mut prune_messages = vec![];
         let mut ping_messages = vec![];
         let mut pong_messages = vec![];
+        let now = timestamp();
+        let capture_sender = self.gossip_capture_sender(now, stakes, epoch_duration);
         for (from_addr, packet) in packets {
             match packet {
                 Protocol::PullRequest(filter, caller) => {
                     pull_requests.push((from_addr, filter, caller))
                 }
-                Protocol::PullResponse(_, mut data) => {
+                Protocol::PullResponse(from, mut data) => {
                     check_duplicate_instance(&data)?;
+                    if let Some(sender) = &capture_sender {
+                        sender.record(now, CaptureKind::PullResponse, &from, &data);
+                    }
                     pull_responses.append(&mut data);
                 }
                 Protocol::PushMessage(from, data) => {
                     check_duplicate_instance(&data)?;
+                    if let Some(sender) = &capture_sender {
+                        sender.record(now, CaptureKind::PushMessage, &from, &data);
+                    }
                     push_messages.push((from, data));
                 }
                 Protocol::PruneMessage(_from, data) => prune_messages.push(data),
@@ -2449,6 +2573,7 @@ impl ClusterInfo {
                 Protocol::PongMessage(pong) => pong_messages.push((from_addr, pong)),
             }
         }
+        drop(capture_sender);
         if self.require_stake_for_gossip(stakes) {
             retain_staked(&mut pull_responses, stakes);
             for (_, data) in &mut push_messages {
@@ -2557,6 +2682,22 @@ impl ClusterInfo {
         Ok(sender.send(packets)?)
     }
 
+    // Deserializes, sanitizes and verifies a message received over QUIC, which
+    // unlike a packet received from the socket may exceed PACKET_DATA_SIZE.
+    pub(crate) fn verify_quic_message(&self, bytes: &[u8]) -> Option<Protocol> {
+        self.stats.packets_received_count.add_relaxed(1);
+        let protocol: Protocol = bincode::options()
+            .with_limit(MAX_MESSAGE_SIZE as u64)
+            .with_fixint_encoding()
+            .reject_trailing_bytes()
+            .deserialize(bytes)
+            .ok()?;
+        protocol.sanitize().ok()?;
+        let protocol = protocol.par_verify(&self.stats)?;
+        self.stats.packets_received_verified_count.add_relaxed(1);
+        Some(protocol)
+    }
+
     /// Process messages from the network
     fn run_listen(
         &self,
@@ -2751,6 +2892,9 @@ fn get_epoch_duration(bank_forks: Option<&RwLock<BankForks>>, stats: &GossipStat
 #[derive(Debug)]
 pub struct Sockets {
     pub gossip: UdpSocket,
+    /// Socket of the optional QUIC gossip transport, only bound by
+    /// `Node::bind_gossip_quic` for nodes which enable it.
+    pub gossip_quic: Option<UdpSocket>,
     pub ip_echo: Option<TcpListener>,
     pub tvu: Vec<UdpSocket>,
     pub tvu_quic: UdpSocket,
@@ -2844,6 +2988,7 @@ impl Node {
             info,
             sockets: Sockets {
                 gossip,
+                gossip_quic: None,
                 ip_echo: Some(ip_echo),
                 tvu: vec![tvu],
                 tvu_quic,
@@ -2882,6 +3027,13 @@ impl Node {
         bind_in_range(bind_ip_addr, port_range).expect("Failed to bind")
     }
 
+    /// Bind the socket of the optional QUIC gossip transport in `port_range`,
+    /// which then needs to be one port wider than the minimum.
+    pub fn bind_gossip_quic(&mut self, bind_ip_addr: IpAddr, port_range: PortRange) {
+        let (_, gossip_quic) = Self::bind(bind_ip_addr, port_range);
+        self.sockets.gossip_quic = Some(gossip_quic);
+    }
+
     pub fn new_single_bind(
         pubkey: &Pubkey,
         gossip_addr: &SocketAddr,
@@ -2941,6 +3093,7 @@ impl Node {
             info,
             sockets: Sockets {
                 gossip,
+                gossip_quic: None,
                 ip_echo: Some(ip_echo),
                 tvu: vec![tvu],
                 tvu_quic,
@@ -3032,6 +3185,7 @@ impl Node {
             info,
             sockets: Sockets {
                 gossip,
+                gossip_quic: None,
                 tvu: tvu_sockets,
                 tvu_quic,
                 tpu: tpu_sockets,
@@ -3155,6 +3309,7 @@ mod tests {
             crds_gossip_pull::tests::MIN_NUM_BLOOM_FILTERS,
             crds_value::{AccountsHashes, CrdsValue, CrdsValueLabel, Vote as CrdsVote},
             duplicate_shred::{self, tests::new_rand_shred, MAX_DUPLICATE_SHREDS},
+            gossip_capture::{CaptureRecord, GossipCaptureReader},
         },
         itertools::izip,
         trezoa_ledger::shred::Shredder,
@@ -3340,6 +3495,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_gossip_capture() {
+        let keypair = Arc::new(Keypair::new());
+        let cluster_info = ClusterInfo::new(
+            ContactInfo::new_localhost(&keypair.pubkey(), timestamp()),
+            keypair,
+            SocketAddrSpace::Unspecified,
+        );
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("gossip.capture");
+        cluster_info.start_gossip_capture(&path).unwrap();
+
+        let remote = Keypair::new();
+        let values = test_crds_values(remote.pubkey());
+        let packets = VecDeque::from([
+            (
+                socketaddr!(Ipv4Addr::LOCALHOST, 8000),
+                Protocol::PushMessage(remote.pubkey(), values.clone()),
+            ),
+            (
+                socketaddr!(Ipv4Addr::LOCALHOST, 8000),
+                Protocol::PullResponse(remote.pubkey(), values.clone()),
+            ),
+        ]);
+        let stakes = Arc::new(HashMap::from([(remote.pubkey(), 1)]));
+        let thread_pool = ThreadPoolBuilder::new().build().unwrap();
+        let (response_sender, _response_receiver) = crossbeam_channel::unbounded();
+        cluster_info
+            .process_packets(
+                packets,
+                &thread_pool,
+                &PacketBatchRecycler::default(),
+                &response_sender,
+                &stakes,
+                None, // feature_set
+                Duration::from_secs(3600),
+                false, // should_check_duplicate_instance
+            )
+            .unwrap();
+        assert_eq!(cluster_info.stop_gossip_capture().unwrap(), Some(2));
+        assert_eq!(cluster_info.stop_gossip_capture().unwrap(), None);
+
+        let records: Vec<_> = GossipCaptureReader::open(&path)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        // The stakes are recorded ahead of the messages processed with them
+        match &records[0] {
+            CaptureRecord::Stakes(captured) => {
+                assert_eq!(captured.stakes, *stakes);
+                assert_eq!(captured.epoch_duration, Duration::from_secs(3600));
+            }
+            record => panic!("unexpected record: {record:?}"),
+        }
+        let kinds = [CaptureKind::PushMessage, CaptureKind::PullResponse];
+        for (record, kind) in records[1..].iter().zip(kinds) {
+            let CaptureRecord::Message(message) = record else {
+                panic!("unexpected record: {record:?}");
+            };
+            assert_eq!(message.kind, kind);
+            assert_eq!(message.from, remote.pubkey());
+            assert_eq!(message.values, values);
+        }
+    }
+
     fn test_crds_values(pubkey: Pubkey) -> Vec<CrdsValue> {
         let entrypoint = LegacyContactInfo::new_localhost(&pubkey, timestamp());
         let entrypoint_crdsvalue = CrdsValue::new_unsigned(CrdsData::LegacyContactInfo(entrypoint));
@@ -3574,7 +3795,7 @@ mod tests {
     #[test]
     fn new_with_external_ip_test_random() {
         let ip = Ipv4Addr::LOCALHOST;
-        let node = Node::new_with_external_ip(
+        let mut node = Node::new_with_external_ip(
             &trezoa_sdk::pubkey::new_rand(),
             &socketaddr!(ip, 0),
             VALIDATOR_PORT_RANGE,
@@ -3584,6 +3805,18 @@ mod tests {
         );
 
         check_node_sockets(&node, IpAddr::V4(ip), VALIDATOR_PORT_RANGE);
+
+        // The QUIC gossip socket is only bound when enabled.
+        assert!(node.sockets.gossip_quic.is_none());
+        node.bind_gossip_quic(IpAddr::V4(ip), VALIDATOR_PORT_RANGE);
+        let gossip_quic_port = node
+            .sockets
+            .gossip_quic
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        assert!((VALIDATOR_PORT_RANGE.0..VALIDATOR_PORT_RANGE.1).contains(&gossip_quic_port));
     }
 
     #[test]
@@ -4211,6 +4444,68 @@ mod tests {
         assert_eq!(cluster_info.repair_peers(5).len(), 5);
     }
 
+    #[test]
+    fn test_gossip_quic_peers() {
+        let node_keypair = Arc::new(Keypair::new());
+        let cluster_info = ClusterInfo::new(
+            ContactInfo::new_localhost(&node_keypair.pubkey(), timestamp()),
+            node_keypair,
+            SocketAddrSpace::Unspecified,
+        );
+        // Nodes which do not advertise a QUIC gossip socket are excluded.
+        let other_node = ContactInfo::new_localhost(&trezoa_sdk::pubkey::new_rand(), timestamp());
+        cluster_info.insert_info(other_node);
+        assert!(cluster_info.gossip_quic_peers().is_empty());
+        let mut quic_node =
+            ContactInfo::new_localhost(&trezoa_sdk::pubkey::new_rand(), timestamp());
+        quic_node
+            .set_gossip_quic(socketaddr!("127.0.0.1:1235"))
+            .unwrap();
+        cluster_info.insert_info(quic_node.clone());
+        assert_eq!(
+            cluster_info.gossip_quic_peers(),
+            HashMap::from([(
+                quic_node.gossip().unwrap(),
+                (*quic_node.pubkey(), socketaddr!("127.0.0.1:1235"))
+            )])
+        );
+    }
+
+    #[test]
+    fn test_verify_quic_message() {
+        let mut rng = rand::thread_rng();
+        let node_keypair = Arc::new(Keypair::new());
+        let cluster_info = ClusterInfo::new(
+            ContactInfo::new_localhost(&node_keypair.pubkey(), timestamp()),
+            node_keypair,
+            SocketAddrSpace::Unspecified,
+        );
+        // Messages received over QUIC may exceed the size of a packet.
+        let mut crds_values = Vec::new();
+        while serialized_size(&crds_values).unwrap() <= PACKET_DATA_SIZE as u64 {
+            crds_values.push(CrdsValue::new_rand(&mut rng, None));
+        }
+        let pull_response = Protocol::PullResponse(Pubkey::new_unique(), crds_values);
+        let bytes = serialize(&pull_response).unwrap();
+        assert!(bytes.len() > PACKET_DATA_SIZE);
+        assert_matches!(
+            cluster_info.verify_quic_message(&bytes),
+            Some(Protocol::PullResponse(..))
+        );
+        // Messages with trailing bytes, or exceeding the maximum size, are not.
+        let mut trailing = bytes.clone();
+        trailing.push(0u8);
+        assert!(cluster_info.verify_quic_message(&trailing).is_none());
+        let Protocol::PullResponse(from, mut crds_values) = pull_response else {
+            unreachable!();
+        };
+        while serialized_size(&crds_values).unwrap() <= MAX_MESSAGE_SIZE as u64 {
+            crds_values.push(CrdsValue::new_rand(&mut rng, None));
+        }
+        let bytes = serialize(&Protocol::PullResponse(from, crds_values)).unwrap();
+        assert!(cluster_info.verify_quic_message(&bytes).is_none());
+    }
+
     #[test]
     fn test_max_bloom_size() {
         // check that the constant fits into the dynamic size
@@ -1,5 +1,6 @@
 use {
     crate::{
+        contact_info::ContactInfo,
         crds::VersionedCrdsValue,
         crds_value::{
             CrdsData, CrdsValue, CrdsValueLabel, LegacyVersion, LowestSlot, SnapshotHashes, Version,
@@ -52,6 +53,7 @@ impl_crds_entry!(CrdsValue, |entry| Some(&entry?.value));
 impl_crds_entry!(VersionedCrdsValue, |entry| entry);
 
 // Lookup by Pubkey.
+impl_crds_entry!(ContactInfo, CrdsData::ContactInfo(node), node);
 impl_crds_entry!(LegacyContactInfo, CrdsData::LegacyContactInfo(node), node);
 impl_crds_entry!(LegacyVersion, CrdsData::LegacyVersion(version), version);
 impl_crds_entry!(LowestSlot, CrdsData::LowestSlot(_, slot), slot);
@@ -102,6 +104,9 @@ mod tests {
             assert_eq!(crds.get::<&VersionedCrdsValue>(&key).unwrap().value, *entry);
             let key = entry.pubkey();
             match &entry.data {
+                CrdsData::ContactInfo(node) => {
+                    assert_eq!(crds.get::<&ContactInfo>(key), Some(node))
+                }
                 CrdsData::LegacyContactInfo(node) => {
                     assert_eq!(crds.get::<&LegacyContactInfo>(key), Some(node))
                 }
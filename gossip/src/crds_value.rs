@@ -27,6 +27,8 @@ use {
         collections::{hash_map::Entry, BTreeSet, HashMap},
         fmt,
     },
+    strum::VariantNames,
+    strum_macros::{EnumVariantNames, IntoStaticStr},
 };
 
 pub const MAX_WALLCLOCK: u64 = 1_000_000_000_000_000;
@@ -81,7 +83,18 @@ impl Signable for CrdsValue {
 /// * Merge Strategy - Latest wallclock is picked
 /// * LowestSlot index is deprecated
 #[allow(clippy::large_enum_variant)]
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, AbiExample, AbiEnumVisitor)]
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+    Eq,
+    AbiExample,
+    AbiEnumVisitor,
+    EnumVariantNames,
+    IntoStaticStr,
+)]
 pub enum CrdsData {
     LegacyContactInfo(LegacyContactInfo),
     Vote(VoteIndex, Vote),
@@ -148,6 +161,13 @@ pub(crate) fn new_rand_timestamp<R: Rng>(rng: &mut R) -> u64 {
 }
 
 impl CrdsData {
+    /// Names of the variants, as returned by `CrdsData::name`
+    pub const NAMES: &'static [&'static str] = <Self as VariantNames>::VARIANTS;
+
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+
     /// New random CrdsData for tests and benchmarks.
     fn new_rand<R: Rng>(rng: &mut R, pubkey: Option<Pubkey>) -> CrdsData {
         let kind = rng.gen_range(0..9);
@@ -762,6 +782,17 @@ mod test {
         assert_eq!(v.label(), CrdsValueLabel::LowestSlot(key));
     }
 
+    #[test]
+    fn test_crds_data_name() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..64 {
+            let data = CrdsData::new_rand(&mut rng, None);
+            let name = data.name();
+            assert!(CrdsData::NAMES.contains(&name));
+            assert!(format!("{data:?}").starts_with(&format!("{name}(")));
+        }
+    }
+
     #[test]
     fn test_lowest_slot_sanitize() {
         let ls = LowestSlot::new(Pubkey::default(), 0, 0);
@@ -0,0 +1,709 @@
+//! Recording and replaying the gossip traffic a node receives
+//!
+//! While a capture is running, `ClusterInfo` hands every push message and
+//! pull response it receives, with the time it was received, to a writer
+//! thread which appends them to a capture file. The stakes and epoch duration
+//! the node filtered and timed out values with are recorded whenever they
+//! change. Replaying the file into a fresh `CrdsGossip` reproduces the state
+//! of the node's crds table deterministically, since the values are inserted
+//! with the timestamps they were received at and the same stakes, and reports
+//! how much of the traffic was redundant or stale and which origins it came
+//! from.
+//!
+//! A capture file starts with `CAPTURE_MAGIC`, followed by one frame per
+//! record: its length as a little endian u32 and the bincode serialized
+//! `CaptureRecord`.
+
+use {
+    crate::{
+        cluster_info::{retain_staked, MIN_NUM_STAKED_NODES},
+        crds::VersionedCrdsValue,
+        crds_gossip::CrdsGossip,
+        crds_gossip_pull::ProcessPullStats,
+        crds_value::CrdsValue,
+        gossip_error::GossipError,
+    },
+    bincode::serialize,
+    crossbeam_channel::{Receiver, Sender, TrySendError},
+    trezoa_sdk::{
+        hash::{hash, Hash},
+        pubkey::Pubkey,
+    },
+    std::{
+        collections::HashMap,
+        fs::File,
+        io::{self, BufReader, BufWriter, Read, Write},
+        path::Path,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread::{Builder, JoinHandle},
+        time::Duration,
+    },
+};
+
+/// Header identifying a gossip capture file and its format version
+pub const CAPTURE_MAGIC: &[u8; 8] = b"GSPCAP01";
+// Upper bound on the size of a frame, guarding against corrupt lengths
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+// Records queued for the writer thread, beyond which messages are dropped
+const CAPTURE_CHANNEL_CAPACITY: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum CaptureKind {
+    PushMessage,
+    PullResponse,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CapturedMessage {
+    /// Local time, in milliseconds, the message was received at
+    pub timestamp: u64,
+    pub kind: CaptureKind,
+    /// Node which sent the message
+    pub from: Pubkey,
+    pub values: Vec<CrdsValue>,
+}
+
+/// Stakes and epoch duration the node processed the following messages with
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CapturedStakes {
+    /// Local time, in milliseconds, the stakes were first used at
+    pub timestamp: u64,
+    pub epoch_duration: Duration,
+    pub stakes: HashMap<Pubkey, u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CaptureRecord {
+    Stakes(CapturedStakes),
+    Message(CapturedMessage),
+}
+
+pub struct GossipCaptureWriter {
+    writer: BufWriter<File>,
+    num_messages: usize,
+}
+
+impl GossipCaptureWriter {
+    /// Create the capture file, truncating any existing one
+    pub fn create(path: &Path) -> Result<Self, GossipError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(CAPTURE_MAGIC)?;
+        Ok(Self {
+            writer,
+            num_messages: 0,
+        })
+    }
+
+    pub fn record(&mut self, record: &CaptureRecord) -> Result<(), GossipError> {
+        let frame = serialize(record)?;
+        let size = u32::try_from(frame.len())
+            .ok()
+            .filter(|&size| size as usize <= MAX_FRAME_SIZE)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "message too large"))?;
+        self.writer.write_all(&size.to_le_bytes())?;
+        self.writer.write_all(&frame)?;
+        if let CaptureRecord::Message(_) = record {
+            self.num_messages += 1;
+        }
+        Ok(())
+    }
+
+    pub fn num_messages(&self) -> usize {
+        self.num_messages
+    }
+
+    /// Flush the capture file, returning the number of messages recorded
+    pub fn finish(mut self) -> Result<usize, GossipError> {
+        self.writer.flush()?;
+        Ok(self.num_messages)
+    }
+}
+
+/// A running capture, whose records are written by a separate thread so
+/// that gossip processing never waits on the file
+pub(crate) struct GossipCapture {
+    sender: CaptureSender,
+    // Stakes last recorded, kept alive so that their address isn't reused
+    stakes: Option<(Arc<HashMap<Pubkey, u64>>, Duration)>,
+    thread: JoinHandle<Result<usize, GossipError>>,
+}
+
+#[derive(Clone)]
+pub(crate) struct CaptureSender {
+    sender: Sender<CaptureRecord>,
+    num_dropped: Arc<AtomicUsize>,
+}
+
+impl GossipCapture {
+    pub(crate) fn start(path: &Path) -> Result<Self, GossipError> {
+        let writer = GossipCaptureWriter::create(path)?;
+        let (sender, receiver) = crossbeam_channel::bounded(CAPTURE_CHANNEL_CAPACITY);
+        let thread = Builder::new()
+            .name("trzGossipCap".to_string())
+            .spawn(move || write_capture(writer, receiver))?;
+        Ok(Self {
+            sender: CaptureSender {
+                sender,
+                num_dropped: Arc::default(),
+            },
+            stakes: None,
+            thread,
+        })
+    }
+
+    /// Returns the sender for the next batch of messages, recording the
+    /// stakes first if they changed since the last batch
+    pub(crate) fn sender(
+        &mut self,
+        timestamp: u64,
+        stakes: &Arc<HashMap<Pubkey, u64>>,
+        epoch_duration: Duration,
+    ) -> CaptureSender {
+        let unchanged = self.stakes.as_ref().is_some_and(|(recorded, duration)| {
+            Arc::ptr_eq(recorded, stakes) && *duration == epoch_duration
+        });
+        if !unchanged {
+            let record = CaptureRecord::Stakes(CapturedStakes {
+                timestamp,
+                epoch_duration,
+                stakes: HashMap::clone(stakes),
+            });
+            // Retried with the next batch if the writer is behind
+            if self.sender.sender.try_send(record).is_ok() {
+                self.stakes = Some((stakes.clone(), epoch_duration));
+            }
+        }
+        self.sender.clone()
+    }
+
+    /// Waits for the queued records to be written, returning the number of
+    /// messages recorded
+    pub(crate) fn finish(self) -> Result<usize, GossipError> {
+        let Self { sender, thread, .. } = self;
+        let num_dropped = sender.num_dropped.load(Ordering::Relaxed);
+        if num_dropped > 0 {
+            warn!("gossip capture dropped {num_dropped} messages");
+        }
+        drop(sender);
+        thread.join().unwrap()
+    }
+}
+
+impl CaptureSender {
+    /// Queues the message, dropping it if the writer is behind
+    pub(crate) fn record(
+        &self,
+        timestamp: u64,
+        kind: CaptureKind,
+        from: &Pubkey,
+        values: &[CrdsValue],
+    ) {
+        let record = CaptureRecord::Message(CapturedMessage {
+            timestamp,
+            kind,
+            from: *from,
+            values: values.to_vec(),
+        });
+        match self.sender.try_send(record) {
+            Ok(()) | Err(TrySendError::Disconnected(_)) => (),
+            Err(TrySendError::Full(_)) => {
+                self.num_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// Stops at the first record which can't be written, the capture's senders
+// then see a disconnected channel
+fn write_capture(
+    mut writer: GossipCaptureWriter,
+    receiver: Receiver<CaptureRecord>,
+) -> Result<usize, GossipError> {
+    for record in receiver {
+        if let Err(err) = writer.record(&record) {
+            error!(
+                "stopping gossip capture after {} messages: {err:?}",
+                writer.num_messages()
+            );
+            return Err(err);
+        }
+    }
+    writer.finish()
+}
+
+/// Iterates over the records of a capture
+pub struct GossipCaptureReader<R> {
+    reader: R,
+}
+
+impl GossipCaptureReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, GossipError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> GossipCaptureReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, GossipError> {
+        let mut magic = [0u8; CAPTURE_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != CAPTURE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a gossip capture").into());
+        }
+        Ok(Self { reader })
+    }
+
+    fn read_record(&mut self) -> Result<Option<CaptureRecord>, GossipError> {
+        let mut size = [0u8; 4];
+        // A capture ends at a frame boundary
+        match self.reader.read_exact(&mut size) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let size = u32::from_le_bytes(size) as usize;
+        if size > MAX_FRAME_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large").into());
+        }
+        let mut frame = vec![0u8; size];
+        self.reader.read_exact(&mut frame)?;
+        Ok(Some(bincode::deserialize(&frame)?))
+    }
+}
+
+impl<R: Read> Iterator for GossipCaptureReader<R> {
+    type Item = Result<CaptureRecord, GossipError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OriginStats {
+    pub num_values: usize,
+    pub num_inserted: usize,
+    pub num_duplicates: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReplayStats {
+    pub num_messages: usize,
+    pub num_push_messages: usize,
+    pub num_pull_responses: usize,
+    pub num_values: usize,
+    /// Values which updated the table
+    pub num_inserted: usize,
+    /// Values already in the table
+    pub num_duplicates: usize,
+    /// Values the table kept a newer version of, or which the gossip
+    /// filters dropped, e.g. for their wallclock
+    pub num_rejected: usize,
+    /// Values dropped because their origin isn't staked
+    pub num_unstaked: usize,
+    /// Milliseconds from the values' wallclock to when they were received
+    staleness: Vec<u64>,
+    /// Counts by the node which created the values
+    pub origins: HashMap<Pubkey, OriginStats>,
+}
+
+impl ReplayStats {
+    /// Staleness, in milliseconds, below which the given share of the values
+    /// were received
+    pub fn staleness_percentile(&self, percentile: f64) -> Option<u64> {
+        if self.staleness.is_empty() {
+            return None;
+        }
+        let mut staleness = self.staleness.clone();
+        staleness.sort_unstable();
+        let index = ((staleness.len() - 1) as f64 * percentile.clamp(0.0, 1.0)).round() as usize;
+        Some(staleness[index])
+    }
+
+    pub fn mean_staleness(&self) -> Option<u64> {
+        let sum: u64 = self.staleness.iter().sum();
+        sum.checked_div(self.staleness.len() as u64)
+    }
+
+    pub fn max_staleness(&self) -> Option<u64> {
+        self.staleness.iter().max().copied()
+    }
+
+    /// Origins sorted by the number of values they created, most first
+    pub fn top_origins(&self, count: usize) -> Vec<(Pubkey, OriginStats)> {
+        let mut origins: Vec<_> = self
+            .origins
+            .iter()
+            .map(|(origin, stats)| (*origin, stats.clone()))
+            .collect();
+        origins.sort_unstable_by(|(a, a_stats), (b, b_stats)| {
+            b_stats.num_values.cmp(&a_stats.num_values).then(a.cmp(b))
+        });
+        origins.truncate(count);
+        origins
+    }
+}
+
+/// Replays captured messages into a fresh crds table
+pub struct CaptureReplayer {
+    gossip: CrdsGossip,
+    // As recorded in the capture, until the first stakes record all values
+    // get the epoch long timeout and none are filtered for their stake
+    stakes: HashMap<Pubkey, u64>,
+    epoch_duration: Duration,
+    stats: ReplayStats,
+}
+
+impl CaptureReplayer {
+    pub fn new(epoch_duration: Duration) -> Self {
+        Self {
+            gossip: CrdsGossip::default(),
+            stakes: HashMap::new(),
+            epoch_duration,
+            stats: ReplayStats::default(),
+        }
+    }
+
+    pub fn replay(&mut self, record: CaptureRecord) {
+        match record {
+            CaptureRecord::Stakes(CapturedStakes {
+                timestamp: _,
+                epoch_duration,
+                stakes,
+            }) => {
+                self.stakes = stakes;
+                self.epoch_duration = epoch_duration;
+            }
+            CaptureRecord::Message(message) => self.replay_message(message),
+        }
+    }
+
+    fn replay_message(&mut self, message: CapturedMessage) {
+        let CapturedMessage {
+            timestamp,
+            kind,
+            from,
+            mut values,
+        } = message;
+        self.stats.num_messages += 1;
+        self.stats.num_values += values.len();
+        for value in &values {
+            self.stats
+                .staleness
+                .push(timestamp.saturating_sub(value.wallclock()));
+            self.stats
+                .origins
+                .entry(value.pubkey())
+                .or_default()
+                .num_values += 1;
+        }
+        // Same filtering as ClusterInfo::process_packets
+        if self.stakes.len() >= MIN_NUM_STAKED_NODES {
+            let num_values = values.len();
+            retain_staked(&mut values, &self.stakes);
+            self.stats.num_unstaked += num_values - values.len();
+        }
+        // Hashes of the values, and whether the table already had them
+        let hashes: Vec<(Hash, bool)> = {
+            let crds = self.gossip.crds.read().unwrap();
+            values
+                .iter()
+                .map(|value| {
+                    let value_hash = hash(&serialize(value).unwrap());
+                    let duplicate = crds
+                        .get::<&VersionedCrdsValue>(&value.label())
+                        .map(|entry| entry.value_hash == value_hash)
+                        .unwrap_or_default();
+                    (value_hash, duplicate)
+                })
+                .collect()
+        };
+        match kind {
+            CaptureKind::PushMessage => {
+                self.stats.num_push_messages += 1;
+                if !values.is_empty() {
+                    self.gossip
+                        .process_push_message(vec![(from, values.clone())], timestamp);
+                }
+            }
+            CaptureKind::PullResponse => {
+                self.stats.num_pull_responses += 1;
+                let timeouts =
+                    self.gossip
+                        .make_timeouts(Pubkey::default(), &self.stakes, self.epoch_duration);
+                let mut pull_stats = ProcessPullStats::default();
+                let (responses, expired, failed_inserts) = self.gossip.filter_pull_responses(
+                    &timeouts,
+                    values.clone(),
+                    timestamp,
+                    &mut pull_stats,
+                );
+                self.gossip.process_pull_responses(
+                    responses,
+                    expired,
+                    failed_inserts,
+                    timestamp,
+                    &mut pull_stats,
+                );
+            }
+        }
+        let crds = self.gossip.crds.read().unwrap();
+        for (value, (value_hash, duplicate)) in values.iter().zip(hashes) {
+            let origin = self.stats.origins.entry(value.pubkey()).or_default();
+            if duplicate {
+                self.stats.num_duplicates += 1;
+                origin.num_duplicates += 1;
+                continue;
+            }
+            let inserted = crds
+                .get::<&VersionedCrdsValue>(&value.label())
+                .map(|entry| entry.value_hash == value_hash)
+                .unwrap_or_default();
+            if inserted {
+                self.stats.num_inserted += 1;
+                origin.num_inserted += 1;
+            } else {
+                self.stats.num_rejected += 1;
+            }
+        }
+    }
+
+    /// Replay every record of the capture, stopping at the first one which
+    /// can't be read
+    pub fn replay_all<I>(&mut self, records: I) -> Result<(), GossipError>
+    where
+        I: IntoIterator<Item = Result<CaptureRecord, GossipError>>,
+    {
+        for record in records {
+            self.replay(record?);
+        }
+        Ok(())
+    }
+
+    pub fn gossip(&self) -> &CrdsGossip {
+        &self.gossip
+    }
+
+    pub fn stats(&self) -> &ReplayStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            crds_gossip_push::CRDS_GOSSIP_PUSH_MSG_TIMEOUT_MS,
+            crds_value::{CrdsData, LowestSlot},
+        },
+        trezoa_sdk::{
+            native_token::LAMPORTS_PER_TRZ,
+            signature::{Keypair, Signer},
+        },
+        tempfile::TempDir,
+    };
+
+    fn new_lowest_slot(keypair: &Keypair, lowest: u64, wallclock: u64) -> CrdsValue {
+        let data = CrdsData::LowestSlot(0, LowestSlot::new(keypair.pubkey(), lowest, wallclock));
+        CrdsValue::new_signed(data, keypair)
+    }
+
+    #[test]
+    fn test_capture_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("gossip.capture");
+        let keypair = Keypair::new();
+        let from = Pubkey::new_unique();
+        let records = vec![
+            CaptureRecord::Stakes(CapturedStakes {
+                timestamp: 1_000,
+                epoch_duration: Duration::from_secs(3600),
+                stakes: HashMap::from([(keypair.pubkey(), 42)]),
+            }),
+            CaptureRecord::Message(CapturedMessage {
+                timestamp: 1_000,
+                kind: CaptureKind::PushMessage,
+                from,
+                values: vec![new_lowest_slot(&keypair, 5, 900)],
+            }),
+            CaptureRecord::Message(CapturedMessage {
+                timestamp: 2_000,
+                kind: CaptureKind::PullResponse,
+                from,
+                values: vec![],
+            }),
+        ];
+        let mut writer = GossipCaptureWriter::create(&path).unwrap();
+        for record in &records {
+            writer.record(record).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), 2);
+
+        let read: Vec<_> = GossipCaptureReader::open(&path)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, records);
+
+        // Not a capture file
+        assert!(GossipCaptureReader::new(&b"not a capture"[..]).is_err());
+        // A truncated frame is an error
+        let bytes = std::fs::read(&path).unwrap();
+        let mut reader = GossipCaptureReader::new(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_gossip_capture_thread() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("gossip.capture");
+        let keypair = Keypair::new();
+        let from = Pubkey::new_unique();
+        let values = vec![new_lowest_slot(&keypair, 5, 900)];
+        let stakes = Arc::new(HashMap::from([(keypair.pubkey(), 42)]));
+        let epoch_duration = Duration::from_secs(3600);
+
+        let mut capture = GossipCapture::start(&path).unwrap();
+        let sender = capture.sender(1_000, &stakes, epoch_duration);
+        sender.record(1_000, CaptureKind::PushMessage, &from, &values);
+        // Unchanged stakes aren't recorded again
+        let sender = capture.sender(2_000, &stakes, epoch_duration);
+        sender.record(2_000, CaptureKind::PullResponse, &from, &values);
+        drop(sender);
+        assert_eq!(capture.finish().unwrap(), 2);
+
+        let records: Vec<_> = GossipCaptureReader::open(&path)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            records,
+            vec![
+                CaptureRecord::Stakes(CapturedStakes {
+                    timestamp: 1_000,
+                    epoch_duration,
+                    stakes: HashMap::clone(&stakes),
+                }),
+                CaptureRecord::Message(CapturedMessage {
+                    timestamp: 1_000,
+                    kind: CaptureKind::PushMessage,
+                    from,
+                    values: values.clone(),
+                }),
+                CaptureRecord::Message(CapturedMessage {
+                    timestamp: 2_000,
+                    kind: CaptureKind::PullResponse,
+                    from,
+                    values,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replay_stats() {
+        let keypair = Keypair::new();
+        let from = Pubkey::new_unique();
+        let now = CRDS_GOSSIP_PUSH_MSG_TIMEOUT_MS * 10;
+        let value = new_lowest_slot(&keypair, 5, now - 100);
+        let newer = new_lowest_slot(&keypair, 6, now);
+        let mut replayer = CaptureReplayer::new(Duration::from_secs(3600));
+        let push = |timestamp, values| {
+            Ok(CaptureRecord::Message(CapturedMessage {
+                timestamp,
+                kind: CaptureKind::PushMessage,
+                from,
+                values,
+            }))
+        };
+        replayer
+            .replay_all([
+                push(now, vec![value.clone()]),
+                // Same value again
+                Ok(CaptureRecord::Message(CapturedMessage {
+                    timestamp: now + 10,
+                    kind: CaptureKind::PullResponse,
+                    from,
+                    values: vec![value.clone()],
+                })),
+                push(now + 20, vec![newer.clone()]),
+                // Older than what the table holds
+                push(now + 30, vec![value]),
+            ])
+            .unwrap();
+
+        let stats = replayer.stats();
+        assert_eq!(stats.num_messages, 4);
+        assert_eq!(stats.num_push_messages, 3);
+        assert_eq!(stats.num_pull_responses, 1);
+        assert_eq!(stats.num_values, 4);
+        assert_eq!(stats.num_inserted, 2);
+        assert_eq!(stats.num_duplicates, 1);
+        assert_eq!(stats.num_rejected, 1);
+        assert_eq!(stats.num_unstaked, 0);
+        assert_eq!(stats.max_staleness(), Some(130));
+        assert_eq!(stats.mean_staleness(), Some((100 + 110 + 20 + 130) / 4));
+        assert_eq!(stats.staleness_percentile(0.0), Some(20));
+        assert_eq!(
+            stats.top_origins(1),
+            vec![(
+                keypair.pubkey(),
+                OriginStats {
+                    num_values: 4,
+                    num_inserted: 2,
+                    num_duplicates: 1,
+                }
+            )]
+        );
+
+        // The table holds the newest value
+        let crds = replayer.gossip().crds.read().unwrap();
+        assert_eq!(crds.get::<&CrdsValue>(&newer.label()), Some(&newer));
+    }
+
+    #[test]
+    fn test_replay_retains_staked() {
+        let staked = Keypair::new();
+        let unstaked = Keypair::new();
+        let from = Pubkey::new_unique();
+        let now = CRDS_GOSSIP_PUSH_MSG_TIMEOUT_MS * 10;
+        let values = vec![
+            new_lowest_slot(&staked, 5, now),
+            new_lowest_slot(&unstaked, 5, now),
+        ];
+        let stakes: HashMap<_, _> = std::iter::repeat_with(|| (Pubkey::new_unique(), 1))
+            .take(MIN_NUM_STAKED_NODES)
+            .chain([(staked.pubkey(), LAMPORTS_PER_TRZ)])
+            .collect();
+        let mut replayer = CaptureReplayer::new(Duration::from_secs(3600));
+        replayer
+            .replay_all([
+                Ok(CaptureRecord::Stakes(CapturedStakes {
+                    timestamp: now,
+                    epoch_duration: Duration::from_secs(3600),
+                    stakes,
+                })),
+                Ok(CaptureRecord::Message(CapturedMessage {
+                    timestamp: now,
+                    kind: CaptureKind::PushMessage,
+                    from,
+                    values: values.clone(),
+                })),
+            ])
+            .unwrap();
+
+        let stats = replayer.stats();
+        assert_eq!(stats.num_values, 2);
+        assert_eq!(stats.num_inserted, 1);
+        assert_eq!(stats.num_unstaked, 1);
+        assert_eq!(stats.origins[&unstaked.pubkey()].num_values, 1);
+        let crds = replayer.gossip().crds.read().unwrap();
+        assert_eq!(crds.get::<&CrdsValue>(&values[0].label()), Some(&values[0]));
+        assert_eq!(crds.get::<&CrdsValue>(&values[1].label()), None);
+    }
+}
@@ -1,15 +1,21 @@
 //! The `gossip_service` module implements the network control plane.
 
 use {
-    crate::{cluster_info::ClusterInfo, legacy_contact_info::LegacyContactInfo as ContactInfo},
-    crossbeam_channel::{unbounded, Sender},
+    crate::{
+        cluster_info::{ClusterInfo, Protocol},
+        legacy_contact_info::LegacyContactInfo as ContactInfo,
+    },
+    bytes::Bytes,
+    crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender},
+    itertools::Itertools,
+    lru::LruCache,
     rand::{thread_rng, Rng},
     trezoa_client::{
         connection_cache::ConnectionCache,
         rpc_client::RpcClient,
         tpu_client::{TpuClient, TpuClientConfig, TpuClientWrapper},
     },
-    trezoa_perf::recycler::Recycler,
+    trezoa_perf::{packet::PACKETS_PER_BATCH, recycler::Recycler},
     trezoa_runtime::bank_forks::BankForks,
     trezoa_sdk::{
         pubkey::Pubkey,
@@ -17,20 +23,34 @@ use {
     },
     trezoa_streamer::{
         socket::SocketAddrSpace,
-        streamer::{self, StreamerReceiveStats},
+        streamer::{self, PacketBatchReceiver, PacketBatchSender, StreamerReceiveStats},
     },
     std::{
-        collections::HashSet,
+        collections::{HashMap, HashSet},
         net::{SocketAddr, TcpListener, UdpSocket},
         sync::{
             atomic::{AtomicBool, Ordering},
-            Arc, RwLock,
+            Arc, Mutex, RwLock,
         },
-        thread::{self, sleep, JoinHandle},
+        thread::{self, sleep, Builder, JoinHandle},
         time::{Duration, Instant},
     },
+    tokio::sync::mpsc::{error::TrySendError, Sender as AsyncSender},
 };
 
+const GOSSIP_QUIC_PEERS_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+// Number of nodes not yet in the crds table whose responses are routed back
+// over QUIC.
+const GOSSIP_QUIC_REMOTES_CAPACITY: usize = 4096;
+const PACKET_COALESCE_DURATION: Duration = Duration::from_millis(1);
+
+/// Channels to the QUIC gossip endpoint: messages sent to and received from
+/// (identity, address) of the remote node.
+pub type GossipQuicEndpoint = (
+    AsyncSender<(Pubkey, SocketAddr, Bytes)>,
+    Receiver<(Pubkey, SocketAddr, Bytes)>,
+);
+
 pub struct GossipService {
     thread_hdls: Vec<JoinHandle<()>>,
 }
@@ -43,6 +63,7 @@ impl GossipService {
         gossip_validators: Option<HashSet<Pubkey>>,
         should_check_duplicate_instance: bool,
         stats_reporter_sender: Option<Sender<Box<dyn FnOnce() + Send>>>,
+        quic_endpoint: Option<GossipQuicEndpoint>,
         exit: Arc<AtomicBool>,
     ) -> Self {
         let (request_sender, request_receiver) = unbounded();
@@ -67,7 +88,7 @@ impl GossipService {
         let (consume_sender, listen_receiver) = unbounded();
         let t_socket_consume = cluster_info.clone().start_socket_consume_thread(
             request_receiver,
-            consume_sender,
+            consume_sender.clone(),
             exit.clone(),
         );
         let (response_sender, response_receiver) = unbounded();
@@ -78,10 +99,37 @@ impl GossipService {
             should_check_duplicate_instance,
             exit.clone(),
         );
-        let t_gossip =
-            cluster_info
-                .clone()
-                .gossip(bank_forks, response_sender, gossip_validators, exit);
+        let t_gossip = cluster_info.clone().gossip(
+            bank_forks,
+            response_sender,
+            gossip_validators,
+            exit.clone(),
+        );
+        // Packets to nodes which advertise a QUIC gossip socket are diverted
+        // to the QUIC endpoint, and the rest go out the UDP socket.
+        let (response_receiver, quic_thread_hdls) = match quic_endpoint {
+            None => (response_receiver, vec![]),
+            Some((quic_endpoint_sender, quic_endpoint_receiver)) => {
+                let (udp_sender, udp_receiver) = unbounded();
+                let quic_remotes =
+                    Arc::new(Mutex::new(LruCache::new(GOSSIP_QUIC_REMOTES_CAPACITY)));
+                let t_quic_sender = route_gossip_quic_packets(
+                    cluster_info.clone(),
+                    response_receiver,
+                    quic_endpoint_sender,
+                    udp_sender,
+                    quic_remotes.clone(),
+                );
+                let t_quic_receiver = receive_gossip_quic_messages(
+                    cluster_info.clone(),
+                    quic_endpoint_receiver,
+                    consume_sender,
+                    quic_remotes,
+                    exit,
+                );
+                (udp_receiver, vec![t_quic_sender, t_quic_receiver])
+            }
+        };
         let t_responder = streamer::responder(
             "Gossip",
             gossip_socket,
@@ -95,7 +143,10 @@ impl GossipService {
             t_socket_consume,
             t_listen,
             t_gossip,
-        ];
+        ]
+        .into_iter()
+        .chain(quic_thread_hdls)
+        .collect();
         Self { thread_hdls }
     }
 
@@ -107,6 +158,125 @@ impl GossipService {
     }
 }
 
+fn route_gossip_quic_packets(
+    cluster_info: Arc<ClusterInfo>,
+    receiver: PacketBatchReceiver,
+    quic_endpoint_sender: AsyncSender<(Pubkey, SocketAddr, Bytes)>,
+    udp_sender: PacketBatchSender,
+    quic_remotes: Arc<Mutex<LruCache<SocketAddr, Pubkey>>>,
+) -> JoinHandle<()> {
+    Builder::new()
+        .name("trzGossipQuicTx".to_string())
+        .spawn(move || {
+            let mut peers = cluster_info.gossip_quic_peers();
+            let mut peers_refreshed = Instant::now();
+            let mut num_packets_quic = 0usize;
+            let mut num_packets_udp_fallback = 0usize;
+            // Exits once the listen and gossip threads drop their senders.
+            for mut packet_batch in receiver.iter() {
+                if peers_refreshed.elapsed() > GOSSIP_QUIC_PEERS_REFRESH_INTERVAL {
+                    datapoint_info!(
+                        "gossip_quic_router",
+                        ("num_peers", peers.len(), i64),
+                        ("num_packets_quic", num_packets_quic, i64),
+                        ("num_packets_udp_fallback", num_packets_udp_fallback, i64),
+                    );
+                    peers = cluster_info.gossip_quic_peers();
+                    peers_refreshed = Instant::now();
+                    num_packets_quic = 0;
+                    num_packets_udp_fallback = 0;
+                }
+                for packet in packet_batch.iter_mut() {
+                    let socket_addr = packet.meta().socket_addr();
+                    let Some((pubkey, addr)) = peers.get(&socket_addr).copied().or_else(|| {
+                        let pubkey = quic_remotes.lock().unwrap().get(&socket_addr).copied()?;
+                        Some((pubkey, socket_addr))
+                    }) else {
+                        continue;
+                    };
+                    let Some(bytes) = packet.data(..).map(Bytes::copy_from_slice) else {
+                        continue;
+                    };
+                    // If the QUIC endpoint is backed up or shut down, the
+                    // packet still goes out the UDP socket.
+                    match quic_endpoint_sender.try_send((pubkey, addr, bytes)) {
+                        Ok(()) => {
+                            packet.meta_mut().set_discard(true);
+                            num_packets_quic += 1;
+                        }
+                        Err(TrySendError::Full(_) | TrySendError::Closed(_)) => {
+                            num_packets_udp_fallback += 1;
+                        }
+                    }
+                }
+                if udp_sender.send(packet_batch).is_err() {
+                    break;
+                }
+            }
+        })
+        .unwrap()
+}
+
+// Messages received over QUIC are stamped with the sender's UDP gossip address
+// rather than the address of its QUIC endpoint, so that responses, pings and
+// pongs are routed back to it over QUIC, or else reach its UDP gossip socket.
+// A node not yet in the crds table is stamped with the address authenticated
+// by the QUIC connection, which is remembered so that responses to it are
+// routed back over QUIC as well.
+fn receive_gossip_quic_messages(
+    cluster_info: Arc<ClusterInfo>,
+    receiver: Receiver<(Pubkey, SocketAddr, Bytes)>,
+    sender: Sender<Vec<(/*from:*/ SocketAddr, Protocol)>>,
+    quic_remotes: Arc<Mutex<LruCache<SocketAddr, Pubkey>>>,
+    exit: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    const RECV_TIMEOUT: Duration = Duration::from_secs(1);
+    Builder::new()
+        .name("trzGossipQuicRx".to_string())
+        .spawn(move || {
+            let mut gossip_addrs = cluster_info.gossip_addrs();
+            let mut gossip_addrs_refreshed = Instant::now();
+            while !exit.load(Ordering::Relaxed) {
+                if gossip_addrs_refreshed.elapsed() > GOSSIP_QUIC_PEERS_REFRESH_INTERVAL {
+                    gossip_addrs = cluster_info.gossip_addrs();
+                    gossip_addrs_refreshed = Instant::now();
+                }
+                let entry = match receiver.recv_timeout(RECV_TIMEOUT) {
+                    Ok(entry) => entry,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                };
+                let deadline = Instant::now() + PACKET_COALESCE_DURATION;
+                let entries = std::iter::once(entry).chain(
+                    std::iter::repeat_with(|| receiver.recv_deadline(deadline).ok()).while_some(),
+                );
+                let mut messages = Vec::new();
+                for (pubkey, addr, bytes) in entries.take(PACKETS_PER_BATCH) {
+                    let Some(protocol) = cluster_info.verify_quic_message(&bytes) else {
+                        continue;
+                    };
+                    // Drop messages claiming to be from a node other than the
+                    // one authenticated by the TLS certificate.
+                    if protocol.sender() != pubkey {
+                        continue;
+                    }
+                    let from = match gossip_addrs.get(&pubkey) {
+                        Some(&gossip_addr) => gossip_addr,
+                        None => {
+                            quic_remotes.lock().unwrap().put(addr, pubkey);
+                            addr
+                        }
+                    };
+                    messages.push((from, protocol));
+                }
+                if !messages.is_empty() && sender.send(messages).is_err() {
+                    return;
+                }
+            }
+        })
+        .unwrap()
+}
+
 /// Discover Validators in a cluster
 pub fn discover_cluster(
     entrypoint: &SocketAddr,
@@ -331,6 +501,7 @@ pub fn make_gossip_node(
         None,
         should_check_duplicate_instance,
         None,
+        None,
         exit,
     );
     (gossip_service, ip_echo, cluster_info)
@@ -343,8 +514,16 @@ mod tests {
         crate::{
             cluster_info::{ClusterInfo, Node},
             contact_info::ContactInfo,
+            quic_endpoint::{close_quic_endpoint, new_quic_endpoint},
+        },
+        std::{
+            iter::repeat_with,
+            net::Ipv4Addr,
+            sync::{
+                atomic::{AtomicBool, AtomicUsize},
+                Arc,
+            },
         },
-        std::sync::{atomic::AtomicBool, Arc},
     };
 
     #[test]
@@ -366,12 +545,113 @@ mod tests {
             None,
             true, // should_check_duplicate_instance
             None,
+            None, // quic_endpoint
             exit.clone(),
         );
         exit.store(true, Ordering::Relaxed);
         d.join().unwrap();
     }
 
+    #[test]
+    fn test_gossip_service_quic() {
+        const NUM_NODES: usize = 2;
+        const TIMEOUT: Duration = Duration::from_secs(60);
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(4)
+            .enable_all()
+            .build()
+            .unwrap();
+        let exit = Arc::new(AtomicBool::new(false));
+        let keypairs: Vec<Arc<Keypair>> = repeat_with(Keypair::new)
+            .map(Arc::new)
+            .take(NUM_NODES)
+            .collect();
+        // Number of pull responses each node has received over QUIC.
+        let num_pull_responses: Vec<Arc<AtomicUsize>> =
+            repeat_with(Arc::default).take(NUM_NODES).collect();
+        let mut endpoints = Vec::new();
+        let mut thread_hdls = Vec::new();
+        let mut nodes: Vec<Arc<ClusterInfo>> = Vec::new();
+        let mut services = Vec::new();
+        for (keypair, num_pull_responses) in keypairs.iter().zip(&num_pull_responses) {
+            let mut node = Node::new_localhost_with_pubkey(&keypair.pubkey());
+            let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            node.info
+                .set_gossip_quic(socket.local_addr().unwrap())
+                .unwrap();
+            let (endpoint_sender, endpoint_receiver) = unbounded();
+            let (endpoint, quic_endpoint_sender, join_handle) =
+                new_quic_endpoint(runtime.handle(), keypair, socket, endpoint_sender).unwrap();
+            // Forwards packets received over QUIC to the gossip service,
+            // counting the pull responses along the way.
+            let (quic_endpoint_receiver_sender, quic_endpoint_receiver) = unbounded();
+            let num_pull_responses = num_pull_responses.clone();
+            thread_hdls.push(thread::spawn(move || {
+                for (pubkey, addr, bytes) in endpoint_receiver.iter() {
+                    if let Ok(Protocol::PullResponse(..)) = bincode::deserialize(&bytes) {
+                        num_pull_responses.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if quic_endpoint_receiver_sender
+                        .send((pubkey, addr, bytes))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }));
+            let cluster_info = Arc::new(ClusterInfo::new(
+                node.info.clone(),
+                keypair.clone(),
+                SocketAddrSpace::Unspecified,
+            ));
+            if let Some(entrypoint) = nodes.first().map(|node| node.my_contact_info()) {
+                let entrypoint =
+                    crate::legacy_contact_info::LegacyContactInfo::try_from(&entrypoint).unwrap();
+                cluster_info.set_entrypoint(entrypoint);
+            }
+            services.push(GossipService::new(
+                &cluster_info,
+                None,
+                node.sockets.gossip,
+                None,
+                true, // should_check_duplicate_instance
+                None,
+                Some((quic_endpoint_sender, quic_endpoint_receiver)),
+                exit.clone(),
+            ));
+            endpoints.push((endpoint, join_handle));
+            nodes.push(cluster_info);
+        }
+        // Each node discovers the other, and the responses to pull requests
+        // are routed back over QUIC.
+        let now = Instant::now();
+        while now.elapsed() < TIMEOUT
+            && !(nodes
+                .iter()
+                .all(|node| node.gossip_peers().len() == NUM_NODES - 1)
+                && num_pull_responses[1].load(Ordering::Relaxed) > 0)
+        {
+            sleep(Duration::from_millis(100));
+        }
+        for (node, other) in nodes.iter().zip(nodes.iter().rev()) {
+            let peers = node.gossip_peers();
+            assert_eq!(peers.len(), 1);
+            assert_eq!(peers[0].pubkey(), &other.id());
+        }
+        assert!(num_pull_responses[1].load(Ordering::Relaxed) > 0);
+        exit.store(true, Ordering::Relaxed);
+        for service in services {
+            service.join().unwrap();
+        }
+        for (endpoint, join_handle) in endpoints {
+            close_quic_endpoint(&endpoint);
+            runtime.block_on(join_handle).unwrap();
+        }
+        for thread_hdl in thread_hdls {
+            thread_hdl.join().unwrap();
+        }
+    }
+
     #[test]
     fn test_gossip_services_spy() {
         const TIMEOUT: Duration = Duration::from_secs(5);
@@ -17,12 +17,14 @@ pub mod duplicate_shred;
 pub mod duplicate_shred_handler;
 pub mod duplicate_shred_listener;
 pub mod epoch_slots;
+pub mod gossip_capture;
 pub mod gossip_error;
 pub mod gossip_service;
 #[macro_use]
 pub mod legacy_contact_info;
 pub mod ping_pong;
 mod push_active_set;
+pub mod quic_endpoint;
 mod received_cache;
 pub mod restart_crds_values;
 pub mod weighted_shuffle;
@@ -11,13 +11,19 @@ use {
         input_validators::{is_keypair_or_ask_keyword, is_port, is_pubkey},
     },
     trezoa_gossip::{
-        gossip_service::discover, legacy_contact_info::LegacyContactInfo as ContactInfo,
+        gossip_capture::{CaptureReplayer, GossipCaptureReader},
+        gossip_service::discover,
+        legacy_contact_info::LegacyContactInfo as ContactInfo,
+    },
+    trezoa_sdk::{
+        clock::{DEFAULT_MS_PER_SLOT, DEFAULT_SLOTS_PER_EPOCH},
+        pubkey::Pubkey,
     },
-    trezoa_sdk::pubkey::Pubkey,
     trezoa_streamer::socket::SocketAddrSpace,
     std::{
         error,
         net::{IpAddr, Ipv4Addr, SocketAddr},
+        path::Path,
         process::exit,
         time::Duration,
     },
@@ -154,6 +160,27 @@ fn parse_matches() -> ArgMatches<'static> {
                         .help("Maximum time to wait in seconds [default: wait forever]"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("replay-capture")
+                .about("Replay a gossip capture into an empty crds table")
+                .setting(AppSettings::DisableVersion)
+                .arg(
+                    Arg::with_name("capture")
+                        .index(1)
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Capture recorded by a node's gossip capture"),
+                )
+                .arg(
+                    Arg::with_name("num_origins")
+                        .long("num-origins")
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .default_value("10")
+                        .help("Number of origins to report, by the values they created"),
+                ),
+        )
         .get_matches()
 }
 
@@ -325,6 +352,60 @@ fn process_rpc_url(
     Ok(())
 }
 
+fn process_replay_capture(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    let path = value_t_or_exit!(matches, "capture", String);
+    let num_origins = value_t_or_exit!(matches, "num_origins", usize);
+    let epoch_duration = Duration::from_millis(DEFAULT_SLOTS_PER_EPOCH * DEFAULT_MS_PER_SLOT);
+    let mut replayer = CaptureReplayer::new(epoch_duration);
+    replayer.replay_all(GossipCaptureReader::open(Path::new(&path))?)?;
+
+    let stats = replayer.stats();
+    println!(
+        "messages: {} ({} push, {} pull responses)",
+        stats.num_messages, stats.num_push_messages, stats.num_pull_responses
+    );
+    println!(
+        "values: {}, inserted: {}, duplicates: {}, rejected: {}, unstaked: {}",
+        stats.num_values,
+        stats.num_inserted,
+        stats.num_duplicates,
+        stats.num_rejected,
+        stats.num_unstaked
+    );
+    let staleness = |staleness: Option<u64>| {
+        staleness
+            .map(|ms| format!("{ms}ms"))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    println!(
+        "staleness: mean {}, p50 {}, p90 {}, p99 {}, max {}",
+        staleness(stats.mean_staleness()),
+        staleness(stats.staleness_percentile(0.5)),
+        staleness(stats.staleness_percentile(0.9)),
+        staleness(stats.staleness_percentile(0.99)),
+        staleness(stats.max_staleness()),
+    );
+    println!(
+        "table: {} values, {} origins seen",
+        replayer.gossip().crds.read().unwrap().len(),
+        stats.origins.len()
+    );
+    println!(
+        "{:<44} {:>10} {:>10} {:>10}",
+        "origin", "values", "inserted", "duplicates"
+    );
+    for (origin, origin_stats) in stats.top_origins(num_origins) {
+        println!(
+            "{:<44} {:>10} {:>10} {:>10}",
+            origin.to_string(),
+            origin_stats.num_values,
+            origin_stats.num_inserted,
+            origin_stats.num_duplicates
+        );
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn error::Error>> {
     trezoa_logger::setup_with_default("trezoa=info");
 
@@ -337,6 +418,9 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         ("rpc-url", Some(matches)) => {
             process_rpc_url(matches, socket_addr_space)?;
         }
+        ("replay-capture", Some(matches)) => {
+            process_replay_capture(matches)?;
+        }
         _ => unreachable!(),
     }
 
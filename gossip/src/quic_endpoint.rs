@@ -0,0 +1,139 @@
+//! QUIC transport for gossip messages between nodes which both advertise a
+//! QUIC gossip socket in their ContactInfo. Each message is sent on its own
+//! unidirectional stream, and the remote node's identity is taken from its
+//! TLS certificate.
+
+pub use trezoa_streamer::quic_endpoint::{close_quic_endpoint, AsyncTryJoinHandle, Error};
+use {
+    bytes::Bytes,
+    crossbeam_channel::Sender,
+    quinn::Endpoint,
+    trezoa_sdk::{pubkey::Pubkey, signature::Keypair},
+    trezoa_streamer::quic_endpoint::{QuicEndpointConfig, QuicTransport},
+    std::{
+        net::{SocketAddr, UdpSocket},
+        sync::Arc,
+    },
+    tokio::sync::mpsc::Sender as AsyncSender,
+};
+
+const ALPN_GOSSIP_PROTOCOL_ID: &str = "trezoa-gossip";
+const ROUTER_CHANNEL_BUFFER: usize = 256;
+
+/// Gossip messages sent over QUIC are not bound by the UDP packet size.
+pub(crate) const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Messages received are sent to `sender` along with the identity and the
+/// address of the remote node. Messages are sent through the returned channel
+/// to the address, only if the remote node's identity matches the given one.
+#[allow(clippy::type_complexity)]
+pub fn new_quic_endpoint(
+    runtime: &tokio::runtime::Handle,
+    keypair: &Keypair,
+    socket: UdpSocket,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+) -> Result<
+    (
+        Endpoint,
+        AsyncSender<(Pubkey, SocketAddr, Bytes)>,
+        AsyncTryJoinHandle,
+    ),
+    Error,
+> {
+    let config = QuicEndpointConfig {
+        alpn_protocol_id: ALPN_GOSSIP_PROTOCOL_ID,
+        transport: QuicTransport::UniStreams {
+            max_message_size: MAX_MESSAGE_SIZE,
+        },
+        router_channel_buffer: ROUTER_CHANNEL_BUFFER,
+        // Unlike turbine and repair, gossip runs on nodes without a bank
+        // (e.g. spy nodes), so connections are not pruned by stake. Instead
+        // the ones with the longest round trip times are closed, which gossip
+        // peers can reconnect or fall back to UDP from.
+        sort_connections: Arc::new(|connections| {
+            connections.sort_unstable_by_key(|(_, connection)| connection.rtt())
+        }),
+        server_metrics_name: "gossip_quic_server",
+        client_metrics_name: "gossip_quic_client",
+    };
+    trezoa_streamer::quic_endpoint::new_quic_endpoint(runtime, keypair, socket, sender, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        itertools::{izip, multiunzip},
+        trezoa_sdk::signature::Signer,
+        std::{iter::repeat_with, net::Ipv4Addr, time::Duration},
+    };
+
+    #[test]
+    fn test_quic_endpoint() {
+        const NUM_ENDPOINTS: usize = 3;
+        const RECV_TIMEOUT: Duration = Duration::from_secs(60);
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(8)
+            .enable_all()
+            .build()
+            .unwrap();
+        let keypairs: Vec<Keypair> = repeat_with(Keypair::new).take(NUM_ENDPOINTS).collect();
+        let sockets: Vec<UdpSocket> = repeat_with(|| UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)))
+            .take(NUM_ENDPOINTS)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let addresses: Vec<SocketAddr> = sockets
+            .iter()
+            .map(UdpSocket::local_addr)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            repeat_with(crossbeam_channel::unbounded::<(Pubkey, SocketAddr, Bytes)>)
+                .take(NUM_ENDPOINTS)
+                .unzip();
+        let (endpoints, senders, tasks): (Vec<_>, Vec<_>, Vec<_>) =
+            multiunzip(keypairs.iter().zip(sockets).zip(senders).map(
+                |((keypair, socket), sender)| {
+                    new_quic_endpoint(runtime.handle(), keypair, socket, sender).unwrap()
+                },
+            ));
+        // Send a unique message from each endpoint to every other endpoint.
+        for (i, (keypair, &address, sender)) in izip!(&keypairs, &addresses, &senders).enumerate() {
+            for (j, (other, &address)) in keypairs.iter().zip(&addresses).enumerate() {
+                if i != j {
+                    let bytes = Bytes::from(format!("{i}=>{j}"));
+                    sender
+                        .blocking_send((other.pubkey(), address, bytes))
+                        .unwrap();
+                }
+            }
+            // Verify all messages are received.
+            for (j, receiver) in receivers.iter().enumerate() {
+                if i != j {
+                    let bytes = Bytes::from(format!("{i}=>{j}"));
+                    let entry = (keypair.pubkey(), address, bytes);
+                    assert_eq!(receiver.recv_timeout(RECV_TIMEOUT).unwrap(), entry);
+                }
+            }
+        }
+        // Messages are not sent if the identity at the address does not match.
+        senders[0]
+            .blocking_send((Pubkey::new_unique(), addresses[1], Bytes::from("spoofed")))
+            .unwrap();
+        senders[2]
+            .blocking_send((keypairs[1].pubkey(), addresses[1], Bytes::from("2=>1")))
+            .unwrap();
+        assert_eq!(
+            receivers[1].recv_timeout(RECV_TIMEOUT).unwrap(),
+            (keypairs[2].pubkey(), addresses[2], Bytes::from("2=>1"))
+        );
+        assert!(receivers[1].try_recv().is_err());
+        drop(senders);
+        for endpoint in endpoints {
+            close_quic_endpoint(&endpoint);
+        }
+        for task in tasks {
+            runtime.block_on(task).unwrap();
+        }
+    }
+}
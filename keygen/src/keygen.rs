@@ -3,6 +3,7 @@
 use {
     bip39::{Mnemonic, MnemonicType, Seed},
     clap::{crate_description, crate_name, value_parser, Arg, ArgMatches, Command},
+    rpassword::prompt_password,
     trezoa_clap_v3_utils::{
         input_parsers::STDOUT_OUTFILE_TOKEN,
         input_validators::is_prompt_signer_source,
@@ -13,10 +14,12 @@ use {
                 acquire_language, acquire_passphrase_and_message, no_passphrase_and_message,
                 WORD_COUNT_ARG,
             },
-            no_outfile_arg, KeyGenerationCommonArgs, NO_OUTFILE_ARG,
+            no_outfile_arg,
+            shamir::{combine_shares, split_secret, SecretShare},
+            KeyGenerationCommonArgs, NO_OUTFILE_ARG,
         },
         keypair::{
-            keypair_from_path, keypair_from_seed_phrase, signer_from_path,
+            keypair_from_path, keypair_from_seed_phrase, prompt_passphrase, signer_from_path,
             SKIP_SEED_PHRASE_VALIDATION_ARG,
         },
         DisplayError,
@@ -29,12 +32,15 @@ use {
         pubkey::{write_pubkey_file, Pubkey},
         signature::{
             keypair_from_seed, keypair_from_seed_and_derivation_path, write_keypair,
-            write_keypair_file, Keypair, Signer,
+            write_keypair_file, EncodableKey, Keypair, Signer,
         },
+        signer::encrypted_keypair::EncryptedKeypair,
     },
     std::{
         collections::HashSet,
         error,
+        fs::OpenOptions,
+        io::Write,
         rc::Rc,
         sync::{
             atomic::{AtomicBool, AtomicU64, Ordering},
@@ -84,8 +90,18 @@ fn output_keypair(
     keypair: &Keypair,
     outfile: &str,
     source: &str,
+    encryption_passphrase: Option<&str>,
 ) -> Result<(), Box<dyn error::Error>> {
-    if outfile == STDOUT_OUTFILE_TOKEN {
+    if let Some(passphrase) = encryption_passphrase {
+        let encrypted_keypair = EncryptedKeypair::encrypt(keypair, passphrase)?;
+        if outfile == STDOUT_OUTFILE_TOKEN {
+            let mut stdout = std::io::stdout();
+            encrypted_keypair.write(&mut stdout)?;
+        } else {
+            encrypted_keypair.write_to_file(outfile)?;
+            println!("Wrote encrypted {source} keypair to {outfile}");
+        }
+    } else if outfile == STDOUT_OUTFILE_TOKEN {
         let mut stdout = std::io::stdout();
         write_keypair(keypair, &mut stdout)?;
     } else {
@@ -95,6 +111,80 @@ fn output_keypair(
     Ok(())
 }
 
+fn acquire_encryption_passphrase() -> Result<String, Box<dyn error::Error>> {
+    let passphrase = prompt_passphrase("Enter passphrase to encrypt the keypair file: ")?;
+    if passphrase.is_empty() {
+        return Err("An empty passphrase cannot be used to encrypt a keypair file".into());
+    }
+    Ok(passphrase)
+}
+
+fn write_share_file(mnemonic: &str, outfile: &str) -> Result<(), Box<dyn error::Error>> {
+    let mut file = {
+        #[cfg(not(unix))]
+        {
+            OpenOptions::new()
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            OpenOptions::new().mode(0o600)
+        }
+    }
+    .write(true)
+    .truncate(true)
+    .create(true)
+    .open(outfile)?;
+    writeln!(file, "{mnemonic}")?;
+    Ok(())
+}
+
+/// Prompts for shares until as many as the threshold of the first one have
+/// been entered
+fn prompt_shares() -> Result<Vec<SecretShare>, Box<dyn error::Error>> {
+    let mut shares: Vec<SecretShare> = vec![];
+    loop {
+        let mnemonic = prompt_password(format!("share {}: ", shares.len() + 1))?;
+        let share = match SecretShare::from_mnemonic(&mnemonic) {
+            Ok(share) => share,
+            Err(err) => {
+                eprintln!("Invalid share: {err}");
+                continue;
+            }
+        };
+        if shares.iter().any(|entered| entered.index == share.index) {
+            eprintln!("Share {} was already entered", share.index);
+            continue;
+        }
+        shares.push(share);
+        let threshold = usize::from(shares[0].threshold);
+        if shares.len() >= threshold {
+            return Ok(shares);
+        }
+        println!("{} more share(s) required", threshold - shares.len());
+    }
+}
+
+/// Recovers a keypair from the shares in `share_files`, or from shares
+/// prompted for if there are none
+fn keypair_from_shares<'a>(
+    share_files: Option<impl Iterator<Item = &'a str>>,
+) -> Result<Keypair, Box<dyn error::Error>> {
+    let shares = if let Some(share_files) = share_files {
+        share_files
+            .map(|share_file| {
+                let mnemonic = std::fs::read_to_string(share_file)?;
+                SecretShare::from_mnemonic(&mnemonic)
+                    .map_err(|err| format!("{share_file}: {err}").into())
+            })
+            .collect::<Result<Vec<_>, Box<dyn error::Error>>>()?
+    } else {
+        prompt_shares()?
+    };
+    let secret = combine_shares(&shares)?;
+    keypair_from_seed(&secret)
+}
+
 fn grind_validator_starts_with(v: &str) -> Result<(), String> {
     if v.matches(':').count() != 1 || (v.starts_with(':') || v.ends_with(':')) {
         return Err(String::from("Expected : between PREFIX and COUNT"));
@@ -285,12 +375,17 @@ fn app<'a>(num_threads: &'a str, crate_version: &'a str) -> Command<'a> {
                         .long("silent")
                         .help("Do not display seed phrase. Useful when piping output to other programs that prompt for user input, like gpg"),
                 )
+                .arg(
+                    Arg::new("encrypt")
+                        .long("encrypt")
+                        .help("Encrypt the keypair file with a passphrase, prompted for on the terminal"),
+                )
                 .arg(
                     derivation_path_arg()
                 )
                 .key_generation_common_args()
                 .arg(no_outfile_arg()
-                    .conflicts_with_all(&["outfile", "silent"])
+                    .conflicts_with_all(&["outfile", "silent", "encrypt"])
                 )
         )
         .subcommand(
@@ -422,9 +517,72 @@ fn app<'a>(num_threads: &'a str, crate_version: &'a str) -> Command<'a> {
                     Arg::new(SKIP_SEED_PHRASE_VALIDATION_ARG.name)
                         .long(SKIP_SEED_PHRASE_VALIDATION_ARG.long)
                         .help(SKIP_SEED_PHRASE_VALIDATION_ARG.help),
+                )
+                .arg(
+                    Arg::new("from_shares")
+                        .long("from-shares")
+                        .conflicts_with("prompt_signer")
+                        .help("Recover from the mnemonic shares created by `split`, prompting for each share"),
+                )
+                .arg(
+                    Arg::new("share_files")
+                        .long("share-file")
+                        .value_name("FILEPATH")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .conflicts_with("prompt_signer")
+                        .help("Recover from the mnemonic shares created by `split`, reading one share from each file"),
                 ),
 
         )
+        .subcommand(
+            Command::new("split")
+                .about("Split a keypair into mnemonic shares, any THRESHOLD of which recover it")
+                .disable_version_flag(true)
+                .arg(
+                    Arg::new("keypair")
+                        .index(1)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .help("Filepath or URL to a keypair"),
+                )
+                .arg(
+                    Arg::new("threshold")
+                        .long("threshold")
+                        .value_name("M")
+                        .takes_value(true)
+                        .required(true)
+                        .value_parser(value_parser!(u8).range(1..))
+                        .help("Number of shares required to recover the keypair"),
+                )
+                .arg(
+                    Arg::new("shares")
+                        .long("shares")
+                        .value_name("N")
+                        .takes_value(true)
+                        .required(true)
+                        .value_parser(value_parser!(u8).range(1..))
+                        .help("Number of shares to create"),
+                )
+                .arg(
+                    Arg::new("outfile_prefix")
+                        .long("outfile-prefix")
+                        .value_name("PREFIX")
+                        .takes_value(true)
+                        .help("Write share I to the file PREFIX-I.txt instead of displaying the shares"),
+                )
+                .arg(
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Overwrite the output files if they exist"),
+                )
+                .arg(
+                    Arg::new(SKIP_SEED_PHRASE_VALIDATION_ARG.name)
+                        .long(SKIP_SEED_PHRASE_VALIDATION_ARG.long)
+                        .help(SKIP_SEED_PHRASE_VALIDATION_ARG.help),
+                )
+        )
 }
 
 fn main() -> Result<(), Box<dyn error::Error>> {
@@ -476,6 +634,12 @@ fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
                 None => (),
             }
 
+            let encryption_passphrase = if matches.is_present("encrypt") {
+                Some(acquire_encryption_passphrase()?)
+            } else {
+                None
+            };
+
             let word_count: usize = matches.value_of_t(WORD_COUNT_ARG.name).unwrap();
             let mnemonic_type = MnemonicType::for_word_count(word_count)?;
             let language = acquire_language(matches);
@@ -498,7 +662,7 @@ fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
             }?;
 
             if let Some(outfile) = outfile {
-                output_keypair(&keypair, outfile, "new")
+                output_keypair(&keypair, outfile, "new", encryption_passphrase.as_deref())
                     .map_err(|err| format!("Unable to write {outfile}: {err}"))?;
             }
 
@@ -525,13 +689,66 @@ fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
             }
 
             let keypair_name = "recover";
-            let keypair = if let Some(path) = matches.value_of("prompt_signer") {
+            let keypair = if matches.is_present("share_files") || matches.is_present("from_shares")
+            {
+                keypair_from_shares(matches.values_of("share_files"))?
+            } else if let Some(path) = matches.value_of("prompt_signer") {
                 keypair_from_path(matches, path, keypair_name, true)?
             } else {
                 let skip_validation = matches.is_present(SKIP_SEED_PHRASE_VALIDATION_ARG.name);
                 keypair_from_seed_phrase(keypair_name, skip_validation, true, None, true)?
             };
-            output_keypair(&keypair, outfile, "recovered")?;
+            output_keypair(&keypair, outfile, "recovered", None)?;
+        }
+        ("split", matches) => {
+            let mut path = dirs_next::home_dir().expect("home directory");
+            let path = if matches.is_present("keypair") {
+                matches.value_of("keypair").unwrap()
+            } else if !config.keypair_path.is_empty() {
+                &config.keypair_path
+            } else {
+                path.extend([".config", "trezoa", "id.json"]);
+                path.to_str().unwrap()
+            };
+            let threshold = *matches.get_one::<u8>("threshold").unwrap();
+            let num_shares = *matches.get_one::<u8>("shares").unwrap();
+            if threshold > num_shares {
+                return Err("THRESHOLD must not exceed the number of shares".into());
+            }
+            let outfile_prefix = matches.value_of("outfile_prefix");
+            let share_outfile =
+                |prefix: &str, share: &SecretShare| format!("{prefix}-{}.txt", share.index);
+
+            let keypair = keypair_from_path(matches, path, "split", false)?;
+            let shares = split_secret(keypair.secret().as_bytes(), threshold, num_shares)?;
+
+            if let Some(prefix) = outfile_prefix {
+                for share in &shares {
+                    check_for_overwrite(&share_outfile(prefix, share), matches)?;
+                }
+            }
+            let mnemonics: Vec<_> = shares.iter().map(SecretShare::to_mnemonic).collect();
+            if let Some(prefix) = outfile_prefix {
+                for (share, mnemonic) in shares.iter().zip(&mnemonics) {
+                    let outfile = share_outfile(prefix, share);
+                    write_share_file(mnemonic, &outfile)?;
+                    println!("Wrote share {} of {num_shares} to {outfile}", share.index);
+                }
+            } else {
+                let width = mnemonics.iter().map(String::len).max().unwrap_or_default();
+                let divider = String::from_utf8(vec![b'='; width]).unwrap();
+                for (share, mnemonic) in shares.iter().zip(&mnemonics) {
+                    println!(
+                        "{divider}\nShare {} of {num_shares}:\n{mnemonic}",
+                        share.index
+                    );
+                }
+                println!("{divider}");
+            }
+            println!(
+                "Any {threshold} of the {num_shares} shares recover the keypair {} with `trezoa-keygen recover --from-shares`",
+                keypair.pubkey()
+            );
         }
         ("grind", matches) => {
             let ignore_case = matches.is_present("ignore_case");
@@ -748,6 +965,7 @@ fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
 mod tests {
     use {
         super::*,
+        trezoa_sdk::signature::read_keypair_file,
         tempfile::{tempdir, TempDir},
     };
 
@@ -1112,4 +1330,124 @@ mod tests {
         ])
         .unwrap();
     }
+
+    #[test]
+    fn test_split() {
+        let keypair_out_dir = tempdir().unwrap();
+        let config_out_dir = tempdir().unwrap();
+        let (pubkey, keypair_path, _) =
+            create_tmp_keypair_and_config_file(&keypair_out_dir, &config_out_dir);
+
+        let outfile_dir = tempdir().unwrap();
+        let prefix = tmp_outfile_path(&outfile_dir, "share");
+        process_test_command(&[
+            "trezoa-keygen",
+            "split",
+            &keypair_path,
+            "--threshold",
+            "2",
+            "--shares",
+            "3",
+            "--outfile-prefix",
+            &prefix,
+        ])
+        .unwrap();
+
+        // Refuses to overwrite the shares
+        process_test_command(&[
+            "trezoa-keygen",
+            "split",
+            &keypair_path,
+            "--threshold",
+            "2",
+            "--shares",
+            "3",
+            "--outfile-prefix",
+            &prefix,
+        ])
+        .unwrap_err();
+
+        let result = process_test_command(&[
+            "trezoa-keygen",
+            "split",
+            &keypair_path,
+            "--threshold",
+            "3",
+            "--shares",
+            "2",
+        ])
+        .unwrap_err()
+        .to_string();
+        assert_eq!(result, "THRESHOLD must not exceed the number of shares");
+
+        let default_num_threads = num_cpus::get().to_string();
+        let trezoa_version = trezoa_version::version!();
+        for threshold in ["0", "256"] {
+            app(&default_num_threads, trezoa_version)
+                .try_get_matches_from([
+                    "trezoa-keygen",
+                    "split",
+                    &keypair_path,
+                    "--threshold",
+                    threshold,
+                    "--shares",
+                    "3",
+                ])
+                .unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_split_recover() {
+        let keypair_out_dir = tempdir().unwrap();
+        let config_out_dir = tempdir().unwrap();
+        let (pubkey, keypair_path, _) =
+            create_tmp_keypair_and_config_file(&keypair_out_dir, &config_out_dir);
+
+        let outfile_dir = tempdir().unwrap();
+        let prefix = tmp_outfile_path(&outfile_dir, "share");
+        process_test_command(&[
+            "trezoa-keygen",
+            "split",
+            &keypair_path,
+            "--threshold",
+            "3",
+            "--shares",
+            "5",
+            "--outfile-prefix",
+            &prefix,
+        ])
+        .unwrap();
+
+        let recovered_path = tmp_outfile_path(&outfile_dir, "recovered.json");
+        process_test_command(&[
+            "trezoa-keygen",
+            "recover",
+            "--share-file",
+            &format!("{prefix}-5.txt"),
+            "--share-file",
+            &format!("{prefix}-2.txt"),
+            "--share-file",
+            &format!("{prefix}-4.txt"),
+            "--outfile",
+            &recovered_path,
+        ])
+        .unwrap();
+        assert_eq!(read_keypair_file(&recovered_path).unwrap().pubkey(), pubkey);
+
+        let recovered_path = tmp_outfile_path(&outfile_dir, "too-few.json");
+        let result = process_test_command(&[
+            "trezoa-keygen",
+            "recover",
+            "--share-file",
+            &format!("{prefix}-1.txt"),
+            "--share-file",
+            &format!("{prefix}-3.txt"),
+            "--outfile",
+            &recovered_path,
+        ])
+        .unwrap_err()
+        .to_string();
+        assert_eq!(result, "3 shares are required, found 2");
+    }
 }
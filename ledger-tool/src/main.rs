@@ -10,6 +10,8 @@ use {
             output_account, AccountsOutputConfig, AccountsOutputMode, AccountsOutputStreamer,
         },
         program::*,
+        repair_trace::*,
+        turbine::*,
     },
     clap::{
         crate_description, crate_name, value_t, value_t_or_exit, values_t_or_exit, App,
@@ -102,6 +104,8 @@ mod ledger_path;
 mod ledger_utils;
 mod output;
 mod program;
+mod repair_trace;
+mod turbine;
 
 fn parse_encoding_format(matches: &ArgMatches<'_>) -> UiAccountEncoding {
     match matches.value_of("encoding") {
@@ -1486,6 +1490,8 @@ fn main() {
                 .arg(&allow_dead_slots_arg),
         )
         .program_subcommand()
+        .repair_trace_subcommand()
+        .turbine_subcommand()
         .get_matches();
 
     info!("{} {}", crate_name!(), trezoa_version::version!());
@@ -1505,6 +1511,8 @@ fn main() {
         ("bigtable", Some(arg_matches)) => bigtable_process_command(&ledger_path, arg_matches),
         ("blockstore", Some(arg_matches)) => blockstore_process_command(&ledger_path, arg_matches),
         ("program", Some(arg_matches)) => program(&ledger_path, arg_matches),
+        ("repair-trace", Some(arg_matches)) => repair_trace(arg_matches),
+        ("turbine", Some(arg_matches)) => turbine(&ledger_path, arg_matches),
         // This match case provides legacy support for commands that were previously top level
         // subcommands of the binary, but have been moved under the blockstore subcommand.
         ("analyze-storage", Some(_))
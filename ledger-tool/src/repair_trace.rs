@@ -0,0 +1,101 @@
+use {
+    clap::{value_t_or_exit, App, Arg, ArgMatches, SubCommand},
+    trezoa_clap_utils::input_validators::is_parsable,
+    trezoa_core::repair::repair_trace::RepairTraceSummary,
+    std::{fs::File, io::BufReader, process::exit, time::Duration},
+};
+
+pub trait RepairTraceSubCommand {
+    fn repair_trace_subcommand(self) -> Self;
+}
+
+impl RepairTraceSubCommand for App<'_, '_> {
+    fn repair_trace_subcommand(self) -> Self {
+        self.subcommand(
+            SubCommand::with_name("repair-trace")
+                .about(
+                    "Summarize a repair trace log written by the validator's --repair-trace-log, \
+                     listing the peers serving repairs the worst first",
+                )
+                .arg(
+                    Arg::with_name("trace_log")
+                        .index(1)
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Repair trace log"),
+                )
+                .arg(
+                    Arg::with_name("min_requests")
+                        .long("min-requests")
+                        .value_name("NUMBER")
+                        .takes_value(true)
+                        .default_value("10")
+                        .validator(is_parsable::<usize>)
+                        .help("Only list peers sent at least this many requests"),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .value_name("NUMBER")
+                        .takes_value(true)
+                        .default_value("20")
+                        .validator(is_parsable::<usize>)
+                        .help("Maximum number of peers to list"),
+                ),
+        )
+    }
+}
+
+pub fn repair_trace(matches: &ArgMatches<'_>) {
+    let path = value_t_or_exit!(matches, "trace_log", String);
+    let min_requests = value_t_or_exit!(matches, "min_requests", usize);
+    let limit = value_t_or_exit!(matches, "limit", usize);
+    let mut summary = File::open(&path)
+        .and_then(|file| RepairTraceSummary::from_reader(BufReader::new(file)))
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to read repair trace log {path}: {err}");
+            exit(1);
+        });
+    if summary.num_invalid_lines > 0 {
+        eprintln!("Skipped {} invalid lines", summary.num_invalid_lines);
+    }
+
+    let num_requests: usize = summary.peers.values().map(|stats| stats.num_requests).sum();
+    println!(
+        "Repair requests sent: {num_requests}, peers: {}",
+        summary.peers.len()
+    );
+    let format_latency = |latency: Option<Duration>| {
+        latency
+            .map(|latency| format!("{}ms", latency.as_millis()))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    println!(
+        "{:<44} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+        "Peer", "Requests", "Repaired", "Rejected", "Expired", "p50", "p90", "p99"
+    );
+    for (peer, mut stats) in summary.worst_peers(min_requests).into_iter().take(limit) {
+        println!(
+            "{:<44} {:>8} {:>7.1}% {:>8} {:>8} {:>8} {:>8} {:>8}",
+            peer,
+            stats.num_requests,
+            100.0 * stats.success_rate(),
+            stats.num_rejected,
+            stats.num_expired,
+            format_latency(stats.latency_percentile(50)),
+            format_latency(stats.latency_percentile(90)),
+            format_latency(stats.latency_percentile(99)),
+        );
+    }
+
+    if !summary.served.is_empty() {
+        let mut served: Vec<_> = summary.served.iter().collect();
+        served.sort_unstable_by_key(|(outcome, _)| outcome.to_string());
+        let served: Vec<_> = served
+            .into_iter()
+            .map(|(outcome, count)| format!("{outcome}: {count}"))
+            .collect();
+        println!("Repair requests served: {}", served.join(", "));
+    }
+}
@@ -0,0 +1,382 @@
+use {
+    crate::{canonicalize_ledger_path, program::load_blockstore},
+    clap::{value_t, value_t_or_exit, App, AppSettings, Arg, ArgMatches, SubCommand},
+    trezoa_clap_utils::{
+        input_parsers::pubkey_of,
+        input_validators::{is_parsable, is_pubkey, is_slot, is_within_range},
+    },
+    trezoa_gossip::gossip_service::discover,
+    trezoa_ledger::{
+        leader_schedule_utils::slot_leader_at,
+        shred::{ShredId, ShredType},
+        use_snapshot_archives_at_startup,
+    },
+    trezoa_runtime::bank::Bank,
+    trezoa_sdk::{clock::Slot, pubkey::Pubkey},
+    trezoa_streamer::socket::SocketAddrSpace,
+    trezoa_turbine::{
+        cluster_nodes::DATA_PLANE_FANOUT,
+        turbine_tree::{simulate, SimulationConfig, TurbineNodes},
+    },
+    std::{
+        collections::HashMap,
+        fs::File,
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        path::Path,
+        process::exit,
+        str::FromStr,
+        sync::Arc,
+        time::Duration,
+    },
+};
+
+pub trait TurbineSubCommand {
+    fn turbine_subcommand(self) -> Self;
+}
+
+impl TurbineSubCommand for App<'_, '_> {
+    fn turbine_subcommand(self) -> Self {
+        let stakes_file_arg = Arg::with_name("stakes_file")
+            .long("stakes-file")
+            .value_name("FILE")
+            .takes_value(true)
+            .help(
+                "JSON file mapping node identities to their stake in lamports, used instead of \
+                 the epoch stakes of the ledger's bank",
+            );
+        let entrypoint_arg = Arg::with_name("entrypoint")
+            .long("entrypoint")
+            .value_name("HOST:PORT")
+            .takes_value(true)
+            .validator(trezoa_net_utils::is_host_port)
+            .help("Add the unstaked nodes found in gossip through this entrypoint");
+        let shred_version_arg = Arg::with_name("shred_version")
+            .long("shred-version")
+            .value_name("VERSION")
+            .takes_value(true)
+            .default_value("0")
+            .help("Filter gossip nodes by this shred version");
+        let gossip_timeout_arg = Arg::with_name("gossip_timeout")
+            .long("gossip-timeout")
+            .value_name("SECONDS")
+            .takes_value(true)
+            .default_value("15")
+            .validator(is_parsable::<u64>)
+            .help("Time spent discovering nodes in gossip");
+        let fanout_arg = Arg::with_name("fanout")
+            .long("fanout")
+            .value_name("NUMBER")
+            .takes_value(true)
+            .validator(|value| is_within_range(value, 1..))
+            .help("Number of nodes each node retransmits shreds to [default: 200]");
+        let max_genesis_arg = Arg::with_name("max_genesis_archive_unpacked_size")
+            .long("max-genesis-archive-unpacked-size")
+            .value_name("NUMBER")
+            .takes_value(true)
+            .default_value("10485760")
+            .help("maximum total uncompressed size of unpacked genesis archive");
+        let use_snapshot_archives_at_startup =
+            Arg::with_name(use_snapshot_archives_at_startup::cli::NAME)
+                .long(use_snapshot_archives_at_startup::cli::LONG_ARG)
+                .takes_value(true)
+                .possible_values(use_snapshot_archives_at_startup::cli::POSSIBLE_VALUES)
+                .default_value(
+                    use_snapshot_archives_at_startup::cli::default_value_for_ledger_tool(),
+                )
+                .help(use_snapshot_archives_at_startup::cli::HELP)
+                .long_help(use_snapshot_archives_at_startup::cli::LONG_HELP);
+
+        self.subcommand(
+            SubCommand::with_name("turbine")
+                .about("Inspect and simulate turbine retransmit trees")
+                .setting(AppSettings::InferSubcommands)
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("tree")
+                        .about("Print the retransmit tree of a shred")
+                        .arg(
+                            Arg::with_name("slot")
+                                .long("slot")
+                                .value_name("SLOT")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_slot)
+                                .help("Slot of the shred"),
+                        )
+                        .arg(
+                            Arg::with_name("shred_index")
+                                .long("shred-index")
+                                .value_name("INDEX")
+                                .takes_value(true)
+                                .default_value("0")
+                                .validator(is_parsable::<u32>)
+                                .help("Index of the shred within the slot"),
+                        )
+                        .arg(
+                            Arg::with_name("shred_type")
+                                .long("shred-type")
+                                .value_name("TYPE")
+                                .takes_value(true)
+                                .possible_values(&["data", "code"])
+                                .default_value("data")
+                                .help("Type of the shred"),
+                        )
+                        .arg(
+                            Arg::with_name("leader")
+                                .long("leader")
+                                .value_name("PUBKEY")
+                                .takes_value(true)
+                                .validator(is_pubkey)
+                                .help(
+                                    "Identity of the slot leader [default: the leader in the \
+                                     ledger's leader schedule]",
+                                ),
+                        )
+                        .arg(&fanout_arg)
+                        .arg(&stakes_file_arg)
+                        .arg(&entrypoint_arg)
+                        .arg(&shred_version_arg)
+                        .arg(&gossip_timeout_arg)
+                        .arg(&max_genesis_arg)
+                        .arg(&use_snapshot_archives_at_startup),
+                )
+                .subcommand(
+                    SubCommand::with_name("simulate")
+                        .about(
+                            "Estimate shred delivery latency and erasure recovery through the \
+                             retransmit trees",
+                        )
+                        .arg(&fanout_arg)
+                        .arg(
+                            Arg::with_name("num_data_shreds")
+                                .long("num-data-shreds")
+                                .value_name("NUMBER")
+                                .takes_value(true)
+                                .default_value("32")
+                                .validator(is_parsable::<usize>)
+                                .help("Data shreds in each erasure batch"),
+                        )
+                        .arg(
+                            Arg::with_name("num_coding_shreds")
+                                .long("num-coding-shreds")
+                                .value_name("NUMBER")
+                                .takes_value(true)
+                                .default_value("32")
+                                .validator(is_parsable::<usize>)
+                                .help("Coding shreds in each erasure batch"),
+                        )
+                        .arg(
+                            Arg::with_name("num_batches")
+                                .long("num-batches")
+                                .value_name("NUMBER")
+                                .takes_value(true)
+                                .default_value("100")
+                                .validator(is_parsable::<usize>)
+                                .help("Erasure batches to simulate"),
+                        )
+                        .arg(
+                            Arg::with_name("node_failure_rate")
+                                .long("node-failure-rate")
+                                .value_name("PROBABILITY")
+                                .takes_value(true)
+                                .default_value("0")
+                                .validator(is_probability)
+                                .help("Probability a node is down for an erasure batch"),
+                        )
+                        .arg(
+                            Arg::with_name("packet_loss_rate")
+                                .long("packet-loss-rate")
+                                .value_name("PROBABILITY")
+                                .takes_value(true)
+                                .default_value("0")
+                                .validator(is_probability)
+                                .help("Probability a shred is lost on each hop"),
+                        )
+                        .arg(
+                            Arg::with_name("hop_latency")
+                                .long("hop-latency")
+                                .value_name("MILLISECONDS")
+                                .takes_value(true)
+                                .default_value("20")
+                                .validator(is_parsable::<u64>)
+                                .help("Mean latency of each hop"),
+                        )
+                        .arg(
+                            Arg::with_name("hop_latency_jitter")
+                                .long("hop-latency-jitter")
+                                .value_name("MILLISECONDS")
+                                .takes_value(true)
+                                .default_value("10")
+                                .validator(is_parsable::<u64>)
+                                .help("Latency of each hop varies uniformly within this jitter"),
+                        )
+                        .arg(
+                            Arg::with_name("seed")
+                                .long("seed")
+                                .value_name("NUMBER")
+                                .takes_value(true)
+                                .default_value("0")
+                                .validator(is_parsable::<u64>)
+                                .help("Seed of the simulation"),
+                        )
+                        .arg(&stakes_file_arg)
+                        .arg(&entrypoint_arg)
+                        .arg(&shred_version_arg)
+                        .arg(&gossip_timeout_arg)
+                        .arg(&max_genesis_arg)
+                        .arg(&use_snapshot_archives_at_startup),
+                ),
+        )
+    }
+}
+
+fn is_probability(value: String) -> Result<(), String> {
+    match f64::from_str(&value) {
+        Ok(probability) if (0.0..=1.0).contains(&probability) => Ok(()),
+        _ => Err(format!("{value} is not a probability between 0 and 1")),
+    }
+}
+
+fn load_stakes_file(path: &Path) -> Result<HashMap<Pubkey, u64>, String> {
+    let file =
+        File::open(path).map_err(|err| format!("Unable to open {}: {err}", path.display()))?;
+    let stakes: HashMap<String, u64> = serde_json::from_reader(file)
+        .map_err(|err| format!("Unable to parse {}: {err}", path.display()))?;
+    stakes
+        .into_iter()
+        .map(|(pubkey, stake)| {
+            let pubkey = Pubkey::from_str(&pubkey)
+                .map_err(|err| format!("Invalid node identity {pubkey}: {err}"))?;
+            Ok((pubkey, stake))
+        })
+        .collect()
+}
+
+// Identities of the validators found in gossip through the entrypoint.
+fn discover_nodes(matches: &ArgMatches<'_>, entrypoint: &str) -> Vec<Pubkey> {
+    let entrypoint_addr = trezoa_net_utils::parse_host_port(entrypoint).unwrap_or_else(|err| {
+        eprintln!("Failed to parse entrypoint address: {err}");
+        exit(1);
+    });
+    let gossip_host =
+        trezoa_net_utils::get_public_ip_addr(&entrypoint_addr).unwrap_or_else(|err| {
+            eprintln!("Failed to contact cluster entrypoint {entrypoint_addr}: {err}");
+            exit(1);
+        });
+    let gossip_port =
+        trezoa_net_utils::find_available_port_in_range(IpAddr::V4(Ipv4Addr::UNSPECIFIED), (0, 1))
+            .expect("unable to find an available gossip port");
+    let gossip_addr = SocketAddr::new(gossip_host, gossip_port);
+    let timeout = value_t_or_exit!(matches, "gossip_timeout", u64);
+    let shred_version = value_t_or_exit!(matches, "shred_version", u16);
+    let (_all_peers, validators) = discover(
+        None, // keypair
+        Some(&entrypoint_addr),
+        None, // num_nodes
+        Duration::from_secs(timeout),
+        None, // find_nodes_by_pubkey
+        None, // find_node_by_gossip_addr
+        Some(&gossip_addr),
+        shred_version,
+        SocketAddrSpace::Unspecified,
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("Failed to discover nodes in gossip: {err}");
+        exit(1);
+    });
+    validators.iter().map(|node| *node.pubkey()).collect()
+}
+
+// Stakes of the nodes, and the ledger's bank if they were loaded from it.
+fn load_stakes(
+    ledger_path: &Path,
+    matches: &ArgMatches<'_>,
+    slot: Option<Slot>,
+) -> (HashMap<Pubkey, u64>, Option<Arc<Bank>>) {
+    let (mut stakes, bank) = match matches.value_of("stakes_file") {
+        Some(path) => {
+            let stakes = load_stakes_file(Path::new(path)).unwrap_or_else(|err| {
+                eprintln!("{err}");
+                exit(1);
+            });
+            (stakes, None)
+        }
+        None => {
+            let ledger_path = canonicalize_ledger_path(ledger_path);
+            let bank = load_blockstore(&ledger_path, matches);
+            let epoch = slot
+                .map(|slot| bank.epoch_schedule().get_epoch(slot))
+                .unwrap_or_else(|| bank.epoch());
+            let stakes = bank.epoch_staked_nodes(epoch).unwrap_or_else(|| {
+                eprintln!(
+                    "No stakes for epoch {epoch} in the bank at slot {}",
+                    bank.slot()
+                );
+                exit(1);
+            });
+            (HashMap::clone(&stakes), Some(bank))
+        }
+    };
+    if let Some(entrypoint) = matches.value_of("entrypoint") {
+        let nodes = discover_nodes(matches, entrypoint);
+        println!("Discovered {} nodes in gossip", nodes.len());
+        for node in nodes {
+            stakes.entry(node).or_default();
+        }
+    }
+    if stakes.is_empty() {
+        eprintln!("No nodes to build turbine trees from");
+        exit(1);
+    }
+    (stakes, bank)
+}
+
+pub fn turbine(ledger_path: &Path, matches: &ArgMatches<'_>) {
+    match matches.subcommand() {
+        ("tree", Some(arg_matches)) => {
+            let slot = value_t_or_exit!(arg_matches, "slot", Slot);
+            let shred_index = value_t_or_exit!(arg_matches, "shred_index", u32);
+            let shred_type = match arg_matches.value_of("shred_type") {
+                Some("code") => ShredType::Code,
+                _ => ShredType::Data,
+            };
+            let fanout = value_t!(arg_matches, "fanout", usize).unwrap_or(DATA_PLANE_FANOUT);
+            let (stakes, bank) = load_stakes(ledger_path, arg_matches, Some(slot));
+            let leader = pubkey_of(arg_matches, "leader")
+                .or_else(|| slot_leader_at(slot, bank.as_deref()?))
+                .unwrap_or_else(|| {
+                    eprintln!("Unknown leader of slot {slot}, specify it with --leader");
+                    exit(1);
+                });
+            let nodes = TurbineNodes::new(&stakes);
+            let shred = ShredId::new(slot, shred_index, shred_type);
+            let tree = nodes.get_turbine_tree(&leader, &shred, fanout);
+            println!("Leader: {leader}, shred: {shred:?}");
+            print!("{tree}");
+        }
+        ("simulate", Some(arg_matches)) => {
+            let hop_latency = value_t_or_exit!(arg_matches, "hop_latency", u64);
+            let hop_latency_jitter = value_t_or_exit!(arg_matches, "hop_latency_jitter", u64);
+            let config = SimulationConfig {
+                fanout: value_t!(arg_matches, "fanout", usize).unwrap_or(DATA_PLANE_FANOUT),
+                num_data_shreds: value_t_or_exit!(arg_matches, "num_data_shreds", usize),
+                num_coding_shreds: value_t_or_exit!(arg_matches, "num_coding_shreds", usize),
+                num_batches: value_t_or_exit!(arg_matches, "num_batches", usize),
+                node_failure_rate: value_t_or_exit!(arg_matches, "node_failure_rate", f64),
+                packet_loss_rate: value_t_or_exit!(arg_matches, "packet_loss_rate", f64),
+                hop_latency: Duration::from_millis(hop_latency),
+                hop_latency_jitter: Duration::from_millis(hop_latency_jitter),
+                seed: value_t_or_exit!(arg_matches, "seed", u64),
+            };
+            let (stakes, _bank) = load_stakes(ledger_path, arg_matches, None);
+            let nodes = TurbineNodes::new(&stakes);
+            println!(
+                "Nodes: {}, staked: {}",
+                nodes.len(),
+                stakes.values().filter(|stake| **stake > 0).count()
+            );
+            println!("{}", simulate(&nodes, &config));
+        }
+        _ => unreachable!(),
+    }
+}
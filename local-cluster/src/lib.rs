@@ -4,4 +4,6 @@ pub mod cluster_tests;
 pub mod integration_tests;
 pub mod local_cluster;
 mod local_cluster_snapshot_utils;
+#[cfg(feature = "dev-context-only-utils")]
+pub mod network_emulation;
 pub mod validator_configs;
@@ -1,3 +1,8 @@
+#[cfg(feature = "dev-context-only-utils")]
+use {
+    crate::network_emulation::{self, NetworkEmulationConfig},
+    trezoa_streamer::net_emulator::{self, NetworkEmulator},
+};
 use {
     crate::{
         cluster::{Cluster, ClusterValidatorInfo, ValidatorInfo},
@@ -97,6 +102,10 @@ pub struct ClusterConfig {
     pub additional_accounts: Vec<(Pubkey, AccountSharedData)>,
     pub tpu_use_quic: bool,
     pub tpu_connection_pool_size: usize,
+    /// Latency, loss and partitions between the nodes, applied in-process.
+    /// Only one cluster per process may emulate the network at a time
+    #[cfg(feature = "dev-context-only-utils")]
+    pub network_emulation: Option<NetworkEmulationConfig>,
 }
 
 impl ClusterConfig {
@@ -136,6 +145,8 @@ impl Default for ClusterConfig {
             additional_accounts: vec![],
             tpu_use_quic: DEFAULT_TPU_USE_QUIC,
             tpu_connection_pool_size: DEFAULT_TPU_CONNECTION_POOL_SIZE,
+            #[cfg(feature = "dev-context-only-utils")]
+            network_emulation: None,
         }
     }
 }
@@ -148,6 +159,8 @@ pub struct LocalCluster {
     pub validators: HashMap<Pubkey, ClusterValidatorInfo>,
     pub genesis_config: GenesisConfig,
     pub connection_cache: Arc<ConnectionCache>,
+    #[cfg(feature = "dev-context-only-utils")]
+    network_emulator: Option<Arc<NetworkEmulator>>,
 }
 
 impl LocalCluster {
@@ -245,6 +258,17 @@ impl LocalCluster {
         let leader_vote_keypair = &keys_in_genesis[0].vote_keypair;
         let leader_pubkey = leader_keypair.pubkey();
         let leader_node = Node::new_localhost_with_pubkey(&leader_pubkey);
+        #[cfg(feature = "dev-context-only-utils")]
+        let network_emulator = config.network_emulation.as_ref().map(|network_emulation| {
+            let num_nodes = config.node_stakes.len() + config.num_listeners as usize;
+            let emulator = network_emulation
+                .new_emulator(num_nodes)
+                .expect("invalid network emulation config");
+            let emulator = Arc::new(emulator);
+            network_emulation::register_node(&emulator, &leader_node);
+            net_emulator::install(emulator.clone());
+            emulator
+        });
 
         let GenesisConfigInfo {
             mut genesis_config,
@@ -333,6 +357,8 @@ impl LocalCluster {
                     config.tpu_connection_pool_size,
                 )),
             },
+            #[cfg(feature = "dev-context-only-utils")]
+            network_emulator,
         };
 
         let node_pubkey_to_vote_key: HashMap<Pubkey, Arc<Keypair>> = keys_in_genesis
@@ -404,6 +430,12 @@ impl LocalCluster {
         }
     }
 
+    /// Emulator of the network between the nodes, if configured
+    #[cfg(feature = "dev-context-only-utils")]
+    pub fn network_emulator(&self) -> Option<&Arc<NetworkEmulator>> {
+        self.network_emulator.as_ref()
+    }
+
     /// Set up validator without voting or staking accounts
     pub fn add_validator_listener(
         &mut self,
@@ -465,6 +497,10 @@ impl LocalCluster {
         }
         let validator_pubkey = validator_keypair.pubkey();
         let validator_node = Node::new_localhost_with_pubkey(&validator_keypair.pubkey());
+        #[cfg(feature = "dev-context-only-utils")]
+        if let Some(emulator) = &self.network_emulator {
+            network_emulation::register_node(emulator, &validator_node);
+        }
         let contact_info = validator_node.info.clone();
         let (ledger_path, _blockhash) = create_new_tmp_ledger!(&self.genesis_config);
 
@@ -883,6 +919,10 @@ impl Cluster for LocalCluster {
     ) -> (Node, Option<ContactInfo>) {
         // Update the stored ContactInfo for this node
         let node = Node::new_localhost_with_pubkey(pubkey);
+        #[cfg(feature = "dev-context-only-utils")]
+        if let Some(emulator) = &self.network_emulator {
+            network_emulation::register_node(emulator, &node);
+        }
         cluster_validator_info.info.contact_info = node.info.clone();
         cluster_validator_info.config.rpc_addrs =
             Some((node.info.rpc().unwrap(), node.info.rpc_pubsub().unwrap()));
@@ -992,5 +1032,9 @@ impl Cluster for LocalCluster {
 impl Drop for LocalCluster {
     fn drop(&mut self) {
         self.close();
+        #[cfg(feature = "dev-context-only-utils")]
+        if self.network_emulator.take().is_some() {
+            net_emulator::uninstall();
+        }
     }
 }
@@ -0,0 +1,194 @@
+//! Emulated network conditions between the nodes of a local cluster
+//!
+//! Topologies are written in net-shaper's configuration format: the share of
+//! the nodes in each partition, and netem options for the traffic from one
+//! partition to another, e.g.
+//!
+//! ```json
+//! {"partitions": [50, 50], "interconnects": [{"a": 0, "b": 1, "config": "loss 100%"}]}
+//! ```
+//!
+//! A schedule lists such topologies with the time, in milliseconds after the
+//! cluster starts, each takes effect at, so partitions can form and heal:
+//!
+//! ```json
+//! [{"start_ms": 0, "partitions": [100], "interconnects": []},
+//!  {"start_ms": 10000, "partitions": [50, 50], "interconnects": [...]}]
+//! ```
+
+use {
+    serde_derive::{Deserialize, Serialize},
+    trezoa_gossip::cluster_info::Node,
+    trezoa_streamer::net_emulator::{NetworkEmulator, Topology},
+    std::{collections::HashMap, fs, net::SocketAddr, path::Path, time::Duration},
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NetworkInterconnect {
+    /// Sending partition
+    pub a: u8,
+    /// Receiving partition
+    pub b: u8,
+    /// netem options, e.g. `loss 10% delay 100ms 10ms`
+    pub config: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NetworkTopology {
+    pub partitions: Vec<u8>,
+    pub interconnects: Vec<NetworkInterconnect>,
+}
+
+impl Default for NetworkTopology {
+    fn default() -> Self {
+        Self {
+            partitions: vec![100],
+            interconnects: vec![],
+        }
+    }
+}
+
+impl NetworkTopology {
+    /// Partitions cut off from each other
+    pub fn new_partitioned(partitions: Vec<u8>) -> Self {
+        Self::new_with_config(partitions, "loss 100%")
+    }
+
+    /// Partitions linked to each other with the same netem options
+    pub fn new_with_config(partitions: Vec<u8>, config: &str) -> Self {
+        let num_partitions = partitions.len() as u8;
+        let interconnects = (0..num_partitions)
+            .flat_map(|a| (0..num_partitions).map(move |b| (a, b)))
+            .filter(|(a, b)| a != b)
+            .map(|(a, b)| NetworkInterconnect {
+                a,
+                b,
+                config: config.to_string(),
+            })
+            .collect();
+        Self {
+            partitions,
+            interconnects,
+        }
+    }
+
+    pub fn verify(&self) -> Result<(), String> {
+        let sum: u32 = self.partitions.iter().copied().map(u32::from).sum();
+        if sum != 100 {
+            return Err(format!(
+                "partitions {:?} don't add up to 100",
+                self.partitions
+            ));
+        }
+        let num_partitions = self.partitions.len();
+        for interconnect in &self.interconnects {
+            if usize::from(interconnect.a) >= num_partitions
+                || usize::from(interconnect.b) >= num_partitions
+            {
+                return Err(format!(
+                    "interconnect {} -> {} refers to an unknown partition",
+                    interconnect.a, interconnect.b
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn to_topology(&self) -> Result<Topology, String> {
+        self.verify()?;
+        let links = self
+            .interconnects
+            .iter()
+            .map(|interconnect| {
+                let link = interconnect.config.parse()?;
+                let key = (usize::from(interconnect.a), usize::from(interconnect.b));
+                Ok((key, link))
+            })
+            .collect::<Result<HashMap<_, _>, String>>()?;
+        Ok(Topology {
+            partitions: self.partitions.clone(),
+            links,
+        })
+    }
+}
+
+/// A topology, and when it takes effect
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NetworkScheduleEntry {
+    /// Milliseconds after the cluster starts
+    #[serde(default)]
+    pub start_ms: u64,
+    #[serde(flatten)]
+    pub topology: NetworkTopology,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NetworkEmulationConfig {
+    pub schedule: Vec<NetworkScheduleEntry>,
+}
+
+impl NetworkEmulationConfig {
+    /// The same topology for the cluster's whole lifetime
+    pub fn new(topology: NetworkTopology) -> Self {
+        Self::default().with_topology_at(Duration::ZERO, topology)
+    }
+
+    /// Switch to the topology once the cluster has run for `start`
+    pub fn with_topology_at(mut self, start: Duration, topology: NetworkTopology) -> Self {
+        self.schedule.push(NetworkScheduleEntry {
+            start_ms: start.as_millis() as u64,
+            topology,
+        });
+        self
+    }
+
+    /// Load a net-shaper topology file, or a schedule of topologies
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let config = fs::read_to_string(path)
+            .map_err(|err| format!("unable to read {}: {err}", path.display()))?;
+        if let Ok(topology) = serde_json::from_str::<NetworkTopology>(&config) {
+            return Ok(Self::new(topology));
+        }
+        let schedule = serde_json::from_str(&config)
+            .map_err(|err| format!("unable to parse {}: {err}", path.display()))?;
+        Ok(Self { schedule })
+    }
+
+    pub fn new_emulator(&self, num_nodes: usize) -> Result<NetworkEmulator, String> {
+        let schedule = self
+            .schedule
+            .iter()
+            .map(|entry| {
+                let start = Duration::from_millis(entry.start_ms);
+                Ok((start, entry.topology.to_topology()?))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(NetworkEmulator::new(num_nodes, schedule))
+    }
+}
+
+/// Attribute the traffic of all the node's sockets to it
+pub(crate) fn register_node(emulator: &NetworkEmulator, node: &Node) -> usize {
+    let sockets = &node.sockets;
+    let addrs = [
+        &sockets.gossip,
+        &sockets.tvu_quic,
+        &sockets.repair,
+        &sockets.serve_repair,
+        &sockets.serve_repair_quic,
+        &sockets.ancestor_hashes_requests,
+        &sockets.tpu_quic,
+        &sockets.tpu_forwards_quic,
+    ]
+    .into_iter()
+    .chain(&sockets.gossip_quic)
+    .chain(&sockets.tvu)
+    .chain(&sockets.tpu)
+    .chain(&sockets.tpu_forwards)
+    .chain(&sockets.tpu_vote)
+    .chain(&sockets.broadcast)
+    .chain(&sockets.retransmit_sockets)
+    .filter_map(|socket| socket.local_addr().ok())
+    .collect::<Vec<SocketAddr>>();
+    emulator.register_node(*node.info.pubkey(), addrs)
+}
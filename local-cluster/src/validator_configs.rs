@@ -28,7 +28,9 @@ pub fn safe_clone_config(config: &ValidatorConfig) -> ValidatorConfig {
         known_validators: config.known_validators.clone(),
         repair_validators: config.repair_validators.clone(),
         repair_whitelist: config.repair_whitelist.clone(),
+        repair_trace_notifier: config.repair_trace_notifier.clone(),
         gossip_validators: config.gossip_validators.clone(),
+        gossip_quic: config.gossip_quic,
         accounts_hash_interval_slots: config.accounts_hash_interval_slots,
         max_genesis_archive_unpacked_size: config.max_genesis_archive_unpacked_size,
         wal_recovery_mode: config.wal_recovery_mode.clone(),
@@ -51,6 +53,7 @@ pub fn safe_clone_config(config: &ValidatorConfig) -> ValidatorConfig {
         accounts_db_skip_shrink: config.accounts_db_skip_shrink,
         accounts_db_force_initial_clean: config.accounts_db_force_initial_clean,
         tpu_coalesce: config.tpu_coalesce,
+        tpu_rate_limits: config.tpu_rate_limits,
         staked_nodes_overrides: config.staked_nodes_overrides.clone(),
         validator_exit: Arc::new(RwLock::new(Exit::default())),
         poh_hashes_per_batch: config.poh_hashes_per_batch,
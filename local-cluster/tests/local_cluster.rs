@@ -44,6 +44,7 @@ use {
             ValidatorTestConfig, DEFAULT_CLUSTER_LAMPORTS, DEFAULT_NODE_STAKE, RUST_LOG_FILTER,
         },
         local_cluster::{ClusterConfig, LocalCluster},
+        network_emulation::{NetworkEmulationConfig, NetworkTopology},
         validator_configs::*,
     },
     trezoa_pubsub_client::pubsub_client::PubsubClient,
@@ -88,7 +89,7 @@ use {
     std::{
         collections::{BTreeSet, HashMap, HashSet},
         fs,
-        io::Read,
+        io::{Read, Write},
         iter,
         num::NonZeroUsize,
         path::Path,
@@ -134,6 +135,63 @@ fn test_local_cluster_start_and_exit_with_config() {
     assert_eq!(cluster.validators.len(), NUM_NODES);
 }
 
+#[test]
+fn test_network_emulation_config_load() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    write!(
+        file,
+        r#"{{"partitions": [50, 50], "interconnects": [{{"a": 0, "b": 1, "config": "loss 100%"}}]}}"#
+    )
+    .unwrap();
+    let config = NetworkEmulationConfig::load(file.path()).unwrap();
+    assert_eq!(config.schedule.len(), 1);
+    assert_eq!(config.schedule[0].start_ms, 0);
+    assert_eq!(config.schedule[0].topology.partitions, vec![50, 50]);
+    assert!(config.new_emulator(2).is_ok());
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    write!(
+        file,
+        r#"[{{"partitions": [100], "interconnects": []}},
+            {{"start_ms": 1000, "partitions": [50, 50], "interconnects": []}}]"#
+    )
+    .unwrap();
+    let config = NetworkEmulationConfig::load(file.path()).unwrap();
+    assert_eq!(config.schedule.len(), 2);
+    assert_eq!(config.schedule[1].start_ms, 1000);
+
+    // Partitions must add up to 100
+    let config = NetworkEmulationConfig::new(NetworkTopology::new_partitioned(vec![50, 40]));
+    assert!(config.new_emulator(2).is_err());
+}
+
+#[test]
+#[serial]
+fn test_local_cluster_network_emulation() {
+    trezoa_logger::setup_with_default(RUST_LOG_FILTER);
+    const NUM_NODES: usize = 2;
+    let topology = NetworkTopology::new_with_config(vec![50, 50], "delay 50ms 10ms");
+    let mut config = ClusterConfig {
+        validator_configs: make_identical_validator_configs(
+            &ValidatorConfig::default_for_test(),
+            NUM_NODES,
+        ),
+        node_stakes: vec![DEFAULT_NODE_STAKE; NUM_NODES],
+        cluster_lamports: DEFAULT_CLUSTER_LAMPORTS,
+        network_emulation: Some(NetworkEmulationConfig::new(topology)),
+        ..ClusterConfig::default()
+    };
+    let cluster = LocalCluster::new(&mut config, SocketAddrSpace::Unspecified);
+    cluster.check_for_new_roots(
+        16,
+        "test_local_cluster_network_emulation",
+        SocketAddrSpace::Unspecified,
+    );
+    let emulator = cluster.network_emulator().unwrap();
+    assert!(emulator.num_delayed() > 0);
+    assert_eq!(emulator.num_dropped(), 0);
+}
+
 #[test]
 #[serial]
 fn test_spend_and_verify_all_nodes_1() {
@@ -480,6 +480,10 @@ impl PohRecorder {
         self.start_bank.slot()
     }
 
+    pub fn start_bank(&self) -> Arc<Bank> {
+        self.start_bank.clone()
+    }
+
     /// Returns if the leader slot has been reached along with the current poh
     /// slot and the parent slot (could be a few slots ago if any previous
     /// leaders needed to be skipped).
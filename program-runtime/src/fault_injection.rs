@@ -0,0 +1,178 @@
+//! Faults injected into transaction processing, for testing
+//!
+//! Faults are matched against each transaction independently, so the same
+//! transaction always fails in the same way no matter what was processed
+//! before it.
+
+use {
+    trezoa_sdk::{instruction::InstructionError, pubkey::Pubkey, signature::Signature},
+    std::sync::RwLock,
+};
+
+/// A single fault to inject
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Fail the `invocation`th (zero based) cross-program invocation into
+    /// `program_id` within a transaction with `error`, without running it
+    FailInvoke {
+        program_id: Pubkey,
+        invocation: usize,
+        error: InstructionError,
+    },
+    /// Limit the compute units available to the top-level instruction at
+    /// `instruction_index`, including its inner instructions, to
+    /// `compute_unit_limit`
+    LimitComputeUnits {
+        instruction_index: usize,
+        compute_unit_limit: u64,
+    },
+    /// Resize the data of `pubkey` to `data_len` bytes right before the
+    /// top-level instruction at `instruction_index`, as if another writer had
+    /// reallocated the account in the meantime. Has no effect on transactions
+    /// which do not load the account.
+    ReallocAccount {
+        pubkey: Pubkey,
+        instruction_index: usize,
+        data_len: usize,
+    },
+    /// Reject the transaction with `signature` as if its blockhash expired
+    DropTransaction { signature: Signature },
+}
+
+/// Set of faults shared between the runtime and the code configuring it
+#[derive(Debug, Default)]
+pub struct FaultInjection {
+    faults: RwLock<Vec<Fault>>,
+}
+
+impl FaultInjection {
+    pub fn new(faults: Vec<Fault>) -> Self {
+        Self {
+            faults: RwLock::new(faults),
+        }
+    }
+
+    pub fn add(&self, fault: Fault) {
+        self.faults.write().unwrap().push(fault);
+    }
+
+    pub fn clear(&self) {
+        self.faults.write().unwrap().clear();
+    }
+
+    pub fn faults(&self) -> Vec<Fault> {
+        self.faults.read().unwrap().clone()
+    }
+
+    /// Error to fail the `invocation`th cross-program invocation into
+    /// `program_id` with, if any
+    pub fn invoke_error(&self, program_id: &Pubkey, invocation: usize) -> Option<InstructionError> {
+        self.faults
+            .read()
+            .unwrap()
+            .iter()
+            .find_map(|fault| match fault {
+                Fault::FailInvoke {
+                    program_id: fault_program_id,
+                    invocation: fault_invocation,
+                    error,
+                } if fault_program_id == program_id && *fault_invocation == invocation => {
+                    Some(error.clone())
+                }
+                _ => None,
+            })
+    }
+
+    /// Lowest compute unit limit injected for the top-level instruction at
+    /// `instruction_index`, if any
+    pub fn compute_unit_limit(&self, instruction_index: usize) -> Option<u64> {
+        self.faults
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|fault| match fault {
+                Fault::LimitComputeUnits {
+                    instruction_index: fault_instruction_index,
+                    compute_unit_limit,
+                } if *fault_instruction_index == instruction_index => Some(*compute_unit_limit),
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Accounts to resize right before the top-level instruction at
+    /// `instruction_index`, along with their new data length
+    pub fn reallocations(&self, instruction_index: usize) -> Vec<(Pubkey, usize)> {
+        self.faults
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|fault| match fault {
+                Fault::ReallocAccount {
+                    pubkey,
+                    instruction_index: fault_instruction_index,
+                    data_len,
+                } if *fault_instruction_index == instruction_index => Some((*pubkey, *data_len)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn should_drop_transaction(&self, signature: &Signature) -> bool {
+        self.faults.read().unwrap().iter().any(|fault| {
+            matches!(fault, Fault::DropTransaction { signature: fault_signature }
+                if fault_signature == signature)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fault_matching() {
+        let program_id = Pubkey::new_unique();
+        let pubkey = Pubkey::new_unique();
+        let signature = Signature::new_unique();
+        let fault_injection = FaultInjection::new(vec![
+            Fault::FailInvoke {
+                program_id,
+                invocation: 1,
+                error: InstructionError::Custom(7),
+            },
+            Fault::LimitComputeUnits {
+                instruction_index: 2,
+                compute_unit_limit: 500,
+            },
+            Fault::LimitComputeUnits {
+                instruction_index: 2,
+                compute_unit_limit: 200,
+            },
+            Fault::ReallocAccount {
+                pubkey,
+                instruction_index: 1,
+                data_len: 10,
+            },
+        ]);
+
+        assert_eq!(fault_injection.invoke_error(&program_id, 0), None);
+        assert_eq!(
+            fault_injection.invoke_error(&program_id, 1),
+            Some(InstructionError::Custom(7))
+        );
+        assert_eq!(fault_injection.invoke_error(&Pubkey::new_unique(), 1), None);
+        assert_eq!(fault_injection.compute_unit_limit(0), None);
+        assert_eq!(fault_injection.compute_unit_limit(2), Some(200));
+        assert_eq!(fault_injection.reallocations(0), vec![]);
+        assert_eq!(fault_injection.reallocations(1), vec![(pubkey, 10)]);
+
+        assert!(!fault_injection.should_drop_transaction(&signature));
+        fault_injection.add(Fault::DropTransaction { signature });
+        assert!(fault_injection.should_drop_transaction(&signature));
+
+        fault_injection.clear();
+        assert!(fault_injection.faults().is_empty());
+        assert_eq!(fault_injection.invoke_error(&program_id, 1), None);
+    }
+}
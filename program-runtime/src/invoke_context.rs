@@ -1,6 +1,7 @@
 use {
     crate::{
         compute_budget::ComputeBudget,
+        fault_injection::FaultInjection,
         ic_msg,
         loaded_programs::{LoadedProgram, LoadedProgramType, LoadedProgramsForTxBatch},
         log_collector::LogCollector,
@@ -33,6 +34,7 @@ use {
     std::{
         alloc::Layout,
         cell::RefCell,
+        collections::HashMap,
         fmt::{self, Debug},
         rc::Rc,
         sync::{atomic::Ordering, Arc},
@@ -168,6 +170,9 @@ pub struct InvokeContext<'a> {
     pub lamports_per_signature: u64,
     pub syscall_context: Vec<Option<SyscallContext>>,
     traces: Vec<Vec<[u64; 12]>>,
+    fault_injection: Option<&'a FaultInjection>,
+    /// number of cross-program invocations into each program so far
+    invocation_counts: HashMap<Pubkey, usize>,
 }
 
 impl<'a> InvokeContext<'a> {
@@ -198,9 +203,16 @@ impl<'a> InvokeContext<'a> {
             lamports_per_signature,
             syscall_context: Vec::new(),
             traces: Vec::new(),
+            fault_injection: None,
+            invocation_counts: HashMap::new(),
         }
     }
 
+    /// Inject the given faults into the instructions processed from now on
+    pub fn set_fault_injection(&mut self, fault_injection: Option<&'a FaultInjection>) {
+        self.fault_injection = fault_injection;
+    }
+
     pub fn find_program_in_cache(&self, pubkey: &Pubkey) -> Option<Arc<LoadedProgram>> {
         // First lookup the cache of the programs modified by the current transaction. If not found, lookup
         // the cache of the cache of the programs that are loaded for the transaction batch.
@@ -424,6 +436,11 @@ impl<'a> InvokeContext<'a> {
         timings: &mut ExecuteTimings,
     ) -> Result<(), InstructionError> {
         *compute_units_consumed = 0;
+        if let Some(fault_injection) = self.fault_injection {
+            if self.get_stack_height() > 0 {
+                self.check_invoke_fault(fault_injection, program_indices)?;
+            }
+        }
         self.transaction_context
             .get_next_instruction_context()?
             .configure(program_indices, instruction_accounts, instruction_data);
@@ -434,6 +451,35 @@ impl<'a> InvokeContext<'a> {
             .and(self.pop())
     }
 
+    /// Count a cross-program invocation and fail it if a fault is injected for it
+    fn check_invoke_fault(
+        &mut self,
+        fault_injection: &FaultInjection,
+        program_indices: &[IndexOfAccount],
+    ) -> Result<(), InstructionError> {
+        let program_index = program_indices
+            .last()
+            .ok_or(InstructionError::UnsupportedProgramId)?;
+        let program_id = *self
+            .transaction_context
+            .get_key_of_account_at_index(*program_index)?;
+        let invocation = self.invocation_counts.entry(program_id).or_default();
+        let error = fault_injection.invoke_error(&program_id, *invocation);
+        *invocation = invocation.saturating_add(1);
+        match error {
+            Some(error) => {
+                ic_msg!(
+                    self,
+                    "Injected fault into invoke of {}: {}",
+                    program_id,
+                    error
+                );
+                Err(error)
+            }
+            None => Ok(()),
+        }
+    }
+
     /// Calls the instruction's program entrypoint method
     fn process_executable_chain(
         &mut self,
@@ -550,6 +596,21 @@ impl<'a> InvokeContext<'a> {
         Ok(())
     }
 
+    /// Cap the compute units remaining at `limit`, returning the units that
+    /// were remaining before
+    pub(crate) fn cap_remaining(&self, limit: u64) -> u64 {
+        let mut compute_meter = self.compute_meter.borrow_mut();
+        let remaining = *compute_meter;
+        *compute_meter = remaining.min(limit);
+        remaining
+    }
+
+    /// Undo [`cap_remaining`](Self::cap_remaining) once `consumed` units have
+    /// been used under the cap
+    pub(crate) fn uncap_remaining(&self, remaining: u64, consumed: u64) {
+        *self.compute_meter.borrow_mut() = remaining.saturating_sub(consumed);
+    }
+
     /// Set compute units
     ///
     /// Only use for tests and benchmarks
@@ -12,6 +12,7 @@ extern crate trezoa_metrics;
 pub use trezoa_rbpf;
 pub mod compute_budget;
 pub mod compute_budget_processor;
+pub mod fault_injection;
 pub mod invoke_context;
 pub mod loaded_programs;
 pub mod log_collector;
@@ -1,6 +1,7 @@
 use {
     crate::{
         compute_budget::ComputeBudget,
+        fault_injection::FaultInjection,
         invoke_context::InvokeContext,
         loaded_programs::LoadedProgramsForTxBatch,
         log_collector::LogCollector,
@@ -56,6 +57,7 @@ impl MessageProcessor {
         blockhash: Hash,
         lamports_per_signature: u64,
         accumulated_consumed_units: &mut u64,
+        fault_injection: Option<&FaultInjection>,
     ) -> Result<(), TransactionError> {
         let mut invoke_context = InvokeContext::new(
             transaction_context,
@@ -68,6 +70,7 @@ impl MessageProcessor {
             blockhash,
             lamports_per_signature,
         );
+        invoke_context.set_fault_injection(fault_injection);
 
         debug_assert_eq!(program_indices.len(), message.instructions().len());
         for (instruction_index, ((program_id, instruction), program_indices)) in message
@@ -95,6 +98,23 @@ impl MessageProcessor {
                 );
             }
 
+            if let Some(fault_injection) = fault_injection {
+                for (pubkey, data_len) in fault_injection.reallocations(instruction_index) {
+                    let Some(account_index) = invoke_context
+                        .transaction_context
+                        .find_index_of_account(&pubkey)
+                    else {
+                        continue;
+                    };
+                    invoke_context
+                        .transaction_context
+                        .get_account_at_index(account_index)
+                        .map_err(|_| TransactionError::InvalidAccountIndex)?
+                        .borrow_mut()
+                        .resize(data_len, 0);
+                }
+            }
+
             let mut instruction_accounts = Vec::with_capacity(instruction.accounts.len());
             for (instruction_account_index, index_in_transaction) in
                 instruction.accounts.iter().enumerate()
@@ -135,6 +155,11 @@ impl MessageProcessor {
             } else {
                 let mut time = Measure::start("execute_instruction");
                 let mut compute_units_consumed = 0;
+                let compute_unit_limit = fault_injection.and_then(|fault_injection| {
+                    fault_injection.compute_unit_limit(instruction_index)
+                });
+                let remaining_units = compute_unit_limit
+                    .map(|compute_unit_limit| invoke_context.cap_remaining(compute_unit_limit));
                 let result = invoke_context.process_instruction(
                     &instruction.data,
                     &instruction_accounts,
@@ -142,6 +167,10 @@ impl MessageProcessor {
                     &mut compute_units_consumed,
                     timings,
                 );
+                if let Some(remaining_units) = remaining_units {
+                    // The rest of the transaction keeps its own budget
+                    invoke_context.uncap_remaining(remaining_units, compute_units_consumed);
+                }
                 time.stop();
                 *accumulated_consumed_units =
                     accumulated_consumed_units.saturating_add(compute_units_consumed);
@@ -306,6 +335,7 @@ mod tests {
             Hash::default(),
             0,
             &mut 0,
+            None,
         );
         assert!(result.is_ok());
         assert_eq!(
@@ -354,6 +384,7 @@ mod tests {
             Hash::default(),
             0,
             &mut 0,
+            None,
         );
         assert_eq!(
             result,
@@ -392,6 +423,7 @@ mod tests {
             Hash::default(),
             0,
             &mut 0,
+            None,
         );
         assert_eq!(
             result,
@@ -521,6 +553,7 @@ mod tests {
             Hash::default(),
             0,
             &mut 0,
+            None,
         );
         assert_eq!(
             result,
@@ -554,6 +587,7 @@ mod tests {
             Hash::default(),
             0,
             &mut 0,
+            None,
         );
         assert!(result.is_ok());
 
@@ -584,6 +618,7 @@ mod tests {
             Hash::default(),
             0,
             &mut 0,
+            None,
         );
         assert!(result.is_ok());
         assert_eq!(
@@ -675,6 +710,7 @@ mod tests {
             Hash::default(),
             0,
             &mut 0,
+            None,
         );
 
         assert_eq!(
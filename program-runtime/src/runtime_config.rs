@@ -1,4 +1,7 @@
-use crate::compute_budget::ComputeBudget;
+use {
+    crate::{compute_budget::ComputeBudget, fault_injection::FaultInjection},
+    std::sync::Arc,
+};
 
 #[cfg(RUSTC_WITH_SPECIALIZATION)]
 impl ::trezoa_frozen_abi::abi_example::AbiExample for RuntimeConfig {
@@ -14,4 +17,6 @@ pub struct RuntimeConfig {
     pub compute_budget: Option<ComputeBudget>,
     pub log_messages_bytes_limit: Option<usize>,
     pub transaction_account_lock_limit: Option<usize>,
+    /// Faults to inject into transaction processing, for testing only
+    pub fault_injection: Option<Arc<FaultInjection>>,
 }
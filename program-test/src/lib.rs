@@ -16,9 +16,9 @@ use {
     trezoa_banks_server::banks_server::start_local_server,
     trezoa_bpf_loader_program::serialization::serialize_parameters,
     trezoa_program_runtime::{
-        compute_budget::ComputeBudget, ic_msg, invoke_context::BuiltinFunctionWithContext,
-        loaded_programs::LoadedProgram, runtime_config::RuntimeConfig, stable_log,
-        timings::ExecuteTimings,
+        compute_budget::ComputeBudget, fault_injection::FaultInjection, ic_msg,
+        invoke_context::BuiltinFunctionWithContext, loaded_programs::LoadedProgram,
+        runtime_config::RuntimeConfig, stable_log, timings::ExecuteTimings,
     },
     trezoa_runtime::{
         accounts_background_service::{AbsRequestSender, SnapshotRequestKind},
@@ -68,11 +68,14 @@ use {
 pub use {
     trezoa_banks_client::{BanksClient, BanksClientError},
     trezoa_banks_interface::BanksTransactionResultWithMetadata,
-    trezoa_program_runtime::invoke_context::InvokeContext,
+    trezoa_program_runtime::{fault_injection::Fault, invoke_context::InvokeContext},
     trezoa_rbpf::{
         error::EbpfError,
         vm::{get_runtime_environment_key, EbpfVm},
     },
+    trezoa_runtime::{
+        bank::account_source::AccountSource, snapshot_account_source::SnapshotArchiveAccountSource,
+    },
     trezoa_sdk::transaction_context::IndexOfAccount,
 };
 
@@ -84,6 +87,9 @@ pub enum ProgramTestError {
     /// The chosen warp slot is not in the future, so warp is not performed
     #[error("Warp slot not in the future")]
     InvalidWarpSlot,
+    /// The snapshot is unknown or its bank has been rooted past or discarded
+    #[error("Snapshot cannot be restored")]
+    InvalidSnapshot,
 }
 
 thread_local! {
@@ -468,6 +474,8 @@ pub struct ProgramTest {
     prefer_bpf: bool,
     deactivate_feature_set: HashSet<Pubkey>,
     transaction_account_lock_limit: Option<usize>,
+    account_source: Option<Arc<dyn AccountSource>>,
+    fault_injection: Arc<FaultInjection>,
 }
 
 impl Default for ProgramTest {
@@ -500,6 +508,8 @@ impl Default for ProgramTest {
             prefer_bpf,
             deactivate_feature_set: HashSet::default(),
             transaction_account_lock_limit: None,
+            account_source: None,
+            fault_injection: Arc::default(),
         }
     }
 }
@@ -548,6 +558,31 @@ impl ProgramTest {
         self.set_compute_max_units(bpf_compute_max_units);
     }
 
+    /// Resolve accounts missing from the test environment from `account_source`
+    ///
+    /// Accounts are fetched lazily: the accounts a transaction references are
+    /// stored in the working bank before the transaction is executed, and
+    /// `BanksClient` reads of accounts not stored yet look through to the
+    /// source.
+    /// Accounts added with [`add_account`](Self::add_account) and the
+    /// environment's own accounts take precedence over the source. Use a
+    /// [`SnapshotArchiveAccountSource`] to run against the state captured in a
+    /// full snapshot archive.
+    pub fn with_account_source(&mut self, account_source: Arc<dyn AccountSource>) {
+        self.account_source = Some(account_source);
+    }
+
+    /// Inject `fault` into the processing of every transaction in the test
+    /// environment
+    ///
+    /// Faults are matched against each transaction on its own, so a transaction
+    /// fails the same way however many transactions ran before it. Use
+    /// [`ProgramTestContext::inject_fault`] to change the faults once the test
+    /// environment is running.
+    pub fn inject_fault(&mut self, fault: Fault) {
+        self.fault_injection.add(fault);
+    }
+
     /// Add an account to the test environment
     pub fn add_account(&mut self, address: Pubkey, account: Account) {
         self.accounts
@@ -814,6 +849,7 @@ impl ProgramTest {
                     ..ComputeBudget::default()
                 }),
                 transaction_account_lock_limit: self.transaction_account_lock_limit,
+                fault_injection: Some(self.fault_injection.clone()),
                 ..RuntimeConfig::default()
             }),
             Vec::default(),
@@ -828,6 +864,10 @@ impl ProgramTest {
             Arc::default(),
         );
 
+        if let Some(account_source) = self.account_source.take() {
+            bank.set_account_source(account_source);
+        }
+
         // Add commonly-used TPL programs as a convenience to the user
         for (program_id, account) in programs::tpl_programs(&Rent::default()).iter() {
             bank.store_account(program_id, account);
@@ -929,6 +969,7 @@ impl ProgramTest {
             banks_client,
             last_blockhash,
             gci,
+            self.fault_injection,
         )
     }
 }
@@ -1010,6 +1051,10 @@ impl<T> Drop for DroppableTask<T> {
     }
 }
 
+/// Handle to a bank state captured by [`ProgramTestContext::snapshot`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SnapshotId(u64);
+
 pub struct ProgramTestContext {
     pub banks_client: BanksClient,
     pub last_blockhash: Hash,
@@ -1017,6 +1062,9 @@ pub struct ProgramTestContext {
     genesis_config: GenesisConfig,
     bank_forks: Arc<RwLock<BankForks>>,
     block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
+    snapshots: HashMap<SnapshotId, Arc<Bank>>,
+    next_snapshot_id: u64,
+    fault_injection: Arc<FaultInjection>,
     _bank_task: DroppableTask<()>,
 }
 
@@ -1027,6 +1075,7 @@ impl ProgramTestContext {
         banks_client: BanksClient,
         last_blockhash: Hash,
         genesis_config_info: GenesisConfigInfo,
+        fault_injection: Arc<FaultInjection>,
     ) -> Self {
         // Run a simulated PohService to provide the client with new blockhashes.  New blockhashes
         // are required when sending multiple otherwise identical transactions in series from a
@@ -1063,6 +1112,9 @@ impl ProgramTestContext {
             genesis_config: genesis_config_info.genesis_config,
             bank_forks,
             block_commitment_cache,
+            snapshots: HashMap::default(),
+            next_snapshot_id: 0,
+            fault_injection,
             _bank_task: bank_task,
         }
     }
@@ -1071,6 +1123,16 @@ impl ProgramTestContext {
         &self.genesis_config
     }
 
+    /// Inject `fault` into the processing of later transactions
+    pub fn inject_fault(&self, fault: Fault) {
+        self.fault_injection.add(fault);
+    }
+
+    /// Remove all injected faults
+    pub fn clear_faults(&self) {
+        self.fault_injection.clear();
+    }
+
     /// Manually increment vote credits for the current epoch in the specified vote account to simulate validator voting activity
     pub fn increment_vote_account_credits(
         &mut self,
@@ -1243,6 +1305,118 @@ impl ProgramTestContext {
         Ok(())
     }
 
+    /// Capture the current bank state so it can later be rolled back to with
+    /// [`restore`](Self::restore).
+    ///
+    /// The working bank is frozen and kept as the snapshot, and a new working
+    /// bank is created on top of it at the next slot. Snapshots share account
+    /// storage with their descendants, so taking one is cheap.
+    pub fn snapshot(&mut self) -> SnapshotId {
+        let mut bank_forks = self.bank_forks.write().unwrap();
+        let bank = bank_forks.working_bank();
+
+        // Fill ticks until a new blockhash is recorded, otherwise retried transactions will have
+        // the same signature
+        bank.fill_bank_with_ticks_for_tests();
+        bank.freeze();
+
+        let snapshot_id = SnapshotId(self.next_snapshot_id);
+        self.next_snapshot_id += 1;
+        self.snapshots.insert(snapshot_id, bank.clone());
+
+        let slot = bank.slot() + 1;
+        bank_forks.insert(Bank::new_from_parent(bank, &Pubkey::default(), slot));
+        Self::set_working_slot(
+            &self.block_commitment_cache,
+            &mut self.last_blockhash,
+            &bank_forks,
+            slot,
+        );
+        snapshot_id
+    }
+
+    /// Roll back accounts, sysvars and slot to the state captured by `snapshot_id`
+    ///
+    /// All banks built on top of the snapshot are discarded, along with any
+    /// snapshots taken from them, and a fresh working bank is created at the
+    /// slot that followed the snapshot. The same snapshot may be restored any
+    /// number of times. Snapshots older than the root, for example those taken
+    /// before a warp, can no longer be restored.
+    pub fn restore(&mut self, snapshot_id: SnapshotId) -> Result<(), ProgramTestError> {
+        let snapshot_bank = self
+            .snapshots
+            .get(&snapshot_id)
+            .cloned()
+            .ok_or(ProgramTestError::InvalidSnapshot)?;
+        let snapshot_slot = snapshot_bank.slot();
+
+        let mut bank_forks = self.bank_forks.write().unwrap();
+        if !Self::is_restorable(&bank_forks, &snapshot_bank) {
+            self.snapshots.remove(&snapshot_id);
+            return Err(ProgramTestError::InvalidSnapshot);
+        }
+
+        // Drop every bank built on top of the snapshot, the same way replay
+        // purges the descendants of a duplicate slot
+        let descendants = bank_forks
+            .descendants()
+            .remove(&snapshot_slot)
+            .unwrap_or_default();
+        let (slots_to_purge, removed_banks): (Vec<_>, Vec<_>) = descendants
+            .into_iter()
+            .map(|slot| {
+                let bank = bank_forks
+                    .remove(slot)
+                    .expect("descendant bank is in bank forks");
+                ((slot, bank.bank_id()), bank)
+            })
+            .unzip();
+        snapshot_bank.remove_unrooted_slots(&slots_to_purge);
+        drop(removed_banks);
+        for (slot, _bank_id) in slots_to_purge {
+            snapshot_bank.clear_slot_signatures(slot);
+            snapshot_bank.prune_program_cache_by_deployment_slot(slot);
+        }
+        self.snapshots
+            .retain(|_, bank| Self::is_restorable(&bank_forks, bank));
+
+        let slot = snapshot_slot + 1;
+        bank_forks.insert(Bank::new_from_parent(
+            snapshot_bank,
+            &Pubkey::default(),
+            slot,
+        ));
+        Self::set_working_slot(
+            &self.block_commitment_cache,
+            &mut self.last_blockhash,
+            &bank_forks,
+            slot,
+        );
+        Ok(())
+    }
+
+    fn is_restorable(bank_forks: &BankForks, snapshot_bank: &Bank) -> bool {
+        bank_forks
+            .get(snapshot_bank.slot())
+            .map(|bank| bank.bank_id() == snapshot_bank.bank_id())
+            .unwrap_or(false)
+    }
+
+    fn set_working_slot(
+        block_commitment_cache: &RwLock<BlockCommitmentCache>,
+        last_blockhash: &mut Hash,
+        bank_forks: &BankForks,
+        slot: Slot,
+    ) {
+        // Update block commitment cache, otherwise banks server will poll at
+        // the wrong slot
+        block_commitment_cache
+            .write()
+            .unwrap()
+            .set_all_slots(slot, slot);
+        *last_blockhash = bank_forks.working_bank().last_blockhash();
+    }
+
     /// Get a new latest blockhash, similar in spirit to RpcClient::get_latest_blockhash()
     pub async fn get_new_latest_blockhash(&mut self) -> io::Result<Hash> {
         let blockhash = self
@@ -0,0 +1,141 @@
+use {
+    trezoa_program_test::{AccountSource, ProgramTest},
+    trezoa_sdk::{
+        account::{Account, AccountSharedData},
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction, system_program,
+        transaction::Transaction,
+    },
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+};
+
+#[derive(Debug, Default)]
+struct MemoryAccountSource {
+    accounts: HashMap<Pubkey, AccountSharedData>,
+    requests: Mutex<Vec<Pubkey>>,
+}
+
+impl AccountSource for MemoryAccountSource {
+    fn get_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        self.requests.lock().unwrap().push(*pubkey);
+        self.accounts.get(pubkey).cloned()
+    }
+}
+
+#[tokio::test]
+async fn missing_accounts_resolved_from_source() {
+    let sourced = Keypair::new();
+    let overridden = Pubkey::new_unique();
+    let mut account_source = MemoryAccountSource::default();
+    account_source.accounts.insert(
+        sourced.pubkey(),
+        AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+    );
+    account_source.accounts.insert(
+        overridden,
+        AccountSharedData::new(1, 0, &system_program::id()),
+    );
+    let account_source = Arc::new(account_source);
+
+    let mut program_test = ProgramTest::default();
+    program_test.with_account_source(account_source.clone());
+    program_test.add_account(
+        overridden,
+        Account {
+            lamports: 42,
+            owner: system_program::id(),
+            ..Account::default()
+        },
+    );
+    let mut context = program_test.start_with_context().await;
+
+    assert_eq!(
+        context.banks_client.get_balance(overridden).await.unwrap(),
+        42
+    );
+    assert!(!account_source
+        .requests
+        .lock()
+        .unwrap()
+        .contains(&overridden));
+
+    // The sourced account can pay for and sign a transaction draining itself
+    let recipient = Pubkey::new_unique();
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &sourced.pubkey(),
+            &recipient,
+            500_000_000,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &sourced],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(sourced.pubkey())
+            .await
+            .unwrap(),
+        500_000_000
+    );
+    assert_eq!(
+        context.banks_client.get_balance(recipient).await.unwrap(),
+        500_000_000
+    );
+
+    // Accounts unknown to the source stay missing
+    let unknown = Pubkey::new_unique();
+    assert_eq!(
+        context.banks_client.get_account(unknown).await.unwrap(),
+        None
+    );
+    assert!(account_source.requests.lock().unwrap().contains(&unknown));
+}
+
+#[tokio::test]
+async fn deleted_accounts_not_resolved_again() {
+    let sourced = Keypair::new();
+    let mut account_source = MemoryAccountSource::default();
+    account_source.accounts.insert(
+        sourced.pubkey(),
+        AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+    );
+
+    let mut program_test = ProgramTest::default();
+    program_test.with_account_source(Arc::new(account_source));
+    let mut context = program_test.start_with_context().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &sourced.pubkey(),
+            &context.payer.pubkey(),
+            1_000_000_000,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &sourced],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    assert_eq!(
+        context
+            .banks_client
+            .get_account(sourced.pubkey())
+            .await
+            .unwrap(),
+        None
+    );
+}
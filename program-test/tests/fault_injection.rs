@@ -0,0 +1,250 @@
+use {
+    trezoa_program_test::{processor, BanksClientError, Fault, ProgramTest, ProgramTestContext},
+    trezoa_sdk::{
+        account::Account,
+        account_info::{next_account_info, AccountInfo},
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        program::invoke,
+        program_error::ProgramError,
+        pubkey::Pubkey,
+        signature::Signer,
+        system_instruction,
+        transaction::{Transaction, TransactionError},
+    },
+};
+
+// Invoke the program passed as the first account twice
+fn invoker_process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _input: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let invoked_program_info = next_account_info(account_info_iter)?;
+    for _ in 0..2 {
+        invoke(
+            &Instruction::new_with_bincode(*invoked_program_info.key, &[0], vec![]),
+            &[invoked_program_info.clone()],
+        )?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn invoked_process_instruction(
+    _program_id: &Pubkey,
+    _accounts: &[AccountInfo],
+    _input: &[u8],
+) -> ProgramResult {
+    Ok(())
+}
+
+// Fail with the data length of the first account unless it is still 4 bytes
+fn data_len_process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _input: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let account_info = next_account_info(account_info_iter)?;
+    match account_info.data_len() {
+        4 => Ok(()),
+        data_len => Err(ProgramError::Custom(data_len as u32)),
+    }
+}
+
+fn transfer_transaction(context: &ProgramTestContext) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &context.payer.pubkey(),
+            &Pubkey::new_unique(),
+            1_000_000,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    )
+}
+
+fn transaction_error(err: BanksClientError) -> TransactionError {
+    match err {
+        BanksClientError::TransactionError(err) => err,
+        BanksClientError::SimulationError { err, .. } => err,
+        err => panic!("unexpected error {err:?}"),
+    }
+}
+
+#[tokio::test]
+async fn fail_nth_invoke() {
+    let invoker_program_id = Pubkey::new_unique();
+    let invoked_program_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "invoker",
+        invoker_program_id,
+        processor!(invoker_process_instruction),
+    );
+    program_test.add_program(
+        "invoked",
+        invoked_program_id,
+        processor!(invoked_process_instruction),
+    );
+    program_test.inject_fault(Fault::FailInvoke {
+        program_id: invoked_program_id,
+        invocation: 1,
+        error: InstructionError::Custom(42),
+    });
+    let mut context = program_test.start_with_context().await;
+
+    // Every transaction counts invocations from zero
+    for _ in 0..2 {
+        let transaction = Transaction::new_signed_with_payer(
+            &[Instruction::new_with_bincode(
+                invoker_program_id,
+                &[0],
+                vec![AccountMeta::new_readonly(invoked_program_id, false)],
+            )],
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.last_blockhash,
+        );
+        let err = context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            transaction_error(err),
+            TransactionError::InstructionError(0, InstructionError::Custom(42))
+        );
+        context.get_new_latest_blockhash().await.unwrap();
+    }
+
+    context.clear_faults();
+    let transaction = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bincode(
+            invoker_program_id,
+            &[0],
+            vec![AccountMeta::new_readonly(invoked_program_id, false)],
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn limit_compute_units() {
+    let context = ProgramTest::default().start_with_context().await;
+    context.inject_fault(Fault::LimitComputeUnits {
+        instruction_index: 0,
+        compute_unit_limit: 1,
+    });
+
+    let err = context
+        .banks_client
+        .clone()
+        .process_transaction(transfer_transaction(&context))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        transaction_error(err),
+        TransactionError::InstructionError(0, InstructionError::ComputationalBudgetExceeded)
+    );
+}
+
+#[tokio::test]
+async fn drop_transaction() {
+    let mut context = ProgramTest::default().start_with_context().await;
+    let transaction = transfer_transaction(&context);
+    context.inject_fault(Fault::DropTransaction {
+        signature: transaction.signatures[0],
+    });
+
+    let err = context
+        .banks_client
+        .process_transaction(transaction.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(transaction_error(err), TransactionError::BlockhashNotFound);
+    assert_eq!(
+        context
+            .banks_client
+            .get_transaction_status(transaction.signatures[0])
+            .await
+            .unwrap(),
+        None
+    );
+
+    // Other transactions are unaffected
+    let transaction = transfer_transaction(&context);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn realloc_account() {
+    let program_id = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new(
+        "data_len",
+        program_id,
+        processor!(data_len_process_instruction),
+    );
+    program_test.add_account(
+        account,
+        Account {
+            lamports: 1_000_000_000,
+            data: vec![0; 4],
+            owner: program_id,
+            ..Account::default()
+        },
+    );
+    program_test.inject_fault(Fault::ReallocAccount {
+        pubkey: account,
+        instruction_index: 1,
+        data_len: 10,
+    });
+    let context = program_test.start_with_context().await;
+
+    let instruction =
+        Instruction::new_with_bincode(program_id, &[0], vec![AccountMeta::new(account, false)]);
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction.clone()],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .clone()
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // The account is resized between the first and second instruction
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction.clone(), instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    let err = context
+        .banks_client
+        .clone()
+        .process_transaction(transaction)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        transaction_error(err),
+        TransactionError::InstructionError(1, InstructionError::Custom(10))
+    );
+}
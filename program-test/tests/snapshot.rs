@@ -0,0 +1,106 @@
+use {
+    trezoa_program_test::{ProgramTest, ProgramTestContext, ProgramTestError},
+    trezoa_sdk::{
+        account::AccountSharedData,
+        pubkey::Pubkey,
+        signature::Signer,
+        system_instruction,
+        sysvar::clock::{self, Clock},
+        transaction::Transaction,
+    },
+};
+
+async fn transfer(context: &mut ProgramTestContext, to: &Pubkey, lamports: u64) {
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &context.payer.pubkey(),
+            to,
+            lamports,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+async fn get_lamports(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    context
+        .banks_client
+        .get_balance(*address)
+        .await
+        .expect("balance")
+}
+
+async fn get_clock_slot(context: &mut ProgramTestContext) -> u64 {
+    let clock_account = context
+        .banks_client
+        .get_account(clock::id())
+        .await
+        .expect("account exists")
+        .unwrap();
+    bincode::deserialize::<Clock>(&clock_account.data)
+        .unwrap()
+        .slot
+}
+
+#[tokio::test]
+async fn restore_rolls_back_accounts_and_slot() {
+    let mut context = ProgramTest::default().start_with_context().await;
+    let recipient = Pubkey::new_unique();
+
+    transfer(&mut context, &recipient, 1_000_000).await;
+    let snapshot = context.snapshot();
+    let snapshot_slot = get_clock_slot(&mut context).await;
+
+    // Same transaction can be processed again on each restored branch
+    for _ in 0..3 {
+        transfer(&mut context, &recipient, 2_000_000).await;
+        let created = Pubkey::new_unique();
+        context.set_account(&created, &AccountSharedData::new(42, 0, &Pubkey::default()));
+        assert_eq!(get_lamports(&mut context, &recipient).await, 3_000_000);
+        assert_eq!(get_lamports(&mut context, &created).await, 42);
+
+        context.restore(snapshot).unwrap();
+        assert_eq!(get_lamports(&mut context, &recipient).await, 1_000_000);
+        assert_eq!(get_lamports(&mut context, &created).await, 0);
+        assert_eq!(get_clock_slot(&mut context).await, snapshot_slot);
+    }
+}
+
+#[tokio::test]
+async fn restore_discards_newer_snapshots() {
+    let mut context = ProgramTest::default().start_with_context().await;
+    let recipient = Pubkey::new_unique();
+
+    let first = context.snapshot();
+    transfer(&mut context, &recipient, 1_000_000).await;
+    let second = context.snapshot();
+    transfer(&mut context, &recipient, 1_000_000).await;
+
+    context.restore(second).unwrap();
+    assert_eq!(get_lamports(&mut context, &recipient).await, 1_000_000);
+
+    context.restore(first).unwrap();
+    assert_eq!(get_lamports(&mut context, &recipient).await, 0);
+    assert_eq!(
+        context.restore(second).unwrap_err(),
+        ProgramTestError::InvalidSnapshot
+    );
+}
+
+#[tokio::test]
+async fn restore_fails_after_warp() {
+    let mut context = ProgramTest::default().start_with_context().await;
+
+    let snapshot = context.snapshot();
+    context.warp_to_slot(100).unwrap();
+    assert_eq!(
+        context.restore(snapshot).unwrap_err(),
+        ProgramTestError::InvalidSnapshot
+    );
+}
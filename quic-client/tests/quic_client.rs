@@ -10,8 +10,12 @@ mod tests {
         },
         trezoa_sdk::{net::DEFAULT_TPU_COALESCE, packet::PACKET_DATA_SIZE, signature::Keypair},
         trezoa_streamer::{
-            nonblocking::quic::DEFAULT_WAIT_FOR_CHUNK_TIMEOUT, quic::SpawnServerResult,
-            streamer::StakedNodes, tls_certificates::new_dummy_x509_certificate,
+            nonblocking::{
+                quic::DEFAULT_WAIT_FOR_CHUNK_TIMEOUT, rate_limiter::QuicRateLimitConfig,
+            },
+            quic::SpawnServerResult,
+            streamer::StakedNodes,
+            tls_certificates::new_dummy_x509_certificate,
         },
         std::{
             net::{SocketAddr, UdpSocket},
@@ -84,6 +88,8 @@ mod tests {
             10,
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
 
@@ -163,6 +169,8 @@ mod tests {
             10,
             Duration::from_secs(1), // wait_for_chunk_timeout
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
 
@@ -225,6 +233,8 @@ mod tests {
             10,
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
 
@@ -253,6 +263,8 @@ mod tests {
             10,
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
 
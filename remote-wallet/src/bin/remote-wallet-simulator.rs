@@ -0,0 +1,68 @@
+/// Runs a simulated Trezor hardware wallet listening for HID reports on a UDP socket
+///
+/// Point signers at it by setting TREZOA_REMOTE_WALLET_SIMULATOR to the bind
+/// address and using `usb://trezor` as the keypair path. The default seed is
+/// all zeros; the simulator is for tests only.
+use {
+    base64::{prelude::BASE64_STANDARD, Engine},
+    clap::{crate_description, crate_name, crate_version, value_t_or_exit, App, Arg},
+    trezoa_remote_wallet::simulator::{
+        TrezorSimulator, DEFAULT_SIMULATOR_ADDRESS, REMOTE_WALLET_SIMULATOR_ENV,
+    },
+    trezoa_sdk::{derivation_path::DerivationPath, signature::Signer},
+    std::{error, net::UdpSocket},
+};
+
+fn main() -> Result<(), Box<dyn error::Error>> {
+    let matches = App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .arg(
+            Arg::with_name("bind")
+                .long("bind")
+                .value_name("HOST:PORT")
+                .takes_value(true)
+                .default_value(DEFAULT_SIMULATOR_ADDRESS)
+                .help("Address to listen for HID reports on"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("BASE64")
+                .takes_value(true)
+                .validator(|value| {
+                    BASE64_STANDARD
+                        .decode(value)
+                        .map(|_| ())
+                        .map_err(|err| format!("invalid base64 seed: {err}"))
+                })
+                .help("Seed of the simulated wallet [default: all zeros]"),
+        )
+        .arg(
+            Arg::with_name("reject_signing")
+                .long("reject-signing")
+                .help("Reject every signing request, as if the user declined it"),
+        )
+        .get_matches();
+
+    let bind_address = value_t_or_exit!(matches, "bind", String);
+    let seed = match matches.value_of("seed") {
+        Some(seed) => BASE64_STANDARD.decode(seed)?,
+        None => vec![0; 64],
+    };
+
+    let mut simulator = TrezorSimulator::new(&seed);
+    if matches.is_present("reject_signing") {
+        simulator = simulator.reject_signing();
+    }
+    let pubkey = simulator.keypair(&DerivationPath::default())?.pubkey();
+    let socket = UdpSocket::bind(&bind_address)?;
+    println!(
+        "Remote wallet simulator listening on {}, base pubkey {}",
+        socket.local_addr()?,
+        pubkey
+    );
+    println!("Set {REMOTE_WALLET_SIMULATOR_ENV}={bind_address} to use it with usb://trezor");
+    simulator.serve(socket)?;
+    Ok(())
+}
@@ -0,0 +1,520 @@
+//! Signers backed by an external signer process
+//!
+//! An external signer lets key custody systems such as HSMs or cloud KMSs sign
+//! on behalf of command line tools without linking them in. It is located with
+//! one of the following URIs:
+//!
+//! - `exec://<program path>[?arg=<arg>&arg=<arg>...]` &mdash; spawn the program
+//!   with the given arguments and talk to it over its stdin and stdout. The
+//!   program is killed when the signer is dropped.
+//! - `plugin://<socket path>` &mdash; connect to a signer listening on a local
+//!   Unix domain socket.
+//!
+//! # Protocol
+//!
+//! Requests and responses are [JSON-RPC 2.0] objects, each serialized on a
+//! single line terminated by `\n`. Requests are sent one at a time and the
+//! signer must answer each of them before the next one is sent.
+//!
+//! - `get_pubkey` takes no parameters and returns the base-58 encoded pubkey
+//!   of the key used for signing.
+//!
+//!   `{"jsonrpc":"2.0","id":1,"method":"get_pubkey"}`
+//!
+//!   `{"jsonrpc":"2.0","id":1,"result":"9rPVSygg3brqghvdZ6wsL2i5YNQTGhXGdJzF65YxaCQd"}`
+//!
+//! - `sign_message` takes the base64 encoded message to sign and returns the
+//!   base-58 encoded ed25519 signature of it.
+//!
+//!   `{"jsonrpc":"2.0","id":2,"method":"sign_message","params":{"message":"AQAB..."}}`
+//!
+//!   `{"jsonrpc":"2.0","id":2,"result":"5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnb..."}`
+//!
+//! A signer refusing a request answers with an error object, for example
+//! `{"jsonrpc":"2.0","id":2,"error":{"code":1,"message":"rejected by policy"}}`.
+//!
+//! [JSON-RPC 2.0]: https://www.jsonrpc.org/specification
+
+use {
+    base64::{prelude::BASE64_STANDARD, Engine},
+    qstring::QString,
+    serde::{Deserialize, Serialize},
+    serde_json::{json, Value},
+    trezoa_sdk::{
+        pubkey::Pubkey,
+        signature::{Signature, Signer, SignerError},
+    },
+    std::{
+        fmt,
+        io::{self, BufRead, BufReader, Write},
+        path::PathBuf,
+        process::{Child, Command, Stdio},
+        str::FromStr,
+        sync::Mutex,
+    },
+    thiserror::Error,
+    uriparse::URIReference,
+};
+
+pub const EXTERNAL_SIGNER_EXEC_SCHEME: &str = "exec";
+pub const EXTERNAL_SIGNER_PLUGIN_SCHEME: &str = "plugin";
+
+const JSONRPC_VERSION: &str = "2.0";
+
+#[derive(Debug, Error)]
+pub enum ExternalSignerError {
+    #[error("invalid external signer uri: {0}")]
+    InvalidUri(String),
+    #[error("external signer io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("external signer protocol error: {0}")]
+    Protocol(String),
+    #[error("external signer error {code}: {message}")]
+    Signer { code: i64, message: String },
+    #[error("unsupported on this platform: {0}")]
+    Unsupported(&'static str),
+}
+
+impl From<serde_json::Error> for ExternalSignerError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Protocol(err.to_string())
+    }
+}
+
+impl From<ExternalSignerError> for SignerError {
+    fn from(err: ExternalSignerError) -> SignerError {
+        match err {
+            ExternalSignerError::Io(err) => SignerError::Connection(err.to_string()),
+            ExternalSignerError::Protocol(message) => SignerError::Protocol(message),
+            ExternalSignerError::Signer { message, .. } => SignerError::Custom(message),
+            err => SignerError::Custom(err.to_string()),
+        }
+    }
+}
+
+/// Where to find an external signer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExternalSignerLocator {
+    /// A program to spawn, talking over its stdin and stdout
+    Exec { program: PathBuf, args: Vec<String> },
+    /// A local socket to connect to
+    Plugin { socket: PathBuf },
+}
+
+impl ExternalSignerLocator {
+    pub fn new_from_uri(uri: &URIReference<'_>) -> Result<Self, ExternalSignerError> {
+        let scheme = uri
+            .scheme()
+            .map(|scheme| scheme.as_str().to_ascii_lowercase())
+            .ok_or_else(|| ExternalSignerError::InvalidUri("missing scheme".to_string()))?;
+        // Paths are split across the authority and path, so that both
+        // `exec:///usr/bin/signer` and `exec://./signer` work
+        let path = format!(
+            "{}{}",
+            uri.authority()
+                .map(|authority| authority.to_string())
+                .unwrap_or_default(),
+            uri.path()
+        );
+        if path.is_empty() {
+            return Err(ExternalSignerError::InvalidUri("missing path".to_string()));
+        }
+        let path = percent_decode(&path);
+        match scheme.as_str() {
+            EXTERNAL_SIGNER_EXEC_SCHEME => {
+                let args = uri
+                    .query()
+                    .map(|query| QString::from(query.as_str()).into_pairs())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(key, value)| {
+                        if key == "arg" {
+                            Ok(value)
+                        } else {
+                            Err(ExternalSignerError::InvalidUri(format!(
+                                "unknown query parameter `{key}`"
+                            )))
+                        }
+                    })
+                    .collect::<Result<_, _>>()?;
+                Ok(Self::Exec {
+                    program: PathBuf::from(path),
+                    args,
+                })
+            }
+            EXTERNAL_SIGNER_PLUGIN_SCHEME => Ok(Self::Plugin {
+                socket: PathBuf::from(path),
+            }),
+            scheme => Err(ExternalSignerError::InvalidUri(format!(
+                "unknown scheme `{scheme}`"
+            ))),
+        }
+    }
+
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            Self::Exec { .. } => EXTERNAL_SIGNER_EXEC_SCHEME,
+            Self::Plugin { .. } => EXTERNAL_SIGNER_PLUGIN_SCHEME,
+        }
+    }
+}
+
+impl fmt::Display for ExternalSignerLocator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Exec { program, args } => {
+                write!(
+                    f,
+                    "{}://{}",
+                    self.scheme(),
+                    percent_encode(&program.to_string_lossy())
+                )?;
+                for (i, arg) in args.iter().enumerate() {
+                    let separator = if i == 0 { '?' } else { '&' };
+                    write!(f, "{separator}arg={}", percent_encode(arg))?;
+                }
+                Ok(())
+            }
+            Self::Plugin { socket } => write!(
+                f,
+                "{}://{}",
+                self.scheme(),
+                percent_encode(&socket.to_string_lossy())
+            ),
+        }
+    }
+}
+
+/// Escape everything but unreserved characters and path separators
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes in a URI path, leaving the path as is if it is not
+/// validly escaped
+fn percent_decode(s: &str) -> String {
+    try_percent_decode(s).unwrap_or_else(|| s.to_string())
+}
+
+fn try_percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    params: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: u64,
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    #[serde(default)]
+    code: i64,
+    message: String,
+}
+
+struct Connection {
+    reader: Box<dyn BufRead + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Option<Child>,
+    next_id: u64,
+}
+
+impl Connection {
+    fn open(locator: &ExternalSignerLocator) -> Result<Self, ExternalSignerError> {
+        match locator {
+            ExternalSignerLocator::Exec { program, args } => {
+                let mut child = Command::new(program)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .spawn()?;
+                let stdin = child.stdin.take().unwrap();
+                let stdout = child.stdout.take().unwrap();
+                Ok(Self {
+                    reader: Box::new(BufReader::new(stdout)),
+                    writer: Box::new(stdin),
+                    child: Some(child),
+                    next_id: 1,
+                })
+            }
+            #[cfg(unix)]
+            ExternalSignerLocator::Plugin { socket } => {
+                let stream = std::os::unix::net::UnixStream::connect(socket)?;
+                Ok(Self::from_stream(stream)?)
+            }
+            #[cfg(not(unix))]
+            ExternalSignerLocator::Plugin { .. } => Err(ExternalSignerError::Unsupported(
+                "plugin signers require unix domain sockets",
+            )),
+        }
+    }
+
+    #[cfg(unix)]
+    fn from_stream(stream: std::os::unix::net::UnixStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: Box::new(BufReader::new(stream.try_clone()?)),
+            writer: Box::new(stream),
+            child: None,
+            next_id: 1,
+        })
+    }
+
+    fn request(
+        &mut self,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<Value, ExternalSignerError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut request = serde_json::to_vec(&Request {
+            jsonrpc: JSONRPC_VERSION,
+            id,
+            method,
+            params,
+        })?;
+        request.push(b'\n');
+        self.writer.write_all(&request)?;
+        self.writer.flush()?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(ExternalSignerError::Io(io::ErrorKind::UnexpectedEof.into()));
+        }
+        let response: Response = serde_json::from_str(&line)?;
+        if response.id != id {
+            return Err(ExternalSignerError::Protocol(format!(
+                "response id {} does not match request id {id}",
+                response.id
+            )));
+        }
+        match (response.result, response.error) {
+            (_, Some(ResponseError { code, message })) => {
+                Err(ExternalSignerError::Signer { code, message })
+            }
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(ExternalSignerError::Protocol(
+                "response has neither result nor error".to_string(),
+            )),
+        }
+    }
+
+    fn request_string(
+        &mut self,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<String, ExternalSignerError> {
+        match self.request(method, params)? {
+            Value::String(result) => Ok(result),
+            result => Err(ExternalSignerError::Protocol(format!(
+                "expected a string result for `{method}`, got {result}"
+            ))),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(child) = self.child.as_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// A [`Signer`] delegating to an external signer process
+pub struct ExternalSigner {
+    pubkey: Pubkey,
+    connection: Mutex<Connection>,
+}
+
+impl ExternalSigner {
+    /// Connect to the external signer at `locator` and fetch its pubkey
+    pub fn new(locator: &ExternalSignerLocator) -> Result<Self, ExternalSignerError> {
+        Self::from_connection(Connection::open(locator)?)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self, ExternalSignerError> {
+        let pubkey = connection.request_string("get_pubkey", None)?;
+        let pubkey = Pubkey::from_str(&pubkey)
+            .map_err(|err| ExternalSignerError::Protocol(format!("invalid pubkey: {err}")))?;
+        Ok(Self {
+            pubkey,
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+impl Signer for ExternalSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let params = json!({ "message": BASE64_STANDARD.encode(message) });
+        let signature = self
+            .connection
+            .lock()
+            .unwrap()
+            .request_string("sign_message", Some(params))?;
+        let signature = Signature::from_str(&signature).map_err(|err| {
+            SignerError::Protocol(format!("external signer returned invalid signature: {err}"))
+        })?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::Protocol(
+                "external signer returned a signature that does not verify".to_string(),
+            ));
+        }
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use {
+        super::*,
+        trezoa_sdk::signature::Keypair,
+        std::{os::unix::net::UnixStream, thread},
+    };
+
+    // Answer requests the way an external signer holding `keypair` would
+    fn serve(keypair: Keypair, stream: UnixStream, reject_signing: bool) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 {
+            let request: Value = serde_json::from_str(&line).unwrap();
+            line.clear();
+            assert_eq!(request["jsonrpc"], JSONRPC_VERSION);
+            let response = match request["method"].as_str().unwrap() {
+                "get_pubkey" => json!({
+                    "jsonrpc": JSONRPC_VERSION,
+                    "id": request["id"],
+                    "result": keypair.pubkey().to_string(),
+                }),
+                "sign_message" if reject_signing => json!({
+                    "jsonrpc": JSONRPC_VERSION,
+                    "id": request["id"],
+                    "error": { "code": 1, "message": "rejected" },
+                }),
+                "sign_message" => {
+                    let message = BASE64_STANDARD
+                        .decode(request["params"]["message"].as_str().unwrap())
+                        .unwrap();
+                    json!({
+                        "jsonrpc": JSONRPC_VERSION,
+                        "id": request["id"],
+                        "result": keypair.sign_message(&message).to_string(),
+                    })
+                }
+                method => panic!("unexpected method {method}"),
+            };
+            writeln!(writer, "{response}").unwrap();
+        }
+    }
+
+    fn start_signer(keypair: Keypair, reject_signing: bool) -> ExternalSigner {
+        let (client, server) = UnixStream::pair().unwrap();
+        thread::spawn(move || serve(keypair, server, reject_signing));
+        ExternalSigner::from_connection(Connection::from_stream(client).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_external_signer() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let signer = start_signer(keypair, false);
+        assert_eq!(signer.pubkey(), pubkey);
+
+        let message = b"hello external signer";
+        let signature = signer.try_sign_message(message).unwrap();
+        assert!(signature.verify(pubkey.as_ref(), message));
+        // Requests keep working on the same connection
+        assert!(signer.try_sign_message(b"again").is_ok());
+    }
+
+    #[test]
+    fn test_external_signer_error() {
+        let signer = start_signer(Keypair::new(), true);
+        assert_eq!(
+            signer.try_sign_message(b"message").unwrap_err(),
+            SignerError::Custom("rejected".to_string())
+        );
+    }
+
+    #[test]
+    fn test_locator_from_uri() {
+        let uri =
+            URIReference::try_from("exec:///usr/bin/kms-signer?arg=--key&arg=treasury").unwrap();
+        assert_eq!(
+            ExternalSignerLocator::new_from_uri(&uri).unwrap(),
+            ExternalSignerLocator::Exec {
+                program: PathBuf::from("/usr/bin/kms-signer"),
+                args: vec!["--key".to_string(), "treasury".to_string()],
+            }
+        );
+
+        let uri = URIReference::try_from("plugin:///run/hsm%20signer.sock").unwrap();
+        assert_eq!(
+            ExternalSignerLocator::new_from_uri(&uri).unwrap(),
+            ExternalSignerLocator::Plugin {
+                socket: PathBuf::from("/run/hsm signer.sock"),
+            }
+        );
+
+        let locator = ExternalSignerLocator::Exec {
+            program: PathBuf::from("/opt/my signer"),
+            args: vec!["--key".to_string(), "a&b".to_string()],
+        };
+        let uri = locator.to_string();
+        assert_eq!(uri, "exec:///opt/my%20signer?arg=--key&arg=a%26b");
+        assert_eq!(
+            ExternalSignerLocator::new_from_uri(&URIReference::try_from(uri.as_str()).unwrap())
+                .unwrap(),
+            locator
+        );
+
+        let uri = URIReference::try_from("exec://").unwrap();
+        assert!(ExternalSignerLocator::new_from_uri(&uri).is_err());
+        let uri = URIReference::try_from("exec:///bin/signer?key=0").unwrap();
+        assert!(ExternalSignerLocator::new_from_uri(&uri).is_err());
+    }
+}
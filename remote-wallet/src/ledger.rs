@@ -1,9 +1,9 @@
 use {
     crate::remote_wallet::{
-        RemoteWallet, RemoteWalletError, RemoteWalletInfo, RemoteWalletManager,
+        select_device_from_info, RemoteWallet, RemoteWalletError, RemoteWalletInfo,
+        RemoteWalletManager,
     },
     console::Emoji,
-    dialoguer::{theme::ColorfulTheme, Select},
     semver::Version as FirmwareVersion,
     trezoa_sdk::derivation_path::DerivationPath,
     std::{fmt, rc::Rc},
@@ -13,7 +13,11 @@ use {
     crate::{ledger_error::LedgerError, locator::Manufacturer},
     log::*,
     num_traits::FromPrimitive,
-    trezoa_sdk::{pubkey::Pubkey, signature::Signature},
+    trezoa_sdk::{
+        offchain_message::{v0, v1, OffchainMessage},
+        pubkey::Pubkey,
+        signature::Signature,
+    },
     std::{cmp::min, convert::TryFrom},
 };
 
@@ -525,10 +529,25 @@ impl RemoteWallet<hidapi::DeviceInfo> for LedgerWallet {
         derivation_path: &DerivationPath,
         message: &[u8],
     ) -> Result<Signature, RemoteWalletError> {
-        if message.len()
-            > trezoa_sdk::offchain_message::v0::OffchainMessage::MAX_LEN_LEDGER
-                + trezoa_sdk::offchain_message::v0::OffchainMessage::HEADER_LEN
-        {
+        // Version 1 messages carry their signers in the header, which count
+        // against the Ledger limit. Larger documents can still be signed on
+        // the device by their digest.
+        let too_long = match message.get(OffchainMessage::SIGNING_DOMAIN.len()) {
+            Some(1) => {
+                let message =
+                    v1::OffchainMessage::deserialize(&message[OffchainMessage::HEADER_LEN..])
+                        .map_err(|_| {
+                            RemoteWalletError::InvalidInput("Invalid off-chain message".to_string())
+                        })?;
+                message.get_message().len()
+                    > v1::OffchainMessage::max_len_ledger(message.get_signers().len())
+            }
+            _ => {
+                message.len()
+                    > v0::OffchainMessage::MAX_LEN_LEDGER + v0::OffchainMessage::HEADER_LEN
+            }
+        };
+        if too_long {
             return Err(RemoteWalletError::InvalidInput(
                 "Off-chain message to sign is too long".to_string(),
             ));
@@ -593,46 +612,8 @@ pub fn get_ledger_from_info(
     keypair_name: &str,
     wallet_manager: &RemoteWalletManager,
 ) -> Result<Rc<LedgerWallet>, RemoteWalletError> {
-    let devices = wallet_manager.list_devices();
-    let mut matches = devices
-        .iter()
-        .filter(|&device_info| device_info.matches(&info));
-    if matches
-        .clone()
-        .all(|device_info| device_info.error.is_some())
-    {
-        let first_device = matches.next();
-        if let Some(device) = first_device {
-            return Err(device.error.clone().unwrap());
-        }
-    }
-    let mut matches: Vec<(String, String)> = matches
-        .filter(|&device_info| device_info.error.is_none())
-        .map(|device_info| {
-            let query_item = format!("{} ({})", device_info.get_pretty_path(), device_info.model,);
-            (device_info.host_device_path.clone(), query_item)
-        })
-        .collect();
-    if matches.is_empty() {
-        return Err(RemoteWalletError::NoDeviceFound);
-    }
-    matches.sort_by(|a, b| a.1.cmp(&b.1));
-    let (host_device_paths, items): (Vec<String>, Vec<String>) = matches.into_iter().unzip();
-
-    let wallet_host_device_path = if host_device_paths.len() > 1 {
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt(format!(
-                "Multiple hardware wallets found. Please select a device for {keypair_name:?}"
-            ))
-            .default(0)
-            .items(&items[..])
-            .interact()
-            .unwrap();
-        &host_device_paths[selection]
-    } else {
-        &host_device_paths[0]
-    };
-    wallet_manager.get_ledger(wallet_host_device_path)
+    let host_device_path = select_device_from_info(info, keypair_name, wallet_manager)?;
+    wallet_manager.get_ledger(&host_device_path)
 }
 
 //
@@ -1,7 +1,12 @@
 #![allow(clippy::arithmetic_side_effects)]
 #![allow(dead_code)]
+pub mod external_signer;
 pub mod ledger;
 pub mod ledger_error;
 pub mod locator;
 pub mod remote_keypair;
 pub mod remote_wallet;
+#[cfg(feature = "dev-context-only-utils")]
+pub mod simulator;
+pub mod transport;
+pub mod trezor;
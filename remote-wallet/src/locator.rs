@@ -12,6 +12,7 @@ use {
 pub enum Manufacturer {
     Unknown,
     Ledger,
+    Trezor,
 }
 
 impl Default for Manufacturer {
@@ -22,6 +23,7 @@ impl Default for Manufacturer {
 
 const MANUFACTURER_UNKNOWN: &str = "unknown";
 const MANUFACTURER_LEDGER: &str = "ledger";
+const MANUFACTURER_TREZOR: &str = "trezor";
 
 #[derive(Clone, Debug, Error, PartialEq, Eq)]
 #[error("not a manufacturer")]
@@ -39,6 +41,7 @@ impl FromStr for Manufacturer {
         let s = s.to_ascii_lowercase();
         match s.as_str() {
             MANUFACTURER_LEDGER => Ok(Self::Ledger),
+            MANUFACTURER_TREZOR => Ok(Self::Trezor),
             _ => Err(ManufacturerError),
         }
     }
@@ -56,6 +59,7 @@ impl AsRef<str> for Manufacturer {
         match self {
             Self::Unknown => MANUFACTURER_UNKNOWN,
             Self::Ledger => MANUFACTURER_LEDGER,
+            Self::Trezor => MANUFACTURER_TREZOR,
         }
     }
 }
@@ -173,6 +177,8 @@ mod tests {
             matches!(Manufacturer::from_str(MANUFACTURER_LEDGER), Ok(v) if v == Manufacturer::Ledger)
         );
         assert_eq!(Manufacturer::Ledger.as_ref(), MANUFACTURER_LEDGER);
+        assert_eq!(MANUFACTURER_TREZOR.try_into(), Ok(Manufacturer::Trezor));
+        assert_eq!(Manufacturer::Trezor.as_ref(), MANUFACTURER_TREZOR);
 
         assert!(
             matches!(Manufacturer::from_str("bad-manufacturer"), Err(e) if e == ManufacturerError)
@@ -1,11 +1,17 @@
 #[cfg(feature = "hidapi")]
-use {crate::ledger::is_valid_ledger, parking_lot::Mutex, std::sync::Arc};
+use {
+    crate::{ledger::is_valid_ledger, trezor::is_valid_trezor},
+    parking_lot::Mutex,
+    std::sync::Arc,
+};
 use {
     crate::{
         ledger::LedgerWallet,
         ledger_error::LedgerError,
         locator::{Locator, LocatorError, Manufacturer},
+        trezor::{TrezorDeviceInfo, TrezorWallet},
     },
+    dialoguer::{theme::ColorfulTheme, Select},
     log::*,
     parking_lot::RwLock,
     trezoa_sdk::{
@@ -14,11 +20,17 @@ use {
         signature::{Signature, SignerError},
     },
     std::{
+        io,
         rc::Rc,
         time::{Duration, Instant},
     },
     thiserror::Error,
 };
+#[cfg(feature = "dev-context-only-utils")]
+use {
+    crate::{simulator::REMOTE_WALLET_SIMULATOR_ENV, transport::UdpTransport},
+    std::net::{SocketAddr, ToSocketAddrs},
+};
 
 const HID_GLOBAL_USAGE_PAGE: u16 = 0xFF00;
 const HID_USB_DEVICE_CLASS: u8 = 0;
@@ -61,6 +73,18 @@ pub enum RemoteWalletError {
 
     #[error(transparent)]
     LocatorError(#[from] LocatorError),
+
+    #[error("device error: {0}")]
+    DeviceError(String),
+
+    #[error("transport error: {0}")]
+    Transport(String),
+}
+
+impl From<io::Error> for RemoteWalletError {
+    fn from(err: io::Error) -> RemoteWalletError {
+        RemoteWalletError::Transport(err.to_string())
+    }
 }
 
 #[cfg(feature = "hidapi")]
@@ -80,6 +104,8 @@ impl From<RemoteWalletError> for SignerError {
             RemoteWalletError::LedgerError(e) => SignerError::Protocol(e.to_string()),
             RemoteWalletError::NoDeviceFound => SignerError::NoDeviceFound,
             RemoteWalletError::Protocol(e) => SignerError::Protocol(e.to_string()),
+            RemoteWalletError::DeviceError(e) => SignerError::Protocol(e),
+            RemoteWalletError::Transport(e) => SignerError::Connection(e),
             RemoteWalletError::UserCancel => {
                 SignerError::UserCancel("remote wallet operation rejected by the user".to_string())
             }
@@ -91,7 +117,9 @@ impl From<RemoteWalletError> for SignerError {
 /// Collection of connected RemoteWallets
 pub struct RemoteWalletManager {
     #[cfg(feature = "hidapi")]
-    usb: Arc<Mutex<hidapi::HidApi>>,
+    usb: Option<Arc<Mutex<hidapi::HidApi>>>,
+    #[cfg(feature = "dev-context-only-utils")]
+    simulator: Option<SocketAddr>,
     devices: RwLock<Vec<Device>>,
 }
 
@@ -100,66 +128,114 @@ impl RemoteWalletManager {
     #[cfg(feature = "hidapi")]
     pub fn new(usb: Arc<Mutex<hidapi::HidApi>>) -> Rc<Self> {
         Rc::new(Self {
-            usb,
+            usb: Some(usb),
+            #[cfg(feature = "dev-context-only-utils")]
+            simulator: simulator_address(),
+            devices: RwLock::new(Vec::new()),
+        })
+    }
+
+    /// Create an instance that only finds the remote wallet simulator
+    /// listening at `address`
+    #[cfg(feature = "dev-context-only-utils")]
+    pub fn new_simulator(address: SocketAddr) -> Rc<Self> {
+        Rc::new(Self {
+            #[cfg(feature = "hidapi")]
+            usb: None,
+            simulator: Some(address),
             devices: RwLock::new(Vec::new()),
         })
     }
 
     /// Repopulate device list
     /// Note: this method iterates over and updates all devices
-    #[cfg(feature = "hidapi")]
     pub fn update_devices(&self) -> Result<usize, RemoteWalletError> {
-        let mut usb = self.usb.lock();
-        usb.refresh_devices()?;
-        let devices = usb.device_list();
         let num_prev_devices = self.devices.read().len();
 
         let mut detected_devices = vec![];
         let mut errors = vec![];
+        self.detect_usb_devices(&mut detected_devices, &mut errors)?;
+        #[cfg(feature = "dev-context-only-utils")]
+        if let Some(address) = self.simulator {
+            match read_simulator_device(address) {
+                Ok(device) => {
+                    trace!("Found simulator: {:?}", device.info);
+                    detected_devices.push(device)
+                }
+                Err(err) => {
+                    error!("Error connecting to simulator at {}: {}", address, err);
+                    errors.push(err)
+                }
+            }
+        }
+
+        let num_curr_devices = detected_devices.len();
+        *self.devices.write() = detected_devices;
+
+        if num_curr_devices == 0 && !errors.is_empty() {
+            return Err(errors[0].clone());
+        }
+
+        Ok(num_curr_devices - num_prev_devices)
+    }
+
+    #[cfg(feature = "hidapi")]
+    fn detect_usb_devices(
+        &self,
+        detected_devices: &mut Vec<Device>,
+        errors: &mut Vec<RemoteWalletError>,
+    ) -> Result<(), RemoteWalletError> {
+        let Some(usb) = &self.usb else {
+            return Ok(());
+        };
+        let mut usb = usb.lock();
+        usb.refresh_devices()?;
+        let devices = usb.device_list();
+
         for device_info in devices.filter(|&device_info| {
             is_valid_hid_device(device_info.usage_page(), device_info.interface_number())
-                && is_valid_ledger(device_info.vendor_id(), device_info.product_id())
+                && (is_valid_ledger(device_info.vendor_id(), device_info.product_id())
+                    || is_valid_trezor(device_info.vendor_id(), device_info.product_id()))
         }) {
             match usb.open_path(device_info.path()) {
                 Ok(device) => {
-                    let mut ledger = LedgerWallet::new(device);
-                    let result = ledger.read_device(device_info);
+                    let result =
+                        if is_valid_trezor(device_info.vendor_id(), device_info.product_id()) {
+                            read_trezor_device(device, device_info)
+                        } else {
+                            read_ledger_device(device, device_info)
+                        };
                     match result {
-                        Ok(info) => {
-                            ledger.pretty_path = info.get_pretty_path();
-                            let path = device_info.path().to_str().unwrap().to_string();
-                            trace!("Found device: {:?}", info);
-                            detected_devices.push(Device {
-                                path,
-                                info,
-                                wallet_type: RemoteWalletType::Ledger(Rc::new(ledger)),
-                            })
+                        Ok(device) => {
+                            trace!("Found device: {:?}", device.info);
+                            detected_devices.push(device)
                         }
                         Err(err) => {
-                            error!("Error connecting to ledger device to read info: {}", err);
+                            error!(
+                                "Error connecting to remote wallet device to read info: {}",
+                                err
+                            );
                             errors.push(err)
                         }
                     }
                 }
-                Err(err) => error!("Error connecting to ledger device to read info: {}", err),
+                Err(err) => error!(
+                    "Error connecting to remote wallet device to read info: {}",
+                    err
+                ),
             }
         }
-
-        let num_curr_devices = detected_devices.len();
-        *self.devices.write() = detected_devices;
-
-        if num_curr_devices == 0 && !errors.is_empty() {
-            return Err(errors[0].clone());
-        }
-
-        Ok(num_curr_devices - num_prev_devices)
+        Ok(())
     }
 
+    // Without hidapi, the manager can only have been created for a simulator
     #[cfg(not(feature = "hidapi"))]
-    pub fn update_devices(&self) -> Result<usize, RemoteWalletError> {
-        Err(RemoteWalletError::Hid(
-            "hidapi crate compilation disabled in trezoa-remote-wallet.".to_string(),
-        ))
+    fn detect_usb_devices(
+        &self,
+        _detected_devices: &mut Vec<Device>,
+        _errors: &mut Vec<RemoteWalletError>,
+    ) -> Result<(), RemoteWalletError> {
+        Ok(())
     }
 
     /// List connected and acknowledged wallets
@@ -168,7 +244,6 @@ impl RemoteWalletManager {
     }
 
     /// Get a particular wallet
-    #[allow(unreachable_patterns)]
     pub fn get_ledger(
         &self,
         host_device_path: &str,
@@ -184,6 +259,22 @@ impl RemoteWalletManager {
             })
     }
 
+    /// Get a particular Trezor wallet
+    pub fn get_trezor(
+        &self,
+        host_device_path: &str,
+    ) -> Result<Rc<TrezorWallet>, RemoteWalletError> {
+        self.devices
+            .read()
+            .iter()
+            .find(|device| device.info.host_device_path == host_device_path)
+            .ok_or(RemoteWalletError::PubkeyNotFound)
+            .and_then(|device| match &device.wallet_type {
+                RemoteWalletType::Trezor(trezor) => Ok(trezor.clone()),
+                _ => Err(RemoteWalletError::DeviceTypeMismatch),
+            })
+    }
+
     /// Get wallet info.
     pub fn get_wallet_info(&self, pubkey: &Pubkey) -> Option<RemoteWalletInfo> {
         self.devices
@@ -262,6 +353,7 @@ pub struct Device {
 #[derive(Debug)]
 pub enum RemoteWalletType {
     Ledger(Rc<LedgerWallet>),
+    Trezor(Rc<TrezorWallet>),
 }
 
 /// Remote wallet information.
@@ -307,17 +399,149 @@ pub fn is_valid_hid_device(usage_page: u16, interface_number: i32) -> bool {
     usage_page == HID_GLOBAL_USAGE_PAGE || interface_number == HID_USB_DEVICE_CLASS as i32
 }
 
+#[cfg(feature = "hidapi")]
+fn read_ledger_device(
+    device: hidapi::HidDevice,
+    device_info: &hidapi::DeviceInfo,
+) -> Result<Device, RemoteWalletError> {
+    let mut ledger = LedgerWallet::new(device);
+    let info = ledger.read_device(device_info)?;
+    ledger.pretty_path = info.get_pretty_path();
+    Ok(Device {
+        path: device_info.path().to_str().unwrap().to_string(),
+        info,
+        wallet_type: RemoteWalletType::Ledger(Rc::new(ledger)),
+    })
+}
+
+#[cfg(feature = "hidapi")]
+fn read_trezor_device(
+    device: hidapi::HidDevice,
+    device_info: &hidapi::DeviceInfo,
+) -> Result<Device, RemoteWalletError> {
+    let host_device_path = device_info.path().to_string_lossy().to_string();
+    let mut trezor = TrezorWallet::new(Box::new(device));
+    let info = trezor.read_device(&TrezorDeviceInfo {
+        host_device_path: host_device_path.clone(),
+    })?;
+    trezor.pretty_path = info.get_pretty_path();
+    Ok(Device {
+        path: host_device_path,
+        info,
+        wallet_type: RemoteWalletType::Trezor(Rc::new(trezor)),
+    })
+}
+
+#[cfg(feature = "dev-context-only-utils")]
+fn read_simulator_device(address: SocketAddr) -> Result<Device, RemoteWalletError> {
+    let host_device_path = format!("udp://{address}");
+    let mut trezor = TrezorWallet::new(Box::new(UdpTransport::connect(address)?));
+    let info = trezor.read_device(&TrezorDeviceInfo {
+        host_device_path: host_device_path.clone(),
+    })?;
+    trezor.pretty_path = info.get_pretty_path();
+    Ok(Device {
+        path: host_device_path,
+        info,
+        wallet_type: RemoteWalletType::Trezor(Rc::new(trezor)),
+    })
+}
+
+/// Address of the remote wallet simulator set in the environment, if any
+#[cfg(feature = "dev-context-only-utils")]
+fn simulator_address() -> Option<SocketAddr> {
+    let address = std::env::var(REMOTE_WALLET_SIMULATOR_ENV).ok()?;
+    match address
+        .to_socket_addrs()
+        .map(|mut addresses| addresses.next())
+    {
+        Ok(Some(address)) => Some(address),
+        _ => {
+            warn!(
+                "Ignoring invalid {} address {}",
+                REMOTE_WALLET_SIMULATOR_ENV, address
+            );
+            None
+        }
+    }
+}
+
+/// Choose a device matching info fields, asking the user if there are several,
+/// and return its host device path
+pub(crate) fn select_device_from_info(
+    info: RemoteWalletInfo,
+    keypair_name: &str,
+    wallet_manager: &RemoteWalletManager,
+) -> Result<String, RemoteWalletError> {
+    let devices = wallet_manager.list_devices();
+    let mut matches = devices
+        .iter()
+        .filter(|&device_info| device_info.matches(&info));
+    if matches
+        .clone()
+        .all(|device_info| device_info.error.is_some())
+    {
+        let first_device = matches.next();
+        if let Some(device) = first_device {
+            return Err(device.error.clone().unwrap());
+        }
+    }
+    let mut matches: Vec<(String, String)> = matches
+        .filter(|&device_info| device_info.error.is_none())
+        .map(|device_info| {
+            let query_item = format!("{} ({})", device_info.get_pretty_path(), device_info.model,);
+            (device_info.host_device_path.clone(), query_item)
+        })
+        .collect();
+    if matches.is_empty() {
+        return Err(RemoteWalletError::NoDeviceFound);
+    }
+    matches.sort_by(|a, b| a.1.cmp(&b.1));
+    let (host_device_paths, items): (Vec<String>, Vec<String>) = matches.into_iter().unzip();
+
+    let wallet_host_device_path = if host_device_paths.len() > 1 {
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "Multiple hardware wallets found. Please select a device for {keypair_name:?}"
+            ))
+            .default(0)
+            .items(&items[..])
+            .interact()
+            .unwrap();
+        &host_device_paths[selection]
+    } else {
+        &host_device_paths[0]
+    };
+    Ok(wallet_host_device_path.clone())
+}
+
 /// Helper to initialize hidapi and RemoteWalletManager
+///
+/// With the `dev-context-only-utils` feature, if hidapi can't be initialized,
+/// the manager still finds the remote wallet simulator when one is set in the
+/// environment.
 #[cfg(feature = "hidapi")]
 pub fn initialize_wallet_manager() -> Result<Rc<RemoteWalletManager>, RemoteWalletError> {
-    let hidapi = Arc::new(Mutex::new(hidapi::HidApi::new()?));
-    Ok(RemoteWalletManager::new(hidapi))
+    match hidapi::HidApi::new() {
+        Ok(hidapi) => Ok(RemoteWalletManager::new(Arc::new(Mutex::new(hidapi)))),
+        #[cfg(feature = "dev-context-only-utils")]
+        Err(err) => simulator_address()
+            .map(RemoteWalletManager::new_simulator)
+            .ok_or_else(|| err.into()),
+        #[cfg(not(feature = "dev-context-only-utils"))]
+        Err(err) => Err(err.into()),
+    }
 }
 #[cfg(not(feature = "hidapi"))]
 pub fn initialize_wallet_manager() -> Result<Rc<RemoteWalletManager>, RemoteWalletError> {
-    Err(RemoteWalletError::Hid(
+    let err = RemoteWalletError::Hid(
         "hidapi crate compilation disabled in trezoa-remote-wallet.".to_string(),
-    ))
+    );
+    #[cfg(feature = "dev-context-only-utils")]
+    if let Some(address) = simulator_address() {
+        return Ok(RemoteWalletManager::new_simulator(address));
+    }
+    Err(err)
 }
 
 pub fn maybe_wallet_manager() -> Result<Option<Rc<RemoteWalletManager>>, RemoteWalletError> {
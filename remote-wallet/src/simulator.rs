@@ -0,0 +1,276 @@
+//! Software simulator of a Trezor hardware wallet
+//!
+//! The simulator answers the same messages, framed into the same HID reports,
+//! as a Trezor device, deriving its keys from a seed instead of keeping them in
+//! secure hardware. It runs in process behind [`SimulatorTransport`], or
+//! listens on a UDP socket like the vendor's emulator with
+//! [`TrezorSimulator::serve`]. When [`REMOTE_WALLET_SIMULATOR_ENV`] is set to
+//! the address it listens on, `usb://trezor` signers use the simulator, so the
+//! remote wallet signer path can be tested end-to-end without a device.
+//!
+//! It is meant for tests only: anyone who knows the seed can sign with its keys.
+//! The module, and the lookup of the environment variable, are only built with
+//! the `dev-context-only-utils` feature.
+
+use {
+    crate::{
+        remote_wallet::RemoteWalletError,
+        transport::{HidTransport, HID_REPORT_SIZE, UDP_PING, UDP_PONG},
+        trezor::{encode_message, failure, messages, proto, MessageDecoder},
+    },
+    log::*,
+    trezoa_sdk::{
+        derivation_path::DerivationPath,
+        signature::{keypair_from_seed_and_derivation_path, Keypair, Signer},
+    },
+    std::{cell::RefCell, collections::VecDeque, io, net::UdpSocket},
+};
+
+/// Address of a listening simulator, used by `RemoteWalletManager` in
+/// addition to connected USB devices
+pub const REMOTE_WALLET_SIMULATOR_ENV: &str = "TREZOA_REMOTE_WALLET_SIMULATOR";
+
+/// Default address the simulator listens on, the same as the vendor's emulator
+pub const DEFAULT_SIMULATOR_ADDRESS: &str = "127.0.0.1:21324";
+
+const HARDENED_BIT: u32 = 1 << 31;
+/// ButtonRequest code asking to confirm a transaction
+const BUTTON_REQUEST_SIGN_TX: u64 = 8;
+/// ButtonRequest code asking to confirm an address shown on the device
+const BUTTON_REQUEST_ADDRESS: u64 = 10;
+
+type Message = (u16, Vec<u8>);
+
+pub struct TrezorSimulator {
+    seed: Vec<u8>,
+    approve_signing: bool,
+    decoder: MessageDecoder,
+    /// Answer held back until the host acknowledges a button request
+    awaiting_button: Option<Message>,
+}
+
+impl TrezorSimulator {
+    pub fn new(seed: &[u8]) -> Self {
+        Self {
+            seed: seed.to_vec(),
+            approve_signing: true,
+            decoder: MessageDecoder::default(),
+            awaiting_button: None,
+        }
+    }
+
+    /// Reject every transaction as if the user declined it on the device
+    pub fn reject_signing(mut self) -> Self {
+        self.approve_signing = false;
+        self
+    }
+
+    pub fn keypair(&self, derivation_path: &DerivationPath) -> Result<Keypair, String> {
+        keypair_from_seed_and_derivation_path(&self.seed, Some(derivation_path.clone()))
+            .map_err(|err| err.to_string())
+    }
+
+    /// Handle a report from the host, returning the reports to answer with
+    pub fn handle_report(&mut self, report: &[u8]) -> Vec<[u8; HID_REPORT_SIZE]> {
+        let (message_type, payload) = match self.decoder.push(report) {
+            Ok(Some(message)) => message,
+            Ok(None) => return vec![],
+            Err(err) => {
+                warn!("Simulator received an invalid report: {}", err);
+                self.decoder = MessageDecoder::default();
+                let (message_type, payload) =
+                    failure_message(failure::UNEXPECTED_MESSAGE, &err.to_string());
+                return encode_message(message_type, &payload);
+            }
+        };
+        let (message_type, payload) = self.handle_message(message_type, &payload);
+        encode_message(message_type, &payload)
+    }
+
+    fn handle_message(&mut self, message_type: u16, payload: &[u8]) -> Message {
+        if let Some(answer) = self.awaiting_button.take() {
+            return if message_type == messages::BUTTON_ACK {
+                answer
+            } else {
+                failure_message(failure::UNEXPECTED_MESSAGE, "Expected ButtonAck")
+            };
+        }
+        let fields = match proto::fields(payload) {
+            Ok(fields) => fields,
+            Err(err) => return failure_message(failure::DATA_ERROR, &err.to_string()),
+        };
+        match message_type {
+            messages::INITIALIZE => {
+                let mut features = vec![];
+                proto::put_bytes(&mut features, 1, b"trezor.io");
+                proto::put_uint(&mut features, 2, 2);
+                proto::put_uint(&mut features, 3, 0);
+                proto::put_uint(&mut features, 4, 0);
+                proto::put_bytes(&mut features, 6, b"SIMULATOR");
+                proto::put_uint(&mut features, 12, 1);
+                proto::put_bytes(&mut features, 21, b"Simulator");
+                (messages::FEATURES, features)
+            }
+            messages::TREZOA_GET_PUBLIC_KEY | messages::TREZOA_SIGN_TX => {
+                let keypair = match self.derive_keypair(&proto::get_repeated_uint(&fields, 1)) {
+                    Ok(keypair) => keypair,
+                    Err(err) => return failure_message(failure::DATA_ERROR, &err),
+                };
+                if message_type == messages::TREZOA_GET_PUBLIC_KEY {
+                    let mut public_key = vec![];
+                    proto::put_bytes(&mut public_key, 1, keypair.pubkey().as_ref());
+                    let answer = (messages::TREZOA_PUBLIC_KEY, public_key);
+                    if proto::get_uint(&fields, 2) == Some(1) {
+                        self.request_button(BUTTON_REQUEST_ADDRESS, answer)
+                    } else {
+                        answer
+                    }
+                } else {
+                    let Some(serialized_tx) = proto::get_bytes(&fields, 2) else {
+                        return failure_message(failure::DATA_ERROR, "Missing serialized_tx");
+                    };
+                    let answer = if self.approve_signing {
+                        let mut signature = vec![];
+                        proto::put_bytes(
+                            &mut signature,
+                            1,
+                            keypair.sign_message(serialized_tx).as_ref(),
+                        );
+                        (messages::TREZOA_TX_SIGNATURE, signature)
+                    } else {
+                        failure_message(failure::ACTION_CANCELLED, "Cancelled")
+                    };
+                    self.request_button(BUTTON_REQUEST_SIGN_TX, answer)
+                }
+            }
+            _ => failure_message(failure::UNEXPECTED_MESSAGE, "Unexpected message"),
+        }
+    }
+
+    fn derive_keypair(&self, address_n: &[u64]) -> Result<Keypair, String> {
+        let indexes = address_n
+            .iter()
+            .map(|index| match u32::try_from(*index) {
+                Ok(index) if index & HARDENED_BIT != 0 => Ok((index & !HARDENED_BIT).to_string()),
+                _ => Err("Only hardened derivation is supported".to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let path = if indexes.is_empty() {
+            "m".to_string()
+        } else {
+            format!("m/{}", indexes.join("/"))
+        };
+        let derivation_path =
+            DerivationPath::from_absolute_path_str(&path).map_err(|err| err.to_string())?;
+        self.keypair(&derivation_path)
+    }
+
+    fn request_button(&mut self, code: u64, answer: Message) -> Message {
+        self.awaiting_button = Some(answer);
+        let mut request = vec![];
+        proto::put_uint(&mut request, 1, code);
+        (messages::BUTTON_REQUEST, request)
+    }
+
+    /// Answer reports received on `socket`, one report per datagram, until an
+    /// IO error occurs
+    pub fn serve(mut self, socket: UdpSocket) -> io::Result<()> {
+        let mut report = [0_u8; HID_REPORT_SIZE];
+        loop {
+            let (size, address) = socket.recv_from(&mut report)?;
+            if &report[..size] == UDP_PING {
+                socket.send_to(UDP_PONG, address)?;
+                continue;
+            }
+            for answer in self.handle_report(&report[..size]) {
+                socket.send_to(&answer, address)?;
+            }
+        }
+    }
+}
+
+fn failure_message(code: u64, message: &str) -> Message {
+    let mut failure = vec![];
+    proto::put_uint(&mut failure, 1, code);
+    proto::put_bytes(&mut failure, 2, message.as_bytes());
+    (messages::FAILURE, failure)
+}
+
+/// An in-process transport to a simulator
+pub struct SimulatorTransport {
+    simulator: RefCell<TrezorSimulator>,
+    answers: RefCell<VecDeque<[u8; HID_REPORT_SIZE]>>,
+}
+
+impl SimulatorTransport {
+    pub fn new(simulator: TrezorSimulator) -> Self {
+        Self {
+            simulator: RefCell::new(simulator),
+            answers: RefCell::new(VecDeque::new()),
+        }
+    }
+}
+
+impl HidTransport for SimulatorTransport {
+    fn write_report(&self, report: &[u8]) -> Result<(), RemoteWalletError> {
+        let answers = self.simulator.borrow_mut().handle_report(report);
+        self.answers.borrow_mut().extend(answers);
+        Ok(())
+    }
+
+    fn read_report(&self, report: &mut [u8; HID_REPORT_SIZE]) -> Result<usize, RemoteWalletError> {
+        let answer = self
+            .answers
+            .borrow_mut()
+            .pop_front()
+            .ok_or(RemoteWalletError::Protocol("No answer from simulator"))?;
+        *report = answer;
+        Ok(HID_REPORT_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            locator::Locator, remote_keypair::generate_remote_keypair,
+            remote_wallet::RemoteWalletManager,
+        },
+        std::thread,
+    };
+
+    #[test]
+    fn test_simulator_over_udp() {
+        let seed = [3; 64];
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        thread::spawn(move || TrezorSimulator::new(&seed).serve(socket));
+
+        let wallet_manager = RemoteWalletManager::new_simulator(address);
+        assert_eq!(wallet_manager.update_devices().unwrap(), 1);
+        let devices = wallet_manager.list_devices();
+        let expected_pubkey = TrezorSimulator::new(&seed)
+            .keypair(&DerivationPath::default())
+            .unwrap()
+            .pubkey();
+        assert_eq!(devices[0].pubkey, expected_pubkey);
+        assert_eq!(devices[0].model, "simulator");
+
+        let derivation_path = DerivationPath::new_bip44(Some(2), None);
+        let keypair = TrezorSimulator::new(&seed)
+            .keypair(&derivation_path)
+            .unwrap();
+        let signer = generate_remote_keypair(
+            Locator::new_from_path("usb://trezor").unwrap(),
+            derivation_path,
+            &wallet_manager,
+            false,
+            "test",
+        )
+        .unwrap();
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+        let message = b"hello";
+        assert_eq!(signer.sign_message(message), keypair.sign_message(message));
+    }
+}
@@ -0,0 +1,96 @@
+//! Channels exchanging fixed size HID reports with a device
+//!
+//! Hardware wallets are reached over USB HID, while the remote wallet
+//! simulator exchanges the same reports over UDP datagrams, one report per
+//! datagram, so the code driving a device can't tell them apart.
+
+use {
+    crate::remote_wallet::RemoteWalletError,
+    std::{
+        net::{SocketAddr, UdpSocket},
+        time::Duration,
+    },
+};
+
+/// Size of a HID report, without the report ID byte hidapi expects on Windows
+pub const HID_REPORT_SIZE: usize = 64;
+
+/// Sent by the host to check that a UDP device is listening
+pub const UDP_PING: &[u8] = b"PINGPING";
+/// Answer of a listening UDP device to `UDP_PING`
+pub const UDP_PONG: &[u8] = b"PONGPONG";
+
+const UDP_PING_TIMEOUT: Duration = Duration::from_secs(1);
+// Reads wait on the user confirming on the device
+const UDP_READ_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[cfg(windows)]
+const HID_PREFIX_ZERO: usize = 1;
+#[cfg(not(windows))]
+const HID_PREFIX_ZERO: usize = 0;
+
+pub trait HidTransport {
+    /// Write a single report of at most `HID_REPORT_SIZE` bytes
+    fn write_report(&self, report: &[u8]) -> Result<(), RemoteWalletError>;
+
+    /// Read a single report, returning the number of bytes read
+    fn read_report(&self, report: &mut [u8; HID_REPORT_SIZE]) -> Result<usize, RemoteWalletError>;
+}
+
+#[cfg(feature = "hidapi")]
+impl HidTransport for hidapi::HidDevice {
+    fn write_report(&self, report: &[u8]) -> Result<(), RemoteWalletError> {
+        let mut buffer = [0_u8; HID_REPORT_SIZE + HID_PREFIX_ZERO];
+        buffer[HID_PREFIX_ZERO..HID_PREFIX_ZERO + report.len()].copy_from_slice(report);
+        let n = self.write(&buffer)?;
+        if n < buffer.len() {
+            return Err(RemoteWalletError::Protocol("Write data size mismatch"));
+        }
+        Ok(())
+    }
+
+    fn read_report(&self, report: &mut [u8; HID_REPORT_SIZE]) -> Result<usize, RemoteWalletError> {
+        Ok(self.read(report)?)
+    }
+}
+
+/// A device, such as the remote wallet simulator, listening for HID reports
+/// on a UDP socket
+pub struct UdpTransport {
+    socket: UdpSocket,
+}
+
+impl UdpTransport {
+    /// Connect to a device at `address`, checking that it answers a ping
+    pub fn connect(address: SocketAddr) -> Result<Self, RemoteWalletError> {
+        let bind_address: SocketAddr = if address.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0_u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind_address)?;
+        socket.connect(address)?;
+        socket.set_read_timeout(Some(UDP_PING_TIMEOUT))?;
+        socket.send(UDP_PING)?;
+        let mut pong = [0_u8; HID_REPORT_SIZE];
+        let n = socket.recv(&mut pong)?;
+        if &pong[..n] != UDP_PONG {
+            return Err(RemoteWalletError::Protocol("Unexpected ping response"));
+        }
+        socket.set_read_timeout(Some(UDP_READ_TIMEOUT))?;
+        Ok(Self { socket })
+    }
+}
+
+impl HidTransport for UdpTransport {
+    fn write_report(&self, report: &[u8]) -> Result<(), RemoteWalletError> {
+        let mut buffer = [0_u8; HID_REPORT_SIZE];
+        buffer[..report.len()].copy_from_slice(report);
+        self.socket.send(&buffer)?;
+        Ok(())
+    }
+
+    fn read_report(&self, report: &mut [u8; HID_REPORT_SIZE]) -> Result<usize, RemoteWalletError> {
+        Ok(self.socket.recv(report)?)
+    }
+}
@@ -0,0 +1,562 @@
+//! Trezor hardware wallets
+//!
+//! Trezor devices exchange protobuf messages split into HID reports. The first
+//! report of a message starts with `?##`, the message type (u16, big endian)
+//! and the payload length (u32, big endian), and the reports that follow start
+//! with `?`. Only the messages needed to read Trezoa pubkeys and sign
+//! transactions are implemented, with a minimal protobuf codec in `proto`.
+
+use {
+    crate::{
+        locator::Manufacturer,
+        remote_wallet::{
+            select_device_from_info, RemoteWallet, RemoteWalletError, RemoteWalletInfo,
+            RemoteWalletManager,
+        },
+        transport::{HidTransport, HID_REPORT_SIZE},
+    },
+    console::Emoji,
+    dialoguer::Password,
+    log::*,
+    trezoa_sdk::{derivation_path::DerivationPath, pubkey::Pubkey, signature::Signature},
+    std::{cmp::min, fmt, rc::Rc},
+};
+
+static CHECK_MARK: Emoji = Emoji("✅ ", "");
+
+const REPORT_MAGIC: u8 = b'?';
+const MESSAGE_MAGIC: [u8; 2] = *b"##";
+const MESSAGE_HEADER_LEN: usize = 9;
+/// Largest message payload accepted from a device
+const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+/// Trezor One vendor and product IDs
+const TREZOR_ONE_VID: u16 = 0x534c;
+const TREZOR_ONE_PID: u16 = 0x0001;
+/// Trezor Model T and Safe vendor and product IDs
+const TREZOR_VID: u16 = 0x1209;
+const TREZOR_PID: u16 = 0x53c1;
+
+pub(crate) mod messages {
+    pub const INITIALIZE: u16 = 0;
+    pub const FAILURE: u16 = 3;
+    pub const FEATURES: u16 = 17;
+    pub const PIN_MATRIX_REQUEST: u16 = 18;
+    pub const PIN_MATRIX_ACK: u16 = 19;
+    pub const BUTTON_REQUEST: u16 = 26;
+    pub const BUTTON_ACK: u16 = 27;
+    pub const PASSPHRASE_REQUEST: u16 = 41;
+    pub const PASSPHRASE_ACK: u16 = 42;
+    pub const TREZOA_GET_PUBLIC_KEY: u16 = 900;
+    pub const TREZOA_PUBLIC_KEY: u16 = 901;
+    pub const TREZOA_SIGN_TX: u16 = 904;
+    pub const TREZOA_TX_SIGNATURE: u16 = 905;
+}
+
+pub(crate) mod failure {
+    pub const DATA_ERROR: u64 = 3;
+    pub const ACTION_CANCELLED: u64 = 4;
+    pub const PIN_CANCELLED: u64 = 6;
+    pub const UNEXPECTED_MESSAGE: u64 = 1;
+}
+
+/// Minimal protobuf encoding of the scalar and bytes fields Trezor messages use
+pub(crate) mod proto {
+    use crate::remote_wallet::RemoteWalletError;
+
+    const WIRE_VARINT: u64 = 0;
+    const WIRE_FIXED64: u64 = 1;
+    const WIRE_LEN: u64 = 2;
+    const WIRE_FIXED32: u64 = 5;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Value<'a> {
+        Varint(u64),
+        Bytes(&'a [u8]),
+    }
+
+    fn put_varint(buffer: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            buffer.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        buffer.push(value as u8);
+    }
+
+    pub fn put_uint(buffer: &mut Vec<u8>, field: u32, value: u64) {
+        put_varint(buffer, (u64::from(field) << 3) | WIRE_VARINT);
+        put_varint(buffer, value);
+    }
+
+    pub fn put_bytes(buffer: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+        put_varint(buffer, (u64::from(field) << 3) | WIRE_LEN);
+        put_varint(buffer, bytes.len() as u64);
+        buffer.extend_from_slice(bytes);
+    }
+
+    fn get_varint(data: &[u8], offset: &mut usize) -> Result<u64, RemoteWalletError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = *data
+                .get(*offset)
+                .ok_or(RemoteWalletError::Protocol("Truncated protobuf varint"))?;
+            *offset += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(RemoteWalletError::Protocol("Invalid protobuf varint"))
+    }
+
+    /// Decode the fields of a message, skipping fixed width fields
+    pub fn fields(data: &[u8]) -> Result<Vec<(u32, Value)>, RemoteWalletError> {
+        let mut fields = vec![];
+        let mut offset = 0;
+        while offset < data.len() {
+            let key = get_varint(data, &mut offset)?;
+            let field = (key >> 3) as u32;
+            let value = match key & 0x7 {
+                WIRE_VARINT => Value::Varint(get_varint(data, &mut offset)?),
+                WIRE_LEN => {
+                    let len = get_varint(data, &mut offset)? as usize;
+                    let bytes = offset
+                        .checked_add(len)
+                        .and_then(|end| data.get(offset..end))
+                        .ok_or(RemoteWalletError::Protocol("Truncated protobuf field"))?;
+                    offset += len;
+                    Value::Bytes(bytes)
+                }
+                WIRE_FIXED64 => {
+                    offset += 8;
+                    continue;
+                }
+                WIRE_FIXED32 => {
+                    offset += 4;
+                    continue;
+                }
+                _ => {
+                    return Err(RemoteWalletError::Protocol(
+                        "Unsupported protobuf wire type",
+                    ))
+                }
+            };
+            fields.push((field, value));
+        }
+        Ok(fields)
+    }
+
+    pub fn get_bytes<'a>(fields: &[(u32, Value<'a>)], field: u32) -> Option<&'a [u8]> {
+        fields.iter().rev().find_map(|(number, value)| match value {
+            Value::Bytes(bytes) if *number == field => Some(*bytes),
+            _ => None,
+        })
+    }
+
+    pub fn get_uint(fields: &[(u32, Value)], field: u32) -> Option<u64> {
+        fields.iter().rev().find_map(|(number, value)| match value {
+            Value::Varint(value) if *number == field => Some(*value),
+            _ => None,
+        })
+    }
+
+    pub fn get_repeated_uint(fields: &[(u32, Value)], field: u32) -> Vec<u64> {
+        fields
+            .iter()
+            .filter_map(|(number, value)| match value {
+                Value::Varint(value) if *number == field => Some(*value),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn get_string(fields: &[(u32, Value)], field: u32) -> Option<String> {
+        get_bytes(fields, field).map(|bytes| String::from_utf8_lossy(bytes).to_string())
+    }
+}
+
+/// Split a message into the HID reports carrying it
+pub(crate) fn encode_message(message_type: u16, payload: &[u8]) -> Vec<[u8; HID_REPORT_SIZE]> {
+    let mut data = Vec::with_capacity(MESSAGE_HEADER_LEN - 1 + payload.len());
+    data.extend_from_slice(&MESSAGE_MAGIC);
+    data.extend_from_slice(&message_type.to_be_bytes());
+    data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    data.extend_from_slice(payload);
+    data.chunks(HID_REPORT_SIZE - 1)
+        .map(|chunk| {
+            let mut report = [0_u8; HID_REPORT_SIZE];
+            report[0] = REPORT_MAGIC;
+            report[1..1 + chunk.len()].copy_from_slice(chunk);
+            report
+        })
+        .collect()
+}
+
+/// Reassembles messages from the HID reports carrying them
+#[derive(Debug, Default)]
+pub(crate) struct MessageDecoder {
+    header: Option<(u16, usize)>,
+    payload: Vec<u8>,
+}
+
+impl MessageDecoder {
+    /// Add a report, returning the message type and payload once the last
+    /// report of the message is in
+    pub fn push(&mut self, report: &[u8]) -> Result<Option<(u16, Vec<u8>)>, RemoteWalletError> {
+        if report.first() != Some(&REPORT_MAGIC) {
+            return Err(RemoteWalletError::Protocol("Unexpected report header"));
+        }
+        let (message_type, len, data) = match self.header {
+            Some((message_type, len)) => (message_type, len, &report[1..]),
+            None => {
+                if report.len() < MESSAGE_HEADER_LEN || report[1..3] != MESSAGE_MAGIC {
+                    return Err(RemoteWalletError::Protocol("Unexpected message header"));
+                }
+                let message_type = u16::from_be_bytes([report[3], report[4]]);
+                let len = u32::from_be_bytes([report[5], report[6], report[7], report[8]]) as usize;
+                if len > MAX_MESSAGE_LEN {
+                    return Err(RemoteWalletError::Protocol("Message too long"));
+                }
+                self.header = Some((message_type, len));
+                (message_type, len, &report[MESSAGE_HEADER_LEN..])
+            }
+        };
+        let size = min(len - self.payload.len(), data.len());
+        self.payload.extend_from_slice(&data[..size]);
+        if self.payload.len() < len {
+            return Ok(None);
+        }
+        self.header = None;
+        Ok(Some((message_type, std::mem::take(&mut self.payload))))
+    }
+}
+
+/// The serialized `address_n` field of a derivation path
+fn serialize_derivation_path(buffer: &mut Vec<u8>, derivation_path: &DerivationPath) {
+    for index in derivation_path.path() {
+        proto::put_uint(buffer, 1, u64::from(index.to_bits()));
+    }
+}
+
+fn parse_failure(payload: &[u8]) -> RemoteWalletError {
+    let Ok(fields) = proto::fields(payload) else {
+        return RemoteWalletError::Protocol("Invalid failure message");
+    };
+    match proto::get_uint(&fields, 1) {
+        Some(failure::ACTION_CANCELLED) | Some(failure::PIN_CANCELLED) => {
+            RemoteWalletError::UserCancel
+        }
+        _ => RemoteWalletError::DeviceError(
+            proto::get_string(&fields, 2).unwrap_or_else(|| "Unknown error".to_string()),
+        ),
+    }
+}
+
+/// Trezor Wallet device
+pub struct TrezorWallet {
+    pub transport: Box<dyn HidTransport>,
+    pub pretty_path: String,
+}
+
+impl fmt::Debug for TrezorWallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TrezorWallet")
+    }
+}
+
+/// Where a Trezor device was found
+#[derive(Debug, Clone)]
+pub struct TrezorDeviceInfo {
+    pub host_device_path: String,
+}
+
+impl TrezorWallet {
+    pub fn new(transport: Box<dyn HidTransport>) -> Self {
+        Self {
+            transport,
+            pretty_path: String::default(),
+        }
+    }
+
+    fn write_message(&self, message_type: u16, payload: &[u8]) -> Result<(), RemoteWalletError> {
+        for report in encode_message(message_type, payload) {
+            trace!("Trezor write {:?}", &report[..]);
+            self.transport.write_report(&report)?;
+        }
+        Ok(())
+    }
+
+    fn read_message(&self) -> Result<(u16, Vec<u8>), RemoteWalletError> {
+        let mut decoder = MessageDecoder::default();
+        loop {
+            let mut report = [0_u8; HID_REPORT_SIZE];
+            let size = self.transport.read_report(&mut report)?;
+            trace!("Trezor read {:?}", &report[..size]);
+            if let Some(message) = decoder.push(&report[..size])? {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Send a message and return the device's answer, handling the PIN,
+    /// passphrase and button requests the device makes along the way
+    fn call(
+        &self,
+        message_type: u16,
+        payload: &[u8],
+        expected_type: u16,
+    ) -> Result<Vec<u8>, RemoteWalletError> {
+        self.write_message(message_type, payload)?;
+        let mut awaiting_approval = false;
+        loop {
+            let (message_type, payload) = self.read_message()?;
+            match message_type {
+                messages::BUTTON_REQUEST => {
+                    if !awaiting_approval {
+                        println!(
+                            "Waiting for your approval on {} {}",
+                            self.name(),
+                            self.pretty_path
+                        );
+                        awaiting_approval = true;
+                    }
+                    self.write_message(messages::BUTTON_ACK, &[])?;
+                }
+                messages::PIN_MATRIX_REQUEST => {
+                    let pin = Password::new()
+                        .with_prompt(
+                            "Enter the PIN using the positions shown on the device \
+                             (7 8 9 / 4 5 6 / 1 2 3)",
+                        )
+                        .interact()
+                        .map_err(|err| RemoteWalletError::InvalidInput(err.to_string()))?;
+                    let mut ack = vec![];
+                    proto::put_bytes(&mut ack, 1, pin.as_bytes());
+                    self.write_message(messages::PIN_MATRIX_ACK, &ack)?;
+                }
+                messages::PASSPHRASE_REQUEST => {
+                    let passphrase = Password::new()
+                        .with_prompt("Enter the wallet passphrase")
+                        .allow_empty_password(true)
+                        .interact()
+                        .map_err(|err| RemoteWalletError::InvalidInput(err.to_string()))?;
+                    let mut ack = vec![];
+                    proto::put_bytes(&mut ack, 1, passphrase.as_bytes());
+                    self.write_message(messages::PASSPHRASE_ACK, &ack)?;
+                }
+                messages::FAILURE => return Err(parse_failure(&payload)),
+                message_type if message_type == expected_type => {
+                    if awaiting_approval {
+                        println!("{CHECK_MARK}Approved");
+                    }
+                    return Ok(payload);
+                }
+                _ => return Err(RemoteWalletError::Protocol("Unexpected response message")),
+            }
+        }
+    }
+}
+
+impl RemoteWallet<TrezorDeviceInfo> for TrezorWallet {
+    fn name(&self) -> &str {
+        "Trezor hardware wallet"
+    }
+
+    fn read_device(
+        &mut self,
+        dev_info: &TrezorDeviceInfo,
+    ) -> Result<RemoteWalletInfo, RemoteWalletError> {
+        let features = self.call(messages::INITIALIZE, &[], messages::FEATURES)?;
+        let features = proto::fields(&features)?;
+        // Features: vendor = 1, device_id = 6, model = 21
+        let model = proto::get_string(&features, 21)
+            .unwrap_or_else(|| "Unknown".to_string())
+            .to_lowercase()
+            .replace(' ', "-");
+        let serial = proto::get_string(&features, 6).unwrap_or_else(|| "Unknown".to_string());
+        let pubkey_result = self.get_pubkey(&DerivationPath::default(), false);
+        let (pubkey, error) = match pubkey_result {
+            Ok(pubkey) => (pubkey, None),
+            Err(err) => (Pubkey::default(), Some(err)),
+        };
+        Ok(RemoteWalletInfo {
+            model,
+            manufacturer: Manufacturer::Trezor,
+            serial,
+            host_device_path: dev_info.host_device_path.clone(),
+            pubkey,
+            error,
+        })
+    }
+
+    fn get_pubkey(
+        &self,
+        derivation_path: &DerivationPath,
+        confirm_key: bool,
+    ) -> Result<Pubkey, RemoteWalletError> {
+        let mut payload = vec![];
+        serialize_derivation_path(&mut payload, derivation_path);
+        proto::put_uint(&mut payload, 2, u64::from(confirm_key));
+        let response = self.call(
+            messages::TREZOA_GET_PUBLIC_KEY,
+            &payload,
+            messages::TREZOA_PUBLIC_KEY,
+        )?;
+        let fields = proto::fields(&response)?;
+        proto::get_bytes(&fields, 1)
+            .and_then(|key| Pubkey::try_from(key).ok())
+            .ok_or(RemoteWalletError::Protocol("Key packet size mismatch"))
+    }
+
+    fn sign_message(
+        &self,
+        derivation_path: &DerivationPath,
+        data: &[u8],
+    ) -> Result<Signature, RemoteWalletError> {
+        // Off-chain messages start with the 0xff signing domain byte
+        if !data.is_empty() && data[0] == 0xff {
+            return self.sign_offchain_message(derivation_path, data);
+        }
+        let mut payload = vec![];
+        serialize_derivation_path(&mut payload, derivation_path);
+        proto::put_bytes(&mut payload, 2, data);
+        let response = self.call(
+            messages::TREZOA_SIGN_TX,
+            &payload,
+            messages::TREZOA_TX_SIGNATURE,
+        )?;
+        let fields = proto::fields(&response)?;
+        proto::get_bytes(&fields, 1)
+            .and_then(|signature| Signature::try_from(signature).ok())
+            .ok_or(RemoteWalletError::Protocol(
+                "Signature packet size mismatch",
+            ))
+    }
+
+    fn sign_offchain_message(
+        &self,
+        _derivation_path: &DerivationPath,
+        _message: &[u8],
+    ) -> Result<Signature, RemoteWalletError> {
+        Err(RemoteWalletError::InvalidInput(
+            "Trezor devices can't sign off-chain messages".to_string(),
+        ))
+    }
+}
+
+/// Check if the detected device is a valid `Trezor device` by checking both the product ID and the vendor ID
+pub fn is_valid_trezor(vendor_id: u16, product_id: u16) -> bool {
+    (vendor_id == TREZOR_ONE_VID && product_id == TREZOR_ONE_PID)
+        || (vendor_id == TREZOR_VID && product_id == TREZOR_PID)
+}
+
+/// Choose a Trezor wallet based on matching info fields
+pub fn get_trezor_from_info(
+    info: RemoteWalletInfo,
+    keypair_name: &str,
+    wallet_manager: &RemoteWalletManager,
+) -> Result<Rc<TrezorWallet>, RemoteWalletError> {
+    let host_device_path = select_device_from_info(info, keypair_name, wallet_manager)?;
+    wallet_manager.get_trezor(&host_device_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::simulator::{SimulatorTransport, TrezorSimulator},
+        trezoa_sdk::signature::{keypair_from_seed_and_derivation_path, Signer},
+    };
+
+    const SEED: [u8; 32] = [7; 32];
+
+    fn simulated_wallet(simulator: TrezorSimulator) -> TrezorWallet {
+        let mut wallet = TrezorWallet::new(Box::new(SimulatorTransport::new(simulator)));
+        let info = wallet
+            .read_device(&TrezorDeviceInfo {
+                host_device_path: "simulator".to_string(),
+            })
+            .unwrap();
+        wallet.pretty_path = info.get_pretty_path();
+        wallet
+    }
+
+    #[test]
+    fn test_proto_round_trip() {
+        let mut buffer = vec![];
+        proto::put_uint(&mut buffer, 1, 0x8000_002c);
+        proto::put_uint(&mut buffer, 1, 0x8000_01f5);
+        proto::put_bytes(&mut buffer, 2, b"hello");
+        proto::put_uint(&mut buffer, 3, 1);
+        let fields = proto::fields(&buffer).unwrap();
+        assert_eq!(
+            proto::get_repeated_uint(&fields, 1),
+            vec![0x8000_002c, 0x8000_01f5]
+        );
+        assert_eq!(proto::get_bytes(&fields, 2), Some(&b"hello"[..]));
+        assert_eq!(proto::get_uint(&fields, 3), Some(1));
+        assert_eq!(proto::get_uint(&fields, 4), None);
+        assert!(proto::fields(&[0x12, 0x05, b'h']).is_err());
+    }
+
+    #[test]
+    fn test_message_framing() {
+        let payload: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let reports = encode_message(messages::TREZOA_SIGN_TX, &payload);
+        // 8 header bytes and the payload, 63 bytes per report
+        assert_eq!(reports.len(), 4);
+        let mut decoder = MessageDecoder::default();
+        for report in &reports[..3] {
+            assert_eq!(decoder.push(report).unwrap(), None);
+        }
+        assert_eq!(
+            decoder.push(&reports[3]).unwrap(),
+            Some((messages::TREZOA_SIGN_TX, payload))
+        );
+
+        assert_eq!(
+            MessageDecoder::default()
+                .push(&encode_message(messages::BUTTON_ACK, &[])[0])
+                .unwrap(),
+            Some((messages::BUTTON_ACK, vec![]))
+        );
+        assert!(MessageDecoder::default()
+            .push(&[0; HID_REPORT_SIZE])
+            .is_err());
+    }
+
+    #[test]
+    fn test_simulated_trezor() {
+        let wallet = simulated_wallet(TrezorSimulator::new(&SEED));
+        let derivation_path = DerivationPath::new_bip44(Some(1), Some(0));
+        let keypair =
+            keypair_from_seed_and_derivation_path(&SEED, Some(derivation_path.clone())).unwrap();
+        assert_eq!(
+            wallet.get_pubkey(&derivation_path, false).unwrap(),
+            keypair.pubkey()
+        );
+        assert!(wallet.pretty_path.starts_with("usb://trezor/"));
+
+        let message = b"transaction message";
+        let signature = wallet.sign_message(&derivation_path, message).unwrap();
+        assert_eq!(signature, keypair.sign_message(message));
+
+        let rejecting_wallet = simulated_wallet(TrezorSimulator::new(&SEED).reject_signing());
+        assert!(matches!(
+            rejecting_wallet.sign_message(&derivation_path, message),
+            Err(RemoteWalletError::UserCancel)
+        ));
+        assert!(matches!(
+            wallet.sign_message(&derivation_path, b"\xfftrezoa offchain"),
+            Err(RemoteWalletError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_valid_trezor() {
+        assert!(is_valid_trezor(TREZOR_ONE_VID, TREZOR_ONE_PID));
+        assert!(is_valid_trezor(TREZOR_VID, TREZOR_PID));
+        assert!(!is_valid_trezor(TREZOR_VID, TREZOR_ONE_PID));
+        assert!(!is_valid_trezor(0x2c97, 0x0001));
+    }
+}
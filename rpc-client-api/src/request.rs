@@ -105,6 +105,7 @@ pub enum RpcRequest {
     GetTokenSupply,
     GetTransaction,
     GetTransactionCount,
+    GetTransactionSendStatus,
     GetVersion,
     GetVoteAccounts,
     IsBlockhashValid,
@@ -181,6 +182,7 @@ impl fmt::Display for RpcRequest {
             RpcRequest::GetTokenLargestAccounts => "getTokenLargestAccounts",
             RpcRequest::GetTransaction => "getTransaction",
             RpcRequest::GetTransactionCount => "getTransactionCount",
+            RpcRequest::GetTransactionSendStatus => "getTransactionSendStatus",
             RpcRequest::GetVersion => "getVersion",
             RpcRequest::GetVoteAccounts => "getVoteAccounts",
             RpcRequest::IsBlockhashValid => "isBlockhashValid",
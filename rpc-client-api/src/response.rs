@@ -555,6 +555,35 @@ pub struct RpcPrioritizationFee {
     pub prioritization_fee: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RpcTransactionSendState {
+    /// Still being retried
+    Pending,
+    /// Processed in a rooted block
+    Rooted,
+    /// The blockhash or durable nonce expired before the transaction was processed
+    Expired,
+    /// Sent as many times as the request or the node allows
+    MaxRetriesExceeded,
+    /// Processed with an error
+    Failed,
+    /// Sent once, then dropped because the node's retry pool was full
+    RetryPoolFull,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcTransactionSendStatus {
+    pub state: RpcTransactionSendState,
+    /// Times the transaction was sent again after its first broadcast
+    pub retries: u64,
+    /// Leaders and peers the transaction was last sent to
+    pub last_sent_to: Vec<String>,
+    /// Milliseconds since the transaction was last sent
+    pub last_sent_ms_ago: Option<u64>,
+}
+
 #[cfg(test)]
 pub mod tests {
 
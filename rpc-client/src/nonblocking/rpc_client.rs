@@ -3581,6 +3581,45 @@ impl RpcClient {
             .await
     }
 
+    /// Returns how the node's send-transaction-service is retrying a
+    /// transaction submitted with [`send_transaction`], or why it stopped.
+    ///
+    /// Returns `None` if the transaction was never submitted to this node, or
+    /// finished too long ago for the node to remember.
+    ///
+    /// [`send_transaction`]: RpcClient::send_transaction
+    ///
+    /// # RPC Reference
+    ///
+    /// This method corresponds directly to the [`getTransactionSendStatus`] RPC method.
+    ///
+    /// [`getTransactionSendStatus`]: https://trezoa.com/docs/rpc/http/gettransactionsendstatus
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trezoa_rpc_client_api::client_error::Error;
+    /// # use trezoa_rpc_client::nonblocking::rpc_client::RpcClient;
+    /// # use trezoa_sdk::signature::Signature;
+    /// # futures::executor::block_on(async {
+    /// #     let rpc_client = RpcClient::new_mock("succeeds".to_string());
+    /// #     let signature = Signature::default();
+    /// let send_status = rpc_client.get_transaction_send_status(&signature).await?;
+    /// #     Ok::<(), Error>(())
+    /// # })?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub async fn get_transaction_send_status(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<Option<RpcTransactionSendStatus>> {
+        self.send(
+            RpcRequest::GetTransactionSendStatus,
+            json!([signature.to_string()]),
+        )
+        .await
+    }
+
     /// Returns the identity pubkey for the current node.
     ///
     /// # RPC Reference
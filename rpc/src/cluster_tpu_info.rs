@@ -18,6 +18,7 @@ pub struct ClusterTpuInfo {
     cluster_info: Arc<ClusterInfo>,
     poh_recorder: Arc<RwLock<PohRecorder>>,
     recent_peers: HashMap<Pubkey, (SocketAddr, SocketAddr)>, // values are socket address for UDP and QUIC protocols
+    recent_stakes: Arc<HashMap<Pubkey, u64>>,
 }
 
 impl ClusterTpuInfo {
@@ -26,6 +27,7 @@ impl ClusterTpuInfo {
             cluster_info,
             poh_recorder,
             recent_peers: HashMap::new(),
+            recent_stakes: Arc::default(),
         }
     }
 }
@@ -46,6 +48,8 @@ impl TpuInfo for ClusterTpuInfo {
                 ))
             })
             .collect();
+        let bank = self.poh_recorder.read().unwrap().start_bank();
+        self.recent_stakes = bank.epoch_staked_nodes(bank.epoch()).unwrap_or_default();
     }
 
     fn get_leader_tpus(&self, max_count: u64, protocol: Protocol) -> Vec<&SocketAddr> {
@@ -100,6 +104,22 @@ impl TpuInfo for ClusterTpuInfo {
         unique_leaders.sort_by_key(|(_addr, slot)| *slot);
         unique_leaders
     }
+
+    fn get_leaders_with_stake(&self, max_count: u64) -> Vec<(Pubkey, f64)> {
+        let recorder = self.poh_recorder.read().unwrap();
+        let leaders: Vec<_> = (0..max_count)
+            .filter_map(|i| recorder.leader_after_n_slots(i * NUM_CONSECUTIVE_LEADER_SLOTS))
+            .collect();
+        drop(recorder);
+        let total_stake = self.recent_stakes.values().sum::<u64>().max(1);
+        leaders
+            .into_iter()
+            .map(|leader| {
+                let stake = self.recent_stakes.get(&leader).copied().unwrap_or_default();
+                (leader, stake as f64 / total_stake as f64)
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -200,6 +220,7 @@ mod test {
             cluster_info,
             poh_recorder: Arc::new(RwLock::new(poh_recorder)),
             recent_peers: recent_peers.clone(),
+            recent_stakes: bank.epoch_staked_nodes(bank.epoch()).unwrap(),
         };
 
         let slot = bank.slot();
@@ -244,5 +265,18 @@ mod test {
         for x in 4..8 {
             assert!(leader_info.get_leader_tpus(x, Protocol::UDP).len() <= recent_peers.len());
         }
+
+        // Rotations with their leader's share of the stake, split evenly here
+        let leaders = leader_info.get_leaders_with_stake(3);
+        assert_eq!(
+            leaders
+                .iter()
+                .map(|(leader, _)| *leader)
+                .collect::<Vec<_>>(),
+            vec![first_leader, second_leader, third_leader]
+        );
+        for (_, stake) in leaders {
+            assert!((stake - 1.0 / 3.0).abs() < 1e-3);
+        }
     }
 }
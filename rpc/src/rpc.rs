@@ -80,7 +80,8 @@ use {
         },
     },
     trezoa_send_transaction_service::{
-        send_transaction_service::{SendTransactionService, TransactionInfo},
+        send_status::{SendOutcome, SendStatusTracker},
+        send_transaction_service::{self, SendTransactionService, TransactionInfo},
         tpu_info::NullTpuInfo,
     },
     trezoa_stake_program,
@@ -210,6 +211,7 @@ pub struct JsonRpcRequestProcessor {
     max_complete_transaction_status_slot: Arc<AtomicU64>,
     max_complete_rewards_slot: Arc<AtomicU64>,
     prioritization_fee_cache: Arc<PrioritizationFeeCache>,
+    send_status_tracker: Arc<SendStatusTracker>,
 }
 impl Metadata for JsonRpcRequestProcessor {}
 
@@ -326,6 +328,7 @@ impl JsonRpcRequestProcessor {
         max_complete_transaction_status_slot: Arc<AtomicU64>,
         max_complete_rewards_slot: Arc<AtomicU64>,
         prioritization_fee_cache: Arc<PrioritizationFeeCache>,
+        send_status_tracker: Arc<SendStatusTracker>,
     ) -> (Self, Receiver<TransactionInfo>) {
         let (sender, receiver) = unbounded();
         (
@@ -348,6 +351,7 @@ impl JsonRpcRequestProcessor {
                 max_complete_transaction_status_slot,
                 max_complete_rewards_slot,
                 prioritization_fee_cache,
+                send_status_tracker,
             },
             receiver,
         )
@@ -377,14 +381,19 @@ impl JsonRpcRequestProcessor {
             .tpu(connection_cache.protocol())
             .unwrap();
         let (sender, receiver) = unbounded();
-        SendTransactionService::new::<NullTpuInfo>(
+        let send_status_tracker = Arc::<SendStatusTracker>::default();
+        SendTransactionService::new_with_config::<NullTpuInfo>(
             tpu_address,
             &bank_forks,
             None,
             receiver,
             &connection_cache,
-            1000,
-            1,
+            send_transaction_service::Config {
+                retry_rate_ms: 1000,
+                leader_forward_count: 1,
+                ..send_transaction_service::Config::default()
+            },
+            send_status_tracker.clone(),
             exit.clone(),
         );
 
@@ -422,6 +431,7 @@ impl JsonRpcRequestProcessor {
             max_complete_transaction_status_slot: Arc::new(AtomicU64::default()),
             max_complete_rewards_slot: Arc::new(AtomicU64::default()),
             prioritization_fee_cache: Arc::new(PrioritizationFeeCache::default()),
+            send_status_tracker,
         }
     }
 
@@ -2223,6 +2233,33 @@ impl JsonRpcRequestProcessor {
             })
             .collect())
     }
+
+    fn get_transaction_send_status(
+        &self,
+        signature: &Signature,
+    ) -> Option<RpcTransactionSendStatus> {
+        let status = self.send_status_tracker.get_status(signature)?;
+        let state = match status.outcome {
+            None => RpcTransactionSendState::Pending,
+            Some(SendOutcome::Rooted) => RpcTransactionSendState::Rooted,
+            Some(SendOutcome::Expired) => RpcTransactionSendState::Expired,
+            Some(SendOutcome::MaxRetriesExceeded) => RpcTransactionSendState::MaxRetriesExceeded,
+            Some(SendOutcome::Failed) => RpcTransactionSendState::Failed,
+            Some(SendOutcome::RetryPoolFull) => RpcTransactionSendState::RetryPoolFull,
+        };
+        Some(RpcTransactionSendStatus {
+            state,
+            retries: status.retries as u64,
+            last_sent_to: status
+                .last_sent_to
+                .iter()
+                .map(|address| address.to_string())
+                .collect(),
+            last_sent_ms_ago: status
+                .last_sent_time
+                .map(|last_sent_time| last_sent_time.elapsed().as_millis() as u64),
+        })
+    }
 }
 
 fn optimize_filters(filters: &mut [RpcFilterType]) {
@@ -3441,6 +3478,13 @@ pub mod rpc_full {
             meta: Self::Metadata,
             pubkey_strs: Option<Vec<String>>,
         ) -> Result<Vec<RpcPrioritizationFee>>;
+
+        #[rpc(meta, name = "getTransactionSendStatus")]
+        fn get_transaction_send_status(
+            &self,
+            meta: Self::Metadata,
+            signature_str: String,
+        ) -> Result<Option<RpcTransactionSendStatus>>;
     }
 
     pub struct FullImpl;
@@ -4063,6 +4107,19 @@ pub mod rpc_full {
                 .collect::<Result<Vec<_>>>()?;
             meta.get_recent_prioritization_fees(pubkeys)
         }
+
+        fn get_transaction_send_status(
+            &self,
+            meta: Self::Metadata,
+            signature_str: String,
+        ) -> Result<Option<RpcTransactionSendStatus>> {
+            debug!(
+                "get_transaction_send_status rpc request received: {:?}",
+                signature_str
+            );
+            let signature = verify_signature(&signature_str)?;
+            Ok(meta.get_transaction_send_status(&signature))
+        }
     }
 }
 
@@ -4899,6 +4956,7 @@ pub mod tests {
                 max_complete_transaction_status_slot.clone(),
                 max_complete_rewards_slot,
                 Arc::new(PrioritizationFeeCache::default()),
+                Arc::default(),
             )
             .0;
 
@@ -6849,6 +6907,70 @@ pub mod tests {
         assert_eq!(error["code"], ErrorCode::InvalidParams.code());
     }
 
+    #[test]
+    fn test_rpc_get_transaction_send_status() {
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(100);
+        let bank = Bank::new_for_tests(&genesis_config);
+        let recent_blockhash = bank.last_blockhash();
+        let connection_cache = Arc::new(ConnectionCache::new("connection_cache_test"));
+        let meta = JsonRpcRequestProcessor::new_from_bank(
+            bank,
+            SocketAddrSpace::Unspecified,
+            connection_cache,
+        );
+
+        let mut io = MetaIoHandler::default();
+        io.extend_with(rpc_full::FullImpl.to_delegate());
+
+        let get_send_status = |signature: &str| {
+            let req = format!(
+                r#"{{"jsonrpc":"2.0","id":1,"method":"getTransactionSendStatus","params":["{signature}"]}}"#
+            );
+            let res = io.handle_request_sync(&req, meta.clone());
+            serde_json::from_str::<Value>(&res.unwrap()).unwrap()
+        };
+
+        let json = get_send_status("37u9WtQpcm6ULa3Vmu7ySnANv");
+        assert_eq!(json["error"]["code"], ErrorCode::InvalidParams.code());
+
+        let transaction = system_transaction::transfer(
+            &mint_keypair,
+            &trezoa_sdk::pubkey::new_rand(),
+            1,
+            recent_blockhash,
+        );
+        let signature = transaction.signatures[0].to_string();
+        assert_eq!(get_send_status(&signature)["result"], Value::Null);
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"sendTransaction","params":["{}",{{"skipPreflight":true}}]}}"#,
+            bs58::encode(serialize(&transaction).unwrap()).into_string()
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let json: Value = serde_json::from_str(&res.unwrap()).unwrap();
+        assert_eq!(json["result"], signature);
+
+        // The transaction enters the retry pool once its first batch is sent
+        let mut status = None;
+        for _ in 0..100 {
+            let result = get_send_status(&signature)["result"].clone();
+            if !result.is_null() {
+                status = Some(serde_json::from_value::<RpcTransactionSendStatus>(result).unwrap());
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let status = status.expect("transaction send status");
+        assert_eq!(status.state, RpcTransactionSendState::Pending);
+        assert_eq!(status.retries, 0);
+        assert_eq!(status.last_sent_to.len(), 1);
+        assert!(status.last_sent_ms_ago.is_some());
+    }
+
     #[test]
     fn test_rpc_send_transaction_preflight() {
         let exit = Arc::new(AtomicBool::new(false));
@@ -6899,6 +7021,7 @@ pub mod tests {
             Arc::new(AtomicU64::default()),
             Arc::new(AtomicU64::default()),
             Arc::new(PrioritizationFeeCache::default()),
+            Arc::default(),
         );
         SendTransactionService::new::<NullTpuInfo>(
             tpu_address,
@@ -7173,6 +7296,7 @@ pub mod tests {
             Arc::new(AtomicU64::default()),
             Arc::new(AtomicU64::default()),
             Arc::new(PrioritizationFeeCache::default()),
+            Arc::default(),
         );
         SendTransactionService::new::<NullTpuInfo>(
             tpu_address,
@@ -8805,6 +8929,7 @@ pub mod tests {
             max_complete_transaction_status_slot,
             max_complete_rewards_slot,
             Arc::new(PrioritizationFeeCache::default()),
+            Arc::default(),
         );
 
         let mut io = MetaIoHandler::default();
@@ -40,7 +40,10 @@ use {
         exit::Exit, genesis_config::DEFAULT_GENESIS_DOWNLOAD_PATH, hash::Hash,
         native_token::lamports_to_trz,
     },
-    trezoa_send_transaction_service::send_transaction_service::{self, SendTransactionService},
+    trezoa_send_transaction_service::{
+        send_status::SendStatusTracker,
+        send_transaction_service::{self, SendTransactionService},
+    },
     trezoa_storage_bigtable::CredentialType,
     std::{
         net::SocketAddr,
@@ -458,6 +461,7 @@ impl JsonRpcService {
         let max_request_body_size = config
             .max_request_body_size
             .unwrap_or(MAX_REQUEST_BODY_SIZE);
+        let send_status_tracker = Arc::<SendStatusTracker>::default();
         let (request_processor, receiver) = JsonRpcRequestProcessor::new(
             config,
             snapshot_config.clone(),
@@ -476,6 +480,7 @@ impl JsonRpcService {
             max_complete_transaction_status_slot,
             max_complete_rewards_slot,
             prioritization_fee_cache,
+            send_status_tracker.clone(),
         );
 
         let leader_info =
@@ -487,6 +492,7 @@ impl JsonRpcService {
             receiver,
             &connection_cache,
             send_transaction_service_config,
+            send_status_tracker,
             exit,
         ));
 
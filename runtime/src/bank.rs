@@ -42,7 +42,7 @@ use trezoa_sdk::recent_blockhashes_account;
 pub use trezoa_sdk::reward_type::RewardType;
 use {
     crate::{
-        bank::metrics::*,
+        bank::{account_source::OptionalAccountSource, metrics::*},
         bank_forks::BankForks,
         builtins::{BuiltinPrototype, BUILTINS},
         epoch_rewards_hasher::hash_rewards_into_partitions,
@@ -207,6 +207,7 @@ struct VerifyAccountsHashConfig {
     store_hash_raw_data_for_debug: bool,
 }
 
+pub mod account_source;
 mod address_lookup_table;
 pub mod bank_hash_details;
 mod builtin_programs;
@@ -339,7 +340,7 @@ impl TransactionBalancesSet {
 }
 pub type TransactionBalances = Vec<Vec<u64>>;
 
-#[derive(Serialize, Deserialize, AbiExample, AbiEnumVisitor, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, AbiExample, AbiEnumVisitor, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TransactionLogCollectorFilter {
     All,
     AllWithVotes,
@@ -551,6 +552,7 @@ impl PartialEq for Bank {
             epoch_reward_status: _,
             transaction_processor: _,
             check_program_modification_slot: _,
+            account_source: _,
             // Ignore new fields explicitly if they do not impact PartialEq.
             // Adding ".." will remove compile-time checks that if a new field
             // is added to the struct, this PartialEq is accordingly updated.
@@ -813,6 +815,9 @@ pub struct Bank {
     transaction_processor: TransactionBatchProcessor<BankForks>,
 
     check_program_modification_slot: bool,
+
+    /// fallback for accounts missing from the accounts db, see [`Bank::set_account_source`]
+    account_source: OptionalAccountSource,
 }
 
 struct VoteWithStakeDelegations {
@@ -1000,6 +1005,7 @@ impl Bank {
             epoch_reward_status: EpochRewardStatus::default(),
             transaction_processor: TransactionBatchProcessor::default(),
             check_program_modification_slot: false,
+            account_source: OptionalAccountSource::default(),
         };
 
         bank.transaction_processor = TransactionBatchProcessor::new(
@@ -1319,6 +1325,7 @@ impl Bank {
             epoch_reward_status: parent.epoch_reward_status.clone(),
             transaction_processor: TransactionBatchProcessor::default(),
             check_program_modification_slot: false,
+            account_source: parent.account_source.clone(),
         };
 
         new.transaction_processor = TransactionBatchProcessor::new(
@@ -1866,6 +1873,7 @@ impl Bank {
             epoch_reward_status: fields.epoch_reward_status,
             transaction_processor: TransactionBatchProcessor::default(),
             check_program_modification_slot: false,
+            account_source: OptionalAccountSource::default(),
         };
 
         bank.transaction_processor = TransactionBatchProcessor::new(
@@ -1985,6 +1993,10 @@ impl Bank {
         self.slot
     }
 
+    pub fn runtime_config(&self) -> &RuntimeConfig {
+        &self.runtime_config
+    }
+
     pub fn bank_id(&self) -> BankId {
         self.bank_id
     }
@@ -4422,6 +4434,7 @@ impl Bank {
     }
 
     pub fn remove_unrooted_slots(&self, slots: &[(Slot, BankId)]) {
+        self.account_source.remove_unrooted_slots(slots);
         self.rc.accounts.accounts_db.remove_unrooted_slots(slots)
     }
 
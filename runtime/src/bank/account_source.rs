@@ -0,0 +1,186 @@
+//! Fallback source for accounts that are missing from a bank's accounts db
+//!
+//! Banks configured with an [`AccountSource`] never consult it while loading
+//! accounts for transactions. Instead, the accounts a transaction may touch
+//! are preloaded into the bank before it is executed, with
+//! [`Bank::preload_message_accounts_from_account_source`], so that resolved
+//! accounts are stored, hashed and capitalized like any other account. Reads
+//! may look through to the source with [`Bank::get_account_from_source`],
+//! which never writes to the bank.
+
+use {
+    super::Bank,
+    log::*,
+    trezoa_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        address_lookup_table::state::AddressLookupTable,
+        bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        clock::{BankId, Slot},
+        message::VersionedMessage,
+        pubkey::Pubkey,
+    },
+    std::{
+        collections::HashMap,
+        fmt,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// Resolves accounts that a bank does not know about
+pub trait AccountSource: fmt::Debug + Send + Sync {
+    /// Returns the account stored at `pubkey`, if the source has one
+    fn get_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData>;
+}
+
+#[derive(Debug)]
+pub(super) struct LoadedAccountSource {
+    source: Arc<dyn AccountSource>,
+    /// slot each account was loaded into, so that accounts which were later
+    /// deleted and cleaned from the index are not resolved again
+    loaded: Mutex<HashMap<Pubkey, Slot>>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct OptionalAccountSource(pub(super) Option<Arc<LoadedAccountSource>>);
+
+#[cfg(RUSTC_WITH_SPECIALIZATION)]
+impl trezoa_frozen_abi::abi_example::AbiExample for OptionalAccountSource {
+    fn example() -> Self {
+        Self(None)
+    }
+}
+
+impl OptionalAccountSource {
+    /// Forget the accounts loaded into `slots`, which are being purged
+    pub(super) fn remove_unrooted_slots(&self, slots: &[(Slot, BankId)]) {
+        if let Some(account_source) = &self.0 {
+            account_source
+                .loaded
+                .lock()
+                .unwrap()
+                .retain(|_, slot| slots.iter().all(|(purged, _)| purged != slot));
+        }
+    }
+}
+
+impl Bank {
+    /// Install a fallback source for accounts missing from this bank and its
+    /// descendants
+    pub fn set_account_source(&mut self, account_source: Arc<dyn AccountSource>) {
+        self.account_source = OptionalAccountSource(Some(Arc::new(LoadedAccountSource {
+            source: account_source,
+            loaded: Mutex::default(),
+        })));
+    }
+
+    /// Fetch `pubkey` from the account source if neither this bank nor its
+    /// ancestors have ever seen it, without storing it
+    pub fn get_account_from_source(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        let account_source = self.account_source.0.as_ref()?;
+        // Zero lamport accounts remain in the index until they are cleaned, so
+        // accounts deleted by a transaction are not brought back
+        if self.rc.accounts.accounts_db.accounts_index.contains(pubkey) {
+            return None;
+        }
+        if let Some(slot) = account_source.loaded.lock().unwrap().get(pubkey) {
+            let max_root = self
+                .rc
+                .accounts
+                .accounts_db
+                .accounts_index
+                .max_root_inclusive();
+            if *slot <= max_root || self.ancestors.contains_key(slot) {
+                return None;
+            }
+        }
+        account_source.source.get_account(pubkey)
+    }
+
+    /// Store the accounts in `pubkeys` which are missing from this bank into
+    /// it, resolving them from the account source
+    ///
+    /// Must be called before the bank is frozen, and not while transactions
+    /// are being executed against it. Returns the number of accounts loaded.
+    pub fn preload_from_account_source<'a>(
+        &self,
+        pubkeys: impl IntoIterator<Item = &'a Pubkey>,
+    ) -> usize {
+        let Some(account_source) = self.account_source.0.as_ref() else {
+            return 0;
+        };
+        if self.freeze_started() {
+            warn!(
+                "Not preloading accounts from account source into frozen bank {}",
+                self.slot()
+            );
+            return 0;
+        }
+        let mut num_loaded = 0;
+        for pubkey in pubkeys {
+            let Some(account) = self.get_account_from_source(pubkey) else {
+                continue;
+            };
+            self.store_account_and_update_capitalization(pubkey, &account);
+            account_source
+                .loaded
+                .lock()
+                .unwrap()
+                .insert(*pubkey, self.slot());
+            num_loaded += 1;
+        }
+        num_loaded
+    }
+
+    /// Store the accounts a transaction with `message` may load which are
+    /// missing from this bank into it, resolving them from the account source
+    ///
+    /// This covers the message's static keys, its address lookup tables and
+    /// the addresses it looks up in them, and the program data accounts of
+    /// upgradeable programs it references. The same restrictions as for
+    /// [`preload_from_account_source`](Self::preload_from_account_source)
+    /// apply.
+    pub fn preload_message_accounts_from_account_source(&self, message: &VersionedMessage) {
+        if self.account_source.0.is_none() {
+            return;
+        }
+        let static_account_keys = message.static_account_keys();
+        self.preload_from_account_source(static_account_keys);
+        let address_table_lookups = message.address_table_lookups().unwrap_or_default();
+        self.preload_from_account_source(
+            address_table_lookups
+                .iter()
+                .map(|lookup| &lookup.account_key),
+        );
+        for lookup in address_table_lookups {
+            let Some(table_account) = self.get_account(&lookup.account_key) else {
+                continue;
+            };
+            let Ok(table) = AddressLookupTable::deserialize(table_account.data()) else {
+                continue;
+            };
+            self.preload_from_account_source(
+                lookup
+                    .writable_indexes
+                    .iter()
+                    .chain(&lookup.readonly_indexes)
+                    .filter_map(|index| table.addresses.get(usize::from(*index))),
+            );
+        }
+        let programdata_addresses: Vec<_> = static_account_keys
+            .iter()
+            .filter_map(|pubkey| {
+                let account = self.get_account(pubkey)?;
+                if !bpf_loader_upgradeable::check_id(account.owner()) {
+                    return None;
+                }
+                match account.deserialize_data() {
+                    Ok(UpgradeableLoaderState::Program {
+                        programdata_address,
+                    }) => Some(programdata_address),
+                    _ => None,
+                }
+            })
+            .collect();
+        self.preload_from_account_source(&programdata_addresses);
+    }
+}
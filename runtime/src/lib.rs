@@ -23,6 +23,7 @@ pub mod prioritization_fee;
 pub mod prioritization_fee_cache;
 pub mod root_bank_cache;
 pub mod serde_snapshot;
+pub mod snapshot_account_source;
 pub mod snapshot_archive_info;
 pub mod snapshot_bank_utils;
 pub mod snapshot_config;
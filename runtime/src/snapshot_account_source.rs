@@ -0,0 +1,189 @@
+//! An [`AccountSource`] backed by the accounts in a full snapshot archive
+//!
+//! The archive is unpacked once into temporary directories and its account
+//! storages are indexed by pubkey. Account data is only read from the storages
+//! when an account is actually requested, so banks using this source behave as
+//! if they had been forked from the snapshot without loading all of it.
+
+use {
+    crate::{
+        bank::account_source::AccountSource,
+        snapshot_archive_info::{FullSnapshotArchiveInfo, SnapshotArchiveInfoGetter},
+        snapshot_utils::{self, Result},
+    },
+    log::*,
+    trezoa_accounts_db::account_storage::AccountStorageMap,
+    trezoa_measure::measure::Measure,
+    trezoa_sdk::{
+        account::{AccountSharedData, ReadableAccount},
+        clock::Slot,
+        pubkey::Pubkey,
+    },
+    std::{collections::HashMap, path::Path},
+    tempfile::TempDir,
+};
+
+/// Resolves accounts from the state captured in a full snapshot archive
+#[derive(Debug)]
+pub struct SnapshotArchiveAccountSource {
+    /// slot of the snapshot the accounts were taken from
+    slot: Slot,
+    storage: AccountStorageMap,
+    /// location of the newest version of each account: storage slot and offset
+    index: HashMap<Pubkey, (Slot, usize)>,
+    _bank_snapshots_dir: TempDir,
+    _accounts_dir: TempDir,
+}
+
+impl SnapshotArchiveAccountSource {
+    /// Unpack the full snapshot archive at `full_snapshot_archive_path` and
+    /// index its accounts
+    pub fn new(full_snapshot_archive_path: impl AsRef<Path>) -> Result<Self> {
+        let full_snapshot_archive_info =
+            FullSnapshotArchiveInfo::new_from_path(full_snapshot_archive_path.as_ref().into())?;
+        let bank_snapshots_dir = tempfile::Builder::new()
+            .prefix("account-source-snapshot-")
+            .tempdir()?;
+        let accounts_dir = tempfile::Builder::new()
+            .prefix("account-source-accounts-")
+            .tempdir()?;
+
+        let (unarchived_full_snapshot, _, _) = snapshot_utils::verify_and_unarchive_snapshots(
+            bank_snapshots_dir.path(),
+            &full_snapshot_archive_info,
+            None,
+            &[accounts_dir.path().to_path_buf()],
+        )?;
+        let storage = unarchived_full_snapshot.storage;
+
+        let mut measure = Measure::start("snapshot account source index");
+        let mut index = HashMap::<Pubkey, (Slot, usize)>::new();
+        for entry in storage.iter() {
+            let slot = *entry.key();
+            for stored_account in entry.value().storage.accounts.account_iter() {
+                index
+                    .entry(*stored_account.pubkey())
+                    .and_modify(|location| {
+                        if slot >= location.0 {
+                            *location = (slot, stored_account.offset());
+                        }
+                    })
+                    .or_insert((slot, stored_account.offset()));
+            }
+        }
+        measure.stop();
+        info!(
+            "Indexed {} accounts from snapshot archive {} in {measure}",
+            index.len(),
+            full_snapshot_archive_info.path().display(),
+        );
+
+        Ok(Self {
+            slot: full_snapshot_archive_info.slot(),
+            storage,
+            index,
+            _bank_snapshots_dir: bank_snapshots_dir,
+            _accounts_dir: accounts_dir,
+        })
+    }
+
+    /// Slot of the snapshot the accounts are resolved from
+    pub fn slot(&self) -> Slot {
+        self.slot
+    }
+
+    /// Number of accounts in the snapshot, including deleted ones
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+impl AccountSource for SnapshotArchiveAccountSource {
+    fn get_account(&self, pubkey: &Pubkey) -> Option<AccountSharedData> {
+        let (slot, offset) = self.index.get(pubkey)?;
+        let storage = self.storage.get(slot)?;
+        let (stored_account, _next_offset) = storage.storage.accounts.get_account(*offset)?;
+        (stored_account.lamports() != 0).then(|| stored_account.to_account_shared_data())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            bank::Bank, snapshot_bank_utils::bank_to_full_snapshot_archive,
+            snapshot_utils::ArchiveFormat,
+        },
+        trezoa_sdk::{genesis_config::create_genesis_config, system_program},
+        std::sync::Arc,
+    };
+
+    #[test]
+    fn test_snapshot_archive_account_source() {
+        let (genesis_config, _mint_keypair) = create_genesis_config(1_000_000_000);
+        let updated = Pubkey::new_unique();
+        let created = Pubkey::new_unique();
+        let deleted = Pubkey::new_unique();
+
+        let bank0 = Bank::new_for_tests(&genesis_config);
+        bank0.store_account(
+            &updated,
+            &AccountSharedData::new(1, 0, &system_program::id()),
+        );
+        bank0.store_account(
+            &deleted,
+            &AccountSharedData::new(2, 0, &system_program::id()),
+        );
+        while !bank0.is_complete() {
+            bank0.register_unique_tick();
+        }
+        let bank1 = Bank::new_from_parent(Arc::new(bank0), &Pubkey::default(), 1);
+        bank1.store_account(
+            &updated,
+            &AccountSharedData::new(3, 0, &system_program::id()),
+        );
+        bank1.store_account(
+            &created,
+            &AccountSharedData::new(4, 0, &system_program::id()),
+        );
+        bank1.store_account(&deleted, &AccountSharedData::default());
+        while !bank1.is_complete() {
+            bank1.register_unique_tick();
+        }
+
+        let bank_snapshots_dir = tempfile::TempDir::new().unwrap();
+        let full_snapshot_archives_dir = tempfile::TempDir::new().unwrap();
+        let incremental_snapshot_archives_dir = tempfile::TempDir::new().unwrap();
+        let full_snapshot_archive_info = bank_to_full_snapshot_archive(
+            &bank_snapshots_dir,
+            &bank1,
+            None,
+            full_snapshot_archives_dir.path(),
+            incremental_snapshot_archives_dir.path(),
+            ArchiveFormat::Tar,
+            snapshot_utils::DEFAULT_MAX_FULL_SNAPSHOT_ARCHIVES_TO_RETAIN,
+            snapshot_utils::DEFAULT_MAX_INCREMENTAL_SNAPSHOT_ARCHIVES_TO_RETAIN,
+        )
+        .unwrap();
+
+        let account_source =
+            SnapshotArchiveAccountSource::new(full_snapshot_archive_info.path()).unwrap();
+        assert_eq!(account_source.slot(), 1);
+        assert!(!account_source.is_empty());
+        // The newest version of an account is resolved
+        assert_eq!(account_source.get_account(&updated).unwrap().lamports(), 3);
+        assert_eq!(account_source.get_account(&created).unwrap().lamports(), 4);
+        assert_eq!(account_source.get_account(&deleted), None);
+        assert_eq!(account_source.get_account(&Pubkey::new_unique()), None);
+        // Accounts from genesis are there as well
+        assert_eq!(
+            account_source.get_account(&system_program::id()),
+            bank1.get_account(&system_program::id())
+        );
+    }
+}
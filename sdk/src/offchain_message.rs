@@ -18,6 +18,10 @@ static_assertions::const_assert_eq!(OffchainMessage::HEADER_LEN, 17);
 static_assertions::const_assert_eq!(v0::OffchainMessage::MAX_LEN, 65515);
 #[cfg(test)]
 static_assertions::const_assert_eq!(v0::OffchainMessage::MAX_LEN_LEDGER, 1212);
+#[cfg(test)]
+static_assertions::const_assert_eq!(v1::OffchainMessage::HEADER_LEN, 38);
+#[cfg(test)]
+static_assertions::const_assert_eq!(v1::OffchainMessage::max_len_ledger(1), 1145);
 
 /// Check if given bytes contain only printable ASCII characters
 pub fn is_printable_ascii(data: &[u8]) -> bool {
@@ -36,10 +40,14 @@ pub fn is_utf8(data: &[u8]) -> bool {
 
 #[repr(u8)]
 #[derive(Debug, PartialEq, Eq, Copy, Clone, TryFromPrimitive, IntoPrimitive)]
+#[non_exhaustive]
 pub enum MessageFormat {
     RestrictedAscii,
     LimitedUtf8,
     ExtendedUtf8,
+    /// Version 1 only: the message is the SHA256 digest of a document that is
+    /// signed by reference
+    Sha256Digest,
 }
 
 #[allow(clippy::arithmetic_side_effects)]
@@ -134,6 +142,7 @@ pub mod v0 {
                     (message.len() <= Self::MAX_LEN_LEDGER) && is_utf8(message)
                 }
                 MessageFormat::ExtendedUtf8 => (message.len() <= Self::MAX_LEN) && is_utf8(message),
+                MessageFormat::Sha256Digest => false,
             };
 
             if is_valid {
@@ -163,9 +172,346 @@ pub mod v0 {
     }
 }
 
+#[allow(clippy::arithmetic_side_effects)]
+pub mod v1 {
+    use {
+        super::{is_printable_ascii, is_utf8, MessageFormat, OffchainMessage as Base},
+        crate::{
+            hash::{self, Hash, Hasher, HASH_BYTES},
+            packet::PACKET_DATA_SIZE,
+            pubkey::{Pubkey, PUBKEY_BYTES},
+            sanitize::SanitizeError,
+            signature::{Signature, Signer, SIGNATURE_BYTES},
+        },
+    };
+
+    /// OffchainMessage Version 1.
+    /// Struct always contains a non-empty valid message and between one and
+    /// `MAX_SIGNERS` distinct signers.
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    pub struct OffchainMessage {
+        application_domain: [u8; Self::APPLICATION_DOMAIN_LEN],
+        format: MessageFormat,
+        signers: Vec<Pubkey>,
+        message: Vec<u8>,
+    }
+
+    impl OffchainMessage {
+        pub const APPLICATION_DOMAIN_LEN: usize = 32;
+        // Header Length = Application Domain (32) + Message Format (1) + Signer Count (1)
+        //   + Message Length (4), followed by the signers
+        pub const HEADER_LEN: usize = Self::APPLICATION_DOMAIN_LEN + 6;
+        // Max number of signers of the OffchainMessage
+        pub const MAX_SIGNERS: usize = u8::MAX as usize;
+        // Max length of the OffchainMessage
+        pub const MAX_LEN: usize = u32::MAX as usize;
+
+        /// Max length of the OffchainMessage with `num_signers` signers supported by the Ledger
+        pub const fn max_len_ledger(num_signers: usize) -> usize {
+            PACKET_DATA_SIZE
+                .saturating_sub(Base::HEADER_LEN + Self::HEADER_LEN)
+                .saturating_sub(num_signers.saturating_mul(PUBKEY_BYTES))
+        }
+
+        /// Construct a new OffchainMessage object from the given message
+        pub fn new(
+            application_domain: [u8; Self::APPLICATION_DOMAIN_LEN],
+            signers: &[Pubkey],
+            message: &[u8],
+        ) -> Result<Self, SanitizeError> {
+            Self::check_signers(signers)?;
+            let format = if message.is_empty() {
+                return Err(SanitizeError::InvalidValue);
+            } else if message.len() <= Self::max_len_ledger(signers.len()) {
+                if is_printable_ascii(message) {
+                    MessageFormat::RestrictedAscii
+                } else if is_utf8(message) {
+                    MessageFormat::LimitedUtf8
+                } else {
+                    return Err(SanitizeError::InvalidValue);
+                }
+            } else if message.len() <= Self::MAX_LEN {
+                if is_utf8(message) {
+                    MessageFormat::ExtendedUtf8
+                } else {
+                    return Err(SanitizeError::InvalidValue);
+                }
+            } else {
+                return Err(SanitizeError::ValueOutOfBounds);
+            };
+            Ok(Self {
+                application_domain,
+                format,
+                signers: signers.to_vec(),
+                message: message.to_vec(),
+            })
+        }
+
+        /// Construct a new OffchainMessage object that signs the SHA256 digest
+        /// of `document`, which may be of any length or encoding
+        pub fn new_hashed(
+            application_domain: [u8; Self::APPLICATION_DOMAIN_LEN],
+            signers: &[Pubkey],
+            document: &[u8],
+        ) -> Result<Self, SanitizeError> {
+            Self::check_signers(signers)?;
+            Ok(Self {
+                application_domain,
+                format: MessageFormat::Sha256Digest,
+                signers: signers.to_vec(),
+                message: hash::hash(document).to_bytes().to_vec(),
+            })
+        }
+
+        fn check_signers(signers: &[Pubkey]) -> Result<(), SanitizeError> {
+            if signers.is_empty() || signers.len() > Self::MAX_SIGNERS {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            if signers
+                .iter()
+                .enumerate()
+                .any(|(i, signer)| signers[..i].contains(signer))
+            {
+                return Err(SanitizeError::InvalidValue);
+            }
+            Ok(())
+        }
+
+        fn is_valid_message(format: MessageFormat, message: &[u8], num_signers: usize) -> bool {
+            let max_len_ledger = Self::max_len_ledger(num_signers);
+            match format {
+                MessageFormat::RestrictedAscii => {
+                    !message.is_empty()
+                        && message.len() <= max_len_ledger
+                        && is_printable_ascii(message)
+                }
+                MessageFormat::LimitedUtf8 => {
+                    !message.is_empty() && message.len() <= max_len_ledger && is_utf8(message)
+                }
+                MessageFormat::ExtendedUtf8 => {
+                    !message.is_empty() && message.len() <= Self::MAX_LEN && is_utf8(message)
+                }
+                MessageFormat::Sha256Digest => message.len() == HASH_BYTES,
+            }
+        }
+
+        /// Serialize the message to bytes, including the full header
+        pub fn serialize(&self, data: &mut Vec<u8>) -> Result<(), SanitizeError> {
+            // invalid messages shouldn't be possible, but a quick sanity check never hurts
+            assert!(Self::is_valid_message(
+                self.format,
+                &self.message,
+                self.signers.len()
+            ));
+            data.reserve(
+                Self::HEADER_LEN
+                    .saturating_add(self.signers.len().saturating_mul(PUBKEY_BYTES))
+                    .saturating_add(self.message.len()),
+            );
+            // application domain
+            data.extend_from_slice(&self.application_domain);
+            // format
+            data.push(self.format.into());
+            // signers
+            data.push(self.signers.len() as u8);
+            for signer in &self.signers {
+                data.extend_from_slice(signer.as_ref());
+            }
+            // message length
+            data.extend_from_slice(&(self.message.len() as u32).to_le_bytes());
+            // message
+            data.extend_from_slice(&self.message);
+            Ok(())
+        }
+
+        /// Deserialize the message from bytes that include a full header
+        pub fn deserialize(data: &[u8]) -> Result<Self, SanitizeError> {
+            if data.len() < Self::HEADER_LEN {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            // decode header
+            let (application_domain, data) = data.split_at(Self::APPLICATION_DOMAIN_LEN);
+            let format =
+                MessageFormat::try_from(data[0]).map_err(|_| SanitizeError::InvalidValue)?;
+            let num_signers = data[1] as usize;
+            let data = &data[2..];
+            let signers_len = num_signers * PUBKEY_BYTES;
+            if data.len() < signers_len + 4 {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            let (signers, data) = data.split_at(signers_len);
+            let signers: Vec<Pubkey> = signers
+                .chunks_exact(PUBKEY_BYTES)
+                .map(|signer| Pubkey::try_from(signer).unwrap())
+                .collect();
+            Self::check_signers(&signers)?;
+            let message_len = u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+            let message = &data[4..];
+            // check header
+            if message.len() != message_len {
+                return Err(SanitizeError::InvalidValue);
+            }
+            // check format
+            if !Self::is_valid_message(format, message, num_signers) {
+                return Err(SanitizeError::InvalidValue);
+            }
+            Ok(Self {
+                application_domain: application_domain.try_into().unwrap(),
+                format,
+                signers,
+                message: message.to_vec(),
+            })
+        }
+
+        /// Compute the SHA256 hash of the serialized off-chain message
+        pub fn hash(serialized_message: &[u8]) -> Result<Hash, SanitizeError> {
+            let mut hasher = Hasher::default();
+            hasher.hash(serialized_message);
+            Ok(hasher.result())
+        }
+
+        /// Check whether this message signs `document` by its SHA256 digest
+        pub fn matches_document(&self, document: &[u8]) -> bool {
+            self.format == MessageFormat::Sha256Digest
+                && self.message == hash::hash(document).as_ref()
+        }
+
+        pub fn get_application_domain(&self) -> &[u8; Self::APPLICATION_DOMAIN_LEN] {
+            &self.application_domain
+        }
+
+        pub fn get_format(&self) -> MessageFormat {
+            self.format
+        }
+
+        pub fn get_signers(&self) -> &[Pubkey] {
+            &self.signers
+        }
+
+        pub fn get_message(&self) -> &Vec<u8> {
+            &self.message
+        }
+    }
+
+    /// Version 1 message together with the signatures of its signers, in
+    /// signer order. Signatures that haven't been collected yet are
+    /// `Signature::default()`.
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    pub struct SignedOffchainMessage {
+        signatures: Vec<Signature>,
+        message: Base,
+    }
+
+    impl SignedOffchainMessage {
+        /// Construct an envelope for the given message with no signatures
+        pub fn new(message: OffchainMessage) -> Self {
+            Self {
+                signatures: vec![Signature::default(); message.signers.len()],
+                message: Base::V1(message),
+            }
+        }
+
+        fn signer_index(&self, pubkey: &Pubkey) -> Result<usize, SanitizeError> {
+            self.message
+                .get_signers()
+                .iter()
+                .position(|signer| signer == pubkey)
+                .ok_or(SanitizeError::InvalidValue)
+        }
+
+        /// Sign the message with the provided keypair, which must be one of its signers
+        pub fn sign(&mut self, signer: &dyn Signer) -> Result<Signature, SanitizeError> {
+            let index = self.signer_index(&signer.pubkey())?;
+            let signature = self.message.sign(signer)?;
+            self.signatures[index] = signature;
+            Ok(signature)
+        }
+
+        /// Add a signature collected elsewhere, failing if it isn't valid for `signer`
+        pub fn add_signature(
+            &mut self,
+            signer: &Pubkey,
+            signature: Signature,
+        ) -> Result<(), SanitizeError> {
+            let index = self.signer_index(signer)?;
+            if !self.message.verify(signer, &signature)? {
+                return Err(SanitizeError::InvalidValue);
+            }
+            self.signatures[index] = signature;
+            Ok(())
+        }
+
+        /// Verify that every signer has provided a valid signature
+        pub fn verify(&self) -> Result<bool, SanitizeError> {
+            let serialized_message = self.message.serialize()?;
+            Ok(self
+                .message
+                .get_signers()
+                .iter()
+                .zip(&self.signatures)
+                .all(|(signer, signature)| signature.verify(signer.as_ref(), &serialized_message)))
+        }
+
+        /// Signers that haven't provided a signature yet
+        pub fn missing_signers(&self) -> Vec<Pubkey> {
+            self.message
+                .get_signers()
+                .iter()
+                .zip(&self.signatures)
+                .filter(|(_, signature)| **signature == Signature::default())
+                .map(|(signer, _)| *signer)
+                .collect()
+        }
+
+        /// Serialize the envelope as the signature count, the signatures and
+        /// the full off-chain message
+        pub fn serialize(&self) -> Result<Vec<u8>, SanitizeError> {
+            let mut data = vec![self.signatures.len() as u8];
+            for signature in &self.signatures {
+                data.extend_from_slice(signature.as_ref());
+            }
+            data.extend_from_slice(&self.message.serialize()?);
+            Ok(data)
+        }
+
+        /// Deserialize an envelope, without verifying its signatures
+        pub fn deserialize(data: &[u8]) -> Result<Self, SanitizeError> {
+            let (num_signatures, data) =
+                data.split_first().ok_or(SanitizeError::ValueOutOfBounds)?;
+            let signatures_len = *num_signatures as usize * SIGNATURE_BYTES;
+            if data.len() < signatures_len {
+                return Err(SanitizeError::ValueOutOfBounds);
+            }
+            let (signatures, data) = data.split_at(signatures_len);
+            let message = Base::deserialize(data)?;
+            if !matches!(message, Base::V1(_))
+                || message.get_signers().len() != *num_signatures as usize
+            {
+                return Err(SanitizeError::InvalidValue);
+            }
+            Ok(Self {
+                signatures: signatures
+                    .chunks_exact(SIGNATURE_BYTES)
+                    .map(|signature| Signature::try_from(signature).unwrap())
+                    .collect(),
+                message,
+            })
+        }
+
+        pub fn get_signatures(&self) -> &[Signature] {
+            &self.signatures
+        }
+
+        pub fn get_message(&self) -> &Base {
+            &self.message
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum OffchainMessage {
     V0(v0::OffchainMessage),
+    V1(v1::OffchainMessage),
 }
 
 impl OffchainMessage {
@@ -173,7 +519,8 @@ impl OffchainMessage {
     // Header Length = Signing Domain (16) + Header Version (1)
     pub const HEADER_LEN: usize = Self::SIGNING_DOMAIN.len() + 1;
 
-    /// Construct a new OffchainMessage object from the given version and message
+    /// Construct a new OffchainMessage object from the given version and message.
+    /// Version 1 messages name their signers and are built with `v1::OffchainMessage`.
     pub fn new(version: u8, message: &[u8]) -> Result<Self, SanitizeError> {
         match version {
             0 => Ok(Self::V0(v0::OffchainMessage::new(message)?)),
@@ -192,6 +539,10 @@ impl OffchainMessage {
                 data.push(0);
                 msg.serialize(&mut data)?;
             }
+            Self::V1(msg) => {
+                data.push(1);
+                msg.serialize(&mut data)?;
+            }
         }
         Ok(data)
     }
@@ -205,6 +556,7 @@ impl OffchainMessage {
         let data = &data[Self::SIGNING_DOMAIN.len().saturating_add(1)..];
         match version {
             0 => Ok(Self::V0(v0::OffchainMessage::deserialize(data)?)),
+            1 => Ok(Self::V1(v1::OffchainMessage::deserialize(data)?)),
             _ => Err(SanitizeError::ValueOutOfBounds),
         }
     }
@@ -213,41 +565,68 @@ impl OffchainMessage {
     pub fn hash(&self) -> Result<Hash, SanitizeError> {
         match self {
             Self::V0(_) => v0::OffchainMessage::hash(&self.serialize()?),
+            Self::V1(_) => v1::OffchainMessage::hash(&self.serialize()?),
         }
     }
 
     pub fn get_version(&self) -> u8 {
         match self {
             Self::V0(_) => 0,
+            Self::V1(_) => 1,
         }
     }
 
     pub fn get_format(&self) -> MessageFormat {
         match self {
             Self::V0(msg) => msg.get_format(),
+            Self::V1(msg) => msg.get_format(),
         }
     }
 
     pub fn get_message(&self) -> &Vec<u8> {
         match self {
             Self::V0(msg) => msg.get_message(),
+            Self::V1(msg) => msg.get_message(),
+        }
+    }
+
+    /// Signers named by the message; version 0 messages don't name any
+    pub fn get_signers(&self) -> &[Pubkey] {
+        match self {
+            Self::V0(_) => &[],
+            Self::V1(msg) => msg.get_signers(),
         }
     }
 
-    /// Sign the message with provided keypair
+    fn is_signer(&self, pubkey: &Pubkey) -> bool {
+        match self {
+            Self::V0(_) => true,
+            Self::V1(msg) => msg.get_signers().contains(pubkey),
+        }
+    }
+
+    /// Sign the message with provided keypair, which must be one of its
+    /// signers if the message names them
     pub fn sign(&self, signer: &dyn Signer) -> Result<Signature, SanitizeError> {
+        if !self.is_signer(&signer.pubkey()) {
+            return Err(SanitizeError::InvalidValue);
+        }
         Ok(signer.sign_message(&self.serialize()?))
     }
 
     /// Verify that the message signature is valid for the given public key
     pub fn verify(&self, signer: &Pubkey, signature: &Signature) -> Result<bool, SanitizeError> {
-        Ok(signature.verify(signer.as_ref(), &self.serialize()?))
+        Ok(self.is_signer(signer) && signature.verify(signer.as_ref(), &self.serialize()?))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use {super::*, crate::signature::Keypair, std::str::FromStr};
+    use {
+        super::*,
+        crate::{hash, packet::PACKET_DATA_SIZE, signature::Keypair},
+        std::str::FromStr,
+    };
 
     #[test]
     fn test_offchain_message_ascii() {
@@ -299,4 +678,136 @@ mod tests {
         let signature = message.sign(&keypair).unwrap();
         assert!(message.verify(&keypair.pubkey(), &signature).unwrap());
     }
+
+    #[test]
+    fn test_offchain_message_v1() {
+        let application_domain = [7; 32];
+        let signers = [
+            Pubkey::new_from_array([1; 32]),
+            Pubkey::new_from_array([2; 32]),
+        ];
+        let message = OffchainMessage::V1(
+            v1::OffchainMessage::new(application_domain, &signers, b"Test Message").unwrap(),
+        );
+        assert_eq!(message.get_version(), 1);
+        assert_eq!(message.get_format(), MessageFormat::RestrictedAscii);
+        assert_eq!(message.get_signers(), &signers);
+        assert_eq!(message.get_message().as_slice(), b"Test Message");
+
+        let serialized = message.serialize().unwrap();
+        let mut expected = OffchainMessage::SIGNING_DOMAIN.to_vec();
+        expected.push(1);
+        expected.extend_from_slice(&application_domain);
+        expected.extend_from_slice(&[0, 2]);
+        expected.extend_from_slice(&[1; 32]);
+        expected.extend_from_slice(&[2; 32]);
+        expected.extend_from_slice(&[12, 0, 0, 0]);
+        expected.extend_from_slice(b"Test Message");
+        assert_eq!(serialized, expected);
+        assert_eq!(message, OffchainMessage::deserialize(&serialized).unwrap());
+
+        // Messages too long for the Ledger are accepted as extended UTF8
+        let long_message = "a".repeat(70_000);
+        let message =
+            v1::OffchainMessage::new(application_domain, &signers, long_message.as_bytes())
+                .unwrap();
+        assert_eq!(message.get_format(), MessageFormat::ExtendedUtf8);
+        let message = OffchainMessage::V1(message);
+        assert_eq!(
+            message,
+            OffchainMessage::deserialize(&message.serialize().unwrap()).unwrap()
+        );
+
+        assert_eq!(
+            v1::OffchainMessage::new(application_domain, &[], b"Test Message"),
+            Err(SanitizeError::ValueOutOfBounds)
+        );
+        assert_eq!(
+            v1::OffchainMessage::new(application_domain, &[signers[0], signers[0]], b"Test"),
+            Err(SanitizeError::InvalidValue)
+        );
+        assert_eq!(
+            v1::OffchainMessage::new(application_domain, &signers, b""),
+            Err(SanitizeError::InvalidValue)
+        );
+
+        // Truncated or tampered messages are rejected
+        assert!(OffchainMessage::deserialize(&serialized[..serialized.len() - 1]).is_err());
+        let mut tampered = serialized;
+        tampered[OffchainMessage::HEADER_LEN + 33] = 3;
+        assert!(OffchainMessage::deserialize(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_offchain_message_v1_hashed() {
+        let signer = Pubkey::new_unique();
+        let document = vec![0xff; 1_000_000];
+        let message = v1::OffchainMessage::new_hashed([0; 32], &[signer], &document).unwrap();
+        assert_eq!(message.get_format(), MessageFormat::Sha256Digest);
+        assert_eq!(
+            message.get_message().as_slice(),
+            hash::hash(&document).as_ref()
+        );
+        assert!(message.matches_document(&document));
+        assert!(!message.matches_document(b"other document"));
+
+        let message = OffchainMessage::V1(message);
+        let serialized = message.serialize().unwrap();
+        assert!(serialized.len() <= PACKET_DATA_SIZE);
+        assert_eq!(message, OffchainMessage::deserialize(&serialized).unwrap());
+
+        // Version 0 has no hashed format
+        let mut v0_serialized = OffchainMessage::new(0, b"Test Message")
+            .unwrap()
+            .serialize()
+            .unwrap();
+        v0_serialized[OffchainMessage::HEADER_LEN] = MessageFormat::Sha256Digest.into();
+        assert_eq!(
+            OffchainMessage::deserialize(&v0_serialized),
+            Err(SanitizeError::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn test_offchain_message_v1_sign_and_verify() {
+        let keypair0 = Keypair::new();
+        let keypair1 = Keypair::new();
+        let outsider = Keypair::new();
+        let message = v1::OffchainMessage::new(
+            [0; 32],
+            &[keypair0.pubkey(), keypair1.pubkey()],
+            b"Test Message",
+        )
+        .unwrap();
+
+        let base = OffchainMessage::V1(message.clone());
+        let signature = base.sign(&keypair1).unwrap();
+        assert!(base.verify(&keypair1.pubkey(), &signature).unwrap());
+        assert!(!base.verify(&keypair0.pubkey(), &signature).unwrap());
+        assert_eq!(base.sign(&outsider), Err(SanitizeError::InvalidValue));
+
+        let mut envelope = v1::SignedOffchainMessage::new(message);
+        assert!(!envelope.verify().unwrap());
+        assert_eq!(
+            envelope.missing_signers(),
+            vec![keypair0.pubkey(), keypair1.pubkey()]
+        );
+        envelope.sign(&keypair0).unwrap();
+        assert_eq!(envelope.missing_signers(), vec![keypair1.pubkey()]);
+        assert_eq!(
+            envelope.add_signature(&keypair0.pubkey(), signature),
+            Err(SanitizeError::InvalidValue)
+        );
+        envelope
+            .add_signature(&keypair1.pubkey(), signature)
+            .unwrap();
+        assert!(envelope.verify().unwrap());
+        assert!(envelope.missing_signers().is_empty());
+
+        let serialized = envelope.serialize().unwrap();
+        let deserialized = v1::SignedOffchainMessage::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized, envelope);
+        assert!(deserialized.verify().unwrap());
+        assert!(v1::SignedOffchainMessage::deserialize(&serialized[1..]).is_err());
+    }
 }
@@ -0,0 +1,407 @@
+#![cfg(feature = "full")]
+
+//! Keypair files encrypted with a passphrase
+//!
+//! An encrypted keypair file is a versioned JSON object instead of the plain
+//! JSON byte array of an unencrypted file:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "pubkey": "9rPVSygg3brqghvdZ6wsL2i5YNQTGhXGdJzF65YxaCQd",
+//!   "kdf": { "name": "scrypt", "log_n": 17, "r": 8, "p": 1, "salt": "<base64>" },
+//!   "cipher": { "name": "aes-256-gcm-siv", "nonce": "<base64>", "ciphertext": "<base64>" }
+//! }
+//! ```
+//!
+//! The encryption key is derived from the passphrase with scrypt, and the
+//! keypair bytes are encrypted with AES-256-GCM-SIV, authenticating the pubkey
+//! as associated data. The pubkey is stored in the clear so that tools can tell
+//! which key a file holds before asking for its passphrase.
+
+use {
+    crate::{
+        pubkey::Pubkey,
+        signer::{keypair::Keypair, EncodableKey, Signer},
+    },
+    aes_gcm_siv::{
+        aead::{Aead, NewAead, Payload},
+        Aes256GcmSiv,
+    },
+    base64::{prelude::BASE64_STANDARD, Engine},
+    rand::{rngs::OsRng, RngCore},
+    serde_derive::{Deserialize, Serialize},
+    std::{
+        error, fs,
+        io::{Read, Write},
+        path::Path,
+        str::FromStr,
+    },
+    thiserror::Error,
+    zeroize::Zeroize,
+};
+
+/// Version of the encrypted keypair format written by this module
+pub const ENCRYPTED_KEYPAIR_VERSION: u32 = 1;
+
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum EncryptedKeypairError {
+    #[error("unsupported encrypted keypair version {0}")]
+    UnsupportedVersion(u32),
+    #[error("invalid encrypted keypair: {0}")]
+    InvalidFormat(String),
+    #[error("incorrect passphrase or corrupted keypair")]
+    Decryption,
+    #[error("keypair is encrypted with a passphrase")]
+    PassphraseRequired,
+}
+
+/// Cost parameters of the scrypt key derivation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for ScryptParams {
+    /// Parameters for keys protecting funds: about 128 MiB of memory and a
+    /// fraction of a second of work per attempt
+    fn default() -> Self {
+        Self {
+            log_n: 17,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+impl ScryptParams {
+    /// Reject parameters more expensive than the defaults, so that a crafted
+    /// file cannot make decryption use unbounded memory and time
+    fn check(&self) -> Result<(), EncryptedKeypairError> {
+        let max = Self::default();
+        if self.log_n > max.log_n || self.r > max.r || self.p > max.p {
+            return Err(EncryptedKeypairError::InvalidFormat(format!(
+                "scrypt params exceed log_n {}, r {}, p {}",
+                max.log_n, max.r, max.p
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "kebab-case")]
+pub enum KdfParams {
+    Scrypt {
+        log_n: u8,
+        r: u32,
+        p: u32,
+        salt: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "kebab-case")]
+pub enum CipherParams {
+    #[serde(rename = "aes-256-gcm-siv")]
+    Aes256GcmSiv { nonce: String, ciphertext: String },
+}
+
+/// The contents of an encrypted keypair file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedKeypair {
+    pub version: u32,
+    pub pubkey: String,
+    pub kdf: KdfParams,
+    pub cipher: CipherParams,
+}
+
+impl EncryptedKeypair {
+    /// Encrypt `keypair` with `passphrase` using the default scrypt parameters
+    pub fn encrypt(keypair: &Keypair, passphrase: &str) -> Result<Self, EncryptedKeypairError> {
+        Self::encrypt_with_params(keypair, passphrase, ScryptParams::default())
+    }
+
+    pub fn encrypt_with_params(
+        keypair: &Keypair,
+        passphrase: &str,
+        params: ScryptParams,
+    ) -> Result<Self, EncryptedKeypairError> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let pubkey = keypair.pubkey();
+        let mut key = derive_key(passphrase, &salt, params)?;
+        let mut keypair_bytes = keypair.to_bytes();
+        let ciphertext = Aes256GcmSiv::new(&key.into()).encrypt(
+            &nonce.into(),
+            Payload {
+                msg: &keypair_bytes,
+                aad: pubkey.as_ref(),
+            },
+        );
+        key.zeroize();
+        keypair_bytes.zeroize();
+        let ciphertext = ciphertext.map_err(|_| {
+            EncryptedKeypairError::InvalidFormat("keypair encryption failed".to_string())
+        })?;
+
+        Ok(Self {
+            version: ENCRYPTED_KEYPAIR_VERSION,
+            pubkey: pubkey.to_string(),
+            kdf: KdfParams::Scrypt {
+                log_n: params.log_n,
+                r: params.r,
+                p: params.p,
+                salt: BASE64_STANDARD.encode(salt),
+            },
+            cipher: CipherParams::Aes256GcmSiv {
+                nonce: BASE64_STANDARD.encode(nonce),
+                ciphertext: BASE64_STANDARD.encode(ciphertext),
+            },
+        })
+    }
+
+    /// The pubkey of the encrypted keypair, available without the passphrase
+    pub fn pubkey(&self) -> Result<Pubkey, EncryptedKeypairError> {
+        Pubkey::from_str(&self.pubkey)
+            .map_err(|err| EncryptedKeypairError::InvalidFormat(format!("pubkey: {err}")))
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<Keypair, EncryptedKeypairError> {
+        if self.version != ENCRYPTED_KEYPAIR_VERSION {
+            return Err(EncryptedKeypairError::UnsupportedVersion(self.version));
+        }
+        let pubkey = self.pubkey()?;
+        let KdfParams::Scrypt { log_n, r, p, salt } = &self.kdf;
+        let CipherParams::Aes256GcmSiv { nonce, ciphertext } = &self.cipher;
+        let salt = decode_base64("salt", salt)?;
+        let nonce = decode_base64("nonce", nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(EncryptedKeypairError::InvalidFormat(
+                "nonce length".to_string(),
+            ));
+        }
+        let ciphertext = decode_base64("ciphertext", ciphertext)?;
+
+        let params = ScryptParams {
+            log_n: *log_n,
+            r: *r,
+            p: *p,
+        };
+        let mut key = derive_key(passphrase, &salt, params)?;
+        let keypair_bytes = Aes256GcmSiv::new(&key.into()).decrypt(
+            nonce.as_slice().into(),
+            Payload {
+                msg: &ciphertext,
+                aad: pubkey.as_ref(),
+            },
+        );
+        key.zeroize();
+        let mut keypair_bytes = keypair_bytes.map_err(|_| EncryptedKeypairError::Decryption)?;
+        let keypair = Keypair::from_bytes(&keypair_bytes);
+        keypair_bytes.zeroize();
+        let keypair = keypair.map_err(|_| EncryptedKeypairError::Decryption)?;
+        if keypair.pubkey() != pubkey {
+            return Err(EncryptedKeypairError::Decryption);
+        }
+        Ok(keypair)
+    }
+}
+
+impl EncodableKey for EncryptedKeypair {
+    fn read<R: Read>(reader: &mut R) -> Result<Self, Box<dyn error::Error>> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<String, Box<dyn error::Error>> {
+        let serialized = serde_json::to_string(self)?;
+        writer.write_all(serialized.as_bytes())?;
+        Ok(serialized)
+    }
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: ScryptParams,
+) -> Result<[u8; KEY_LEN], EncryptedKeypairError> {
+    params.check()?;
+    let params = scrypt::Params::new(params.log_n, params.r, params.p, KEY_LEN)
+        .map_err(|err| EncryptedKeypairError::InvalidFormat(format!("scrypt params: {err}")))?;
+    let mut key = [0u8; KEY_LEN];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|err| EncryptedKeypairError::InvalidFormat(format!("scrypt: {err}")))?;
+    Ok(key)
+}
+
+fn decode_base64(field: &str, value: &str) -> Result<Vec<u8>, EncryptedKeypairError> {
+    BASE64_STANDARD
+        .decode(value)
+        .map_err(|err| EncryptedKeypairError::InvalidFormat(format!("{field}: {err}")))
+}
+
+/// Whether the JSON keypair in `contents` is an encrypted keypair rather than
+/// a plain byte array
+pub fn is_encrypted_keypair(contents: &str) -> bool {
+    contents.trim_start().starts_with('{')
+}
+
+/// Encrypts `keypair` with `passphrase` and writes it to `outfile`
+pub fn write_encrypted_keypair_file<F: AsRef<Path>>(
+    keypair: &Keypair,
+    passphrase: &str,
+    outfile: F,
+) -> Result<String, Box<dyn error::Error>> {
+    EncryptedKeypair::encrypt(keypair, passphrase)?.write_to_file(outfile)
+}
+
+/// Reads a `Keypair` from a file that is either plain or encrypted
+///
+/// `passphrase` is only called for encrypted files, with the pubkey of the
+/// encrypted keypair, and should obtain the passphrase from the user.
+pub fn read_keypair_file_with_passphrase<F, P>(
+    path: F,
+    passphrase: P,
+) -> Result<Keypair, Box<dyn error::Error>>
+where
+    F: AsRef<Path>,
+    P: FnOnce(&Pubkey) -> Result<String, Box<dyn error::Error>>,
+{
+    let contents = fs::read_to_string(path)?;
+    if is_encrypted_keypair(&contents) {
+        let encrypted: EncryptedKeypair = serde_json::from_str(&contents)?;
+        let passphrase = passphrase(&encrypted.pubkey()?)?;
+        Ok(encrypted.decrypt(&passphrase)?)
+    } else {
+        Keypair::read(&mut contents.as_bytes())
+    }
+}
+
+/// Reads the pubkey of a keypair file that is either plain or encrypted,
+/// without requiring the passphrase of an encrypted file
+pub fn read_keypair_file_pubkey<F: AsRef<Path>>(path: F) -> Result<Pubkey, Box<dyn error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    if is_encrypted_keypair(&contents) {
+        let encrypted: EncryptedKeypair = serde_json::from_str(&contents)?;
+        Ok(encrypted.pubkey()?)
+    } else {
+        Ok(Keypair::read(&mut contents.as_bytes())?.pubkey())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::signer::keypair::read_keypair_file, tempfile::TempDir};
+
+    // Cheap parameters so tests stay fast
+    const TEST_PARAMS: ScryptParams = ScryptParams {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let keypair = Keypair::new();
+        let encrypted =
+            EncryptedKeypair::encrypt_with_params(&keypair, "hunter2", TEST_PARAMS).unwrap();
+        assert_eq!(encrypted.pubkey().unwrap(), keypair.pubkey());
+        assert_eq!(encrypted.decrypt("hunter2").unwrap(), keypair);
+        assert_eq!(
+            encrypted.decrypt("hunter3").unwrap_err(),
+            EncryptedKeypairError::Decryption
+        );
+
+        // The stored pubkey is authenticated
+        let mut tampered = encrypted.clone();
+        tampered.pubkey = Keypair::new().pubkey().to_string();
+        assert_eq!(
+            tampered.decrypt("hunter2").unwrap_err(),
+            EncryptedKeypairError::Decryption
+        );
+
+        let mut future = encrypted.clone();
+        future.version = ENCRYPTED_KEYPAIR_VERSION + 1;
+        assert_eq!(
+            future.decrypt("hunter2").unwrap_err(),
+            EncryptedKeypairError::UnsupportedVersion(ENCRYPTED_KEYPAIR_VERSION + 1)
+        );
+
+        // Key derivation costs above the defaults are rejected up front
+        for (log_n, r, p) in [
+            (18, 8, 1),
+            (4, 9, 1),
+            (4, 8, 2),
+            (u8::MAX, u32::MAX, u32::MAX),
+        ] {
+            let mut expensive = encrypted.clone();
+            let KdfParams::Scrypt { salt, .. } = encrypted.kdf.clone();
+            expensive.kdf = KdfParams::Scrypt { log_n, r, p, salt };
+            assert!(matches!(
+                expensive.decrypt("hunter2").unwrap_err(),
+                EncryptedKeypairError::InvalidFormat(_)
+            ));
+        }
+        let expensive_params = ScryptParams {
+            log_n: 18,
+            ..ScryptParams::default()
+        };
+        assert!(
+            EncryptedKeypair::encrypt_with_params(&keypair, "hunter2", expensive_params).is_err()
+        );
+    }
+
+    #[test]
+    fn test_read_keypair_file_with_passphrase() {
+        let dir = TempDir::new().unwrap();
+        let keypair = Keypair::new();
+
+        let plain_path = dir.path().join("plain.json");
+        keypair.write_to_file(&plain_path).unwrap();
+        let read = read_keypair_file_with_passphrase(&plain_path, |_| {
+            panic!("plain keypair files need no passphrase")
+        })
+        .unwrap();
+        assert_eq!(read, keypair);
+
+        let encrypted_path = dir.path().join("encrypted.json");
+        EncryptedKeypair::encrypt_with_params(&keypair, "hunter2", TEST_PARAMS)
+            .unwrap()
+            .write_to_file(&encrypted_path)
+            .unwrap();
+        assert_eq!(
+            read_keypair_file_pubkey(&plain_path).unwrap(),
+            keypair.pubkey()
+        );
+        assert_eq!(
+            read_keypair_file_pubkey(&encrypted_path).unwrap(),
+            keypair.pubkey()
+        );
+        let read = read_keypair_file_with_passphrase(&encrypted_path, |pubkey| {
+            assert_eq!(*pubkey, keypair.pubkey());
+            Ok("hunter2".to_string())
+        })
+        .unwrap();
+        assert_eq!(read, keypair);
+        assert!(
+            read_keypair_file_with_passphrase(&encrypted_path, |_| Ok("wrong".to_string()))
+                .is_err()
+        );
+
+        // Reading without a passphrase explains why it failed
+        let err = read_keypair_file(&encrypted_path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            EncryptedKeypairError::PassphraseRequired.to_string()
+        );
+    }
+}
@@ -5,7 +5,10 @@ use {
         derivation_path::DerivationPath,
         pubkey::Pubkey,
         signature::Signature,
-        signer::{EncodableKey, EncodableKeypair, SeedDerivable, Signer, SignerError},
+        signer::{
+            encrypted_keypair::{is_encrypted_keypair, EncryptedKeypairError},
+            EncodableKey, EncodableKeypair, SeedDerivable, Signer, SignerError,
+        },
     },
     ed25519_dalek::Signer as DalekSigner,
     ed25519_dalek_bip32::Error as Bip32Error,
@@ -174,7 +177,12 @@ impl EncodableKeypair for Keypair {
 
 /// Reads a JSON-encoded `Keypair` from a `Reader` implementor
 pub fn read_keypair<R: Read>(reader: &mut R) -> Result<Keypair, Box<dyn error::Error>> {
-    let bytes: Vec<u8> = serde_json::from_reader(reader)?;
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    if is_encrypted_keypair(&contents) {
+        return Err(EncryptedKeypairError::PassphraseRequired.into());
+    }
+    let bytes: Vec<u8> = serde_json::from_str(&contents)?;
     Keypair::from_bytes(&bytes)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()).into())
 }
@@ -20,6 +20,7 @@ use {
     thiserror::Error,
 };
 
+pub mod encrypted_keypair;
 pub mod keypair;
 pub mod null_signer;
 pub mod presigner;
@@ -0,0 +1,169 @@
+//! Policies choosing where the send-transaction-service forwards transactions
+//!
+//! Every policy forwards to upcoming leaders, falling back to the node's own
+//! tpu when no leader is known. They differ in how many leaders are chosen and
+//! in which other peers, such as trusted relayers, also receive transactions.
+
+use {
+    crate::tpu_info::TpuInfo,
+    trezoa_sdk::pubkey::Pubkey,
+    std::{collections::HashSet, fmt, net::SocketAddr, str::FromStr},
+};
+
+/// Default number of leaders a stake weighted fanout forwards to at least
+pub const DEFAULT_STAKE_WEIGHTED_MIN_LEADERS: u64 = 1;
+/// Default number of leaders a stake weighted fanout forwards to at most
+pub const DEFAULT_STAKE_WEIGHTED_MAX_LEADERS: u64 = 8;
+/// Default share of the epoch's stake the chosen leaders should hold
+pub const DEFAULT_STAKE_WEIGHTED_STAKE_THRESHOLD: f64 = 0.05;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ForwardingPolicy {
+    /// Forward to `Config::leader_forward_count` upcoming leaders
+    #[default]
+    LeadersOnly,
+    /// Forward to `Config::leader_forward_count` upcoming leaders and to a
+    /// static set of peers
+    LeadersAndPeers { peers: Vec<SocketAddr> },
+    /// Forward to upcoming leaders until the distinct ones chosen hold
+    /// `stake_threshold` of the epoch's stake, so that the transactions reach
+    /// more leaders when the next ones are lightly staked and more likely to
+    /// skip their slots
+    StakeWeighted {
+        min_leaders: u64,
+        max_leaders: u64,
+        /// Share of the epoch's stake, between 0 and 1
+        stake_threshold: f64,
+    },
+}
+
+impl ForwardingPolicy {
+    pub fn stake_weighted() -> Self {
+        Self::StakeWeighted {
+            min_leaders: DEFAULT_STAKE_WEIGHTED_MIN_LEADERS,
+            max_leaders: DEFAULT_STAKE_WEIGHTED_MAX_LEADERS,
+            stake_threshold: DEFAULT_STAKE_WEIGHTED_STAKE_THRESHOLD,
+        }
+    }
+
+    /// Name of the policy, used to tag its metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::LeadersOnly => "leaders-only",
+            Self::LeadersAndPeers { .. } => "leaders-and-peers",
+            Self::StakeWeighted { .. } => "stake-weighted",
+        }
+    }
+
+    /// Peers receiving every transaction in addition to the leaders
+    pub fn peers(&self) -> &[SocketAddr] {
+        match self {
+            Self::LeadersAndPeers { peers } => peers,
+            Self::LeadersOnly | Self::StakeWeighted { .. } => &[],
+        }
+    }
+
+    /// Number of upcoming leaders to forward to
+    pub fn leader_count<T: TpuInfo>(
+        &self,
+        leader_info: Option<&T>,
+        leader_forward_count: u64,
+    ) -> u64 {
+        match self {
+            Self::LeadersOnly | Self::LeadersAndPeers { .. } => leader_forward_count,
+            Self::StakeWeighted {
+                min_leaders,
+                max_leaders,
+                stake_threshold,
+            } => {
+                let leaders = leader_info
+                    .map(|leader_info| leader_info.get_leaders_with_stake(*max_leaders))
+                    .unwrap_or_default();
+                stake_weighted_leader_count(&leaders, *min_leaders, *max_leaders, *stake_threshold)
+            }
+        }
+    }
+}
+
+fn stake_weighted_leader_count(
+    leaders: &[(Pubkey, f64)],
+    min_leaders: u64,
+    max_leaders: u64,
+    stake_threshold: f64,
+) -> u64 {
+    let mut chosen = HashSet::new();
+    let mut stake = 0.0;
+    let mut count = 0;
+    for (leader, leader_stake) in leaders {
+        if count >= max_leaders || (count >= min_leaders && stake >= stake_threshold) {
+            break;
+        }
+        count += 1;
+        // A leader holding consecutive rotations doesn't add to the stake reached
+        if chosen.insert(leader) {
+            stake += leader_stake;
+        }
+    }
+    count.clamp(min_leaders, max_leaders.max(min_leaders))
+}
+
+impl FromStr for ForwardingPolicy {
+    type Err = String;
+
+    /// Parse a policy name, peers of `leaders-and-peers` are configured apart
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "leaders-only" => Ok(Self::LeadersOnly),
+            "leaders-and-peers" => Ok(Self::LeadersAndPeers { peers: vec![] }),
+            "stake-weighted" => Ok(Self::stake_weighted()),
+            _ => Err(format!("unknown forwarding policy {s:?}")),
+        }
+    }
+}
+
+impl fmt::Display for ForwardingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stake_weighted_leader_count() {
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        // Stops once the threshold is reached
+        let leaders = [(a, 0.02), (b, 0.04), (c, 0.3)];
+        assert_eq!(stake_weighted_leader_count(&leaders, 1, 8, 0.05), 2);
+        assert_eq!(stake_weighted_leader_count(&leaders, 1, 8, 0.01), 1);
+        assert_eq!(stake_weighted_leader_count(&leaders, 1, 8, 0.2), 3);
+
+        // Bounded by the minimum and maximum
+        assert_eq!(stake_weighted_leader_count(&leaders, 3, 8, 0.01), 3);
+        assert_eq!(stake_weighted_leader_count(&leaders, 1, 2, 0.2), 2);
+        assert_eq!(stake_weighted_leader_count(&[], 2, 8, 0.05), 2);
+
+        // Repeated leaders count once towards the stake
+        let leaders = [(a, 0.03), (a, 0.03), (b, 0.03)];
+        assert_eq!(stake_weighted_leader_count(&leaders, 1, 8, 0.05), 3);
+    }
+
+    #[test]
+    fn test_forwarding_policy_from_str() {
+        for policy in [
+            ForwardingPolicy::LeadersOnly,
+            ForwardingPolicy::LeadersAndPeers { peers: vec![] },
+            ForwardingPolicy::stake_weighted(),
+        ] {
+            assert_eq!(policy.to_string().parse::<ForwardingPolicy>(), Ok(policy));
+        }
+        assert!("leaders".parse::<ForwardingPolicy>().is_err());
+    }
+}
@@ -1,4 +1,6 @@
 #![allow(clippy::arithmetic_side_effects)]
+pub mod forwarding_policy;
+pub mod send_status;
 pub mod send_transaction_service;
 pub mod tpu_info;
 
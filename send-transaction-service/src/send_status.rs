@@ -0,0 +1,154 @@
+//! Where the transactions handed to the send-transaction-service stand
+//!
+//! Transactions still being retried are looked up in the service's retry map,
+//! and those it stopped retrying in a bounded history of the most recent ones,
+//! so clients can find out how often a transaction was sent, to which
+//! addresses, and why the service gave up on it.
+
+use {
+    crate::send_transaction_service::TransactionInfo,
+    trezoa_sdk::signature::Signature,
+    std::{
+        collections::{HashMap, VecDeque},
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+        time::Instant,
+    },
+};
+
+/// Default number of finished transactions whose status is kept
+pub const DEFAULT_FINISHED_HISTORY_SIZE: usize = 10_000;
+
+/// Why the service stopped retrying a transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SendOutcome {
+    /// Processed in a rooted bank
+    Rooted,
+    /// Its blockhash or durable nonce expired before it was processed
+    Expired,
+    /// Sent as many times as the transaction or the service allows
+    MaxRetriesExceeded,
+    /// Processed with an error
+    Failed,
+    /// Sent once, then dropped because the retry pool was full
+    RetryPoolFull,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionSendStatus {
+    /// Times the transaction was sent again after its first broadcast
+    pub retries: usize,
+    pub last_sent_time: Option<Instant>,
+    /// Leaders and peers the transaction was last sent to
+    pub last_sent_to: Vec<SocketAddr>,
+    /// None while the transaction is retried
+    pub outcome: Option<SendOutcome>,
+}
+
+#[derive(Default)]
+struct FinishedHistory {
+    statuses: HashMap<Signature, TransactionSendStatus>,
+    // Signatures in the order they finished, oldest first
+    order: VecDeque<Signature>,
+}
+
+pub struct SendStatusTracker {
+    retry_transactions: Arc<Mutex<HashMap<Signature, TransactionInfo>>>,
+    finished: Mutex<FinishedHistory>,
+    history_size: usize,
+}
+
+impl Default for SendStatusTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_FINISHED_HISTORY_SIZE)
+    }
+}
+
+impl SendStatusTracker {
+    pub fn new(history_size: usize) -> Self {
+        Self {
+            retry_transactions: Arc::default(),
+            finished: Mutex::default(),
+            history_size,
+        }
+    }
+
+    /// Status of the transaction, or None if the service never retried it or
+    /// finished it too long ago to remember
+    pub fn get_status(&self, signature: &Signature) -> Option<TransactionSendStatus> {
+        // The retry map is checked first, transactions move from it to the
+        // history under its lock
+        if let Some(transaction_info) = self.retry_transactions.lock().unwrap().get(signature) {
+            return Some(transaction_info.send_status(None));
+        }
+        self.finished
+            .lock()
+            .unwrap()
+            .statuses
+            .get(signature)
+            .cloned()
+    }
+
+    pub(crate) fn retry_transactions(&self) -> &Arc<Mutex<HashMap<Signature, TransactionInfo>>> {
+        &self.retry_transactions
+    }
+
+    pub(crate) fn finish(
+        &self,
+        signature: Signature,
+        transaction_info: &TransactionInfo,
+        outcome: SendOutcome,
+    ) {
+        if self.history_size == 0 {
+            return;
+        }
+        let mut finished = self.finished.lock().unwrap();
+        let status = transaction_info.send_status(Some(outcome));
+        if finished.statuses.insert(signature, status).is_none() {
+            finished.order.push_back(signature);
+        }
+        while finished.order.len() > self.history_size {
+            if let Some(oldest) = finished.order.pop_front() {
+                finished.statuses.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_finished_history() {
+        let tracker = SendStatusTracker::new(2);
+        let transaction_info =
+            TransactionInfo::new(Signature::default(), vec![], 0, None, None, None);
+        let signatures: Vec<_> = (0..3).map(|_| Signature::new_unique()).collect();
+
+        tracker.finish(signatures[0], &transaction_info, SendOutcome::Expired);
+        tracker.finish(signatures[1], &transaction_info, SendOutcome::Rooted);
+        assert_eq!(
+            tracker.get_status(&signatures[0]).unwrap().outcome,
+            Some(SendOutcome::Expired)
+        );
+
+        // The oldest status is forgotten
+        tracker.finish(signatures[2], &transaction_info, SendOutcome::Failed);
+        assert_eq!(tracker.get_status(&signatures[0]), None);
+        assert_eq!(
+            tracker.get_status(&signatures[2]).unwrap().outcome,
+            Some(SendOutcome::Failed)
+        );
+
+        // Transactions being retried are pending
+        tracker
+            .retry_transactions()
+            .lock()
+            .unwrap()
+            .insert(signatures[0], transaction_info);
+        let status = tracker.get_status(&signatures[0]).unwrap();
+        assert_eq!(status.outcome, None);
+        assert_eq!(status.retries, 0);
+    }
+}
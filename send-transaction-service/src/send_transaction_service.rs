@@ -1,5 +1,9 @@
 use {
-    crate::tpu_info::TpuInfo,
+    crate::{
+        forwarding_policy::ForwardingPolicy,
+        send_status::{SendOutcome, SendStatusTracker, TransactionSendStatus},
+        tpu_info::TpuInfo,
+    },
     crossbeam_channel::{Receiver, RecvTimeoutError},
     log::*,
     trezoa_client::{
@@ -70,6 +74,8 @@ pub struct TransactionInfo {
     retries: usize,
     /// Last time the transaction was sent
     last_sent_time: Option<Instant>,
+    /// Addresses the transaction was last sent to
+    last_sent_to: Vec<SocketAddr>,
 }
 
 impl TransactionInfo {
@@ -89,6 +95,16 @@ impl TransactionInfo {
             max_retries,
             retries: 0,
             last_sent_time,
+            last_sent_to: vec![],
+        }
+    }
+
+    pub(crate) fn send_status(&self, outcome: Option<SendOutcome>) -> TransactionSendStatus {
+        TransactionSendStatus {
+            retries: self.retries,
+            last_sent_time: self.last_sent_time,
+            last_sent_to: self.last_sent_to.clone(),
+            outcome,
         }
     }
 }
@@ -115,9 +131,16 @@ pub struct Config {
     pub batch_send_rate_ms: u64,
     /// When the retry pool exceeds this max size, new transactions are dropped after their first broadcast attempt
     pub retry_pool_max_size: usize,
+    #[deprecated(
+        since = "2.0.0",
+        note = "Please use `forwarding_policy: ForwardingPolicy::LeadersAndPeers` instead"
+    )]
     pub tpu_peers: Option<Vec<SocketAddr>>,
+    /// Which leaders and peers transactions are forwarded to
+    pub forwarding_policy: ForwardingPolicy,
 }
 
+#[allow(deprecated)]
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -129,10 +152,37 @@ impl Default for Config {
             batch_send_rate_ms: DEFAULT_BATCH_SEND_RATE_MS,
             retry_pool_max_size: MAX_TRANSACTION_RETRY_POOL_SIZE,
             tpu_peers: None,
+            forwarding_policy: ForwardingPolicy::default(),
         }
     }
 }
 
+impl Config {
+    /// Move the deprecated `tpu_peers` into the static peers of the
+    /// forwarding policy
+    #[allow(deprecated)]
+    fn with_tpu_peers_in_forwarding_policy(mut self) -> Self {
+        let Some(tpu_peers) = self.tpu_peers.take() else {
+            return self;
+        };
+        self.forwarding_policy = match std::mem::take(&mut self.forwarding_policy) {
+            ForwardingPolicy::LeadersOnly => ForwardingPolicy::LeadersAndPeers { peers: tpu_peers },
+            ForwardingPolicy::LeadersAndPeers { mut peers } => {
+                peers.extend(tpu_peers);
+                ForwardingPolicy::LeadersAndPeers { peers }
+            }
+            policy @ ForwardingPolicy::StakeWeighted { .. } => {
+                warn!(
+                    "Ignoring tpu_peers, the {} forwarding policy has no static peers",
+                    policy.name()
+                );
+                policy
+            }
+        };
+        self
+    }
+}
+
 /// The maximum duration the retry thread may be configured to sleep before
 /// processing the transactions that need to be retried.
 pub const MAX_RETRY_SLEEP_MS: u64 = 1000;
@@ -231,12 +281,26 @@ struct SendTransactionServiceStats {
 
     /// Count of transactions failed
     failed_transactions: AtomicU64,
+
+    /// Count of sends to leaders chosen by the forwarding policy
+    leader_sends: AtomicU64,
+
+    /// Count of sends to the static peers of the forwarding policy
+    peer_sends: AtomicU64,
+
+    /// Count of times the forwarding policy chose the leaders
+    leader_selections: AtomicU64,
+
+    /// Sum of the number of leaders chosen by the forwarding policy
+    selected_leaders: AtomicU64,
 }
 
 #[derive(Default)]
 struct SendTransactionServiceStatsReport {
     stats: SendTransactionServiceStats,
     last_report: AtomicInterval,
+    /// Name of the forwarding policy, tagging its metrics
+    forwarding_policy: &'static str,
 }
 
 impl SendTransactionServiceStatsReport {
@@ -323,6 +387,30 @@ impl SendTransactionServiceStatsReport {
                     i64
                 )
             );
+            datapoint_info!(
+                "send_transaction_service-forwarding",
+                "policy" => self.forwarding_policy,
+                (
+                    "leader-sends",
+                    self.stats.leader_sends.swap(0, Ordering::Relaxed),
+                    i64
+                ),
+                (
+                    "peer-sends",
+                    self.stats.peer_sends.swap(0, Ordering::Relaxed),
+                    i64
+                ),
+                (
+                    "leader-selections",
+                    self.stats.leader_selections.swap(0, Ordering::Relaxed),
+                    i64
+                ),
+                (
+                    "selected-leaders",
+                    self.stats.selected_leaders.swap(0, Ordering::Relaxed),
+                    i64
+                ),
+            );
         }
     }
 }
@@ -353,10 +441,12 @@ impl SendTransactionService {
             receiver,
             connection_cache,
             config,
+            Arc::default(),
             exit,
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_with_config<T: TpuInfo + std::marker::Send + 'static>(
         tpu_address: SocketAddr,
         bank_forks: &Arc<RwLock<BankForks>>,
@@ -364,11 +454,14 @@ impl SendTransactionService {
         receiver: Receiver<TransactionInfo>,
         connection_cache: &Arc<ConnectionCache>,
         config: Config,
+        send_status_tracker: Arc<SendStatusTracker>,
         exit: Arc<AtomicBool>,
     ) -> Self {
-        let stats_report = Arc::new(SendTransactionServiceStatsReport::default());
-
-        let retry_transactions = Arc::new(Mutex::new(HashMap::new()));
+        let config = config.with_tpu_peers_in_forwarding_policy();
+        let stats_report = Arc::new(SendTransactionServiceStatsReport {
+            forwarding_policy: config.forwarding_policy.name(),
+            ..SendTransactionServiceStatsReport::default()
+        });
 
         let leader_info_provider = Arc::new(Mutex::new(CurrentLeaderInfo::new(leader_info)));
 
@@ -378,7 +471,7 @@ impl SendTransactionService {
             leader_info_provider.clone(),
             connection_cache.clone(),
             config.clone(),
-            retry_transactions.clone(),
+            send_status_tracker.clone(),
             stats_report.clone(),
             exit.clone(),
         );
@@ -389,7 +482,7 @@ impl SendTransactionService {
             leader_info_provider,
             connection_cache.clone(),
             config,
-            retry_transactions,
+            send_status_tracker,
             stats_report,
             exit.clone(),
         );
@@ -407,10 +500,11 @@ impl SendTransactionService {
         leader_info_provider: Arc<Mutex<CurrentLeaderInfo<T>>>,
         connection_cache: Arc<ConnectionCache>,
         config: Config,
-        retry_transactions: Arc<Mutex<HashMap<Signature, TransactionInfo>>>,
+        send_status_tracker: Arc<SendStatusTracker>,
         stats_report: Arc<SendTransactionServiceStatsReport>,
         exit: Arc<AtomicBool>,
     ) -> JoinHandle<()> {
+        let retry_transactions = send_status_tracker.retry_transactions().clone();
         let mut last_batch_sent = Instant::now();
         let mut transactions = HashMap::new();
 
@@ -465,7 +559,7 @@ impl SendTransactionService {
                         .fetch_add(transactions.len() as u64, Ordering::Relaxed);
                     Self::send_transactions_in_batch(
                         &tpu_address,
-                        &transactions,
+                        &mut transactions,
                         leader_info_provider.lock().unwrap().get_leader_info(),
                         &connection_cache,
                         &config,
@@ -481,10 +575,14 @@ impl SendTransactionService {
                             let retry_len = retry_transactions.len();
                             let entry = retry_transactions.entry(signature);
                             if let Entry::Vacant(_) = entry {
+                                transaction_info.last_sent_time = Some(last_sent_time);
                                 if retry_len >= config.retry_pool_max_size {
-                                    break;
+                                    send_status_tracker.finish(
+                                        signature,
+                                        &transaction_info,
+                                        SendOutcome::RetryPoolFull,
+                                    );
                                 } else {
-                                    transaction_info.last_sent_time = Some(last_sent_time);
                                     saturating_add_assign!(transactions_added_to_retry, 1);
                                     entry.or_insert(transaction_info);
                                 }
@@ -513,10 +611,11 @@ impl SendTransactionService {
         leader_info_provider: Arc<Mutex<CurrentLeaderInfo<T>>>,
         connection_cache: Arc<ConnectionCache>,
         config: Config,
-        retry_transactions: Arc<Mutex<HashMap<Signature, TransactionInfo>>>,
+        send_status_tracker: Arc<SendStatusTracker>,
         stats_report: Arc<SendTransactionServiceStatsReport>,
         exit: Arc<AtomicBool>,
     ) -> JoinHandle<()> {
+        let retry_transactions = send_status_tracker.retry_transactions().clone();
         info!(
             "Starting send-transaction-service::retry_thread with config {:?}",
             config
@@ -550,6 +649,7 @@ impl SendTransactionService {
                         &leader_info_provider,
                         &connection_cache,
                         &config,
+                        &send_status_tracker,
                         stats,
                     );
                     stats_report.report();
@@ -561,24 +661,22 @@ impl SendTransactionService {
     /// Process transactions in batch.
     fn send_transactions_in_batch<T: TpuInfo>(
         tpu_address: &SocketAddr,
-        transactions: &HashMap<Signature, TransactionInfo>,
+        transactions: &mut HashMap<Signature, TransactionInfo>,
         leader_info: Option<&T>,
         connection_cache: &Arc<ConnectionCache>,
         config: &Config,
         stats: &SendTransactionServiceStats,
     ) {
         // Processing the transactions in batch
-        let mut addresses = config
-            .tpu_peers
-            .as_ref()
-            .map(|addrs| addrs.iter().map(|a| (a, 0)).collect::<Vec<_>>())
-            .unwrap_or_default();
+        let peers = config.forwarding_policy.peers();
+        let leader_count = Self::select_leader_count(leader_info, config, stats);
         let leader_addresses = Self::get_tpu_addresses_with_slots(
             tpu_address,
             leader_info,
-            config,
+            leader_count,
             connection_cache.protocol(),
         );
+        let mut addresses = peers.iter().map(|a| (a, 0)).collect::<Vec<_>>();
         addresses.extend(leader_addresses);
 
         let wire_transactions = transactions
@@ -595,6 +693,12 @@ impl SendTransactionService {
         for (address, _) in &addresses {
             Self::send_transactions(address, &wire_transactions, connection_cache, stats);
         }
+        Self::count_forwarded_sends(peers.len(), addresses.len(), stats);
+
+        let sent_to: Vec<_> = addresses.iter().map(|(address, _)| **address).collect();
+        for transaction_info in transactions.values_mut() {
+            transaction_info.last_sent_to = sent_to.clone();
+        }
     }
 
     /// Retry transactions sent before.
@@ -606,6 +710,7 @@ impl SendTransactionService {
         leader_info_provider: &Arc<Mutex<CurrentLeaderInfo<T>>>,
         connection_cache: &Arc<ConnectionCache>,
         config: &Config,
+        send_status_tracker: &SendStatusTracker,
         stats: &SendTransactionServiceStats,
     ) -> ProcessTransactionsResult {
         let mut result = ProcessTransactionsResult::default();
@@ -621,6 +726,7 @@ impl SendTransactionService {
                 info!("Transaction is rooted: {}", signature);
                 result.rooted += 1;
                 stats.rooted_transactions.fetch_add(1, Ordering::Relaxed);
+                send_status_tracker.finish(*signature, transaction_info, SendOutcome::Rooted);
                 return false;
             }
             let signature_status = working_bank.get_signature_status_slot(signature);
@@ -637,6 +743,7 @@ impl SendTransactionService {
                     info!("Dropping expired durable-nonce transaction: {}", signature);
                     result.expired += 1;
                     stats.expired_transactions.fetch_add(1, Ordering::Relaxed);
+                    send_status_tracker.finish(*signature, transaction_info, SendOutcome::Expired);
                     return false;
                 }
             }
@@ -644,6 +751,7 @@ impl SendTransactionService {
                 info!("Dropping expired transaction: {}", signature);
                 result.expired += 1;
                 stats.expired_transactions.fetch_add(1, Ordering::Relaxed);
+                send_status_tracker.finish(*signature, transaction_info, SendOutcome::Expired);
                 return false;
             }
 
@@ -659,6 +767,11 @@ impl SendTransactionService {
                     stats
                         .transactions_exceeding_max_retries
                         .fetch_add(1, Ordering::Relaxed);
+                    send_status_tracker.finish(
+                        *signature,
+                        transaction_info,
+                        SendOutcome::MaxRetriesExceeded,
+                    );
                     return false;
                 }
             }
@@ -691,6 +804,11 @@ impl SendTransactionService {
                         info!("Dropping failed transaction: {}", signature);
                         result.failed += 1;
                         stats.failed_transactions.fetch_add(1, Ordering::Relaxed);
+                        send_status_tracker.finish(
+                            *signature,
+                            transaction_info,
+                            SendOutcome::Failed,
+                        );
                         false
                     } else {
                         result.retained += 1;
@@ -702,25 +820,28 @@ impl SendTransactionService {
 
         if !batched_transactions.is_empty() {
             // Processing the transactions in batch
-            let wire_transactions = transactions
+            let (signatures, wire_transactions): (Vec<_>, Vec<&[u8]>) = transactions
                 .iter()
                 .filter(|(signature, _)| batched_transactions.contains(signature))
-                .map(|(_, transaction_info)| transaction_info.wire_transaction.as_ref())
-                .collect::<Vec<&[u8]>>();
-
-            let iter = wire_transactions.chunks(config.batch_size);
-            for chunk in iter {
-                let mut addresses = config
-                    .tpu_peers
-                    .as_ref()
-                    .map(|addrs| addrs.iter().collect::<Vec<_>>())
-                    .unwrap_or_default();
+                .map(|(signature, transaction_info)| {
+                    (*signature, transaction_info.wire_transaction.as_ref())
+                })
+                .unzip();
+
+            let mut sent_to = Vec::with_capacity(signatures.len());
+            let iter = signatures
+                .chunks(config.batch_size)
+                .zip(wire_transactions.chunks(config.batch_size));
+            for (signatures, chunk) in iter {
+                let peers = config.forwarding_policy.peers();
+                let mut addresses = peers.iter().collect::<Vec<_>>();
                 let mut leader_info_provider = leader_info_provider.lock().unwrap();
                 let leader_info = leader_info_provider.get_leader_info();
+                let leader_count = Self::select_leader_count(leader_info, config, stats);
                 let leader_addresses = Self::get_tpu_addresses(
                     tpu_address,
                     leader_info,
-                    config,
+                    leader_count,
                     connection_cache.protocol(),
                 );
                 addresses.extend(leader_addresses);
@@ -728,6 +849,19 @@ impl SendTransactionService {
                 for address in &addresses {
                     Self::send_transactions(address, chunk, connection_cache, stats);
                 }
+                Self::count_forwarded_sends(peers.len(), addresses.len(), stats);
+
+                let addresses: Vec<_> = addresses.into_iter().copied().collect();
+                sent_to.extend(
+                    signatures
+                        .iter()
+                        .map(|signature| (*signature, addresses.clone())),
+                );
+            }
+            for (signature, addresses) in sent_to {
+                if let Some(transaction_info) = transactions.get_mut(&signature) {
+                    transaction_info.last_sent_to = addresses;
+                }
             }
         }
         result
@@ -778,15 +912,45 @@ impl SendTransactionService {
         stats.send_attempt_count.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Number of upcoming leaders the forwarding policy forwards to
+    fn select_leader_count<T: TpuInfo>(
+        leader_info: Option<&T>,
+        config: &Config,
+        stats: &SendTransactionServiceStats,
+    ) -> u64 {
+        let leader_count = config
+            .forwarding_policy
+            .leader_count(leader_info, config.leader_forward_count);
+        stats.leader_selections.fetch_add(1, Ordering::Relaxed);
+        stats
+            .selected_leaders
+            .fetch_add(leader_count, Ordering::Relaxed);
+        leader_count
+    }
+
+    fn count_forwarded_sends(
+        peer_count: usize,
+        address_count: usize,
+        stats: &SendTransactionServiceStats,
+    ) {
+        stats
+            .peer_sends
+            .fetch_add(peer_count as u64, Ordering::Relaxed);
+        stats.leader_sends.fetch_add(
+            address_count.saturating_sub(peer_count) as u64,
+            Ordering::Relaxed,
+        );
+    }
+
     fn get_tpu_addresses<'a, T: TpuInfo>(
         tpu_address: &'a SocketAddr,
         leader_info: Option<&'a T>,
-        config: &'a Config,
+        leader_count: u64,
         protocol: Protocol,
     ) -> Vec<&'a SocketAddr> {
         let addresses = leader_info
             .as_ref()
-            .map(|leader_info| leader_info.get_leader_tpus(config.leader_forward_count, protocol));
+            .map(|leader_info| leader_info.get_leader_tpus(leader_count, protocol));
         addresses
             .map(|address_list| {
                 if address_list.is_empty() {
@@ -801,14 +965,12 @@ impl SendTransactionService {
     fn get_tpu_addresses_with_slots<'a, T: TpuInfo>(
         tpu_address: &'a SocketAddr,
         leader_info: Option<&'a T>,
-        config: &'a Config,
+        leader_count: u64,
         protocol: Protocol,
     ) -> Vec<(&'a SocketAddr, Slot)> {
         leader_info
             .as_ref()
-            .map(|leader_info| {
-                leader_info.get_leader_tpus_with_slots(config.leader_forward_count, protocol)
-            })
+            .map(|leader_info| leader_info.get_leader_tpus_with_slots(leader_count, protocol))
             .filter(|addresses| !addresses.is_empty())
             .unwrap_or_else(|| vec![(tpu_address, 0)])
     }
@@ -837,6 +999,42 @@ mod test {
         std::ops::Sub,
     };
 
+    #[test]
+    #[allow(deprecated)]
+    fn test_tpu_peers_forwarding_policy() {
+        let peer: SocketAddr = "127.0.0.1:8001".parse().unwrap();
+        let other_peer: SocketAddr = "127.0.0.1:8002".parse().unwrap();
+
+        let config = Config::default().with_tpu_peers_in_forwarding_policy();
+        assert_eq!(config.forwarding_policy, ForwardingPolicy::LeadersOnly);
+
+        let config = Config {
+            tpu_peers: Some(vec![peer]),
+            ..Config::default()
+        }
+        .with_tpu_peers_in_forwarding_policy();
+        assert_eq!(config.tpu_peers, None);
+        assert_eq!(config.forwarding_policy.peers(), &[peer]);
+
+        let config = Config {
+            tpu_peers: Some(vec![peer]),
+            forwarding_policy: ForwardingPolicy::LeadersAndPeers {
+                peers: vec![other_peer],
+            },
+            ..Config::default()
+        }
+        .with_tpu_peers_in_forwarding_policy();
+        assert_eq!(config.forwarding_policy.peers(), &[other_peer, peer]);
+
+        let config = Config {
+            tpu_peers: Some(vec![peer]),
+            forwarding_policy: ForwardingPolicy::stake_weighted(),
+            ..Config::default()
+        }
+        .with_tpu_peers_in_forwarding_policy();
+        assert_eq!(config.forwarding_policy, ForwardingPolicy::stake_weighted());
+    }
+
     #[test]
     fn service_exit() {
         let tpu_address = "127.0.0.1:0".parse().unwrap();
@@ -875,6 +1073,7 @@ mod test {
             max_retries: None,
             retries: 0,
             last_sent_time: None,
+            last_sent_to: vec![],
         };
 
         let exit = Arc::new(AtomicBool::new(false));
@@ -958,6 +1157,7 @@ mod test {
         info!("Expired transactions are dropped...");
         let leader_info_provider = Arc::new(Mutex::new(CurrentLeaderInfo::new(None)));
         let stats = SendTransactionServiceStats::default();
+        let send_status_tracker = SendStatusTracker::default();
         transactions.insert(
             Signature::default(),
             TransactionInfo::new(
@@ -978,6 +1178,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert!(transactions.is_empty());
@@ -988,6 +1189,13 @@ mod test {
                 ..ProcessTransactionsResult::default()
             }
         );
+        assert_eq!(
+            send_status_tracker
+                .get_status(&Signature::default())
+                .unwrap()
+                .outcome,
+            Some(SendOutcome::Expired)
+        );
 
         info!("Rooted transactions are dropped...");
         transactions.insert(
@@ -1009,6 +1217,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert!(transactions.is_empty());
@@ -1019,6 +1228,13 @@ mod test {
                 ..ProcessTransactionsResult::default()
             }
         );
+        assert_eq!(
+            send_status_tracker
+                .get_status(&rooted_signature)
+                .unwrap()
+                .outcome,
+            Some(SendOutcome::Rooted)
+        );
 
         info!("Failed transactions are dropped...");
         transactions.insert(
@@ -1040,6 +1256,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert!(transactions.is_empty());
@@ -1071,6 +1288,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert_eq!(transactions.len(), 1);
@@ -1104,6 +1322,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert_eq!(transactions.len(), 1);
@@ -1147,6 +1366,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert_eq!(transactions.len(), 1);
@@ -1166,6 +1386,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert!(transactions.is_empty());
@@ -1255,6 +1476,7 @@ mod test {
         );
         let leader_info_provider = Arc::new(Mutex::new(CurrentLeaderInfo::new(None)));
         let stats = SendTransactionServiceStats::default();
+        let send_status_tracker = SendStatusTracker::default();
         let connection_cache = Arc::new(ConnectionCache::new("connection_cache_test"));
         let result = SendTransactionService::process_transactions::<NullTpuInfo>(
             &working_bank,
@@ -1264,6 +1486,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert!(transactions.is_empty());
@@ -1294,6 +1517,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert!(transactions.is_empty());
@@ -1326,6 +1550,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert!(transactions.is_empty());
@@ -1356,6 +1581,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert!(transactions.is_empty());
@@ -1387,6 +1613,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert!(transactions.is_empty());
@@ -1418,6 +1645,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert_eq!(transactions.len(), 1);
@@ -1451,6 +1679,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert_eq!(transactions.len(), 1);
@@ -1481,6 +1710,7 @@ mod test {
             &leader_info_provider,
             &connection_cache,
             &config,
+            &send_status_tracker,
             &stats,
         );
         assert_eq!(transactions.len(), 0);
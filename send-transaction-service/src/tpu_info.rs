@@ -1,4 +1,8 @@
-use {trezoa_client::connection_cache::Protocol, trezoa_sdk::clock::Slot, std::net::SocketAddr};
+use {
+    trezoa_client::connection_cache::Protocol,
+    trezoa_sdk::{clock::Slot, pubkey::Pubkey},
+    std::net::SocketAddr,
+};
 
 pub trait TpuInfo {
     fn refresh_recent_peers(&mut self);
@@ -9,6 +13,12 @@ pub trait TpuInfo {
         max_count: u64,
         protocol: Protocol,
     ) -> Vec<(&SocketAddr, Slot)>;
+    /// Leaders of the next `max_count` leader rotations, with the share of the
+    /// epoch's stake each holds. Without them, the stake weighted forwarding
+    /// policy forwards to its minimum number of leaders
+    fn get_leaders_with_stake(&self, _max_count: u64) -> Vec<(Pubkey, f64)> {
+        vec![]
+    }
 }
 
 #[derive(Clone)]
@@ -1,7 +1,10 @@
 #![allow(clippy::arithmetic_side_effects)]
+#[cfg(feature = "dev-context-only-utils")]
+pub mod net_emulator;
 pub mod nonblocking;
 pub mod packet;
 pub mod quic;
+pub mod quic_endpoint;
 pub mod recvmmsg;
 pub mod sendmmsg;
 pub mod socket;
@@ -0,0 +1,696 @@
+//! In-process network emulation for clusters running in a single process
+//!
+//! net-shaper emulates network conditions between hosts with `tc` rules, which
+//! needs root and a host per node. When every node of a cluster runs in the
+//! same process, as in local-cluster, the emulator instead drops and delays
+//! UDP packets and QUIC streams as the streamer receives them, according to
+//! which nodes sent and receive them.
+//!
+//! Nodes are assigned to partitions, and the links between partitions given a
+//! loss rate and latency, the same way net-shaper does it. The topology can
+//! change over time so that partitions form and heal while the cluster runs.
+//!
+//! Traffic is attributed to nodes by the addresses registered for them. QUIC
+//! traffic is attributed by the identity of the peer's certificate first, and
+//! UDP packets from unregistered addresses, e.g. a node's ephemeral client
+//! sockets, by the fee payer of the transaction they carry, which for votes
+//! is the voting node. QUIC endpoints outside of the streamer, e.g. turbine's
+//! and repair's, consult [`NetworkEmulator::stream_verdict`] themselves.
+//!
+//! Only built with the `dev-context-only-utils` feature.
+
+use {
+    crate::streamer::PacketBatchSender,
+    rand::{thread_rng, Rng},
+    trezoa_perf::packet::{Packet, PacketBatch},
+    trezoa_sdk::{pubkey::Pubkey, transaction::VersionedTransaction},
+    std::{
+        cmp::{Ordering as CmpOrdering, Reverse},
+        collections::{BTreeMap, BinaryHeap, HashMap},
+        net::SocketAddr,
+        str::FromStr,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc, Condvar, Mutex, RwLock,
+        },
+        thread::{Builder, JoinHandle},
+        time::{Duration, Instant},
+    },
+};
+
+// Emulator applied to the traffic received by every streamer in the process
+static NETWORK_EMULATOR: RwLock<Option<Arc<NetworkEmulator>>> = RwLock::new(None);
+// Spares the receive paths the lock while no emulator is installed
+static NETWORK_EMULATOR_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Apply the emulator to all the traffic received in this process
+pub fn install(emulator: Arc<NetworkEmulator>) {
+    *NETWORK_EMULATOR.write().unwrap() = Some(emulator);
+    NETWORK_EMULATOR_INSTALLED.store(true, Ordering::Release);
+}
+
+/// Stop emulating, returning the emulator which was installed
+pub fn uninstall() -> Option<Arc<NetworkEmulator>> {
+    NETWORK_EMULATOR_INSTALLED.store(false, Ordering::Release);
+    NETWORK_EMULATOR.write().unwrap().take()
+}
+
+pub fn get() -> Option<Arc<NetworkEmulator>> {
+    if !NETWORK_EMULATOR_INSTALLED.load(Ordering::Acquire) {
+        return None;
+    }
+    NETWORK_EMULATOR.read().unwrap().clone()
+}
+
+/// Holds back a message a QUIC server listening on `server_addr` received
+/// from a client for as long as the installed emulator delays it, returning
+/// false if the emulator drops it
+pub async fn delay_or_drop_stream(
+    client_identity: Option<&Pubkey>,
+    client_addr: &SocketAddr,
+    server_addr: Option<SocketAddr>,
+) -> bool {
+    let verdict = match get().zip(server_addr) {
+        Some((emulator, server_addr)) => {
+            emulator.stream_verdict(client_identity, client_addr, &server_addr)
+        }
+        None => Verdict::Deliver,
+    };
+    verdict.hold().await
+}
+
+/// Same as [`delay_or_drop_stream`], for the response the server sends back
+pub async fn delay_or_drop_response(
+    server_addr: Option<SocketAddr>,
+    client_identity: Option<&Pubkey>,
+    client_addr: &SocketAddr,
+) -> bool {
+    let verdict = match get().zip(server_addr) {
+        Some((emulator, server_addr)) => {
+            emulator.response_verdict(&server_addr, client_identity, client_addr)
+        }
+        None => Verdict::Deliver,
+    };
+    verdict.hold().await
+}
+
+/// Conditions of the traffic from one partition to another
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkConfig {
+    /// Share of the packets dropped, between 0 and 1
+    pub loss: f64,
+    pub delay: Duration,
+    /// Packets are delayed by `delay` plus or minus up to `jitter`
+    pub jitter: Duration,
+}
+
+impl LinkConfig {
+    /// A link dropping everything, separating two partitions
+    pub fn partitioned() -> Self {
+        Self {
+            loss: 1.0,
+            ..Self::default()
+        }
+    }
+}
+
+impl FromStr for LinkConfig {
+    type Err = String;
+
+    /// Parse the netem options net-shaper configures links with, e.g.
+    /// `loss 15% 25% delay 100ms 10ms`. Correlations are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut link = Self::default();
+        let mut tokens = s.split_whitespace().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                "loss" => {
+                    let loss = tokens.next().ok_or("loss requires a percentage")?;
+                    link.loss = parse_percentage(loss)?;
+                    // Correlation
+                    tokens.next_if(|token| token.ends_with('%'));
+                }
+                "delay" => {
+                    let delay = tokens.next().ok_or("delay requires a time")?;
+                    link.delay = parse_time(delay)?;
+                    if let Some(jitter) = tokens.next_if(|token| parse_time(token).is_ok()) {
+                        link.jitter = parse_time(jitter)?;
+                        // Correlation
+                        tokens.next_if(|token| token.ends_with('%'));
+                    }
+                }
+                _ => return Err(format!("unsupported link option {token:?}")),
+            }
+        }
+        Ok(link)
+    }
+}
+
+fn parse_percentage(s: &str) -> Result<f64, String> {
+    s.strip_suffix('%')
+        .and_then(|percentage| percentage.parse::<f64>().ok())
+        .filter(|percentage| (0.0..=100.0).contains(percentage))
+        .map(|percentage| percentage / 100.0)
+        .ok_or_else(|| format!("invalid percentage {s:?}"))
+}
+
+fn parse_time(s: &str) -> Result<Duration, String> {
+    let (value, unit) = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .map(|index| s.split_at(index))
+        .ok_or_else(|| format!("time {s:?} has no unit"))?;
+    let value: f64 = value.parse().map_err(|_| format!("invalid time {s:?}"))?;
+    let micros = match unit {
+        "us" => value,
+        "ms" => value * 1_000.0,
+        "s" => value * 1_000_000.0,
+        _ => return Err(format!("invalid time unit in {s:?}")),
+    };
+    Ok(Duration::from_micros(micros as u64))
+}
+
+/// Nodes split into partitions, and the links between them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Topology {
+    /// Share of the nodes, in percent, in each partition
+    pub partitions: Vec<u8>,
+    /// Links keyed by the sending and the receiving partition. Traffic between
+    /// partitions without a link, or within a partition, is unaffected
+    pub links: HashMap<(usize, usize), LinkConfig>,
+}
+
+impl Topology {
+    /// Partition of the node at `index` out of `num_nodes`, assigned as
+    /// net-shaper assigns hosts
+    pub fn partition_of(&self, index: usize, num_nodes: usize) -> usize {
+        let position = (index + 1) * 100 / num_nodes.max(1);
+        let mut watermark = 0;
+        for (partition, share) in self.partitions.iter().enumerate() {
+            watermark += usize::from(*share);
+            if watermark >= position {
+                return partition;
+            }
+        }
+        0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verdict {
+    Deliver,
+    Drop,
+    Delay(Duration),
+}
+
+impl Verdict {
+    // Waits out the delay, returning whether to deliver
+    async fn hold(self) -> bool {
+        match self {
+            Verdict::Deliver => true,
+            Verdict::Drop => false,
+            Verdict::Delay(delay) => {
+                tokio::time::sleep(delay).await;
+                true
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct NodeRegistry {
+    identities: HashMap<Pubkey, usize>,
+    addrs: HashMap<SocketAddr, usize>,
+}
+
+impl NodeRegistry {
+    fn node_of(&self, identity: Option<&Pubkey>, addr: &SocketAddr) -> Option<usize> {
+        identity
+            .and_then(|identity| self.identities.get(identity))
+            .or_else(|| self.addrs.get(addr))
+            .copied()
+    }
+
+    fn node_of_packet(&self, packet: &Packet) -> Option<usize> {
+        if let Some(node) = self.addrs.get(&packet.meta().socket_addr()) {
+            return Some(*node);
+        }
+        let transaction: VersionedTransaction = packet.deserialize_slice(..).ok()?;
+        let fee_payer = transaction.message.static_account_keys().first()?;
+        self.identities.get(fee_payer).copied()
+    }
+}
+
+pub struct NetworkEmulator {
+    num_nodes: usize,
+    // Topologies and the time after start they apply from, sorted
+    schedule: Vec<(Duration, Topology)>,
+    start: Instant,
+    nodes: RwLock<NodeRegistry>,
+    delay_line: Arc<DelayLine>,
+    delay_thread: Option<JoinHandle<()>>,
+    num_dropped: AtomicU64,
+    num_delayed: AtomicU64,
+}
+
+impl NetworkEmulator {
+    /// Emulate a network of `num_nodes` nodes, following the schedule of
+    /// topologies from now on
+    pub fn new(num_nodes: usize, mut schedule: Vec<(Duration, Topology)>) -> Self {
+        schedule.sort_by_key(|(start, _)| *start);
+        let delay_line = Arc::<DelayLine>::default();
+        let delay_thread = {
+            let delay_line = delay_line.clone();
+            Builder::new()
+                .name("trzNetEmulator".to_string())
+                .spawn(move || delay_line.run())
+                .unwrap()
+        };
+        Self {
+            num_nodes,
+            schedule,
+            start: Instant::now(),
+            nodes: RwLock::default(),
+            delay_line,
+            delay_thread: Some(delay_thread),
+            num_dropped: AtomicU64::default(),
+            num_delayed: AtomicU64::default(),
+        }
+    }
+
+    /// Attribute traffic from and to the addresses, and from QUIC clients
+    /// presenting the identity, to a node. Nodes are numbered in the order
+    /// they are first registered; registering an identity again, e.g. for a
+    /// restarted node, adds addresses to the same node.
+    pub fn register_node<I>(&self, identity: Pubkey, addrs: I) -> usize
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        let mut nodes = self.nodes.write().unwrap();
+        let next_index = nodes.identities.len();
+        let index = *nodes.identities.entry(identity).or_insert(next_index);
+        nodes
+            .addrs
+            .extend(addrs.into_iter().map(|addr| (addr, index)));
+        index
+    }
+
+    /// Topology currently in effect
+    pub fn topology(&self) -> Option<&Topology> {
+        let elapsed = self.start.elapsed();
+        self.schedule
+            .iter()
+            .take_while(|(start, _)| *start <= elapsed)
+            .last()
+            .map(|(_, topology)| topology)
+    }
+
+    /// What happens to traffic from one node to another
+    pub fn verdict(&self, from: usize, to: usize) -> Verdict {
+        let Some(topology) = self.topology() else {
+            return Verdict::Deliver;
+        };
+        let from = topology.partition_of(from, self.num_nodes);
+        let to = topology.partition_of(to, self.num_nodes);
+        let Some(link) = topology.links.get(&(from, to)).filter(|_| from != to) else {
+            return Verdict::Deliver;
+        };
+        let mut rng = thread_rng();
+        if link.loss > 0.0 && rng.gen_bool(link.loss.min(1.0)) {
+            self.num_dropped.fetch_add(1, Ordering::Relaxed);
+            return Verdict::Drop;
+        }
+        let delay = if link.jitter.is_zero() {
+            link.delay
+        } else {
+            let jitter = rng.gen_range(0..=2 * link.jitter.as_micros() as u64);
+            (link.delay + Duration::from_micros(jitter)).saturating_sub(link.jitter)
+        };
+        if delay.is_zero() {
+            Verdict::Deliver
+        } else {
+            self.num_delayed.fetch_add(1, Ordering::Relaxed);
+            Verdict::Delay(delay)
+        }
+    }
+
+    /// What happens to a QUIC stream from a client, identified by its
+    /// certificate or else its address, to a server
+    pub fn stream_verdict(
+        &self,
+        client_identity: Option<&Pubkey>,
+        client_addr: &SocketAddr,
+        server_addr: &SocketAddr,
+    ) -> Verdict {
+        let (from, to) = {
+            let nodes = self.nodes.read().unwrap();
+            (
+                nodes.node_of(client_identity, client_addr),
+                nodes.node_of(None, server_addr),
+            )
+        };
+        match (from, to) {
+            (Some(from), Some(to)) => self.verdict(from, to),
+            _ => Verdict::Deliver,
+        }
+    }
+
+    /// What happens to the response a server sends back on a client's QUIC
+    /// stream
+    pub fn response_verdict(
+        &self,
+        server_addr: &SocketAddr,
+        client_identity: Option<&Pubkey>,
+        client_addr: &SocketAddr,
+    ) -> Verdict {
+        let (from, to) = {
+            let nodes = self.nodes.read().unwrap();
+            (
+                nodes.node_of(None, server_addr),
+                nodes.node_of(client_identity, client_addr),
+            )
+        };
+        match (from, to) {
+            (Some(from), Some(to)) => self.verdict(from, to),
+            _ => Verdict::Deliver,
+        }
+    }
+
+    /// Drop and delay the packets received on `receiver_addr`. Delayed
+    /// packets are sent to `sender` once due, and those to deliver right away
+    /// are returned.
+    pub fn shape_packet_batch(
+        &self,
+        receiver_addr: &SocketAddr,
+        packet_batch: PacketBatch,
+        sender: &PacketBatchSender,
+    ) -> Option<PacketBatch> {
+        let nodes = self.nodes.read().unwrap();
+        let Some(to) = nodes.node_of(None, receiver_addr) else {
+            return Some(packet_batch);
+        };
+        let mut deliver = Vec::with_capacity(packet_batch.len());
+        // Delayed packets grouped by the millisecond they are due at
+        let mut delayed = BTreeMap::<u128, Vec<Packet>>::new();
+        for packet in packet_batch.iter() {
+            let verdict = nodes
+                .node_of_packet(packet)
+                .map(|from| self.verdict(from, to))
+                .unwrap_or(Verdict::Deliver);
+            match verdict {
+                Verdict::Deliver => deliver.push(packet.clone()),
+                Verdict::Drop => (),
+                Verdict::Delay(delay) => delayed
+                    .entry(delay.as_millis())
+                    .or_default()
+                    .push(packet.clone()),
+            }
+        }
+        drop(nodes);
+        let now = Instant::now();
+        for (delay, packets) in delayed {
+            let due = now + Duration::from_millis(delay as u64);
+            self.delay_line
+                .push(due, PacketBatch::new(packets), sender.clone());
+        }
+        (!deliver.is_empty()).then(|| PacketBatch::new(deliver))
+    }
+
+    /// Number of packets and streams dropped
+    pub fn num_dropped(&self) -> u64 {
+        self.num_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Number of packets and streams delayed
+    pub fn num_delayed(&self) -> u64 {
+        self.num_delayed.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for NetworkEmulator {
+    fn drop(&mut self) {
+        self.delay_line.exit.store(true, Ordering::Relaxed);
+        self.delay_line.condvar.notify_all();
+        if let Some(delay_thread) = self.delay_thread.take() {
+            let _ = delay_thread.join();
+        }
+    }
+}
+
+struct DelayedBatch {
+    due: Instant,
+    // Keeps batches due at the same time in order
+    seq: u64,
+    packet_batch: PacketBatch,
+    sender: PacketBatchSender,
+}
+
+impl PartialEq for DelayedBatch {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for DelayedBatch {}
+
+impl PartialOrd for DelayedBatch {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DelayedBatch {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (self.due, self.seq).cmp(&(other.due, other.seq))
+    }
+}
+
+#[derive(Default)]
+struct DelayLine {
+    queue: Mutex<(BinaryHeap<Reverse<DelayedBatch>>, /*next seq:*/ u64)>,
+    condvar: Condvar,
+    exit: AtomicBool,
+}
+
+impl DelayLine {
+    fn push(&self, due: Instant, packet_batch: PacketBatch, sender: PacketBatchSender) {
+        let mut queue = self.queue.lock().unwrap();
+        let (batches, seq) = &mut *queue;
+        batches.push(Reverse(DelayedBatch {
+            due,
+            seq: *seq,
+            packet_batch,
+            sender,
+        }));
+        *seq += 1;
+        self.condvar.notify_one();
+    }
+
+    fn run(&self) {
+        const MAX_WAIT: Duration = Duration::from_millis(100);
+        let mut queue = self.queue.lock().unwrap();
+        while !self.exit.load(Ordering::Relaxed) {
+            let now = Instant::now();
+            let wait = match queue.0.peek() {
+                Some(Reverse(batch)) if batch.due <= now => {
+                    let Reverse(batch) = queue.0.pop().unwrap();
+                    drop(queue);
+                    // The receiving end may have exited
+                    let _ = batch.sender.send(batch.packet_batch);
+                    queue = self.queue.lock().unwrap();
+                    continue;
+                }
+                Some(Reverse(batch)) => (batch.due - now).min(MAX_WAIT),
+                None => MAX_WAIT,
+            };
+            queue = self.condvar.wait_timeout(queue, wait).unwrap().0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crossbeam_channel::unbounded,
+        trezoa_sdk::transaction::Transaction,
+        std::{iter::repeat_with, net::Ipv4Addr},
+    };
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, port))
+    }
+
+    fn two_partitions(link: LinkConfig) -> Topology {
+        Topology {
+            partitions: vec![50, 50],
+            links: HashMap::from([((0, 1), link.clone()), ((1, 0), link)]),
+        }
+    }
+
+    #[test]
+    fn test_parse_link_config() {
+        assert_eq!(
+            "loss 15% 25% delay 100ms 10ms".parse(),
+            Ok(LinkConfig {
+                loss: 0.15,
+                delay: Duration::from_millis(100),
+                jitter: Duration::from_millis(10),
+            })
+        );
+        assert_eq!(
+            "delay 1.5s".parse(),
+            Ok(LinkConfig {
+                delay: Duration::from_millis(1500),
+                ..LinkConfig::default()
+            })
+        );
+        assert_eq!("loss 100%".parse(), Ok(LinkConfig::partitioned()));
+        assert_eq!("".parse(), Ok(LinkConfig::default()));
+        assert!("loss 150%".parse::<LinkConfig>().is_err());
+        assert!("delay 100".parse::<LinkConfig>().is_err());
+        assert!("rate 1mbit".parse::<LinkConfig>().is_err());
+    }
+
+    #[test]
+    fn test_partition_of() {
+        let topology = Topology {
+            partitions: vec![50, 25, 25],
+            ..Topology::default()
+        };
+        let partitions: Vec<_> = (0..4).map(|node| topology.partition_of(node, 4)).collect();
+        assert_eq!(partitions, vec![0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_verdict() {
+        let emulator = NetworkEmulator::new(
+            2,
+            vec![
+                (Duration::ZERO, two_partitions(LinkConfig::partitioned())),
+                // Heals in the far future
+                (Duration::from_secs(3600), Topology::default()),
+            ],
+        );
+        assert_eq!(emulator.verdict(0, 1), Verdict::Drop);
+        assert_eq!(emulator.verdict(1, 0), Verdict::Drop);
+        assert_eq!(emulator.verdict(0, 0), Verdict::Deliver);
+        assert_eq!(emulator.num_dropped(), 2);
+
+        let delay = Duration::from_millis(20);
+        let emulator = NetworkEmulator::new(
+            2,
+            vec![(
+                Duration::ZERO,
+                two_partitions(LinkConfig {
+                    delay,
+                    ..LinkConfig::default()
+                }),
+            )],
+        );
+        assert_eq!(emulator.verdict(0, 1), Verdict::Delay(delay));
+
+        // Nothing scheduled yet
+        let emulator = NetworkEmulator::new(
+            2,
+            vec![(
+                Duration::from_secs(3600),
+                two_partitions(LinkConfig::partitioned()),
+            )],
+        );
+        assert_eq!(emulator.verdict(0, 1), Verdict::Deliver);
+    }
+
+    #[test]
+    fn test_stream_verdict() {
+        let emulator = NetworkEmulator::new(
+            2,
+            vec![(
+                Duration::ZERO,
+                Topology {
+                    partitions: vec![50, 50],
+                    links: HashMap::from([((1, 0), LinkConfig::partitioned())]),
+                },
+            )],
+        );
+        let client = Pubkey::new_unique();
+        emulator.register_node(Pubkey::new_unique(), [addr(8000)]);
+        emulator.register_node(client, [addr(8001)]);
+        // The client's certificate attributes streams from unknown addresses
+        assert_eq!(
+            emulator.stream_verdict(Some(&client), &addr(9000), &addr(8000)),
+            Verdict::Drop
+        );
+        assert_eq!(
+            emulator.stream_verdict(None, &addr(9000), &addr(8000)),
+            Verdict::Deliver
+        );
+        assert_eq!(
+            emulator.response_verdict(&addr(8000), Some(&client), &addr(9000)),
+            Verdict::Deliver
+        );
+        assert_eq!(
+            emulator.response_verdict(&addr(8001), None, &addr(8000)),
+            Verdict::Drop
+        );
+    }
+
+    #[test]
+    fn test_shape_packet_batch() {
+        let delay = Duration::from_millis(20);
+        let emulator = NetworkEmulator::new(
+            3,
+            vec![(
+                Duration::ZERO,
+                Topology {
+                    partitions: vec![34, 33, 33],
+                    links: HashMap::from([
+                        ((1, 0), LinkConfig::partitioned()),
+                        (
+                            (2, 0),
+                            LinkConfig {
+                                delay,
+                                ..LinkConfig::default()
+                            },
+                        ),
+                    ]),
+                },
+            )],
+        );
+        let identities: Vec<_> = repeat_with(Pubkey::new_unique).take(3).collect();
+        for (node, (identity, port)) in identities.iter().zip([8000, 8001, 8002]).enumerate() {
+            let index = emulator.register_node(*identity, [addr(port)]);
+            assert_eq!(index, node);
+        }
+        let mut packets: Vec<_> = [8000, 8001, 8002, 9000]
+            .into_iter()
+            .map(|port| {
+                let mut packet = Packet::default();
+                packet.meta_mut().set_socket_addr(&addr(port));
+                packet
+            })
+            .collect();
+        // A vote from an unregistered socket of the partitioned node
+        let vote =
+            VersionedTransaction::from(Transaction::new_with_payer(&[], Some(&identities[1])));
+        packets.push(Packet::from_data(Some(&addr(9001)), vote).unwrap());
+        let (sender, receiver) = unbounded();
+        let delivered = emulator
+            .shape_packet_batch(&addr(8000), PacketBatch::new(packets.clone()), &sender)
+            .unwrap();
+        // Packets from the same partition and from unknown senders pass
+        let ports: Vec<_> = delivered
+            .iter()
+            .map(|packet| packet.meta().socket_addr().port())
+            .collect();
+        assert_eq!(ports, vec![8000, 9000]);
+        let delayed = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(delayed.len(), 1);
+        assert_eq!(delayed[0].meta().socket_addr(), addr(8002));
+
+        // Traffic to unknown addresses is not shaped
+        let delivered = emulator
+            .shape_packet_batch(&addr(9000), PacketBatch::new(packets), &sender)
+            .unwrap();
+        assert_eq!(delivered.len(), 5);
+    }
+}
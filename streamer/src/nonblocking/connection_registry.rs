@@ -0,0 +1,284 @@
+//! Live connections of a QUIC server, and the IP addresses and client
+//! identities blocked from connecting to it
+//!
+//! The connection tables are behind async locks held by the server tasks, so
+//! connections also register here, where they can be listed and closed from
+//! outside the server runtime, such as by the validator admin RPC.
+
+use {
+    crate::nonblocking::quic::{
+        ConnectionPeerType, CONNECTION_CLOSE_CODE_BLOCKED, CONNECTION_CLOSE_CODE_EVICTED,
+        CONNECTION_CLOSE_REASON_BLOCKED, CONNECTION_CLOSE_REASON_EVICTED,
+    },
+    quinn::Connection,
+    trezoa_sdk::pubkey::Pubkey,
+    std::{
+        collections::HashMap,
+        fmt,
+        net::{IpAddr, SocketAddr},
+        str::FromStr,
+        sync::{
+            atomic::{AtomicU64, AtomicUsize, Ordering},
+            Arc, RwLock,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+/// Longest a peer may be blocked for, blocks are lifted on restart anyway
+pub const MAX_BLOCK_DURATION: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Selects the connections of a peer, by IP address or by client identity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PeerFilter {
+    Ip(IpAddr),
+    Identity(Pubkey),
+}
+
+impl PeerFilter {
+    fn matches(&self, ip: IpAddr, client_pubkey: Option<&Pubkey>) -> bool {
+        match self {
+            PeerFilter::Ip(filter_ip) => *filter_ip == ip,
+            PeerFilter::Identity(pubkey) => Some(pubkey) == client_pubkey,
+        }
+    }
+}
+
+impl FromStr for PeerFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(ip) = s.parse::<IpAddr>() {
+            return Ok(PeerFilter::Ip(ip));
+        }
+        s.parse::<Pubkey>()
+            .map(PeerFilter::Identity)
+            .map_err(|_| format!("{s} is neither an IP address nor a pubkey"))
+    }
+}
+
+impl fmt::Display for PeerFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PeerFilter::Ip(ip) => write!(f, "{ip}"),
+            PeerFilter::Identity(pubkey) => write!(f, "{pubkey}"),
+        }
+    }
+}
+
+/// Snapshot of a live connection
+#[derive(Clone, Debug)]
+pub struct ConnectionInfo {
+    pub remote_address: SocketAddr,
+    /// Identity in the client certificate, whether or not it is staked
+    pub client_pubkey: Option<Pubkey>,
+    pub peer_type: ConnectionPeerType,
+    pub open_streams: usize,
+    pub bytes_received: u64,
+    pub packets_received: u64,
+    pub age: Duration,
+}
+
+pub(crate) struct RegisteredConnection {
+    connection: Connection,
+    remote_address: SocketAddr,
+    client_pubkey: Option<Pubkey>,
+    peer_type: ConnectionPeerType,
+    established: Instant,
+    pub(crate) open_streams: AtomicUsize,
+    pub(crate) bytes_received: AtomicU64,
+    pub(crate) packets_received: AtomicU64,
+}
+
+impl RegisteredConnection {
+    fn info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            remote_address: self.remote_address,
+            client_pubkey: self.client_pubkey,
+            peer_type: self.peer_type,
+            open_streams: self.open_streams.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            packets_received: self.packets_received.load(Ordering::Relaxed),
+            age: self.established.elapsed(),
+        }
+    }
+
+    fn matches(&self, filter: &PeerFilter) -> bool {
+        filter.matches(self.remote_address.ip(), self.client_pubkey.as_ref())
+    }
+}
+
+#[derive(Default)]
+pub struct ConnectionRegistry {
+    // Keyed by the connection's stable id, unique within an endpoint
+    connections: RwLock<HashMap<usize, Arc<RegisteredConnection>>>,
+    // Blocked peers and when their block expires
+    blocked: RwLock<HashMap<PeerFilter, Instant>>,
+}
+
+impl ConnectionRegistry {
+    pub(crate) fn register(
+        &self,
+        connection: &Connection,
+        client_pubkey: Option<Pubkey>,
+        peer_type: ConnectionPeerType,
+    ) -> Arc<RegisteredConnection> {
+        let registered = Arc::new(RegisteredConnection {
+            connection: connection.clone(),
+            remote_address: connection.remote_address(),
+            client_pubkey,
+            peer_type,
+            established: Instant::now(),
+            open_streams: AtomicUsize::default(),
+            bytes_received: AtomicU64::default(),
+            packets_received: AtomicU64::default(),
+        });
+        self.connections
+            .write()
+            .unwrap()
+            .insert(connection.stable_id(), registered.clone());
+        registered
+    }
+
+    pub(crate) fn unregister(&self, stable_id: usize) {
+        self.connections.write().unwrap().remove(&stable_id);
+    }
+
+    /// Live connections, oldest first
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        let mut connections: Vec<_> = self
+            .connections
+            .read()
+            .unwrap()
+            .values()
+            .map(|connection| connection.info())
+            .collect();
+        connections.sort_by(|a, b| b.age.cmp(&a.age));
+        connections
+    }
+
+    /// Close the connections matching `filter`, returning how many were closed
+    pub fn evict(&self, filter: &PeerFilter) -> usize {
+        self.close_matching(
+            filter,
+            CONNECTION_CLOSE_CODE_EVICTED,
+            CONNECTION_CLOSE_REASON_EVICTED,
+        )
+    }
+
+    /// Refuse new connections matching `filter` for `duration`, at most
+    /// `MAX_BLOCK_DURATION`, and close the existing ones, returning how many
+    /// were closed
+    pub fn block(&self, filter: PeerFilter, duration: Duration) -> usize {
+        let now = Instant::now();
+        let expiry = now + duration.min(MAX_BLOCK_DURATION);
+        {
+            let mut blocked = self.blocked.write().unwrap();
+            blocked.retain(|_, expiry| *expiry > now);
+            blocked.insert(filter, expiry);
+        }
+        self.close_matching(
+            &filter,
+            CONNECTION_CLOSE_CODE_BLOCKED,
+            CONNECTION_CLOSE_REASON_BLOCKED,
+        )
+    }
+
+    /// Lift the block on `filter`, returning false if it wasn't blocked
+    pub fn unblock(&self, filter: &PeerFilter) -> bool {
+        let now = Instant::now();
+        self.blocked
+            .write()
+            .unwrap()
+            .remove(filter)
+            .map_or(false, |expiry| expiry > now)
+    }
+
+    /// Blocked peers with the time left until they are unblocked
+    pub fn blocked(&self) -> Vec<(PeerFilter, Duration)> {
+        let now = Instant::now();
+        self.blocked
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, expiry)| **expiry > now)
+            .map(|(filter, expiry)| (*filter, expiry.duration_since(now)))
+            .collect()
+    }
+
+    pub(crate) fn is_blocked(&self, filter: &PeerFilter) -> bool {
+        self.blocked
+            .read()
+            .unwrap()
+            .get(filter)
+            .map_or(false, |expiry| *expiry > Instant::now())
+    }
+
+    fn close_matching(&self, filter: &PeerFilter, code: u32, reason: &[u8]) -> usize {
+        let connections: Vec<_> = self
+            .connections
+            .read()
+            .unwrap()
+            .values()
+            .filter(|connection| connection.matches(filter))
+            .cloned()
+            .collect();
+        for registered in &connections {
+            // The connection's handler unregisters it once it notices the close
+            registered.connection.close(code.into(), reason);
+        }
+        connections.len()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_peer_filter() {
+        let pubkey = Pubkey::new_unique();
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        assert_eq!("10.0.0.1".parse::<PeerFilter>(), Ok(PeerFilter::Ip(ip)));
+        assert_eq!(
+            "::1".parse::<PeerFilter>(),
+            Ok(PeerFilter::Ip("::1".parse().unwrap()))
+        );
+        assert_eq!(
+            pubkey.to_string().parse::<PeerFilter>(),
+            Ok(PeerFilter::Identity(pubkey))
+        );
+        assert!("10.0.0".parse::<PeerFilter>().is_err());
+        assert_eq!(PeerFilter::Ip(ip).to_string(), "10.0.0.1");
+
+        assert!(PeerFilter::Ip(ip).matches(ip, None));
+        assert!(!PeerFilter::Ip(ip).matches(IpAddr::from([10, 0, 0, 2]), Some(&pubkey)));
+        assert!(PeerFilter::Identity(pubkey).matches(IpAddr::from([10, 0, 0, 2]), Some(&pubkey)));
+        assert!(!PeerFilter::Identity(pubkey).matches(ip, None));
+    }
+
+    #[test]
+    fn test_block_expiry() {
+        let registry = ConnectionRegistry::default();
+        let ip = PeerFilter::Ip(IpAddr::from([10, 0, 0, 1]));
+        let identity = PeerFilter::Identity(Pubkey::new_unique());
+
+        assert_eq!(registry.block(ip, Duration::from_secs(60)), 0);
+        assert_eq!(registry.block(identity, Duration::ZERO), 0);
+        assert!(registry.is_blocked(&ip));
+        assert!(!registry.is_blocked(&identity));
+        let blocked = registry.blocked();
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].0, ip);
+
+        assert_eq!(registry.block(identity, Duration::MAX), 0);
+        assert!(registry.is_blocked(&identity));
+        assert!(registry.blocked()[0].1 <= MAX_BLOCK_DURATION);
+        assert!(registry.unblock(&identity));
+
+        assert!(!registry.unblock(&identity));
+        assert!(registry.unblock(&ip));
+        assert!(!registry.is_blocked(&ip));
+        assert!(registry.blocked().is_empty());
+    }
+}
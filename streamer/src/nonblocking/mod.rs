@@ -1,4 +1,6 @@
+pub mod connection_registry;
 pub mod quic;
+pub mod rate_limiter;
 pub mod recvmmsg;
 pub mod sendmmsg;
 mod stream_throttle;
@@ -1,7 +1,11 @@
 use {
     crate::{
-        nonblocking::stream_throttle::{
-            ConnectionStreamCounter, StakedStreamLoadEMA, STREAM_STOP_CODE_THROTTLING,
+        nonblocking::{
+            connection_registry::{ConnectionRegistry, PeerFilter},
+            rate_limiter::{QuicRateLimitConfig, QuicRateLimiter},
+            stream_throttle::{
+                ConnectionStreamCounter, StakedStreamLoadEMA, STREAM_STOP_CODE_THROTTLING,
+            },
         },
         quic::{configure_server, QuicServerError, StreamStats},
         streamer::StakedNodes,
@@ -60,6 +64,10 @@ pub const DEFAULT_WAIT_FOR_CHUNK_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub const ALPN_TPU_PROTOCOL_ID: &[u8] = b"trezoa-tpu";
 
+// Stops the streams the network emulator drops
+#[cfg(feature = "dev-context-only-utils")]
+const STREAM_STOP_CODE_EMULATED_LOSS: u32 = 16;
+
 const CONNECTION_CLOSE_CODE_DROPPED_ENTRY: u32 = 1;
 const CONNECTION_CLOSE_REASON_DROPPED_ENTRY: &[u8] = b"dropped";
 
@@ -72,6 +80,15 @@ const CONNECTION_CLOSE_REASON_EXCEED_MAX_STREAM_COUNT: &[u8] = b"exceed_max_stre
 const CONNECTION_CLOSE_CODE_TOO_MANY: u32 = 4;
 const CONNECTION_CLOSE_REASON_TOO_MANY: &[u8] = b"too_many";
 
+const CONNECTION_CLOSE_CODE_RATE_LIMITED: u32 = 5;
+const CONNECTION_CLOSE_REASON_RATE_LIMITED: &[u8] = b"rate_limited";
+
+pub(crate) const CONNECTION_CLOSE_CODE_BLOCKED: u32 = 6;
+pub(crate) const CONNECTION_CLOSE_REASON_BLOCKED: &[u8] = b"blocked";
+
+pub(crate) const CONNECTION_CLOSE_CODE_EVICTED: u32 = 7;
+pub(crate) const CONNECTION_CLOSE_REASON_EVICTED: &[u8] = b"evicted";
+
 // A sequence of bytes that is part of a packet
 // along with where in the packet it is
 struct PacketChunk {
@@ -121,6 +138,8 @@ pub fn spawn_server(
     max_unstaked_connections: usize,
     wait_for_chunk_timeout: Duration,
     coalesce: Duration,
+    rate_limits: QuicRateLimitConfig,
+    connection_registry: Arc<ConnectionRegistry>,
 ) -> Result<(Endpoint, Arc<StreamStats>, JoinHandle<()>), QuicServerError> {
     info!("Start {name} quic server on {sock:?}");
     let (config, _cert) = configure_server(keypair)?;
@@ -145,6 +164,8 @@ pub fn spawn_server(
         stats.clone(),
         wait_for_chunk_timeout,
         coalesce,
+        rate_limits,
+        connection_registry,
     ));
     Ok((endpoint, stats, handle))
 }
@@ -162,6 +183,8 @@ async fn run_server(
     stats: Arc<StreamStats>,
     wait_for_chunk_timeout: Duration,
     coalesce: Duration,
+    rate_limits: QuicRateLimitConfig,
+    connection_registry: Arc<ConnectionRegistry>,
 ) {
     const WAIT_FOR_CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
     debug!("spawn quic server");
@@ -174,6 +197,8 @@ async fn run_server(
     ));
     let staked_connection_table: Arc<Mutex<ConnectionTable>> =
         Arc::new(Mutex::new(ConnectionTable::new()));
+    let rate_limiter = Arc::new(QuicRateLimiter::new(rate_limits, stats.clone()));
+    let server_addr = incoming.local_addr().ok();
     let (sender, receiver) = async_unbounded();
     tokio::spawn(packet_batch_sender(
         packet_sender,
@@ -204,6 +229,9 @@ async fn run_server(
                 stats.clone(),
                 wait_for_chunk_timeout,
                 stream_load_ema.clone(),
+                rate_limiter.clone(),
+                connection_registry.clone(),
+                server_addr,
             ));
         } else {
             debug!("accept(): Timed out waiting for connection");
@@ -300,6 +328,14 @@ struct NewConnectionHandlerParams {
     stats: Arc<StreamStats>,
     max_stake: u64,
     min_stake: u64,
+    // Identity in the client certificate, set for unstaked peers too unlike
+    // remote_pubkey, so streams can be rate limited per identity
+    client_pubkey: Option<Pubkey>,
+    rate_limiter: Arc<QuicRateLimiter>,
+    connection_registry: Arc<ConnectionRegistry>,
+    // Address the server listens on, identifying it to the network emulator
+    #[cfg_attr(not(feature = "dev-context-only-utils"), allow(dead_code))]
+    server_addr: Option<SocketAddr>,
 }
 
 impl NewConnectionHandlerParams {
@@ -307,6 +343,10 @@ impl NewConnectionHandlerParams {
         packet_sender: AsyncSender<PacketAccumulator>,
         max_connections_per_peer: usize,
         stats: Arc<StreamStats>,
+        client_pubkey: Option<Pubkey>,
+        rate_limiter: Arc<QuicRateLimiter>,
+        connection_registry: Arc<ConnectionRegistry>,
+        server_addr: Option<SocketAddr>,
     ) -> NewConnectionHandlerParams {
         NewConnectionHandlerParams {
             packet_sender,
@@ -317,6 +357,10 @@ impl NewConnectionHandlerParams {
             stats,
             max_stake: 0,
             min_stake: 0,
+            client_pubkey,
+            rate_limiter,
+            connection_registry,
+            server_addr,
         }
     }
 }
@@ -482,19 +526,57 @@ async fn setup_connection(
     stats: Arc<StreamStats>,
     wait_for_chunk_timeout: Duration,
     stream_load_ema: Arc<StakedStreamLoadEMA>,
+    rate_limiter: Arc<QuicRateLimiter>,
+    connection_registry: Arc<ConnectionRegistry>,
+    server_addr: Option<SocketAddr>,
 ) {
     const PRUNE_RANDOM_SAMPLE_SIZE: usize = 2;
     let from = connecting.remote_address();
+    // Dropping the connection before the handshake is cheaper than completing it
+    if connection_registry.is_blocked(&PeerFilter::Ip(from.ip())) {
+        debug!("Connection from blocked {from} refused");
+        stats.connections_blocked.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    if !rate_limiter.allow_connection_from_ip(from.ip()) {
+        debug!("Connection from {from} rate limited");
+        return;
+    }
     if let Ok(connecting_result) = timeout(QUIC_CONNECTION_HANDSHAKE_TIMEOUT, connecting).await {
         match connecting_result {
             Ok(new_connection) => {
                 stats.total_new_connections.fetch_add(1, Ordering::Relaxed);
 
+                let client_pubkey = get_remote_pubkey(&new_connection);
+                if let Some(pubkey) = client_pubkey {
+                    if connection_registry.is_blocked(&PeerFilter::Identity(pubkey)) {
+                        debug!("Connection from blocked {pubkey} at {from} refused");
+                        stats.connections_blocked.fetch_add(1, Ordering::Relaxed);
+                        new_connection.close(
+                            CONNECTION_CLOSE_CODE_BLOCKED.into(),
+                            CONNECTION_CLOSE_REASON_BLOCKED,
+                        );
+                        return;
+                    }
+                    if !rate_limiter.allow_connection_from_identity(&pubkey) {
+                        debug!("Connection from {pubkey} at {from} rate limited");
+                        new_connection.close(
+                            CONNECTION_CLOSE_CODE_RATE_LIMITED.into(),
+                            CONNECTION_CLOSE_REASON_RATE_LIMITED,
+                        );
+                        return;
+                    }
+                }
+
                 let params = get_connection_stake(&new_connection, &staked_nodes).map_or(
                     NewConnectionHandlerParams::new_unstaked(
                         packet_sender.clone(),
                         max_connections_per_peer,
                         stats.clone(),
+                        client_pubkey,
+                        rate_limiter.clone(),
+                        connection_registry.clone(),
+                        server_addr,
                     ),
                     |(pubkey, stake, total_stake, max_stake, min_stake)| {
                         let peer_type = if stake > 0 {
@@ -511,6 +593,10 @@ async fn setup_connection(
                             stats: stats.clone(),
                             max_stake,
                             min_stake,
+                            client_pubkey,
+                            rate_limiter: rate_limiter.clone(),
+                            connection_registry: connection_registry.clone(),
+                            server_addr,
                         }
                     },
                 );
@@ -737,6 +823,10 @@ async fn handle_connection(
         stats.total_connections.load(Ordering::Relaxed),
     );
     let stable_id = connection.stable_id();
+    let registered_connection =
+        params
+            .connection_registry
+            .register(&connection, params.client_pubkey, params.peer_type);
     stats.total_connections.fetch_add(1, Ordering::Relaxed);
     while !stream_exit.load(Ordering::Relaxed) {
         if let Ok(stream) =
@@ -744,6 +834,34 @@ async fn handle_connection(
         {
             match stream {
                 Ok(mut stream) => {
+                    #[cfg(feature = "dev-context-only-utils")]
+                    let emulated_delay = {
+                        use crate::net_emulator::{self, Verdict};
+                        let verdict = match net_emulator::get().zip(params.server_addr) {
+                            Some((emulator, server_addr)) => emulator.stream_verdict(
+                                params.client_pubkey.as_ref(),
+                                &remote_addr,
+                                &server_addr,
+                            ),
+                            None => Verdict::Deliver,
+                        };
+                        match verdict {
+                            Verdict::Deliver => None,
+                            Verdict::Drop => {
+                                let _ =
+                                    stream.stop(VarInt::from_u32(STREAM_STOP_CODE_EMULATED_LOSS));
+                                continue;
+                            }
+                            Verdict::Delay(delay) => Some(delay),
+                        }
+                    };
+                    if !params
+                        .rate_limiter
+                        .allow_stream(remote_addr.ip(), params.client_pubkey.as_ref())
+                    {
+                        let _ = stream.stop(VarInt::from_u32(STREAM_STOP_CODE_THROTTLING));
+                        continue;
+                    }
                     let max_streams_per_throttling_interval = stream_load_ema
                         .available_load_capacity_in_throttling_duration(
                             params.peer_type,
@@ -767,7 +885,15 @@ async fn handle_connection(
                     let packet_sender = params.packet_sender.clone();
                     let last_update = last_update.clone();
                     let stream_load_ema = stream_load_ema.clone();
+                    let registered_connection = registered_connection.clone();
+                    registered_connection
+                        .open_streams
+                        .fetch_add(1, Ordering::Relaxed);
                     tokio::spawn(async move {
+                        #[cfg(feature = "dev-context-only-utils")]
+                        if let Some(delay) = emulated_delay {
+                            tokio::time::sleep(delay).await;
+                        }
                         let mut maybe_batch = None;
                         // The min is to guard against a value too small which can wake up unnecessarily
                         // frequently and wasting CPU cycles. The max guard against waiting for too long
@@ -784,6 +910,19 @@ async fn handle_connection(
                             )
                             .await
                             {
+                                match &chunk {
+                                    Ok(Some(chunk)) => {
+                                        registered_connection
+                                            .bytes_received
+                                            .fetch_add(chunk.bytes.len() as u64, Ordering::Relaxed);
+                                    }
+                                    Ok(None) if maybe_batch.is_some() => {
+                                        registered_connection
+                                            .packets_received
+                                            .fetch_add(1, Ordering::Relaxed);
+                                    }
+                                    _ => {}
+                                }
                                 if handle_chunk(
                                     chunk,
                                     &mut maybe_batch,
@@ -807,6 +946,9 @@ async fn handle_connection(
                             }
                         }
                         stats.total_streams.fetch_sub(1, Ordering::Relaxed);
+                        registered_connection
+                            .open_streams
+                            .fetch_sub(1, Ordering::Relaxed);
                         stream_load_ema.update_ema_if_needed();
                     });
                 }
@@ -832,6 +974,7 @@ async fn handle_connection(
             .connection_remove_failed
             .fetch_add(1, Ordering::Relaxed);
     }
+    params.connection_registry.unregister(stable_id);
     stats.total_connections.fetch_sub(1, Ordering::Relaxed);
 }
 
@@ -1160,7 +1303,7 @@ pub mod test {
     use {
         super::*,
         crate::{
-            nonblocking::quic::compute_max_allowed_uni_streams,
+            nonblocking::{quic::compute_max_allowed_uni_streams, rate_limiter::RateLimit},
             quic::{MAX_STAKED_CONNECTIONS, MAX_UNSTAKED_CONNECTIONS},
             tls_certificates::new_dummy_x509_certificate,
         },
@@ -1251,6 +1394,8 @@ pub mod test {
             MAX_UNSTAKED_CONNECTIONS,
             Duration::from_secs(2),
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
         (t, exit, receiver, server_address, stats)
@@ -1685,6 +1830,8 @@ pub mod test {
             0, // Do not allow any connection from unstaked clients/nodes
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
 
@@ -1693,6 +1840,112 @@ pub mod test {
         t.await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_quic_server_rate_limit_connections_per_identity() {
+        trezoa_logger::setup();
+        let s = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let exit = Arc::new(AtomicBool::new(false));
+        let (sender, _) = unbounded();
+        let keypair = Keypair::new();
+        let server_address = s.local_addr().unwrap();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let (_, stats, t) = spawn_server(
+            "quic_streamer_test",
+            s,
+            &keypair,
+            sender,
+            exit.clone(),
+            2,
+            staked_nodes,
+            MAX_STAKED_CONNECTIONS,
+            MAX_UNSTAKED_CONNECTIONS,
+            DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
+            DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig {
+                connections_per_identity: Some(RateLimit::new(1, 1)),
+                ..QuicRateLimitConfig::default()
+            },
+            Arc::default(),
+        )
+        .unwrap();
+
+        let client_keypair = Keypair::new();
+        let _conn1 = make_client_endpoint(&server_address, Some(&client_keypair)).await;
+        let conn2 = make_client_endpoint(&server_address, Some(&client_keypair)).await;
+        assert_matches!(
+            conn2.closed().await,
+            quinn::ConnectionError::ApplicationClosed(close)
+                if close.error_code == CONNECTION_CLOSE_CODE_RATE_LIMITED.into()
+        );
+        assert_eq!(
+            stats
+                .connections_rate_limited_by_identity
+                .load(Ordering::Relaxed),
+            1
+        );
+
+        exit.store(true, Ordering::Relaxed);
+        t.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quic_server_evict_and_block() {
+        trezoa_logger::setup();
+        let s = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let exit = Arc::new(AtomicBool::new(false));
+        let (sender, _) = unbounded();
+        let keypair = Keypair::new();
+        let server_address = s.local_addr().unwrap();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let connection_registry = Arc::<ConnectionRegistry>::default();
+        let (_, stats, t) = spawn_server(
+            "quic_streamer_test",
+            s,
+            &keypair,
+            sender,
+            exit.clone(),
+            2,
+            staked_nodes,
+            MAX_STAKED_CONNECTIONS,
+            MAX_UNSTAKED_CONNECTIONS,
+            DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
+            DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            connection_registry.clone(),
+        )
+        .unwrap();
+
+        let client_keypair = Keypair::new();
+        let peer = PeerFilter::Identity(client_keypair.pubkey());
+        let conn1 = make_client_endpoint(&server_address, Some(&client_keypair)).await;
+        let mut connections = connection_registry.connections();
+        while connections.is_empty() {
+            sleep(Duration::from_millis(10)).await;
+            connections = connection_registry.connections();
+        }
+        assert_eq!(connections[0].client_pubkey, Some(client_keypair.pubkey()));
+        assert_eq!(connections[0].remote_address.ip(), server_address.ip());
+
+        assert_eq!(connection_registry.evict(&peer), 1);
+        assert_matches!(
+            conn1.closed().await,
+            quinn::ConnectionError::ApplicationClosed(close)
+                if close.error_code == CONNECTION_CLOSE_CODE_EVICTED.into()
+        );
+
+        connection_registry.block(peer, Duration::from_secs(60));
+        let conn2 = make_client_endpoint(&server_address, Some(&client_keypair)).await;
+        assert_matches!(
+            conn2.closed().await,
+            quinn::ConnectionError::ApplicationClosed(close)
+                if close.error_code == CONNECTION_CLOSE_CODE_BLOCKED.into()
+        );
+        assert_eq!(stats.connections_blocked.load(Ordering::Relaxed), 1);
+
+        exit.store(true, Ordering::Relaxed);
+        t.await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_quic_server_multiple_streams() {
         trezoa_logger::setup();
@@ -1714,6 +1967,8 @@ pub mod test {
             MAX_UNSTAKED_CONNECTIONS,
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
 
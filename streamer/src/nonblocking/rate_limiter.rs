@@ -0,0 +1,374 @@
+//! Token bucket rate limits on new connections and streams per source IP
+//! address and per client identity
+//!
+//! The connection tables bound how many connections a peer holds at once and
+//! stream throttling bounds how many streams a connection opens per interval,
+//! but neither stops a peer from churning connections. These limits bound the
+//! rate over time, shared by every connection from the same IP or identity.
+//! IPv6 addresses are limited by their /64 prefix, since a single host is
+//! usually handed a whole prefix.
+
+use {
+    crate::quic::StreamStats,
+    lru::LruCache,
+    trezoa_sdk::pubkey::Pubkey,
+    std::{
+        collections::hash_map::RandomState,
+        fmt,
+        hash::{BuildHasher, Hash},
+        net::{IpAddr, Ipv6Addr},
+        str::FromStr,
+        sync::{atomic::Ordering, Arc, Mutex},
+        time::Instant,
+    },
+};
+
+/// Most buckets a limiter tracks, the least recently used ones are evicted
+/// beyond this
+const MAX_TRACKED_KEYS: usize = 65_536;
+/// Buckets are spread over this many independently locked shards
+const NUM_SHARDS: usize = 32;
+const SHARD_CAPACITY: usize = MAX_TRACKED_KEYS / NUM_SHARDS;
+
+/// Sustained rate and burst of a token bucket
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Tokens added to the bucket per second
+    pub per_second: u64,
+    /// Capacity of the bucket, the most events allowed back to back
+    pub burst: u64,
+}
+
+impl RateLimit {
+    pub fn new(per_second: u64, burst: u64) -> Self {
+        Self { per_second, burst }
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    /// Parse `PER_SECOND` or `PER_SECOND:BURST`, the burst defaulting to the rate
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (per_second, burst) = match s.split_once(':') {
+            Some((per_second, burst)) => (per_second, Some(burst)),
+            None => (s, None),
+        };
+        let per_second = per_second
+            .parse::<u64>()
+            .map_err(|err| format!("invalid rate {per_second:?}: {err}"))?;
+        let burst = match burst {
+            Some(burst) => burst
+                .parse::<u64>()
+                .map_err(|err| format!("invalid burst {burst:?}: {err}"))?,
+            None => per_second,
+        };
+        if per_second == 0 || burst == 0 {
+            return Err("rate and burst must be greater than zero".to_string());
+        }
+        Ok(Self::new(per_second, burst))
+    }
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.per_second, self.burst)
+    }
+}
+
+/// Rate limits applied by a QUIC server, `None` leaves a dimension unlimited
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QuicRateLimitConfig {
+    /// New connections from one IP address, checked before the handshake
+    pub connections_per_ip: Option<RateLimit>,
+    /// New connections from one client identity, checked after the handshake
+    pub connections_per_identity: Option<RateLimit>,
+    /// Streams opened from one IP address, across all its connections
+    pub streams_per_ip: Option<RateLimit>,
+    /// Streams opened by one client identity, across all its connections
+    pub streams_per_identity: Option<RateLimit>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second as f64).min(limit.burst as f64);
+        self.last_refill = now;
+    }
+
+    /// A full bucket behaves the same as one that was never created
+    fn is_full(&self, limit: &RateLimit, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens + elapsed * limit.per_second as f64 >= limit.burst as f64
+    }
+}
+
+pub(crate) struct KeyedRateLimiter<K> {
+    limit: RateLimit,
+    shards: Vec<Mutex<LruCache<K, TokenBucket>>>,
+    hasher: RandomState,
+}
+
+impl<K: Copy + Eq + Hash> KeyedRateLimiter<K> {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            shards: (0..NUM_SHARDS)
+                .map(|_| Mutex::new(LruCache::new(SHARD_CAPACITY)))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    /// Take a token from the bucket of `key`, returning false if it is empty
+    pub(crate) fn try_acquire(&self, key: K) -> bool {
+        self.try_acquire_at(key, Instant::now())
+    }
+
+    fn try_acquire_at(&self, key: K, now: Instant) -> bool {
+        let mut buckets = self.shard(&key).lock().unwrap();
+        if buckets.peek(&key).is_none() {
+            if buckets.len() >= SHARD_CAPACITY {
+                if let Some((_, evicted)) = buckets.pop_lru() {
+                    if !evicted.is_full(&self.limit, now) {
+                        debug!("Rate limiter evicted a key with a pending limit");
+                    }
+                }
+            }
+            buckets.put(
+                key,
+                TokenBucket {
+                    tokens: self.limit.burst as f64,
+                    last_refill: now,
+                },
+            );
+        }
+        let bucket = buckets.get_mut(&key).unwrap();
+        bucket.refill(&self.limit, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn shard(&self, key: &K) -> &Mutex<LruCache<K, TokenBucket>> {
+        let index = self.hasher.hash_one(key) as usize % NUM_SHARDS;
+        &self.shards[index]
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+}
+
+/// Addresses within the same IPv6 /64 share a bucket
+fn ip_rate_limit_key(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => ip,
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => IpAddr::V4(ip),
+            None => IpAddr::V6(Ipv6Addr::from(u128::from(ip) & !((1u128 << 64) - 1))),
+        },
+    }
+}
+
+pub(crate) struct QuicRateLimiter {
+    connections_per_ip: Option<KeyedRateLimiter<IpAddr>>,
+    connections_per_identity: Option<KeyedRateLimiter<Pubkey>>,
+    streams_per_ip: Option<KeyedRateLimiter<IpAddr>>,
+    streams_per_identity: Option<KeyedRateLimiter<Pubkey>>,
+    stats: Arc<StreamStats>,
+}
+
+impl QuicRateLimiter {
+    pub(crate) fn new(config: QuicRateLimitConfig, stats: Arc<StreamStats>) -> Self {
+        Self {
+            connections_per_ip: config.connections_per_ip.map(KeyedRateLimiter::new),
+            connections_per_identity: config.connections_per_identity.map(KeyedRateLimiter::new),
+            streams_per_ip: config.streams_per_ip.map(KeyedRateLimiter::new),
+            streams_per_identity: config.streams_per_identity.map(KeyedRateLimiter::new),
+            stats,
+        }
+    }
+
+    pub(crate) fn allow_connection_from_ip(&self, ip: IpAddr) -> bool {
+        let allowed = self
+            .connections_per_ip
+            .as_ref()
+            .map_or(true, |limiter| limiter.try_acquire(ip_rate_limit_key(ip)));
+        if !allowed {
+            self.stats
+                .connections_rate_limited_by_ip
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        allowed
+    }
+
+    pub(crate) fn allow_connection_from_identity(&self, pubkey: &Pubkey) -> bool {
+        let allowed = self
+            .connections_per_identity
+            .as_ref()
+            .map_or(true, |limiter| limiter.try_acquire(*pubkey));
+        if !allowed {
+            self.stats
+                .connections_rate_limited_by_identity
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        allowed
+    }
+
+    pub(crate) fn allow_stream(&self, ip: IpAddr, pubkey: Option<&Pubkey>) -> bool {
+        if let Some(limiter) = &self.streams_per_ip {
+            if !limiter.try_acquire(ip_rate_limit_key(ip)) {
+                self.stats
+                    .streams_rate_limited_by_ip
+                    .fetch_add(1, Ordering::Relaxed);
+                return false;
+            }
+        }
+        if let (Some(limiter), Some(pubkey)) = (&self.streams_per_identity, pubkey) {
+            if !limiter.try_acquire(*pubkey) {
+                self.stats
+                    .streams_rate_limited_by_identity
+                    .fetch_add(1, Ordering::Relaxed);
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use {
+        super::*,
+        std::{net::Ipv4Addr, time::Duration},
+    };
+
+    #[test]
+    fn test_rate_limit_from_str() {
+        assert_eq!("10".parse::<RateLimit>(), Ok(RateLimit::new(10, 10)));
+        assert_eq!("10:50".parse::<RateLimit>(), Ok(RateLimit::new(10, 50)));
+        assert_eq!(RateLimit::new(10, 50).to_string(), "10:50");
+        assert!("0".parse::<RateLimit>().is_err());
+        assert!("10:0".parse::<RateLimit>().is_err());
+        assert!("ten".parse::<RateLimit>().is_err());
+        assert!("10:".parse::<RateLimit>().is_err());
+    }
+
+    #[test]
+    fn test_token_bucket_refill() {
+        let limiter = KeyedRateLimiter::new(RateLimit::new(10, 3));
+        let start = Instant::now();
+        let ip = IpAddr::from([10, 0, 0, 1]);
+
+        // The burst is available at once, then tokens come back at the rate
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at(ip, start));
+        }
+        assert!(!limiter.try_acquire_at(ip, start));
+        assert!(!limiter.try_acquire_at(ip, start + Duration::from_millis(50)));
+        assert!(limiter.try_acquire_at(ip, start + Duration::from_millis(100)));
+        assert!(!limiter.try_acquire_at(ip, start + Duration::from_millis(100)));
+
+        // Other keys have their own bucket
+        assert!(limiter.try_acquire_at(IpAddr::from([10, 0, 0, 2]), start));
+
+        // Refill is capped at the burst
+        let later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at(ip, later));
+        }
+        assert!(!limiter.try_acquire_at(ip, later));
+    }
+
+    #[test]
+    fn test_evict_least_recently_used_buckets() {
+        let limiter = KeyedRateLimiter::new(RateLimit::new(1, 2));
+        let start = Instant::now();
+        assert!(limiter.try_acquire_at(0, start));
+        assert!(limiter.try_acquire_at(0, start));
+        // A bucket in use keeps its pending limit while others are evicted
+        for key in 1..=2 * MAX_TRACKED_KEYS {
+            assert!(limiter.try_acquire_at(key, start));
+            assert!(!limiter.try_acquire_at(0, start));
+        }
+        assert!(limiter.len() <= MAX_TRACKED_KEYS);
+        // Evicted buckets start over with a full burst
+        assert!(limiter.try_acquire_at(1, start));
+        assert!(limiter.try_acquire_at(1, start));
+        assert!(!limiter.try_acquire_at(1, start));
+    }
+
+    #[test]
+    fn test_ip_rate_limit_key() {
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        assert_eq!(ip_rate_limit_key(ip), ip);
+        let mapped = IpAddr::from(Ipv4Addr::new(10, 0, 0, 1).to_ipv6_mapped());
+        assert_eq!(ip_rate_limit_key(mapped), ip);
+        let ip: IpAddr = "2001:db8:1:2:3:4:5:6".parse().unwrap();
+        let prefix: IpAddr = "2001:db8:1:2::".parse().unwrap();
+        assert_eq!(ip_rate_limit_key(ip), prefix);
+        let other: IpAddr = "2001:db8:1:3::1".parse().unwrap();
+        assert_ne!(ip_rate_limit_key(other), prefix);
+    }
+
+    #[test]
+    fn test_quic_rate_limiter() {
+        let stats = Arc::<StreamStats>::default();
+        let limiter = QuicRateLimiter::new(
+            QuicRateLimitConfig {
+                connections_per_ip: Some(RateLimit::new(1, 1)),
+                streams_per_identity: Some(RateLimit::new(1, 2)),
+                ..QuicRateLimitConfig::default()
+            },
+            stats.clone(),
+        );
+        let ip = IpAddr::from([10, 0, 0, 1]);
+        let pubkey = Pubkey::new_unique();
+
+        assert!(limiter.allow_connection_from_ip(ip));
+        assert!(!limiter.allow_connection_from_ip(ip));
+        assert_eq!(
+            stats.connections_rate_limited_by_ip.load(Ordering::Relaxed),
+            1
+        );
+        for _ in 0..10 {
+            assert!(limiter.allow_connection_from_identity(&pubkey));
+        }
+
+        assert!(limiter.allow_stream(ip, Some(&pubkey)));
+        assert!(limiter.allow_stream(ip, Some(&pubkey)));
+        assert!(!limiter.allow_stream(ip, Some(&pubkey)));
+        assert_eq!(
+            stats
+                .streams_rate_limited_by_identity
+                .load(Ordering::Relaxed),
+            1
+        );
+        // Streams without a client identity are only limited by IP
+        assert!(limiter.allow_stream(ip, None));
+        assert_eq!(stats.streams_rate_limited_by_ip.load(Ordering::Relaxed), 0);
+
+        // Addresses within an IPv6 /64 share a bucket
+        let ip: IpAddr = "2001:db8::1".parse().unwrap();
+        assert!(limiter.allow_connection_from_ip(ip));
+        assert!(!limiter.allow_connection_from_ip("2001:db8::2".parse().unwrap()));
+        assert!(limiter.allow_connection_from_ip("2001:db8:0:1::1".parse().unwrap()));
+    }
+}
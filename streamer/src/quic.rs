@@ -1,6 +1,10 @@
 use {
     crate::{
-        nonblocking::quic::ALPN_TPU_PROTOCOL_ID, streamer::StakedNodes,
+        nonblocking::{
+            connection_registry::ConnectionRegistry, quic::ALPN_TPU_PROTOCOL_ID,
+            rate_limiter::QuicRateLimitConfig,
+        },
+        streamer::StakedNodes,
         tls_certificates::new_dummy_x509_certificate,
     },
     crossbeam_channel::Sender,
@@ -175,6 +179,11 @@ pub struct StreamStats {
     pub(crate) stream_load_ema: AtomicUsize,
     pub(crate) stream_load_ema_overflow: AtomicUsize,
     pub(crate) stream_load_capacity_overflow: AtomicUsize,
+    pub(crate) connections_rate_limited_by_ip: AtomicUsize,
+    pub(crate) connections_rate_limited_by_identity: AtomicUsize,
+    pub(crate) streams_rate_limited_by_ip: AtomicUsize,
+    pub(crate) streams_rate_limited_by_identity: AtomicUsize,
+    pub(crate) connections_blocked: AtomicUsize,
 }
 
 impl StreamStats {
@@ -425,6 +434,34 @@ impl StreamStats {
                 self.stream_load_capacity_overflow.load(Ordering::Relaxed),
                 i64
             ),
+            (
+                "connections_rate_limited_by_ip",
+                self.connections_rate_limited_by_ip
+                    .swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "connections_rate_limited_by_identity",
+                self.connections_rate_limited_by_identity
+                    .swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "streams_rate_limited_by_ip",
+                self.streams_rate_limited_by_ip.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "streams_rate_limited_by_identity",
+                self.streams_rate_limited_by_identity
+                    .swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "connections_blocked",
+                self.connections_blocked.swap(0, Ordering::Relaxed),
+                i64
+            ),
         );
     }
 }
@@ -443,6 +480,8 @@ pub fn spawn_server(
     max_unstaked_connections: usize,
     wait_for_chunk_timeout: Duration,
     coalesce: Duration,
+    rate_limits: QuicRateLimitConfig,
+    connection_registry: Arc<ConnectionRegistry>,
 ) -> Result<SpawnServerResult, QuicServerError> {
     let runtime = rt(format!("{thread_name}Rt"));
     let (endpoint, _stats, task) = {
@@ -459,6 +498,8 @@ pub fn spawn_server(
             max_unstaked_connections,
             wait_for_chunk_timeout,
             coalesce,
+            rate_limits,
+            connection_registry,
         )
     }?;
     let handle = thread::Builder::new()
@@ -518,6 +559,8 @@ mod test {
             MAX_UNSTAKED_CONNECTIONS,
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
         (t, exit, receiver, server_address)
@@ -577,6 +620,8 @@ mod test {
             MAX_UNSTAKED_CONNECTIONS,
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
 
@@ -623,6 +668,8 @@ mod test {
             0, // Do not allow any connection from unstaked clients/nodes
             DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
             DEFAULT_TPU_COALESCE,
+            QuicRateLimitConfig::default(),
+            Arc::default(),
         )
         .unwrap();
 
@@ -0,0 +1,1211 @@
+//! QUIC endpoint shared by the gossip and turbine transports.
+//!
+//! Each endpoint both accepts connections and makes them, and keeps at most
+//! one connection per remote node. Messages are carried either as unreliable
+//! datagrams or each on its own unidirectional stream, and the remote node's
+//! identity is taken from its TLS certificate.
+
+use {
+    crate::{quic::SkipClientVerification, tls_certificates::new_dummy_x509_certificate},
+    bytes::Bytes,
+    crossbeam_channel::Sender,
+    futures_util::future::TryJoin,
+    quinn::{
+        ClientConfig, ConnectError, Connecting, Connection, ConnectionError, Endpoint,
+        EndpointConfig, IdleTimeout, ReadError, ReadToEndError, RecvStream, SendDatagramError,
+        ServerConfig, TokioRuntime, TransportConfig, VarInt, WriteError,
+    },
+    rustls::{Certificate, PrivateKey},
+    trezoa_sdk::{pubkey::Pubkey, signature::Keypair},
+    std::{
+        collections::{hash_map::Entry, HashMap},
+        io::Error as IoError,
+        net::{SocketAddr, UdpSocket},
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
+    thiserror::Error,
+    tokio::{
+        sync::{
+            mpsc::{error::TrySendError, Receiver as AsyncReceiver, Sender as AsyncSender},
+            Mutex, RwLock as AsyncRwLock,
+        },
+        task::JoinHandle,
+    },
+};
+
+const CLIENT_CHANNEL_BUFFER: usize = 1 << 14;
+const CONNECTION_CACHE_CAPACITY: usize = 3072;
+
+// Transport config.
+const DATAGRAM_RECEIVE_BUFFER_SIZE: usize = 256 * 1024 * 1024;
+const DATAGRAM_SEND_BUFFER_SIZE: usize = 128 * 1024 * 1024;
+const INITIAL_MAXIMUM_TRANSMISSION_UNIT: u16 = MINIMUM_MAXIMUM_TRANSMISSION_UNIT;
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(4);
+const MAX_CONCURRENT_UNI_STREAMS: VarInt = VarInt::from_u32(512);
+const MAX_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+const MINIMUM_MAXIMUM_TRANSMISSION_UNIT: u16 = 1280;
+const READ_TIMEOUT_DURATION: Duration = Duration::from_secs(2);
+
+const CONNECTION_CLOSE_ERROR_CODE_SHUTDOWN: VarInt = VarInt::from_u32(1);
+const CONNECTION_CLOSE_ERROR_CODE_DROPPED: VarInt = VarInt::from_u32(2);
+const CONNECTION_CLOSE_ERROR_CODE_INVALID_IDENTITY: VarInt = VarInt::from_u32(3);
+const CONNECTION_CLOSE_ERROR_CODE_REPLACED: VarInt = VarInt::from_u32(4);
+const CONNECTION_CLOSE_ERROR_CODE_PRUNED: VarInt = VarInt::from_u32(5);
+
+const CONNECTION_CLOSE_REASON_SHUTDOWN: &[u8] = b"SHUTDOWN";
+const CONNECTION_CLOSE_REASON_DROPPED: &[u8] = b"DROPPED";
+const CONNECTION_CLOSE_REASON_INVALID_IDENTITY: &[u8] = b"INVALID_IDENTITY";
+const CONNECTION_CLOSE_REASON_REPLACED: &[u8] = b"REPLACED";
+const CONNECTION_CLOSE_REASON_PRUNED: &[u8] = b"PRUNED";
+
+pub type AsyncTryJoinHandle = TryJoin<JoinHandle<()>, JoinHandle<()>>;
+
+/// Orders the cached connections when there are too many of them; the first
+/// ones are kept and the rest are closed.
+pub type SortConnections = Arc<dyn Fn(&mut [(Pubkey, Connection)]) + Send + Sync>;
+
+// Identity of the remote node, if known, and the channel to its connection.
+type Router = AsyncRwLock<HashMap<SocketAddr, (Option<Pubkey>, AsyncSender<Bytes>)>>;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Channel Send Error")]
+    ChannelSendError,
+    #[error(transparent)]
+    ConnectError(#[from] ConnectError),
+    #[error(transparent)]
+    ConnectionError(#[from] ConnectionError),
+    #[error("Invalid Identity: {0:?}")]
+    InvalidIdentity(SocketAddr),
+    #[error(transparent)]
+    IoError(#[from] IoError),
+    #[error(transparent)]
+    ReadToEndError(#[from] ReadToEndError),
+    #[error("read_to_end Timeout")]
+    ReadToEndTimeout,
+    #[error(transparent)]
+    SendDatagramError(#[from] SendDatagramError),
+    #[error(transparent)]
+    TlsError(#[from] rustls::Error),
+    #[error(transparent)]
+    WriteError(#[from] WriteError),
+}
+
+macro_rules! add_metric {
+    ($metric: expr) => {{
+        $metric.fetch_add(1, Ordering::Relaxed);
+    }};
+}
+
+/// How messages are carried over a connection.
+#[derive(Clone, Copy, Debug)]
+pub enum QuicTransport {
+    /// Each message is an unreliable datagram, and so has to fit in a packet.
+    Datagrams,
+    /// Each message is sent on its own unidirectional stream.
+    UniStreams { max_message_size: usize },
+}
+
+#[derive(Clone)]
+pub struct QuicEndpointConfig {
+    /// Also used as the server name connections are made to.
+    pub alpn_protocol_id: &'static str,
+    pub transport: QuicTransport,
+    /// Messages buffered for each connection before they are dropped.
+    pub router_channel_buffer: usize,
+    pub sort_connections: SortConnections,
+    pub server_metrics_name: &'static str,
+    pub client_metrics_name: &'static str,
+}
+
+/// Message sent through the endpoint to the remote node at an address.
+pub trait OutgoingMessage: Send + 'static {
+    fn remote_address(&self) -> SocketAddr;
+    /// If given, the message is only sent if the remote node at the address
+    /// has this identity.
+    fn remote_pubkey(&self) -> Option<Pubkey>;
+    fn into_bytes(self) -> Bytes;
+}
+
+impl OutgoingMessage for (SocketAddr, Bytes) {
+    fn remote_address(&self) -> SocketAddr {
+        self.0
+    }
+
+    fn remote_pubkey(&self) -> Option<Pubkey> {
+        None
+    }
+
+    fn into_bytes(self) -> Bytes {
+        self.1
+    }
+}
+
+impl OutgoingMessage for (Pubkey, SocketAddr, Bytes) {
+    fn remote_address(&self) -> SocketAddr {
+        self.1
+    }
+
+    fn remote_pubkey(&self) -> Option<Pubkey> {
+        Some(self.0)
+    }
+
+    fn into_bytes(self) -> Bytes {
+        self.2
+    }
+}
+
+/// Messages received are sent to `sender` along with the identity and the
+/// address of the remote node.
+#[allow(clippy::type_complexity)]
+pub fn new_quic_endpoint<T: OutgoingMessage>(
+    runtime: &tokio::runtime::Handle,
+    keypair: &Keypair,
+    socket: UdpSocket,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    config: QuicEndpointConfig,
+) -> Result<(Endpoint, AsyncSender<T>, AsyncTryJoinHandle), Error> {
+    let (cert, key) = new_dummy_x509_certificate(keypair);
+    let server_config = new_server_config(cert.clone(), key.clone(), &config)?;
+    let client_config = new_client_config(cert, key, &config)?;
+    let mut endpoint = {
+        // Endpoint::new requires entering the runtime context,
+        // otherwise the code below will panic.
+        let _guard = runtime.enter();
+        Endpoint::new(
+            EndpointConfig::default(),
+            Some(server_config),
+            socket,
+            Arc::new(TokioRuntime),
+        )?
+    };
+    endpoint.set_default_client_config(client_config);
+    let config = Arc::new(config);
+    let prune_cache_pending = Arc::<AtomicBool>::default();
+    let cache = Arc::<Mutex<HashMap<Pubkey, Connection>>>::default();
+    let router = Arc::<Router>::default();
+    let (client_sender, client_receiver) = tokio::sync::mpsc::channel(CLIENT_CHANNEL_BUFFER);
+    let server_task = runtime.spawn(run_server(
+        endpoint.clone(),
+        sender.clone(),
+        config.clone(),
+        prune_cache_pending.clone(),
+        router.clone(),
+        cache.clone(),
+    ));
+    let client_task = runtime.spawn(run_client(
+        endpoint.clone(),
+        client_receiver,
+        sender,
+        config,
+        prune_cache_pending,
+        router,
+        cache,
+    ));
+    let task = futures_util::future::try_join(server_task, client_task);
+    Ok((endpoint, client_sender, task))
+}
+
+pub fn close_quic_endpoint(endpoint: &Endpoint) {
+    endpoint.close(
+        CONNECTION_CLOSE_ERROR_CODE_SHUTDOWN,
+        CONNECTION_CLOSE_REASON_SHUTDOWN,
+    );
+}
+
+fn new_server_config(
+    cert: Certificate,
+    key: PrivateKey,
+    config: &QuicEndpointConfig,
+) -> Result<ServerConfig, rustls::Error> {
+    let mut crypto = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(SkipClientVerification::new())
+        .with_single_cert(vec![cert], key)?;
+    crypto.alpn_protocols = vec![config.alpn_protocol_id.as_bytes().to_vec()];
+    let mut server_config = ServerConfig::with_crypto(Arc::new(crypto));
+    server_config
+        .transport_config(Arc::new(new_transport_config(config.transport)))
+        .use_retry(true)
+        .migration(false);
+    Ok(server_config)
+}
+
+fn new_client_config(
+    cert: Certificate,
+    key: PrivateKey,
+    config: &QuicEndpointConfig,
+) -> Result<ClientConfig, rustls::Error> {
+    let mut crypto = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(SkipServerVerification::new())
+        .with_client_auth_cert(vec![cert], key)?;
+    crypto.enable_early_data = true;
+    crypto.alpn_protocols = vec![config.alpn_protocol_id.as_bytes().to_vec()];
+    let mut client_config = ClientConfig::new(Arc::new(crypto));
+    client_config.transport_config(Arc::new(new_transport_config(config.transport)));
+    Ok(client_config)
+}
+
+fn new_transport_config(transport: QuicTransport) -> TransportConfig {
+    let max_idle_timeout = IdleTimeout::try_from(MAX_IDLE_TIMEOUT).unwrap();
+    let mut config = TransportConfig::default();
+    config
+        .keep_alive_interval(Some(KEEP_ALIVE_INTERVAL))
+        .max_concurrent_bidi_streams(VarInt::from(0u8))
+        .max_idle_timeout(Some(max_idle_timeout));
+    match transport {
+        QuicTransport::Datagrams => config
+            .datagram_receive_buffer_size(Some(DATAGRAM_RECEIVE_BUFFER_SIZE))
+            .datagram_send_buffer_size(DATAGRAM_SEND_BUFFER_SIZE)
+            .initial_mtu(INITIAL_MAXIMUM_TRANSMISSION_UNIT)
+            .max_concurrent_uni_streams(VarInt::from(0u8))
+            .min_mtu(MINIMUM_MAXIMUM_TRANSMISSION_UNIT)
+            .mtu_discovery_config(None),
+        QuicTransport::UniStreams { .. } => config
+            .datagram_receive_buffer_size(None)
+            .max_concurrent_uni_streams(MAX_CONCURRENT_UNI_STREAMS),
+    };
+    config
+}
+
+async fn run_server(
+    endpoint: Endpoint,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    config: Arc<QuicEndpointConfig>,
+    prune_cache_pending: Arc<AtomicBool>,
+    router: Arc<Router>,
+    cache: Arc<Mutex<HashMap<Pubkey, Connection>>>,
+) {
+    let stats = Arc::<QuicEndpointStats>::default();
+    let report_metrics_task = tokio::task::spawn(report_metrics_task(
+        config.server_metrics_name,
+        stats.clone(),
+    ));
+    while let Some(connecting) = endpoint.accept().await {
+        tokio::task::spawn(handle_connecting_task(
+            endpoint.clone(),
+            connecting,
+            sender.clone(),
+            config.clone(),
+            prune_cache_pending.clone(),
+            router.clone(),
+            cache.clone(),
+            stats.clone(),
+        ));
+    }
+    report_metrics_task.abort();
+}
+
+async fn run_client<T: OutgoingMessage>(
+    endpoint: Endpoint,
+    mut receiver: AsyncReceiver<T>,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    config: Arc<QuicEndpointConfig>,
+    prune_cache_pending: Arc<AtomicBool>,
+    router: Arc<Router>,
+    cache: Arc<Mutex<HashMap<Pubkey, Connection>>>,
+) {
+    let stats = Arc::<QuicEndpointStats>::default();
+    let report_metrics_task = tokio::task::spawn(report_metrics_task(
+        config.client_metrics_name,
+        stats.clone(),
+    ));
+    while let Some(message) = receiver.recv().await {
+        let remote_address = message.remote_address();
+        let remote_pubkey = message.remote_pubkey();
+        let bytes = message.into_bytes();
+        let Some(bytes) = try_route_bytes(
+            remote_pubkey.as_ref(),
+            &remote_address,
+            bytes,
+            &*router.read().await,
+            &stats,
+        ) else {
+            continue;
+        };
+        let receiver = {
+            let mut router = router.write().await;
+            let Some(bytes) = try_route_bytes(
+                remote_pubkey.as_ref(),
+                &remote_address,
+                bytes,
+                &router,
+                &stats,
+            ) else {
+                continue;
+            };
+            let (sender, receiver) = tokio::sync::mpsc::channel(config.router_channel_buffer);
+            sender.try_send(bytes).unwrap();
+            router.insert(remote_address, (remote_pubkey, sender));
+            receiver
+        };
+        tokio::task::spawn(make_connection_task(
+            endpoint.clone(),
+            remote_pubkey,
+            remote_address,
+            sender.clone(),
+            receiver,
+            config.clone(),
+            prune_cache_pending.clone(),
+            router.clone(),
+            cache.clone(),
+            stats.clone(),
+        ));
+    }
+    close_quic_endpoint(&endpoint);
+    // Drop sender channels to unblock threads waiting on the receiving end.
+    router.write().await.clear();
+    report_metrics_task.abort();
+}
+
+fn try_route_bytes(
+    remote_pubkey: Option<&Pubkey>,
+    remote_address: &SocketAddr,
+    bytes: Bytes,
+    router: &HashMap<SocketAddr, (Option<Pubkey>, AsyncSender<Bytes>)>,
+    stats: &QuicEndpointStats,
+) -> Option<Bytes> {
+    match router.get(remote_address) {
+        None => Some(bytes),
+        Some((pubkey, sender)) => {
+            if remote_pubkey.is_some() && pubkey.as_ref() != remote_pubkey {
+                // The node at the address is not the intended recipient.
+                add_metric!(stats.router_invalid_identity);
+                return None;
+            }
+            match sender.try_send(bytes) {
+                Ok(()) => None,
+                Err(TrySendError::Full(_)) => {
+                    debug!("TrySendError::Full {remote_address}");
+                    add_metric!(stats.router_try_send_error_full);
+                    None
+                }
+                Err(TrySendError::Closed(bytes)) => Some(bytes),
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_connecting_task(
+    endpoint: Endpoint,
+    connecting: Connecting,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    config: Arc<QuicEndpointConfig>,
+    prune_cache_pending: Arc<AtomicBool>,
+    router: Arc<Router>,
+    cache: Arc<Mutex<HashMap<Pubkey, Connection>>>,
+    stats: Arc<QuicEndpointStats>,
+) {
+    if let Err(err) = handle_connecting(
+        endpoint,
+        connecting,
+        sender,
+        config,
+        prune_cache_pending,
+        router,
+        cache,
+        stats.clone(),
+    )
+    .await
+    {
+        debug!("handle_connecting: {err:?}");
+        record_error(&err, &stats);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_connecting(
+    endpoint: Endpoint,
+    connecting: Connecting,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    config: Arc<QuicEndpointConfig>,
+    prune_cache_pending: Arc<AtomicBool>,
+    router: Arc<Router>,
+    cache: Arc<Mutex<HashMap<Pubkey, Connection>>>,
+    stats: Arc<QuicEndpointStats>,
+) -> Result<(), Error> {
+    let connection = connecting.await?;
+    let remote_address = connection.remote_address();
+    let remote_pubkey = get_remote_pubkey(&connection)?;
+    let receiver = {
+        let (sender, receiver) = tokio::sync::mpsc::channel(config.router_channel_buffer);
+        router
+            .write()
+            .await
+            .insert(remote_address, (Some(remote_pubkey), sender));
+        receiver
+    };
+    handle_connection(
+        endpoint,
+        remote_address,
+        remote_pubkey,
+        connection,
+        sender,
+        receiver,
+        config,
+        prune_cache_pending,
+        router,
+        cache,
+        stats,
+    )
+    .await;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_connection(
+    endpoint: Endpoint,
+    remote_address: SocketAddr,
+    remote_pubkey: Pubkey,
+    connection: Connection,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    receiver: AsyncReceiver<Bytes>,
+    config: Arc<QuicEndpointConfig>,
+    prune_cache_pending: Arc<AtomicBool>,
+    router: Arc<Router>,
+    cache: Arc<Mutex<HashMap<Pubkey, Connection>>>,
+    stats: Arc<QuicEndpointStats>,
+) {
+    cache_connection(
+        remote_pubkey,
+        connection.clone(),
+        config.sort_connections.clone(),
+        prune_cache_pending,
+        router.clone(),
+        cache.clone(),
+    )
+    .await;
+    let send_task = tokio::task::spawn(send_task(
+        connection.clone(),
+        receiver,
+        config.transport,
+        stats.clone(),
+    ));
+    let read_task = tokio::task::spawn(read_task(
+        endpoint,
+        remote_address,
+        remote_pubkey,
+        connection.clone(),
+        sender,
+        config.transport,
+        stats.clone(),
+    ));
+    match futures_util::future::try_join(send_task, read_task).await {
+        Err(err) => error!("handle_connection: {remote_pubkey}, {remote_address}, {err:?}"),
+        Ok(out) => {
+            if let (Err(ref err), _) = out {
+                debug!("send_task: {remote_pubkey}, {remote_address}, {err:?}");
+                record_error(err, &stats);
+            }
+            if let (_, Err(ref err)) = out {
+                debug!("read_task: {remote_pubkey}, {remote_address}, {err:?}");
+                record_error(err, &stats);
+            }
+        }
+    }
+    drop_connection(remote_pubkey, &connection, &cache).await;
+    if let Entry::Occupied(entry) = router.write().await.entry(remote_address) {
+        if entry.get().1.is_closed() {
+            entry.remove();
+        }
+    }
+}
+
+async fn read_task(
+    endpoint: Endpoint,
+    remote_address: SocketAddr,
+    remote_pubkey: Pubkey,
+    connection: Connection,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    transport: QuicTransport,
+    stats: Arc<QuicEndpointStats>,
+) -> Result<(), Error> {
+    // Assert that send won't block.
+    debug_assert_eq!(sender.capacity(), None);
+    match transport {
+        QuicTransport::Datagrams => {
+            read_datagrams(
+                endpoint,
+                remote_address,
+                remote_pubkey,
+                connection,
+                sender,
+                stats,
+            )
+            .await
+        }
+        QuicTransport::UniStreams { max_message_size } => {
+            read_streams(
+                endpoint,
+                remote_address,
+                remote_pubkey,
+                connection,
+                sender,
+                max_message_size,
+                stats,
+            )
+            .await
+        }
+    }
+}
+
+async fn read_datagrams(
+    endpoint: Endpoint,
+    remote_address: SocketAddr,
+    remote_pubkey: Pubkey,
+    connection: Connection,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    stats: Arc<QuicEndpointStats>,
+) -> Result<(), Error> {
+    #[cfg(feature = "dev-context-only-utils")]
+    let local_address = endpoint.local_addr().ok();
+    loop {
+        match connection.read_datagram().await {
+            #[cfg(feature = "dev-context-only-utils")]
+            Ok(bytes) if crate::net_emulator::get().is_some() => {
+                let endpoint = endpoint.clone();
+                let sender = sender.clone();
+                tokio::task::spawn(async move {
+                    if crate::net_emulator::delay_or_drop_stream(
+                        Some(&remote_pubkey),
+                        &remote_address,
+                        local_address,
+                    )
+                    .await
+                        && sender.send((remote_pubkey, remote_address, bytes)).is_err()
+                    {
+                        close_quic_endpoint(&endpoint);
+                    }
+                });
+            }
+            Ok(bytes) => {
+                if let Err(err) = sender.send((remote_pubkey, remote_address, bytes)) {
+                    close_quic_endpoint(&endpoint);
+                    return Err(Error::from(err));
+                }
+            }
+            Err(err) => {
+                if let Some(err) = connection.close_reason() {
+                    return Err(Error::from(err));
+                }
+                debug!("connection.read_datagram: {remote_pubkey}, {remote_address}, {err:?}");
+                record_error(&Error::from(err), &stats);
+            }
+        };
+    }
+}
+
+async fn read_streams(
+    endpoint: Endpoint,
+    remote_address: SocketAddr,
+    remote_pubkey: Pubkey,
+    connection: Connection,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    max_message_size: usize,
+    stats: Arc<QuicEndpointStats>,
+) -> Result<(), Error> {
+    #[cfg(feature = "dev-context-only-utils")]
+    let local_address = endpoint.local_addr().ok();
+    loop {
+        let recv_stream = connection.accept_uni().await?;
+        let endpoint = endpoint.clone();
+        let sender = sender.clone();
+        let stats = stats.clone();
+        tokio::task::spawn(async move {
+            match read_stream(recv_stream, max_message_size).await {
+                Ok(bytes) => {
+                    #[cfg(feature = "dev-context-only-utils")]
+                    if !crate::net_emulator::delay_or_drop_stream(
+                        Some(&remote_pubkey),
+                        &remote_address,
+                        local_address,
+                    )
+                    .await
+                    {
+                        return;
+                    }
+                    if sender.send((remote_pubkey, remote_address, bytes)).is_err() {
+                        close_quic_endpoint(&endpoint);
+                    }
+                }
+                Err(err) => {
+                    debug!("read_stream: {remote_pubkey}, {remote_address}, {err:?}");
+                    record_error(&err, &stats);
+                }
+            }
+        });
+    }
+}
+
+async fn read_stream(mut recv_stream: RecvStream, max_message_size: usize) -> Result<Bytes, Error> {
+    let bytes = tokio::time::timeout(
+        READ_TIMEOUT_DURATION,
+        recv_stream.read_to_end(max_message_size),
+    )
+    .await
+    .map_err(|_| Error::ReadToEndTimeout)??;
+    Ok(Bytes::from(bytes))
+}
+
+async fn send_task(
+    connection: Connection,
+    mut receiver: AsyncReceiver<Bytes>,
+    transport: QuicTransport,
+    stats: Arc<QuicEndpointStats>,
+) -> Result<(), Error> {
+    tokio::pin! {
+        let connection_closed = connection.closed();
+    }
+    loop {
+        tokio::select! {
+            biased;
+            bytes = receiver.recv() => {
+                match (bytes, transport) {
+                    (None, _) => return Ok(()),
+                    (Some(bytes), QuicTransport::Datagrams) => connection.send_datagram(bytes)?,
+                    (Some(bytes), QuicTransport::UniStreams { .. }) => {
+                        // Messages are sent concurrently, limited by the
+                        // number of streams the remote node allows.
+                        let connection = connection.clone();
+                        let stats = stats.clone();
+                        tokio::task::spawn(async move {
+                            if let Err(err) = send_stream(&connection, &bytes).await {
+                                record_error(&err, &stats);
+                            }
+                        });
+                    }
+                }
+            }
+            err = &mut connection_closed => return Err(Error::from(err)),
+        }
+    }
+}
+
+async fn send_stream(connection: &Connection, bytes: &[u8]) -> Result<(), Error> {
+    let mut send_stream = connection.open_uni().await?;
+    send_stream.write_all(bytes).await?;
+    send_stream.finish().await.map_err(Error::from)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn make_connection_task(
+    endpoint: Endpoint,
+    remote_pubkey: Option<Pubkey>,
+    remote_address: SocketAddr,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    receiver: AsyncReceiver<Bytes>,
+    config: Arc<QuicEndpointConfig>,
+    prune_cache_pending: Arc<AtomicBool>,
+    router: Arc<Router>,
+    cache: Arc<Mutex<HashMap<Pubkey, Connection>>>,
+    stats: Arc<QuicEndpointStats>,
+) {
+    if let Err(err) = make_connection(
+        endpoint,
+        remote_pubkey,
+        remote_address,
+        sender,
+        receiver,
+        config,
+        prune_cache_pending,
+        router.clone(),
+        cache,
+        stats.clone(),
+    )
+    .await
+    {
+        debug!("make_connection: {remote_address}, {err:?}");
+        record_error(&err, &stats);
+        // Drop the channel so that later messages make a new connection.
+        if let Entry::Occupied(entry) = router.write().await.entry(remote_address) {
+            if entry.get().1.is_closed() {
+                entry.remove();
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn make_connection(
+    endpoint: Endpoint,
+    remote_pubkey: Option<Pubkey>,
+    remote_address: SocketAddr,
+    sender: Sender<(Pubkey, SocketAddr, Bytes)>,
+    receiver: AsyncReceiver<Bytes>,
+    config: Arc<QuicEndpointConfig>,
+    prune_cache_pending: Arc<AtomicBool>,
+    router: Arc<Router>,
+    cache: Arc<Mutex<HashMap<Pubkey, Connection>>>,
+    stats: Arc<QuicEndpointStats>,
+) -> Result<(), Error> {
+    let connection = endpoint
+        .connect(remote_address, config.alpn_protocol_id)?
+        .await?;
+    let pubkey = get_remote_pubkey(&connection)?;
+    // Only send messages to the node they are intended for.
+    if remote_pubkey.is_some() && remote_pubkey != Some(pubkey) {
+        connection.close(
+            CONNECTION_CLOSE_ERROR_CODE_INVALID_IDENTITY,
+            CONNECTION_CLOSE_REASON_INVALID_IDENTITY,
+        );
+        return Err(Error::InvalidIdentity(remote_address));
+    }
+    handle_connection(
+        endpoint,
+        connection.remote_address(),
+        pubkey,
+        connection,
+        sender,
+        receiver,
+        config,
+        prune_cache_pending,
+        router,
+        cache,
+        stats,
+    )
+    .await;
+    Ok(())
+}
+
+fn get_remote_pubkey(connection: &Connection) -> Result<Pubkey, Error> {
+    match crate::nonblocking::quic::get_remote_pubkey(connection) {
+        Some(remote_pubkey) => Ok(remote_pubkey),
+        None => {
+            connection.close(
+                CONNECTION_CLOSE_ERROR_CODE_INVALID_IDENTITY,
+                CONNECTION_CLOSE_REASON_INVALID_IDENTITY,
+            );
+            Err(Error::InvalidIdentity(connection.remote_address()))
+        }
+    }
+}
+
+async fn cache_connection(
+    remote_pubkey: Pubkey,
+    connection: Connection,
+    sort_connections: SortConnections,
+    prune_cache_pending: Arc<AtomicBool>,
+    router: Arc<Router>,
+    cache: Arc<Mutex<HashMap<Pubkey, Connection>>>,
+) {
+    let (old, should_prune_cache) = {
+        let mut cache = cache.lock().await;
+        (
+            cache.insert(remote_pubkey, connection),
+            cache.len() >= CONNECTION_CACHE_CAPACITY.saturating_mul(2),
+        )
+    };
+    if let Some(old) = old {
+        old.close(
+            CONNECTION_CLOSE_ERROR_CODE_REPLACED,
+            CONNECTION_CLOSE_REASON_REPLACED,
+        );
+    }
+    if should_prune_cache && !prune_cache_pending.swap(true, Ordering::Relaxed) {
+        tokio::task::spawn(prune_connection_cache(
+            sort_connections,
+            prune_cache_pending,
+            router,
+            cache,
+        ));
+    }
+}
+
+async fn drop_connection(
+    remote_pubkey: Pubkey,
+    connection: &Connection,
+    cache: &Mutex<HashMap<Pubkey, Connection>>,
+) {
+    connection.close(
+        CONNECTION_CLOSE_ERROR_CODE_DROPPED,
+        CONNECTION_CLOSE_REASON_DROPPED,
+    );
+    if let Entry::Occupied(entry) = cache.lock().await.entry(remote_pubkey) {
+        if entry.get().stable_id() == connection.stable_id() {
+            entry.remove();
+        }
+    }
+}
+
+async fn prune_connection_cache(
+    sort_connections: SortConnections,
+    prune_cache_pending: Arc<AtomicBool>,
+    router: Arc<Router>,
+    cache: Arc<Mutex<HashMap<Pubkey, Connection>>>,
+) {
+    debug_assert!(prune_cache_pending.load(Ordering::Relaxed));
+    {
+        let mut cache = cache.lock().await;
+        if cache.len() < CONNECTION_CACHE_CAPACITY.saturating_mul(2) {
+            prune_cache_pending.store(false, Ordering::Relaxed);
+            return;
+        }
+        let mut connections: Vec<_> = cache
+            .drain()
+            .filter(|(_, connection)| connection.close_reason().is_none())
+            .collect();
+        sort_connections(&mut connections);
+        for (_, connection) in connections.iter().skip(CONNECTION_CACHE_CAPACITY) {
+            connection.close(
+                CONNECTION_CLOSE_ERROR_CODE_PRUNED,
+                CONNECTION_CLOSE_REASON_PRUNED,
+            );
+        }
+        cache.extend(connections.into_iter().take(CONNECTION_CACHE_CAPACITY));
+        prune_cache_pending.store(false, Ordering::Relaxed);
+    }
+    router
+        .write()
+        .await
+        .retain(|_, (_, sender)| !sender.is_closed());
+}
+
+impl<T> From<crossbeam_channel::SendError<T>> for Error {
+    fn from(_: crossbeam_channel::SendError<T>) -> Self {
+        Error::ChannelSendError
+    }
+}
+
+/// Skips verifying the server certificate, as the remote node's identity is
+/// checked against the expected one once connected.
+struct SkipServerVerification;
+
+impl SkipServerVerification {
+    fn new() -> Arc<Self> {
+        Arc::new(Self)
+    }
+}
+
+impl rustls::client::ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: std::time::SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::ServerCertVerified::assertion())
+    }
+}
+
+#[derive(Default)]
+struct QuicEndpointStats {
+    connect_error_invalid_remote_address: AtomicU64,
+    connect_error_other: AtomicU64,
+    connect_error_too_many_connections: AtomicU64,
+    connection_error_application_closed: AtomicU64,
+    connection_error_connection_closed: AtomicU64,
+    connection_error_locally_closed: AtomicU64,
+    connection_error_reset: AtomicU64,
+    connection_error_timed_out: AtomicU64,
+    connection_error_transport_error: AtomicU64,
+    connection_error_version_mismatch: AtomicU64,
+    invalid_identity: AtomicU64,
+    read_to_end_error: AtomicU64,
+    read_to_end_error_too_long: AtomicU64,
+    read_to_end_timeout: AtomicU64,
+    router_invalid_identity: AtomicU64,
+    router_try_send_error_full: AtomicU64,
+    send_datagram_error_connection_lost: AtomicU64,
+    send_datagram_error_too_large: AtomicU64,
+    send_datagram_error_unsupported_by_peer: AtomicU64,
+    write_error: AtomicU64,
+}
+
+async fn report_metrics_task(name: &'static str, stats: Arc<QuicEndpointStats>) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        report_metrics(name, &stats);
+    }
+}
+
+fn record_error(err: &Error, stats: &QuicEndpointStats) {
+    match err {
+        Error::ChannelSendError => (),
+        Error::ConnectError(ConnectError::EndpointStopping) => {
+            add_metric!(stats.connect_error_other)
+        }
+        Error::ConnectError(ConnectError::TooManyConnections) => {
+            add_metric!(stats.connect_error_too_many_connections)
+        }
+        Error::ConnectError(ConnectError::InvalidDnsName(_)) => {
+            add_metric!(stats.connect_error_other)
+        }
+        Error::ConnectError(ConnectError::InvalidRemoteAddress(_)) => {
+            add_metric!(stats.connect_error_invalid_remote_address)
+        }
+        Error::ConnectError(ConnectError::NoDefaultClientConfig) => {
+            add_metric!(stats.connect_error_other)
+        }
+        Error::ConnectError(ConnectError::UnsupportedVersion) => {
+            add_metric!(stats.connect_error_other)
+        }
+        Error::ConnectionError(ConnectionError::VersionMismatch) => {
+            add_metric!(stats.connection_error_version_mismatch)
+        }
+        Error::ConnectionError(ConnectionError::TransportError(_)) => {
+            add_metric!(stats.connection_error_transport_error)
+        }
+        Error::ConnectionError(ConnectionError::ConnectionClosed(_)) => {
+            add_metric!(stats.connection_error_connection_closed)
+        }
+        Error::ConnectionError(ConnectionError::ApplicationClosed(_)) => {
+            add_metric!(stats.connection_error_application_closed)
+        }
+        Error::ConnectionError(ConnectionError::Reset) => add_metric!(stats.connection_error_reset),
+        Error::ConnectionError(ConnectionError::TimedOut) => {
+            add_metric!(stats.connection_error_timed_out)
+        }
+        Error::ConnectionError(ConnectionError::LocallyClosed) => {
+            add_metric!(stats.connection_error_locally_closed)
+        }
+        Error::InvalidIdentity(_) => add_metric!(stats.invalid_identity),
+        Error::IoError(_) => (),
+        Error::ReadToEndError(ReadToEndError::TooLong) => {
+            add_metric!(stats.read_to_end_error_too_long)
+        }
+        Error::ReadToEndError(ReadToEndError::Read(
+            ReadError::Reset(_)
+            | ReadError::ConnectionLost(_)
+            | ReadError::UnknownStream
+            | ReadError::IllegalOrderedRead
+            | ReadError::ZeroRttRejected,
+        )) => add_metric!(stats.read_to_end_error),
+        Error::ReadToEndTimeout => add_metric!(stats.read_to_end_timeout),
+        Error::SendDatagramError(SendDatagramError::UnsupportedByPeer) => {
+            add_metric!(stats.send_datagram_error_unsupported_by_peer)
+        }
+        Error::SendDatagramError(SendDatagramError::Disabled) => (),
+        Error::SendDatagramError(SendDatagramError::TooLarge) => {
+            add_metric!(stats.send_datagram_error_too_large)
+        }
+        Error::SendDatagramError(SendDatagramError::ConnectionLost(_)) => {
+            add_metric!(stats.send_datagram_error_connection_lost)
+        }
+        Error::TlsError(_) => (),
+        Error::WriteError(_) => add_metric!(stats.write_error),
+    }
+}
+
+fn report_metrics(name: &'static str, stats: &QuicEndpointStats) {
+    macro_rules! reset_metric {
+        ($metric: expr) => {
+            $metric.swap(0, Ordering::Relaxed)
+        };
+    }
+    datapoint_info!(
+        name,
+        (
+            "connect_error_invalid_remote_address",
+            reset_metric!(stats.connect_error_invalid_remote_address),
+            i64
+        ),
+        (
+            "connect_error_other",
+            reset_metric!(stats.connect_error_other),
+            i64
+        ),
+        (
+            "connect_error_too_many_connections",
+            reset_metric!(stats.connect_error_too_many_connections),
+            i64
+        ),
+        (
+            "connection_error_application_closed",
+            reset_metric!(stats.connection_error_application_closed),
+            i64
+        ),
+        (
+            "connection_error_connection_closed",
+            reset_metric!(stats.connection_error_connection_closed),
+            i64
+        ),
+        (
+            "connection_error_locally_closed",
+            reset_metric!(stats.connection_error_locally_closed),
+            i64
+        ),
+        (
+            "connection_error_reset",
+            reset_metric!(stats.connection_error_reset),
+            i64
+        ),
+        (
+            "connection_error_timed_out",
+            reset_metric!(stats.connection_error_timed_out),
+            i64
+        ),
+        (
+            "connection_error_transport_error",
+            reset_metric!(stats.connection_error_transport_error),
+            i64
+        ),
+        (
+            "connection_error_version_mismatch",
+            reset_metric!(stats.connection_error_version_mismatch),
+            i64
+        ),
+        (
+            "invalid_identity",
+            reset_metric!(stats.invalid_identity),
+            i64
+        ),
+        (
+            "read_to_end_error",
+            reset_metric!(stats.read_to_end_error),
+            i64
+        ),
+        (
+            "read_to_end_error_too_long",
+            reset_metric!(stats.read_to_end_error_too_long),
+            i64
+        ),
+        (
+            "read_to_end_timeout",
+            reset_metric!(stats.read_to_end_timeout),
+            i64
+        ),
+        (
+            "router_invalid_identity",
+            reset_metric!(stats.router_invalid_identity),
+            i64
+        ),
+        (
+            "router_try_send_error_full",
+            reset_metric!(stats.router_try_send_error_full),
+            i64
+        ),
+        (
+            "send_datagram_error_connection_lost",
+            reset_metric!(stats.send_datagram_error_connection_lost),
+            i64
+        ),
+        (
+            "send_datagram_error_too_large",
+            reset_metric!(stats.send_datagram_error_too_large),
+            i64
+        ),
+        (
+            "send_datagram_error_unsupported_by_peer",
+            reset_metric!(stats.send_datagram_error_unsupported_by_peer),
+            i64
+        ),
+        ("write_error", reset_metric!(stats.write_error), i64),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        itertools::{izip, multiunzip},
+        trezoa_sdk::{packet::PACKET_DATA_SIZE, signature::Signer},
+        std::{iter::repeat_with, net::Ipv4Addr},
+    };
+
+    fn new_test_config(transport: QuicTransport) -> QuicEndpointConfig {
+        QuicEndpointConfig {
+            alpn_protocol_id: "trezoa-test",
+            transport,
+            router_channel_buffer: 64,
+            sort_connections: Arc::new(|connections| {
+                connections.sort_unstable_by_key(|(_, connection)| connection.rtt())
+            }),
+            server_metrics_name: "test_quic_server",
+            client_metrics_name: "test_quic_client",
+        }
+    }
+
+    #[test]
+    fn test_quic_endpoint() {
+        const NUM_ENDPOINTS: usize = 3;
+        const MAX_MESSAGE_SIZE: usize = 4 * PACKET_DATA_SIZE;
+        const RECV_TIMEOUT: Duration = Duration::from_secs(60);
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(8)
+            .enable_all()
+            .build()
+            .unwrap();
+        let keypairs: Vec<Keypair> = repeat_with(Keypair::new).take(NUM_ENDPOINTS).collect();
+        let sockets: Vec<UdpSocket> = repeat_with(|| UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)))
+            .take(NUM_ENDPOINTS)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let addresses: Vec<SocketAddr> = sockets
+            .iter()
+            .map(UdpSocket::local_addr)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            repeat_with(crossbeam_channel::unbounded::<(Pubkey, SocketAddr, Bytes)>)
+                .take(NUM_ENDPOINTS)
+                .unzip();
+        let config = new_test_config(QuicTransport::UniStreams {
+            max_message_size: MAX_MESSAGE_SIZE,
+        });
+        let (endpoints, senders, tasks): (Vec<_>, Vec<_>, Vec<_>) =
+            multiunzip(keypairs.iter().zip(sockets).zip(senders).map(
+                |((keypair, socket), sender)| {
+                    new_quic_endpoint::<(Pubkey, SocketAddr, Bytes)>(
+                        runtime.handle(),
+                        keypair,
+                        socket,
+                        sender,
+                        config.clone(),
+                    )
+                    .unwrap()
+                },
+            ));
+        // Send a unique message from each endpoint to every other endpoint.
+        for (i, (keypair, &address, sender)) in izip!(&keypairs, &addresses, &senders).enumerate() {
+            for (j, (other, &address)) in keypairs.iter().zip(&addresses).enumerate() {
+                if i != j {
+                    let bytes = Bytes::from(format!("{i}=>{j}"));
+                    sender
+                        .blocking_send((other.pubkey(), address, bytes))
+                        .unwrap();
+                }
+            }
+            // Verify all messages are received.
+            for (j, receiver) in receivers.iter().enumerate() {
+                if i != j {
+                    let bytes = Bytes::from(format!("{i}=>{j}"));
+                    let entry = (keypair.pubkey(), address, bytes);
+                    assert_eq!(receiver.recv_timeout(RECV_TIMEOUT).unwrap(), entry);
+                }
+            }
+        }
+        // Messages are not sent if the identity at the address does not match.
+        senders[0]
+            .blocking_send((Pubkey::new_unique(), addresses[1], Bytes::from("spoofed")))
+            .unwrap();
+        // Messages larger than a packet are received, up to the maximum size.
+        let large = Bytes::from(vec![7u8; MAX_MESSAGE_SIZE]);
+        let too_large = Bytes::from(vec![7u8; MAX_MESSAGE_SIZE + 1]);
+        for bytes in [too_large, large.clone()] {
+            senders[2]
+                .blocking_send((keypairs[1].pubkey(), addresses[1], bytes))
+                .unwrap();
+        }
+        assert_eq!(
+            receivers[1].recv_timeout(RECV_TIMEOUT).unwrap(),
+            (keypairs[2].pubkey(), addresses[2], large)
+        );
+        assert!(receivers[1].try_recv().is_err());
+        drop(senders);
+        for endpoint in endpoints {
+            close_quic_endpoint(&endpoint);
+        }
+        for task in tasks {
+            runtime.block_on(task).unwrap();
+        }
+    }
+}
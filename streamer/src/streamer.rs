@@ -111,6 +111,8 @@ fn recv_loop(
     use_pinned_memory: bool,
     in_vote_only_mode: Option<Arc<AtomicBool>>,
 ) -> Result<()> {
+    #[cfg(feature = "dev-context-only-utils")]
+    let local_addr = socket.local_addr().ok();
     loop {
         let mut packet_batch = if use_pinned_memory {
             PacketBatch::new_with_recycler(recycler, PACKETS_PER_BATCH, stats.name)
@@ -148,7 +150,20 @@ fn recv_loop(
                         full_packet_batches_count.fetch_add(1, Ordering::Relaxed);
                     }
 
-                    packet_batch_sender.send(packet_batch)?;
+                    #[cfg(feature = "dev-context-only-utils")]
+                    let packet_batch = match (crate::net_emulator::get(), &local_addr) {
+                        (Some(emulator), Some(local_addr)) => emulator.shape_packet_batch(
+                            local_addr,
+                            packet_batch,
+                            packet_batch_sender,
+                        ),
+                        _ => Some(packet_batch),
+                    };
+                    #[cfg(not(feature = "dev-context-only-utils"))]
+                    let packet_batch = Some(packet_batch);
+                    if let Some(packet_batch) = packet_batch {
+                        packet_batch_sender.send(packet_batch)?;
+                    }
                 }
                 break;
             }
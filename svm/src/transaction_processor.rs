@@ -643,6 +643,7 @@ impl<FG: ForkGraph> TransactionBatchProcessor<FG> {
             blockhash,
             lamports_per_signature,
             &mut executed_units,
+            self.runtime_config.fault_injection.as_deref(),
         );
         process_message_time.stop();
 
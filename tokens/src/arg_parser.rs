@@ -1,14 +1,18 @@
 use {
     crate::args::{
-        Args, BalancesArgs, Command, DistributeTokensArgs, SenderStakeArgs, TplTokenArgs,
-        StakeArgs, TransactionLogArgs,
+        Args, BalancesArgs, CloseLookupTablesArgs, Command, DistributeTokensArgs, SenderStakeArgs,
+        TplTokenArgs, StakeArgs, TransactionLogArgs,
     },
     clap::{
         crate_description, crate_name, value_t, value_t_or_exit, App, Arg, ArgMatches, SubCommand,
     },
     trezoa_clap_utils::{
+        compute_unit_price::{compute_unit_price_arg, COMPUTE_UNIT_PRICE_ARG},
         input_parsers::{pubkey_of_signer, value_of},
-        input_validators::{is_amount, is_url_or_moniker, is_valid_pubkey, is_valid_signer},
+        input_validators::{
+            is_amount, is_parsable, is_url_or_moniker, is_valid_pubkey, is_valid_signer,
+            is_within_range,
+        },
         keypair::{pubkey_from_path, signer_from_path},
     },
     trezoa_cli_config::CONFIG_FILE,
@@ -17,6 +21,28 @@ use {
     std::{error::Error, ffi::OsString, process::exit},
 };
 
+fn transaction_args<'a, 'b>() -> [Arg<'a, 'b>; 3] {
+    [
+        Arg::with_name("batch_size")
+            .long("batch-size")
+            .takes_value(true)
+            .value_name("NUMBER")
+            .validator(|s| is_within_range(s, 1..))
+            .help(
+                "Pack up to this many recipients into each versioned transaction. \
+                Recipient addresses are loaded from address lookup tables that are \
+                created for the distribution and closed once it is finalized.",
+            ),
+        compute_unit_price_arg(),
+        Arg::with_name("compute_unit_limit")
+            .long("with-compute-unit-limit")
+            .takes_value(true)
+            .value_name("COMPUTE-UNIT-LIMIT")
+            .validator(is_parsable::<u32>)
+            .help("Set compute unit limit for each recipient, batched transactions request it once per recipient they pay"),
+    ]
+}
+
 fn get_matches<'a, I, T>(args: I) -> ArgMatches<'a>
 where
     I: IntoIterator<Item = T>,
@@ -109,7 +135,8 @@ where
                         .value_name("KEYPAIR")
                         .validator(is_valid_signer)
                         .help("Fee payer"),
-                ),
+                )
+                .args(&transaction_args()),
         )
         .subcommand(
             SubCommand::with_name("create-stake")
@@ -180,7 +207,8 @@ where
                         .value_name("KEYPAIR")
                         .validator(is_valid_signer)
                         .help("Fee payer"),
-                ),
+                )
+                .args(&transaction_args()),
         )
         .subcommand(
             SubCommand::with_name("distribute-stake")
@@ -278,7 +306,8 @@ where
                         .value_name("KEYPAIR")
                         .validator(is_valid_signer)
                         .help("Fee payer"),
-                ),
+                )
+                .args(&transaction_args()),
         )
         .subcommand(
             SubCommand::with_name("distribute-tpl-tokens")
@@ -350,7 +379,8 @@ where
                         .value_name("KEYPAIR")
                         .validator(is_valid_signer)
                         .help("Fee payer"),
-                ),
+                )
+                .args(&transaction_args()),
         )
         .subcommand(
             SubCommand::with_name("balances")
@@ -385,6 +415,34 @@ where
                         .help("TPL token mint of distribution"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("stake-report")
+                .about("Reconcile distributed stake accounts against the allocations CSV")
+                .arg(
+                    Arg::with_name("db_path")
+                        .long("db-path")
+                        .required(true)
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("Location of the distribution database"),
+                )
+                .arg(
+                    Arg::with_name("input_csv")
+                        .long("input-csv")
+                        .required(true)
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("Allocations CSV file"),
+                )
+                .arg(
+                    Arg::with_name("unlocked_trz")
+                        .default_value("1.0")
+                        .long("unlocked-trz")
+                        .takes_value(true)
+                        .value_name("TRZ_AMOUNT")
+                        .help("Amount of TRZ that was put in system accounts to pay for fees"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("transaction-log")
                 .about("Print the database to a CSV file")
@@ -405,6 +463,28 @@ where
                         .help("Output file"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("close-lookup-tables")
+                .about("Close the lookup tables of a batched distribution once they deactivate")
+                .arg(
+                    Arg::with_name("db_path")
+                        .long("db-path")
+                        .required(true)
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("Location of the distribution database"),
+                )
+                .arg(
+                    Arg::with_name("fee_payer")
+                        .long("fee-payer")
+                        .required(true)
+                        .takes_value(true)
+                        .value_name("KEYPAIR")
+                        .validator(is_valid_signer)
+                        .help("Fee payer and authority of the lookup tables"),
+                )
+                .arg(compute_unit_price_arg()),
+        )
         .get_matches_from(args)
 }
 
@@ -440,6 +520,9 @@ fn parse_distribute_tokens_args(
         stake_args: None,
         tpl_token_args: None,
         transfer_amount: value_of(matches, "transfer_amount").map(trz_to_lamports),
+        batch_size: value_of(matches, "batch_size"),
+        compute_unit_price: value_of(matches, COMPUTE_UNIT_PRICE_ARG.name),
+        compute_unit_limit: value_of(matches, "compute_unit_limit"),
     })
 }
 
@@ -492,6 +575,9 @@ fn parse_create_stake_args(
         stake_args: Some(stake_args),
         tpl_token_args: None,
         transfer_amount: None,
+        batch_size: value_of(matches, "batch_size"),
+        compute_unit_price: value_of(matches, COMPUTE_UNIT_PRICE_ARG.name),
+        compute_unit_limit: value_of(matches, "compute_unit_limit"),
     })
 }
 
@@ -576,6 +662,9 @@ fn parse_distribute_stake_args(
         stake_args: Some(stake_args),
         tpl_token_args: None,
         transfer_amount: None,
+        batch_size: value_of(matches, "batch_size"),
+        compute_unit_price: value_of(matches, COMPUTE_UNIT_PRICE_ARG.name),
+        compute_unit_limit: value_of(matches, "compute_unit_limit"),
     })
 }
 
@@ -622,6 +711,9 @@ fn parse_distribute_tpl_tokens_args(
             ..TplTokenArgs::default()
         }),
         transfer_amount: value_of(matches, "transfer_amount"),
+        batch_size: value_of(matches, "batch_size"),
+        compute_unit_price: value_of(matches, COMPUTE_UNIT_PRICE_ARG.name),
+        compute_unit_limit: value_of(matches, "compute_unit_limit"),
     })
 }
 
@@ -638,6 +730,14 @@ fn parse_balances_args(matches: &ArgMatches<'_>) -> Result<BalancesArgs, Box<dyn
     })
 }
 
+fn parse_stake_report_args(matches: &ArgMatches<'_>) -> StakeReportArgs {
+    StakeReportArgs {
+        input_csv: value_t_or_exit!(matches, "input_csv", String),
+        transaction_db: value_t_or_exit!(matches, "db_path", String),
+        unlocked_trz: trz_to_lamports(value_t_or_exit!(matches, "unlocked_trz", f64)),
+    }
+}
+
 fn parse_transaction_log_args(matches: &ArgMatches<'_>) -> TransactionLogArgs {
     TransactionLogArgs {
         transaction_db: value_t_or_exit!(matches, "db_path", String),
@@ -645,6 +745,27 @@ fn parse_transaction_log_args(matches: &ArgMatches<'_>) -> TransactionLogArgs {
     }
 }
 
+fn parse_close_lookup_tables_args(
+    matches: &ArgMatches<'_>,
+) -> Result<CloseLookupTablesArgs, Box<dyn Error>> {
+    let mut wallet_manager = maybe_wallet_manager()?;
+    let signer_matches = ArgMatches::default(); // No default signer
+
+    let fee_payer_str = value_t_or_exit!(matches, "fee_payer", String);
+    let fee_payer = signer_from_path(
+        &signer_matches,
+        &fee_payer_str,
+        "fee-payer",
+        &mut wallet_manager,
+    )?;
+
+    Ok(CloseLookupTablesArgs {
+        transaction_db: value_t_or_exit!(matches, "db_path", String),
+        fee_payer,
+        compute_unit_price: value_of(matches, COMPUTE_UNIT_PRICE_ARG.name),
+    })
+}
+
 pub fn parse_args<I, T>(args: I) -> Result<Args, Box<dyn Error>>
 where
     I: IntoIterator<Item = T>,
@@ -669,9 +790,13 @@ where
         }
         ("balances", Some(matches)) => Command::Balances(parse_balances_args(matches)?),
         ("tpl-token-balances", Some(matches)) => Command::Balances(parse_balances_args(matches)?),
+        ("stake-report", Some(matches)) => Command::StakeReport(parse_stake_report_args(matches)),
         ("transaction-log", Some(matches)) => {
             Command::TransactionLog(parse_transaction_log_args(matches))
         }
+        ("close-lookup-tables", Some(matches)) => {
+            Command::CloseLookupTables(parse_close_lookup_tables_args(matches)?)
+        }
         _ => {
             eprintln!("{}", matches.usage());
             exit(1);
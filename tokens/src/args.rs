@@ -24,6 +24,11 @@ pub struct DistributeTokensArgs {
     pub stake_args: Option<StakeArgs>,
     pub tpl_token_args: Option<TplTokenArgs>,
     pub transfer_amount: Option<u64>,
+    /// Pack up to this many recipients into each versioned transaction,
+    /// loading their addresses from lookup tables created for the distribution
+    pub batch_size: Option<usize>,
+    pub compute_unit_price: Option<u64>,
+    pub compute_unit_limit: Option<u32>,
 }
 
 #[derive(Default)]
@@ -38,16 +43,30 @@ pub struct BalancesArgs {
     pub tpl_token_args: Option<TplTokenArgs>,
 }
 
+pub struct StakeReportArgs {
+    pub input_csv: String,
+    pub transaction_db: String,
+    pub unlocked_trz: u64,
+}
+
 pub struct TransactionLogArgs {
     pub transaction_db: String,
     pub output_path: String,
 }
 
+pub struct CloseLookupTablesArgs {
+    pub transaction_db: String,
+    pub fee_payer: Box<dyn Signer>,
+    pub compute_unit_price: Option<u64>,
+}
+
 #[allow(clippy::large_enum_variant)]
 pub enum Command {
     DistributeTokens(DistributeTokensArgs),
     Balances(BalancesArgs),
+    StakeReport(StakeReportArgs),
     TransactionLog(TransactionLogArgs),
+    CloseLookupTables(CloseLookupTablesArgs),
 }
 
 pub struct Args {
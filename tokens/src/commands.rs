@@ -1,16 +1,18 @@
 use {
     crate::{
         args::{
-            BalancesArgs, DistributeTokensArgs, SenderStakeArgs, StakeArgs, TransactionLogArgs,
+            BalancesArgs, CloseLookupTablesArgs, DistributeTokensArgs, SenderStakeArgs, StakeArgs,
+            StakeReportArgs, TransactionLogArgs,
         },
         db::{self, TransactionInfo},
         tpl_token::*,
         token_display::Token,
+        vesting::{VestingError, VestingSchedule},
     },
     chrono::prelude::*,
     console::style,
     csv::{ReaderBuilder, Trim},
-    indexmap::IndexMap,
+    indexmap::{IndexMap, IndexSet},
     indicatif::{ProgressBar, ProgressStyle},
     pickledb::PickleDb,
     serde::{Deserialize, Serialize},
@@ -22,26 +24,45 @@ use {
         request::{MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS, MAX_MULTIPLE_ACCOUNTS},
     },
     trezoa_sdk::{
-        clock::Slot,
+        account::{from_account, Account},
+        account_utils::StateMut,
+        address_lookup_table::{
+            instruction::{
+                close_lookup_table, create_lookup_table, deactivate_lookup_table,
+                extend_lookup_table,
+            },
+            state::{
+                AddressLookupTable, LookupTableStatus, LOOKUP_TABLE_MAX_ADDRESSES,
+                LOOKUP_TABLE_META_SIZE,
+            },
+            AddressLookupTableAccount,
+        },
+        clock::{Epoch, Slot},
         commitment_config::CommitmentConfig,
+        compute_budget::ComputeBudgetInstruction,
         hash::Hash,
         instruction::Instruction,
-        message::Message,
+        message::{v0, CompileError, Message, VersionedMessage},
         native_token::{lamports_to_trz, trz_to_lamports},
+        packet::PACKET_DATA_SIZE,
+        pubkey::PUBKEY_BYTES,
         signature::{unique_signers, Signature, Signer},
+        slot_hashes::{SlotHashes, MAX_ENTRIES},
         stake::{
             instruction::{self as stake_instruction, LockupArgs},
             state::{Authorized, Lockup, StakeAuthorize, StakeStateV2},
         },
-        system_instruction,
-        transaction::Transaction,
+        system_instruction, sysvar,
+        transaction::{Transaction, VersionedTransaction},
     },
     trezoa_transaction_status::TransactionStatus,
     tpl_associated_token_account::get_associated_token_address,
     tpl_token::trezoa_program::program_error::ProgramError,
     std::{
         cmp::{self},
+        collections::{HashMap, HashSet},
         io,
+        ops::Range,
         str::FromStr,
         sync::{
             atomic::{AtomicBool, Ordering},
@@ -66,6 +87,21 @@ pub struct TypedAllocation {
     pub recipient: Pubkey,
     pub amount: u64,
     pub lockup_date: Option<DateTime<Utc>>,
+    pub lockup_epoch: Option<Epoch>,
+    /// Position of the allocation among the tranches of its vesting schedule
+    pub vesting_tranche: Option<usize>,
+}
+
+impl TypedAllocation {
+    /// The part of a stake allocation paid to the recipient's system account.
+    /// An allocation split by a vesting schedule pays it only once, with its
+    /// first tranche.
+    fn unlocked_trz(&self, unlocked_trz: u64) -> u64 {
+        match self.vesting_tranche {
+            None | Some(0) => unlocked_trz,
+            Some(_) => 0,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -122,6 +158,8 @@ pub enum Error {
         input: String,
         err: chrono::ParseError,
     },
+    #[error("Bad input data for vesting schedule: {input}, error: {err}")]
+    BadInputVestingSchedule { input: String, err: VestingError },
     #[error("PickleDb error")]
     PickleDbError(#[from] pickledb::error::Error),
     #[error("Transport error")]
@@ -138,8 +176,26 @@ pub enum Error {
     ProgramError(#[from] ProgramError),
     #[error("Exit signal received")]
     ExitSignal,
+    #[error("Message compile error")]
+    CompileError(#[from] CompileError),
+    #[error("Invalid address lookup table {0}")]
+    InvalidLookupTable(Pubkey),
+    #[error("SlotHashes sysvar unavailable")]
+    SlotHashesUnavailable,
+    #[error("Allocation of {amount} to {recipient} is below the {minimum} required for its stake account")]
+    StakeAllocationTooSmall {
+        recipient: Pubkey,
+        amount: String,
+        minimum: String,
+    },
 }
 
+// Keep extend transactions well within the packet size limit
+const MAX_LOOKUP_TABLE_EXTEND_ADDRESSES: usize = 20;
+
+// The most compute units a transaction may request
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
 fn merge_allocations(allocations: &[TypedAllocation]) -> Vec<TypedAllocation> {
     let mut allocation_map = IndexMap::new();
     for allocation in allocations {
@@ -149,6 +205,8 @@ fn merge_allocations(allocations: &[TypedAllocation]) -> Vec<TypedAllocation> {
                 recipient: allocation.recipient,
                 amount: 0,
                 lockup_date: None,
+                lockup_epoch: None,
+                vesting_tranche: None,
             })
             .amount += allocation.amount;
     }
@@ -159,6 +217,7 @@ fn merge_allocations(allocations: &[TypedAllocation]) -> Vec<TypedAllocation> {
 fn has_same_recipient(allocation: &TypedAllocation, transaction_info: &TransactionInfo) -> bool {
     allocation.recipient == transaction_info.recipient
         && allocation.lockup_date == transaction_info.lockup_date
+        && allocation.lockup_epoch == transaction_info.lockup_epoch
 }
 
 fn apply_previous_transactions(
@@ -223,7 +282,7 @@ fn distribution_instructions(
 
         // Stake args provided, so create a recipient stake account.
         Some(stake_args) => {
-            let unlocked_trz = stake_args.unlocked_trz;
+            let unlocked_trz = allocation.unlocked_trz(stake_args.unlocked_trz);
             let sender_pubkey = args.sender_keypair.pubkey();
             let recipient = allocation.recipient;
 
@@ -239,6 +298,9 @@ fn distribution_instructions(
                     if let Some(lockup_date) = lockup_date {
                         lockup.unix_timestamp = lockup_date.timestamp();
                     }
+                    if let Some(lockup_epoch) = allocation.lockup_epoch {
+                        lockup.epoch = lockup_epoch;
+                    }
                     if let Some(lockup_authority) = stake_args.lockup_authority {
                         lockup.custodian = lockup_authority;
                     }
@@ -294,10 +356,10 @@ fn distribution_instructions(
                     ));
 
                     // Add lockup
-                    if let Some(lockup_date) = lockup_date {
+                    if lockup_date.is_some() || allocation.lockup_epoch.is_some() {
                         let lockup = LockupArgs {
-                            unix_timestamp: Some(lockup_date.timestamp()),
-                            epoch: None,
+                            unix_timestamp: lockup_date.map(|lockup_date| lockup_date.timestamp()),
+                            epoch: allocation.lockup_epoch,
                             custodian: None,
                         };
                         instructions.push(stake_instruction::set_lockup(
@@ -312,24 +374,60 @@ fn distribution_instructions(
             };
 
             // Transfer some unlocked tokens to recipient, which they can use for transaction fees.
-            instructions.push(system_instruction::transfer(
-                &sender_pubkey,
-                &recipient,
-                unlocked_trz,
-            ));
+            if unlocked_trz > 0 {
+                instructions.push(system_instruction::transfer(
+                    &sender_pubkey,
+                    &recipient,
+                    unlocked_trz,
+                ));
+            }
 
             instructions
         }
     }
 }
 
-fn build_messages(
+// The compute unit limit is given per recipient, and scaled by the number of
+// recipients packed into the transaction.
+fn compute_budget_instructions(
+    args: &DistributeTokensArgs,
+    num_allocations: usize,
+) -> Vec<Instruction> {
+    let mut instructions = vec![];
+    if let Some(compute_unit_limit) = args.compute_unit_limit {
+        let num_allocations = u32::try_from(num_allocations).unwrap_or(u32::MAX);
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+            compute_unit_limit
+                .saturating_mul(num_allocations)
+                .min(MAX_COMPUTE_UNIT_LIMIT),
+        ));
+    }
+    if let Some(compute_unit_price) = args.compute_unit_price {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            compute_unit_price,
+        ));
+    }
+    instructions
+}
+
+fn build_message(instructions: &[Instruction], args: &DistributeTokensArgs) -> Message {
+    let mut message_instructions = compute_budget_instructions(args, 1);
+    message_instructions.extend_from_slice(instructions);
+    let fee_payer_pubkey = args.fee_payer.pubkey();
+    Message::new_with_blockhash(
+        &message_instructions,
+        Some(&fee_payer_pubkey),
+        &Hash::default(), // populated by a real blockhash for balance check and submission
+    )
+}
+
+fn build_instructions(
     client: &RpcClient,
     db: &mut PickleDb,
     allocations: &[TypedAllocation],
     args: &DistributeTokensArgs,
     exit: Arc<AtomicBool>,
-    messages: &mut Vec<Message>,
+    instructions: &mut Vec<Vec<Instruction>>,
     stake_extras: &mut StakeExtras,
     created_accounts: &mut u64,
 ) -> Result<(), Error> {
@@ -378,25 +476,42 @@ fn build_messages(
             );
             false
         };
-        let instructions = distribution_instructions(
+        instructions.push(distribution_instructions(
             allocation,
             &new_stake_account_keypair.pubkey(),
             args,
             lockup_date,
             do_create_associated_token_account,
-        );
-        let fee_payer_pubkey = args.fee_payer.pubkey();
-        let message = Message::new_with_blockhash(
-            &instructions,
-            Some(&fee_payer_pubkey),
-            &Hash::default(), // populated by a real blockhash for balance check and submission
-        );
-        messages.push(message);
+        ));
         stake_extras.push((new_stake_account_keypair, lockup_date));
     }
     Ok(())
 }
 
+fn allocation_signers<'a>(
+    allocation: &TypedAllocation,
+    args: &'a DistributeTokensArgs,
+    new_stake_account_keypair: &'a Keypair,
+) -> Result<Vec<&'a dyn Signer>, Error> {
+    let mut signers = vec![&*args.fee_payer, &*args.sender_keypair];
+    if let Some(stake_args) = &args.stake_args {
+        signers.push(new_stake_account_keypair);
+        if let Some(sender_stake_args) = &stake_args.sender_stake_args {
+            signers.push(&*sender_stake_args.stake_authority);
+            signers.push(&*sender_stake_args.withdraw_authority);
+            signers.push(new_stake_account_keypair);
+            if allocation.lockup_date.is_some() || allocation.lockup_epoch.is_some() {
+                if let Some(lockup_authority) = &sender_stake_args.lockup_authority {
+                    signers.push(&**lockup_authority);
+                } else {
+                    return Err(Error::MissingLockupAuthority);
+                }
+            }
+        }
+    }
+    Ok(signers)
+}
+
 fn send_messages(
     client: &RpcClient,
     db: &mut PickleDb,
@@ -415,23 +530,11 @@ fn send_messages(
         }
         let new_stake_account_address = new_stake_account_keypair.pubkey();
 
-        let mut signers = vec![&*args.fee_payer, &*args.sender_keypair];
-        if let Some(stake_args) = &args.stake_args {
-            signers.push(&new_stake_account_keypair);
-            if let Some(sender_stake_args) = &stake_args.sender_stake_args {
-                signers.push(&*sender_stake_args.stake_authority);
-                signers.push(&*sender_stake_args.withdraw_authority);
-                signers.push(&new_stake_account_keypair);
-                if allocation.lockup_date.is_some() {
-                    if let Some(lockup_authority) = &sender_stake_args.lockup_authority {
-                        signers.push(&**lockup_authority);
-                    } else {
-                        return Err(Error::MissingLockupAuthority);
-                    }
-                }
-            }
-        }
-        let signers = unique_signers(signers);
+        let signers = unique_signers(allocation_signers(
+            allocation,
+            args,
+            &new_stake_account_keypair,
+        )?);
         let result: ClientResult<(Transaction, u64)> = {
             if args.dry_run {
                 Ok((Transaction::new_unsigned(message), std::u64::MAX))
@@ -460,6 +563,7 @@ fn send_messages(
                     false,
                     last_valid_block_height,
                     lockup_date,
+                    allocation.lockup_epoch,
                 )?;
             }
             Err(e) => {
@@ -470,6 +574,449 @@ fn send_messages(
     Ok(())
 }
 
+// Return the addresses that `instructions` could load from a lookup table,
+// which excludes signers and invoked programs.
+fn lookup_table_addresses(instructions: &[Instruction]) -> IndexSet<Pubkey> {
+    let static_addresses: HashSet<Pubkey> = instructions
+        .iter()
+        .flat_map(|instruction| {
+            instruction
+                .accounts
+                .iter()
+                .filter(|account_meta| account_meta.is_signer)
+                .map(|account_meta| account_meta.pubkey)
+                .chain(std::iter::once(instruction.program_id))
+        })
+        .collect();
+    instructions
+        .iter()
+        .flat_map(|instruction| &instruction.accounts)
+        .map(|account_meta| account_meta.pubkey)
+        .filter(|address| !static_addresses.contains(address))
+        .collect()
+}
+
+// The addresses of a group of allocations to store in its lookup table
+fn group_lookup_table_addresses(instructions: &[Vec<Instruction>]) -> Vec<Pubkey> {
+    instructions
+        .iter()
+        .flat_map(|allocation_instructions| lookup_table_addresses(allocation_instructions))
+        .collect::<IndexSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+// Split the allocations into consecutive groups whose addresses fit in a
+// single lookup table.
+fn lookup_table_groups(instructions: &[Vec<Instruction>]) -> Vec<Range<usize>> {
+    let mut groups = vec![];
+    let mut start = 0;
+    let mut addresses = IndexSet::new();
+    for (i, allocation_instructions) in instructions.iter().enumerate() {
+        let allocation_addresses = lookup_table_addresses(allocation_instructions);
+        if i > start && addresses.union(&allocation_addresses).count() > LOOKUP_TABLE_MAX_ADDRESSES
+        {
+            groups.push(start..i);
+            start = i;
+            addresses.clear();
+        }
+        addresses.extend(allocation_addresses);
+    }
+    if start < instructions.len() {
+        groups.push(start..instructions.len());
+    }
+    groups
+}
+
+fn compile_batch(
+    instructions: &[Vec<Instruction>],
+    lookup_table: &AddressLookupTableAccount,
+    args: &DistributeTokensArgs,
+    blockhash: Hash,
+) -> Result<v0::Message, CompileError> {
+    let mut batch_instructions = compute_budget_instructions(args, instructions.len());
+    batch_instructions.extend(instructions.iter().flatten().cloned());
+    v0::Message::try_compile(
+        &args.fee_payer.pubkey(),
+        &batch_instructions,
+        std::slice::from_ref(lookup_table),
+        blockhash,
+    )
+}
+
+fn batched_transaction_size(message: v0::Message) -> usize {
+    let num_signatures = message.header.num_required_signatures as usize;
+    // The signature count is a compact-u16, which is a single byte for
+    // anything that fits in a packet
+    1 + num_signatures * std::mem::size_of::<Signature>()
+        + VersionedMessage::V0(message).serialize().len()
+}
+
+// Pack consecutive allocations into batches of at most `batch_size`
+// recipients whose transaction still fits in a packet.
+fn pack_batches(
+    instructions: &[Vec<Instruction>],
+    lookup_table: &AddressLookupTableAccount,
+    args: &DistributeTokensArgs,
+    batch_size: usize,
+) -> Result<Vec<Range<usize>>, Error> {
+    let mut batches = vec![];
+    let mut start = 0;
+    for end in 1..=instructions.len() {
+        let batch_len = end - start;
+        if batch_len > 1
+            && (batch_len > batch_size
+                || batched_transaction_size(compile_batch(
+                    &instructions[start..end],
+                    lookup_table,
+                    args,
+                    Hash::default(),
+                )?) > PACKET_DATA_SIZE)
+        {
+            batches.push(start..end - 1);
+            start = end - 1;
+        }
+    }
+    if start < instructions.len() {
+        batches.push(start..instructions.len());
+    }
+    Ok(batches)
+}
+
+fn fee_payer_message(
+    fee_payer: &Pubkey,
+    compute_unit_price: Option<u64>,
+    instructions: &[Instruction],
+) -> Message {
+    let mut message_instructions = vec![];
+    if let Some(compute_unit_price) = compute_unit_price {
+        message_instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            compute_unit_price,
+        ));
+    }
+    message_instructions.extend_from_slice(instructions);
+    Message::new(&message_instructions, Some(fee_payer))
+}
+
+fn send_and_confirm_fee_payer_instructions(
+    client: &RpcClient,
+    fee_payer: &dyn Signer,
+    compute_unit_price: Option<u64>,
+    instructions: &[Instruction],
+) -> Result<Signature, Error> {
+    let message = fee_payer_message(&fee_payer.pubkey(), compute_unit_price, instructions);
+    let blockhash = client.get_latest_blockhash()?;
+    let transaction = Transaction::new(&[fee_payer], message, blockhash);
+    Ok(client.send_and_confirm_transaction_with_spinner(&transaction)?)
+}
+
+fn create_distribution_lookup_table(
+    client: &RpcClient,
+    db: &mut PickleDb,
+    args: &DistributeTokensArgs,
+    addresses: Vec<Pubkey>,
+) -> Result<AddressLookupTableAccount, Error> {
+    let authority = args.fee_payer.pubkey();
+    // The table address is derived from a recent slot, so tables created in
+    // the same slot would collide. Wait for a fresh slot until the derived
+    // address is unused.
+    let (create_instruction, lookup_table_address) = loop {
+        let recent_slot = client.get_slot_with_commitment(CommitmentConfig::processed())?;
+        let (create_instruction, lookup_table_address) =
+            create_lookup_table(authority, authority, recent_slot);
+        let collides = db::read_lookup_tables(db).contains(&lookup_table_address)
+            || client
+                .get_account_with_commitment(&lookup_table_address, CommitmentConfig::processed())?
+                .value
+                .is_some();
+        if !collides {
+            break (create_instruction, lookup_table_address);
+        }
+        sleep(Duration::from_millis(100));
+    };
+    db::set_lookup_table(db, &lookup_table_address)?;
+    db.dump()?;
+
+    println!(
+        "{} {} ({} addresses)",
+        style("Creating lookup table").bold(),
+        lookup_table_address,
+        addresses.len(),
+    );
+    send_and_confirm_fee_payer_instructions(
+        client,
+        &*args.fee_payer,
+        args.compute_unit_price,
+        &[create_instruction],
+    )?;
+    for chunk in addresses.chunks(MAX_LOOKUP_TABLE_EXTEND_ADDRESSES) {
+        let extend_instruction = extend_lookup_table(
+            lookup_table_address,
+            authority,
+            Some(authority),
+            chunk.to_vec(),
+        );
+        send_and_confirm_fee_payer_instructions(
+            client,
+            &*args.fee_payer,
+            args.compute_unit_price,
+            &[extend_instruction],
+        )?;
+    }
+
+    // Addresses can only be loaded from a table after the slot that added them
+    let extended_slot = client.get_slot()?;
+    while client.get_slot()? <= extended_slot {
+        sleep(Duration::from_millis(100));
+    }
+
+    Ok(AddressLookupTableAccount {
+        key: lookup_table_address,
+        addresses,
+    })
+}
+
+// The rent and fees the fee payer needs for the lookup tables of a batched
+// distribution. The rent is refunded when the tables are closed, but only once
+// the whole distribution is finalized, so every table has to be funded.
+fn lookup_table_cost(
+    instructions: &[Vec<Instruction>],
+    client: &RpcClient,
+    args: &DistributeTokensArgs,
+) -> Result<u64, Error> {
+    if args.batch_size.is_none() || args.dry_run {
+        return Ok(0);
+    }
+    let mut rent: u64 = 0;
+    let mut num_transactions: u64 = 0;
+    for group in lookup_table_groups(instructions) {
+        let num_addresses = group_lookup_table_addresses(&instructions[group]).len();
+        let table_rent = client.get_minimum_balance_for_rent_exemption(
+            LOOKUP_TABLE_META_SIZE + num_addresses * PUBKEY_BYTES,
+        )?;
+        rent = rent
+            .checked_add(table_rent)
+            .ok_or(Error::FeeEstimationError)?;
+        // Create, extend, deactivate and close the table
+        num_transactions += 3 + num_addresses.div_ceil(MAX_LOOKUP_TABLE_EXTEND_ADDRESSES) as u64;
+    }
+
+    let authority = args.fee_payer.pubkey();
+    let (create_instruction, _) = create_lookup_table(authority, authority, 0);
+    let mut message = fee_payer_message(&authority, args.compute_unit_price, &[create_instruction]);
+    message.recent_blockhash = client.get_latest_blockhash()?;
+    let fees = client
+        .get_fee_for_message(&message)?
+        .checked_mul(num_transactions)
+        .ok_or(Error::FeeEstimationError)?;
+    rent.checked_add(fees).ok_or(Error::FeeEstimationError)
+}
+
+fn send_batches(
+    client: &RpcClient,
+    db: &mut PickleDb,
+    allocations: &[TypedAllocation],
+    args: &DistributeTokensArgs,
+    exit: Arc<AtomicBool>,
+    instructions: Vec<Vec<Instruction>>,
+    stake_extras: StakeExtras,
+) -> Result<(), Error> {
+    let batch_size = args.batch_size.expect("batch_size must be some");
+    for group in lookup_table_groups(&instructions) {
+        if exit.load(Ordering::SeqCst) {
+            db.dump()?;
+            return Err(Error::ExitSignal);
+        }
+        let addresses = group_lookup_table_addresses(&instructions[group.clone()]);
+        let lookup_table = if args.dry_run {
+            AddressLookupTableAccount {
+                key: Pubkey::default(),
+                addresses,
+            }
+        } else {
+            create_distribution_lookup_table(client, db, args, addresses)?
+        };
+
+        for batch in pack_batches(
+            &instructions[group.clone()],
+            &lookup_table,
+            args,
+            batch_size,
+        )? {
+            if exit.load(Ordering::SeqCst) {
+                db.dump()?;
+                return Err(Error::ExitSignal);
+            }
+            let batch = group.start + batch.start..group.start + batch.end;
+
+            let mut signers = vec![];
+            for i in batch.clone() {
+                signers.extend(allocation_signers(
+                    &allocations[i],
+                    args,
+                    &stake_extras[i].0,
+                )?);
+            }
+            let signers = unique_signers(signers);
+            let result: ClientResult<(VersionedTransaction, u64)> = {
+                if args.dry_run {
+                    let message = compile_batch(
+                        &instructions[batch.clone()],
+                        &lookup_table,
+                        args,
+                        Hash::default(),
+                    )?;
+                    let num_signatures = message.header.num_required_signatures as usize;
+                    let transaction = VersionedTransaction {
+                        signatures: vec![Signature::default(); num_signatures],
+                        message: VersionedMessage::V0(message),
+                    };
+                    Ok((transaction, std::u64::MAX))
+                } else {
+                    let (blockhash, last_valid_block_height) =
+                        client.get_latest_blockhash_with_commitment(CommitmentConfig::default())?;
+                    let message = compile_batch(
+                        &instructions[batch.clone()],
+                        &lookup_table,
+                        args,
+                        blockhash,
+                    )?;
+                    let transaction =
+                        VersionedTransaction::try_new(VersionedMessage::V0(message), &signers)
+                            .map_err(ClientError::from)?;
+                    let config = RpcSendTransactionConfig {
+                        skip_preflight: true,
+                        ..RpcSendTransactionConfig::default()
+                    };
+                    client.send_transaction_with_config(&transaction, config)?;
+                    Ok((transaction, last_valid_block_height))
+                }
+            };
+            match result {
+                Ok((transaction, last_valid_block_height)) => {
+                    for (batch_index, i) in batch.enumerate() {
+                        let allocation = &allocations[i];
+                        let (new_stake_account_keypair, lockup_date) = &stake_extras[i];
+                        let new_stake_account_address = new_stake_account_keypair.pubkey();
+                        let new_stake_account_address_option =
+                            args.stake_args.as_ref().map(|_| &new_stake_account_address);
+                        db::set_batched_transaction_info(
+                            db,
+                            &allocation.recipient,
+                            allocation.amount,
+                            &transaction,
+                            batch_index,
+                            new_stake_account_address_option,
+                            last_valid_block_height,
+                            *lockup_date,
+                            allocation.lockup_epoch,
+                        )?;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error sending batch of {} transfers: {}", batch.len(), e);
+                }
+            };
+        }
+    }
+    Ok(())
+}
+
+// Deactivate the lookup tables created by this and any earlier runs, and close
+// the ones that are no longer usable by in-flight transactions. Deactivation
+// takes about as many slots as the SlotHashes sysvar holds, so unless `wait` is
+// set, tables that are still deactivating are left to `close-lookup-tables`.
+fn close_lookup_tables(
+    client: &RpcClient,
+    db: &mut PickleDb,
+    fee_payer: &dyn Signer,
+    compute_unit_price: Option<u64>,
+    wait: bool,
+    exit: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    if db::read_lookup_tables(db).is_empty() {
+        return Ok(());
+    }
+    let authority = fee_payer.pubkey();
+    let progress_bar = new_spinner_progress_bar();
+
+    loop {
+        let lookup_tables = db::read_lookup_tables(db);
+        if lookup_tables.is_empty() {
+            break;
+        }
+
+        let current_slot = client.get_slot()?;
+        let slot_hashes_account = client.get_account(&sysvar::slot_hashes::id())?;
+        let slot_hashes: SlotHashes =
+            from_account(&slot_hashes_account).ok_or(Error::SlotHashesUnavailable)?;
+
+        let mut remaining_blocks = 0;
+        for lookup_table_address in lookup_tables {
+            let Some(account) = client
+                .get_account_with_commitment(&lookup_table_address, client.commitment())?
+                .value
+            else {
+                // Never created, or closed by an earlier run
+                db::remove_lookup_table(db, &lookup_table_address)?;
+                continue;
+            };
+            let lookup_table = AddressLookupTable::deserialize(&account.data)
+                .map_err(|_| Error::InvalidLookupTable(lookup_table_address))?;
+            match lookup_table.meta.status(current_slot, &slot_hashes) {
+                LookupTableStatus::Activated => {
+                    send_and_confirm_fee_payer_instructions(
+                        client,
+                        fee_payer,
+                        compute_unit_price,
+                        &[deactivate_lookup_table(lookup_table_address, authority)],
+                    )?;
+                    remaining_blocks = cmp::max(remaining_blocks, MAX_ENTRIES + 1);
+                }
+                LookupTableStatus::Deactivating {
+                    remaining_blocks: blocks,
+                } => {
+                    remaining_blocks = cmp::max(remaining_blocks, blocks);
+                }
+                LookupTableStatus::Deactivated => {
+                    send_and_confirm_fee_payer_instructions(
+                        client,
+                        fee_payer,
+                        compute_unit_price,
+                        &[close_lookup_table(
+                            lookup_table_address,
+                            authority,
+                            authority,
+                        )],
+                    )?;
+                    db::remove_lookup_table(db, &lookup_table_address)?;
+                }
+            }
+        }
+        db.dump()?;
+
+        if remaining_blocks > 0 {
+            if !wait {
+                progress_bar.finish_and_clear();
+                println!(
+                    "Lookup tables are deactivating, run `trezoa-tokens close-lookup-tables` \
+                    in about {remaining_blocks} blocks to reclaim their rent"
+                );
+                return Ok(());
+            }
+            progress_bar.set_message(format!(
+                "[{remaining_blocks} blocks] Waiting for lookup tables to deactivate"
+            ));
+            sleep(Duration::from_millis(500));
+        }
+        if exit.load(Ordering::SeqCst) {
+            return Err(Error::ExitSignal);
+        }
+    }
+    progress_bar.finish_and_clear();
+    Ok(())
+}
+
 fn distribute_allocations(
     client: &RpcClient,
     db: &mut PickleDb,
@@ -477,28 +1024,56 @@ fn distribute_allocations(
     args: &DistributeTokensArgs,
     exit: Arc<AtomicBool>,
 ) -> Result<(), Error> {
-    let mut messages: Vec<Message> = vec![];
+    let mut instructions: Vec<Vec<Instruction>> = vec![];
     let mut stake_extras: StakeExtras = vec![];
     let mut created_accounts = 0;
 
-    build_messages(
+    check_stake_allocations(allocations, client, args)?;
+    build_instructions(
         client,
         db,
         allocations,
         args,
         exit.clone(),
-        &mut messages,
+        &mut instructions,
         &mut stake_extras,
         &mut created_accounts,
     )?;
 
+    // Fees are estimated as if every recipient was paid in its own
+    // transaction, which is an upper bound for batched distributions.
+    let messages: Vec<Message> = instructions
+        .iter()
+        .map(|instructions| build_message(instructions, args))
+        .collect();
+    let lookup_table_cost = lookup_table_cost(&instructions, client, args)?;
+
     if args.tpl_token_args.is_some() {
-        check_tpl_token_balances(&messages, allocations, client, args, created_accounts)?;
+        check_tpl_token_balances(
+            &messages,
+            allocations,
+            client,
+            args,
+            created_accounts,
+            lookup_table_cost,
+        )?;
     } else {
-        check_payer_balances(&messages, allocations, client, args)?;
+        check_payer_balances(&messages, allocations, client, args, lookup_table_cost)?;
     }
 
-    send_messages(client, db, allocations, args, exit, messages, stake_extras)?;
+    if args.batch_size.is_some() {
+        send_batches(
+            client,
+            db,
+            allocations,
+            args,
+            exit,
+            instructions,
+            stake_extras,
+        )?;
+    } else {
+        send_messages(client, db, allocations, args, exit, messages, stake_extras)?;
+    }
 
     db.dump()?;
     Ok(())
@@ -525,38 +1100,74 @@ fn read_allocations(
                     recipient,
                     amount,
                     lockup_date: None,
+                    lockup_epoch: None,
+                    vesting_tranche: None,
                 })
             })
             .collect::<Result<Vec<TypedAllocation>, Error>>()?
     } else if with_lockup {
         // We only support TRZ token in "require lockup" mode.
-        rdr.deserialize()
-            .map(|recipient| {
-                let (recipient, amount, lockup_date): (String, f64, String) = recipient?;
-                let recipient =
-                    Pubkey::from_str(&recipient).map_err(|err| Error::BadInputPubkeyError {
-                        input: recipient,
+        let mut allocations = vec![];
+        for recipient in rdr.deserialize() {
+            // The lockup epoch and vesting schedule columns are optional
+            let (recipient, amount, lockup_date, lockup_epoch, vesting_schedule): (
+                String,
+                f64,
+                String,
+                Option<Epoch>,
+                Option<String>,
+            ) = recipient?;
+            let recipient =
+                Pubkey::from_str(&recipient).map_err(|err| Error::BadInputPubkeyError {
+                    input: recipient,
+                    err,
+                })?;
+            let lockup_date = if !lockup_date.is_empty() {
+                let lockup_date = lockup_date.parse::<DateTime<Utc>>().map_err(|err| {
+                    Error::BadInputLockupDate {
+                        input: lockup_date,
                         err,
-                    })?;
-                let lockup_date = if !lockup_date.is_empty() {
-                    let lockup_date = lockup_date.parse::<DateTime<Utc>>().map_err(|err| {
-                        Error::BadInputLockupDate {
-                            input: lockup_date,
-                            err,
-                        }
-                    })?;
-                    Some(lockup_date)
-                } else {
-                    // empty lockup date means no lockup, it's okay to have only some lockups specified
-                    None
-                };
-                Ok(TypedAllocation {
+                    }
+                })?;
+                Some(lockup_date)
+            } else {
+                // empty lockup date means no lockup, it's okay to have only some lockups specified
+                None
+            };
+            let amount = trz_to_lamports(amount);
+            match vesting_schedule.filter(|schedule| !schedule.is_empty()) {
+                None => allocations.push(TypedAllocation {
                     recipient,
-                    amount: trz_to_lamports(amount),
+                    amount,
                     lockup_date,
-                })
-            })
-            .collect::<Result<Vec<TypedAllocation>, Error>>()?
+                    lockup_epoch,
+                    vesting_tranche: None,
+                }),
+                // Each tranche becomes its own allocation, locked until it vests.
+                Some(schedule) => {
+                    let tranches = schedule
+                        .parse::<VestingSchedule>()
+                        .and_then(|vesting_schedule| {
+                            let start = lockup_date.ok_or(VestingError::MissingStartDate)?;
+                            vesting_schedule.tranches(start, amount)
+                        })
+                        .map_err(|err| Error::BadInputVestingSchedule {
+                            input: schedule,
+                            err,
+                        })?;
+                    allocations.extend(tranches.into_iter().enumerate().map(
+                        |(i, (unlock_date, amount))| TypedAllocation {
+                            recipient,
+                            amount,
+                            lockup_date: Some(unlock_date),
+                            lockup_epoch,
+                            vesting_tranche: Some(i),
+                        },
+                    ));
+                }
+            }
+        }
+        allocations
     } else if raw_amount {
         rdr.deserialize()
             .map(|recipient| {
@@ -570,6 +1181,8 @@ fn read_allocations(
                     recipient,
                     amount,
                     lockup_date: None,
+                    lockup_epoch: None,
+                    vesting_tranche: None,
                 })
             })
             .collect::<Result<Vec<TypedAllocation>, Error>>()?
@@ -586,6 +1199,8 @@ fn read_allocations(
                     recipient,
                     amount: trz_to_lamports(amount),
                     lockup_date: None,
+                    lockup_epoch: None,
+                    vesting_tranche: None,
                 })
             })
             .collect::<Result<Vec<TypedAllocation>, Error>>()?
@@ -641,6 +1256,16 @@ pub fn process_allocations(
     apply_previous_transactions(&mut allocations, &transaction_infos);
 
     if allocations.is_empty() {
+        if !args.dry_run {
+            close_lookup_tables(
+                client,
+                &mut db,
+                &*args.fee_payer,
+                args.compute_unit_price,
+                false,
+                exit,
+            )?;
+        }
         eprintln!("No work to do");
         return Ok(confirmations);
     }
@@ -678,7 +1303,18 @@ pub fn process_allocations(
 
     distribute_allocations(client, &mut db, &allocations, args, exit.clone())?;
 
-    let opt_confirmations = finalize_transactions(client, &mut db, args.dry_run, exit)?;
+    let opt_confirmations = finalize_transactions(client, &mut db, args.dry_run, exit.clone())?;
+
+    if !args.dry_run {
+        close_lookup_tables(
+            client,
+            &mut db,
+            &*args.fee_payer,
+            args.compute_unit_price,
+            false,
+            exit,
+        )?;
+    }
 
     if !args.dry_run {
         if let Some(output_path) = &args.output_path {
@@ -728,19 +1364,20 @@ fn update_finalized_transactions(
     exit: Arc<AtomicBool>,
 ) -> Result<Option<usize>, Error> {
     let transaction_infos = db::read_transaction_infos(db);
-    let unconfirmed_transactions: Vec<_> = transaction_infos
+    // Recipients of a batched transaction share its signature
+    let unconfirmed_transactions: IndexMap<Signature, Slot> = transaction_infos
         .iter()
         .filter_map(|info| {
             if info.finalized_date.is_some() {
                 None
             } else {
-                Some((&info.transaction, info.last_valid_block_height))
+                Some((info.transaction.signatures[0], info.last_valid_block_height))
             }
         })
         .collect();
     let unconfirmed_signatures: Vec<_> = unconfirmed_transactions
-        .iter()
-        .map(|(tx, _slot)| tx.signatures[0])
+        .keys()
+        .copied()
         .filter(|sig| *sig != Signature::default()) // Filter out dry-run signatures
         .collect();
     let mut statuses = vec![];
@@ -760,7 +1397,7 @@ fn update_finalized_transactions(
         client,
         db,
         exit,
-        unconfirmed_transactions,
+        unconfirmed_transactions.into_iter().collect(),
         statuses,
         &mut confirmations,
     )?;
@@ -772,18 +1409,18 @@ fn log_transaction_confirmations(
     client: &RpcClient,
     db: &mut PickleDb,
     exit: Arc<AtomicBool>,
-    unconfirmed_transactions: Vec<(&Transaction, Slot)>,
+    unconfirmed_transactions: Vec<(Signature, Slot)>,
     statuses: Vec<Option<TransactionStatus>>,
     confirmations: &mut Option<usize>,
 ) -> Result<(), Error> {
     let finalized_block_height = client.get_block_height()?;
-    for ((transaction, last_valid_block_height), opt_transaction_status) in unconfirmed_transactions
+    for ((signature, last_valid_block_height), opt_transaction_status) in unconfirmed_transactions
         .into_iter()
         .zip(statuses.into_iter())
     {
         match db::update_finalized_transaction(
             db,
-            &transaction.signatures[0],
+            &signature,
             opt_transaction_status,
             last_valid_block_height,
             finalized_block_height,
@@ -817,17 +1454,55 @@ pub fn get_fee_estimate_for_messages(
     Ok(fee_estimate)
 }
 
+// Every stake account must be left with at least the rent-exempt reserve and
+// the minimum delegation once the unlocked TRZ is taken out of its allocation.
+fn check_stake_allocations(
+    allocations: &[TypedAllocation],
+    client: &RpcClient,
+    args: &DistributeTokensArgs,
+) -> Result<(), Error> {
+    let Some(stake_args) = &args.stake_args else {
+        return Ok(());
+    };
+    let rent_exempt_reserve = match &stake_args.sender_stake_args {
+        Some(sender_stake_args) => sender_stake_args
+            .rent_exempt_reserve
+            .expect("SenderStakeArgs.rent_exempt_reserve should be populated"),
+        None => client.get_minimum_balance_for_rent_exemption(StakeStateV2::size_of())?,
+    };
+    let minimum_stake = rent_exempt_reserve.saturating_add(client.get_stake_minimum_delegation()?);
+    for allocation in allocations {
+        let minimum = allocation
+            .unlocked_trz(stake_args.unlocked_trz)
+            .saturating_add(minimum_stake);
+        if allocation.amount < minimum {
+            return Err(Error::StakeAllocationTooSmall {
+                recipient: allocation.recipient,
+                amount: lamports_to_trz(allocation.amount).to_string(),
+                minimum: lamports_to_trz(minimum).to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
 fn check_payer_balances(
     messages: &[Message],
     allocations: &[TypedAllocation],
     client: &RpcClient,
     args: &DistributeTokensArgs,
+    lookup_table_cost: u64,
 ) -> Result<(), Error> {
     let mut undistributed_tokens: u64 = allocations.iter().map(|x| x.amount).sum();
-    let fees = get_fee_estimate_for_messages(messages, client)?;
+    let fees = get_fee_estimate_for_messages(messages, client)?
+        .checked_add(lookup_table_cost)
+        .ok_or(Error::FeeEstimationError)?;
 
     let (distribution_source, unlocked_trz_source) = if let Some(stake_args) = &args.stake_args {
-        let total_unlocked_trz = allocations.len() as u64 * stake_args.unlocked_trz;
+        let total_unlocked_trz: u64 = allocations
+            .iter()
+            .map(|allocation| allocation.unlocked_trz(stake_args.unlocked_trz))
+            .sum();
         undistributed_tokens -= total_unlocked_trz;
         let from_pubkey = if let Some(sender_stake_args) = &stake_args.sender_stake_args {
             sender_stake_args.stake_account_address
@@ -947,12 +1622,213 @@ pub fn process_balances(
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StakeReportStatus {
+    Ok,
+    NotDistributed,
+    Unfinalized,
+    MissingAccount,
+    NotAStakeAccount,
+    AuthorityMismatch,
+    LockupMismatch,
+    BalanceMismatch,
+}
+
+impl std::fmt::Display for StakeReportStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            Self::Ok => "ok",
+            Self::NotDistributed => "not distributed",
+            Self::Unfinalized => "unfinalized",
+            Self::MissingAccount => "stake account missing",
+            Self::NotAStakeAccount => "not a stake account",
+            Self::AuthorityMismatch => "authority mismatch",
+            Self::LockupMismatch => "lockup mismatch",
+            Self::BalanceMismatch => "balance below allocation",
+        };
+        write!(f, "{status}")
+    }
+}
+
+// Compare a distributed stake account against the allocation it was created
+// for. The balance is only checked while the lockup is in force, since the
+// recipient may have withdrawn from it since.
+fn reconcile_stake_account(
+    allocation: &TypedAllocation,
+    transaction_info: Option<&TransactionInfo>,
+    account: Option<&Account>,
+    unlocked_trz: u64,
+    now: DateTime<Utc>,
+    current_epoch: Epoch,
+) -> StakeReportStatus {
+    let Some(transaction_info) = transaction_info else {
+        return StakeReportStatus::NotDistributed;
+    };
+    if transaction_info.finalized_date.is_none() {
+        return StakeReportStatus::Unfinalized;
+    }
+    let Some(account) = account else {
+        return StakeReportStatus::MissingAccount;
+    };
+    let Some(meta) = StateMut::<StakeStateV2>::state(account)
+        .ok()
+        .and_then(|stake_state| stake_state.meta())
+    else {
+        return StakeReportStatus::NotAStakeAccount;
+    };
+
+    if meta.authorized.staker != allocation.recipient
+        || meta.authorized.withdrawer != allocation.recipient
+    {
+        return StakeReportStatus::AuthorityMismatch;
+    }
+    let lockup_date_matches = allocation.lockup_date.map_or(true, |lockup_date| {
+        meta.lockup.unix_timestamp == lockup_date.timestamp()
+    });
+    let lockup_epoch_matches = allocation
+        .lockup_epoch
+        .map_or(true, |lockup_epoch| meta.lockup.epoch == lockup_epoch);
+    if !lockup_date_matches || !lockup_epoch_matches {
+        return StakeReportStatus::LockupMismatch;
+    }
+    let lockup_in_force =
+        meta.lockup.unix_timestamp > now.timestamp() || meta.lockup.epoch > current_epoch;
+    if lockup_in_force && account.lamports < allocation.amount.saturating_sub(unlocked_trz) {
+        return StakeReportStatus::BalanceMismatch;
+    }
+    StakeReportStatus::Ok
+}
+
+pub fn process_stake_report(
+    client: &RpcClient,
+    args: &StakeReportArgs,
+    exit: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let allocations = read_allocations(&args.input_csv, None, true, false)?;
+    let db = db::open_db(&args.transaction_db, true)?;
+    let transaction_infos = db::read_transaction_infos(&db);
+
+    // Pair every allocation with the transaction that distributed it
+    let mut matched = vec![false; transaction_infos.len()];
+    let allocation_infos: Vec<Option<&TransactionInfo>> = allocations
+        .iter()
+        .map(|allocation| {
+            let (i, transaction_info) =
+                transaction_infos
+                    .iter()
+                    .enumerate()
+                    .find(|(i, transaction_info)| {
+                        !matched[*i]
+                            && transaction_info.amount == allocation.amount
+                            && has_same_recipient(allocation, transaction_info)
+                    })?;
+            matched[i] = true;
+            Some(transaction_info)
+        })
+        .collect();
+
+    let stake_account_addresses: Vec<Pubkey> = allocation_infos
+        .iter()
+        .flatten()
+        .filter_map(|transaction_info| transaction_info.new_stake_account_address)
+        .collect();
+    let mut stake_accounts = HashMap::new();
+    for addresses in stake_account_addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        if exit.load(Ordering::SeqCst) {
+            return Err(Error::ExitSignal);
+        }
+        let accounts = client.get_multiple_accounts(addresses)?;
+        stake_accounts.extend(
+            addresses
+                .iter()
+                .zip(accounts)
+                .filter_map(|(address, account)| Some((*address, account?))),
+        );
+    }
+
+    let now = Utc::now();
+    let current_epoch = client.get_epoch_info()?.epoch;
+    println!(
+        "{}",
+        style(format!(
+            "{:<44}  {:<25}  {:<44}  {:>20}  {:>20}  {}",
+            "Recipient", "Unlocks", "Stake Account", "Expected Balance", "Actual Balance", "Status"
+        ))
+        .bold()
+    );
+    let mut mismatches = 0;
+    for (allocation, transaction_info) in allocations.iter().zip(allocation_infos) {
+        let stake_account_address = transaction_info
+            .and_then(|transaction_info| transaction_info.new_stake_account_address);
+        let account = stake_account_address.and_then(|address| stake_accounts.get(&address));
+        let status = reconcile_stake_account(
+            allocation,
+            transaction_info,
+            account,
+            allocation.unlocked_trz(args.unlocked_trz),
+            now,
+            current_epoch,
+        );
+        if status != StakeReportStatus::Ok {
+            mismatches += 1;
+        }
+        let unlocks = match (allocation.lockup_date, allocation.lockup_epoch) {
+            (Some(lockup_date), Some(lockup_epoch)) => {
+                format!("{} epoch {lockup_epoch}", lockup_date.format("%Y-%m-%d"))
+            }
+            (Some(lockup_date), None) => lockup_date.format("%Y-%m-%d %H:%M:%S").to_string(),
+            (None, Some(lockup_epoch)) => format!("epoch {lockup_epoch}"),
+            (None, None) => "-".to_string(),
+        };
+        println!(
+            "{:<44}  {:<25}  {:<44}  {:>20.9}  {:>20}  {}",
+            allocation.recipient,
+            unlocks,
+            stake_account_address
+                .map(|address| address.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            lamports_to_trz(
+                allocation
+                    .amount
+                    .saturating_sub(allocation.unlocked_trz(args.unlocked_trz)),
+            ),
+            account
+                .map(|account| format!("{:.9}", lamports_to_trz(account.lamports)))
+                .unwrap_or_else(|| "-".to_string()),
+            status,
+        );
+    }
+    println!(
+        "{} {} of {} stake accounts",
+        style("Reconciled:").bold(),
+        allocations.len() - mismatches,
+        allocations.len(),
+    );
+    Ok(())
+}
+
 pub fn process_transaction_log(args: &TransactionLogArgs) -> Result<(), Error> {
     let db = db::open_db(&args.transaction_db, true)?;
     db::write_transaction_log(&db, &args.output_path)?;
     Ok(())
 }
 
+pub fn process_close_lookup_tables(
+    client: &RpcClient,
+    args: &CloseLookupTablesArgs,
+    exit: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let mut db = db::open_db(&args.transaction_db, false)?;
+    close_lookup_tables(
+        client,
+        &mut db,
+        &*args.fee_payer,
+        args.compute_unit_price,
+        true,
+        exit,
+    )
+}
+
 use {
     crate::db::check_output_file,
     trezoa_sdk::{
@@ -1022,6 +1898,9 @@ pub fn test_process_distribute_tokens_with_client(
         stake_args: None,
         tpl_token_args: None,
         transfer_amount,
+        batch_size: None,
+        compute_unit_price: None,
+        compute_unit_limit: None,
     };
     let confirmations = process_allocations(client, &args, exit.clone()).unwrap();
     assert_eq!(confirmations, None);
@@ -1129,6 +2008,9 @@ pub fn test_process_create_stake_with_client(client: &RpcClient, sender_keypair:
         tpl_token_args: None,
         sender_keypair: Box::new(sender_keypair),
         transfer_amount: None,
+        batch_size: None,
+        compute_unit_price: None,
+        compute_unit_limit: None,
     };
     let confirmations = process_allocations(client, &args, exit.clone()).unwrap();
     assert_eq!(confirmations, None);
@@ -1261,6 +2143,9 @@ pub fn test_process_distribute_stake_with_client(client: &RpcClient, sender_keyp
         tpl_token_args: None,
         sender_keypair: Box::new(sender_keypair),
         transfer_amount: None,
+        batch_size: None,
+        compute_unit_price: None,
+        compute_unit_limit: None,
     };
     let confirmations = process_allocations(client, &args, exit.clone()).unwrap();
     assert_eq!(confirmations, None);
@@ -1308,12 +2193,14 @@ mod tests {
     use {
         super::*,
         trezoa_sdk::{
+            fee_calculator::FeeRateGovernor,
             instruction::AccountMeta,
+            rent::Rent,
             signature::{read_keypair_file, write_keypair_file, Signer},
-            stake::instruction::StakeInstruction,
+            stake::{self, instruction::StakeInstruction, state::Meta},
         },
         trezoa_streamer::socket::SocketAddrSpace,
-        trezoa_test_validator::TestValidator,
+        trezoa_test_validator::{TestValidator, TestValidatorGenesis},
         trezoa_transaction_status::TransactionConfirmationStatus,
     };
 
@@ -1349,6 +2236,99 @@ mod tests {
         test_process_distribute_tokens_with_client(&client, alice, Some(trz_to_lamports(1.5)));
     }
 
+    #[test]
+    fn test_process_batched_allocations() {
+        let alice = Keypair::new();
+        let test_validator = TestValidatorGenesis::default()
+            .fee_rate_governor(FeeRateGovernor::new(0, 0))
+            .rent(Rent {
+                lamports_per_byte_year: 1,
+                exemption_threshold: 1.0,
+                ..Rent::default()
+            })
+            // The lookup tables are closed once they have been deactivated for
+            // `MAX_ENTRIES` slots, so keep the slots short
+            .ticks_per_slot(8)
+            .start_with_mint_address(alice.pubkey(), SocketAddrSpace::Unspecified)
+            .expect("validator start failed");
+        test_validator.set_startup_verification_complete_for_tests();
+        let url = test_validator.rpc_url();
+        let client = RpcClient::new_with_commitment(url, CommitmentConfig::processed());
+
+        let fee_payer = Keypair::new();
+        let transaction =
+            transfer(&client, trz_to_lamports(1.0), &alice, &fee_payer.pubkey()).unwrap();
+        client
+            .send_and_confirm_transaction_with_spinner(&transaction)
+            .unwrap();
+
+        let recipients: Vec<_> = (0..25).map(|_| Pubkey::new_unique()).collect();
+        let allocations_file = NamedTempFile::new().unwrap();
+        let input_csv = allocations_file.path().to_str().unwrap().to_string();
+        let mut wtr = csv::WriterBuilder::new().from_writer(allocations_file);
+        wtr.write_record(["recipient", "amount"]).unwrap();
+        for recipient in &recipients {
+            wtr.write_record([recipient.to_string(), "1".to_string()])
+                .unwrap();
+        }
+        wtr.flush().unwrap();
+
+        let dir = tempdir().unwrap();
+        let transaction_db = dir
+            .path()
+            .join("transactions.db")
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let args = DistributeTokensArgs {
+            sender_keypair: Box::new(alice),
+            fee_payer: Box::new(fee_payer.insecure_clone()),
+            dry_run: false,
+            input_csv,
+            transaction_db: transaction_db.clone(),
+            output_path: None,
+            stake_args: None,
+            tpl_token_args: None,
+            transfer_amount: None,
+            batch_size: Some(10),
+            compute_unit_price: Some(1),
+            // Too little for a whole batch unless scaled by its size
+            compute_unit_limit: Some(1_000),
+        };
+        let confirmations = process_allocations(&client, &args, Arc::default()).unwrap();
+        assert_eq!(confirmations, None);
+
+        let db = db::open_db(&transaction_db, true).unwrap();
+        let transaction_infos = db::read_transaction_infos(&db);
+        assert_eq!(transaction_infos.len(), recipients.len());
+        let signatures: HashSet<_> = transaction_infos
+            .iter()
+            .map(|info| info.transaction.signatures[0])
+            .collect();
+        assert_eq!(signatures.len(), 3);
+        for recipient in &recipients {
+            assert_eq!(client.get_balance(recipient).unwrap(), trz_to_lamports(1.0));
+        }
+
+        // The lookup tables are deactivated, and closed by a separate step
+        let lookup_tables = db::read_lookup_tables(&db);
+        assert_eq!(lookup_tables.len(), 1);
+        let args = CloseLookupTablesArgs {
+            transaction_db: transaction_db.clone(),
+            fee_payer: Box::new(fee_payer),
+            compute_unit_price: None,
+        };
+        process_close_lookup_tables(&client, &args, Arc::default()).unwrap();
+        let db = db::open_db(&transaction_db, true).unwrap();
+        assert!(db::read_lookup_tables(&db).is_empty());
+        assert!(client
+            .get_account_with_commitment(&lookup_tables[0], CommitmentConfig::processed())
+            .unwrap()
+            .value
+            .is_none());
+    }
+
     fn simple_test_validator_no_fees(pubkey: Pubkey) -> TestValidator {
         let test_validator =
             TestValidator::with_no_fees(pubkey, None, SocketAddrSpace::Unspecified);
@@ -1383,6 +2363,8 @@ mod tests {
             recipient: alice_pubkey,
             amount: 42,
             lockup_date: None,
+            lockup_epoch: None,
+            vesting_tranche: None,
         };
         let file = NamedTempFile::new().unwrap();
         let input_csv = file.path().to_str().unwrap().to_string();
@@ -1410,6 +2392,8 @@ mod tests {
             recipient: alice_pubkey,
             amount: trz_to_lamports(42.0),
             lockup_date: None,
+            lockup_epoch: None,
+            vesting_tranche: None,
         };
 
         assert_eq!(
@@ -1444,11 +2428,15 @@ mod tests {
                 recipient: pubkey0,
                 amount: trz_to_lamports(42.0),
                 lockup_date: None,
+                lockup_epoch: None,
+                vesting_tranche: None,
             },
             TypedAllocation {
                 recipient: pubkey1,
                 amount: trz_to_lamports(43.0),
                 lockup_date: None,
+                lockup_epoch: None,
+                vesting_tranche: None,
             },
         ];
         assert_eq!(
@@ -1457,6 +2445,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_allocations_vesting() {
+        let pubkey0 = pubkey::new_rand();
+        let pubkey1 = pubkey::new_rand();
+        let file = NamedTempFile::new().unwrap();
+        let input_csv = file.path().to_str().unwrap().to_string();
+        let mut wtr = csv::WriterBuilder::new().from_writer(file);
+        wtr.write_record([
+            "recipient",
+            "amount",
+            "lockup_date",
+            "lockup_epoch",
+            "vesting_schedule",
+        ])
+        .unwrap();
+        wtr.write_record([
+            pubkey0.to_string().as_str(),
+            "30.0",
+            "2024-01-01T00:00:00Z",
+            "",
+            "period=1y;duration=3y",
+        ])
+        .unwrap();
+        wtr.write_record([pubkey1.to_string().as_str(), "4.0", "", "200", ""])
+            .unwrap();
+        wtr.flush().unwrap();
+
+        let tranche = |vesting_tranche: usize, lockup_date: &str| TypedAllocation {
+            recipient: pubkey0,
+            amount: trz_to_lamports(10.0),
+            lockup_date: lockup_date.parse().ok(),
+            lockup_epoch: None,
+            vesting_tranche: Some(vesting_tranche),
+        };
+        let allocations = read_allocations(&input_csv, None, true, false).unwrap();
+        assert_eq!(
+            allocations,
+            vec![
+                tranche(0, "2025-01-01T00:00:00Z"),
+                tranche(1, "2026-01-01T00:00:00Z"),
+                tranche(2, "2027-01-01T00:00:00Z"),
+                TypedAllocation {
+                    recipient: pubkey1,
+                    amount: trz_to_lamports(4.0),
+                    lockup_date: None,
+                    lockup_epoch: Some(200),
+                    vesting_tranche: None,
+                },
+            ]
+        );
+
+        // The unlocked TRZ is paid once per allocation, with its first tranche
+        let unlocked_trz = trz_to_lamports(1.0);
+        assert_eq!(
+            allocations
+                .iter()
+                .map(|allocation| allocation.unlocked_trz(unlocked_trz))
+                .collect::<Vec<_>>(),
+            vec![unlocked_trz, 0, 0, unlocked_trz]
+        );
+
+        // A vesting schedule needs a lockup date to start from
+        let file = NamedTempFile::new().unwrap();
+        let input_csv = file.path().to_str().unwrap().to_string();
+        let mut wtr = csv::WriterBuilder::new().from_writer(file);
+        wtr.write_record([
+            "recipient",
+            "amount",
+            "lockup_date",
+            "lockup_epoch",
+            "vesting_schedule",
+        ])
+        .unwrap();
+        wtr.write_record([
+            pubkey0.to_string().as_str(),
+            "3.0",
+            "",
+            "",
+            "period=1y;duration=3y",
+        ])
+        .unwrap();
+        wtr.flush().unwrap();
+        assert!(matches!(
+            read_allocations(&input_csv, None, true, false),
+            Err(Error::BadInputVestingSchedule {
+                err: VestingError::MissingStartDate,
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn test_read_allocations_malformed() {
         let pubkey0 = pubkey::new_rand();
@@ -1678,16 +2757,22 @@ mod tests {
                 recipient: pubkey0,
                 amount,
                 lockup_date: None,
+                lockup_epoch: None,
+                vesting_tranche: None,
             },
             TypedAllocation {
                 recipient: pubkey1,
                 amount,
                 lockup_date: None,
+                lockup_epoch: None,
+                vesting_tranche: None,
             },
             TypedAllocation {
                 recipient: pubkey2,
                 amount,
                 lockup_date: None,
+                lockup_epoch: None,
+                vesting_tranche: None,
             },
         ];
         assert_eq!(
@@ -1705,11 +2790,15 @@ mod tests {
                 recipient: alice,
                 amount: trz_to_lamports(1.0),
                 lockup_date: None,
+                lockup_epoch: None,
+                vesting_tranche: None,
             },
             TypedAllocation {
                 recipient: bob,
                 amount: trz_to_lamports(1.0),
                 lockup_date: None,
+                lockup_epoch: None,
+                vesting_tranche: None,
             },
         ];
         let transaction_infos = vec![TransactionInfo {
@@ -1735,11 +2824,15 @@ mod tests {
             recipient: alice_pubkey,
             amount: trz_to_lamports(1.0),
             lockup_date: None,
+            lockup_epoch: None,
+            vesting_tranche: None,
         };
         let alice_alloc_lockup0 = TypedAllocation {
             recipient: alice_pubkey,
             amount: trz_to_lamports(1.0),
             lockup_date: lockup0.parse().ok(),
+            lockup_epoch: None,
+            vesting_tranche: None,
         };
         let alice_info = TransactionInfo {
             recipient: alice_pubkey,
@@ -1774,6 +2867,135 @@ mod tests {
         )); // Same recipient, same lockups
     }
 
+    #[test]
+    fn test_reconcile_stake_account() {
+        let recipient = pubkey::new_rand();
+        let now: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        let lockup_date: DateTime<Utc> = "2025-01-01T00:00:00Z".parse().unwrap();
+        let allocation = TypedAllocation {
+            recipient,
+            amount: trz_to_lamports(10.0),
+            lockup_date: Some(lockup_date),
+            lockup_epoch: Some(100),
+            vesting_tranche: None,
+        };
+        let unlocked_trz = trz_to_lamports(1.0);
+        let transaction_info = TransactionInfo {
+            recipient,
+            amount: allocation.amount,
+            finalized_date: Some(now),
+            lockup_date: Some(lockup_date),
+            lockup_epoch: Some(100),
+            new_stake_account_address: Some(pubkey::new_rand()),
+            ..TransactionInfo::default()
+        };
+        let meta = Meta {
+            authorized: Authorized::auto(&recipient),
+            lockup: Lockup {
+                unix_timestamp: lockup_date.timestamp(),
+                epoch: 100,
+                custodian: Pubkey::default(),
+            },
+            ..Meta::default()
+        };
+        let stake_account = |meta: &Meta, lamports: u64| {
+            Account::new_data_with_space(
+                lamports,
+                &StakeStateV2::Initialized(*meta),
+                StakeStateV2::size_of(),
+                &stake::program::id(),
+            )
+            .unwrap()
+        };
+        let reconcile = |transaction_info: Option<&TransactionInfo>,
+                         account: Option<&Account>,
+                         now: DateTime<Utc>| {
+            reconcile_stake_account(
+                &allocation,
+                transaction_info,
+                account,
+                unlocked_trz,
+                now,
+                50,
+            )
+        };
+
+        let account = stake_account(&meta, trz_to_lamports(9.0));
+        assert_eq!(
+            reconcile(Some(&transaction_info), Some(&account), now),
+            StakeReportStatus::Ok
+        );
+        assert_eq!(
+            reconcile(None, Some(&account), now),
+            StakeReportStatus::NotDistributed
+        );
+        let unfinalized_info = TransactionInfo {
+            finalized_date: None,
+            ..transaction_info.clone()
+        };
+        assert_eq!(
+            reconcile(Some(&unfinalized_info), Some(&account), now),
+            StakeReportStatus::Unfinalized
+        );
+        assert_eq!(
+            reconcile(Some(&transaction_info), None, now),
+            StakeReportStatus::MissingAccount
+        );
+        assert_eq!(
+            reconcile(
+                Some(&transaction_info),
+                Some(&Account::new(allocation.amount, 0, &Pubkey::default())),
+                now
+            ),
+            StakeReportStatus::NotAStakeAccount
+        );
+
+        let mut other_meta = meta;
+        other_meta.authorized.withdrawer = pubkey::new_rand();
+        assert_eq!(
+            reconcile(
+                Some(&transaction_info),
+                Some(&stake_account(&other_meta, allocation.amount)),
+                now
+            ),
+            StakeReportStatus::AuthorityMismatch
+        );
+        let mut other_meta = meta;
+        other_meta.lockup.epoch = 0;
+        assert_eq!(
+            reconcile(
+                Some(&transaction_info),
+                Some(&stake_account(&other_meta, allocation.amount)),
+                now
+            ),
+            StakeReportStatus::LockupMismatch
+        );
+
+        // The balance only has to be intact while the lockup is in force
+        let account = stake_account(&meta, trz_to_lamports(8.0));
+        assert_eq!(
+            reconcile(Some(&transaction_info), Some(&account), now),
+            StakeReportStatus::BalanceMismatch
+        );
+        let mut unlocked_meta = meta;
+        unlocked_meta.lockup.epoch = 0;
+        let unlocked_allocation = TypedAllocation {
+            lockup_epoch: None,
+            ..allocation.clone()
+        };
+        assert_eq!(
+            reconcile_stake_account(
+                &unlocked_allocation,
+                Some(&transaction_info),
+                Some(&stake_account(&unlocked_meta, 0)),
+                unlocked_trz,
+                "2026-01-01T00:00:00Z".parse().unwrap(),
+                50,
+            ),
+            StakeReportStatus::Ok
+        );
+    }
+
     const SET_LOCKUP_INDEX: usize = 6;
 
     #[test]
@@ -1783,6 +3005,8 @@ mod tests {
             recipient: Pubkey::default(),
             amount: trz_to_lamports(1.002_282_880),
             lockup_date: lockup_date_str.parse().ok(),
+            lockup_epoch: None,
+            vesting_tranche: None,
         };
         let stake_account_address = pubkey::new_rand();
         let new_stake_account_address = pubkey::new_rand();
@@ -1810,6 +3034,9 @@ mod tests {
             tpl_token_args: None,
             sender_keypair: Box::new(Keypair::new()),
             transfer_amount: None,
+            batch_size: None,
+            compute_unit_price: None,
+            compute_unit_limit: None,
         };
         let lockup_date = lockup_date_str.parse().unwrap();
         let instructions = distribution_instructions(
@@ -1848,6 +3075,8 @@ mod tests {
             recipient,
             amount: allocation_amount,
             lockup_date: None,
+            lockup_epoch: None,
+            vesting_tranche: None,
         }];
         let args = DistributeTokensArgs {
             sender_keypair: Box::new(read_keypair_file(sender_keypair_file).unwrap()),
@@ -1859,6 +3088,9 @@ mod tests {
             stake_args,
             tpl_token_args: None,
             transfer_amount: None,
+            batch_size: None,
+            compute_unit_price: None,
+            compute_unit_limit: None,
         };
         (allocations, args)
     }
@@ -1887,7 +3119,14 @@ mod tests {
             &sender_keypair_file,
             None,
         );
-        check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args).unwrap();
+        check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap();
 
         // Unfunded payer
         let unfunded_payer = Keypair::new();
@@ -1896,9 +3135,14 @@ mod tests {
         args.sender_keypair = Box::new(read_keypair_file(&unfunded_payer_keypair_file).unwrap());
         args.fee_payer = Box::new(read_keypair_file(&unfunded_payer_keypair_file).unwrap());
 
-        let err_result =
-            check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args)
-                .unwrap_err();
+        let err_result = check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap_err();
         if let Error::InsufficientFunds(sources, amount) = err_result {
             assert_eq!(
                 sources,
@@ -1932,9 +3176,14 @@ mod tests {
         args.sender_keypair =
             Box::new(read_keypair_file(&partially_funded_payer_keypair_file).unwrap());
         args.fee_payer = Box::new(read_keypair_file(&partially_funded_payer_keypair_file).unwrap());
-        let err_result =
-            check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args)
-                .unwrap_err();
+        let err_result = check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap_err();
         if let Error::InsufficientFunds(sources, amount) = err_result {
             assert_eq!(
                 sources,
@@ -1986,7 +3235,14 @@ mod tests {
             &sender_keypair_file,
             None,
         );
-        check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args).unwrap();
+        check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap();
 
         // Unfunded sender
         let unfunded_payer = Keypair::new();
@@ -1995,9 +3251,14 @@ mod tests {
         args.sender_keypair = Box::new(read_keypair_file(&unfunded_payer_keypair_file).unwrap());
         args.fee_payer = Box::new(read_keypair_file(&sender_keypair_file).unwrap());
 
-        let err_result =
-            check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args)
-                .unwrap_err();
+        let err_result = check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap_err();
         if let Error::InsufficientFunds(sources, amount) = err_result {
             assert_eq!(sources, vec![FundingSource::SystemAccount].into());
             assert_eq!(amount, allocation_amount.to_string());
@@ -2009,9 +3270,14 @@ mod tests {
         args.sender_keypair = Box::new(read_keypair_file(&sender_keypair_file).unwrap());
         args.fee_payer = Box::new(read_keypair_file(&unfunded_payer_keypair_file).unwrap());
 
-        let err_result =
-            check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args)
-                .unwrap_err();
+        let err_result = check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap_err();
         if let Error::InsufficientFunds(sources, amount) = err_result {
             assert_eq!(sources, vec![FundingSource::FeePayer].into());
             assert_eq!(amount, fees_in_trz.to_string());
@@ -2073,6 +3339,53 @@ mod tests {
         test_validator
     }
 
+    #[test]
+    fn test_check_stake_allocations() {
+        let alice = Keypair::new();
+        let test_validator = simple_test_validator(alice.pubkey());
+        let url = test_validator.rpc_url();
+        let client = RpcClient::new_with_commitment(url, CommitmentConfig::processed());
+
+        let sender_keypair_file = tmp_file_path("keypair_file", &alice.pubkey());
+        write_keypair_file(&alice, &sender_keypair_file).unwrap();
+
+        let unlocked_trz = trz_to_lamports(1.0);
+        let stake_args =
+            initialize_stake_account(trz_to_lamports(1000.0), unlocked_trz, &alice, &client);
+        let rent_exempt_reserve = stake_args
+            .sender_stake_args
+            .as_ref()
+            .and_then(|sender_stake_args| sender_stake_args.rent_exempt_reserve)
+            .unwrap();
+        let minimum_stake = rent_exempt_reserve + client.get_stake_minimum_delegation().unwrap();
+        let (mut allocations, args) = initialize_check_payer_balances_inputs(
+            unlocked_trz + minimum_stake,
+            &sender_keypair_file,
+            &sender_keypair_file,
+            Some(stake_args),
+        );
+        check_stake_allocations(&allocations, &client, &args).unwrap();
+
+        // Only the first tranche of a vesting schedule pays the unlocked TRZ
+        allocations[0].amount = minimum_stake;
+        allocations[0].vesting_tranche = Some(1);
+        check_stake_allocations(&allocations, &client, &args).unwrap();
+        allocations[0].vesting_tranche = Some(0);
+        assert!(matches!(
+            check_stake_allocations(&allocations, &client, &args),
+            Err(Error::StakeAllocationTooSmall { .. })
+        ));
+
+        // Allocations smaller than the unlocked TRZ are rejected rather than
+        // underflowing
+        allocations[0].amount = unlocked_trz / 2;
+        allocations[0].vesting_tranche = None;
+        assert!(matches!(
+            check_stake_allocations(&allocations, &client, &args),
+            Err(Error::StakeAllocationTooSmall { .. })
+        ));
+    }
+
     #[test]
     fn test_check_payer_balances_distribute_stakes_single_payer() {
         let alice = Keypair::new();
@@ -2104,7 +3417,14 @@ mod tests {
             &sender_keypair_file,
             Some(stake_args),
         );
-        check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args).unwrap();
+        check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap();
 
         // Underfunded stake-account
         let expensive_allocation_amount = 5000.0;
@@ -2112,12 +3432,15 @@ mod tests {
             recipient: pubkey::new_rand(),
             amount: trz_to_lamports(expensive_allocation_amount),
             lockup_date: None,
+            lockup_epoch: None,
+            vesting_tranche: None,
         }];
         let err_result = check_payer_balances(
             &[one_signer_message(&client)],
             &expensive_allocations,
             &client,
             &args,
+            0,
         )
         .unwrap_err();
         if let Error::InsufficientFunds(sources, amount) = err_result {
@@ -2137,9 +3460,14 @@ mod tests {
         args.sender_keypair = Box::new(read_keypair_file(&unfunded_payer_keypair_file).unwrap());
         args.fee_payer = Box::new(read_keypair_file(&unfunded_payer_keypair_file).unwrap());
 
-        let err_result =
-            check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args)
-                .unwrap_err();
+        let err_result = check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap_err();
         if let Error::InsufficientFunds(sources, amount) = err_result {
             assert_eq!(
                 sources,
@@ -2173,9 +3501,14 @@ mod tests {
         args.sender_keypair =
             Box::new(read_keypair_file(&partially_funded_payer_keypair_file).unwrap());
         args.fee_payer = Box::new(read_keypair_file(&partially_funded_payer_keypair_file).unwrap());
-        let err_result =
-            check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args)
-                .unwrap_err();
+        let err_result = check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap_err();
         if let Error::InsufficientFunds(sources, amount) = err_result {
             assert_eq!(
                 sources,
@@ -2234,7 +3567,14 @@ mod tests {
             &sender_keypair_file,
             Some(stake_args),
         );
-        check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args).unwrap();
+        check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap();
 
         // Unfunded sender
         let unfunded_payer = Keypair::new();
@@ -2243,9 +3583,14 @@ mod tests {
         args.sender_keypair = Box::new(read_keypair_file(&unfunded_payer_keypair_file).unwrap());
         args.fee_payer = Box::new(read_keypair_file(&sender_keypair_file).unwrap());
 
-        let err_result =
-            check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args)
-                .unwrap_err();
+        let err_result = check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap_err();
         if let Error::InsufficientFunds(sources, amount) = err_result {
             assert_eq!(sources, vec![FundingSource::SystemAccount].into());
             assert_eq!(amount, unlocked_trz.to_string());
@@ -2257,9 +3602,14 @@ mod tests {
         args.sender_keypair = Box::new(read_keypair_file(&sender_keypair_file).unwrap());
         args.fee_payer = Box::new(read_keypair_file(&unfunded_payer_keypair_file).unwrap());
 
-        let err_result =
-            check_payer_balances(&[one_signer_message(&client)], &allocations, &client, &args)
-                .unwrap_err();
+        let err_result = check_payer_balances(
+            &[one_signer_message(&client)],
+            &allocations,
+            &client,
+            &args,
+            0,
+        )
+        .unwrap_err();
         if let Error::InsufficientFunds(sources, amount) = err_result {
             assert_eq!(sources, vec![FundingSource::FeePayer].into());
             assert_eq!(amount, fees_in_trz.to_string());
@@ -2269,12 +3619,66 @@ mod tests {
     }
 
     #[test]
-    fn test_build_messages_dump_db() {
+    fn test_pack_batches() {
+        let args = DistributeTokensArgs {
+            sender_keypair: Box::new(Keypair::new()),
+            fee_payer: Box::new(Keypair::new()),
+            dry_run: true,
+            input_csv: "".to_string(),
+            transaction_db: "".to_string(),
+            output_path: None,
+            stake_args: None,
+            tpl_token_args: None,
+            transfer_amount: None,
+            batch_size: Some(10),
+            compute_unit_price: Some(1_000),
+            compute_unit_limit: Some(100_000),
+        };
+        let instructions: Vec<Vec<Instruction>> = (0..600)
+            .map(|_| {
+                let allocation = TypedAllocation {
+                    recipient: Pubkey::new_unique(),
+                    amount: trz_to_lamports(1.0),
+                    lockup_date: None,
+                    lockup_epoch: None,
+                    vesting_tranche: None,
+                };
+                distribution_instructions(&allocation, &Pubkey::default(), &args, None, false)
+            })
+            .collect();
+
+        // Only the recipients are loaded from the lookup tables
+        let groups = lookup_table_groups(&instructions);
+        assert_eq!(groups, vec![0..256, 256..512, 512..600]);
+
+        let lookup_table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: lookup_table_addresses(&instructions[0..256].concat())
+                .into_iter()
+                .collect(),
+        };
+        let batches = pack_batches(&instructions[0..256], &lookup_table, &args, 10).unwrap();
+        assert_eq!(batches.len(), 26);
+        assert_eq!(batches[0], 0..10);
+        assert_eq!(batches[25], 250..256);
+
+        // Batches shrink to fit in a packet
+        let batches = pack_batches(&instructions[0..256], &lookup_table, &args, 256).unwrap();
+        assert!(batches.len() > 1);
+        for batch in batches {
+            let message =
+                compile_batch(&instructions[batch], &lookup_table, &args, Hash::default()).unwrap();
+            assert!(batched_transaction_size(message) <= PACKET_DATA_SIZE);
+        }
+    }
+
+    #[test]
+    fn test_build_instructions_dump_db() {
         let client = RpcClient::new_mock("mock_client".to_string());
         let dir = tempdir().unwrap();
         let db_file = dir
             .path()
-            .join("build_messages.db")
+            .join("build_instructions.db")
             .to_str()
             .unwrap()
             .to_string();
@@ -2296,6 +3700,7 @@ mod tests {
             false,
             last_valid_block_height,
             None,
+            None,
         )
         .unwrap();
 
@@ -2303,7 +3708,7 @@ mod tests {
         let read_db = db::open_db(&db_file, true).unwrap();
         assert!(db::read_transaction_infos(&read_db).is_empty());
 
-        // This is just dummy data; Args will not affect messages built
+        // This is just dummy data; Args will not affect instructions built
         let args = DistributeTokensArgs {
             sender_keypair: Box::new(Keypair::new()),
             fee_payer: Box::new(Keypair::new()),
@@ -2314,60 +3719,65 @@ mod tests {
             stake_args: None,
             tpl_token_args: None,
             transfer_amount: None,
+            batch_size: None,
+            compute_unit_price: None,
+            compute_unit_limit: None,
         };
         let allocation = TypedAllocation {
             recipient,
             amount: trz_to_lamports(1.0),
             lockup_date: None,
+            lockup_epoch: None,
+            vesting_tranche: None,
         };
 
-        let mut messages: Vec<Message> = vec![];
+        let mut instructions: Vec<Vec<Instruction>> = vec![];
         let mut stake_extras: StakeExtras = vec![];
         let mut created_accounts = 0;
 
         // Exit false will not dump data
-        build_messages(
+        build_instructions(
             &client,
             &mut db,
             &[allocation.clone()],
             &args,
             Arc::new(AtomicBool::new(false)),
-            &mut messages,
+            &mut instructions,
             &mut stake_extras,
             &mut created_accounts,
         )
         .unwrap();
         let read_db = db::open_db(&db_file, true).unwrap();
         assert!(db::read_transaction_infos(&read_db).is_empty());
-        assert_eq!(messages.len(), 1);
+        assert_eq!(instructions.len(), 1);
 
         // Empty allocations will not dump data
-        let mut messages: Vec<Message> = vec![];
+        let mut instructions: Vec<Vec<Instruction>> = vec![];
         let exit = Arc::new(AtomicBool::new(true));
-        build_messages(
+        build_instructions(
             &client,
             &mut db,
             &[],
             &args,
             exit.clone(),
-            &mut messages,
+            &mut instructions,
             &mut stake_extras,
             &mut created_accounts,
         )
         .unwrap();
         let read_db = db::open_db(&db_file, true).unwrap();
         assert!(db::read_transaction_infos(&read_db).is_empty());
-        assert!(messages.is_empty());
+        assert!(instructions.is_empty());
 
         // Any allocation should prompt data dump
-        let mut messages: Vec<Message> = vec![];
-        build_messages(
+        let mut instructions: Vec<Vec<Instruction>> = vec![];
+        build_instructions(
             &client,
             &mut db,
             &[allocation],
             &args,
             exit,
-            &mut messages,
+            &mut instructions,
             &mut stake_extras,
             &mut created_accounts,
         )
@@ -2385,9 +3795,10 @@ mod tests {
                 transaction,
                 last_valid_block_height,
                 lockup_date: None,
+                lockup_epoch: None,
             }
         );
-        assert_eq!(messages.len(), 0);
+        assert_eq!(instructions.len(), 0);
     }
 
     #[test]
@@ -2418,6 +3829,7 @@ mod tests {
             false,
             last_valid_block_height,
             None,
+            None,
         )
         .unwrap();
 
@@ -2436,11 +3848,16 @@ mod tests {
             stake_args: None,
             tpl_token_args: None,
             transfer_amount: None,
+            batch_size: None,
+            compute_unit_price: None,
+            compute_unit_limit: None,
         };
         let allocation = TypedAllocation {
             recipient,
             amount: trz_to_lamports(1.0),
             lockup_date: None,
+            lockup_epoch: None,
+            vesting_tranche: None,
         };
         let message = transaction.message.clone();
 
@@ -2489,6 +3906,7 @@ mod tests {
             transaction,
             last_valid_block_height,
             lockup_date: None,
+            lockup_epoch: None,
         }));
         assert!(transaction_info.contains(&TransactionInfo {
             recipient,
@@ -2498,6 +3916,7 @@ mod tests {
             transaction: Transaction::new_unsigned(message),
             last_valid_block_height: std::u64::MAX,
             lockup_date: None,
+            lockup_epoch: None,
         }));
 
         // Next dump should write record written in last send_messages call
@@ -2540,6 +3959,8 @@ mod tests {
             recipient,
             amount: trz_to_lamports(1.0),
             lockup_date: None,
+            lockup_epoch: None,
+            vesting_tranche: None,
         };
         // This is just dummy data; Args will not affect messages
         let args = DistributeTokensArgs {
@@ -2552,6 +3973,9 @@ mod tests {
             stake_args: None,
             tpl_token_args: None,
             transfer_amount: None,
+            batch_size: None,
+            compute_unit_price: None,
+            compute_unit_limit: None,
         };
 
         let exit = Arc::new(AtomicBool::new(false));
@@ -2591,6 +4015,7 @@ mod tests {
             false,
             last_valid_block_height,
             None,
+            None,
         )
         .unwrap();
 
@@ -2619,7 +4044,7 @@ mod tests {
             &client,
             &mut db,
             Arc::new(AtomicBool::new(false)),
-            vec![(&transaction, 111)],
+            vec![(transaction.signatures[0], 111)],
             vec![Some(TransactionStatus {
                 slot: 40,
                 confirmations: Some(15),
@@ -2639,7 +4064,7 @@ mod tests {
             &client,
             &mut db,
             exit,
-            vec![(&transaction, 111)],
+            vec![(transaction.signatures[0], 111)],
             vec![Some(TransactionStatus {
                 slot: 55,
                 confirmations: None,
@@ -2684,6 +4109,7 @@ mod tests {
             false,
             last_valid_block_height,
             None,
+            None,
         )
         .unwrap();
 
@@ -2,11 +2,21 @@ use {
     chrono::prelude::*,
     pickledb::{error::Error, PickleDb, PickleDbDumpPolicy},
     serde::{Deserialize, Serialize},
-    trezoa_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature, transaction::Transaction},
+    trezoa_sdk::{
+        clock::{Epoch, Slot},
+        pubkey::Pubkey,
+        signature::Signature,
+        transaction::{Transaction, VersionedTransaction},
+    },
     trezoa_transaction_status::TransactionStatus,
     std::{cmp::Ordering, fs, io, path::Path},
 };
 
+const LOOKUP_TABLE_KEY_PREFIX: &str = "lookup-table:";
+
+/// Recipients paid in a batched versioned transaction each get their own
+/// TransactionInfo, whose `transaction` only carries that transaction's
+/// signatures.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TransactionInfo {
     pub recipient: Pubkey,
@@ -16,6 +26,8 @@ pub struct TransactionInfo {
     pub transaction: Transaction,
     pub last_valid_block_height: Slot,
     pub lockup_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub lockup_epoch: Option<Epoch>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
@@ -42,6 +54,7 @@ impl Default for TransactionInfo {
             transaction,
             last_valid_block_height: 0,
             lockup_date: None,
+            lockup_epoch: None,
         }
     }
 }
@@ -99,10 +112,28 @@ pub fn write_transaction_log<P: AsRef<Path>>(db: &PickleDb, path: &P) -> Result<
 
 pub fn read_transaction_infos(db: &PickleDb) -> Vec<TransactionInfo> {
     db.iter()
+        .filter(|kv| !kv.get_key().starts_with(LOOKUP_TABLE_KEY_PREFIX))
         .map(|kv| kv.get_value::<TransactionInfo>().unwrap())
         .collect()
 }
 
+fn batched_transaction_info_key(signature: &str, batch_index: usize) -> String {
+    format!("{signature}:{batch_index}")
+}
+
+// Return the keys of the TransactionInfos recorded for `signature`, which are
+// several if the transaction paid a batch of recipients.
+fn transaction_info_keys(db: &PickleDb, signature: &Signature) -> Vec<String> {
+    let signature = signature.to_string();
+    if db.exists(&signature) {
+        return vec![signature];
+    }
+    (0..)
+        .map(|batch_index| batched_transaction_info_key(&signature, batch_index))
+        .take_while(|key| db.exists(key))
+        .collect()
+}
+
 pub fn set_transaction_info(
     db: &mut PickleDb,
     recipient: &Pubkey,
@@ -112,6 +143,7 @@ pub fn set_transaction_info(
     finalized: bool,
     last_valid_block_height: u64,
     lockup_date: Option<DateTime<Utc>>,
+    lockup_epoch: Option<Epoch>,
 ) -> Result<(), Error> {
     let finalized_date = if finalized { Some(Utc::now()) } else { None };
     let transaction_info = TransactionInfo {
@@ -122,12 +154,64 @@ pub fn set_transaction_info(
         transaction: transaction.clone(),
         last_valid_block_height,
         lockup_date,
+        lockup_epoch,
     };
     let signature = transaction.signatures[0];
     db.set(&signature.to_string(), &transaction_info)?;
     Ok(())
 }
 
+pub fn set_batched_transaction_info(
+    db: &mut PickleDb,
+    recipient: &Pubkey,
+    amount: u64,
+    transaction: &VersionedTransaction,
+    batch_index: usize,
+    new_stake_account_address: Option<&Pubkey>,
+    last_valid_block_height: u64,
+    lockup_date: Option<DateTime<Utc>>,
+    lockup_epoch: Option<Epoch>,
+) -> Result<(), Error> {
+    let transaction_info = TransactionInfo {
+        recipient: *recipient,
+        amount,
+        new_stake_account_address: new_stake_account_address.cloned(),
+        finalized_date: None,
+        transaction: Transaction {
+            signatures: transaction.signatures.clone(),
+            ..Transaction::default()
+        },
+        last_valid_block_height,
+        lockup_date,
+        lockup_epoch,
+    };
+    let signature = transaction.signatures[0];
+    db.set(
+        &batched_transaction_info_key(&signature.to_string(), batch_index),
+        &transaction_info,
+    )?;
+    Ok(())
+}
+
+pub fn read_lookup_tables(db: &PickleDb) -> Vec<Pubkey> {
+    db.iter()
+        .filter(|kv| kv.get_key().starts_with(LOOKUP_TABLE_KEY_PREFIX))
+        .map(|kv| kv.get_value::<Pubkey>().unwrap())
+        .collect()
+}
+
+// Lookup tables are recorded before they are created, so that a distribution
+// that is interrupted can still close them when it is resumed.
+pub fn set_lookup_table(db: &mut PickleDb, address: &Pubkey) -> Result<(), Error> {
+    db.set(&format!("{LOOKUP_TABLE_KEY_PREFIX}{address}"), address)?;
+    Ok(())
+}
+
+pub fn remove_lookup_table(db: &mut PickleDb, address: &Pubkey) -> Result<(), Error> {
+    db.rem(&format!("{LOOKUP_TABLE_KEY_PREFIX}{address}"))?;
+    Ok(())
+}
+
 // Set the finalized bit in the database if the transaction is rooted.
 // Remove the TransactionInfo from the database if the transaction failed.
 // Return the number of confirmations on the transaction or None if either
@@ -167,14 +251,19 @@ pub fn update_finalized_transaction(
         eprintln!("Error in transaction with signature {signature}: {e}");
         eprintln!("Discarding transaction record");
         eprintln!();
-        db.rem(&signature.to_string())?;
+        for key in transaction_info_keys(db, signature) {
+            db.rem(&key)?;
+        }
         return Ok(None);
     }
 
     // Transaction is rooted. Set the finalized date in the database.
-    let mut transaction_info = db.get::<TransactionInfo>(&signature.to_string()).unwrap();
-    transaction_info.finalized_date = Some(Utc::now());
-    db.set(&signature.to_string(), &transaction_info)?;
+    let finalized_date = Some(Utc::now());
+    for key in transaction_info_keys(db, signature) {
+        let mut transaction_info = db.get::<TransactionInfo>(&key).unwrap();
+        transaction_info.finalized_date = finalized_date;
+        db.set(&key, &transaction_info)?;
+    }
     Ok(None)
 }
 
@@ -350,6 +439,70 @@ mod tests {
         assert_eq!(db.get::<TransactionInfo>(&signature.to_string()), None);
     }
 
+    #[test]
+    fn test_update_finalized_batched_transaction() {
+        let mut db =
+            PickleDb::new_yaml(NamedTempFile::new().unwrap(), PickleDbDumpPolicy::NeverDump);
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::new_unique()],
+            ..VersionedTransaction::default()
+        };
+        let signature = transaction.signatures[0];
+        let recipients = [Pubkey::new_unique(), Pubkey::new_unique()];
+        for (batch_index, recipient) in recipients.iter().enumerate() {
+            set_batched_transaction_info(
+                &mut db,
+                recipient,
+                42,
+                &transaction,
+                batch_index,
+                None,
+                0,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+        let lookup_table = Pubkey::new_unique();
+        set_lookup_table(&mut db, &lookup_table).unwrap();
+        assert_eq!(read_lookup_tables(&db), vec![lookup_table]);
+        assert_eq!(read_transaction_infos(&db).len(), 2);
+
+        let transaction_status = TransactionStatus {
+            slot: 0,
+            confirmations: None,
+            err: None,
+            status: Ok(()),
+            confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+        };
+        assert_eq!(
+            update_finalized_transaction(&mut db, &signature, Some(transaction_status), 0, 0)
+                .unwrap(),
+            None
+        );
+        let transaction_infos = read_transaction_infos(&db);
+        assert_eq!(transaction_infos.len(), 2);
+        for transaction_info in transaction_infos {
+            assert!(recipients.contains(&transaction_info.recipient));
+            assert_eq!(transaction_info.transaction.signatures, vec![signature]);
+            assert!(transaction_info.finalized_date.is_some());
+        }
+
+        // A failed batch discards the records of all of its recipients
+        let transaction_status = TransactionStatus {
+            slot: 0,
+            confirmations: None,
+            err: Some(TransactionError::AccountNotFound),
+            status: Ok(()),
+            confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+        };
+        update_finalized_transaction(&mut db, &signature, Some(transaction_status), 0, 0).unwrap();
+        assert!(read_transaction_infos(&db).is_empty());
+
+        remove_lookup_table(&mut db, &lookup_table).unwrap();
+        assert!(read_lookup_tables(&db).is_empty());
+    }
+
     #[test]
     fn test_update_finalized_transaction_finalized() {
         // Don't wait once the transaction has been finalized.
@@ -6,3 +6,4 @@ mod db;
 pub mod tpl_token;
 pub mod stake;
 pub mod token_display;
+pub mod vesting;
@@ -50,9 +50,15 @@ fn main() -> Result<(), Box<dyn Error>> {
             tpl_token::update_decimals(&client, &mut args.tpl_token_args)?;
             commands::process_balances(&client, &args, exit)?;
         }
+        Command::StakeReport(args) => {
+            commands::process_stake_report(&client, &args, exit)?;
+        }
         Command::TransactionLog(args) => {
             commands::process_transaction_log(&args)?;
         }
+        Command::CloseLookupTables(args) => {
+            commands::process_close_lookup_tables(&client, &args, exit)?;
+        }
     }
     Ok(())
 }
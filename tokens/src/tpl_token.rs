@@ -79,13 +79,16 @@ pub(crate) fn check_tpl_token_balances(
     client: &RpcClient,
     args: &DistributeTokensArgs,
     created_accounts: u64,
+    lookup_table_cost: u64,
 ) -> Result<(), Error> {
     let tpl_token_args = args
         .tpl_token_args
         .as_ref()
         .expect("tpl_token_args must be some");
     let allocation_amount: u64 = allocations.iter().map(|x| x.amount).sum();
-    let fees = get_fee_estimate_for_messages(messages, client)?;
+    let fees = get_fee_estimate_for_messages(messages, client)?
+        .checked_add(lookup_table_cost)
+        .ok_or(Error::FeeEstimationError)?;
 
     let token_account_rent_exempt_balance =
         client.get_minimum_balance_for_rent_exemption(TplTokenAccount::LEN)?;
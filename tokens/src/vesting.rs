@@ -0,0 +1,312 @@
+//! Vesting schedules that split an allocation into separately locked tranches
+//!
+//! A schedule is written as semicolon separated `key=value` pairs, for example
+//! `cliff=12mo;period=1mo;duration=48mo`. Lengths are a number followed by
+//! `d` (days), `w` (weeks), `mo` (months) or `y` (years).
+//!
+//! Tokens vest linearly over `duration`, starting at the allocation's lockup
+//! date. Nothing unlocks before `cliff`; after that, everything vested so far
+//! unlocks every `period`, and the remainder unlocks at the end of `duration`.
+
+use {
+    chrono::{prelude::*, Days, Months},
+    std::{fmt, str::FromStr},
+    thiserror::Error,
+};
+
+/// Upper bound on the number of tranches a single allocation can be split into
+pub const MAX_TRANCHES: usize = 1_000;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum VestingError {
+    #[error("invalid length {0:?}, expected a number followed by d, w, mo or y")]
+    InvalidLength(String),
+    #[error("unknown schedule field {0:?}")]
+    UnknownField(String),
+    #[error("missing schedule field {0:?}")]
+    MissingField(&'static str),
+    #[error("period must not be zero")]
+    ZeroPeriod,
+    #[error("cliff must not be longer than duration")]
+    CliffAfterEnd,
+    #[error("schedule has more than {MAX_TRANCHES} tranches")]
+    TooManyTranches,
+    #[error("schedule requires a lockup date to start from")]
+    MissingStartDate,
+    #[error("unlock date out of range")]
+    DateOutOfRange,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VestingLength {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+}
+
+impl VestingLength {
+    fn is_zero(&self) -> bool {
+        matches!(
+            self,
+            Self::Days(0) | Self::Weeks(0) | Self::Months(0) | Self::Years(0)
+        )
+    }
+
+    /// Return `date` advanced by `multiple` times this length. Months and
+    /// years are added on the calendar, so that monthly unlocks keep falling
+    /// on the same day of the month.
+    fn checked_add_to(&self, date: DateTime<Utc>, multiple: u32) -> Option<DateTime<Utc>> {
+        match *self {
+            Self::Days(days) => {
+                date.checked_add_days(Days::new(days.checked_mul(multiple)? as u64))
+            }
+            Self::Weeks(weeks) => date.checked_add_days(Days::new(
+                (weeks.checked_mul(multiple)? as u64).checked_mul(7)?,
+            )),
+            Self::Months(months) => {
+                date.checked_add_months(Months::new(months.checked_mul(multiple)?))
+            }
+            Self::Years(years) => {
+                date.checked_add_months(Months::new(years.checked_mul(multiple)?.checked_mul(12)?))
+            }
+        }
+    }
+}
+
+impl FromStr for VestingLength {
+    type Err = VestingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VestingError::InvalidLength(s.to_string());
+        let unit_start = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let (count, unit) = s.split_at(unit_start);
+        let count = count.parse::<u32>().map_err(|_| invalid())?;
+        match unit {
+            "d" => Ok(Self::Days(count)),
+            "w" => Ok(Self::Weeks(count)),
+            "mo" => Ok(Self::Months(count)),
+            "y" => Ok(Self::Years(count)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for VestingLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Days(days) => write!(f, "{days}d"),
+            Self::Weeks(weeks) => write!(f, "{weeks}w"),
+            Self::Months(months) => write!(f, "{months}mo"),
+            Self::Years(years) => write!(f, "{years}y"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VestingSchedule {
+    pub cliff: Option<VestingLength>,
+    pub period: VestingLength,
+    pub duration: VestingLength,
+}
+
+impl FromStr for VestingSchedule {
+    type Err = VestingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cliff = None;
+        let mut period = None;
+        let mut duration = None;
+        for field in s
+            .split(';')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+        {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| VestingError::UnknownField(field.to_string()))?;
+            let value = value.trim().parse::<VestingLength>()?;
+            match key.trim() {
+                "cliff" => cliff = Some(value),
+                "period" => period = Some(value),
+                "duration" => duration = Some(value),
+                key => return Err(VestingError::UnknownField(key.to_string())),
+            }
+        }
+        let period = period.ok_or(VestingError::MissingField("period"))?;
+        let duration = duration.ok_or(VestingError::MissingField("duration"))?;
+        if period.is_zero() {
+            return Err(VestingError::ZeroPeriod);
+        }
+        Ok(Self {
+            cliff: cliff.filter(|cliff| !cliff.is_zero()),
+            period,
+            duration,
+        })
+    }
+}
+
+impl fmt::Display for VestingSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(cliff) = self.cliff {
+            write!(f, "cliff={cliff};")?;
+        }
+        write!(f, "period={};duration={}", self.period, self.duration)
+    }
+}
+
+impl VestingSchedule {
+    /// Split `amount` into tranches vesting from `start`, returning each
+    /// tranche's unlock date and amount. The amounts add up to `amount`.
+    pub fn tranches(
+        &self,
+        start: DateTime<Utc>,
+        amount: u64,
+    ) -> Result<Vec<(DateTime<Utc>, u64)>, VestingError> {
+        let add = |length: VestingLength, date: DateTime<Utc>, multiple: u32| {
+            length
+                .checked_add_to(date, multiple)
+                .ok_or(VestingError::DateOutOfRange)
+        };
+        let end = add(self.duration, start, 1)?;
+        let (base, first_period) = match self.cliff {
+            Some(cliff) => (add(cliff, start, 1)?, 0),
+            None => (start, 1),
+        };
+        if base > end {
+            return Err(VestingError::CliffAfterEnd);
+        }
+
+        let mut unlock_dates = vec![];
+        for multiple in first_period.. {
+            let unlock_date = add(self.period, base, multiple)?;
+            if unlock_date >= end {
+                break;
+            }
+            if unlock_dates.len() == MAX_TRANCHES {
+                return Err(VestingError::TooManyTranches);
+            }
+            unlock_dates.push(unlock_date);
+        }
+        unlock_dates.push(end);
+
+        let total_seconds = (end - start).num_seconds().max(1) as u128;
+        let mut tranches = vec![];
+        let mut vested = 0;
+        for unlock_date in unlock_dates {
+            let elapsed_seconds = (unlock_date - start).num_seconds().max(0) as u128;
+            let cumulative = if unlock_date == end {
+                amount
+            } else {
+                (amount as u128 * elapsed_seconds / total_seconds) as u64
+            };
+            if cumulative > vested {
+                tranches.push((unlock_date, cumulative - vested));
+                vested = cumulative;
+            }
+        }
+        Ok(tranches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_vesting_schedule() {
+        let schedule = "cliff=12mo; period=1mo; duration=4y"
+            .parse::<VestingSchedule>()
+            .unwrap();
+        assert_eq!(
+            schedule,
+            VestingSchedule {
+                cliff: Some(VestingLength::Months(12)),
+                period: VestingLength::Months(1),
+                duration: VestingLength::Years(4),
+            }
+        );
+        assert_eq!(schedule.to_string(), "cliff=12mo;period=1mo;duration=4y");
+        assert_eq!(
+            schedule.to_string().parse::<VestingSchedule>().unwrap(),
+            schedule
+        );
+
+        assert_eq!(
+            "period=2w;duration=30d".parse::<VestingSchedule>(),
+            Ok(VestingSchedule {
+                cliff: None,
+                period: VestingLength::Weeks(2),
+                duration: VestingLength::Days(30),
+            })
+        );
+        assert_eq!(
+            "period=1mo".parse::<VestingSchedule>(),
+            Err(VestingError::MissingField("duration"))
+        );
+        assert_eq!(
+            "period=0d;duration=1y".parse::<VestingSchedule>(),
+            Err(VestingError::ZeroPeriod)
+        );
+        assert_eq!(
+            "period=1q;duration=1y".parse::<VestingSchedule>(),
+            Err(VestingError::InvalidLength("1q".to_string()))
+        );
+        assert_eq!(
+            "start=1d;period=1d;duration=1y".parse::<VestingSchedule>(),
+            Err(VestingError::UnknownField("start".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_vesting_tranches() {
+        let start = date("2024-01-31T00:00:00Z");
+        let schedule = "cliff=12mo;period=1mo;duration=48mo"
+            .parse::<VestingSchedule>()
+            .unwrap();
+        let amount = 48_000_000_001;
+        let tranches = schedule.tranches(start, amount).unwrap();
+
+        // A quarter unlocks at the cliff, then the rest monthly
+        assert_eq!(tranches.len(), 37);
+        assert_eq!(tranches[0].0, date("2025-01-31T00:00:00Z"));
+        assert_eq!(tranches[1].0, date("2025-02-28T00:00:00Z"));
+        assert_eq!(tranches[2].0, date("2025-03-31T00:00:00Z"));
+        assert_eq!(tranches[36].0, date("2028-01-31T00:00:00Z"));
+        assert!(tranches[0].1 > 11_900_000_000 && tranches[0].1 < 12_100_000_000);
+        assert_eq!(
+            tranches.iter().map(|(_, amount)| amount).sum::<u64>(),
+            amount
+        );
+        assert!(tranches.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        // Without a cliff, the first tranche unlocks after one period
+        let schedule = "period=1y;duration=3y".parse::<VestingSchedule>().unwrap();
+        assert_eq!(
+            schedule.tranches(start, 300).unwrap(),
+            vec![
+                (date("2025-01-31T00:00:00Z"), 100),
+                (date("2026-01-31T00:00:00Z"), 100),
+                (date("2027-01-31T00:00:00Z"), 100),
+            ]
+        );
+
+        let schedule = "cliff=2y;period=1mo;duration=1y"
+            .parse::<VestingSchedule>()
+            .unwrap();
+        assert_eq!(
+            schedule.tranches(start, 300),
+            Err(VestingError::CliffAfterEnd)
+        );
+
+        let schedule = "period=1d;duration=10y".parse::<VestingSchedule>().unwrap();
+        assert_eq!(
+            schedule.tranches(start, 300),
+            Err(VestingError::TooManyTranches)
+        );
+    }
+}
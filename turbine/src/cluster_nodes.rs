@@ -36,7 +36,7 @@ use {
     thiserror::Error,
 };
 
-const DATA_PLANE_FANOUT: usize = 200;
+pub const DATA_PLANE_FANOUT: usize = 200;
 pub(crate) const MAX_NUM_TURBINE_HOPS: usize = 4;
 
 #[derive(Debug, Error)]
@@ -331,7 +331,7 @@ fn get_nodes(cluster_info: &ClusterInfo, stakes: &HashMap<Pubkey, u64>) -> Vec<N
     .collect()
 }
 
-fn get_seeded_rng(leader: &Pubkey, shred: &ShredId) -> ChaChaRng {
+pub(crate) fn get_seeded_rng(leader: &Pubkey, shred: &ShredId) -> ChaChaRng {
     let seed = shred.seed(leader);
     ChaChaRng::from_seed(seed)
 }
@@ -349,7 +349,7 @@ fn get_seeded_rng(leader: &Pubkey, shred: &ShredId) -> ChaChaRng {
 // Each other node retransmits shreds to fanout many nodes in the next layer.
 // For example the node k in the 1st layer will retransmit to nodes:
 // fanout + k, 2*fanout + k, ..., fanout*fanout + k
-fn get_retransmit_peers<T: Copy>(
+pub(crate) fn get_retransmit_peers<T: Copy>(
     fanout: usize,
     index: usize, // Local node's index within the nodes slice.
     nodes: &[T],
@@ -369,7 +369,7 @@ fn get_retransmit_peers<T: Copy>(
 
 // Returns the parent node in the turbine broadcast tree.
 // Returns None if the node is the root of the tree.
-fn get_retransmit_parent<T: Copy>(
+pub(crate) fn get_retransmit_parent<T: Copy>(
     fanout: usize,
     index: usize, // Local node's index within the nodes slice.
     nodes: &[T],
@@ -573,6 +573,8 @@ pub fn check_feature_activation(feature: &Pubkey, shred_slot: Slot, root_bank: &
 mod tests {
     use {
         super::*,
+        crate::turbine_tree::TurbineNodes,
+        trezoa_ledger::shred::ShredType,
         std::{fmt::Debug, hash::Hash},
         test_case::test_case,
     };
@@ -794,6 +796,43 @@ mod tests {
         check_retransmit_nodes(/*fanout:*/ 3, &nodes, peers);
     }
 
+    #[test]
+    fn test_turbine_tree_matches_cluster_nodes() {
+        let mut rng = rand::thread_rng();
+        let (nodes, stakes, cluster_info) = make_test_cluster(&mut rng, 200, None);
+        let cluster_nodes = new_cluster_nodes::<RetransmitStage>(&cluster_info, &stakes);
+        // Nodes in gossip and staked nodes, as in get_nodes.
+        let stakes: HashMap<_, _> = stakes
+            .iter()
+            .filter(|(_, stake)| **stake > 0)
+            .map(|(pubkey, stake)| (*pubkey, *stake))
+            .chain(nodes.iter().map(|node| {
+                let stake = stakes.get(node.pubkey()).copied().unwrap_or_default();
+                (*node.pubkey(), stake)
+            }))
+            .collect();
+        let turbine_nodes = TurbineNodes::new(&stakes);
+        let slot_leader = *nodes[1].pubkey();
+        let shred = ShredId::new(7, 3, ShredType::Code);
+        let fanout = 8;
+        let tree = turbine_nodes.get_turbine_tree(&slot_leader, &shred, fanout);
+        let position = tree.position(&cluster_info.id()).unwrap();
+        let peers = cluster_nodes
+            .get_retransmit_peers(&slot_leader, &shred, fanout)
+            .unwrap();
+        assert_eq!(peers.root_distance, tree.layer(position).min(3));
+        assert_eq!(
+            peers
+                .children
+                .iter()
+                .map(|node| node.pubkey())
+                .collect::<Vec<_>>(),
+            tree.children(position)
+                .map(|child| tree.node(child).0)
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test_case(2, 1_347)]
     #[test_case(3, 1_359)]
     #[test_case(4, 4_296)]
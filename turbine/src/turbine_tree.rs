@@ -0,0 +1,506 @@
+//! Turbine retransmit trees computed from a stake distribution alone, and a
+//! simulator of shreds propagating through them.
+//!
+//! Nodes are ordered and shuffled the same way `ClusterNodes` does for the
+//! retransmit stage, so the tree of a shred is the one the cluster would use
+//! if every node in the stake distribution had its contact-info in gossip.
+//! This allows evaluating fanout and stake distribution changes offline.
+
+use {
+    crate::cluster_nodes::{get_retransmit_parent, get_retransmit_peers, get_seeded_rng},
+    rand::{Rng, SeedableRng},
+    rand_chacha::ChaChaRng,
+    trezoa_gossip::weighted_shuffle::WeightedShuffle,
+    trezoa_ledger::shred::{ShredId, ShredType},
+    trezoa_sdk::{clock::Slot, pubkey::Pubkey},
+    std::{
+        cmp::Reverse,
+        collections::HashMap,
+        fmt::{self, Display, Formatter},
+        time::Duration,
+    },
+};
+
+/// Nodes of the cluster sorted by (stake, pubkey) in descending order, as in
+/// `ClusterNodes`.
+pub struct TurbineNodes {
+    nodes: Vec<(Pubkey, /*stake:*/ u64)>,
+    index: HashMap<Pubkey, /*index:*/ usize>,
+    weighted_shuffle: WeightedShuffle</*stake:*/ u64>,
+}
+
+/// Retransmit tree of a shred.
+pub struct TurbineTree<'a> {
+    nodes: &'a TurbineNodes,
+    fanout: usize,
+    // Indices of the nodes in the order of the shuffle, the root first.
+    // The slot leader is not part of the tree.
+    order: Vec<usize>,
+    // Reverse index from the nodes to their position in the tree.
+    positions: Vec<Option<usize>>,
+}
+
+impl TurbineNodes {
+    /// Unstaked nodes known from gossip may be included with zero stake.
+    pub fn new(stakes: &HashMap<Pubkey, u64>) -> Self {
+        let mut nodes: Vec<_> = stakes
+            .iter()
+            .map(|(&pubkey, &stake)| (pubkey, stake))
+            .collect();
+        nodes.sort_unstable_by_key(|&(pubkey, stake)| Reverse((stake, pubkey)));
+        let index = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, (pubkey, _))| (*pubkey, index))
+            .collect();
+        let stakes: Vec<u64> = nodes.iter().map(|(_, stake)| *stake).collect();
+        let weighted_shuffle = WeightedShuffle::new("turbine-tree", &stakes);
+        Self {
+            nodes,
+            index,
+            weighted_shuffle,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn total_stake(&self) -> u64 {
+        self.nodes.iter().map(|(_, stake)| stake).sum()
+    }
+
+    /// Picks a slot leader with probability proportional to its stake.
+    pub fn sample_leader<R: Rng>(&self, rng: &mut R) -> Option<Pubkey> {
+        let index = self.weighted_shuffle.first(rng)?;
+        Some(self.nodes[index].0)
+    }
+
+    pub fn get_turbine_tree(
+        &self,
+        slot_leader: &Pubkey,
+        shred: &ShredId,
+        fanout: usize,
+    ) -> TurbineTree {
+        // Layers would never grow, and TurbineTree::layer would not return.
+        assert!(fanout > 0, "turbine fanout must be positive");
+        let mut weighted_shuffle = self.weighted_shuffle.clone();
+        // Exclude slot leader from list of nodes.
+        if let Some(index) = self.index.get(slot_leader) {
+            weighted_shuffle.remove_index(*index);
+        }
+        let mut rng = get_seeded_rng(slot_leader, shred);
+        let order: Vec<usize> = weighted_shuffle.shuffle(&mut rng).collect();
+        let mut positions = vec![None; self.nodes.len()];
+        for (position, &index) in order.iter().enumerate() {
+            positions[index] = Some(position);
+        }
+        TurbineTree {
+            nodes: self,
+            fanout,
+            order,
+            positions,
+        }
+    }
+}
+
+impl TurbineTree<'_> {
+    pub fn fanout(&self) -> usize {
+        self.fanout
+    }
+
+    /// Number of nodes in the tree, excluding the slot leader.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Pubkey and stake of the node at the position, the root being at 0.
+    pub fn node(&self, position: usize) -> (Pubkey, u64) {
+        self.nodes.nodes[self.order[position]]
+    }
+
+    /// Position of the node in the tree, None for the slot leader or unknown
+    /// nodes.
+    pub fn position(&self, pubkey: &Pubkey) -> Option<usize> {
+        self.positions[*self.nodes.index.get(pubkey)?]
+    }
+
+    /// Position of the node's parent, None for the root.
+    pub fn parent(&self, position: usize) -> Option<usize> {
+        let parent = get_retransmit_parent(self.fanout, position, &self.order)?;
+        self.positions[parent]
+    }
+
+    /// Positions of the nodes the node retransmits shreds to.
+    pub fn children(&self, position: usize) -> impl Iterator<Item = usize> + '_ {
+        get_retransmit_peers(self.fanout, position, &self.order)
+            .filter_map(|index| self.positions[index])
+    }
+
+    /// Distance from the root, i.e. the number of retransmit hops a shred
+    /// takes to reach the node.
+    pub fn layer(&self, position: usize) -> usize {
+        // The root alone is in layer 0, and each node in the next layers has
+        // fanout many children.
+        let mut layer = 0;
+        let mut layer_size = 1usize;
+        let mut layer_end = 1usize;
+        while position >= layer_end {
+            layer += 1;
+            layer_size = layer_size.saturating_mul(self.fanout);
+            layer_end = layer_end.saturating_add(layer_size);
+        }
+        layer
+    }
+
+    /// Number of nodes and their total stake in each layer.
+    pub fn layers(&self) -> Vec<(/*num nodes:*/ usize, /*stake:*/ u64)> {
+        let mut layers = Vec::new();
+        for position in 0..self.len() {
+            let layer = self.layer(position);
+            if layers.len() <= layer {
+                layers.resize(layer + 1, (0, 0));
+            }
+            layers[layer].0 += 1;
+            layers[layer].1 += self.node(position).1;
+        }
+        layers
+    }
+
+    fn fmt_subtree(&self, f: &mut Formatter, position: usize, depth: usize) -> fmt::Result {
+        let (pubkey, stake) = self.node(position);
+        writeln!(
+            f,
+            "{:indent$}{position:>6} {pubkey} {stake}",
+            "",
+            indent = 2 * depth
+        )?;
+        for child in self.children(position) {
+            self.fmt_subtree(f, child, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for TurbineTree<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let total_stake = self.nodes.total_stake().max(1);
+        writeln!(f, "fanout: {}, nodes: {}", self.fanout, self.len())?;
+        for (layer, (num_nodes, stake)) in self.layers().into_iter().enumerate() {
+            writeln!(
+                f,
+                "layer {layer}: {num_nodes} nodes, {:.2}% of stake",
+                stake as f64 * 100.0 / total_stake as f64
+            )?;
+        }
+        if !self.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{:>6} {:<44} Stake", "Index", "Pubkey")?;
+            self.fmt_subtree(f, /*position:*/ 0, /*depth:*/ 0)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    pub fanout: usize,
+    /// Data shreds in each erasure batch; as many shreds of the batch are
+    /// needed to recover it.
+    pub num_data_shreds: usize,
+    pub num_coding_shreds: usize,
+    /// Erasure batches to simulate, each from a slot leader picked by stake.
+    pub num_batches: usize,
+    /// Probability a node, other than the slot leader, is down for a batch.
+    pub node_failure_rate: f64,
+    /// Probability a shred is lost on each hop.
+    pub packet_loss_rate: f64,
+    /// Latency of each hop, uniformly distributed within the jitter.
+    pub hop_latency: Duration,
+    pub hop_latency_jitter: Duration,
+    /// Seed of the simulation, so that runs are reproducible.
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            fanout: crate::cluster_nodes::DATA_PLANE_FANOUT,
+            num_data_shreds: 32,
+            num_coding_shreds: 32,
+            num_batches: 100,
+            node_failure_rate: 0.0,
+            packet_loss_rate: 0.0,
+            hop_latency: Duration::from_millis(20),
+            hop_latency_jitter: Duration::from_millis(10),
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    fn new(mut samples: Vec<Duration>) -> Self {
+        samples.sort_unstable();
+        let percentile = |p: usize| {
+            let index = (samples.len() * p / 100).min(samples.len().saturating_sub(1));
+            samples.get(index).copied().unwrap_or_default()
+        };
+        Self {
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: samples.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+impl Display for LatencyStats {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+            self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimulationReport {
+    pub num_batches: usize,
+    /// Share of the shreds sent to live nodes which reached them.
+    pub delivery_rate: f64,
+    /// Share of the erasure batches live nodes received enough shreds of to
+    /// recover them.
+    pub recovery_rate: f64,
+    /// Same as recovery_rate, weighted by the nodes' stake.
+    pub stake_recovery_rate: f64,
+    /// Time from the slot leader broadcasting a shred to a node receiving it.
+    pub shred_latency: LatencyStats,
+    /// Time from the slot leader broadcasting an erasure batch to a node
+    /// receiving enough of its shreds to recover it.
+    pub recovery_latency: LatencyStats,
+    /// Most retransmit hops a shred took, excluding the hop from the leader.
+    pub max_layer: usize,
+}
+
+impl Display for SimulationReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "Erasure batches: {}", self.num_batches)?;
+        writeln!(f, "Max layer: {}", self.max_layer)?;
+        writeln!(f, "Shreds delivered: {:.4}%", self.delivery_rate * 100.0)?;
+        writeln!(
+            f,
+            "Batches recovered: {:.4}% ({:.4}% of stake)",
+            self.recovery_rate * 100.0,
+            self.stake_recovery_rate * 100.0
+        )?;
+        writeln!(f, "Shred latency: {}", self.shred_latency)?;
+        write!(f, "Recovery latency: {}", self.recovery_latency)
+    }
+}
+
+/// Simulates broadcasting erasure batches through their turbine trees.
+///
+/// Nodes retransmit the shreds they receive to their children, but shreds
+/// recovered from the erasure codes are not retransmitted, so the delivery
+/// and recovery rates under loss are lower bounds.
+pub fn simulate(nodes: &TurbineNodes, config: &SimulationConfig) -> SimulationReport {
+    let mut rng = ChaChaRng::seed_from_u64(config.seed);
+    let num_shreds = config.num_data_shreds + config.num_coding_shreds;
+    let shreds: Vec<_> = (0..config.num_data_shreds)
+        .map(|index| (index as u32, ShredType::Data))
+        .chain((0..config.num_coding_shreds).map(|index| (index as u32, ShredType::Code)))
+        .collect();
+    let mut shred_latencies = Vec::new();
+    let mut recovery_latencies = Vec::new();
+    let mut num_sent = 0u64;
+    let mut num_recovered = 0u64;
+    let mut num_live = 0u64;
+    let mut stake_recovered = 0u128;
+    let mut stake_live = 0u128;
+    let mut max_layer = 0;
+    // Arrival time of each shred of the batch at each node.
+    let mut arrivals: Vec<Vec<Duration>> = vec![Vec::with_capacity(num_shreds); nodes.len()];
+    for batch in 0..config.num_batches {
+        let Some(slot_leader) = nodes.sample_leader(&mut rng) else {
+            break;
+        };
+        let slot = batch as Slot;
+        let live: Vec<bool> = nodes
+            .nodes
+            .iter()
+            .map(|(pubkey, _)| pubkey == &slot_leader || !rng.gen_bool(config.node_failure_rate))
+            .collect();
+        arrivals.iter_mut().for_each(Vec::clear);
+        for &(index, shred_type) in &shreds {
+            let shred = ShredId::new(slot, index, shred_type);
+            let tree = nodes.get_turbine_tree(&slot_leader, &shred, config.fanout);
+            // Arrival time at each position in the tree.
+            let mut arrival = vec![None; tree.len()];
+            if !tree.is_empty() {
+                arrival[0] = send(&mut rng, config, Duration::ZERO);
+            }
+            for position in 0..tree.len() {
+                let index = tree.order[position];
+                if !live[index] {
+                    arrival[position] = None;
+                }
+                let Some(time) = arrival[position] else {
+                    continue;
+                };
+                arrivals[index].push(time);
+                shred_latencies.push(time);
+                max_layer = max_layer.max(tree.layer(position));
+                for child in tree.children(position) {
+                    arrival[child] = send(&mut rng, config, time);
+                }
+            }
+        }
+        for (index, (pubkey, stake)) in nodes.nodes.iter().enumerate() {
+            if !live[index] || pubkey == &slot_leader {
+                continue;
+            }
+            num_sent += num_shreds as u64;
+            num_live += 1;
+            stake_live += u128::from(*stake);
+            let arrivals = &mut arrivals[index];
+            if arrivals.len() >= config.num_data_shreds {
+                arrivals.sort_unstable();
+                let time = config
+                    .num_data_shreds
+                    .checked_sub(1)
+                    .map(|k| arrivals[k])
+                    .unwrap_or_default();
+                recovery_latencies.push(time);
+                num_recovered += 1;
+                stake_recovered += u128::from(*stake);
+            }
+        }
+    }
+    let ratio = |num: u128, den: u128| {
+        if den == 0 {
+            0.0
+        } else {
+            num as f64 / den as f64
+        }
+    };
+    SimulationReport {
+        num_batches: config.num_batches,
+        delivery_rate: ratio(shred_latencies.len() as u128, u128::from(num_sent)),
+        recovery_rate: ratio(u128::from(num_recovered), u128::from(num_live)),
+        stake_recovery_rate: ratio(stake_recovered, stake_live),
+        shred_latency: LatencyStats::new(shred_latencies),
+        recovery_latency: LatencyStats::new(recovery_latencies),
+        max_layer,
+    }
+}
+
+// Returns the arrival time of a shred sent at the given time, or None if it
+// is lost.
+fn send<R: Rng>(rng: &mut R, config: &SimulationConfig, time: Duration) -> Option<Duration> {
+    if rng.gen_bool(config.packet_loss_rate) {
+        return None;
+    }
+    let jitter = config.hop_latency_jitter.as_micros() as i64;
+    let latency = config.hop_latency.as_micros() as i64 + rng.gen_range(-jitter..=jitter);
+    Some(time + Duration::from_micros(latency.max(0) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::collections::HashSet};
+
+    fn make_stakes(num_nodes: usize) -> HashMap<Pubkey, u64> {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        (0..num_nodes)
+            .map(|_| (Pubkey::new_unique(), rng.gen_range(1..1_000)))
+            .collect()
+    }
+
+    #[test]
+    #[should_panic(expected = "turbine fanout must be positive")]
+    fn test_turbine_tree_zero_fanout() {
+        let stakes = make_stakes(10);
+        let nodes = TurbineNodes::new(&stakes);
+        let slot_leader = nodes.nodes[0].0;
+        let shred = ShredId::new(42, 7, ShredType::Data);
+        nodes.get_turbine_tree(&slot_leader, &shred, /*fanout:*/ 0);
+    }
+
+    #[test]
+    fn test_turbine_tree() {
+        let stakes = make_stakes(1_000);
+        let nodes = TurbineNodes::new(&stakes);
+        let slot_leader = nodes.nodes[3].0;
+        let shred = ShredId::new(42, 7, ShredType::Data);
+        let tree = nodes.get_turbine_tree(&slot_leader, &shred, /*fanout:*/ 10);
+        assert_eq!(tree.len(), 999);
+        assert_eq!(tree.position(&slot_leader), None);
+        assert_eq!(tree.parent(0), None);
+        assert_eq!(tree.layer(0), 0);
+        assert_eq!(tree.layer(10), 1);
+        assert_eq!(tree.layer(11), 2);
+        assert_eq!(tree.layer(110), 2);
+        assert_eq!(tree.layer(111), 3);
+        let layers = tree.layers();
+        assert_eq!(
+            layers
+                .iter()
+                .map(|(num_nodes, _)| *num_nodes)
+                .collect::<Vec<_>>(),
+            vec![1, 10, 100, 888]
+        );
+        // Every node but the root is the child of its parent, once.
+        let mut seen = HashSet::new();
+        for position in 1..tree.len() {
+            let parent = tree.parent(position).unwrap();
+            assert_eq!(tree.layer(parent) + 1, tree.layer(position));
+            assert!(tree.children(parent).any(|child| child == position));
+            assert!(seen.insert(position));
+        }
+    }
+
+    #[test]
+    fn test_simulate() {
+        let stakes = make_stakes(500);
+        let nodes = TurbineNodes::new(&stakes);
+        let config = SimulationConfig {
+            fanout: 32,
+            num_batches: 4,
+            ..SimulationConfig::default()
+        };
+        let report = simulate(&nodes, &config);
+        assert_eq!(report.delivery_rate, 1.0);
+        assert_eq!(report.recovery_rate, 1.0);
+        assert_eq!(report.stake_recovery_rate, 1.0);
+        assert_eq!(report.max_layer, 2);
+        assert!(report.shred_latency.max <= 3 * Duration::from_millis(30));
+        // Runs are reproducible.
+        assert_eq!(simulate(&nodes, &config), report);
+
+        let config = SimulationConfig {
+            node_failure_rate: 0.2,
+            packet_loss_rate: 0.1,
+            ..config
+        };
+        let report = simulate(&nodes, &config);
+        assert!(report.delivery_rate < 1.0);
+        assert!(report.recovery_rate > 0.0);
+    }
+}
@@ -16,7 +16,10 @@ use {
         validator::ValidatorStartProgress,
     },
     trezoa_geyser_plugin_manager::GeyserPluginManagerRequest,
-    trezoa_gossip::contact_info::{ContactInfo, Protocol, SOCKET_ADDR_UNSPECIFIED},
+    trezoa_gossip::{
+        contact_info::{ContactInfo, Protocol, SOCKET_ADDR_UNSPECIFIED},
+        crds_value::CrdsData,
+    },
     trezoa_rpc::rpc::verify_pubkey,
     trezoa_rpc_client_api::{config::RpcAccountIndex, custom_error::RpcCustomError},
     trezoa_sdk::{
@@ -24,6 +27,10 @@ use {
         pubkey::Pubkey,
         signature::{read_keypair_file, Keypair, Signer},
     },
+    trezoa_streamer::nonblocking::{
+        connection_registry::{ConnectionInfo, ConnectionRegistry, PeerFilter, MAX_BLOCK_DURATION},
+        quic::ConnectionPeerType,
+    },
     std::{
         collections::{HashMap, HashSet},
         error,
@@ -37,6 +44,9 @@ use {
     tokio::runtime::Runtime,
 };
 
+/// Most values a single gossipDump request returns
+pub const MAX_GOSSIP_DUMP_VALUES: usize = 1_000;
+
 #[derive(Clone)]
 pub struct AdminRpcRequestMetadata {
     pub rpc_addr: Option<SocketAddr>,
@@ -89,6 +99,68 @@ pub struct AdminRpcRepairWhitelist {
     pub whitelist: Vec<Pubkey>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminRpcQuicConnection {
+    pub server: String,
+    pub remote_address: SocketAddr,
+    pub identity: Option<String>,
+    pub staked: bool,
+    pub stake: u64,
+    pub open_streams: usize,
+    pub bytes_received: u64,
+    pub packets_received: u64,
+    pub age_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminRpcQuicBlockedPeer {
+    pub peer: String,
+    pub remaining_secs: u64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AdminRpcQuicConnections {
+    pub connections: Vec<AdminRpcQuicConnection>,
+    pub blocked: Vec<AdminRpcQuicBlockedPeer>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdminRpcCrdsValue {
+    pub data_type: String,
+    pub origin: String,
+    pub wallclock: u64,
+    /// Local time the value was inserted into the crds table
+    pub insert_timestamp: u64,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AdminRpcGossipDump {
+    pub values: Vec<AdminRpcCrdsValue>,
+    /// Values matching the request, including those outside of the page
+    pub num_matching: usize,
+}
+
+impl AdminRpcQuicConnection {
+    fn new(server: &str, info: ConnectionInfo) -> Self {
+        let (staked, stake) = match info.peer_type {
+            ConnectionPeerType::Staked(stake) => (true, stake),
+            ConnectionPeerType::Unstaked => (false, 0),
+        };
+        Self {
+            server: server.to_string(),
+            remote_address: info.remote_address,
+            identity: info.client_pubkey.map(|pubkey| pubkey.to_string()),
+            staked,
+            stake,
+            open_streams: info.open_streams,
+            bytes_received: info.bytes_received,
+            packets_received: info.packets_received,
+            age_secs: info.age.as_secs(),
+        }
+    }
+}
+
 impl From<ContactInfo> for AdminRpcContactInfo {
     fn from(node: ContactInfo) -> Self {
         macro_rules! unwrap_socket {
@@ -140,6 +212,61 @@ impl Display for AdminRpcRepairWhitelist {
     }
 }
 
+impl Display for AdminRpcGossipDump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for value in &self.values {
+            writeln!(
+                f,
+                "{} from {}, wallclock: {}, inserted: {}",
+                value.data_type, value.origin, value.wallclock, value.insert_timestamp
+            )?;
+            writeln!(
+                f,
+                "{}",
+                serde_json::to_string_pretty(&value.value).map_err(|_| fmt::Error)?
+            )?;
+        }
+        writeln!(f, "{} of {} values", self.values.len(), self.num_matching)
+    }
+}
+
+impl Display for AdminRpcQuicConnections {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<13} {:<40} {:<44} {:>18} {:>7} {:>12} {:>9} {:>8}",
+            "Server", "Remote Address", "Identity", "Stake", "Streams", "Bytes", "Packets", "Age"
+        )?;
+        for connection in &self.connections {
+            writeln!(
+                f,
+                "{:<13} {:<40} {:<44} {:>18} {:>7} {:>12} {:>9} {:>7}s",
+                connection.server,
+                connection.remote_address,
+                connection.identity.as_deref().unwrap_or("-"),
+                if connection.staked {
+                    connection.stake.to_string()
+                } else {
+                    "unstaked".to_string()
+                },
+                connection.open_streams,
+                connection.bytes_received,
+                connection.packets_received,
+                connection.age_secs,
+            )?;
+        }
+        writeln!(f, "{} connections", self.connections.len())?;
+        for blocked in &self.blocked {
+            writeln!(
+                f,
+                "Blocked: {} for {}s",
+                blocked.peer, blocked.remaining_secs
+            )?;
+        }
+        Ok(())
+    }
+}
+
 #[rpc]
 pub trait AdminRpc {
     type Metadata;
@@ -243,6 +370,39 @@ pub trait AdminRpc {
         meta: Self::Metadata,
         public_tpu_forwards_addr: SocketAddr,
     ) -> Result<()>;
+
+    #[rpc(meta, name = "quicConnections")]
+    fn quic_connections(&self, meta: Self::Metadata) -> Result<AdminRpcQuicConnections>;
+
+    #[rpc(meta, name = "evictQuicPeer")]
+    fn evict_quic_peer(&self, meta: Self::Metadata, peer: String) -> Result<usize>;
+
+    #[rpc(meta, name = "blockQuicPeer")]
+    fn block_quic_peer(
+        &self,
+        meta: Self::Metadata,
+        peer: String,
+        duration_secs: u64,
+    ) -> Result<usize>;
+
+    #[rpc(meta, name = "unblockQuicPeer")]
+    fn unblock_quic_peer(&self, meta: Self::Metadata, peer: String) -> Result<bool>;
+
+    #[rpc(meta, name = "gossipDump")]
+    fn gossip_dump(
+        &self,
+        meta: Self::Metadata,
+        data_type: Option<String>,
+        origin: Option<String>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<AdminRpcGossipDump>;
+
+    #[rpc(meta, name = "startGossipCapture")]
+    fn start_gossip_capture(&self, meta: Self::Metadata, path: String) -> Result<()>;
+
+    #[rpc(meta, name = "stopGossipCapture")]
+    fn stop_gossip_capture(&self, meta: Self::Metadata) -> Result<Option<usize>>;
 }
 
 pub struct AdminRpcImpl;
@@ -674,6 +834,193 @@ impl AdminRpc for AdminRpcImpl {
             Ok(())
         })
     }
+
+    fn quic_connections(&self, meta: Self::Metadata) -> Result<AdminRpcQuicConnections> {
+        debug!("quic_connections rpc request received");
+
+        meta.with_post_init(|post_init| {
+            let mut quic_connections = AdminRpcQuicConnections::default();
+            let mut blocked = HashMap::<PeerFilter, Duration>::new();
+            for (server, registry) in quic_connection_registries(post_init) {
+                quic_connections.connections.extend(
+                    registry
+                        .connections()
+                        .into_iter()
+                        .map(|info| AdminRpcQuicConnection::new(server, info)),
+                );
+                for (peer, remaining) in registry.blocked() {
+                    let entry = blocked.entry(peer).or_default();
+                    *entry = (*entry).max(remaining);
+                }
+            }
+            quic_connections.blocked = blocked
+                .into_iter()
+                .map(|(peer, remaining)| AdminRpcQuicBlockedPeer {
+                    peer: peer.to_string(),
+                    remaining_secs: remaining.as_secs(),
+                })
+                .collect();
+            Ok(quic_connections)
+        })
+    }
+
+    fn evict_quic_peer(&self, meta: Self::Metadata, peer: String) -> Result<usize> {
+        debug!("evict_quic_peer rpc request received: {peer}");
+        let peer = parse_peer_filter(&peer)?;
+
+        meta.with_post_init(|post_init| {
+            let evicted: usize = quic_connection_registries(post_init)
+                .map(|(_, registry)| registry.evict(&peer))
+                .sum();
+            warn!("Evicted {evicted} QUIC connections of {peer}");
+            Ok(evicted)
+        })
+    }
+
+    fn block_quic_peer(
+        &self,
+        meta: Self::Metadata,
+        peer: String,
+        duration_secs: u64,
+    ) -> Result<usize> {
+        debug!("block_quic_peer rpc request received: {peer} for {duration_secs}s");
+        let peer = parse_peer_filter(&peer)?;
+        let duration = Duration::from_secs(duration_secs);
+        if duration > MAX_BLOCK_DURATION {
+            return Err(jsonrpc_core::error::Error::invalid_params(format!(
+                "Block duration {duration_secs}s exceeds the maximum of {}s",
+                MAX_BLOCK_DURATION.as_secs()
+            )));
+        }
+
+        meta.with_post_init(|post_init| {
+            let evicted: usize = quic_connection_registries(post_init)
+                .map(|(_, registry)| registry.block(peer, duration))
+                .sum();
+            warn!("Blocked {peer} from QUIC for {duration_secs}s, evicted {evicted} connections");
+            Ok(evicted)
+        })
+    }
+
+    fn unblock_quic_peer(&self, meta: Self::Metadata, peer: String) -> Result<bool> {
+        debug!("unblock_quic_peer rpc request received: {peer}");
+        let peer = parse_peer_filter(&peer)?;
+
+        meta.with_post_init(|post_init| {
+            let mut unblocked = false;
+            for (_, registry) in quic_connection_registries(post_init) {
+                unblocked |= registry.unblock(&peer);
+            }
+            if unblocked {
+                warn!("Unblocked {peer} from QUIC");
+            }
+            Ok(unblocked)
+        })
+    }
+
+    fn gossip_dump(
+        &self,
+        meta: Self::Metadata,
+        data_type: Option<String>,
+        origin: Option<String>,
+        offset: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<AdminRpcGossipDump> {
+        debug!(
+            "gossip_dump rpc request received: {data_type:?} from {origin:?}, offset \
+             {offset:?}, limit {limit:?}"
+        );
+        if let Some(data_type) = &data_type {
+            if !CrdsData::NAMES.contains(&data_type.as_str()) {
+                return Err(jsonrpc_core::error::Error::invalid_params(format!(
+                    "Unknown data type {data_type}, expected one of {}",
+                    CrdsData::NAMES.join(", ")
+                )));
+            }
+        }
+        let origin = origin.as_deref().map(verify_pubkey).transpose()?;
+        let limit = limit
+            .unwrap_or(MAX_GOSSIP_DUMP_VALUES)
+            .min(MAX_GOSSIP_DUMP_VALUES);
+
+        meta.with_post_init(|post_init| {
+            let (values, num_matching) = post_init.cluster_info.get_crds_values(
+                data_type.as_deref(),
+                origin.as_ref(),
+                offset.unwrap_or_default(),
+                limit,
+            );
+            let values = values
+                .into_iter()
+                .map(|(value, insert_timestamp)| {
+                    Ok(AdminRpcCrdsValue {
+                        data_type: value.data.name().to_string(),
+                        origin: value.pubkey().to_string(),
+                        wallclock: value.wallclock(),
+                        insert_timestamp,
+                        value: serde_json::to_value(&value.data).map_err(|err| {
+                            jsonrpc_core::error::Error::invalid_params(format!(
+                                "Failed to serialize {} value: {err}",
+                                value.data.name()
+                            ))
+                        })?,
+                    })
+                })
+                .collect::<Result<_>>()?;
+            Ok(AdminRpcGossipDump {
+                values,
+                num_matching,
+            })
+        })
+    }
+
+    fn start_gossip_capture(&self, meta: Self::Metadata, path: String) -> Result<()> {
+        debug!("start_gossip_capture rpc request received: {path}");
+        meta.with_post_init(|post_init| {
+            post_init
+                .cluster_info
+                .start_gossip_capture(Path::new(&path))
+                .map_err(|err| {
+                    jsonrpc_core::error::Error::invalid_params(format!(
+                        "Failed to start gossip capture to {path}: {err}"
+                    ))
+                })?;
+            warn!("Started gossip capture to {path}");
+            Ok(())
+        })
+    }
+
+    fn stop_gossip_capture(&self, meta: Self::Metadata) -> Result<Option<usize>> {
+        debug!("stop_gossip_capture rpc request received");
+        meta.with_post_init(|post_init| {
+            let recorded = post_init
+                .cluster_info
+                .stop_gossip_capture()
+                .map_err(|err| {
+                    error!("Failed to finish gossip capture: {err}");
+                    jsonrpc_core::error::Error::internal_error()
+                })?;
+            if let Some(recorded) = recorded {
+                warn!("Stopped gossip capture, recorded {recorded} messages");
+            }
+            Ok(recorded)
+        })
+    }
+}
+
+fn quic_connection_registries(
+    post_init: &AdminRpcRequestMetadataPostInit,
+) -> impl Iterator<Item = (&'static str, &Arc<ConnectionRegistry>)> {
+    [
+        ("tpu", &post_init.tpu_connection_registry),
+        ("tpu_forwards", &post_init.tpu_forwards_connection_registry),
+    ]
+    .into_iter()
+}
+
+fn parse_peer_filter(peer: &str) -> Result<PeerFilter> {
+    peer.parse()
+        .map_err(jsonrpc_core::error::Error::invalid_params)
 }
 
 impl AdminRpcImpl {
@@ -894,7 +1241,7 @@ mod tests {
     }
 
     impl RpcHandler {
-        fn _start() -> Self {
+        fn start() -> Self {
             Self::start_with_config(TestConfig::default())
         }
 
@@ -937,6 +1284,8 @@ mod tests {
                     cluster_slots: Arc::new(
                         trezoa_core::cluster_slots_service::cluster_slots::ClusterSlots::default(),
                     ),
+                    tpu_connection_registry: Arc::default(),
+                    tpu_forwards_connection_registry: Arc::default(),
                 }))),
                 staked_nodes_overrides: Arc::new(RwLock::new(HashMap::new())),
                 rpc_to_plugin_manager_sender: None,
@@ -969,6 +1318,159 @@ mod tests {
         (BankForks::new_rw_arc(bank), Arc::new(voting_keypair))
     }
 
+    #[test]
+    fn test_quic_peer_blocking() {
+        let RpcHandler { io, meta, .. } = RpcHandler::start();
+        let peer = Pubkey::new_unique();
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"blockQuicPeer","params":["{peer}", 60]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["result"], 0);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"quicConnections"}"#;
+        let res = io.handle_request_sync(req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let quic_connections: AdminRpcQuicConnections =
+            serde_json::from_value(result["result"].clone()).unwrap();
+        assert!(quic_connections.connections.is_empty());
+        assert_eq!(quic_connections.blocked.len(), 1);
+        assert_eq!(quic_connections.blocked[0].peer, peer.to_string());
+        assert!(quic_connections.blocked[0].remaining_secs <= 60);
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"unblockQuicPeer","params":["{peer}"]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["result"], true);
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"blockQuicPeer","params":["{peer}", {}]}}"#,
+            u64::MAX
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert!(result["error"].is_object());
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"evictQuicPeer","params":["not a peer"]}"#;
+        let res = io.handle_request_sync(req, meta);
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert!(result["error"].is_object());
+    }
+
+    #[test]
+    fn test_gossip_dump() {
+        let RpcHandler { io, meta, .. } = RpcHandler::start();
+        let id = meta
+            .post_init
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .cluster_info
+            .id();
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"gossipDump","params":["ContactInfo", "{id}"]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let gossip_dump: AdminRpcGossipDump =
+            serde_json::from_value(result["result"].clone()).unwrap();
+        assert_eq!(gossip_dump.values.len(), 1);
+        assert_eq!(gossip_dump.num_matching, 1);
+        assert_eq!(gossip_dump.values[0].data_type, "ContactInfo");
+        assert_eq!(gossip_dump.values[0].origin, id.to_string());
+        assert!(gossip_dump.values[0].value["ContactInfo"].is_object());
+
+        // Pages past the matching values are empty
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"gossipDump","params":[null, "{id}", 1, 10]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let gossip_dump: AdminRpcGossipDump =
+            serde_json::from_value(result["result"].clone()).unwrap();
+        assert!(gossip_dump.num_matching > 1);
+        assert_eq!(gossip_dump.values.len(), gossip_dump.num_matching - 1);
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"gossipDump","params":[null, "{id}", 0, 1]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["result"]["values"].as_array().unwrap().len(), 1);
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"gossipDump","params":["Vote", "{id}"]}}"#,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["result"]["values"], Value::Array(vec![]));
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"gossipDump","params":["Votes"]}"#;
+        let res = io.handle_request_sync(req, meta);
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert!(result["error"].is_object());
+    }
+
+    #[test]
+    fn test_gossip_capture() {
+        let RpcHandler { io, meta, .. } = RpcHandler::start();
+        let path = std::env::temp_dir().join(format!("gossip-capture-{}", Pubkey::new_unique()));
+
+        let req = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "startGossipCapture",
+            "params": [path],
+        })
+        .to_string();
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["result"], Value::Null);
+        assert!(result["error"].is_null());
+        assert!(path.exists());
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"stopGossipCapture"}"#;
+        let res = io.handle_request_sync(req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert!(result["result"].is_u64());
+
+        // No capture is running anymore
+        let res = io.handle_request_sync(req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["result"], Value::Null);
+        std::fs::remove_file(&path).unwrap();
+
+        let req = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "startGossipCapture",
+            "params": [path.join("not-a-directory")],
+        })
+        .to_string();
+        let res = io.handle_request_sync(&req, meta);
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert!(result["error"].is_object());
+    }
+
     #[test]
     fn test_secondary_index_key_sizes() {
         for secondary_index_enabled in [true, false] {
@@ -167,7 +167,8 @@ fn start_gossip_node(
         gossip_socket,
         gossip_validators,
         should_check_duplicate_instance,
-        None,
+        None, // stats_reporter_sender
+        None, // quic_endpoint
         gossip_exit_flag.clone(),
     );
     (cluster_info, gossip_exit_flag, gossip_service)
@@ -24,6 +24,7 @@ use {
         validator::{BlockProductionMethod, BlockVerificationMethod},
     },
     trezoa_faucet::faucet::{self, FAUCET_PORT},
+    trezoa_gossip::crds_value::CrdsData,
     trezoa_ledger::use_snapshot_archives_at_startup,
     trezoa_net_utils::{MINIMUM_VALIDATOR_PORT_RANGE_WIDTH, VALIDATOR_PORT_RANGE},
     trezoa_rayon_threadlimit::get_thread_count,
@@ -44,9 +45,13 @@ use {
         clock::Slot, epoch_schedule::MINIMUM_SLOTS_PER_EPOCH, hash::Hash, quic::QUIC_PORT_OFFSET,
         rpc_port,
     },
-    trezoa_send_transaction_service::send_transaction_service::{
-        self, MAX_BATCH_SEND_RATE_MS, MAX_TRANSACTION_BATCH_SIZE,
+    trezoa_send_transaction_service::{
+        forwarding_policy::{
+            DEFAULT_STAKE_WEIGHTED_MAX_LEADERS, DEFAULT_STAKE_WEIGHTED_STAKE_THRESHOLD,
+        },
+        send_transaction_service::{self, MAX_BATCH_SEND_RATE_MS, MAX_TRANSACTION_BATCH_SIZE},
     },
+    trezoa_streamer::nonblocking::{connection_registry::PeerFilter, rate_limiter::RateLimit},
     trezoa_tpu_client::tpu_client::DEFAULT_TPU_CONNECTION_POOL_SIZE,
     trezoa_unified_scheduler_pool::DefaultSchedulerPool,
     std::{path::PathBuf, str::FromStr},
@@ -830,6 +835,17 @@ pub fn app<'a>(version: &'a str, default_args: &'a DefaultArgs) -> App<'a, 'a> {
                      other validators. [default: all validators]",
                 ),
         )
+        .arg(
+            Arg::with_name("repair_trace_log")
+                .long("repair-trace-log")
+                .value_name("FILE")
+                .takes_value(true)
+                .help(
+                    "Write every repair request sent and served to this file, one JSON object \
+                     per line, with its slot, shred index, peer, latency and outcome. \
+                     Summarize it with `trezoa-ledger-tool repair-trace`",
+                ),
+        )
         .arg(
             Arg::with_name("gossip_validators")
                 .long("gossip-validator")
@@ -842,6 +858,16 @@ pub fn app<'a>(version: &'a str, default_args: &'a DefaultArgs) -> App<'a, 'a> {
                      push/pull from from validators outside this set. [default: all validators]",
                 ),
         )
+        .arg(
+            Arg::with_name("gossip_quic")
+                .long("gossip-quic")
+                .takes_value(false)
+                .help(
+                    "Advertise a QUIC gossip port and exchange gossip messages over QUIC with \
+                     nodes which also advertise one, falling back to UDP otherwise. The port \
+                     takes one more port from --dynamic-port-range",
+                ),
+        )
         .arg(
             Arg::with_name("tpu_coalesce_ms")
                 .long("tpu-coalesce-ms")
@@ -878,6 +904,50 @@ pub fn app<'a>(version: &'a str, default_args: &'a DefaultArgs) -> App<'a, 'a> {
                 .validator(is_parsable::<usize>)
                 .help("Controls the TPU connection pool size per remote address"),
         )
+        .arg(
+            Arg::with_name("tpu_connection_rate_limit_per_ip")
+                .long("tpu-connection-rate-limit-per-ip")
+                .value_name("RATE[:BURST]")
+                .takes_value(true)
+                .validator(is_parsable::<RateLimit>)
+                .help(
+                    "Limit new TPU QUIC connections from one IP address to RATE per second, \
+                     allowing BURST at once [default: unlimited]",
+                ),
+        )
+        .arg(
+            Arg::with_name("tpu_connection_rate_limit_per_identity")
+                .long("tpu-connection-rate-limit-per-identity")
+                .value_name("RATE[:BURST]")
+                .takes_value(true)
+                .validator(is_parsable::<RateLimit>)
+                .help(
+                    "Limit new TPU QUIC connections from one client identity to RATE per \
+                     second, allowing BURST at once [default: unlimited]",
+                ),
+        )
+        .arg(
+            Arg::with_name("tpu_stream_rate_limit_per_ip")
+                .long("tpu-stream-rate-limit-per-ip")
+                .value_name("RATE[:BURST]")
+                .takes_value(true)
+                .validator(is_parsable::<RateLimit>)
+                .help(
+                    "Limit TPU QUIC streams opened from one IP address to RATE per second, \
+                     allowing BURST at once [default: unlimited]",
+                ),
+        )
+        .arg(
+            Arg::with_name("tpu_stream_rate_limit_per_identity")
+                .long("tpu-stream-rate-limit-per-identity")
+                .value_name("RATE[:BURST]")
+                .takes_value(true)
+                .validator(is_parsable::<RateLimit>)
+                .help(
+                    "Limit TPU QUIC streams opened by one client identity to RATE per \
+                     second, allowing BURST at once [default: unlimited]",
+                ),
+        )
         .arg(
             Arg::with_name("staked_nodes_overrides")
                 .long("staked-nodes-overrides")
@@ -1179,6 +1249,43 @@ pub fn app<'a>(version: &'a str, default_args: &'a DefaultArgs) -> App<'a, 'a> {
                 .requires("rpc_send_transaction_tpu_peer")
                 .help("With `--rpc-send-transaction-tpu-peer HOST:PORT`, also send to the current leader")
         )
+        .arg(
+            Arg::with_name("rpc_send_transaction_forwarding_policy")
+                .long("rpc-send-transaction-forwarding-policy")
+                .value_name("POLICY")
+                .takes_value(true)
+                .possible_values(&["leaders-only", "leaders-and-peers", "stake-weighted"])
+                .help(
+                    "How transactions sent via rpc service are forwarded. leaders-only: to \
+                     `--rpc-send-leader-count` upcoming leaders. leaders-and-peers: also to the \
+                     `--rpc-send-transaction-tpu-peer` peers. stake-weighted: to at least \
+                     `--rpc-send-leader-count` upcoming leaders, and more until they hold \
+                     `--rpc-send-transaction-stake-weighted-threshold` of the stake. Defaults to \
+                     leaders-and-peers with `--rpc-send-transaction-tpu-peer`, otherwise \
+                     leaders-only",
+                ),
+        )
+        .arg(
+            Arg::with_name("rpc_send_transaction_stake_weighted_max_leaders")
+                .long("rpc-send-transaction-stake-weighted-max-leaders")
+                .value_name("NUMBER")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value(&default_args.rpc_send_transaction_stake_weighted_max_leaders)
+                .help("Most upcoming leaders the stake-weighted forwarding policy forwards to"),
+        )
+        .arg(
+            Arg::with_name("rpc_send_transaction_stake_weighted_threshold")
+                .long("rpc-send-transaction-stake-weighted-threshold")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .validator(is_valid_percentage)
+                .default_value(&default_args.rpc_send_transaction_stake_weighted_threshold)
+                .help(
+                    "Percentage of the epoch's stake the leaders chosen by the stake-weighted \
+                     forwarding policy should hold",
+                ),
+        )
         .arg(
             Arg::with_name("rpc_scan_and_fix_roots")
                 .long("rpc-scan-and-fix-roots")
@@ -1728,6 +1835,147 @@ pub fn app<'a>(version: &'a str, default_args: &'a DefaultArgs) -> App<'a, 'a> {
                         ),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("quic-connections")
+                .about("Inspect and evict the connections to the validator's TPU QUIC servers")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .setting(AppSettings::InferSubcommands)
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("Display the live TPU and TPU forwards QUIC connections")
+                        .arg(
+                            Arg::with_name("output")
+                                .long("output")
+                                .takes_value(true)
+                                .value_name("MODE")
+                                .possible_values(&["json", "json-compact"])
+                                .help("Output display mode"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("evict")
+                        .about("Close the QUIC connections of a peer")
+                        .arg(
+                            Arg::with_name("peer")
+                                .index(1)
+                                .value_name("IP_ADDRESS_OR_IDENTITY")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<PeerFilter>)
+                                .help("IP address or client identity pubkey of the peer"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("block")
+                        .about("Close the QUIC connections of a peer and refuse new ones")
+                        .arg(
+                            Arg::with_name("peer")
+                                .index(1)
+                                .value_name("IP_ADDRESS_OR_IDENTITY")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<PeerFilter>)
+                                .help("IP address or client identity pubkey of the peer"),
+                        )
+                        .arg(
+                            Arg::with_name("duration")
+                                .long("duration")
+                                .value_name("SECONDS")
+                                .takes_value(true)
+                                .default_value("600")
+                                .validator(is_parsable::<u64>)
+                                .help("How long to refuse connections from the peer"),
+                        )
+                        .after_help(
+                            "Note: blocks only apply to the currently running validator instance",
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("unblock")
+                        .about("Allow a blocked peer to connect again")
+                        .arg(
+                            Arg::with_name("peer")
+                                .index(1)
+                                .value_name("IP_ADDRESS_OR_IDENTITY")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_parsable::<PeerFilter>)
+                                .help("IP address or client identity pubkey of the peer"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("gossip-dump")
+                .about("Display the values in the validator's gossip crds table")
+                .arg(
+                    Arg::with_name("data_type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .takes_value(true)
+                        .possible_values(CrdsData::NAMES)
+                        .help("Only display values of this type"),
+                )
+                .arg(
+                    Arg::with_name("origin")
+                        .long("origin")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .validator(is_pubkey)
+                        .help("Only display values created by this node"),
+                )
+                .arg(
+                    Arg::with_name("offset")
+                        .long("offset")
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .validator(is_parsable::<usize>)
+                        .default_value("0")
+                        .help("Skip this many values, oldest inserted first"),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .validator(is_parsable::<usize>)
+                        .help("Display at most this many values [default and maximum: 1000]"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .takes_value(true)
+                        .value_name("MODE")
+                        .possible_values(&["json", "json-compact"])
+                        .help("Output display mode"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("gossip-capture")
+                .about("Record the gossip traffic the validator receives")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .setting(AppSettings::InferSubcommands)
+                .subcommand(
+                    SubCommand::with_name("start")
+                        .about(
+                            "Start recording received push messages and pull responses, \
+                             replacing any capture already running",
+                        )
+                        .arg(
+                            Arg::with_name("path")
+                                .index(1)
+                                .value_name("PATH")
+                                .takes_value(true)
+                                .required(true)
+                                .help("File to write the capture to"),
+                        )
+                        .after_help(
+                            "Note: replay the capture with `trezoa-gossip replay-capture PATH`",
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("stop").about("Stop the running capture"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("init").about("Initialize the ledger directory then exit"),
         )
@@ -2152,6 +2400,8 @@ pub struct DefaultArgs {
     pub rpc_send_transaction_service_max_retries: String,
     pub rpc_send_transaction_batch_size: String,
     pub rpc_send_transaction_retry_pool_max_size: String,
+    pub rpc_send_transaction_stake_weighted_max_leaders: String,
+    pub rpc_send_transaction_stake_weighted_threshold: String,
     pub rpc_threads: String,
     pub rpc_niceness_adjustment: String,
     pub rpc_bigtable_timeout: String,
@@ -2239,6 +2489,11 @@ impl DefaultArgs {
             rpc_send_transaction_retry_pool_max_size: default_send_transaction_service_config
                 .retry_pool_max_size
                 .to_string(),
+            rpc_send_transaction_stake_weighted_max_leaders: DEFAULT_STAKE_WEIGHTED_MAX_LEADERS
+                .to_string(),
+            rpc_send_transaction_stake_weighted_threshold: (DEFAULT_STAKE_WEIGHTED_STAKE_THRESHOLD
+                * 100.0)
+                .to_string(),
             rpc_threads: num_cpus::get().to_string(),
             rpc_niceness_adjustment: "0".to_string(),
             rpc_bigtable_timeout: "30".to_string(),
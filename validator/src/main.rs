@@ -20,6 +20,7 @@ use {
     trezoa_core::{
         banking_trace::DISABLED_BAKING_TRACE_DIR,
         consensus::tower_storage,
+        repair::repair_trace::{RepairTraceLog, RepairTraceNotifier},
         system_monitor_service::SystemMonitorService,
         tpu::DEFAULT_TPU_COALESCE,
         validator::{
@@ -57,8 +58,13 @@ use {
         pubkey::Pubkey,
         signature::{read_keypair, Keypair, Signer},
     },
-    trezoa_send_transaction_service::send_transaction_service,
-    trezoa_streamer::socket::SocketAddrSpace,
+    trezoa_send_transaction_service::{
+        forwarding_policy::ForwardingPolicy, send_transaction_service,
+    },
+    trezoa_streamer::{
+        nonblocking::rate_limiter::{QuicRateLimitConfig, RateLimit},
+        socket::SocketAddrSpace,
+    },
     trezoa_tpu_client::tpu_client::DEFAULT_TPU_ENABLE_UDP,
     trezoa_validator::{
         admin_rpc_service,
@@ -864,6 +870,138 @@ pub fn main() {
                 _ => unreachable!(),
             }
         }
+        ("quic-connections", Some(quic_connections_subcommand_matches)) => {
+            let admin_client = admin_rpc_service::connect(&ledger_path);
+            match quic_connections_subcommand_matches.subcommand() {
+                ("list", Some(subcommand_matches)) => {
+                    let output_mode = subcommand_matches.value_of("output");
+                    let quic_connections = admin_rpc_service::runtime()
+                        .block_on(async move { admin_client.await?.quic_connections().await })
+                        .unwrap_or_else(|err| {
+                            eprintln!("QUIC connections query failed: {err}");
+                            exit(1);
+                        });
+                    if let Some(mode) = output_mode {
+                        match mode {
+                            "json" => println!(
+                                "{}",
+                                serde_json::to_string_pretty(&quic_connections).unwrap()
+                            ),
+                            "json-compact" => {
+                                print!("{}", serde_json::to_string(&quic_connections).unwrap())
+                            }
+                            _ => unreachable!(),
+                        }
+                    } else {
+                        print!("{quic_connections}");
+                    }
+                }
+                ("evict", Some(subcommand_matches)) => {
+                    let peer = value_t_or_exit!(subcommand_matches, "peer", String);
+                    let evicted = admin_rpc_service::runtime()
+                        .block_on(async move { admin_client.await?.evict_quic_peer(peer).await })
+                        .unwrap_or_else(|err| {
+                            eprintln!("evictQuicPeer request failed: {err}");
+                            exit(1);
+                        });
+                    println!("Evicted {evicted} connections");
+                }
+                ("block", Some(subcommand_matches)) => {
+                    let peer = value_t_or_exit!(subcommand_matches, "peer", String);
+                    let duration_secs = value_t_or_exit!(subcommand_matches, "duration", u64);
+                    let evicted = admin_rpc_service::runtime()
+                        .block_on(async move {
+                            admin_client
+                                .await?
+                                .block_quic_peer(peer, duration_secs)
+                                .await
+                        })
+                        .unwrap_or_else(|err| {
+                            eprintln!("blockQuicPeer request failed: {err}");
+                            exit(1);
+                        });
+                    println!("Blocked for {duration_secs}s, evicted {evicted} connections");
+                }
+                ("unblock", Some(subcommand_matches)) => {
+                    let peer = value_t_or_exit!(subcommand_matches, "peer", String);
+                    let unblocked = admin_rpc_service::runtime()
+                        .block_on(async move { admin_client.await?.unblock_quic_peer(peer).await })
+                        .unwrap_or_else(|err| {
+                            eprintln!("unblockQuicPeer request failed: {err}");
+                            exit(1);
+                        });
+                    if !unblocked {
+                        println!("Peer was not blocked");
+                    }
+                }
+                _ => unreachable!(),
+            }
+            return;
+        }
+        ("gossip-dump", Some(subcommand_matches)) => {
+            let output_mode = subcommand_matches.value_of("output");
+            let data_type = subcommand_matches.value_of("data_type").map(str::to_string);
+            let origin = subcommand_matches.value_of("origin").map(str::to_string);
+            let offset = value_t!(subcommand_matches, "offset", usize).ok();
+            let limit = value_t!(subcommand_matches, "limit", usize).ok();
+            let admin_client = admin_rpc_service::connect(&ledger_path);
+            let gossip_dump = admin_rpc_service::runtime()
+                .block_on(async move {
+                    admin_client
+                        .await?
+                        .gossip_dump(data_type, origin, offset, limit)
+                        .await
+                })
+                .unwrap_or_else(|err| {
+                    eprintln!("Gossip dump query failed: {err}");
+                    exit(1);
+                });
+            if let Some(mode) = output_mode {
+                match mode {
+                    "json" => println!("{}", serde_json::to_string_pretty(&gossip_dump).unwrap()),
+                    "json-compact" => print!("{}", serde_json::to_string(&gossip_dump).unwrap()),
+                    _ => unreachable!(),
+                }
+            } else {
+                print!("{gossip_dump}");
+            }
+            return;
+        }
+        ("gossip-capture", Some(subcommand_matches)) => {
+            let admin_client = admin_rpc_service::connect(&ledger_path);
+            match subcommand_matches.subcommand() {
+                ("start", Some(subcommand_matches)) => {
+                    // The validator may run from another directory
+                    let path = value_t_or_exit!(subcommand_matches, "path", PathBuf);
+                    let path = env::current_dir()
+                        .map(|current_dir| current_dir.join(&path))
+                        .unwrap_or(path);
+                    let path = path.display().to_string();
+                    admin_rpc_service::runtime()
+                        .block_on(
+                            async move { admin_client.await?.start_gossip_capture(path).await },
+                        )
+                        .unwrap_or_else(|err| {
+                            eprintln!("startGossipCapture request failed: {err}");
+                            exit(1);
+                        });
+                }
+                ("stop", _) => {
+                    let recorded = admin_rpc_service::runtime()
+                        .block_on(async move { admin_client.await?.stop_gossip_capture().await })
+                        .unwrap_or_else(|err| {
+                            eprintln!("stopGossipCapture request failed: {err}");
+                            exit(1);
+                        });
+                    match recorded {
+                        Some(recorded) => println!("Recorded {recorded} messages"),
+                        None => println!("No gossip capture was running"),
+                    }
+                }
+                _ => unreachable!(),
+            }
+            return;
+        }
         ("set-public-address", Some(subcommand_matches)) => {
             let parse_arg_addr = |arg_name: &str, arg_long: &str| -> Option<SocketAddr> {
                 subcommand_matches.value_of(arg_name).map(|host_port| {
@@ -1049,6 +1187,13 @@ pub fn main() {
         "--repair-whitelist",
     );
     let repair_whitelist = Arc::new(RwLock::new(repair_whitelist.unwrap_or_default()));
+    let repair_trace_notifier = matches.value_of("repair_trace_log").map(|path| {
+        let repair_trace_log = RepairTraceLog::new(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Unable to create repair trace log {path}: {err}");
+            exit(1);
+        });
+        Arc::new(repair_trace_log) as RepairTraceNotifier
+    });
     let gossip_validators = validators_set(
         &identity_keypair.pubkey(),
         &matches,
@@ -1082,6 +1227,18 @@ pub fn main() {
     };
 
     let tpu_connection_pool_size = value_t_or_exit!(matches, "tpu_connection_pool_size", usize);
+    let tpu_rate_limits = QuicRateLimitConfig {
+        connections_per_ip: value_t!(matches, "tpu_connection_rate_limit_per_ip", RateLimit).ok(),
+        connections_per_identity: value_t!(
+            matches,
+            "tpu_connection_rate_limit_per_identity",
+            RateLimit
+        )
+        .ok(),
+        streams_per_ip: value_t!(matches, "tpu_stream_rate_limit_per_ip", RateLimit).ok(),
+        streams_per_identity: value_t!(matches, "tpu_stream_rate_limit_per_identity", RateLimit)
+            .ok(),
+    };
 
     let shrink_ratio = value_t_or_exit!(matches, "accounts_shrink_ratio", f64);
     if !(0.0..=1.0).contains(&shrink_ratio) {
@@ -1328,6 +1485,48 @@ pub fn main() {
         } else {
             value_t_or_exit!(matches, "rpc_send_transaction_leader_forward_count", u64)
         };
+    let forwarding_policy = value_t!(
+        matches,
+        "rpc_send_transaction_forwarding_policy",
+        ForwardingPolicy
+    )
+    .unwrap_or(if rpc_send_transaction_tpu_peers.is_some() {
+        ForwardingPolicy::LeadersAndPeers { peers: vec![] }
+    } else {
+        ForwardingPolicy::LeadersOnly
+    });
+    let forwarding_policy = match (forwarding_policy, rpc_send_transaction_tpu_peers) {
+        (ForwardingPolicy::LeadersAndPeers { .. }, Some(peers)) => {
+            ForwardingPolicy::LeadersAndPeers { peers }
+        }
+        (ForwardingPolicy::LeadersAndPeers { .. }, None) => {
+            eprintln!(
+                "The leaders-and-peers forwarding policy requires --rpc-send-transaction-tpu-peer"
+            );
+            exit(1);
+        }
+        (_, Some(_)) => {
+            eprintln!(
+                "--rpc-send-transaction-tpu-peer requires the leaders-and-peers forwarding policy"
+            );
+            exit(1);
+        }
+        (ForwardingPolicy::StakeWeighted { .. }, None) => ForwardingPolicy::StakeWeighted {
+            min_leaders: leader_forward_count,
+            max_leaders: value_t_or_exit!(
+                matches,
+                "rpc_send_transaction_stake_weighted_max_leaders",
+                u64
+            ),
+            stake_threshold: value_t_or_exit!(
+                matches,
+                "rpc_send_transaction_stake_weighted_threshold",
+                u8
+            ) as f64
+                / 100.0,
+        },
+        (ForwardingPolicy::LeadersOnly, None) => ForwardingPolicy::LeadersOnly,
+    };
 
     let full_api = matches.is_present("full_rpc_api");
 
@@ -1412,7 +1611,9 @@ pub fn main() {
         known_validators,
         repair_validators,
         repair_whitelist,
+        repair_trace_notifier,
         gossip_validators,
+        gossip_quic: matches.is_present("gossip_quic"),
         wal_recovery_mode,
         run_verification: !(matches.is_present("skip_poh_verify")
             || matches.is_present("skip_startup_ledger_verification")),
@@ -1439,7 +1640,8 @@ pub fn main() {
                 "rpc_send_transaction_retry_pool_max_size",
                 usize
             ),
-            tpu_peers: rpc_send_transaction_tpu_peers,
+            forwarding_policy,
+            ..send_transaction_service::Config::default()
         },
         no_poh_speed_test: matches.is_present("no_poh_speed_test"),
         no_os_memory_stats_reporting: matches.is_present("no_os_memory_stats_reporting"),
@@ -1457,6 +1659,7 @@ pub fn main() {
         accounts_db_skip_shrink: true,
         accounts_db_force_initial_clean: matches.is_present("no_skip_initial_accounts_db_clean"),
         tpu_coalesce,
+        tpu_rate_limits,
         no_wait_for_vote_to_start_leader: matches.is_present("no_wait_for_vote_to_start_leader"),
         accounts_shrink_ratio,
         runtime_config: RuntimeConfig {
@@ -1849,6 +2052,15 @@ pub fn main() {
         public_tpu_addr,
         public_tpu_forwards_addr,
     );
+    if validator_config.gossip_quic {
+        // The QUIC gossip socket takes one port more than the minimum range
+        let min_port_range_width = trezoa_net_utils::MINIMUM_VALIDATOR_PORT_RANGE_WIDTH + 1;
+        if dynamic_port_range.1 - dynamic_port_range.0 < min_port_range_width {
+            eprintln!("--gossip-quic requires a --dynamic-port-range of at least {min_port_range_width} ports");
+            exit(1);
+        }
+        node.bind_gossip_quic(bind_address, dynamic_port_range);
+    }
 
     if restricted_repair_only_mode {
         // When in --restricted_repair_only_mode is enabled only the gossip and repair ports